@@ -31,10 +31,12 @@ use lib::core::rewrite::rewrite_hooks::get_deferred_commits_path;
 use lib::util::EyreExitOr;
 use tracing::{error, instrument, warn};
 
-use lib::core::eventlog::{should_ignore_ref_updates, Event, EventLogDb, EventReplayer};
+use lib::core::eventlog::{
+    get_head_ref_name, should_ignore_ref_updates, Event, EventLogDb, EventReplayer,
+};
 use lib::core::formatting::{Glyphs, Pluralize};
 use lib::core::gc::{gc, mark_commit_reachable};
-use lib::git::{CategorizedReferenceName, MaybeZeroOid, NonZeroOid, ReferenceName, Repo};
+use lib::git::{CategorizedReferenceName, MaybeZeroOid, NonZeroOid, Repo};
 
 use lib::core::effects::Effects;
 pub use lib::core::rewrite::rewrite_hooks::{
@@ -75,7 +77,7 @@ fn hook_post_checkout(
             let oid: MaybeZeroOid = current_head_oid.parse()?;
             oid
         },
-        ref_name: ReferenceName::from("HEAD"),
+        ref_name: get_head_ref_name(&repo),
         message: None,
     }])?;
     Ok(())
@@ -583,6 +585,7 @@ fn hook_reference_transaction(effects: &Effects, transaction_state: &str) -> eyr
         .duration_since(SystemTime::UNIX_EPOCH)
         .wrap_err("Calculating timestamp")?
         .as_secs_f64();
+    let head_ref_name = get_head_ref_name(&repo);
     let events: eyre::Result<Vec<Event>> = parsed_lines
         .into_iter()
         .map(
@@ -593,6 +596,13 @@ fn hook_reference_transaction(effects: &Effects, transaction_state: &str) -> eyr
              }| {
                 let old_oid = old_value.as_oid(&repo)?;
                 let new_oid = new_value.as_oid(&repo)?;
+                // Each worktree has its own `HEAD`, so record which one this
+                // was.
+                let ref_name = if ref_name.as_str() == "HEAD" {
+                    head_ref_name.clone()
+                } else {
+                    ref_name
+                };
                 Ok(Event::RefUpdateEvent {
                     timestamp,
                     event_tx_id,
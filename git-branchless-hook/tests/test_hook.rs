@@ -1,3 +1,8 @@
+use std::collections::HashSet;
+
+use lib::core::effects::Effects;
+use lib::core::eventlog::{is_head_ref_name, Event, EventLogDb, EventReplayer};
+use lib::core::formatting::Glyphs;
 use lib::testing::{make_git, make_git_worktree, GitRunOptions, GitWorktreeWrapper};
 
 #[test]
@@ -184,7 +189,7 @@ fn test_hooks_in_worktree() -> eyre::Result<()> {
         :
         @ 62fc20d (master) create test1.txt
         |
-        o 1bed0d8 new empty commit
+        o 1bed0d8 (empty) new empty commit
         "###);
     }
     {
@@ -193,7 +198,7 @@ fn test_hooks_in_worktree() -> eyre::Result<()> {
         :
         O 62fc20d (master) create test1.txt
         |
-        @ 1bed0d8 new empty commit
+        @ 1bed0d8 (empty) new empty commit
         "###);
     }
 
@@ -216,7 +221,7 @@ fn test_hooks_in_worktree() -> eyre::Result<()> {
         :
         @ 62fc20d (master) create test1.txt
         |
-        o cc4313e amended
+        o cc4313e (empty) amended
         "###);
     }
     {
@@ -225,9 +230,65 @@ fn test_hooks_in_worktree() -> eyre::Result<()> {
         :
         O 62fc20d (master) create test1.txt
         |
-        @ cc4313e amended
+        @ cc4313e (empty) amended
         "###);
     }
 
     Ok(())
 }
+
+#[test]
+fn test_worktree_head_events() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.detach_head()?;
+
+    let GitWorktreeWrapper {
+        temp_dir: _temp_dir,
+        worktree,
+    } = make_git_worktree(&git, "new-worktree")?;
+    worktree.run(&["commit", "--allow-empty", "-m", "new empty commit"])?;
+    let worktree_head_oid = worktree.get_repo()?.get_head_info()?.oid;
+
+    let effects = Effects::new_suppress_for_test(Glyphs::text());
+    let repo = git.get_repo()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let head_ref_names: HashSet<String> = event_log_db
+        .get_events()?
+        .into_iter()
+        .filter_map(|event| match event {
+            Event::RefUpdateEvent { ref_name, .. } if is_head_ref_name(&ref_name) => {
+                Some(ref_name.as_str().to_owned())
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        head_ref_names,
+        HashSet::from([
+            "HEAD".to_string(),
+            "worktrees/new-worktree/HEAD".to_string()
+        ])
+    );
+
+    // Each worktree should only see its own `HEAD` when replaying events.
+    let event_replayer = EventReplayer::from_event_log_db(&effects, &repo, &event_log_db)?;
+    let references_snapshot =
+        event_replayer.get_references_snapshot(&repo, event_replayer.make_default_cursor())?;
+    assert_eq!(references_snapshot.head_oid, Some(test1_oid));
+
+    let worktree_repo = worktree.get_repo()?;
+    let event_replayer = EventReplayer::from_event_log_db(&effects, &worktree_repo, &event_log_db)?;
+    let references_snapshot = event_replayer
+        .get_references_snapshot(&worktree_repo, event_replayer.make_default_cursor())?;
+    assert_eq!(references_snapshot.head_oid, worktree_head_oid);
+
+    Ok(())
+}
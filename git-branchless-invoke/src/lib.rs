@@ -14,10 +14,8 @@
 #![allow(clippy::too_many_arguments, clippy::blocks_in_if_conditions)]
 
 use std::any::Any;
-use std::collections::HashMap;
 use std::ffi::OsString;
 use std::fmt::Write;
-use std::path::PathBuf;
 use std::time::SystemTime;
 
 use clap::{CommandFactory, FromArgMatches, Parser};
@@ -25,7 +23,7 @@ use cursive_core::theme::BaseColor;
 use cursive_core::utils::markup::StyledString;
 use eyre::Context;
 use git_branchless_opts::{ColorSetting, GlobalArgs};
-use lib::core::config::env_vars::{get_git_exec_path, get_path_to_git};
+use lib::core::check_storage::check_storage;
 use lib::core::effects::Effects;
 use lib::core::formatting::Glyphs;
 use lib::git::GitRunInfo;
@@ -161,6 +159,44 @@ support repositories with this configuration option enabled.",
     Ok(None)
 }
 
+/// Check that git-branchless can store its state for the repository in the
+/// current directory, printing an explanation if it can't. This should be
+/// called before running commands which rewrite commits or write to the event
+/// log, but it touches the disk, so it's not worth running for hooks or
+/// read-only commands.
+#[instrument]
+pub fn check_storage_usable(effects: &Effects) -> eyre::Result<Option<ExitCode>> {
+    let repo = match Repo::from_current_dir() {
+        Ok(repo) => repo,
+        Err(_) => return Ok(None),
+    };
+    let err = match check_storage(&repo)? {
+        Ok(()) => return Ok(None),
+        Err(err) => err,
+    };
+
+    writeln!(
+        effects.get_error_stream(),
+        "\
+{error}
+
+git-branchless refuses to run so that it doesn't fail partway through an
+operation and leave the repository in an inconsistent state.
+
+Here are some options:
+
+- Make sure that the .git directory and its branchless subdirectory are on a
+  local filesystem and are writable.
+- To store temporary files elsewhere, run: git config branchless.tempDir <path>
+  - The path must be on the same filesystem as the .git directory.",
+        error = effects.get_glyphs().render(StyledString::styled(
+            format!("Error: git-branchless cannot store its state for this repository: {err}"),
+            BaseColor::Red.light()
+        ))?,
+    )?;
+    Ok(Some(ExitCode(1)))
+}
+
 /// Wrapper function for `main` to ensure that `Drop` is called for local
 /// variables, since `std::process::exit` will skip them. You probably want to
 /// call `invoke_subcommand_main` instead.
@@ -187,20 +223,7 @@ pub fn do_main_and_drop_locals<T: Parser>(
         })?;
     }
 
-    let path_to_git = get_path_to_git().unwrap_or_else(|_| PathBuf::from("git"));
-    let path_to_git = PathBuf::from(&path_to_git);
-    let git_run_info = GitRunInfo {
-        path_to_git,
-        working_directory: std::env::current_dir()?,
-        env: {
-            let mut env: HashMap<OsString, OsString> = std::env::vars_os().collect();
-            if let Ok(git_exec_path) = get_git_exec_path() {
-                env.entry("GIT_EXEC_PATH".into())
-                    .or_insert(git_exec_path.into());
-            }
-            env
-        },
-    };
+    let git_run_info = GitRunInfo::from_env()?;
 
     let color = match color {
         Some(ColorSetting::Always) => Glyphs::pretty(),
@@ -0,0 +1,258 @@
+//! Stable identifiers for logical changes.
+//!
+//! Rewriting a commit (amending, rebasing, rewording, etc.) produces a new
+//! commit with a new OID. A change ID instead identifies the logical change
+//! which the commit represents, and is carried over from the old commit to the
+//! new commit whenever a rewrite is recorded in the event log. A commit which
+//! wasn't produced by a rewrite has a change ID derived from its own OID.
+//!
+//! Undoing a rewrite (which records the inverse rewrite) removes the change ID
+//! that the rewrite assigned, and `git branchless gc` removes the change IDs
+//! of commits which no longer exist in the repository.
+
+use std::fmt::Display;
+
+use eyre::Context;
+use tracing::instrument;
+
+use crate::git::{NonZeroOid, Repo};
+
+/// The number of characters of a change ID to display by default.
+pub const SHORT_CHANGE_ID_LEN: usize = 8;
+
+/// Identifies a logical change across all the commits that it has been
+/// rewritten into.
+///
+/// Change IDs are written using the letters `k` through `z` rather than
+/// hexadecimal digits, so that they can't be confused with commit hashes.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ChangeId(String);
+
+impl ChangeId {
+    /// Derive the change ID for a commit which wasn't produced by a rewrite.
+    pub fn from_commit_oid(oid: NonZeroOid) -> Self {
+        let change_id = oid
+            .to_string()
+            .chars()
+            .map(|c| {
+                let digit = c.to_digit(16).expect("OID should be hexadecimal");
+                char::from(b'z' - u8::try_from(digit).expect("Hex digit should fit in u8"))
+            })
+            .collect();
+        ChangeId(change_id)
+    }
+
+    /// Convert a prefix of a change ID into the corresponding prefix of the
+    /// OID it would be derived from with [`ChangeId::from_commit_oid`].
+    /// Returns `None` if the prefix contains characters which can't appear in
+    /// a change ID.
+    pub fn to_oid_prefix(prefix: &str) -> Option<String> {
+        prefix
+            .chars()
+            .map(|c| match c {
+                'k'..='z' => char::from_digit(u32::from(b'z') - u32::from(c), 16),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Get the full change ID.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Get an abbreviated version of the change ID for display.
+    pub fn get_short(&self) -> &str {
+        &self.0[..SHORT_CHANGE_ID_LEN.min(self.0.len())]
+    }
+}
+
+impl Display for ChangeId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[instrument]
+pub(crate) fn init_tables(conn: &rusqlite::Connection) -> eyre::Result<()> {
+    conn.execute(
+        "
+CREATE TABLE IF NOT EXISTS change_ids (
+    commit_oid TEXT NOT NULL PRIMARY KEY,
+    change_id TEXT NOT NULL,
+    rewritten_from_oid TEXT NOT NULL
+)
+",
+        rusqlite::params![],
+    )
+    .wrap_err("Creating `change_ids` table")?;
+
+    conn.execute(
+        "
+CREATE INDEX IF NOT EXISTS change_ids_change_id
+ON change_ids (change_id)
+",
+        rusqlite::params![],
+    )
+    .wrap_err("Creating `change_ids_change_id` index")?;
+
+    Ok(())
+}
+
+fn get_recorded_change_id(
+    conn: &rusqlite::Connection,
+    commit_oid: NonZeroOid,
+) -> eyre::Result<Option<ChangeId>> {
+    let mut stmt = conn.prepare_cached("SELECT change_id FROM change_ids WHERE commit_oid = ?")?;
+    let mut rows = stmt
+        .query_map(rusqlite::params![commit_oid.to_string()], |row| {
+            let change_id: String = row.get("change_id")?;
+            Ok(ChangeId(change_id))
+        })
+        .wrap_err("Querying change ID")?;
+    let change_id = rows.next().transpose()?;
+    Ok(change_id)
+}
+
+/// Record that `new_commit_oid` was produced by rewriting `old_commit_oid`, so
+/// that it inherits the old commit's change ID. If the new commit already has
+/// a change ID (such as when several commits are squashed into one), it's
+/// left unchanged.
+///
+/// If this rewrite is the inverse of the rewrite which assigned the old
+/// commit its change ID (as recorded by `git undo`), then that change ID is
+/// removed instead.
+#[instrument]
+pub(crate) fn record_rewrite(
+    conn: &rusqlite::Connection,
+    old_commit_oid: NonZeroOid,
+    new_commit_oid: NonZeroOid,
+) -> eyre::Result<()> {
+    if old_commit_oid == new_commit_oid {
+        return Ok(());
+    }
+
+    let num_undone = conn
+        .execute(
+            "DELETE FROM change_ids WHERE commit_oid = ? AND rewritten_from_oid = ?",
+            rusqlite::params![old_commit_oid.to_string(), new_commit_oid.to_string()],
+        )
+        .wrap_err("Undoing change ID")?;
+    if num_undone > 0 {
+        return Ok(());
+    }
+
+    let change_id = get_recorded_change_id(conn, old_commit_oid)?
+        .unwrap_or_else(|| ChangeId::from_commit_oid(old_commit_oid));
+    conn.execute(
+        "
+INSERT OR IGNORE INTO change_ids (commit_oid, change_id, rewritten_from_oid)
+VALUES (?, ?, ?)
+",
+        rusqlite::params![
+            new_commit_oid.to_string(),
+            change_id.as_str(),
+            old_commit_oid.to_string()
+        ],
+    )
+    .wrap_err("Inserting change ID")?;
+    Ok(())
+}
+
+/// Looks up the change IDs of commits. Change IDs are recorded by
+/// `EventLogDb::add_events` as rewrite events are added.
+pub struct ChangeIdDb<'conn> {
+    conn: &'conn rusqlite::Connection,
+}
+
+impl std::fmt::Debug for ChangeIdDb<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<ChangeIdDb>")
+    }
+}
+
+impl<'conn> ChangeIdDb<'conn> {
+    /// Constructor.
+    #[instrument]
+    pub fn new(conn: &'conn rusqlite::Connection) -> eyre::Result<Self> {
+        init_tables(conn)?;
+        Ok(ChangeIdDb { conn })
+    }
+
+    /// Get the change ID of the given commit.
+    #[instrument]
+    pub fn get_change_id(&self, commit_oid: NonZeroOid) -> eyre::Result<ChangeId> {
+        let change_id = get_recorded_change_id(self.conn, commit_oid)?
+            .unwrap_or_else(|| ChangeId::from_commit_oid(commit_oid));
+        Ok(change_id)
+    }
+
+    /// Get all commits which were recorded as having a change ID starting with
+    /// `prefix`. This doesn't include commits whose change ID is derived from
+    /// their own OID, since those aren't stored in the database.
+    #[instrument]
+    pub fn get_recorded_commits_with_prefix(&self, prefix: &str) -> eyre::Result<Vec<NonZeroOid>> {
+        if ChangeId::to_oid_prefix(prefix).is_none() {
+            return Ok(Vec::new());
+        }
+        // Query the range of change IDs starting with `prefix`, rather than
+        // comparing substrings, so that the index on `change_id` can be used.
+        // Change IDs only contain the letters `k` through `z`, so they all sort
+        // before `prefix` followed by `{`, the character after `z`.
+        let upper_bound = format!("{prefix}{{");
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT commit_oid FROM change_ids WHERE change_id >= ? AND change_id < ?",
+        )?;
+        let commit_oids = stmt
+            .query_map(rusqlite::params![prefix, upper_bound], |row| {
+                let commit_oid: String = row.get("commit_oid")?;
+                Ok(commit_oid)
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .wrap_err("Querying commits by change ID")?;
+        commit_oids
+            .into_iter()
+            .map(|commit_oid| {
+                commit_oid
+                    .parse()
+                    .wrap_err_with(|| format!("Parsing commit OID: {commit_oid}"))
+            })
+            .collect()
+    }
+
+    /// Remove the recorded change IDs of commits which no longer exist in the
+    /// repository, such as after they've been garbage-collected by Git.
+    ///
+    /// Returns: the number of change IDs removed.
+    #[instrument]
+    pub fn prune(&self, repo: &Repo) -> eyre::Result<usize> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT commit_oid FROM change_ids")?;
+        let commit_oids = stmt
+            .query_map(rusqlite::params![], |row| {
+                let commit_oid: String = row.get("commit_oid")?;
+                Ok(commit_oid)
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .wrap_err("Querying change ID commits")?;
+
+        let mut num_pruned = 0;
+        for commit_oid in commit_oids {
+            let is_missing = match commit_oid.parse() {
+                Ok(oid) => repo.find_commit(oid)?.is_none(),
+                Err(_) => true,
+            };
+            if is_missing {
+                num_pruned += self
+                    .conn
+                    .execute(
+                        "DELETE FROM change_ids WHERE commit_oid = ?",
+                        rusqlite::params![commit_oid],
+                    )
+                    .wrap_err("Deleting change ID")?;
+            }
+        }
+        Ok(num_pruned)
+    }
+}
@@ -12,8 +12,8 @@ use tracing::instrument;
 
 use crate::core::config::get_auto_switch_branches;
 use crate::git::{
-    update_index, CategorizedReferenceName, GitRunInfo, MaybeZeroOid, NonZeroOid, ReferenceName,
-    Repo, Stage, UpdateIndexCommand, WorkingCopySnapshot,
+    update_index, CategorizedReferenceName, Commit, GitRunInfo, MaybeZeroOid, NonZeroOid,
+    ReferenceName, Repo, Stage, UpdateIndexCommand, WorkingCopySnapshot,
 };
 use crate::try_exit_code;
 use crate::util::EyreExitOr;
@@ -41,7 +41,8 @@ pub enum CheckoutTarget {
 /// Options for checking out a commit.
 #[derive(Clone, Debug)]
 pub struct CheckOutCommitOptions {
-    /// Additional arguments to pass to `git checkout`.
+    /// Additional arguments to pass to `git checkout` (or `git reset`, if
+    /// `reset` is set).
     pub additional_args: Vec<OsString>,
 
     /// Use `git reset` rather than `git checkout`; that is, leave the index and
@@ -142,7 +143,13 @@ pub fn check_out_commit(
 
     if *reset {
         if let Some(target) = &target {
-            try_exit_code!(git_run_info.run(effects, Some(event_tx_id), &["reset", target])?);
+            let reset_args = {
+                let mut args = vec![OsStr::new("reset")];
+                args.extend(additional_args.iter().map(OsStr::new));
+                args.push(OsStr::new(target.as_str()));
+                args
+            };
+            try_exit_code!(git_run_info.run(effects, Some(event_tx_id), reset_args.as_slice())?);
         }
     } else {
         let checkout_args = {
@@ -238,6 +245,11 @@ pub fn create_snapshot<'repo>(
 /// If there are untracked changes in the working copy, they are left intact,
 /// *unless* they would conflict with the working copy snapshot contents. In
 /// that case, the operation is aborted.
+///
+/// If the snapshot includes untracked files (see
+/// `branchless.snapshot.includeUntracked`), they are written back into the
+/// working copy, except for any which already exist there with different
+/// contents.
 pub fn restore_snapshot(
     effects: &Effects,
     git_run_info: &GitRunInfo,
@@ -351,5 +363,56 @@ pub fn restore_snapshot(
             .context("Checking out snapshot branch")?);
     }
 
+    if let Some(commit_untracked) = &snapshot.commit_untracked {
+        restore_untracked_files(effects, repo, commit_untracked)?;
+    }
+
     Ok(Ok(()))
 }
+
+/// Write the untracked files stored in a snapshot's `commit_untracked` into
+/// the working copy, except for any which already exist there.
+pub fn restore_untracked_files(
+    effects: &Effects,
+    repo: &Repo,
+    commit: &Commit,
+) -> eyre::Result<()> {
+    let working_copy_path = match repo.get_working_copy_path() {
+        Some(working_copy_path) => working_copy_path,
+        None => eyre::bail!("Cannot restore untracked files in a bare repository"),
+    };
+
+    let tree = commit.get_tree()?;
+    let paths = repo
+        .get_paths_touched_by_commit(commit)?
+        .into_iter()
+        .sorted()
+        .collect_vec();
+    for path in paths {
+        let tree_entry = match tree.get_path(&path)? {
+            Some(tree_entry) => tree_entry,
+            None => continue,
+        };
+        let blob = repo.find_blob_or_fail(tree_entry.get_oid())?;
+        let contents = blob.get_content();
+
+        let file_path = working_copy_path.join(&path);
+        if file_path.exists() {
+            if std::fs::read(&file_path)? != contents {
+                writeln!(
+                    effects.get_error_stream(),
+                    "branchless: not restoring untracked file {path:?}, as it already exists"
+                )?;
+            }
+            continue;
+        }
+
+        if let Some(parent) = file_path.parent() {
+            std::fs::create_dir_all(parent)
+                .wrap_err_with(|| format!("Creating directory {parent:?}"))?;
+        }
+        std::fs::write(&file_path, contents)
+            .wrap_err_with(|| format!("Restoring untracked file {file_path:?}"))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,167 @@
+//! Check that the places where git-branchless stores its state can actually be
+//! written to before starting an operation.
+//!
+//! Otherwise, an operation like a rebase might fail with an I/O error after
+//! only some of its steps have been applied, which is much harder to recover
+//! from than refusing to start at all.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+use tracing::instrument;
+
+use crate::core::config::get_tempfile_dir;
+use crate::git::{Repo, RepoError};
+
+/// A problem with the storage used by git-branchless for a repository.
+#[derive(Debug, Error)]
+pub enum StorageError {
+    /// A directory that git-branchless needs to write to is read-only or
+    /// otherwise unusable.
+    #[error("the directory {} is not writable: {source}", path.display())]
+    NotWritable {
+        /// The directory which couldn't be written to.
+        path: PathBuf,
+
+        /// The underlying error.
+        #[source]
+        source: io::Error,
+    },
+
+    /// Files in the temporary directory can't be atomically moved into the Git
+    /// directory, which is usually because they're on different filesystems.
+    #[error(
+        "files in the temporary directory {} cannot be moved into {} (are they on different filesystems?): {source}",
+        temp_dir.display(),
+        target_dir.display()
+    )]
+    CrossFilesystemTempDir {
+        /// The directory used for temporary files.
+        temp_dir: PathBuf,
+
+        /// The directory that temporary files are moved into.
+        target_dir: PathBuf,
+
+        /// The underlying error.
+        #[source]
+        source: io::Error,
+    },
+
+    /// The event log database can't be written to. This typically happens
+    /// on network filesystems such as NFS or SMB, which may not support the
+    /// file locking that SQLite relies on.
+    #[error(
+        "the database at {} cannot be written to (network filesystems such as NFS or SMB may not support the necessary file locking): {source}",
+        path.display()
+    )]
+    Database {
+        /// The path to the database.
+        path: PathBuf,
+
+        /// The underlying error.
+        #[source]
+        source: rusqlite::Error,
+    },
+}
+
+fn make_probe_file_name() -> String {
+    format!(".branchless-storage-check-{}", std::process::id())
+}
+
+fn write_probe_file(dir: &Path) -> Result<PathBuf, StorageError> {
+    let path = dir.join(make_probe_file_name());
+    match std::fs::write(&path, "") {
+        Ok(()) => Ok(path),
+        Err(err) => Err(StorageError::NotWritable {
+            path: dir.to_owned(),
+            source: err,
+        }),
+    }
+}
+
+fn check_dir_writable(dir: &Path) -> Result<(), StorageError> {
+    let probe_path = write_probe_file(dir)?;
+    let _ignored: io::Result<()> = std::fs::remove_file(probe_path);
+    Ok(())
+}
+
+/// Write a probe file into the temporary directory and move it into the
+/// branchless directory. This is done on every invocation, so it's limited to
+/// a single create and rename; the more specific diagnosis is only done if
+/// that fails.
+fn check_temp_dir(temp_dir: &Path, branchless_dir: &Path) -> Result<(), StorageError> {
+    std::fs::create_dir_all(temp_dir).map_err(|err| StorageError::NotWritable {
+        path: temp_dir.to_owned(),
+        source: err,
+    })?;
+    let probe_path = write_probe_file(temp_dir)?;
+    let target_path = branchless_dir.join(make_probe_file_name());
+    let result = std::fs::rename(&probe_path, &target_path);
+    let _ignored: io::Result<()> = std::fs::remove_file(&probe_path);
+    let _ignored: io::Result<()> = std::fs::remove_file(&target_path);
+    match result {
+        Ok(()) => Ok(()),
+        Err(err) => {
+            check_dir_writable(branchless_dir)?;
+            Err(StorageError::CrossFilesystemTempDir {
+                temp_dir: temp_dir.to_owned(),
+                target_dir: branchless_dir.to_owned(),
+                source: err,
+            })
+        }
+    }
+}
+
+fn check_database(repo: &Repo) -> eyre::Result<Result<(), StorageError>> {
+    let path = repo.get_branchless_dir()?.join("db.sqlite3");
+    let conn = match repo.get_db_conn() {
+        Ok(conn) => conn,
+        Err(RepoError::OpenDatabase { source, path }) => {
+            return Ok(Err(StorageError::Database { path, source }))
+        }
+        Err(err) => return Err(err.into()),
+    };
+    match conn.execute_batch("BEGIN IMMEDIATE; ROLLBACK;") {
+        Ok(()) => Ok(Ok(())),
+
+        // Another process may legitimately be holding the lock right now, in
+        // which case the database itself is fine.
+        Err(err)
+            if matches!(
+                err.sqlite_error_code(),
+                Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked)
+            ) =>
+        {
+            Ok(Ok(()))
+        }
+
+        Err(err) => Ok(Err(StorageError::Database { path, source: err })),
+    }
+}
+
+/// Check that the `.git/branchless` directory, the temporary directory, and
+/// the event log database are all usable for the given repository.
+///
+/// Returns: `Ok(Err(..))` describing the first problem found, if any.
+#[instrument]
+pub fn check_storage(repo: &Repo) -> eyre::Result<Result<(), StorageError>> {
+    let branchless_dir = match repo.get_branchless_dir() {
+        Ok(branchless_dir) => branchless_dir,
+        Err(RepoError::CreateBranchlessDir { source, path }) => {
+            return Ok(Err(StorageError::NotWritable { path, source }))
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    // Temporary files are moved into place under the Git directory (such as
+    // the `rewritten-list` for an ongoing rebase), so check that they can be
+    // moved into the branchless directory, which also checks that the
+    // branchless directory itself is writable.
+    let temp_dir = get_tempfile_dir(repo)?;
+    if let Err(err) = check_temp_dir(&temp_dir, &branchless_dir) {
+        return Ok(Err(err));
+    }
+
+    check_database(repo)
+}
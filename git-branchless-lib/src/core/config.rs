@@ -3,10 +3,12 @@
 use std::ffi::OsString;
 use std::fmt::Write;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use cursive::theme::{BaseColor, Effect, Style};
 use cursive::utils::markup::StyledString;
 use eyre::Context;
+use regex::Regex;
 use tracing::{instrument, warn};
 
 use crate::core::formatting::StyledStringBuilder;
@@ -120,6 +122,21 @@ pub fn get_comment_char(repo: &Repo) -> eyre::Result<char> {
     Ok(comment_char)
 }
 
+/// Get the directory to use for temporary files which are later moved into
+/// the Git directory.
+///
+/// This is `.git/branchless/tmp` by default, but can be set with
+/// `branchless.tempDir` in case that directory can't be used. The directory
+/// must be on the same filesystem as the Git directory.
+#[instrument]
+pub fn get_tempfile_dir(repo: &Repo) -> eyre::Result<PathBuf> {
+    let tempfile_dir: Option<String> = repo.get_readonly_config()?.get("branchless.tempDir")?;
+    match tempfile_dir {
+        Some(tempfile_dir) => Ok(PathBuf::from(tempfile_dir)),
+        None => Ok(repo.get_tempfile_dir()?),
+    }
+}
+
 /// Get the commit template message, if any.
 #[instrument]
 pub fn get_commit_template(repo: &Repo) -> eyre::Result<Option<String>> {
@@ -151,6 +168,59 @@ pub fn get_commit_template(repo: &Repo) -> eyre::Result<Option<String>> {
     }
 }
 
+/// Get the trailers which `git record` and `git reword` add to commit
+/// messages, in the order in which they should be added:
+///
+/// - Each value of the multi-valued `branchless.commit.trailer`, such as
+///   `Reviewed-by: Name <email>`.
+/// - If `branchless.commit.branchTrailer` is set, a trailer with that key and
+///   a value extracted from the current branch name with the regex
+///   `branchless.commit.branchTrailerPattern`. If the regex has a capture
+///   group, then the first group is used as the value; otherwise, the whole
+///   match is used. The default pattern matches issue IDs like `ABC-123`.
+/// - If `branchless.commit.signoff` is set, a `Signed-off-by` trailer for the
+///   configured user.
+#[instrument]
+pub fn get_commit_trailers(repo: &Repo, branch_name: Option<&str>) -> eyre::Result<Vec<String>> {
+    let config = repo.get_readonly_config()?;
+    let mut trailers: Vec<String> = config
+        .list(r"^branchless\.commit\.trailer$")?
+        .into_iter()
+        .map(|(_key, value)| value)
+        .collect();
+
+    let branch_trailer_key: Option<String> = config.get("branchless.commit.branchTrailer")?;
+    if let (Some(key), Some(branch_name)) = (branch_trailer_key, branch_name) {
+        let pattern = config.get_or_else("branchless.commit.branchTrailerPattern", || {
+            r"[A-Z][A-Z0-9]*-[0-9]+".to_string()
+        })?;
+        let pattern = Regex::new(&pattern)
+            .wrap_err("Parsing regex for branchless.commit.branchTrailerPattern")?;
+        if let Some(captures) = pattern.captures(branch_name) {
+            if let Some(value) = captures.get(1).or_else(|| captures.get(0)) {
+                trailers.push(format!("{key}: {}", value.as_str()));
+            }
+        }
+    }
+
+    if config.get_or("branchless.commit.signoff", false)? {
+        let name: Option<String> = config.get("user.name")?;
+        let email: Option<String> = config.get("user.email")?;
+        match (name, email) {
+            (Some(name), Some(email)) => trailers.push(format!("Signed-off-by: {name} <{email}>")),
+            (name, email) => {
+                warn!(
+                    ?name,
+                    ?email,
+                    "Not adding Signed-off-by trailer because user.name or user.email is not set"
+                );
+            }
+        }
+    }
+
+    Ok(trailers)
+}
+
 /// Get the default init branch name.
 #[instrument]
 pub fn get_default_branch_name(repo: &Repo) -> eyre::Result<Option<String>> {
@@ -204,6 +274,45 @@ pub fn get_undo_create_snapshots(repo: &Repo) -> eyre::Result<bool> {
         .get_or("branchless.undo.createSnapshots", true)
 }
 
+/// A shell command to run after `git undo` has applied its changes, if any.
+#[instrument]
+pub fn get_hook_post_undo(repo: &Repo) -> eyre::Result<Option<String>> {
+    repo.get_readonly_config()?.get("branchless.hook.postUndo")
+}
+
+/// A shell command to run after commits have been rewritten, if any. The
+/// mapping from old to new commit OIDs is passed on stdin.
+#[instrument]
+pub fn get_hook_post_rewrite(repo: &Repo) -> eyre::Result<Option<String>> {
+    repo.get_readonly_config()?
+        .get("branchless.hook.postRewrite")
+}
+
+/// If `true`, include untracked (but not ignored) files in working copy
+/// snapshots, so that they can be restored by `git undo`.
+#[instrument]
+pub fn get_snapshot_include_untracked(repo: &Repo) -> eyre::Result<bool> {
+    repo.get_readonly_config()?
+        .get_or("branchless.snapshot.includeUntracked", false)
+}
+
+/// How long to keep events in the event log before pruning them during garbage
+/// collection, if at all. Set with `branchless.eventLog.retention`, as a number
+/// of days.
+#[instrument]
+pub fn get_event_log_retention(repo: &Repo) -> eyre::Result<Option<Duration>> {
+    let retention_days: Option<i32> = repo
+        .get_readonly_config()?
+        .get("branchless.eventLog.retention")?;
+    match retention_days {
+        Some(retention_days) if retention_days > 0 => {
+            let retention_days = u64::try_from(retention_days)?;
+            Ok(Some(Duration::from_secs(retention_days * 24 * 60 * 60)))
+        }
+        Some(_) | None => Ok(None),
+    }
+}
+
 /// If `true`, when restacking a commit, do not update its timestamp to the
 /// current time.
 #[instrument]
@@ -212,6 +321,84 @@ pub fn get_restack_preserve_timestamps(repo: &Repo) -> eyre::Result<bool> {
         .get_or("branchless.restack.preserveTimestamps", false)
 }
 
+/// If `false`, when rewriting a commit, update its author timestamp to the
+/// current time, like `git rebase --reset-author-date`.
+#[instrument]
+pub fn get_restack_preserve_author_dates(repo: &Repo) -> eyre::Result<bool> {
+    repo.get_readonly_config()?
+        .get_or("branchless.restack.preserveAuthorDates", true)
+}
+
+/// If `true`, when an in-memory rebase runs into a merge conflict which was
+/// resolved during a previous on-disk rebase, reuse that resolution.
+#[instrument]
+pub fn get_restack_reuse_resolutions(repo: &Repo) -> eyre::Result<bool> {
+    repo.get_readonly_config()?
+        .get_or("branchless.restack.reuseResolutions", true)
+}
+
+/// If `true`, `git move` and `git sync` stash any uncommitted changes before
+/// rewriting commits and restore them afterwards, as if `--autostash` were
+/// passed, rather than refusing to modify a dirty working copy.
+#[instrument]
+pub fn get_restack_autostash(repo: &Repo) -> eyre::Result<bool> {
+    repo.get_readonly_config()?
+        .get_or("branchless.restack.autoStash", false)
+}
+
+/// If `true`, `git sync` deletes local branches which have landed in the main
+/// branch, as if `--prune-merged` were passed.
+#[instrument]
+pub fn get_sync_prune_merged(repo: &Repo) -> eyre::Result<bool> {
+    repo.get_readonly_config()?
+        .get_or("branchless.sync.pruneMerged", false)
+}
+
+/// The remote to pull the main branch from when running `git sync --pull`, if
+/// it shouldn't be pulled from the upstream branch which it tracks. Set with
+/// `branchless.sync.remote`, such as for triangular workflows which pull from
+/// one remote and push to another.
+#[instrument]
+pub fn get_sync_remote(repo: &Repo) -> eyre::Result<Option<String>> {
+    repo.get_readonly_config()?.get("branchless.sync.remote")
+}
+
+/// How to update a local branch which has diverged from its upstream branch
+/// when running `git sync --pull`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyncStrategy {
+    /// Rebase the local-only commits on top of the upstream branch.
+    Rebase,
+
+    /// Create a merge commit joining the local branch and the upstream branch.
+    Merge,
+}
+
+/// Get the strategy to use when updating the given local branch from its
+/// upstream branch. Checked in this order:
+///
+/// - `branch.<name>.branchlessSyncStrategy`
+/// - `branchless.sync.strategy`
+///
+/// Defaults to [`SyncStrategy::Rebase`].
+#[instrument]
+pub fn get_sync_strategy(repo: &Repo, branch_name: &str) -> eyre::Result<SyncStrategy> {
+    let config = repo.get_readonly_config()?;
+    let branch_key = format!("branch.{branch_name}.branchlessSyncStrategy");
+    let global_key = "branchless.sync.strategy";
+    let (key, value): (&str, Option<String>) = match config.get(&branch_key)? {
+        Some(value) => (&branch_key, Some(value)),
+        None => (global_key, config.get(global_key)?),
+    };
+    match value.as_deref() {
+        None | Some("rebase") => Ok(SyncStrategy::Rebase),
+        Some("merge") => Ok(SyncStrategy::Merge),
+        Some(other) => eyre::bail!(
+            "Invalid value for config {key}: {other:?} (expected \"rebase\" or \"merge\")"
+        ),
+    }
+}
+
 /// If `true`, when advancing to a "next" commit, prompt interactively to
 /// if there is ambiguity in which commit to advance to.
 #[instrument]
@@ -220,6 +407,15 @@ pub fn get_next_interactive(repo: &Repo) -> eyre::Result<bool> {
         .get_or("branchless.next.interactive", false)
 }
 
+/// If `true`, `git next` and `git prev` skip over obsolete commits (commits
+/// which have been rewritten but are still visible because of their
+/// descendants) instead of stopping at them.
+#[instrument]
+pub fn get_navigation_skip_obsolete(repo: &Repo) -> eyre::Result<bool> {
+    repo.get_readonly_config()?
+        .get_or("branchless.navigation.skipObsolete", false)
+}
+
 /// If `true`, show branches pointing to each commit in the smartlog.
 #[instrument]
 pub fn get_commit_descriptors_branches(repo: &Repo) -> eyre::Result<bool> {
@@ -241,6 +437,29 @@ pub fn get_commit_descriptors_relative_time(repo: &Repo) -> eyre::Result<bool> {
         .get_or("branchless.commitDescriptors.relativeTime", true)
 }
 
+/// If `true`, show the change ID of each commit in the smartlog.
+#[instrument]
+pub fn get_commit_descriptors_change_id(repo: &Repo) -> eyre::Result<bool> {
+    repo.get_readonly_config()?
+        .get_or("branchless.commitDescriptors.changeId", true)
+}
+
+/// If `true`, show the cached code review status of each commit in the
+/// smartlog, as of the last `git submit status`.
+#[instrument]
+pub fn get_commit_descriptors_review_status(repo: &Repo) -> eyre::Result<bool> {
+    repo.get_readonly_config()?
+        .get_or("branchless.commitDescriptors.reviewStatus", true)
+}
+
+/// If `true`, mark commits which intentionally contain no changes in the
+/// smartlog.
+#[instrument]
+pub fn get_commit_descriptors_empty_commits(repo: &Repo) -> eyre::Result<bool> {
+    repo.get_readonly_config()?
+        .get_or("branchless.commitDescriptors.emptyCommits", true)
+}
+
 /// Config key for `get_restack_warn_abandoned`.
 pub const RESTACK_WARN_ABANDONED_CONFIG_KEY: &str = "branchless.restack.warnAbandoned";
 
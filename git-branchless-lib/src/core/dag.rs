@@ -5,12 +5,13 @@ use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::future::Future;
+use std::io::Write;
 use std::sync::{Arc, Mutex};
 
 use async_trait::async_trait;
 use eden_dag::namedag::MemNameDag;
 use eden_dag::nameset::hints::Hints;
-use eden_dag::ops::{DagPersistent, Parents};
+use eden_dag::ops::{DagPersistent, Parents, PrefixLookup};
 use eden_dag::{DagAlgorithm, Group, VertexListWithOptions, VertexOptions};
 use eyre::Context;
 use futures::{StreamExt, TryStreamExt};
@@ -21,6 +22,7 @@ use tracing::{instrument, trace, warn};
 use crate::core::effects::{Effects, OperationType};
 use crate::core::eventlog::{CommitActivityStatus, EventCursor, EventReplayer};
 use crate::git::{Commit, MaybeZeroOid, NonZeroOid, Repo, Time};
+use crate::util::ExitCode;
 
 use super::repo_ext::RepoReferencesSnapshot;
 
@@ -516,6 +518,25 @@ impl Dag {
         self.obsolete_commits.clone()
     }
 
+    /// Get the commits in the DAG whose OIDs start with the given hexadecimal
+    /// prefix.
+    #[instrument]
+    pub fn query_commits_with_hex_prefix(&self, hex_prefix: &str) -> eyre::Result<CommitSet> {
+        // The lookup preallocates space for `limit` results, so grow the limit
+        // until all of the matching commits have been found.
+        let mut limit = 16;
+        loop {
+            let vertexes = self.run_blocking(
+                self.inner
+                    .vertexes_by_hex_prefix(hex_prefix.as_bytes(), limit),
+            )?;
+            if vertexes.len() < limit {
+                return Ok(CommitSet::from_static_names(vertexes));
+            }
+            limit *= 2;
+        }
+    }
+
     /// Determine the set of "draft" commits. The draft commits are all visible
     /// commits which aren't public.
     #[instrument]
@@ -798,3 +819,69 @@ pub fn sorted_commit_set<'repo>(
 
     Ok(commits)
 }
+
+/// The bounds of a linear range of commits, as returned by
+/// [`linear_commit_range`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LinearCommitRange {
+    /// The parent of the first commit in the range.
+    pub base_oid: NonZeroOid,
+
+    /// The first commit in the range.
+    pub root_oid: NonZeroOid,
+
+    /// The last commit in the range.
+    pub head_oid: NonZeroOid,
+}
+
+/// Check that the given non-empty list of commits, sorted as by
+/// [`sorted_commit_set`], forms a single linear range without gaps or merges,
+/// so that there's an unambiguous order in which to operate on them.
+///
+/// If it doesn't, print an error describing the problem, using `verb` (such as
+/// "edit") to describe the operation, and return an exit code.
+pub fn linear_commit_range(
+    effects: &Effects,
+    commits: &[Commit],
+    verb: &str,
+) -> eyre::Result<Result<LinearCommitRange, ExitCode>> {
+    let (root, head) = match (commits.first(), commits.last()) {
+        (Some(root), Some(head)) => (root, head),
+        _ => eyre::bail!("No commits provided to form a linear range"),
+    };
+
+    let base_oid = match root.get_parent_oids().as_slice() {
+        [base_oid] => *base_oid,
+        _ => {
+            writeln!(
+                effects.get_error_stream(),
+                "The first commit to {verb} must have exactly 1 parent: {}",
+                effects
+                    .get_glyphs()
+                    .render(root.friendly_describe(effects.get_glyphs())?)?,
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+    for (parent, child) in commits.iter().zip(commits.iter().skip(1)) {
+        if child.get_parent_oids() != vec![parent.get_oid()] {
+            writeln!(
+                effects.get_error_stream(),
+                "The commits to {verb} must form a single range of commits without gaps or merges, but {} is not the only parent of {}",
+                effects
+                    .get_glyphs()
+                    .render(parent.friendly_describe(effects.get_glyphs())?)?,
+                effects
+                    .get_glyphs()
+                    .render(child.friendly_describe(effects.get_glyphs())?)?,
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+    }
+
+    Ok(Ok(LinearCommitRange {
+        base_oid,
+        root_oid: root.get_oid(),
+        head_oid: head.get_oid(),
+    }))
+}
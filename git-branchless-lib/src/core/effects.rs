@@ -96,6 +96,10 @@ impl Display for OperationType {
 #[derive(Clone, Debug)]
 enum OutputDest {
     Stdout,
+
+    /// Like `Stdout`, but regular output is written to stderr instead.
+    Stderr,
+
     Suppress,
     BufferForTest {
         stdout: Arc<Mutex<Vec<u8>>>,
@@ -569,6 +573,23 @@ impl Effects {
         }
     }
 
+    /// Send regular output for the returned `Effects` to stderr instead, such
+    /// as when stdout is reserved for machine-readable output.
+    pub fn redirect_output_to_stderr(&self) -> Self {
+        let dest = match &self.dest {
+            OutputDest::Stdout | OutputDest::Stderr => OutputDest::Stderr,
+            OutputDest::Suppress => OutputDest::Suppress,
+            OutputDest::BufferForTest { stdout: _, stderr } => OutputDest::BufferForTest {
+                stdout: Arc::clone(stderr),
+                stderr: Arc::clone(stderr),
+            },
+        };
+        Self {
+            dest,
+            ..self.clone()
+        }
+    }
+
     /// Apply transformations to the returned `Effects` to support emitting
     /// graphical output in the opposite of its usual order.
     pub fn reverse_order(&self, reverse: bool) -> Self {
@@ -608,7 +629,7 @@ impl Effects {
             operation_key: operation_key.clone(),
         };
         match self.dest {
-            OutputDest::Stdout => {}
+            OutputDest::Stdout | OutputDest::Stderr => {}
             OutputDest::Suppress | OutputDest::BufferForTest { .. } => {
                 return (self.clone(), progress)
             }
@@ -629,7 +650,7 @@ impl Effects {
 
     fn on_notify_progress(&self, operation_key: &OperationKey, current: usize, total: usize) {
         match self.dest {
-            OutputDest::Stdout => {}
+            OutputDest::Stdout | OutputDest::Stderr => {}
             OutputDest::Suppress | OutputDest::BufferForTest { .. } => return,
         }
 
@@ -640,7 +661,7 @@ impl Effects {
 
     fn on_notify_progress_inc(&self, operation_key: &OperationKey, increment: usize) {
         match self.dest {
-            OutputDest::Stdout => {}
+            OutputDest::Stdout | OutputDest::Stderr => {}
             OutputDest::Suppress | OutputDest::BufferForTest { .. } => return,
         }
 
@@ -655,7 +676,7 @@ impl Effects {
 
     fn on_set_message(&self, operation_key: &OperationKey, icon: OperationIcon, message: String) {
         match self.dest {
-            OutputDest::Stdout => {}
+            OutputDest::Stdout | OutputDest::Stderr => {}
             OutputDest::Suppress | OutputDest::BufferForTest { .. } => return,
         }
 
@@ -671,7 +692,7 @@ impl Effects {
 
     fn on_drop_progress_handle(&self, operation_key: &OperationKey) {
         match self.dest {
-            OutputDest::Stdout => {}
+            OutputDest::Stdout | OutputDest::Stderr => {}
             OutputDest::Suppress | OutputDest::BufferForTest { .. } => return,
         }
 
@@ -879,6 +900,16 @@ impl Write for OutputStream {
                 self.flush();
             }
 
+            OutputDest::Stderr => {
+                let mut error_stream = ErrorStream {
+                    dest: OutputDest::Stderr,
+                    buffer: Default::default(),
+                    updater_thread_handle: Arc::clone(&self.updater_thread_handle),
+                    root_operation: Arc::clone(&self.root_operation),
+                };
+                Write::write_str(&mut error_stream, s)?;
+            }
+
             OutputDest::Suppress => {
                 // Do nothing.
             }
@@ -934,7 +965,7 @@ impl WriteProgress for ErrorStream {
 impl Write for ErrorStream {
     fn write_str(&mut self, s: &str) -> std::fmt::Result {
         match &self.dest {
-            OutputDest::Stdout => {
+            OutputDest::Stdout | OutputDest::Stderr => {
                 self.buffer.push_str(s);
                 WriteProgress::flush(self);
             }
@@ -958,7 +989,7 @@ impl Write for ErrorStream {
 impl io::Write for ErrorStream {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         match &self.dest {
-            OutputDest::Stdout => {
+            OutputDest::Stdout | OutputDest::Stderr => {
                 self.buffer.push_str(buf.to_str_lossy().as_ref());
                 Ok(buf.len())
             }
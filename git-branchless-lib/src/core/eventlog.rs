@@ -13,12 +13,15 @@ use std::str::FromStr;
 use std::time::{Duration, SystemTime};
 
 use eyre::Context;
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
 use tracing::{error, instrument};
 
 use crate::core::effects::{Effects, OperationType};
 use crate::core::repo_ext::RepoExt;
 use crate::git::{CategorizedReferenceName, MaybeZeroOid, NonZeroOid, ReferenceName, Repo};
 
+use super::change_id::{init_tables as init_change_id_tables, record_rewrite};
 use super::repo_ext::RepoReferencesSnapshot;
 
 /// When this environment variable is set, we reuse the ID for the transaction
@@ -464,6 +467,48 @@ impl TryFrom<Row> for Event {
     }
 }
 
+/// The version of the format produced by [`EventLogDb::export_event_log`].
+pub const EXPORTED_EVENT_LOG_VERSION: u32 = 1;
+
+/// A row of the `event_transactions` table, in a form which can be serialized
+/// and later imported into another repository.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[allow(missing_docs)]
+pub struct ExportedEventTransaction {
+    pub timestamp: f64,
+    pub event_tx_id: isize,
+    pub message: Option<String>,
+}
+
+/// A row of the `event_log` table, in a form which can be serialized and later
+/// imported into another repository.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[allow(missing_docs)]
+pub struct ExportedEvent {
+    pub timestamp: f64,
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub event_tx_id: isize,
+    pub old_ref: Option<String>,
+    pub new_ref: Option<String>,
+    pub ref_name: Option<String>,
+    pub message: Option<String>,
+}
+
+/// A portable copy of the entire event log, including the transactions which
+/// the events belong to.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ExportedEventLog {
+    /// The version of the export format. See [`EXPORTED_EVENT_LOG_VERSION`].
+    pub version: u32,
+
+    /// The event transactions, ordered from oldest to newest.
+    pub transactions: Vec<ExportedEventTransaction>,
+
+    /// The events, ordered from oldest to newest.
+    pub events: Vec<ExportedEvent>,
+}
+
 /// Stores `Event`s on disk.
 pub struct EventLogDb<'conn> {
     conn: &'conn rusqlite::Connection,
@@ -475,6 +520,26 @@ impl std::fmt::Debug for EventLogDb<'_> {
     }
 }
 
+fn read_row(row: &rusqlite::Row) -> rusqlite::Result<Row> {
+    let timestamp: f64 = row.get("timestamp")?;
+    let event_tx_id: isize = row.get("event_tx_id")?;
+    let type_: String = row.get("type")?;
+    let ref_name: Option<String> = row.get("ref_name")?;
+    let old_ref: Option<String> = row.get("old_ref")?;
+    let new_ref: Option<String> = row.get("new_ref")?;
+    let message: Option<String> = row.get("message")?;
+
+    Ok(Row {
+        timestamp,
+        event_tx_id,
+        type_,
+        ref_name: ref_name.map(ReferenceName::from),
+        ref1: old_ref.map(ReferenceName::from),
+        ref2: new_ref.map(ReferenceName::from),
+        message: message.map(ReferenceName::from),
+    })
+}
+
 #[instrument]
 fn init_tables(conn: &rusqlite::Connection) -> eyre::Result<()> {
     conn.execute(
@@ -519,17 +584,39 @@ impl<'conn> EventLogDb<'conn> {
     #[instrument]
     pub fn new(conn: &'conn rusqlite::Connection) -> eyre::Result<Self> {
         init_tables(conn)?;
+        init_change_id_tables(conn)?;
         Ok(EventLogDb { conn })
     }
 
+    /// Start a transaction which will write to the database. The database is
+    /// shared between all worktrees of the repository, so the write lock is
+    /// acquired up-front to serialize writers, rather than upgrading from a
+    /// read lock partway through the transaction (which fails immediately if
+    /// another process is already writing).
+    fn begin_write_transaction(&self) -> rusqlite::Result<rusqlite::Transaction<'conn>> {
+        rusqlite::Transaction::new_unchecked(self.conn, rusqlite::TransactionBehavior::Immediate)
+    }
+
     /// Add events in the given order to the database, in a transaction.
     ///
     /// Args:
     /// * events: The events to add.
     #[instrument]
     pub fn add_events(&self, events: Vec<Event>) -> eyre::Result<()> {
-        let tx = self.conn.unchecked_transaction()?;
+        let tx = self.begin_write_transaction()?;
         for event in events {
+            // Carry the change ID over to the rewritten commit in the same
+            // transaction, so that it's never observed without one.
+            if let Event::RewriteEvent {
+                timestamp: _,
+                event_tx_id: _,
+                old_commit_oid: MaybeZeroOid::NonZero(old_commit_oid),
+                new_commit_oid: MaybeZeroOid::NonZero(new_commit_oid),
+            } = &event
+            {
+                record_rewrite(&tx, *old_commit_oid, *new_commit_oid)?;
+            }
+
             let row = match Row::try_from(event) {
                 Ok(row) => row,
                 Err(()) => continue,
@@ -589,31 +676,146 @@ FROM event_log
 ORDER BY rowid ASC
 ",
         )?;
-        let rows: rusqlite::Result<Vec<Row>> = stmt
-            .query_map(rusqlite::params![], |row| {
-                let timestamp: f64 = row.get("timestamp")?;
-                let event_tx_id: isize = row.get("event_tx_id")?;
-                let type_: String = row.get("type")?;
-                let ref_name: Option<String> = row.get("ref_name")?;
-                let old_ref: Option<String> = row.get("old_ref")?;
-                let new_ref: Option<String> = row.get("new_ref")?;
-                let message: Option<String> = row.get("message")?;
-
-                Ok(Row {
-                    timestamp,
-                    event_tx_id,
-                    type_,
-                    ref_name: ref_name.map(ReferenceName::from),
-                    ref1: old_ref.map(ReferenceName::from),
-                    ref2: new_ref.map(ReferenceName::from),
-                    message: message.map(ReferenceName::from),
-                })
-            })?
-            .collect();
+        let rows: rusqlite::Result<Vec<Row>> =
+            stmt.query_map(rusqlite::params![], read_row)?.collect();
         let rows = rows?;
         rows.into_iter().map(Event::try_from).collect()
     }
 
+    /// Delete events which happened before `cutoff`, except for those which
+    /// are needed to reconstruct the state of the repository as of `cutoff`.
+    ///
+    /// For each commit which is still visible as of `cutoff`, the latest event
+    /// which made it visible is kept. Likewise, for each reference which still
+    /// exists as of `cutoff`, the latest update to it is kept. This means that
+    /// the repository can still be undone to any point in time after `cutoff`.
+    ///
+    /// Returns: The pruned events.
+    #[instrument]
+    pub fn prune_events(&self, cutoff: SystemTime) -> eyre::Result<Vec<Event>> {
+        let cutoff = cutoff
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .wrap_err("Calculating event log cutoff timestamp")?
+            .as_secs_f64();
+
+        let tx = self.begin_write_transaction()?;
+        let rows: Vec<(i64, Row)> = {
+            let mut stmt = tx.prepare(
+                "
+SELECT rowid, timestamp, type, event_tx_id, old_ref, new_ref, ref_name, message
+FROM event_log
+WHERE timestamp < :cutoff
+ORDER BY rowid ASC
+",
+            )?;
+            let rows: rusqlite::Result<Vec<(i64, Row)>> = stmt
+                .query_map(rusqlite::named_params! { ":cutoff": cutoff }, |row| {
+                    let rowid: i64 = row.get("rowid")?;
+                    Ok((rowid, read_row(row)?))
+                })?
+                .collect();
+            rows?
+        };
+
+        #[derive(Eq, Hash, PartialEq)]
+        enum Key {
+            Commit(NonZeroOid),
+            Ref(ReferenceName),
+        }
+
+        // Walk the events from newest to oldest, so that the first event seen
+        // for a given commit or reference is the one which determines its
+        // state as of the cutoff.
+        let mut seen_keys: HashSet<Key> = HashSet::new();
+        let mut pruned_events = Vec::new();
+        let mut pruned_rowids = Vec::new();
+        for (rowid, row) in rows.into_iter().rev() {
+            let event = match Event::try_from(row) {
+                Ok(event) => event,
+                // Don't delete anything that we don't understand.
+                Err(_) => continue,
+            };
+
+            let keep = match &event {
+                Event::CommitEvent {
+                    timestamp: _,
+                    event_tx_id: _,
+                    commit_oid,
+                }
+                | Event::UnobsoleteEvent {
+                    timestamp: _,
+                    event_tx_id: _,
+                    commit_oid,
+                } => seen_keys.insert(Key::Commit(*commit_oid)),
+
+                Event::ObsoleteEvent {
+                    timestamp: _,
+                    event_tx_id: _,
+                    commit_oid,
+                } => {
+                    seen_keys.insert(Key::Commit(*commit_oid));
+                    false
+                }
+
+                Event::RewriteEvent {
+                    timestamp: _,
+                    event_tx_id: _,
+                    old_commit_oid,
+                    new_commit_oid,
+                } => {
+                    if let MaybeZeroOid::NonZero(old_commit_oid) = old_commit_oid {
+                        seen_keys.insert(Key::Commit(*old_commit_oid));
+                    }
+                    match new_commit_oid {
+                        MaybeZeroOid::NonZero(new_commit_oid) => {
+                            seen_keys.insert(Key::Commit(*new_commit_oid))
+                        }
+                        MaybeZeroOid::Zero => false,
+                    }
+                }
+
+                Event::RefUpdateEvent {
+                    timestamp: _,
+                    event_tx_id: _,
+                    ref_name,
+                    old_oid: _,
+                    new_oid,
+                    message: _,
+                } => {
+                    seen_keys.insert(Key::Ref(ref_name.clone()))
+                        && matches!(new_oid, MaybeZeroOid::NonZero(_))
+                }
+
+                Event::WorkingCopySnapshot { .. } => false,
+            };
+            if !keep {
+                pruned_rowids.push(rowid);
+                pruned_events.push(event);
+            }
+        }
+
+        for rowid in pruned_rowids {
+            tx.execute(
+                "DELETE FROM event_log WHERE rowid = :rowid",
+                rusqlite::named_params! { ":rowid": rowid },
+            )
+            .wrap_err("Deleting pruned event")?;
+        }
+        tx.execute(
+            "
+DELETE FROM event_transactions
+WHERE timestamp < :cutoff
+AND event_tx_id NOT IN (SELECT event_tx_id FROM event_log)
+",
+            rusqlite::named_params! { ":cutoff": cutoff },
+        )
+        .wrap_err("Deleting empty event transactions")?;
+        tx.commit()?;
+
+        pruned_events.reverse();
+        Ok(pruned_events)
+    }
+
     #[instrument]
     fn make_transaction_id_inner(
         &self,
@@ -626,7 +828,7 @@ ORDER BY rowid ASC
             }
         }
 
-        let tx = self.conn.unchecked_transaction()?;
+        let tx = self.begin_write_transaction()?;
 
         let timestamp = now
             .duration_since(SystemTime::UNIX_EPOCH)
@@ -665,6 +867,201 @@ ORDER BY rowid ASC
         self.make_transaction_id_inner(now, message.as_ref())
     }
 
+    /// Dump the contents of the event log, including its transactions, so that
+    /// it can be imported into another repository with
+    /// [`EventLogDb::import_event_log`].
+    #[instrument]
+    pub fn export_event_log(&self) -> eyre::Result<ExportedEventLog> {
+        let mut stmt = self.conn.prepare(
+            "
+SELECT timestamp, event_tx_id, message
+FROM event_transactions
+ORDER BY event_tx_id ASC
+",
+        )?;
+        let transactions: rusqlite::Result<Vec<ExportedEventTransaction>> = stmt
+            .query_map(rusqlite::params![], |row| {
+                Ok(ExportedEventTransaction {
+                    timestamp: row.get("timestamp")?,
+                    event_tx_id: row.get("event_tx_id")?,
+                    message: row.get("message")?,
+                })
+            })?
+            .collect();
+        let transactions = transactions.wrap_err("Reading event transactions")?;
+
+        let mut stmt = self.conn.prepare(
+            "
+SELECT timestamp, type, event_tx_id, old_ref, new_ref, ref_name, message
+FROM event_log
+ORDER BY rowid ASC
+",
+        )?;
+        let events: rusqlite::Result<Vec<ExportedEvent>> = stmt
+            .query_map(rusqlite::params![], |row| {
+                Ok(ExportedEvent {
+                    timestamp: row.get("timestamp")?,
+                    type_: row.get("type")?,
+                    event_tx_id: row.get("event_tx_id")?,
+                    old_ref: row.get("old_ref")?,
+                    new_ref: row.get("new_ref")?,
+                    ref_name: row.get("ref_name")?,
+                    message: row.get("message")?,
+                })
+            })?
+            .collect();
+        let events = events.wrap_err("Reading events")?;
+
+        Ok(ExportedEventLog {
+            version: EXPORTED_EVENT_LOG_VERSION,
+            transactions,
+            events,
+        })
+    }
+
+    /// Append the contents of a previously-exported event log to this event
+    /// log, in a transaction.
+    ///
+    /// The imported transactions are assigned new IDs, so that they don't
+    /// collide with any transactions already present in this event log.
+    /// Transactions and events which are already present (such as when the
+    /// same export is imported twice) are skipped. A transaction is identified
+    /// by its timestamp and message, and an event by its timestamp, type,
+    /// transaction, and references.
+    ///
+    /// Returns: The number of events which were imported.
+    #[instrument(skip(exported_event_log))]
+    pub fn import_event_log(&self, exported_event_log: ExportedEventLog) -> eyre::Result<usize> {
+        let ExportedEventLog {
+            version,
+            transactions,
+            events,
+        } = exported_event_log;
+        if version != EXPORTED_EVENT_LOG_VERSION {
+            eyre::bail!(
+                "Unsupported event log export version {version} (expected version {EXPORTED_EVENT_LOG_VERSION})"
+            );
+        }
+
+        let tx = self.begin_write_transaction()?;
+        let mut event_tx_id_map: HashMap<isize, isize> = HashMap::new();
+        for transaction in transactions {
+            let ExportedEventTransaction {
+                timestamp,
+                event_tx_id,
+                message,
+            } = transaction;
+            let existing_event_tx_id: Option<isize> = tx
+                .query_row(
+                    "
+SELECT event_tx_id
+FROM event_transactions
+WHERE timestamp = :timestamp AND message IS :message
+",
+                    rusqlite::named_params! {
+                        ":timestamp": timestamp,
+                        ":message": &message,
+                    },
+                    |row| row.get("event_tx_id"),
+                )
+                .optional()
+                .wrap_err("Looking up event transaction")?;
+            if let Some(existing_event_tx_id) = existing_event_tx_id {
+                event_tx_id_map.insert(event_tx_id, existing_event_tx_id);
+                continue;
+            }
+
+            tx.execute(
+                "
+INSERT INTO event_transactions
+(timestamp, message)
+VALUES
+(:timestamp, :message)
+",
+                rusqlite::named_params! {
+                    ":timestamp": timestamp,
+                    ":message": &message,
+                },
+            )
+            .wrap_err("Importing event transaction")?;
+            let new_event_tx_id: isize = tx.last_insert_rowid().try_into()?;
+            event_tx_id_map.insert(event_tx_id, new_event_tx_id);
+        }
+
+        let mut num_events = 0;
+        for event in events {
+            let ExportedEvent {
+                timestamp,
+                type_,
+                event_tx_id,
+                old_ref,
+                new_ref,
+                ref_name,
+                message,
+            } = event;
+            let event_tx_id = match event_tx_id_map.get(&event_tx_id) {
+                Some(event_tx_id) => *event_tx_id,
+                None => eyre::bail!("Event refers to unknown event transaction ID {event_tx_id}"),
+            };
+            let is_duplicate: bool = tx
+                .query_row(
+                    "
+SELECT EXISTS (
+    SELECT 1
+    FROM event_log
+    WHERE timestamp = :timestamp
+        AND type = :type
+        AND event_tx_id = :event_tx_id
+        AND old_ref IS :old_ref
+        AND new_ref IS :new_ref
+        AND ref_name IS :ref_name
+)
+",
+                    rusqlite::named_params! {
+                        ":timestamp": timestamp,
+                        ":type": &type_,
+                        ":event_tx_id": event_tx_id,
+                        ":old_ref": &old_ref,
+                        ":new_ref": &new_ref,
+                        ":ref_name": &ref_name,
+                    },
+                    |row| row.get(0),
+                )
+                .wrap_err("Looking up event")?;
+            if is_duplicate {
+                continue;
+            }
+
+            tx.execute(
+                "
+INSERT INTO event_log VALUES (
+    :timestamp,
+    :type,
+    :event_tx_id,
+    :old_ref,
+    :new_ref,
+    :ref_name,
+    :message
+)
+",
+                rusqlite::named_params! {
+                    ":timestamp": timestamp,
+                    ":type": &type_,
+                    ":event_tx_id": event_tx_id,
+                    ":old_ref": &old_ref,
+                    ":new_ref": &new_ref,
+                    ":ref_name": &ref_name,
+                    ":message": &message,
+                },
+            )
+            .wrap_err("Importing event")?;
+            num_events += 1;
+        }
+
+        tx.commit()?;
+        Ok(num_events)
+    }
+
     /// Get the message associated with the given transaction.
     pub fn get_transaction_message(&self, event_tx_id: EventTransactionId) -> eyre::Result<String> {
         let event_tx_id = match event_tx_id {
@@ -720,6 +1117,29 @@ pub fn should_ignore_ref_updates(reference_name: &ReferenceName) -> bool {
     )
 }
 
+/// Get the name under which updates to `HEAD` in the given repository's
+/// worktree are recorded in the event log.
+///
+/// The event log is shared between all worktrees of a repository, but each
+/// worktree has its own `HEAD`. Updates to the main worktree's `HEAD` are
+/// recorded as `HEAD`, while updates to a secondary worktree's `HEAD` are
+/// recorded as `worktrees/<name>/HEAD`, which is how Git itself refers to that
+/// reference from other worktrees.
+pub fn get_head_ref_name(repo: &Repo) -> ReferenceName {
+    match repo.get_worktree_name() {
+        Some(worktree_name) => ReferenceName::from(format!("worktrees/{worktree_name}/HEAD")),
+        None => ReferenceName::from("HEAD"),
+    }
+}
+
+/// Determine whether the given reference name, as recorded in the event log,
+/// refers to the `HEAD` of any worktree. See [`get_head_ref_name`].
+pub fn is_head_ref_name(reference_name: &ReferenceName) -> bool {
+    let reference_name = reference_name.as_str();
+    reference_name == "HEAD"
+        || (reference_name.starts_with("worktrees/") && reference_name.ends_with("/HEAD"))
+}
+
 #[derive(Debug)]
 enum EventClassification {
     Show,
@@ -779,6 +1199,10 @@ pub struct EventReplayer {
     /// The name of the reference representing the main branch.
     main_branch_reference_name: ReferenceName,
 
+    /// The name under which updates to `HEAD` for the current worktree are
+    /// recorded. See [`get_head_ref_name`].
+    head_ref_name: ReferenceName,
+
     /// The events that have affected each commit.
     commit_history: HashMap<NonZeroOid, Vec<EventInfo>>,
 
@@ -802,11 +1226,12 @@ impl std::fmt::Debug for EventReplayer {
 }
 
 impl EventReplayer {
-    fn new(main_branch_reference_name: ReferenceName) -> Self {
+    fn new(main_branch_reference_name: ReferenceName, head_ref_name: ReferenceName) -> Self {
         EventReplayer {
             id_counter: 0,
             events: vec![],
             main_branch_reference_name,
+            head_ref_name,
             commit_history: HashMap::new(),
             ref_locations: HashMap::new(),
         }
@@ -827,13 +1252,19 @@ impl EventReplayer {
         let (_effects, _progress) = effects.start_operation(OperationType::ProcessEvents);
 
         let main_branch_reference_name = repo.get_main_branch()?.get_reference_name()?;
-        let mut result = EventReplayer::new(main_branch_reference_name);
+        let mut result = EventReplayer::new(main_branch_reference_name, get_head_ref_name(repo));
         for event in event_log_db.get_events()? {
             result.process_event(&event);
         }
         Ok(result)
     }
 
+    /// Get the name under which updates to `HEAD` for the current worktree are
+    /// recorded in the event log. See [`get_head_ref_name`].
+    pub fn get_head_ref_name(&self) -> &ReferenceName {
+        &self.head_ref_name
+    }
+
     /// Process the given event.
     ///
     /// This also sets the event cursor to point to immediately after the event
@@ -1191,41 +1622,72 @@ impl EventReplayer {
 
     /// Get the OID of `HEAD` at the cursor's point in time.
     ///
+    /// Only updates to the `HEAD` of the worktree which this replayer was
+    /// constructed for are considered.
+    ///
     /// Returns: The OID pointed to by `HEAD` at that time, or `None` if `HEAD`
     /// was never observed.
     fn get_cursor_head_oid(&self, cursor: EventCursor) -> Option<NonZeroOid> {
         let cursor_event_id: usize = cursor.event_id.try_into().unwrap();
-        self.events[0..cursor_event_id]
+        let events = &self.events[0..cursor_event_id];
+
+        // Commits and snapshots aren't associated with a worktree, so use the
+        // `HEAD` updates of other worktrees to exclude the ones which were made
+        // there.
+        let other_worktree_head_oids: HashSet<NonZeroOid> = events
             .iter()
-            .rev()
-            .find_map(|event| {
-                match &event {
-                    Event::RefUpdateEvent {
-                        ref_name,
-                        new_oid: MaybeZeroOid::NonZero(new_oid),
-                        ..
-                    } if ref_name.as_str() == "HEAD" => Some(*new_oid),
-                    Event::RefUpdateEvent { .. } => None,
-
-                    // Not strictly necessary, but helps to compensate in case
-                    // the user is not running Git v2.29 or above, and therefore
-                    // doesn't have the corresponding `RefUpdateEvent`.
-                    Event::CommitEvent { commit_oid, .. } => Some(*commit_oid),
-
-                    Event::WorkingCopySnapshot {
-                        head_oid: MaybeZeroOid::NonZero(head_oid),
-                        ..
-                    } => Some(*head_oid),
-                    Event::WorkingCopySnapshot {
-                        head_oid: MaybeZeroOid::Zero,
-                        ..
-                    } => None,
-
-                    Event::RewriteEvent { .. }
-                    | Event::ObsoleteEvent { .. }
-                    | Event::UnobsoleteEvent { .. } => None,
+            .filter_map(|event| match event {
+                Event::RefUpdateEvent {
+                    ref_name,
+                    new_oid: MaybeZeroOid::NonZero(new_oid),
+                    ..
+                } if is_head_ref_name(ref_name) && ref_name != &self.head_ref_name => {
+                    Some(*new_oid)
                 }
+                _ => None,
             })
+            .collect();
+
+        events.iter().rev().find_map(|event| {
+            match &event {
+                Event::RefUpdateEvent {
+                    ref_name,
+                    new_oid: MaybeZeroOid::NonZero(new_oid),
+                    ..
+                } if ref_name == &self.head_ref_name => Some(*new_oid),
+                Event::RefUpdateEvent { .. } => None,
+
+                // Not strictly necessary, but helps to compensate in case
+                // the user is not running Git v2.29 or above, and therefore
+                // doesn't have the corresponding `RefUpdateEvent`.
+                Event::CommitEvent { commit_oid, .. } => {
+                    if other_worktree_head_oids.contains(commit_oid) {
+                        None
+                    } else {
+                        Some(*commit_oid)
+                    }
+                }
+
+                Event::WorkingCopySnapshot {
+                    head_oid: MaybeZeroOid::NonZero(head_oid),
+                    ..
+                } => {
+                    if other_worktree_head_oids.contains(head_oid) {
+                        None
+                    } else {
+                        Some(*head_oid)
+                    }
+                }
+                Event::WorkingCopySnapshot {
+                    head_oid: MaybeZeroOid::Zero,
+                    ..
+                } => None,
+
+                Event::RewriteEvent { .. }
+                | Event::ObsoleteEvent { .. }
+                | Event::UnobsoleteEvent { .. } => None,
+            }
+        })
     }
 
     fn get_cursor_branch_oid(
@@ -1402,7 +1864,7 @@ pub mod testing {
 
     /// Create a new `EventReplayer`, for testing.
     pub fn new_event_replayer(main_branch_reference_name: ReferenceName) -> EventReplayer {
-        EventReplayer::new(main_branch_reference_name)
+        EventReplayer::new(main_branch_reference_name, ReferenceName::from("HEAD"))
     }
 
     /// Create a new transaction ID, for testing.
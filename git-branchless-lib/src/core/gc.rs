@@ -9,16 +9,19 @@
 //! active.
 
 use std::fmt::Write;
+use std::time::SystemTime;
 
 use eyre::Context;
 use tracing::instrument;
 
+use crate::core::change_id::ChangeIdDb;
+use crate::core::config::get_event_log_retention;
 use crate::core::effects::Effects;
 use crate::core::eventlog::{
-    is_gc_ref, CommitActivityStatus, EventCursor, EventLogDb, EventReplayer,
+    is_gc_ref, CommitActivityStatus, Event, EventCursor, EventLogDb, EventReplayer,
 };
 use crate::core::formatting::Pluralize;
-use crate::git::{NonZeroOid, Reference, Repo};
+use crate::git::{NonZeroOid, Reference, ReferenceName, Repo};
 
 /// Find references under `refs/branchless/` which point to commits which are no
 /// longer active. These are safe to remove.
@@ -124,5 +127,31 @@ pub fn gc(effects: &Effects) -> eyre::Result<()> {
         effects.get_output_stream(),
         "branchless: {num_dangling_references} deleted",
     )?;
+
+    ChangeIdDb::new(&conn)?.prune(&repo)?;
+
+    // Prune only after deleting dangling references, since pruning the events
+    // for a hidden commit makes it no longer appear to be hidden.
+    if let Some(retention) = get_event_log_retention(&repo)? {
+        let pruned_events = event_log_db.prune_events(SystemTime::now() - retention)?;
+        for event in pruned_events.iter() {
+            if let Event::WorkingCopySnapshot { commit_oid, .. } = event {
+                let ref_name = ReferenceName::from(format!("refs/branchless/{commit_oid}"));
+                if let Some(mut reference) = repo.find_reference(&ref_name)? {
+                    reference.delete()?;
+                }
+            }
+        }
+        writeln!(
+            effects.get_output_stream(),
+            "branchless: {} pruned from the event log",
+            Pluralize {
+                determiner: None,
+                amount: pruned_events.len(),
+                unit: ("event", "events"),
+            }
+        )?;
+    }
+
     Ok(())
 }
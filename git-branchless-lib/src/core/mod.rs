@@ -1,6 +1,8 @@
 //! Core algorithms and data structures.
 
+pub mod change_id;
 pub mod check_out;
+pub mod check_storage;
 pub mod config;
 pub mod dag;
 pub mod effects;
@@ -8,6 +10,11 @@ pub mod eventlog;
 pub mod formatting;
 pub mod gc;
 pub mod node_descriptors;
+pub mod path_index;
+pub mod pin;
 pub mod repo_ext;
+pub mod rerere;
+pub mod review_status;
 pub mod rewrite;
+pub mod stash;
 pub mod task;
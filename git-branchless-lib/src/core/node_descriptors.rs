@@ -14,10 +14,13 @@ use lazy_static::lazy_static;
 use regex::Regex;
 use tracing::instrument;
 
+use crate::core::change_id::ChangeIdDb;
 use crate::core::config::{
-    get_commit_descriptors_branches, get_commit_descriptors_differential_revision,
-    get_commit_descriptors_relative_time,
+    get_commit_descriptors_branches, get_commit_descriptors_change_id,
+    get_commit_descriptors_differential_revision, get_commit_descriptors_empty_commits,
+    get_commit_descriptors_relative_time, get_commit_descriptors_review_status,
 };
+use crate::core::review_status::ReviewStatusDb;
 use crate::git::{
     CategorizedReferenceName, Commit, NonZeroOid, ReferenceName, Repo, ResolvedReferenceInfo,
 };
@@ -440,6 +443,124 @@ impl NodeDescriptor for DifferentialRevisionDescriptor<'_> {
     }
 }
 
+/// Display the change ID of a given commit, which identifies it across
+/// rewrites.
+#[derive(Debug)]
+pub struct ChangeIdDescriptor<'conn> {
+    change_id_db: Option<ChangeIdDb<'conn>>,
+}
+
+impl<'conn> ChangeIdDescriptor<'conn> {
+    /// Constructor.
+    pub fn new(repo: &Repo, conn: &'conn rusqlite::Connection) -> eyre::Result<Self> {
+        let change_id_db = if get_commit_descriptors_change_id(repo)? {
+            Some(ChangeIdDb::new(conn)?)
+        } else {
+            None
+        };
+        Ok(ChangeIdDescriptor { change_id_db })
+    }
+}
+
+impl NodeDescriptor for ChangeIdDescriptor<'_> {
+    #[instrument]
+    fn describe_node(
+        &mut self,
+        _glyphs: &Glyphs,
+        object: &NodeObject,
+    ) -> eyre::Result<Option<StyledString>> {
+        let change_id_db = match &self.change_id_db {
+            Some(change_id_db) => change_id_db,
+            None => return Ok(None),
+        };
+        let change_id = change_id_db.get_change_id(object.get_oid())?;
+        Ok(Some(StyledString::styled(
+            change_id.get_short(),
+            BaseColor::Magenta.dark(),
+        )))
+    }
+}
+
+/// Display the cached code review status of a given commit, as of the last
+/// `git submit status`.
+#[derive(Debug)]
+pub struct ReviewStatusDescriptor<'conn> {
+    review_status_db: Option<ReviewStatusDb<'conn>>,
+}
+
+impl<'conn> ReviewStatusDescriptor<'conn> {
+    /// Constructor.
+    pub fn new(repo: &Repo, conn: &'conn rusqlite::Connection) -> eyre::Result<Self> {
+        let review_status_db = if get_commit_descriptors_review_status(repo)? {
+            Some(ReviewStatusDb::new(conn)?)
+        } else {
+            None
+        };
+        Ok(ReviewStatusDescriptor { review_status_db })
+    }
+}
+
+impl NodeDescriptor for ReviewStatusDescriptor<'_> {
+    #[instrument]
+    fn describe_node(
+        &mut self,
+        _glyphs: &Glyphs,
+        object: &NodeObject,
+    ) -> eyre::Result<Option<StyledString>> {
+        let review_status_db = match &self.review_status_db {
+            Some(review_status_db) => review_status_db,
+            None => return Ok(None),
+        };
+        let review_status = match review_status_db.get(object.get_oid())? {
+            Some(review_status) => review_status,
+            None => return Ok(None),
+        };
+        Ok(Some(StyledString::styled(
+            review_status.describe(),
+            BaseColor::Cyan.dark(),
+        )))
+    }
+}
+
+/// Mark commits which don't contain any changes, such as those created with
+/// `git commit --allow-empty` to serve as markers in a stack.
+#[derive(Debug)]
+pub struct EmptyCommitDescriptor {
+    is_enabled: bool,
+}
+
+impl EmptyCommitDescriptor {
+    /// Constructor.
+    pub fn new(repo: &Repo) -> eyre::Result<Self> {
+        let is_enabled = get_commit_descriptors_empty_commits(repo)?;
+        Ok(EmptyCommitDescriptor { is_enabled })
+    }
+}
+
+impl NodeDescriptor for EmptyCommitDescriptor {
+    #[instrument]
+    fn describe_node(
+        &mut self,
+        _glyphs: &Glyphs,
+        object: &NodeObject,
+    ) -> eyre::Result<Option<StyledString>> {
+        if !self.is_enabled {
+            return Ok(None);
+        }
+        let commit = match object {
+            NodeObject::Commit { commit } => commit,
+            NodeObject::GarbageCollected { oid: _ } => return Ok(None),
+        };
+        if !commit.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(StyledString::styled(
+            "(empty)",
+            BaseColor::Black.light(),
+        )))
+    }
+}
+
 /// Display how long ago the given commit was committed.
 #[derive(Debug)]
 pub struct RelativeTimeDescriptor {
@@ -0,0 +1,148 @@
+//! Persistent index of the paths changed by each commit.
+//!
+//! Computing the paths touched by a commit requires diffing its tree against
+//! its parents' trees, which is slow when done for thousands of commits (such
+//! as when evaluating `paths.changed()` over all draft commits). Since commits
+//! are immutable, the result can be stored in the database the first time it's
+//! computed and reused for all later queries.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use eyre::Context;
+use tracing::instrument;
+
+use crate::git::{Commit, NonZeroOid, Repo};
+
+/// Stores the paths changed by each commit. The index is filled in lazily:
+/// each commit is diffed at most once, and its changed paths are then read
+/// from the database.
+#[derive(Debug)]
+pub struct PathIndexDb {
+    conn: Mutex<rusqlite::Connection>,
+}
+
+#[instrument]
+fn init_tables(conn: &rusqlite::Connection) -> eyre::Result<()> {
+    conn.execute(
+        "
+CREATE TABLE IF NOT EXISTS path_index_commits (
+    commit_oid TEXT NOT NULL PRIMARY KEY
+)
+",
+        rusqlite::params![],
+    )
+    .wrap_err("Creating `path_index_commits` table")?;
+
+    conn.execute(
+        "
+CREATE TABLE IF NOT EXISTS path_index_paths (
+    commit_oid TEXT NOT NULL,
+    path TEXT NOT NULL
+)
+",
+        rusqlite::params![],
+    )
+    .wrap_err("Creating `path_index_paths` table")?;
+
+    conn.execute(
+        "
+CREATE INDEX IF NOT EXISTS path_index_paths_commit_oid
+ON path_index_paths (commit_oid)
+",
+        rusqlite::params![],
+    )
+    .wrap_err("Creating `path_index_paths_commit_oid` index")?;
+
+    Ok(())
+}
+
+impl PathIndexDb {
+    /// Constructor.
+    #[instrument]
+    pub fn new(conn: rusqlite::Connection) -> eyre::Result<Self> {
+        init_tables(&conn)?;
+        Ok(PathIndexDb {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn get_indexed_paths(&self, commit_oid: NonZeroOid) -> eyre::Result<Option<HashSet<PathBuf>>> {
+        let conn = self.conn.lock().unwrap();
+        let commit_oid = commit_oid.to_string();
+        let is_indexed = conn
+            .prepare_cached("SELECT 1 FROM path_index_commits WHERE commit_oid = ?")?
+            .exists(rusqlite::params![commit_oid])
+            .wrap_err("Querying path index commits")?;
+        if !is_indexed {
+            return Ok(None);
+        }
+
+        let mut stmt =
+            conn.prepare_cached("SELECT path FROM path_index_paths WHERE commit_oid = ?")?;
+        let paths = stmt
+            .query_map(rusqlite::params![commit_oid], |row| {
+                let path: String = row.get("path")?;
+                Ok(PathBuf::from(path))
+            })?
+            .collect::<rusqlite::Result<HashSet<_>>>()
+            .wrap_err("Querying path index paths")?;
+        Ok(Some(paths))
+    }
+
+    fn add_indexed_paths(
+        &self,
+        commit_oid: NonZeroOid,
+        paths: &HashSet<PathBuf>,
+    ) -> eyre::Result<()> {
+        // Paths are stored as text, so commits touching paths which aren't
+        // valid UTF-8 aren't indexed, and are diffed every time instead.
+        let paths: Option<Vec<&str>> = paths.iter().map(|path| path.to_str()).collect();
+        let paths = match paths {
+            Some(paths) => paths,
+            None => return Ok(()),
+        };
+
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+        let commit_oid = commit_oid.to_string();
+        let num_inserted = tx
+            .execute(
+                "INSERT OR IGNORE INTO path_index_commits (commit_oid) VALUES (?)",
+                rusqlite::params![commit_oid],
+            )
+            .wrap_err("Inserting path index commit")?;
+        // Another process may have indexed this commit in the meantime.
+        if num_inserted > 0 {
+            for path in paths {
+                tx.execute(
+                    "INSERT INTO path_index_paths (commit_oid, path) VALUES (?, ?)",
+                    rusqlite::params![commit_oid, path],
+                )
+                .wrap_err("Inserting path index path")?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Get the file paths which were added, removed, or changed by the given
+    /// commit, as in `Repo::get_paths_touched_by_commit`, consulting and
+    /// updating the index.
+    #[instrument]
+    pub fn get_paths_touched_by_commit(
+        &self,
+        repo: &Repo,
+        commit: &Commit,
+    ) -> eyre::Result<HashSet<PathBuf>> {
+        let commit_oid = commit.get_oid();
+        if let Some(paths) = self.get_indexed_paths(commit_oid)? {
+            return Ok(paths);
+        }
+
+        let paths = repo.get_paths_touched_by_commit(commit)?;
+        self.add_indexed_paths(commit_oid, &paths)?;
+        Ok(paths)
+    }
+}
@@ -0,0 +1,114 @@
+//! Persist the set of commits which were pinned with `git branchless pin`.
+//! Stacks containing a pinned commit are left in place by `git sync` and `git
+//! restack`, such as for work which is intentionally based on an old release
+//! branch.
+//!
+//! Pins are stored by change ID (see `ChangeIdDb`), so a pinned commit stays
+//! pinned after it's amended or otherwise rewritten.
+
+use eyre::Context;
+use tracing::instrument;
+
+use crate::core::change_id::ChangeIdDb;
+use crate::core::dag::{CommitSet, Dag};
+use crate::git::NonZeroOid;
+
+/// Stores the change IDs of pinned commits.
+pub struct PinDb<'conn> {
+    conn: &'conn rusqlite::Connection,
+    change_id_db: ChangeIdDb<'conn>,
+}
+
+impl std::fmt::Debug for PinDb<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<PinDb>")
+    }
+}
+
+#[instrument]
+fn init_tables(conn: &rusqlite::Connection) -> eyre::Result<()> {
+    conn.execute(
+        "
+CREATE TABLE IF NOT EXISTS pinned_changes (
+    change_id TEXT NOT NULL PRIMARY KEY
+)
+",
+        rusqlite::params![],
+    )
+    .wrap_err("Creating `pinned_changes` table")?;
+
+    Ok(())
+}
+
+impl<'conn> PinDb<'conn> {
+    /// Constructor.
+    #[instrument]
+    pub fn new(conn: &'conn rusqlite::Connection) -> eyre::Result<Self> {
+        init_tables(conn)?;
+        let change_id_db = ChangeIdDb::new(conn)?;
+        Ok(PinDb { conn, change_id_db })
+    }
+
+    /// Pin the given commit. Returns `false` if it was already pinned.
+    #[instrument]
+    pub fn pin(&self, commit_oid: NonZeroOid) -> eyre::Result<bool> {
+        let change_id = self.change_id_db.get_change_id(commit_oid)?;
+        let num_rows = self
+            .conn
+            .execute(
+                "INSERT OR IGNORE INTO pinned_changes (change_id) VALUES (?)",
+                rusqlite::params![change_id.as_str()],
+            )
+            .wrap_err("Pinning commit")?;
+        Ok(num_rows > 0)
+    }
+
+    /// Unpin the given commit. Returns `false` if it wasn't pinned.
+    #[instrument]
+    pub fn unpin(&self, commit_oid: NonZeroOid) -> eyre::Result<bool> {
+        let change_id = self.change_id_db.get_change_id(commit_oid)?;
+        let num_rows = self
+            .conn
+            .execute(
+                "DELETE FROM pinned_changes WHERE change_id = ?",
+                rusqlite::params![change_id.as_str()],
+            )
+            .wrap_err("Unpinning commit")?;
+        Ok(num_rows > 0)
+    }
+
+    /// Determine whether the given commit is pinned.
+    #[instrument]
+    pub fn is_pinned(&self, commit_oid: NonZeroOid) -> eyre::Result<bool> {
+        let change_id = self.change_id_db.get_change_id(commit_oid)?;
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT COUNT(*) FROM pinned_changes WHERE change_id = ?")?;
+        let count: i64 = stmt
+            .query_row(rusqlite::params![change_id.as_str()], |row| row.get(0))
+            .wrap_err("Querying pinned commit")?;
+        Ok(count > 0)
+    }
+
+    /// Get the subset of `commits` which are pinned.
+    #[instrument]
+    pub fn query_pinned_commits(&self, dag: &Dag, commits: &CommitSet) -> eyre::Result<CommitSet> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT COUNT(*) FROM pinned_changes")?;
+        let num_pins: i64 = stmt
+            .query_row(rusqlite::params![], |row| row.get(0))
+            .wrap_err("Counting pinned commits")?;
+        if num_pins == 0 {
+            return Ok(CommitSet::empty());
+        }
+
+        let mut pinned_commits = Vec::new();
+        for commit_oid in dag.commit_set_to_vec(commits)? {
+            if self.is_pinned(commit_oid)? {
+                pinned_commits.push(commit_oid);
+            }
+        }
+        Ok(pinned_commits.into_iter().collect())
+    }
+}
@@ -0,0 +1,270 @@
+//! Reuse recorded resolutions of merge conflicts, like `git rerere`.
+//!
+//! When an on-disk rebase finishes, the contents of each rewritten file which
+//! was changed on both sides of the rebase are recorded, keyed by the ancestor,
+//! "ours", and "theirs" versions of that file. If an in-memory rebase later
+//! runs into a conflict between exactly the same versions of a file (such as
+//! when restacking the same stack again), then the recorded resolution is used
+//! instead of failing the in-memory rebase.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use eyre::Context;
+use tracing::instrument;
+
+use crate::git::{
+    hydrate_tree, CherryPickFastOptions, Commit, CreateCommitFastError, FileMode, MaybeZeroOid,
+    NonZeroOid, Repo, Tree,
+};
+
+/// The version of a file in a tree: its OID and file mode, or `None` if the
+/// file doesn't exist in the tree.
+pub type FileVersion = Option<(NonZeroOid, FileMode)>;
+
+/// Get the version of the file at `path` in `tree`.
+pub fn get_file_version(tree: &Tree, path: &Path) -> eyre::Result<FileVersion> {
+    let entry = tree.get_path(path)?;
+    Ok(entry.map(|entry| (entry.get_oid(), entry.get_filemode())))
+}
+
+fn file_version_oid(file_version: &FileVersion) -> String {
+    let oid: MaybeZeroOid = file_version.map(|(oid, _)| oid).into();
+    oid.to_string()
+}
+
+fn insert_resolution(
+    conn: &rusqlite::Connection,
+    ancestor: &FileVersion,
+    ours: &FileVersion,
+    theirs: &FileVersion,
+    resolved: &FileVersion,
+) -> eyre::Result<()> {
+    let resolved_file_mode: i32 = match resolved {
+        Some((_, file_mode)) => (*file_mode).into(),
+        None => 0,
+    };
+    conn.execute(
+        "
+INSERT OR REPLACE INTO conflict_resolutions
+    (ancestor_oid, our_oid, their_oid, resolved_oid, resolved_file_mode)
+VALUES (?, ?, ?, ?, ?)
+",
+        rusqlite::params![
+            file_version_oid(ancestor),
+            file_version_oid(ours),
+            file_version_oid(theirs),
+            file_version_oid(resolved),
+            resolved_file_mode,
+        ],
+    )
+    .wrap_err("Inserting conflict resolution")?;
+    Ok(())
+}
+
+/// Stores the resolutions of merge conflicts.
+#[derive(Debug)]
+pub struct ResolutionDb {
+    conn: Mutex<rusqlite::Connection>,
+}
+
+#[instrument]
+fn init_tables(conn: &rusqlite::Connection) -> eyre::Result<()> {
+    conn.execute(
+        "
+CREATE TABLE IF NOT EXISTS conflict_resolutions (
+    ancestor_oid TEXT NOT NULL,
+    our_oid TEXT NOT NULL,
+    their_oid TEXT NOT NULL,
+    resolved_oid TEXT NOT NULL,
+    resolved_file_mode INTEGER NOT NULL,
+    PRIMARY KEY (ancestor_oid, our_oid, their_oid)
+)
+",
+        rusqlite::params![],
+    )
+    .wrap_err("Creating `conflict_resolutions` table")?;
+
+    Ok(())
+}
+
+impl ResolutionDb {
+    /// Constructor.
+    #[instrument]
+    pub fn new(conn: rusqlite::Connection) -> eyre::Result<Self> {
+        init_tables(&conn)?;
+        Ok(ResolutionDb {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn get_resolution(
+        &self,
+        ancestor: &FileVersion,
+        ours: &FileVersion,
+        theirs: &FileVersion,
+    ) -> eyre::Result<Option<FileVersion>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare_cached(
+            "
+SELECT resolved_oid, resolved_file_mode
+FROM conflict_resolutions
+WHERE ancestor_oid = ? AND our_oid = ? AND their_oid = ?
+",
+        )?;
+        let mut rows = stmt
+            .query_map(
+                rusqlite::params![
+                    file_version_oid(ancestor),
+                    file_version_oid(ours),
+                    file_version_oid(theirs),
+                ],
+                |row| {
+                    let resolved_oid: String = row.get("resolved_oid")?;
+                    let resolved_file_mode: i32 = row.get("resolved_file_mode")?;
+                    Ok((resolved_oid, resolved_file_mode))
+                },
+            )
+            .wrap_err("Querying conflict resolution")?;
+        let (resolved_oid, resolved_file_mode) = match rows.next().transpose()? {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+        let resolved_oid: MaybeZeroOid = resolved_oid
+            .parse()
+            .wrap_err_with(|| format!("Parsing resolved OID: {resolved_oid}"))?;
+        let resolution = match resolved_oid {
+            MaybeZeroOid::NonZero(resolved_oid) => {
+                Some((resolved_oid, FileMode::from(resolved_file_mode)))
+            }
+            MaybeZeroOid::Zero => None,
+        };
+        Ok(Some(resolution))
+    }
+
+    /// Record how `old_commit_oid` was rebased into `new_commit_oid`. For each
+    /// file changed by the old commit which was also changed between the old
+    /// and new parent commits, the file's contents in the new commit are
+    /// recorded as the resolution of the conflict between those changes.
+    #[instrument]
+    pub fn record_resolutions(
+        &self,
+        repo: &Repo,
+        old_commit_oid: NonZeroOid,
+        new_commit_oid: NonZeroOid,
+    ) -> eyre::Result<()> {
+        let old_commit = repo.find_commit_or_fail(old_commit_oid)?;
+        let new_commit = repo.find_commit_or_fail(new_commit_oid)?;
+        let (old_parent, new_parent) =
+            match (old_commit.get_only_parent(), new_commit.get_only_parent()) {
+                (Some(old_parent), Some(new_parent)) => (old_parent, new_parent),
+                _ => return Ok(()),
+            };
+        if old_parent.get_tree_oid() == new_parent.get_tree_oid() {
+            return Ok(());
+        }
+
+        let ancestor_tree = old_parent.get_tree()?;
+        let our_tree = new_parent.get_tree()?;
+        let their_tree = old_commit.get_tree()?;
+        let resolved_tree = new_commit.get_tree()?;
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+        for path in repo.get_paths_touched_by_commit(&old_commit)? {
+            let ancestor = get_file_version(&ancestor_tree, &path)?;
+            let ours = get_file_version(&our_tree, &path)?;
+            let theirs = get_file_version(&their_tree, &path)?;
+            if ancestor == ours || ancestor == theirs || ours == theirs {
+                // Only one side changed the file, so there was no conflict to
+                // resolve.
+                continue;
+            }
+
+            let resolved = get_file_version(&resolved_tree, &path)?;
+            insert_resolution(&tx, &ancestor, &ours, &theirs, &resolved)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Record `resolved` as the resolution of the conflict between the
+    /// `ancestor`, `ours`, and `theirs` versions of a file.
+    #[instrument]
+    pub fn record_resolution(
+        &self,
+        ancestor: &FileVersion,
+        ours: &FileVersion,
+        theirs: &FileVersion,
+        resolved: &FileVersion,
+    ) -> eyre::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        insert_resolution(&conn, ancestor, ours, theirs, resolved)
+    }
+
+    /// Cherry-pick `patch_commit` onto `target_commit`, using the recorded
+    /// resolutions for `conflicting_paths`. Returns `None` if any of the
+    /// conflicting paths doesn't have a recorded resolution.
+    #[instrument]
+    pub fn cherry_pick_with_resolutions<'repo>(
+        &self,
+        repo: &'repo Repo,
+        patch_commit: &Commit,
+        target_commit: &Commit,
+        conflicting_paths: &HashSet<PathBuf>,
+    ) -> eyre::Result<Option<Tree<'repo>>> {
+        let patch_parent = match patch_commit.get_only_parent() {
+            Some(patch_parent) => patch_parent,
+            None => return Ok(None),
+        };
+        let ancestor_tree = patch_parent.get_tree()?;
+        let our_tree = target_commit.get_tree()?;
+        let their_tree = patch_commit.get_tree()?;
+
+        let mut ancestor_entries: HashMap<PathBuf, FileVersion> = HashMap::new();
+        let mut resolved_entries: HashMap<PathBuf, FileVersion> = HashMap::new();
+        for path in conflicting_paths {
+            let ancestor = get_file_version(&ancestor_tree, path)?;
+            let ours = get_file_version(&our_tree, path)?;
+            let theirs = get_file_version(&their_tree, path)?;
+            match self.get_resolution(&ancestor, &ours, &theirs)? {
+                Some(resolved) => {
+                    ancestor_entries.insert(path.clone(), ancestor);
+                    resolved_entries.insert(path.clone(), resolved);
+                }
+                None => return Ok(None),
+            }
+        }
+
+        // Cherry-pick the rest of the patch by itself, leaving the
+        // conflicting paths as they were in the target commit, and then apply
+        // the resolutions on top.
+        let partial_patch_tree_oid = hydrate_tree(repo, Some(&their_tree), ancestor_entries)?;
+        let partial_patch_tree = repo.find_tree_or_fail(partial_patch_tree_oid)?;
+        let partial_patch_commit_oid = repo.create_commit(
+            None,
+            &patch_commit.get_author(),
+            &patch_commit.get_committer(),
+            "",
+            &partial_patch_tree,
+            vec![&patch_parent],
+        )?;
+        let partial_patch_commit = repo.find_commit_or_fail(partial_patch_commit_oid)?;
+        let partial_tree = match repo.cherry_pick_fast(
+            &partial_patch_commit,
+            target_commit,
+            &CherryPickFastOptions {
+                reuse_parent_tree_if_possible: false,
+            },
+        ) {
+            Ok(partial_tree) => partial_tree,
+            Err(CreateCommitFastError::MergeConflict {
+                conflicting_paths: _,
+            }) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let resolved_tree_oid = hydrate_tree(repo, Some(&partial_tree), resolved_entries)?;
+        let resolved_tree = repo.find_tree_or_fail(resolved_tree_oid)?;
+        Ok(Some(resolved_tree))
+    }
+}
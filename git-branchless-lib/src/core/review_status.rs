@@ -0,0 +1,218 @@
+//! The status of the code reviews for submitted commits.
+//!
+//! `git submit status` queries the forge for the code review associated with
+//! each commit, and caches the result here, keyed by commit OID. The cached
+//! status can then be displayed without contacting the forge, such as in the
+//! smartlog. Since a rewritten commit has a new OID, its cached status is
+//! dropped until the next time the forge is queried.
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use eyre::Context;
+use tracing::instrument;
+
+use crate::git::NonZeroOid;
+
+/// The state of a code review.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReviewState {
+    /// The code review is open and ready for review.
+    Open,
+
+    /// The code review is open, but not yet ready for review.
+    Draft,
+
+    /// The code review was merged or landed.
+    Merged,
+
+    /// The code review was closed or abandoned without being merged.
+    Closed,
+}
+
+impl Display for ReviewState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReviewState::Open => write!(f, "open"),
+            ReviewState::Draft => write!(f, "draft"),
+            ReviewState::Merged => write!(f, "merged"),
+            ReviewState::Closed => write!(f, "closed"),
+        }
+    }
+}
+
+impl FromStr for ReviewState {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "open" => Ok(ReviewState::Open),
+            "draft" => Ok(ReviewState::Draft),
+            "merged" => Ok(ReviewState::Merged),
+            "closed" => Ok(ReviewState::Closed),
+            other => eyre::bail!("Unknown review state: {other:?}"),
+        }
+    }
+}
+
+/// The status of the code review for a commit, as reported by the forge.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReviewStatus {
+    /// The identifier of the code review on the forge, such as `#12` or
+    /// `D123`.
+    pub review_name: String,
+
+    /// The URL of the code review, if any.
+    pub url: Option<String>,
+
+    /// The state of the code review.
+    pub state: ReviewState,
+
+    /// The outcome of the review so far, such as `approved` or `changes
+    /// requested`, if the forge reports it.
+    pub review_decision: Option<String>,
+
+    /// The combined status of the CI checks for the commit, such as `passing`
+    /// or `failing`, if the forge reports it.
+    pub ci_status: Option<String>,
+}
+
+impl ReviewStatus {
+    /// Get a one-line description of this status, such as `#12 (open,
+    /// approved, CI passing)`.
+    pub fn describe(&self) -> String {
+        let Self {
+            review_name,
+            url: _,
+            state,
+            review_decision,
+            ci_status,
+        } = self;
+        let mut details = vec![state.to_string()];
+        details.extend(review_decision.clone());
+        details.extend(
+            ci_status
+                .as_ref()
+                .map(|ci_status| format!("CI {ci_status}")),
+        );
+        format!("{review_name} ({})", details.join(", "))
+    }
+}
+
+#[instrument]
+fn init_tables(conn: &rusqlite::Connection) -> eyre::Result<()> {
+    conn.execute(
+        "
+CREATE TABLE IF NOT EXISTS review_statuses (
+    commit_oid TEXT NOT NULL PRIMARY KEY,
+    review_name TEXT NOT NULL,
+    url TEXT,
+    state TEXT NOT NULL,
+    review_decision TEXT,
+    ci_status TEXT
+)
+",
+        rusqlite::params![],
+    )
+    .wrap_err("Creating `review_statuses` table")?;
+    Ok(())
+}
+
+/// Stores the status of the code review for each commit, as of the last time
+/// it was queried from the forge.
+pub struct ReviewStatusDb<'conn> {
+    conn: &'conn rusqlite::Connection,
+}
+
+impl std::fmt::Debug for ReviewStatusDb<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<ReviewStatusDb>")
+    }
+}
+
+impl<'conn> ReviewStatusDb<'conn> {
+    /// Constructor.
+    #[instrument]
+    pub fn new(conn: &'conn rusqlite::Connection) -> eyre::Result<Self> {
+        init_tables(conn)?;
+        Ok(ReviewStatusDb { conn })
+    }
+
+    /// Get the cached review status of the given commit, if any.
+    #[instrument]
+    pub fn get(&self, commit_oid: NonZeroOid) -> eyre::Result<Option<ReviewStatus>> {
+        let mut stmt = self.conn.prepare_cached(
+            "
+SELECT review_name, url, state, review_decision, ci_status
+FROM review_statuses
+WHERE commit_oid = ?
+",
+        )?;
+        let mut rows = stmt
+            .query_map(rusqlite::params![commit_oid.to_string()], |row| {
+                let review_name: String = row.get("review_name")?;
+                let url: Option<String> = row.get("url")?;
+                let state: String = row.get("state")?;
+                let review_decision: Option<String> = row.get("review_decision")?;
+                let ci_status: Option<String> = row.get("ci_status")?;
+                Ok((review_name, url, state, review_decision, ci_status))
+            })
+            .wrap_err("Querying review status")?;
+        let (review_name, url, state, review_decision, ci_status) = match rows.next() {
+            Some(row) => row?,
+            None => return Ok(None),
+        };
+        Ok(Some(ReviewStatus {
+            review_name,
+            url,
+            state: state.parse()?,
+            review_decision,
+            ci_status,
+        }))
+    }
+
+    /// Cache the review status of the given commit, or clear it if `None`.
+    #[instrument]
+    pub fn set(
+        &self,
+        commit_oid: NonZeroOid,
+        review_status: Option<&ReviewStatus>,
+    ) -> eyre::Result<()> {
+        match review_status {
+            Some(ReviewStatus {
+                review_name,
+                url,
+                state,
+                review_decision,
+                ci_status,
+            }) => {
+                self.conn
+                    .execute(
+                        "
+INSERT OR REPLACE INTO review_statuses
+    (commit_oid, review_name, url, state, review_decision, ci_status)
+VALUES (?, ?, ?, ?, ?, ?)
+",
+                        rusqlite::params![
+                            commit_oid.to_string(),
+                            review_name,
+                            url,
+                            state.to_string(),
+                            review_decision,
+                            ci_status,
+                        ],
+                    )
+                    .wrap_err("Inserting review status")?;
+            }
+            None => {
+                self.conn
+                    .execute(
+                        "DELETE FROM review_statuses WHERE commit_oid = ?",
+                        rusqlite::params![commit_oid.to_string()],
+                    )
+                    .wrap_err("Deleting review status")?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,12 +1,13 @@
 use std::collections::{HashMap, HashSet};
 
 use std::fmt::Write;
+use std::io::IsTerminal;
 use std::path::PathBuf;
 use std::time::SystemTime;
 
 use bstr::BString;
 use eyre::Context;
-use tracing::warn;
+use tracing::{instrument, warn};
 
 use crate::core::check_out::{check_out_commit, CheckOutCommitOptions, CheckoutTarget};
 use crate::core::effects::Effects;
@@ -17,9 +18,11 @@ use crate::git::{
     BranchType, CategorizedReferenceName, GitRunInfo, MaybeZeroOid, NonZeroOid, ReferenceName,
     Repo, ResolvedReferenceInfo,
 };
+use crate::try_exit_code;
 use crate::util::{ExitCode, EyreExitOr};
 
 use super::plan::RebasePlan;
+use super::saved_plan::{SavedRebasePlan, SavedRebasePlanDb};
 
 /// Given a list of rewritten OIDs, move the branches attached to those OIDs
 /// from their old commits to their new commits. Invoke the
@@ -337,6 +340,11 @@ pub enum FailedMergeInfo {
         /// The OID of the commit that, when moved, caused a conflict.
         commit_oid: NonZeroOid,
 
+        /// The OID of the commit that it was being applied onto. This may be a
+        /// commit which was created during the rebase, and which isn't
+        /// referenced anywhere.
+        onto_oid: NonZeroOid,
+
         /// The paths which were in conflict.
         conflicting_paths: HashSet<PathBuf>,
     },
@@ -360,6 +368,7 @@ impl FailedMergeInfo {
         match self {
             FailedMergeInfo::Conflict {
                 commit_oid,
+                onto_oid: _,
                 conflicting_paths,
             } => {
                 writeln!(
@@ -425,23 +434,27 @@ mod in_memory {
     use std::collections::HashMap;
     use std::fmt::Write;
 
-    use bstr::{BString, ByteSlice};
+    use bstr::{BString, ByteSlice, ByteVec};
     use eyre::Context;
+    use rayon::{prelude::*, ThreadPoolBuilder};
     use tracing::{instrument, warn};
 
-    use crate::core::effects::{Effects, OperationIcon, OperationType};
+    use crate::core::config::{get_restack_preserve_author_dates, get_restack_reuse_resolutions};
+    use crate::core::effects::{Effects, OperationIcon, OperationType, ProgressHandle};
     use crate::core::eventlog::EventLogDb;
+    use crate::core::formatting::Pluralize;
     use crate::core::gc::mark_commit_reachable;
+    use crate::core::rerere::ResolutionDb;
     use crate::core::rewrite::execute::check_out_updated_head;
-    use crate::core::rewrite::move_branches;
     use crate::core::rewrite::plan::{OidOrLabel, RebaseCommand, RebasePlan};
+    use crate::core::rewrite::{move_branches, RepoResource};
     use crate::git::{
-        AmendFastOptions, CherryPickFastOptions, CreateCommitFastError, GitRunInfo, MaybeZeroOid,
-        NonZeroOid, Repo,
+        AmendFastOptions, CherryPickFastOptions, Commit, CreateCommitFastError, GitRunInfo,
+        MaybeZeroOid, NonZeroOid, Repo, Signature, Signer,
     };
     use crate::util::EyreExitOr;
 
-    use super::{ExecuteRebasePlanOptions, FailedMergeInfo};
+    use super::{EmptyCommitsMode, ExecuteRebasePlanOptions, FailedMergeInfo};
 
     pub enum RebaseInMemoryResult {
         Succeeded {
@@ -453,92 +466,343 @@ mod in_memory {
             /// - This doesn't capture if `HEAD` was pointing to a branch. The
             ///   caller will need to figure that out.
             new_head_oid: Option<NonZeroOid>,
+
+            /// The commits which were dropped because they became empty as a
+            /// result of the rebase, in the order of the rebase plan.
+            emptied_commit_oids: Vec<NonZeroOid>,
+
+            /// The commits which were skipped because they were already
+            /// applied upstream, in the order of the rebase plan.
+            upstream_commit_oids: Vec<NonZeroOid>,
         },
         MergeFailed(FailedMergeInfo),
     }
 
-    #[instrument]
-    pub fn rebase_in_memory(
-        effects: &Effects,
+    /// Get the message to use for `original_commit` after applying
+    /// `commits_to_apply_oids` to it. As with `git rebase --autosquash`, the
+    /// bodies of any `squash!` commits are appended to the original message,
+    /// while the messages of other fixup commits are discarded.
+    fn squash_commit_messages(
         repo: &Repo,
-        rebase_plan: &RebasePlan,
-        options: &ExecuteRebasePlanOptions,
-    ) -> eyre::Result<RebaseInMemoryResult> {
-        if let Some(merge_commit_oid) =
-            rebase_plan
+        original_commit: &Commit,
+        commits_to_apply_oids: &[NonZeroOid],
+    ) -> eyre::Result<BString> {
+        let mut message = original_commit.get_message_raw();
+        for commit_oid in commits_to_apply_oids {
+            if *commit_oid == original_commit.get_oid() {
+                continue;
+            }
+
+            let commit = repo.find_commit_or_fail(*commit_oid)?;
+            let squash_message = commit.get_message_pretty();
+            if !squash_message.starts_with(b"squash! ") {
+                continue;
+            }
+            let body = match squash_message.find_byte(b'\n') {
+                Some(index) => squash_message[index..].trim(),
+                None => continue,
+            };
+            if body.is_empty() {
+                continue;
+            }
+
+            let mut new_message = BString::from(message.trim_end());
+            new_message.push_str("\n\n");
+            new_message.push_str(body);
+            new_message.push_str("\n");
+            message = new_message;
+        }
+        Ok(message)
+    }
+
+    /// Information about the rebase plan which is shared by all groups of
+    /// commands being applied.
+    struct RebaseContext<'a> {
+        rebase_plan: &'a RebasePlan,
+        options: &'a ExecuteRebasePlanOptions,
+
+        /// The OID of `HEAD` before the rebase, or `None` if `HEAD` is unborn.
+        head_oid: Option<NonZeroOid>,
+
+        /// For each command in the rebase plan, the number of commits which
+        /// will have been applied once that command has been applied.
+        commit_nums: Vec<usize>,
+
+        /// The total number of commits to apply.
+        num_picks: usize,
+
+        /// If set, recorded resolutions are used to resolve merge conflicts.
+        resolution_db: Option<ResolutionDb>,
+
+        /// If set, rebased commits are signed, as configured by
+        /// `commit.gpgSign`.
+        signer: Option<Signer>,
+
+        /// Whether rebased commits keep their original author timestamps, as
+        /// configured by `branchless.restack.preserveAuthorDates`.
+        preserve_author_dates: bool,
+    }
+
+    impl<'a> RebaseContext<'a> {
+        fn new(
+            rebase_plan: &'a RebasePlan,
+            options: &'a ExecuteRebasePlanOptions,
+            head_oid: Option<NonZeroOid>,
+            resolution_db: Option<ResolutionDb>,
+            signer: Option<Signer>,
+            preserve_author_dates: bool,
+        ) -> Self {
+            let mut num_picks = 0;
+            let commit_nums = rebase_plan
                 .commands
                 .iter()
-                .find_map(|command| match command {
+                .map(|command| {
+                    match command {
+                        RebaseCommand::CreateLabel { .. }
+                        | RebaseCommand::Reset { .. }
+                        | RebaseCommand::Break
+                        | RebaseCommand::RegisterExtraPostRewriteHook
+                        | RebaseCommand::DetectEmptyCommit { .. } => {}
+                        RebaseCommand::Pick { .. }
+                        | RebaseCommand::Merge { .. }
+                        | RebaseCommand::Replace { .. }
+                        | RebaseCommand::SkipUpstreamAppliedCommit { .. } => num_picks += 1,
+                    }
+                    num_picks
+                })
+                .collect();
+            Self {
+                rebase_plan,
+                options,
+                head_oid,
+                commit_nums,
+                num_picks,
+                resolution_db,
+                signer,
+                preserve_author_dates,
+            }
+        }
+
+        /// Get the author and committer to use for the rebased version of
+        /// `original_commit`, which is the commit with OID `commit_oid` in the
+        /// rebase plan (or its replacement).
+        fn get_rebased_signatures<'c>(
+            &self,
+            repo: &Repo,
+            commit_oid: NonZeroOid,
+            original_commit: &'c Commit,
+        ) -> eyre::Result<(Signature<'c>, Signature<'c>)> {
+            let ExecuteRebasePlanOptions {
+                now,
+                preserve_timestamps,
+                reset_author,
+                committer_as_author,
+                signature_commit_oids,
+                ..
+            } = self.options;
+            let (reset_author, committer_as_author) = match signature_commit_oids {
+                Some(signature_commit_oids) if !signature_commit_oids.contains(&commit_oid) => {
+                    (&false, &false)
+                }
+                Some(_) | None => (reset_author, committer_as_author),
+            };
+            let author = if *reset_author {
+                repo.get_current_user_signature()?.update_timestamp(*now)?
+            } else if self.preserve_author_dates {
+                original_commit.get_author()
+            } else {
+                original_commit.get_author().update_timestamp(*now)?
+            };
+            let committer = if *committer_as_author {
+                author.to_owned()
+            } else if *preserve_timestamps {
+                original_commit.get_committer()
+            } else {
+                original_commit.get_committer().update_timestamp(*now)?
+            };
+            Ok((author, committer))
+        }
+
+        fn describe_commit_num(&self, index: usize) -> String {
+            format!("[{}/{}]", self.commit_nums[index], self.num_picks)
+        }
+    }
+
+    /// The result of applying a group of commands from the rebase plan.
+    #[derive(Default)]
+    struct AppliedCommandGroup {
+        rewritten_oids: HashMap<NonZeroOid, MaybeZeroOid>,
+
+        /// If `HEAD` pointed to a commit which was skipped, then the OID that
+        /// `HEAD` should point to instead.
+        skipped_head_new_oid: Option<NonZeroOid>,
+
+        /// The messages to print for the applied commands, along with the
+        /// index of the corresponding command in the rebase plan.
+        messages: Vec<(usize, String)>,
+
+        /// The commits which were dropped because they became empty, along
+        /// with the index of the corresponding command in the rebase plan.
+        emptied_commit_oids: Vec<(usize, NonZeroOid)>,
+
+        /// The commits which were skipped because they were already applied
+        /// upstream, along with the index of the corresponding command in the
+        /// rebase plan.
+        upstream_commit_oids: Vec<(usize, NonZeroOid)>,
+
+        /// If applying a command failed, then the index of that command in the
+        /// rebase plan and the reason it failed. No further commands in the
+        /// group are applied in that case.
+        merge_failure: Option<(usize, FailedMergeInfo)>,
+    }
+
+    /// Partition the commands in the rebase plan into groups which don't
+    /// depend on each other, and which can therefore be applied concurrently.
+    /// Each group is a list of indexes into the rebase plan's commands, in
+    /// order.
+    ///
+    /// The commands are first split into segments at each `reset` to a
+    /// specific commit, since the commands after such a `reset` don't depend
+    /// on where the previous commands left off. Segments are then merged back
+    /// together if one of them refers to a label created or a commit rewritten
+    /// by the other.
+    fn find_independent_command_groups(commands: &[RebaseCommand]) -> Vec<Vec<usize>> {
+        let mut segments: Vec<Vec<usize>> = Vec::new();
+        for (index, command) in commands.iter().enumerate() {
+            let starts_segment = matches!(
+                command,
+                RebaseCommand::Reset {
+                    target: OidOrLabel::Oid(_)
+                }
+            );
+            match segments.last_mut() {
+                Some(segment) if !starts_segment => segment.push(index),
+                _ => segments.push(vec![index]),
+            }
+        }
+
+        let mut label_segments: HashMap<&str, usize> = HashMap::new();
+        let mut rewritten_oid_segments: HashMap<NonZeroOid, usize> = HashMap::new();
+        for (segment_index, segment) in segments.iter().enumerate() {
+            for index in segment {
+                match &commands[*index] {
+                    RebaseCommand::CreateLabel { label_name } => {
+                        label_segments.insert(label_name.as_str(), segment_index);
+                    }
+                    RebaseCommand::Pick {
+                        original_commit_oid,
+                        commits_to_apply_oids,
+                    } => {
+                        rewritten_oid_segments.insert(*original_commit_oid, segment_index);
+                        for commit_oid in commits_to_apply_oids {
+                            rewritten_oid_segments.insert(*commit_oid, segment_index);
+                        }
+                    }
+                    RebaseCommand::Merge { commit_oid, .. }
+                    | RebaseCommand::Replace { commit_oid, .. }
+                    | RebaseCommand::SkipUpstreamAppliedCommit { commit_oid } => {
+                        rewritten_oid_segments.insert(*commit_oid, segment_index);
+                    }
+                    RebaseCommand::Reset { .. }
+                    | RebaseCommand::Break
+                    | RebaseCommand::RegisterExtraPostRewriteHook
+                    | RebaseCommand::DetectEmptyCommit { .. } => {}
+                }
+            }
+        }
+
+        // Each segment points to the earlier segment that it was merged into,
+        // or to itself if it hasn't been merged.
+        fn find_root(merged_into: &[usize], mut segment_index: usize) -> usize {
+            while merged_into[segment_index] != segment_index {
+                segment_index = merged_into[segment_index];
+            }
+            segment_index
+        }
+        let mut merged_into: Vec<usize> = (0..segments.len()).collect();
+        for (segment_index, segment) in segments.iter().enumerate() {
+            for index in segment {
+                let targets: Vec<&OidOrLabel> = match &commands[*index] {
+                    RebaseCommand::Reset { target } => vec![target],
                     RebaseCommand::Merge {
-                        commit_oid,
-                        commits_to_merge: _,
-                    } => Some(commit_oid),
+                        commit_oid: _,
+                        commits_to_merge,
+                    } => commits_to_merge.iter().collect(),
+                    RebaseCommand::Replace {
+                        commit_oid: _,
+                        replacement_commit_oid: _,
+                        parents,
+                    } => parents.iter().collect(),
                     RebaseCommand::CreateLabel { .. }
-                    | RebaseCommand::Reset { .. }
                     | RebaseCommand::Pick { .. }
-                    | RebaseCommand::Replace { .. }
                     | RebaseCommand::Break
                     | RebaseCommand::RegisterExtraPostRewriteHook
                     | RebaseCommand::DetectEmptyCommit { .. }
-                    | RebaseCommand::SkipUpstreamAppliedCommit { .. } => None,
-                })
-        {
-            return Ok(RebaseInMemoryResult::MergeFailed(
-                FailedMergeInfo::CannotRebaseMergeInMemory {
-                    commit_oid: *merge_commit_oid,
-                },
-            ));
+                    | RebaseCommand::SkipUpstreamAppliedCommit { .. } => Vec::new(),
+                };
+                for target in targets {
+                    let dependency_index = match target {
+                        OidOrLabel::Oid(oid) => rewritten_oid_segments.get(oid),
+                        OidOrLabel::Label(label_name) => label_segments.get(label_name.as_str()),
+                    };
+                    if let Some(dependency_index) = dependency_index {
+                        let lhs = find_root(&merged_into, segment_index);
+                        let rhs = find_root(&merged_into, *dependency_index);
+                        merged_into[lhs.max(rhs)] = lhs.min(rhs);
+                    }
+                }
+            }
+        }
+
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        let mut root_groups: HashMap<usize, usize> = HashMap::new();
+        for (segment_index, segment) in segments.into_iter().enumerate() {
+            let root = find_root(&merged_into, segment_index);
+            let group_index = *root_groups.entry(root).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+            groups[group_index].extend(segment);
         }
+        groups
+    }
 
+    /// Apply the commands at the given indexes of the rebase plan, in order.
+    /// Commits are only created in `repo`; no references are updated.
+    fn apply_command_group(
+        effects: &Effects,
+        progress: &ProgressHandle,
+        repo: &Repo,
+        context: &RebaseContext,
+        group: &[usize],
+    ) -> eyre::Result<AppliedCommandGroup> {
         let ExecuteRebasePlanOptions {
-            now,
+            // Timestamps and signatures are handled by
+            // `RebaseContext::get_rebased_signatures`.
+            now: _,
             // Transaction ID will be passed to the `post-rewrite` hook via
             // environment variable.
             event_tx_id: _,
-            preserve_timestamps,
+            preserve_timestamps: _,
             force_in_memory: _,
             force_on_disk: _,
             resolve_merge_conflicts: _, // May be needed once we can resolve merge conflicts in memory.
             check_out_commit_options: _, // Caller is responsible for checking out to new HEAD.
-        } = options;
+            reset_author: _,
+            committer_as_author: _,
+            signature_commit_oids: _,
+            empty_commits,
+        } = context.options;
 
-        let mut current_oid = rebase_plan.first_dest_oid;
+        let mut current_oid = context.rebase_plan.first_dest_oid;
         let mut labels: HashMap<String, NonZeroOid> = HashMap::new();
-        let mut rewritten_oids: HashMap<NonZeroOid, MaybeZeroOid> = HashMap::new();
-
-        // Normally, we can determine the new `HEAD` OID by looking at the
-        // rewritten commits. However, if `HEAD` pointed to a commit that was
-        // skipped, then the rewritten OID is zero. In that case, we need to
-        // delete the branch (responsibility of the caller) and choose a
-        // different `HEAD` OID.
-        let head_oid = repo.get_head_info()?.oid;
-        let mut skipped_head_new_oid = None;
-        let mut maybe_set_skipped_head_new_oid = |skipped_head_oid, current_oid| {
-            if Some(skipped_head_oid) == head_oid {
-                skipped_head_new_oid.get_or_insert(current_oid);
-            }
-        };
-
-        let mut i = 0;
-        let num_picks = rebase_plan
-            .commands
-            .iter()
-            .filter(|command| match command {
-                RebaseCommand::CreateLabel { .. }
-                | RebaseCommand::Reset { .. }
-                | RebaseCommand::Break
-                | RebaseCommand::RegisterExtraPostRewriteHook
-                | RebaseCommand::DetectEmptyCommit { .. } => false,
-                RebaseCommand::Pick { .. }
-                | RebaseCommand::Merge { .. }
-                | RebaseCommand::Replace { .. }
-                | RebaseCommand::SkipUpstreamAppliedCommit { .. } => true,
-            })
-            .count();
-        let (effects, progress) = effects.start_operation(OperationType::RebaseCommits);
-
-        for command in rebase_plan.commands.iter() {
-            match command {
+        // For each commit which was dropped (because it became empty or was
+        // already applied upstream), the commit that its descendants should be
+        // applied onto instead, i.e. its nearest ancestor which wasn't dropped.
+        let mut dropped_commit_onto_oids: HashMap<NonZeroOid, NonZeroOid> = HashMap::new();
+        let mut result = AppliedCommandGroup::default();
+        for index in group.iter().copied() {
+            match &context.rebase_plan.commands[index] {
                 RebaseCommand::CreateLabel { label_name } => {
                     labels.insert(label_name.clone(), current_oid);
                 }
@@ -555,15 +819,23 @@ mod in_memory {
                 RebaseCommand::Reset {
                     target: OidOrLabel::Oid(commit_oid),
                 } => {
-                    current_oid = match rewritten_oids.get(commit_oid) {
+                    current_oid = match result.rewritten_oids.get(commit_oid) {
                         Some(MaybeZeroOid::NonZero(rewritten_oid)) => {
                             // HEAD has been rewritten.
                             *rewritten_oid
                         }
-                        Some(MaybeZeroOid::Zero) | None => {
-                            // Either HEAD was not rewritten, or it was but its
-                            // associated commit was skipped. Either way, just
-                            // use the current OID.
+                        Some(MaybeZeroOid::Zero) => {
+                            // HEAD was rewritten, but its associated commit was
+                            // skipped, so use the commit that it would have
+                            // been applied onto.
+                            match dropped_commit_onto_oids.get(commit_oid) {
+                                Some(onto_oid) => *onto_oid,
+                                None => *commit_oid,
+                            }
+                        }
+                        None => {
+                            // HEAD was not rewritten, so just use the current
+                            // OID.
                             *commit_oid
                         }
                     };
@@ -580,12 +852,11 @@ mod in_memory {
                     let original_commit = repo
                         .find_commit_or_fail(*original_commit_oid)
                         .wrap_err("Finding commit to apply")?;
-                    i += 1;
+                    let commit_num = context.describe_commit_num(index);
+                    progress.notify_progress_inc(1);
 
-                    let commit_num = format!("[{i}/{num_picks}]");
-                    progress.notify_progress(i, num_picks);
-
-                    let commit_message = original_commit.get_message_raw();
+                    let commit_message =
+                        squash_commit_messages(repo, &original_commit, commits_to_apply_oids)?;
                     let commit_message = commit_message.to_str().with_context(|| {
                         eyre::eyre!(
                             "Could not decode commit message for commit: {:?}",
@@ -593,12 +864,11 @@ mod in_memory {
                         )
                     })?;
 
-                    let commit_author = original_commit.get_author();
-                    let committer_signature = if *preserve_timestamps {
-                        original_commit.get_committer()
-                    } else {
-                        original_commit.get_committer().update_timestamp(*now)?
-                    };
+                    let (commit_author, committer_signature) = context.get_rebased_signatures(
+                        repo,
+                        *original_commit_oid,
+                        &original_commit,
+                    )?;
                     let mut rebased_commit_oid = None;
                     let mut rebased_commit = None;
 
@@ -615,11 +885,13 @@ mod in_memory {
                                 ?commit_oid,
                                 "BUG: Merge commit should have been detected during planning phase"
                             );
-                            return Ok(RebaseInMemoryResult::MergeFailed(
+                            result.merge_failure = Some((
+                                index,
                                 FailedMergeInfo::CannotRebaseMergeInMemory {
                                     commit_oid: *commit_oid,
                                 },
                             ));
+                            return Ok(result);
                         };
 
                         progress.notify_status(
@@ -632,31 +904,66 @@ mod in_memory {
                         // Is it even possible to repeatedly amend a tree and then commit
                         // it once at the end?
 
-                        let maybe_tree = if rebased_commit.is_none() {
-                            repo.cherry_pick_fast(
+                        let maybe_tree = match &rebased_commit {
+                            None => match repo.cherry_pick_fast(
                                 &commit_to_apply,
                                 &current_commit,
                                 &CherryPickFastOptions {
                                     reuse_parent_tree_if_possible: true,
                                 },
-                            )
-                        } else {
-                            repo.amend_fast(
-                                &rebased_commit.expect("rebased commit should not be None"),
+                            ) {
+                                Err(CreateCommitFastError::MergeConflict { conflicting_paths }) => {
+                                    let resolved_tree = match &context.resolution_db {
+                                        Some(resolution_db) => resolution_db
+                                            .cherry_pick_with_resolutions(
+                                                repo,
+                                                &commit_to_apply,
+                                                &current_commit,
+                                                &conflicting_paths,
+                                            )?,
+                                        None => None,
+                                    };
+                                    match resolved_tree {
+                                        Some(resolved_tree) => {
+                                            result.messages.push((
+                                                index,
+                                                format!(
+                                                    "{commit_num} Reused recorded resolution for {}: {commit_description}",
+                                                    Pluralize {
+                                                        determiner: None,
+                                                        amount: conflicting_paths.len(),
+                                                        unit: ("conflicting file", "conflicting files"),
+                                                    }
+                                                ),
+                                            ));
+                                            Ok(resolved_tree)
+                                        }
+                                        None => Err(CreateCommitFastError::MergeConflict {
+                                            conflicting_paths,
+                                        }),
+                                    }
+                                }
+                                result => result,
+                            },
+                            Some(rebased_commit) => repo.amend_fast(
+                                rebased_commit,
                                 &AmendFastOptions::FromCommit {
                                     commit: commit_to_apply,
                                 },
-                            )
+                            ),
                         };
                         let commit_tree = match maybe_tree {
                             Ok(tree) => tree,
                             Err(CreateCommitFastError::MergeConflict { conflicting_paths }) => {
-                                return Ok(RebaseInMemoryResult::MergeFailed(
+                                result.merge_failure = Some((
+                                    index,
                                     FailedMergeInfo::Conflict {
                                         commit_oid: *commit_oid,
+                                        onto_oid: current_oid,
                                         conflicting_paths,
                                     },
-                                ))
+                                ));
+                                return Ok(result);
                             }
                             Err(other) => eyre::bail!(other),
                         };
@@ -669,13 +976,13 @@ mod in_memory {
                             format!("Committing to repository: {commit_description}"),
                         );
                         rebased_commit_oid = Some(
-                            repo.create_commit(
-                                None,
+                            repo.create_commit_with_signer(
                                 &commit_author,
                                 &committer_signature,
                                 commit_message,
                                 &commit_tree,
                                 vec![&current_commit],
+                                context.signer.as_ref(),
                             )
                             .wrap_err("Applying rebased commit")?,
                         );
@@ -693,48 +1000,160 @@ mod in_memory {
                                 rebased_commit_oid,
                             )?)?;
 
-                    if rebased_commit
-                        .expect("rebased commit should not be None")
-                        .is_empty()
+                    // Commits which were already empty before being rebased
+                    // were presumably created intentionally (for example, as
+                    // markers in a stack), so only drop commits which became
+                    // empty as a result of the rebase.
+                    let mut was_originally_empty = true;
+                    for commit_oid in commits_to_apply_oids.iter() {
+                        if !repo.find_commit_or_fail(*commit_oid)?.is_empty() {
+                            was_originally_empty = false;
+                            break;
+                        }
+                    }
+                    if !was_originally_empty
+                        && *empty_commits != EmptyCommitsMode::Keep
+                        && rebased_commit
+                            .expect("rebased commit should not be None")
+                            .is_empty()
                     {
-                        rewritten_oids.insert(*original_commit_oid, MaybeZeroOid::Zero);
-                        maybe_set_skipped_head_new_oid(*original_commit_oid, current_oid);
+                        result
+                            .rewritten_oids
+                            .insert(*original_commit_oid, MaybeZeroOid::Zero);
+                        dropped_commit_onto_oids.insert(*original_commit_oid, current_oid);
+                        if Some(*original_commit_oid) == context.head_oid {
+                            result.skipped_head_new_oid.get_or_insert(current_oid);
+                        }
 
-                        writeln!(
-                            effects.get_output_stream(),
-                            "{commit_num} Skipped now-empty commit: {commit_description}",
-                        )?;
+                        result
+                            .emptied_commit_oids
+                            .push((index, *original_commit_oid));
+                        result.messages.push((
+                            index,
+                            format!("{commit_num} Skipped now-empty commit: {commit_description}"),
+                        ));
                     } else {
-                        rewritten_oids.insert(
+                        result.rewritten_oids.insert(
                             *original_commit_oid,
                             MaybeZeroOid::NonZero(rebased_commit_oid),
                         );
                         for commit_oid in commits_to_apply_oids {
-                            rewritten_oids
+                            result
+                                .rewritten_oids
                                 .insert(*commit_oid, MaybeZeroOid::NonZero(rebased_commit_oid));
                         }
 
                         current_oid = rebased_commit_oid;
 
-                        writeln!(
-                            effects.get_output_stream(),
-                            "{commit_num} Committed as: {commit_description}"
-                        )?;
+                        result.messages.push((
+                            index,
+                            format!("{commit_num} Committed as: {commit_description}"),
+                        ));
                     }
                 }
 
                 RebaseCommand::Merge {
                     commit_oid,
-                    commits_to_merge: _,
+                    commits_to_merge,
                 } => {
-                    warn!(
-                        ?commit_oid,
-                        "BUG: Merge commit without replacement should have been detected when starting in-memory rebase"
+                    let original_commit = repo
+                        .find_commit_or_fail(*commit_oid)
+                        .wrap_err("Finding merge commit")?;
+                    let original_commit_description = effects
+                        .get_glyphs()
+                        .render(original_commit.friendly_describe(effects.get_glyphs())?)?;
+
+                    let commit_num = context.describe_commit_num(index);
+                    progress.notify_progress_inc(1);
+                    progress.notify_status(
+                        OperationIcon::InProgress,
+                        format!("Applying merge commit: {original_commit_description}"),
+                    );
+
+                    // The first parent is the commit that the preceding
+                    // `Reset` command moved to.
+                    let mut parent_oids = vec![current_oid];
+                    for commit_to_merge in commits_to_merge {
+                        let parent_oid = match commit_to_merge {
+                            OidOrLabel::Label(label_name) => match labels.get(label_name) {
+                                Some(oid) => *oid,
+                                None => {
+                                    eyre::bail!("BUG: no associated OID for label: {label_name}")
+                                }
+                            },
+                            OidOrLabel::Oid(oid) => match result.rewritten_oids.get(oid) {
+                                Some(MaybeZeroOid::NonZero(rewritten_oid)) => *rewritten_oid,
+                                Some(MaybeZeroOid::Zero) => {
+                                    match dropped_commit_onto_oids.get(oid) {
+                                        Some(onto_oid) => *onto_oid,
+                                        None => *oid,
+                                    }
+                                }
+                                None => *oid,
+                            },
+                        };
+                        parent_oids.push(parent_oid);
+                    }
+
+                    let commit_tree = match repo.rebase_merge_fast(&original_commit, &parent_oids) {
+                        Ok(tree) => tree,
+                        Err(CreateCommitFastError::MergeConflict { conflicting_paths }) => {
+                            result.merge_failure = Some((
+                                index,
+                                FailedMergeInfo::Conflict {
+                                    commit_oid: *commit_oid,
+                                    onto_oid: current_oid,
+                                    conflicting_paths,
+                                },
+                            ));
+                            return Ok(result);
+                        }
+                        Err(other) => eyre::bail!(other),
+                    };
+
+                    let commit_message = original_commit.get_message_raw();
+                    let commit_message = commit_message.to_str().with_context(|| {
+                        eyre::eyre!(
+                            "Could not decode commit message for commit: {:?}",
+                            commit_oid
+                        )
+                    })?;
+                    let (commit_author, committer_signature) =
+                        context.get_rebased_signatures(repo, *commit_oid, &original_commit)?;
+                    let parents = parent_oids
+                        .into_iter()
+                        .map(|parent_oid| repo.find_commit_or_fail(parent_oid))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    progress.notify_status(
+                        OperationIcon::InProgress,
+                        format!("Committing to repository: {original_commit_description}"),
                     );
-                    return Ok(RebaseInMemoryResult::MergeFailed(
-                        FailedMergeInfo::CannotRebaseMergeInMemory {
-                            commit_oid: *commit_oid,
-                        },
+                    let rebased_commit_oid = repo
+                        .create_commit_with_signer(
+                            &commit_author,
+                            &committer_signature,
+                            commit_message,
+                            &commit_tree,
+                            parents.iter().collect(),
+                            context.signer.as_ref(),
+                        )
+                        .wrap_err("Applying rebased merge commit")?;
+
+                    let commit_description =
+                        effects
+                            .get_glyphs()
+                            .render(repo.friendly_describe_commit_from_oid(
+                                effects.get_glyphs(),
+                                rebased_commit_oid,
+                            )?)?;
+                    result
+                        .rewritten_oids
+                        .insert(*commit_oid, MaybeZeroOid::NonZero(rebased_commit_oid));
+                    current_oid = rebased_commit_oid;
+
+                    result.messages.push((
+                        index,
+                        format!("{commit_num} Committed as: {commit_description}"),
                     ));
                 }
 
@@ -750,9 +1169,8 @@ mod in_memory {
                         .get_glyphs()
                         .render(original_commit.friendly_describe(effects.get_glyphs())?)?;
 
-                    i += 1;
-                    let commit_num = format!("[{i}/{num_picks}]");
-                    progress.notify_progress(i, num_picks);
+                    let commit_num = context.describe_commit_num(index);
+                    progress.notify_progress_inc(1);
                     progress.notify_status(
                         OperationIcon::InProgress,
                         format!("Replacing commit: {original_commit_description}"),
@@ -776,11 +1194,8 @@ mod in_memory {
                         OperationIcon::InProgress,
                         format!("Committing to repository: {replacement_commit_description}"),
                     );
-                    let committer_signature = if *preserve_timestamps {
-                        replacement_commit.get_committer()
-                    } else {
-                        replacement_commit.get_committer().update_timestamp(*now)?
-                    };
+                    let (commit_author, committer_signature) =
+                        context.get_rebased_signatures(repo, *commit_oid, &replacement_commit)?;
                     let parents = {
                         let mut result = Vec::new();
                         for parent in parents {
@@ -801,13 +1216,13 @@ mod in_memory {
                         result
                     };
                     let rebased_commit_oid = repo
-                        .create_commit(
-                            None,
-                            &replacement_commit.get_author(),
+                        .create_commit_with_signer(
+                            &commit_author,
                             &committer_signature,
                             replacement_commit_message,
                             &replacement_tree,
                             parents.iter().collect(),
+                            context.signer.as_ref(),
                         )
                         .wrap_err("Applying rebased commit")?;
 
@@ -818,13 +1233,15 @@ mod in_memory {
                                 effects.get_glyphs(),
                                 rebased_commit_oid,
                             )?)?;
-                    rewritten_oids.insert(*commit_oid, MaybeZeroOid::NonZero(rebased_commit_oid));
+                    result
+                        .rewritten_oids
+                        .insert(*commit_oid, MaybeZeroOid::NonZero(rebased_commit_oid));
                     current_oid = rebased_commit_oid;
 
-                    writeln!(
-                        effects.get_output_stream(),
-                        "{commit_num} Committed as: {commit_description}"
-                    )?;
+                    result.messages.push((
+                        index,
+                        format!("{commit_num} Committed as: {commit_description}"),
+                    ));
                 }
 
                 RebaseCommand::Break => {
@@ -832,19 +1249,24 @@ mod in_memory {
                 }
 
                 RebaseCommand::SkipUpstreamAppliedCommit { commit_oid } => {
-                    i += 1;
-                    let commit_num = format!("[{i}/{num_picks}]");
+                    let commit_num = context.describe_commit_num(index);
 
                     let commit = repo.find_commit_or_fail(*commit_oid)?;
-                    rewritten_oids.insert(*commit_oid, MaybeZeroOid::Zero);
-                    maybe_set_skipped_head_new_oid(*commit_oid, current_oid);
+                    result
+                        .rewritten_oids
+                        .insert(*commit_oid, MaybeZeroOid::Zero);
+                    dropped_commit_onto_oids.insert(*commit_oid, current_oid);
+                    if Some(*commit_oid) == context.head_oid {
+                        result.skipped_head_new_oid.get_or_insert(current_oid);
+                    }
 
                     let commit_description = commit.friendly_describe(effects.get_glyphs())?;
                     let commit_description = effects.get_glyphs().render(commit_description)?;
-                    writeln!(
-                        effects.get_output_stream(),
-                        "{commit_num} Skipped commit (was already applied upstream): {commit_description}"
-                    )?;
+                    result.upstream_commit_oids.push((index, *commit_oid));
+                    result.messages.push((
+                        index,
+                        format!("{commit_num} Skipped commit (was already applied upstream): {commit_description}"),
+                    ));
                 }
 
                 RebaseCommand::RegisterExtraPostRewriteHook
@@ -855,6 +1277,161 @@ mod in_memory {
             }
         }
 
+        Ok(result)
+    }
+
+    /// Rebase the commits in the rebase plan in memory. Only new commits are
+    /// created; no references are updated and the working copy isn't touched.
+    ///
+    /// Groups of commands which don't depend on each other (such as the
+    /// commands to rebase independent subtrees of commits) are applied
+    /// concurrently.
+    #[instrument]
+    pub fn rebase_in_memory(
+        effects: &Effects,
+        repo: &Repo,
+        rebase_plan: &RebasePlan,
+        options: &ExecuteRebasePlanOptions,
+    ) -> eyre::Result<RebaseInMemoryResult> {
+        let (result, messages) = rebase_in_memory_deferred(effects, repo, rebase_plan, options)?;
+        for message in messages {
+            writeln!(effects.get_output_stream(), "{message}")?;
+        }
+        Ok(result)
+    }
+
+    /// Like [`rebase_in_memory`], but return the messages describing the
+    /// rewritten commits instead of printing them, so that the caller can
+    /// print them later.
+    #[instrument]
+    pub fn rebase_in_memory_deferred(
+        effects: &Effects,
+        repo: &Repo,
+        rebase_plan: &RebasePlan,
+        options: &ExecuteRebasePlanOptions,
+    ) -> eyre::Result<(RebaseInMemoryResult, Vec<String>)> {
+        let groups = find_independent_command_groups(&rebase_plan.commands);
+        rebase_command_groups_in_memory(effects, repo, rebase_plan, options, &groups)
+    }
+
+    /// Like [`rebase_in_memory`], but apply all commands serially using `repo`
+    /// itself. This is necessary if objects created in `repo` aren't visible to
+    /// other instances of the repository, such as when it was opened with
+    /// [`Repo::try_clone_in_memory`].
+    #[instrument]
+    pub fn rebase_in_memory_serially(
+        effects: &Effects,
+        repo: &Repo,
+        rebase_plan: &RebasePlan,
+        options: &ExecuteRebasePlanOptions,
+    ) -> eyre::Result<RebaseInMemoryResult> {
+        let group: Vec<usize> = (0..rebase_plan.commands.len()).collect();
+        let (result, messages) =
+            rebase_command_groups_in_memory(effects, repo, rebase_plan, options, &[group])?;
+        for message in messages {
+            writeln!(effects.get_output_stream(), "{message}")?;
+        }
+        Ok(result)
+    }
+
+    fn rebase_command_groups_in_memory(
+        effects: &Effects,
+        repo: &Repo,
+        rebase_plan: &RebasePlan,
+        options: &ExecuteRebasePlanOptions,
+        groups: &[Vec<usize>],
+    ) -> eyre::Result<(RebaseInMemoryResult, Vec<String>)> {
+        // Normally, we can determine the new `HEAD` OID by looking at the
+        // rewritten commits. However, if `HEAD` pointed to a commit that was
+        // skipped, then the rewritten OID is zero. In that case, we need to
+        // delete the branch (responsibility of the caller) and choose a
+        // different `HEAD` OID.
+        let head_oid = repo.get_head_info()?.oid;
+        let resolution_db = if get_restack_reuse_resolutions(repo)? {
+            Some(ResolutionDb::new(repo.get_db_conn()?)?)
+        } else {
+            None
+        };
+        let signer = Signer::from_config(repo)?;
+        let preserve_author_dates = get_restack_preserve_author_dates(repo)?;
+        let context = RebaseContext::new(
+            rebase_plan,
+            options,
+            head_oid,
+            resolution_db,
+            signer,
+            preserve_author_dates,
+        );
+
+        let (effects, progress) = effects.start_operation(OperationType::RebaseCommits);
+        progress.notify_progress(0, context.num_picks);
+        let applied_groups: Vec<AppliedCommandGroup> = if groups.len() <= 1 {
+            groups
+                .iter()
+                .map(|group| apply_command_group(&effects, &progress, repo, &context, group))
+                .collect::<eyre::Result<_>>()?
+        } else {
+            let pool = ThreadPoolBuilder::new().build()?;
+            let repo_pool = RepoResource::new_pool(repo)?;
+            pool.install(|| {
+                groups
+                    .par_iter()
+                    .map(|group| {
+                        let repo = repo_pool.try_create()?;
+                        apply_command_group(&effects, &progress, &repo, &context, group)
+                    })
+                    .collect::<eyre::Result<_>>()
+            })?
+        };
+
+        let mut rewritten_oids: HashMap<NonZeroOid, MaybeZeroOid> = HashMap::new();
+        let mut skipped_head_new_oid = None;
+        let mut messages = Vec::new();
+        let mut emptied_commit_oids = Vec::new();
+        let mut upstream_commit_oids = Vec::new();
+        let mut merge_failure: Option<(usize, FailedMergeInfo)> = None;
+        for applied_group in applied_groups {
+            let AppliedCommandGroup {
+                rewritten_oids: group_rewritten_oids,
+                skipped_head_new_oid: group_skipped_head_new_oid,
+                messages: group_messages,
+                emptied_commit_oids: group_emptied_commit_oids,
+                upstream_commit_oids: group_upstream_commit_oids,
+                merge_failure: group_merge_failure,
+            } = applied_group;
+            rewritten_oids.extend(group_rewritten_oids);
+            emptied_commit_oids.extend(group_emptied_commit_oids);
+            upstream_commit_oids.extend(group_upstream_commit_oids);
+            skipped_head_new_oid = skipped_head_new_oid.or(group_skipped_head_new_oid);
+            messages.extend(group_messages);
+            merge_failure = match (merge_failure, group_merge_failure) {
+                (Some((lhs_index, lhs)), Some((rhs_index, _))) if lhs_index < rhs_index => {
+                    Some((lhs_index, lhs))
+                }
+                (merge_failure, None) => merge_failure,
+                (_, group_merge_failure @ Some(_)) => group_merge_failure,
+            };
+        }
+
+        // Return the messages in the order of the rebase plan, regardless of
+        // which group finished first. If a merge failed, then only return the
+        // messages for the commands which came before it in the rebase plan.
+        messages.sort_by_key(|(index, _)| *index);
+        let failed_index = merge_failure.as_ref().map(|(index, _)| *index);
+        let messages = messages
+            .into_iter()
+            .take_while(
+                |(index, _)| !matches!(failed_index, Some(failed_index) if *index > failed_index),
+            )
+            .map(|(_, message)| message)
+            .collect();
+        if let Some((_, failed_merge_info)) = merge_failure {
+            return Ok((
+                RebaseInMemoryResult::MergeFailed(failed_merge_info),
+                messages,
+            ));
+        }
+
         let new_head_oid: Option<NonZeroOid> = match head_oid {
             None => {
                 // `HEAD` is unborn, so keep it that way.
@@ -888,10 +1465,22 @@ mod in_memory {
                 }
             }
         };
-        Ok(RebaseInMemoryResult::Succeeded {
-            rewritten_oids,
-            new_head_oid,
-        })
+        let sorted_commit_oids = |mut commit_oids: Vec<(usize, NonZeroOid)>| {
+            commit_oids.sort_by_key(|(index, _)| *index);
+            commit_oids
+                .into_iter()
+                .map(|(_, commit_oid)| commit_oid)
+                .collect()
+        };
+        Ok((
+            RebaseInMemoryResult::Succeeded {
+                rewritten_oids,
+                new_head_oid,
+                emptied_commit_oids: sorted_commit_oids(emptied_commit_oids),
+                upstream_commit_oids: sorted_commit_oids(upstream_commit_oids),
+            },
+            messages,
+        ))
     }
 
     pub fn post_rebase_in_memory(
@@ -911,6 +1500,10 @@ mod in_memory {
             force_on_disk: _,
             resolve_merge_conflicts: _,
             check_out_commit_options,
+            reset_author: _,
+            committer_as_author: _,
+            signature_commit_oids: _,
+            empty_commits: _,
         } = options;
 
         for new_oid in rewritten_oids.values() {
@@ -966,42 +1559,67 @@ mod on_disk {
     use eyre::Context;
     use tracing::instrument;
 
+    use crate::core::config::get_restack_preserve_author_dates;
     use crate::core::effects::{Effects, OperationType};
     use crate::core::rewrite::plan::RebaseCommand;
     use crate::core::rewrite::plan::RebasePlan;
     use crate::core::rewrite::rewrite_hooks::save_original_head_info;
+    use crate::core::rewrite::saved_plan::{SavedRebasePlan, SavedRebasePlanDb};
     use crate::git::{GitRunInfo, Repo};
 
     use crate::util::ExitCode;
 
-    use super::ExecuteRebasePlanOptions;
+    use super::{EmptyCommitsMode, ExecuteRebasePlanOptions};
 
     pub enum Error {
         ChangedFilesInRepository,
         OperationAlreadyInProgress { operation_type: String },
     }
 
+    impl Error {
+        pub fn describe(&self, effects: &Effects) -> eyre::Result<()> {
+            match self {
+                Error::ChangedFilesInRepository => {
+                    write!(
+                        effects.get_output_stream(),
+                        "\
+This operation would modify the working copy, but you have uncommitted changes
+in your working copy which might be overwritten as a result.
+Commit your changes and then try again.
+"
+                    )?;
+                }
+                Error::OperationAlreadyInProgress { operation_type } => {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "A {operation_type} operation is already in progress."
+                    )?;
+                    writeln!(
+                        effects.get_output_stream(),
+                        "Run git {operation_type} --continue or git {operation_type} --abort to resolve it and proceed."
+                    )?;
+                }
+            }
+            Ok(())
+        }
+    }
+
     fn write_rebase_state_to_disk(
         effects: &Effects,
         git_run_info: &GitRunInfo,
         repo: &Repo,
-        rebase_plan: &RebasePlan,
-        options: &ExecuteRebasePlanOptions,
+        saved_plan: &SavedRebasePlan,
     ) -> eyre::Result<Result<(), Error>> {
-        let ExecuteRebasePlanOptions {
-            now: _,
+        let SavedRebasePlan {
             event_tx_id: _,
+            head_info,
+            first_dest_oid,
+            todo_lines,
             preserve_timestamps,
-            force_in_memory: _,
-            force_on_disk: _,
-            resolve_merge_conflicts: _,
-            check_out_commit_options: _, // Checkout happens after rebase has concluded.
-        } = options;
+        } = saved_plan;
 
         let (effects, _progress) = effects.start_operation(OperationType::InitializeRebase);
 
-        let head_info = repo.get_head_info()?;
-
         let current_operation_type = repo.get_current_operation_type();
         if let Some(current_operation_type) = current_operation_type {
             return Ok(Err(Error::OperationAlreadyInProgress {
@@ -1059,51 +1677,33 @@ mod on_disk {
             )
             .wrap_err_with(|| format!("Writing head-name to: {:?}", &head_name_file_path))?;
 
-            save_original_head_info(repo, &head_info)?;
+            save_original_head_info(repo, head_info)?;
 
             // Dummy `head` file. We will `reset` to the appropriate commit as soon as
             // we start the rebase.
             let rebase_merge_head_file_path = rebase_state_dir.join("head");
-            std::fs::write(
-                &rebase_merge_head_file_path,
-                rebase_plan.first_dest_oid.to_string(),
-            )
-            .wrap_err_with(|| format!("Writing head to: {:?}", &rebase_merge_head_file_path))?;
+            std::fs::write(&rebase_merge_head_file_path, first_dest_oid.to_string())
+                .wrap_err_with(|| format!("Writing head to: {:?}", &rebase_merge_head_file_path))?;
         }
 
         // Dummy `onto` file. We may be rebasing onto a set of unrelated
         // nodes in the same operation, so there may not be a single "onto" node to
         // refer to.
         let onto_file_path = rebase_state_dir.join("onto");
-        std::fs::write(&onto_file_path, rebase_plan.first_dest_oid.to_string()).wrap_err_with(
-            || {
-                format!(
-                    "Writing onto {:?} to: {:?}",
-                    &rebase_plan.first_dest_oid, &onto_file_path
-                )
-            },
-        )?;
-
-        if rebase_plan.commands.iter().any(|command| match command {
-            RebaseCommand::Pick {
-                original_commit_oid,
-                commits_to_apply_oids,
-            } => !commits_to_apply_oids
-                .iter()
-                .any(|oid| oid == original_commit_oid),
-            _ => false,
-        }) {
-            eyre::bail!("Not implemented: replacing commits in an on disk rebase");
-        }
+        std::fs::write(&onto_file_path, first_dest_oid.to_string()).wrap_err_with(|| {
+            format!(
+                "Writing onto {:?} to: {:?}",
+                &first_dest_oid, &onto_file_path
+            )
+        })?;
 
         let todo_file_path = rebase_state_dir.join("git-rebase-todo");
         #[allow(clippy::format_collect)]
         std::fs::write(
             &todo_file_path,
-            rebase_plan
-                .commands
+            todo_lines
                 .iter()
-                .map(|command| format!("{}\n", command.to_rebase_command()))
+                .map(|line| format!("{line}\n"))
                 .collect::<String>(),
         )
         .wrap_err_with(|| {
@@ -1114,11 +1714,8 @@ mod on_disk {
         })?;
 
         let end_file_path = rebase_state_dir.join("end");
-        std::fs::write(
-            end_file_path.as_path(),
-            format!("{}\n", rebase_plan.commands.len()),
-        )
-        .wrap_err_with(|| format!("Writing `end` to: {:?}", end_file_path.as_path()))?;
+        std::fs::write(end_file_path.as_path(), format!("{}\n", todo_lines.len()))
+            .wrap_err_with(|| format!("Writing `end` to: {:?}", end_file_path.as_path()))?;
 
         // Corresponds to the `--empty=keep` flag. We'll drop the commits later once
         // we find out that they're empty.
@@ -1130,6 +1727,16 @@ mod on_disk {
             )
         })?;
 
+        if !get_restack_preserve_author_dates(repo)? {
+            let ignore_date_file_path = rebase_state_dir.join("ignore_date");
+            std::fs::write(&ignore_date_file_path, "").wrap_err_with(|| {
+                format!(
+                    "Writing `ignore_date` option file to: {:?}",
+                    &ignore_date_file_path
+                )
+            })?;
+        }
+
         if *preserve_timestamps {
             let cdate_is_adate_file_path = rebase_state_dir.join("cdate_is_adate");
             std::fs::write(&cdate_is_adate_file_path, "").wrap_err_with(|| {
@@ -1144,7 +1751,7 @@ mod on_disk {
         // actually needs to be moved, then it will be moved as part of the
         // post-rebase operations.
         if head_info.oid.is_some() {
-            repo.detach_head(&head_info)?;
+            repo.detach_head(head_info)?;
         }
 
         Ok(Ok(()))
@@ -1167,29 +1774,194 @@ mod on_disk {
             // `git rebase` will make its own timestamp.
             now: _,
             event_tx_id,
-            preserve_timestamps: _,
+            preserve_timestamps,
             force_in_memory: _,
             force_on_disk: _,
             resolve_merge_conflicts: _,
             check_out_commit_options: _, // Checkout happens after rebase has concluded.
+            // Only supported for in-memory rebases.
+            reset_author: _,
+            committer_as_author: _,
+            signature_commit_oids: _,
+            empty_commits,
         } = options;
 
-        match write_rebase_state_to_disk(effects, git_run_info, repo, rebase_plan, options)? {
+        if rebase_plan.commands.iter().any(|command| match command {
+            RebaseCommand::Pick {
+                original_commit_oid,
+                commits_to_apply_oids,
+            } => !commits_to_apply_oids
+                .iter()
+                .any(|oid| oid == original_commit_oid),
+            _ => false,
+        }) {
+            eyre::bail!("Not implemented: replacing commits in an on disk rebase");
+        }
+
+        let saved_plan = SavedRebasePlan {
+            event_tx_id: *event_tx_id,
+            head_info: repo.get_head_info()?,
+            first_dest_oid: rebase_plan.first_dest_oid,
+            todo_lines: rebase_plan
+                .commands
+                .iter()
+                .filter(|command| {
+                    !(*empty_commits == EmptyCommitsMode::Keep
+                        && matches!(command, RebaseCommand::DetectEmptyCommit { .. }))
+                })
+                .map(|command| command.to_rebase_command())
+                .collect(),
+            preserve_timestamps: *preserve_timestamps,
+        };
+        resume_rebase_on_disk(effects, git_run_info, repo, &saved_plan)
+    }
+
+    /// Start the on-disk rebase described by `saved_plan` from the beginning,
+    /// and save the plan so that it can be resumed or aborted as a whole if
+    /// it's interrupted.
+    #[instrument]
+    pub fn resume_rebase_on_disk(
+        effects: &Effects,
+        git_run_info: &GitRunInfo,
+        repo: &Repo,
+        saved_plan: &SavedRebasePlan,
+    ) -> eyre::Result<Result<ExitCode, Error>> {
+        match write_rebase_state_to_disk(effects, git_run_info, repo, saved_plan)? {
             Ok(()) => {}
             Err(err) => return Ok(Err(err)),
         };
+        let conn = repo.get_db_conn()?;
+        SavedRebasePlanDb::new(&conn)?.save(repo, saved_plan)?;
 
         writeln!(
             effects.get_output_stream(),
             "Calling Git for on-disk rebase..."
         )?;
-        match git_run_info.run(effects, Some(*event_tx_id), &["rebase", "--continue"])? {
+        match git_run_info.run(
+            effects,
+            Some(saved_plan.event_tx_id),
+            &["rebase", "--continue"],
+        )? {
             Ok(()) => Ok(Ok(ExitCode::success())),
             Err(err) => Ok(Ok(err)),
         }
     }
 }
 
+/// Continue the interrupted on-disk rebase for the current worktree, as
+/// started by [`execute_rebase_plan`]. If Git's rebase is still in progress,
+/// then it's continued. Otherwise (for example, if the process was killed
+/// before Git started rebasing, or if the Git rebase was aborted), the entire
+/// saved rebase plan is started again from the beginning.
+#[instrument]
+pub fn continue_saved_rebase_plan(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+) -> EyreExitOr<()> {
+    let conn = repo.get_db_conn()?;
+    let saved_plan = match SavedRebasePlanDb::new(&conn)?.load(repo)? {
+        Some(saved_plan) => saved_plan,
+        None => {
+            writeln!(
+                effects.get_output_stream(),
+                "There is no interrupted operation to continue."
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+
+    if repo.is_rebase_underway()? {
+        return git_run_info.run(
+            effects,
+            Some(saved_plan.event_tx_id),
+            &["rebase", "--continue"],
+        );
+    }
+
+    use on_disk::*;
+    writeln!(
+        effects.get_output_stream(),
+        "Restarting the interrupted operation from the beginning."
+    )?;
+    match resume_rebase_on_disk(effects, git_run_info, repo, &saved_plan)? {
+        Ok(exit_code) if exit_code.is_success() => Ok(Ok(())),
+        Ok(exit_code) => Ok(Err(exit_code)),
+        Err(err) => {
+            err.describe(effects)?;
+            Ok(Err(ExitCode(1)))
+        }
+    }
+}
+
+/// Abort the interrupted on-disk rebase for the current worktree, as started
+/// by [`execute_rebase_plan`], and restore `HEAD` to where it was before the
+/// rebase started. Branches are only moved once the entire rebase has
+/// finished, so they don't need to be restored.
+#[instrument]
+pub fn abort_saved_rebase_plan(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+) -> EyreExitOr<()> {
+    let conn = repo.get_db_conn()?;
+    let saved_plan_db = SavedRebasePlanDb::new(&conn)?;
+    let SavedRebasePlan {
+        event_tx_id,
+        head_info,
+        first_dest_oid: _,
+        todo_lines: _,
+        preserve_timestamps: _,
+    } = match saved_plan_db.load(repo)? {
+        Some(saved_plan) => saved_plan,
+        None => {
+            writeln!(
+                effects.get_output_stream(),
+                "There is no interrupted operation to abort."
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+
+    if repo.is_rebase_underway()? {
+        try_exit_code!(git_run_info.run(effects, Some(event_tx_id), &["rebase", "--abort"])?);
+    } else {
+        let target = match head_info {
+            ResolvedReferenceInfo {
+                oid: _,
+                reference_name: Some(reference_name),
+            } => Some(CheckoutTarget::Reference(reference_name)),
+            ResolvedReferenceInfo {
+                oid: Some(oid),
+                reference_name: None,
+            } => Some(CheckoutTarget::Oid(oid)),
+            ResolvedReferenceInfo {
+                oid: None,
+                reference_name: None,
+            } => None,
+        };
+        if target.is_some() {
+            let event_log_db = EventLogDb::new(&conn)?;
+            try_exit_code!(check_out_commit(
+                effects,
+                git_run_info,
+                repo,
+                &event_log_db,
+                event_tx_id,
+                target,
+                &CheckOutCommitOptions::default(),
+            )?);
+        }
+    }
+
+    saved_plan_db.clear(repo)?;
+    writeln!(
+        effects.get_output_stream(),
+        "Aborted the interrupted operation."
+    )?;
+    Ok(Ok(()))
+}
+
 /// Options to use when executing a `RebasePlan`.
 #[derive(Clone, Debug)]
 pub struct ExecuteRebasePlanOptions {
@@ -1204,6 +1976,21 @@ pub struct ExecuteRebasePlanOptions {
     /// to the current time.
     pub preserve_timestamps: bool,
 
+    /// If `true`, set the author of rewritten commits to the current user and
+    /// the current time. Only supported for in-memory rebases.
+    pub reset_author: bool,
+
+    /// If `true`, set the committer of rewritten commits to be the same as
+    /// their author, including the timestamp. Only supported for in-memory
+    /// rebases.
+    pub committer_as_author: bool,
+
+    /// If set, `reset_author` and `committer_as_author` only apply to the
+    /// commits with these OIDs (before the rebase), and the other rewritten
+    /// commits keep their original authors and committers. Otherwise, they
+    /// apply to all rewritten commits.
+    pub signature_commit_oids: Option<HashSet<NonZeroOid>>,
+
     /// Force an in-memory rebase (as opposed to an on-disk rebase).
     pub force_in_memory: bool,
 
@@ -1216,6 +2003,25 @@ pub struct ExecuteRebasePlanOptions {
 
     /// If `HEAD` was moved, the options for checking out the new `HEAD` commit.
     pub check_out_commit_options: CheckOutCommitOptions,
+
+    /// What to do with commits which become empty as a result of the rebase.
+    pub empty_commits: EmptyCommitsMode,
+}
+
+/// What to do with commits which become empty as a result of being rebased.
+/// Commits which were already empty before the rebase are always kept.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmptyCommitsMode {
+    /// Drop the commits.
+    Drop,
+
+    /// Keep the commits as empty commits.
+    Keep,
+
+    /// Ask the user whether to drop the commits. The commits are dropped
+    /// without asking if standard input isn't a terminal, or if the rebase
+    /// happens on disk.
+    Prompt,
 }
 
 /// The result of executing a rebase plan.
@@ -1243,6 +2049,63 @@ pub enum ExecuteRebasePlanResult {
     },
 }
 
+/// Carry out the rebase plan in memory without applying the results, to see
+/// what the rewritten commits would be. No references are updated and the
+/// working copy isn't touched. To avoid writing the rewritten commits to disk,
+/// `repo` should be opened with [`Repo::try_clone_in_memory`].
+///
+/// Returns a mapping from each original commit to its would-be rewritten
+/// commit, or information about the first commit which would fail to merge.
+/// Commits after a failed merge aren't checked.
+pub fn dry_run_rebase_plan(
+    effects: &Effects,
+    repo: &Repo,
+    rebase_plan: &RebasePlan,
+    options: &ExecuteRebasePlanOptions,
+) -> eyre::Result<Result<HashMap<NonZeroOid, MaybeZeroOid>, FailedMergeInfo>> {
+    use in_memory::*;
+    match rebase_in_memory_serially(&effects.suppress(), repo, rebase_plan, options)? {
+        RebaseInMemoryResult::Succeeded {
+            rewritten_oids,
+            new_head_oid: _,
+            emptied_commit_oids: _,
+            upstream_commit_oids: _,
+        } => Ok(Ok(rewritten_oids)),
+        RebaseInMemoryResult::MergeFailed(failed_merge_info) => Ok(Err(failed_merge_info)),
+    }
+}
+
+/// The result of rebasing the commits in a rebase plan in memory ahead of time
+/// with [`rebase_plan_in_memory`]. Only new commits have been created; no
+/// references have been updated yet.
+pub struct InMemoryRebaseResult {
+    result: in_memory::RebaseInMemoryResult,
+    messages: Vec<String>,
+    head_oid: Option<NonZeroOid>,
+}
+
+/// Rebase the commits in the provided rebase plan in memory, without updating
+/// any references or printing any output, so that the result can be applied
+/// later with [`execute_rebase_plan_with_in_memory_result`].
+///
+/// This can be called concurrently for independent rebase plans, using a
+/// different `repo` instance for each one.
+pub fn rebase_plan_in_memory(
+    effects: &Effects,
+    repo: &Repo,
+    rebase_plan: &RebasePlan,
+    options: &ExecuteRebasePlanOptions,
+) -> eyre::Result<InMemoryRebaseResult> {
+    let head_oid = repo.get_head_info()?.oid;
+    let (result, messages) =
+        in_memory::rebase_in_memory_deferred(&effects.suppress(), repo, rebase_plan, options)?;
+    Ok(InMemoryRebaseResult {
+        result,
+        messages,
+        head_oid,
+    })
+}
+
 /// Execute the provided rebase plan. Returns the exit status (zero indicates
 /// success).
 pub fn execute_rebase_plan(
@@ -1252,6 +2115,116 @@ pub fn execute_rebase_plan(
     event_log_db: &EventLogDb,
     rebase_plan: &RebasePlan,
     options: &ExecuteRebasePlanOptions,
+) -> eyre::Result<ExecuteRebasePlanResult> {
+    execute_rebase_plan_inner(
+        effects,
+        git_run_info,
+        repo,
+        event_log_db,
+        rebase_plan,
+        options,
+        None,
+    )
+}
+
+/// Like [`execute_rebase_plan`], but use the in-memory rebase which was
+/// already carried out by [`rebase_plan_in_memory`] instead of rebasing the
+/// commits in memory again. If the in-memory rebase failed, then the on-disk
+/// rebase is attempted as usual.
+pub fn execute_rebase_plan_with_in_memory_result(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    event_log_db: &EventLogDb,
+    rebase_plan: &RebasePlan,
+    options: &ExecuteRebasePlanOptions,
+    in_memory_result: InMemoryRebaseResult,
+) -> eyre::Result<ExecuteRebasePlanResult> {
+    execute_rebase_plan_inner(
+        effects,
+        git_run_info,
+        repo,
+        event_log_db,
+        rebase_plan,
+        options,
+        Some(in_memory_result),
+    )
+}
+
+/// Print a one-line summary of the commits which were dropped from the rebase
+/// for the given reason, if any.
+fn describe_dropped_commits(
+    effects: &Effects,
+    repo: &Repo,
+    reason: &str,
+    commit_oids: &[NonZeroOid],
+) -> eyre::Result<()> {
+    if commit_oids.is_empty() {
+        return Ok(());
+    }
+
+    let mut short_oids = Vec::new();
+    for commit_oid in commit_oids {
+        short_oids.push(repo.find_commit_or_fail(*commit_oid)?.get_short_oid()?);
+    }
+    writeln!(
+        effects.get_output_stream(),
+        "Skipped {} {reason}: {}",
+        Pluralize {
+            determiner: None,
+            amount: commit_oids.len(),
+            unit: ("commit", "commits"),
+        },
+        short_oids.join(", "),
+    )?;
+    Ok(())
+}
+
+/// Ask the user whether the commits which became empty as a result of the
+/// rebase should be dropped.
+fn confirm_drop_empty_commits(
+    effects: &Effects,
+    repo: &Repo,
+    commit_oids: &[NonZeroOid],
+) -> eyre::Result<bool> {
+    writeln!(
+        effects.get_output_stream(),
+        "{} would become empty as a result of the rebase:",
+        Pluralize {
+            determiner: Some(("This", "These")),
+            amount: commit_oids.len(),
+            unit: ("commit", "commits"),
+        }
+    )?;
+    for commit_oid in commit_oids {
+        let commit = repo.find_commit_or_fail(*commit_oid)?;
+        writeln!(
+            effects.get_output_stream(),
+            "{}",
+            effects
+                .get_glyphs()
+                .render(commit.friendly_describe(effects.get_glyphs())?)?
+        )?;
+    }
+    write!(effects.get_output_stream(), "Drop them? [Yn] ")?;
+    let mut user_input = String::new();
+    match std::io::stdin().read_line(&mut user_input) {
+        Ok(_size) => {
+            let user_input = user_input.trim();
+            Ok(!(user_input == "n" || user_input == "N"))
+        }
+        Err(_) => Ok(true),
+    }
+}
+
+fn execute_rebase_plan_inner(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    event_log_db: &EventLogDb,
+    rebase_plan: &RebasePlan,
+    options: &ExecuteRebasePlanOptions,
+    in_memory_result: Option<InMemoryRebaseResult>,
 ) -> eyre::Result<ExecuteRebasePlanResult> {
     let ExecuteRebasePlanOptions {
         now: _,
@@ -1261,8 +2234,23 @@ pub fn execute_rebase_plan(
         force_on_disk,
         resolve_merge_conflicts,
         check_out_commit_options: _,
+        reset_author,
+        committer_as_author,
+        signature_commit_oids: _,
+        empty_commits,
     } = options;
 
+    let requires_in_memory = *reset_author || *committer_as_author;
+    if requires_in_memory && (*force_on_disk || *resolve_merge_conflicts) {
+        writeln!(
+            effects.get_output_stream(),
+            "The --reset-author and --committer-as-author options are only supported for in-memory rebases, so they can't be combined with --on-disk or --merge."
+        )?;
+        return Ok(ExecuteRebasePlanResult::Failed {
+            exit_code: ExitCode(1),
+        });
+    }
+
     if !force_on_disk {
         use in_memory::*;
         writeln!(
@@ -1270,13 +2258,61 @@ pub fn execute_rebase_plan(
             "Attempting rebase in-memory..."
         )?;
 
-        let failed_merge_info = match rebase_in_memory(effects, repo, rebase_plan, options)? {
+        // The in-memory rebase decides where `HEAD` should end up, so a result
+        // which was computed before `HEAD` moved (such as by applying another
+        // rebase plan in the meantime) can't be used.
+        let current_head_oid = repo.get_head_info()?.oid;
+        let (result, messages) = match in_memory_result {
+            Some(InMemoryRebaseResult {
+                result,
+                messages,
+                head_oid,
+            }) if head_oid == current_head_oid => (result, messages),
+            Some(_) | None => rebase_in_memory_deferred(effects, repo, rebase_plan, options)?,
+        };
+        let (result, messages) = match result {
+            RebaseInMemoryResult::Succeeded {
+                ref emptied_commit_oids,
+                ..
+            } if *empty_commits == EmptyCommitsMode::Prompt
+                && !emptied_commit_oids.is_empty()
+                && std::io::stdin().is_terminal()
+                && !confirm_drop_empty_commits(effects, repo, emptied_commit_oids)? =>
+            {
+                let options = ExecuteRebasePlanOptions {
+                    empty_commits: EmptyCommitsMode::Keep,
+                    ..options.clone()
+                };
+                rebase_in_memory_deferred(effects, repo, rebase_plan, &options)?
+            }
+            result => (result, messages),
+        };
+        for message in messages {
+            writeln!(effects.get_output_stream(), "{message}")?;
+        }
+
+        let failed_merge_info = match result {
             RebaseInMemoryResult::MergeFailed(failed_merge_info) => failed_merge_info,
 
             RebaseInMemoryResult::Succeeded {
                 rewritten_oids,
                 new_head_oid,
+                emptied_commit_oids,
+                upstream_commit_oids,
             } => {
+                describe_dropped_commits(
+                    effects,
+                    repo,
+                    "already applied upstream",
+                    &upstream_commit_oids,
+                )?;
+                describe_dropped_commits(
+                    effects,
+                    repo,
+                    "which became empty",
+                    &emptied_commit_oids,
+                )?;
+
                 // Ignore the return code, as it probably indicates that the
                 // checkout failed (which might happen if the user has changes
                 // which don't merge cleanly). The user can resolve that
@@ -1310,7 +2346,7 @@ pub fn execute_rebase_plan(
 
         // The rebase has failed at this point, decide whether or not to try
         // again with an on-disk rebase.
-        if *force_in_memory {
+        if *force_in_memory || requires_in_memory {
             writeln!(
                 effects.get_output_stream(),
                 "Aborting since an in-memory rebase was requested."
@@ -1333,28 +2369,8 @@ pub fn execute_rebase_plan(
                 });
             }
             Ok(exit_code) => return Ok(ExecuteRebasePlanResult::Failed { exit_code }),
-            Err(Error::ChangedFilesInRepository) => {
-                write!(
-                    effects.get_output_stream(),
-                    "\
-This operation would modify the working copy, but you have uncommitted changes
-in your working copy which might be overwritten as a result.
-Commit your changes and then try again.
-"
-                )?;
-                return Ok(ExecuteRebasePlanResult::Failed {
-                    exit_code: ExitCode(1),
-                });
-            }
-            Err(Error::OperationAlreadyInProgress { operation_type }) => {
-                writeln!(
-                    effects.get_output_stream(),
-                    "A {operation_type} operation is already in progress."
-                )?;
-                writeln!(
-                    effects.get_output_stream(),
-                    "Run git {operation_type} --continue or git {operation_type} --abort to resolve it and proceed."
-                )?;
+            Err(err) => {
+                err.describe(effects)?;
                 return Ok(ExecuteRebasePlanResult::Failed {
                     exit_code: ExitCode(1),
                 });
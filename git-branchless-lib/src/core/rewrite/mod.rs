@@ -4,18 +4,22 @@ mod evolve;
 mod execute;
 mod plan;
 pub mod rewrite_hooks;
+mod saved_plan;
 
 use std::sync::Mutex;
 
 pub use evolve::{find_abandoned_children, find_rewrite_target};
 pub use execute::{
-    execute_rebase_plan, move_branches, ExecuteRebasePlanOptions, ExecuteRebasePlanResult,
-    FailedMergeInfo, MergeConflictRemediation,
+    abort_saved_rebase_plan, check_out_updated_head, continue_saved_rebase_plan,
+    dry_run_rebase_plan, execute_rebase_plan, execute_rebase_plan_with_in_memory_result,
+    move_branches, rebase_plan_in_memory, EmptyCommitsMode, ExecuteRebasePlanOptions,
+    ExecuteRebasePlanResult, FailedMergeInfo, InMemoryRebaseResult, MergeConflictRemediation,
 };
 pub use plan::{
     BuildRebasePlanError, BuildRebasePlanOptions, OidOrLabel, RebaseCommand, RebasePlan,
     RebasePlanBuilder, RebasePlanPermissions,
 };
+pub use saved_plan::{SavedRebasePlan, SavedRebasePlanDb};
 use tracing::instrument;
 
 use crate::core::task::{Resource, ResourcePool};
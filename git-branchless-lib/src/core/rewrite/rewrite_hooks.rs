@@ -6,6 +6,7 @@ use std::fmt::Write;
 use std::fs::{self, File};
 use std::io::{self, stdin, BufRead, BufReader, Read, Write as WriteIo};
 use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 use std::str::FromStr;
 use std::time::SystemTime;
 
@@ -16,18 +17,25 @@ use tempfile::NamedTempFile;
 use tracing::instrument;
 
 use crate::core::check_out::CheckOutCommitOptions;
-use crate::core::config::{get_hint_enabled, print_hint_suppression_notice, Hint};
+use crate::core::config::{
+    get_hint_enabled, get_hook_post_rewrite, get_tempfile_dir, print_hint_suppression_notice, Hint,
+};
 use crate::core::dag::Dag;
 use crate::core::effects::Effects;
-use crate::core::eventlog::{Event, EventLogDb, EventReplayer};
+use crate::core::eventlog::{
+    Event, EventLogDb, EventReplayer, EventTransactionId, BRANCHLESS_TRANSACTION_ID_ENV_VAR,
+};
 use crate::core::formatting::Pluralize;
 use crate::core::repo_ext::RepoExt;
+use crate::core::rerere::ResolutionDb;
 use crate::git::{
     CategorizedReferenceName, GitRunInfo, MaybeZeroOid, NonZeroOid, ReferenceName, Repo,
     ResolvedReferenceInfo,
 };
+use crate::util::get_sh;
 
 use super::execute::check_out_updated_head;
+use super::saved_plan::SavedRebasePlanDb;
 use super::{find_abandoned_children, move_branches};
 
 /// Get the path to the file which stores the list of "deferred commits".
@@ -190,6 +198,15 @@ pub fn hook_post_rewrite(
     )?;
     event_log_db.add_events(rewrite_events)?;
 
+    if rewrite_type == "rebase" {
+        let resolution_db = ResolutionDb::new(repo.get_db_conn()?)?;
+        for (old_commit_oid, new_commit_oid) in rewritten_oids.iter() {
+            if let MaybeZeroOid::NonZero(new_commit_oid) = new_commit_oid {
+                resolution_db.record_resolutions(&repo, *old_commit_oid, *new_commit_oid)?;
+            }
+        }
+    }
+
     if repo
         .get_rebase_state_dir_path()
         .join(EXTRA_POST_REWRITE_FILE_NAME)
@@ -200,6 +217,9 @@ pub fn hook_post_rewrite(
         let previous_head_info = load_original_head_info(&repo)?;
         move_branches(effects, git_run_info, &repo, event_tx_id, &rewritten_oids)?;
 
+        // The on-disk rebase has finished, so it no longer needs to be resumed.
+        SavedRebasePlanDb::new(&conn)?.clear(&repo)?;
+
         let skipped_head_updated_oid = load_updated_head_oid(&repo)?;
         match check_out_updated_head(
             effects,
@@ -219,6 +239,15 @@ pub fn hook_post_rewrite(
         }
     }
 
+    run_post_rewrite_user_hook(
+        effects,
+        git_run_info,
+        &repo,
+        event_tx_id,
+        rewrite_type,
+        &rewritten_oids,
+    )?;
+
     let should_check_abandoned_commits = get_hint_enabled(&repo, Hint::RestackWarnAbandoned)?;
     if should_check_abandoned_commits && !is_spurious_event {
         let printed_hint = warn_abandoned(
@@ -236,6 +265,69 @@ pub fn hook_post_rewrite(
     Ok(())
 }
 
+/// Run the user's `branchless.hook.postRewrite` command, if any, after commits
+/// have been rewritten. Like Git's `post-rewrite` hook, each line of its stdin
+/// contains an old commit OID and the OID of the commit which it was rewritten
+/// into, and the type of rewrite (`amend` or `rebase`) is passed in the
+/// `BRANCHLESS_REWRITE_TYPE` environment variable. A failing hook is reported,
+/// but doesn't cause the rewrite itself to fail, since it has already been
+/// applied.
+#[instrument]
+pub fn run_post_rewrite_user_hook(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    event_tx_id: EventTransactionId,
+    rewrite_type: &str,
+    rewritten_oids: &HashMap<NonZeroOid, MaybeZeroOid>,
+) -> eyre::Result<()> {
+    let command = match get_hook_post_rewrite(repo)? {
+        Some(command) => command,
+        None => return Ok(()),
+    };
+    if rewritten_oids.is_empty() {
+        return Ok(());
+    }
+    let shell_path = match get_sh() {
+        Some(shell_path) => shell_path,
+        None => {
+            effects.get_error_stream().write_str(
+                "branchless: could not determine path to shell, not running postRewrite hook\n",
+            )?;
+            return Ok(());
+        }
+    };
+
+    #[allow(clippy::format_collect)]
+    let hook_stdin: String = rewritten_oids
+        .iter()
+        .sorted()
+        .map(|(old_oid, new_oid)| format!("{old_oid} {new_oid}\n"))
+        .collect();
+    let mut child = Command::new(shell_path)
+        .arg("-c")
+        .arg(&command)
+        .current_dir(&git_run_info.working_directory)
+        .env(BRANCHLESS_TRANSACTION_ID_ENV_VAR, event_tx_id.to_string())
+        .env("BRANCHLESS_REWRITE_TYPE", rewrite_type)
+        .stdin(Stdio::piped())
+        .spawn()
+        .wrap_err("Running postRewrite hook")?;
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(hook_stdin.as_bytes())
+        .wrap_err("Writing postRewrite hook stdin")?;
+    let exit_status = child.wait().wrap_err("Waiting for postRewrite hook")?;
+    if !exit_status.success() {
+        effects.get_error_stream().write_str(&format!(
+            "branchless: postRewrite hook failed ({exit_status}): {command}\n"
+        ))?;
+    }
+    Ok(())
+}
+
 #[instrument(skip(old_commit_oids))]
 fn warn_abandoned(
     effects: &Effects,
@@ -484,6 +576,14 @@ pub fn hook_drop_commit_if_empty(
         return Ok(());
     }
 
+    // Keep commits which were intentionally empty to begin with; only drop
+    // commits which became empty as a result of the rebase.
+    if let Some(old_commit) = repo.find_commit(old_commit_oid)? {
+        if old_commit.is_empty() {
+            return Ok(());
+        }
+    }
+
     let only_parent_oid = match head_commit.get_only_parent_oid() {
         Some(only_parent_oid) => only_parent_oid,
         None => return Ok(()),
@@ -507,7 +607,7 @@ pub fn hook_drop_commit_if_empty(
         save_updated_head_oid(&repo, only_parent_oid)?;
     }
     add_rewritten_list_entries(
-        &repo.get_tempfile_dir()?,
+        &get_tempfile_dir(&repo)?,
         &repo.get_rebase_state_dir_path().join("rewritten-list"),
         &[
             (old_commit_oid, MaybeZeroOid::Zero),
@@ -546,7 +646,7 @@ pub fn hook_skip_upstream_applied_commit(
         }
     }
     add_rewritten_list_entries(
-        &repo.get_tempfile_dir()?,
+        &get_tempfile_dir(&repo)?,
         &repo.get_rebase_state_dir_path().join("rewritten-list"),
         &[(commit_oid, MaybeZeroOid::Zero)],
     )?;
@@ -0,0 +1,185 @@
+//! Persist the plans of on-disk rebases, so that an interrupted operation can
+//! be resumed or rolled back as a whole, rather than one `git rebase` step at a
+//! time.
+
+use std::fmt::Write;
+
+use eyre::Context;
+use tracing::instrument;
+
+use crate::core::eventlog::EventTransactionId;
+use crate::git::{MaybeZeroOid, NonZeroOid, ReferenceName, Repo, ResolvedReferenceInfo};
+
+/// A rebase plan which was started on-disk but which hasn't finished yet.
+#[derive(Debug)]
+pub struct SavedRebasePlan {
+    /// The transaction ID of the operation which started the rebase.
+    pub event_tx_id: EventTransactionId,
+
+    /// The value of `HEAD` before the rebase started.
+    pub head_info: ResolvedReferenceInfo,
+
+    /// The first commit which the rebase checks out.
+    pub first_dest_oid: NonZeroOid,
+
+    /// The lines of the `git-rebase-todo` file for the entire rebase.
+    pub todo_lines: Vec<String>,
+
+    /// Whether the rebase preserves the committer timestamps of the rewritten
+    /// commits.
+    pub preserve_timestamps: bool,
+}
+
+/// Stores the plans of on-disk rebases which are in progress. There is at most
+/// one such plan per worktree.
+pub struct SavedRebasePlanDb<'conn> {
+    conn: &'conn rusqlite::Connection,
+}
+
+impl std::fmt::Debug for SavedRebasePlanDb<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<SavedRebasePlanDb>")
+    }
+}
+
+#[instrument]
+fn init_tables(conn: &rusqlite::Connection) -> eyre::Result<()> {
+    conn.execute(
+        "
+CREATE TABLE IF NOT EXISTS saved_rebase_plans (
+    rebase_state_dir TEXT PRIMARY KEY NOT NULL,
+    event_tx_id TEXT NOT NULL,
+    head_oid TEXT NOT NULL,
+    head_reference_name TEXT,
+    first_dest_oid TEXT NOT NULL,
+    todo TEXT NOT NULL,
+    preserve_timestamps INTEGER NOT NULL
+)
+",
+        rusqlite::params![],
+    )
+    .wrap_err("Creating `saved_rebase_plans` table")?;
+
+    Ok(())
+}
+
+fn get_key(repo: &Repo) -> String {
+    repo.get_rebase_state_dir_path()
+        .to_string_lossy()
+        .into_owned()
+}
+
+impl<'conn> SavedRebasePlanDb<'conn> {
+    /// Constructor.
+    #[instrument]
+    pub fn new(conn: &'conn rusqlite::Connection) -> eyre::Result<Self> {
+        init_tables(conn)?;
+        Ok(SavedRebasePlanDb { conn })
+    }
+
+    /// Save the plan for the on-disk rebase in the current worktree, replacing
+    /// any previously-saved plan.
+    #[instrument]
+    pub fn save(&self, repo: &Repo, saved_plan: &SavedRebasePlan) -> eyre::Result<()> {
+        let SavedRebasePlan {
+            event_tx_id,
+            head_info:
+                ResolvedReferenceInfo {
+                    oid: head_oid,
+                    reference_name: head_reference_name,
+                },
+            first_dest_oid,
+            todo_lines,
+            preserve_timestamps,
+        } = saved_plan;
+        let head_oid: MaybeZeroOid = (*head_oid).into();
+        let mut todo = String::new();
+        for line in todo_lines {
+            writeln!(todo, "{line}")?;
+        }
+        self.conn
+            .execute(
+                "
+INSERT OR REPLACE INTO saved_rebase_plans
+    (rebase_state_dir, event_tx_id, head_oid, head_reference_name, first_dest_oid, todo, preserve_timestamps)
+VALUES (?, ?, ?, ?, ?, ?, ?)
+",
+                rusqlite::params![
+                    get_key(repo),
+                    event_tx_id.to_string(),
+                    head_oid.to_string(),
+                    head_reference_name
+                        .as_ref()
+                        .map(|reference_name| reference_name.as_str().to_owned()),
+                    first_dest_oid.to_string(),
+                    todo,
+                    preserve_timestamps,
+                ],
+            )
+            .wrap_err("Saving rebase plan")?;
+        Ok(())
+    }
+
+    /// Load the plan for the on-disk rebase in the current worktree, if any.
+    #[instrument]
+    pub fn load(&self, repo: &Repo) -> eyre::Result<Option<SavedRebasePlan>> {
+        let mut stmt = self.conn.prepare(
+            "
+SELECT event_tx_id, head_oid, head_reference_name, first_dest_oid, todo, preserve_timestamps
+FROM saved_rebase_plans
+WHERE rebase_state_dir = ?
+",
+        )?;
+        let mut rows = stmt
+            .query_map(rusqlite::params![get_key(repo)], |row| {
+                let event_tx_id: String = row.get("event_tx_id")?;
+                let head_oid: String = row.get("head_oid")?;
+                let head_reference_name: Option<String> = row.get("head_reference_name")?;
+                let first_dest_oid: String = row.get("first_dest_oid")?;
+                let todo: String = row.get("todo")?;
+                let preserve_timestamps: bool = row.get("preserve_timestamps")?;
+                Ok((
+                    event_tx_id,
+                    head_oid,
+                    head_reference_name,
+                    first_dest_oid,
+                    todo,
+                    preserve_timestamps,
+                ))
+            })
+            .wrap_err("Querying saved rebase plan")?;
+        let (event_tx_id, head_oid, head_reference_name, first_dest_oid, todo, preserve_timestamps) =
+            match rows.next().transpose()? {
+                Some(row) => row,
+                None => return Ok(None),
+            };
+
+        let head_oid: MaybeZeroOid = head_oid.parse().wrap_err("Parsing saved head OID")?;
+        Ok(Some(SavedRebasePlan {
+            event_tx_id: event_tx_id
+                .parse()
+                .wrap_err("Parsing saved event transaction ID")?,
+            head_info: ResolvedReferenceInfo {
+                oid: head_oid.into(),
+                reference_name: head_reference_name.map(ReferenceName::from),
+            },
+            first_dest_oid: first_dest_oid
+                .parse()
+                .wrap_err("Parsing saved first destination OID")?,
+            todo_lines: todo.lines().map(|line| line.to_owned()).collect(),
+            preserve_timestamps,
+        }))
+    }
+
+    /// Delete the plan for the on-disk rebase in the current worktree, if any.
+    #[instrument]
+    pub fn clear(&self, repo: &Repo) -> eyre::Result<()> {
+        self.conn
+            .execute(
+                "DELETE FROM saved_rebase_plans WHERE rebase_state_dir = ?",
+                rusqlite::params![get_key(repo)],
+            )
+            .wrap_err("Clearing saved rebase plan")?;
+        Ok(())
+    }
+}
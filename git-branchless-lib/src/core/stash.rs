@@ -0,0 +1,504 @@
+//! Persist the stack of working copy snapshots which were shelved with `git
+//! branchless stash push`, so that they can later be listed and restored.
+//!
+//! The contents of each stash are stored as a regular working copy snapshot
+//! (see `WorkingCopySnapshot`), which is also recorded in the event log. This
+//! database only keeps track of which snapshots are currently stashed.
+//!
+//! Stashes are also used to implement `--autostash` for commands which rewrite
+//! commits, so that they can be run with a dirty working copy.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use eyre::Context;
+use itertools::Itertools;
+use tracing::instrument;
+
+use crate::core::check_out::{create_snapshot, restore_snapshot, restore_untracked_files};
+use crate::core::effects::Effects;
+use crate::core::eventlog::{EventLogDb, EventTransactionId};
+use crate::core::gc::mark_commit_reachable;
+use crate::git::{
+    CherryPickFastOptions, CreateCommitFastError, GitRunInfo, GitRunResult, NonZeroOid, Repo,
+    WorkingCopyChangesType, WorkingCopySnapshot,
+};
+use crate::try_exit_code;
+use crate::util::{ExitCode, EyreExitOr};
+
+/// A working copy snapshot which has been stashed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StashEntry {
+    /// The OID of the base commit of the working copy snapshot.
+    pub snapshot_oid: NonZeroOid,
+
+    /// The user-provided description of the stash.
+    pub message: String,
+
+    /// When the stash was created.
+    pub timestamp: SystemTime,
+
+    /// The transaction ID of the `stash push` operation which created the
+    /// stash.
+    pub event_tx_id: EventTransactionId,
+}
+
+/// Stores the stack of stashed working copy snapshots.
+pub struct StashDb<'conn> {
+    conn: &'conn rusqlite::Connection,
+}
+
+impl std::fmt::Debug for StashDb<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<StashDb>")
+    }
+}
+
+#[instrument]
+fn init_tables(conn: &rusqlite::Connection) -> eyre::Result<()> {
+    conn.execute(
+        "
+CREATE TABLE IF NOT EXISTS stashes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    snapshot_oid TEXT NOT NULL,
+    message TEXT NOT NULL,
+    timestamp REAL NOT NULL,
+    event_tx_id TEXT NOT NULL
+)
+",
+        rusqlite::params![],
+    )
+    .wrap_err("Creating `stashes` table")?;
+
+    Ok(())
+}
+
+impl<'conn> StashDb<'conn> {
+    /// Constructor.
+    #[instrument]
+    pub fn new(conn: &'conn rusqlite::Connection) -> eyre::Result<Self> {
+        init_tables(conn)?;
+        Ok(StashDb { conn })
+    }
+
+    /// Add a stash to the top of the stack.
+    #[instrument]
+    pub fn push(&self, stash_entry: &StashEntry) -> eyre::Result<()> {
+        let StashEntry {
+            snapshot_oid,
+            message,
+            timestamp,
+            event_tx_id,
+        } = stash_entry;
+        let timestamp = timestamp
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_secs_f64();
+        self.conn
+            .execute(
+                "
+INSERT INTO stashes
+    (snapshot_oid, message, timestamp, event_tx_id)
+VALUES (?, ?, ?, ?)
+",
+                rusqlite::params![
+                    snapshot_oid.to_string(),
+                    message,
+                    timestamp,
+                    event_tx_id.to_string(),
+                ],
+            )
+            .wrap_err("Saving stash")?;
+        Ok(())
+    }
+
+    /// Get all stashes, with the most recently-pushed stash first. The index of
+    /// a stash in this list is used to refer to it as `stash@{<index>}`.
+    #[instrument]
+    pub fn list(&self) -> eyre::Result<Vec<StashEntry>> {
+        let mut stmt = self.conn.prepare(
+            "
+SELECT snapshot_oid, message, timestamp, event_tx_id
+FROM stashes
+ORDER BY id DESC
+",
+        )?;
+        let rows = stmt
+            .query_map(rusqlite::params![], |row| {
+                let snapshot_oid: String = row.get("snapshot_oid")?;
+                let message: String = row.get("message")?;
+                let timestamp: f64 = row.get("timestamp")?;
+                let event_tx_id: String = row.get("event_tx_id")?;
+                Ok((snapshot_oid, message, timestamp, event_tx_id))
+            })
+            .wrap_err("Querying stashes")?;
+
+        let mut result = Vec::new();
+        for row in rows {
+            let (snapshot_oid, message, timestamp, event_tx_id) = row?;
+            result.push(StashEntry {
+                snapshot_oid: snapshot_oid
+                    .parse()
+                    .wrap_err("Parsing stashed snapshot OID")?,
+                message,
+                timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs_f64(timestamp),
+                event_tx_id: event_tx_id
+                    .parse()
+                    .wrap_err("Parsing stash event transaction ID")?,
+            });
+        }
+        Ok(result)
+    }
+
+    /// Remove the stash at the given index (as returned by `list`), returning
+    /// it, or `None` if there is no such stash.
+    #[instrument]
+    pub fn remove(&self, index: usize) -> eyre::Result<Option<StashEntry>> {
+        let mut stmt = self.conn.prepare(
+            "
+SELECT id
+FROM stashes
+ORDER BY id DESC
+LIMIT 1 OFFSET ?
+",
+        )?;
+        let mut rows = stmt
+            .query_map(rusqlite::params![index], |row| {
+                let id: isize = row.get("id")?;
+                Ok(id)
+            })
+            .wrap_err("Querying stash")?;
+        let id = match rows.next().transpose()? {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+
+        let stash_entry = self.list()?.into_iter().nth(index);
+        self.conn
+            .execute("DELETE FROM stashes WHERE id = ?", rusqlite::params![id])
+            .wrap_err("Removing stash")?;
+        Ok(stash_entry)
+    }
+}
+
+/// Run a Git command without printing its output, and report an error if it
+/// fails.
+fn run_git_silent(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    event_tx_id: EventTransactionId,
+    args: &[&str],
+) -> EyreExitOr<()> {
+    let GitRunResult {
+        exit_code,
+        stdout: _,
+        stderr,
+    } = git_run_info.run_silent(repo, Some(event_tx_id), args, Default::default())?;
+    if exit_code.is_success() {
+        Ok(Ok(()))
+    } else {
+        write!(
+            effects.get_error_stream(),
+            "{}",
+            String::from_utf8_lossy(&stderr)
+        )?;
+        Ok(Err(exit_code))
+    }
+}
+
+/// Snapshot the working copy, add the snapshot to the top of the stash list,
+/// and reset the working copy to `HEAD`. If no message is provided, one is
+/// generated from the current branch and commit, like `git stash` does.
+///
+/// Returns `None` if there were no working copy changes to stash.
+pub fn push_stash(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    event_log_db: &EventLogDb,
+    event_tx_id: EventTransactionId,
+    now: SystemTime,
+    message: Option<String>,
+) -> EyreExitOr<Option<StashEntry>> {
+    let conn = repo.get_db_conn()?;
+    let stash_db = StashDb::new(&conn)?;
+
+    let head_info = repo.get_head_info()?;
+    let snapshot = create_snapshot(effects, git_run_info, repo, event_log_db, event_tx_id)?;
+    match snapshot.get_working_copy_changes_type()? {
+        WorkingCopyChangesType::None => return Ok(Ok(None)),
+        WorkingCopyChangesType::Conflicts => {
+            writeln!(
+                effects.get_output_stream(),
+                "Cannot stash, because there are unresolved merge conflicts. Resolve the merge conflicts and try again."
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+        WorkingCopyChangesType::Unstaged | WorkingCopyChangesType::Staged => {}
+    }
+
+    let message = match message {
+        Some(message) => message,
+        None => {
+            let branch_name = head_info.get_branch_name()?.unwrap_or("(no branch)");
+            match &snapshot.head_commit {
+                Some(head_commit) => format!(
+                    "WIP on {branch_name}: {} {}",
+                    head_commit.get_short_oid()?,
+                    String::from_utf8_lossy(&head_commit.get_summary()?)
+                ),
+                None => format!("WIP on {branch_name}"),
+            }
+        }
+    };
+
+    // Snapshot commits aren't otherwise referenced, so make sure that Git
+    // doesn't garbage-collect the stashed contents.
+    mark_commit_reachable(repo, snapshot.base_commit.get_oid())
+        .wrap_err("Marking commit as reachable for GC purposes.")?;
+    let stash_entry = StashEntry {
+        snapshot_oid: snapshot.base_commit.get_oid(),
+        message,
+        timestamp: now,
+        event_tx_id,
+    };
+    stash_db.push(&stash_entry)?;
+
+    try_exit_code!(run_git_silent(
+        effects,
+        git_run_info,
+        repo,
+        event_tx_id,
+        &["reset", "--hard", "HEAD", "--"],
+    )
+    .wrap_err("Discarding working copy")?);
+    Ok(Ok(Some(stash_entry)))
+}
+
+/// The reason that a stash couldn't be applied to the working copy.
+#[derive(Debug)]
+pub enum ApplyStashError {
+    /// `HEAD` has moved since the stash was created, and either it or the
+    /// stashed `HEAD` is on an unborn branch.
+    UnbornBranch,
+
+    /// The stashed changes conflict with the current `HEAD`.
+    MergeConflict {
+        /// The paths which had conflicts.
+        conflicting_paths: HashSet<PathBuf>,
+    },
+
+    /// A Git command failed while updating the working copy.
+    Failed {
+        /// The exit code of the failed command.
+        exit_code: ExitCode,
+    },
+}
+
+/// Apply a stashed snapshot to the working copy, which should be clean. If
+/// `HEAD` hasn't moved since the stash was created, the snapshot is restored
+/// exactly, including any staged changes. Otherwise, the stashed changes are
+/// merged onto the current `HEAD` and left unstaged.
+pub fn apply_stash(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    event_tx_id: EventTransactionId,
+    snapshot: &WorkingCopySnapshot,
+) -> eyre::Result<Result<(), ApplyStashError>> {
+    let head_info = repo.get_head_info()?;
+    let snapshot_head_oid = snapshot
+        .head_commit
+        .as_ref()
+        .map(|head_commit| head_commit.get_oid());
+    if snapshot_head_oid == head_info.oid
+        && snapshot.head_reference_name == head_info.reference_name
+    {
+        return match restore_snapshot(effects, git_run_info, repo, event_tx_id, snapshot)? {
+            Ok(()) => Ok(Ok(())),
+            Err(exit_code) => Ok(Err(ApplyStashError::Failed { exit_code })),
+        };
+    }
+
+    // Otherwise, merge the stashed changes onto the current `HEAD`. The
+    // unstaged changes commit contains all of the working copy contents, with
+    // the snapshot's `HEAD` commit as its parent.
+    let head_commit = match head_info.oid {
+        Some(head_oid) if snapshot.head_commit.is_some() => repo.find_commit_or_fail(head_oid)?,
+        _ => return Ok(Err(ApplyStashError::UnbornBranch)),
+    };
+    let merged_tree = match repo.cherry_pick_fast(
+        &snapshot.commit_unstaged,
+        &head_commit,
+        &CherryPickFastOptions {
+            reuse_parent_tree_if_possible: false,
+        },
+    ) {
+        Ok(merged_tree) => merged_tree,
+        Err(CreateCommitFastError::MergeConflict { conflicting_paths }) => {
+            return Ok(Err(ApplyStashError::MergeConflict { conflicting_paths }));
+        }
+        Err(err) => return Err(err.into()),
+    };
+    let merged_commit_oid = repo.create_commit(
+        None,
+        &head_commit.get_author(),
+        &head_commit.get_committer(),
+        "branchless: restored stash contents",
+        &merged_tree,
+        vec![&head_commit],
+    )?;
+
+    // Update the working copy (failing if that would overwrite untracked
+    // files), and then unstage the changes.
+    if let Err(exit_code) = run_git_silent(
+        effects,
+        git_run_info,
+        repo,
+        event_tx_id,
+        &[
+            "read-tree",
+            "-m",
+            "-u",
+            "HEAD",
+            &merged_commit_oid.to_string(),
+        ],
+    )
+    .wrap_err("Restoring stashed changes")?
+    {
+        return Ok(Err(ApplyStashError::Failed { exit_code }));
+    }
+    if let Err(exit_code) = run_git_silent(
+        effects,
+        git_run_info,
+        repo,
+        event_tx_id,
+        &["reset", "--quiet"],
+    )
+    .wrap_err("Unstaging stashed changes")?
+    {
+        return Ok(Err(ApplyStashError::Failed { exit_code }));
+    }
+    if let Some(commit_untracked) = &snapshot.commit_untracked {
+        restore_untracked_files(effects, repo, commit_untracked)?;
+    }
+    Ok(Ok(()))
+}
+
+/// If `enabled`, stash any uncommitted changes before calling `f`, and restore
+/// them afterwards, for commands which support `--autostash`. If `f` leaves an
+/// on-disk rebase in progress, or the changes can't be restored cleanly, the
+/// changes are kept in the stash list instead, so that they can be restored
+/// with `git branchless stash pop`.
+pub fn with_autostash<T>(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    event_log_db: &EventLogDb,
+    enabled: bool,
+    f: impl FnOnce() -> EyreExitOr<T>,
+) -> EyreExitOr<T> {
+    if !enabled {
+        return f();
+    }
+
+    let now = SystemTime::now();
+    let event_tx_id = event_log_db.make_transaction_id(now, "autostash")?;
+    let stash_entry = match push_stash(
+        effects,
+        git_run_info,
+        repo,
+        event_log_db,
+        event_tx_id,
+        now,
+        Some("autostash".to_string()),
+    )? {
+        Ok(Some(stash_entry)) => stash_entry,
+        Ok(None) => return f(),
+        Err(exit_code) => return Ok(Err(exit_code)),
+    };
+    writeln!(
+        effects.get_output_stream(),
+        "Autostashed uncommitted changes."
+    )?;
+
+    let result = f()?;
+    let restore_result =
+        restore_autostash(effects, git_run_info, repo, event_log_db, &stash_entry)?;
+    match (result, restore_result) {
+        (Ok(value), Ok(())) => Ok(Ok(value)),
+        (Err(exit_code), _) | (Ok(_), Err(exit_code)) => Ok(Err(exit_code)),
+    }
+}
+
+fn restore_autostash(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    event_log_db: &EventLogDb,
+    stash_entry: &StashEntry,
+) -> EyreExitOr<()> {
+    let conn = repo.get_db_conn()?;
+    let stash_db = StashDb::new(&conn)?;
+    let index = match stash_db
+        .list()?
+        .iter()
+        .position(|entry| entry.snapshot_oid == stash_entry.snapshot_oid)
+    {
+        Some(index) => index,
+        None => return Ok(Ok(())),
+    };
+
+    if let Some(operation_type) = repo.get_current_operation_type() {
+        writeln!(
+            effects.get_output_stream(),
+            "A {operation_type} operation is in progress, so the autostashed changes were kept in stash@{{{index}}}."
+        )?;
+        writeln!(
+            effects.get_output_stream(),
+            "Once it's finished, restore them with: git branchless stash pop {index}"
+        )?;
+        return Ok(Ok(()));
+    }
+
+    let event_tx_id = event_log_db.make_transaction_id(SystemTime::now(), "autostash restore")?;
+    let base_commit = repo.find_commit_or_fail(stash_entry.snapshot_oid)?;
+    let snapshot = match WorkingCopySnapshot::try_from_base_commit(repo, &base_commit)? {
+        Some(snapshot) => snapshot,
+        None => eyre::bail!(
+            "Stashed commit is not a working copy snapshot: {}",
+            stash_entry.snapshot_oid
+        ),
+    };
+    match apply_stash(effects, git_run_info, repo, event_tx_id, &snapshot)? {
+        Ok(()) => {
+            stash_db.remove(index)?;
+            writeln!(effects.get_output_stream(), "Restored autostashed changes.")?;
+            Ok(Ok(()))
+        }
+        Err(ApplyStashError::MergeConflict { conflicting_paths }) => {
+            writeln!(
+                effects.get_output_stream(),
+                "Couldn't restore the autostashed changes, because they conflict with HEAD in these paths:"
+            )?;
+            for path in conflicting_paths.into_iter().sorted() {
+                writeln!(effects.get_output_stream(), "  {}", path.display())?;
+            }
+            writeln!(
+                effects.get_output_stream(),
+                "They were kept in stash@{{{index}}}."
+            )?;
+            Ok(Err(ExitCode(1)))
+        }
+        Err(ApplyStashError::UnbornBranch) => {
+            writeln!(
+                effects.get_output_stream(),
+                "Couldn't restore the autostashed changes onto an unborn branch. They were kept in stash@{{{index}}}."
+            )?;
+            Ok(Err(ExitCode(1)))
+        }
+        Err(ApplyStashError::Failed { exit_code }) => Ok(Err(exit_code)),
+    }
+}
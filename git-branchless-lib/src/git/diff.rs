@@ -3,6 +3,8 @@ use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
+use cursive::theme::{BaseColor, Effect};
+use cursive::utils::markup::StyledString;
 use eyre::Context;
 use itertools::Itertools;
 use scm_record::helpers::make_binary_description;
@@ -15,6 +17,42 @@ pub struct Diff<'repo> {
     pub(super) inner: git2::Diff<'repo>,
 }
 
+impl Diff<'_> {
+    /// Render a summary of the number of lines changed in each file, in the
+    /// style of `git diff --stat`, wrapped to the given width.
+    pub fn render_stat(&self, width: usize) -> eyre::Result<String> {
+        let stats = self.inner.stats().wrap_err("Computing diff stats")?;
+        let buf = stats
+            .to_buf(git2::DiffStatsFormat::FULL, width)
+            .wrap_err("Rendering diff stats")?;
+        Ok(String::from_utf8_lossy(&buf).into_owned())
+    }
+
+    /// Render the full patch, in the style of `git diff`.
+    pub fn render_patch(&self) -> eyre::Result<StyledString> {
+        let mut result = StyledString::new();
+        self.inner
+            .print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
+                let content = String::from_utf8_lossy(line.content());
+                match line.origin() {
+                    origin @ ('+' | '>') => {
+                        result.append_styled(format!("{origin}{content}"), BaseColor::Green.light())
+                    }
+                    origin @ ('-' | '<') => {
+                        result.append_styled(format!("{origin}{content}"), BaseColor::Red.light())
+                    }
+                    ' ' => result.append_plain(format!(" {content}")),
+                    'F' => result.append_styled(content, Effect::Bold),
+                    'H' => result.append_styled(content, BaseColor::Cyan.light()),
+                    _ => result.append_plain(content),
+                }
+                true
+            })
+            .wrap_err("Rendering patch")?;
+        Ok(result)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 struct GitHunk {
     old_start: usize,
@@ -8,6 +8,7 @@ mod oid;
 mod reference;
 mod repo;
 mod run;
+mod sign;
 mod snapshot;
 mod status;
 mod test;
@@ -22,11 +23,13 @@ pub use reference::{
     Branch, BranchType, CategorizedReferenceName, Reference, ReferenceName, ReferenceTarget,
 };
 pub use repo::{
-    message_prettify, AmendFastOptions, CherryPickFastOptions, CreateCommitFastError,
-    Error as RepoError, GitErrorCode, GitVersion, PatchId, Repo, ResolvedReferenceInfo,
-    Result as RepoResult, Time,
+    message_add_trailers, message_prettify, AmendFastOptions, CherryPickFastOptions,
+    CreateCommitFastError, Error as RepoError, GitErrorCode, GitVersion, MergeFavor,
+    MergeTreesFastOptions, PatchId, Repo, ResolvedReferenceInfo, Result as RepoResult, Signature,
+    Time,
 };
 pub use run::{GitRunInfo, GitRunOpts, GitRunResult};
+pub use sign::{SignatureFormat, Signer};
 pub use snapshot::{WorkingCopyChangesType, WorkingCopySnapshot};
 pub use status::{FileMode, FileStatus, StatusEntry};
 pub use test::{
@@ -11,8 +11,8 @@ use crate::core::node_descriptors::{
     render_node_descriptors, CommitMessageDescriptor, CommitOidDescriptor, NodeObject, Redactor,
 };
 use crate::git::oid::make_non_zero_oid;
-use crate::git::repo::{Error, Result, Signature};
-use crate::git::{NonZeroOid, Time, Tree};
+use crate::git::repo::{create_signed_commit, Error, Repo, Result, Signature};
+use crate::git::{NonZeroOid, Signer, Time, Tree};
 
 use super::MaybeZeroOid;
 
@@ -34,15 +34,17 @@ impl<'repo> Commit<'repo> {
     /// Get the short object ID of the commit.
     #[instrument]
     pub fn get_short_oid(&self) -> Result<String> {
-        Ok(String::from_utf8_lossy(
-            &self
-                .inner
-                .clone()
-                .into_object()
-                .short_id()
-                .map_err(Error::Git)?,
-        )
-        .to_string())
+        match self.inner.clone().into_object().short_id() {
+            Ok(short_id) => Ok(String::from_utf8_lossy(&short_id).to_string()),
+            // Commits which only exist in an in-memory object database (such
+            // as for `git move --dry-run`) can't be looked up by prefix, so
+            // fall back to the default abbreviation length.
+            Err(err) if err.code() == git2::ErrorCode::NotFound => {
+                let oid = self.get_oid().to_string();
+                Ok(oid[..7].to_string())
+            }
+            Err(err) => Err(Error::Git(err)),
+        }
     }
 
     /// Get the object IDs of the parents of this commit.
@@ -266,6 +268,13 @@ impl<'repo> Commit<'repo> {
         }
     }
 
+    /// Determine if this commit carries a signature (GPG, SSH, or X.509). The
+    /// signature isn't checked; use `git verify-commit` for that.
+    pub fn is_signed(&self) -> bool {
+        self.inner.header_field_bytes("gpgsig").is_ok()
+            || self.inner.header_field_bytes("gpgsig-sha256").is_ok()
+    }
+
     /// Determine if this commit added, removed, or changed the entry at the
     /// provided file path.
     #[instrument]
@@ -313,6 +322,54 @@ impl<'repo> Commit<'repo> {
             .map_err(Error::Amend)?;
         Ok(make_non_zero_oid(oid))
     }
+
+    /// Like `amend_commit`, but sign the resulting commit with `signer` if
+    /// provided. No reference is updated to point to the new commit. `repo`
+    /// must be the repository which contains this commit.
+    #[instrument]
+    pub fn amend_commit_with_signer(
+        &self,
+        repo: &Repo,
+        author: Option<&Signature>,
+        committer: Option<&Signature>,
+        message: Option<&str>,
+        tree: Option<&Tree>,
+        signer: Option<&Signer>,
+    ) -> Result<NonZeroOid> {
+        let signer = match signer {
+            Some(signer) => signer,
+            None => return self.amend_commit(None, author, committer, message, tree),
+        };
+
+        let original_author = self.inner.author();
+        let original_committer = self.inner.committer();
+        let message = match message.or_else(|| self.inner.message_raw()) {
+            Some(message) => message,
+            None => {
+                return Err(Error::SignCommit {
+                    source: eyre::eyre!("commit message for {} is not valid UTF-8", self.get_oid()),
+                })
+            }
+        };
+        let original_tree;
+        let tree = match tree {
+            Some(tree) => tree,
+            None => {
+                original_tree = self.get_tree()?;
+                &original_tree
+            }
+        };
+        let parents: Vec<git2::Commit> = self.inner.parents().collect();
+        create_signed_commit(
+            &repo.inner,
+            author.map_or(&original_author, |author| &author.inner),
+            committer.map_or(&original_committer, |committer| &committer.inner),
+            message,
+            &tree.inner,
+            parents.iter().collect::<Vec<_>>().as_slice(),
+            signer,
+        )
+    }
 }
 
 pub struct Blob<'repo> {
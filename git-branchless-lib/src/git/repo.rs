@@ -27,6 +27,7 @@ use itertools::Itertools;
 use thiserror::Error;
 use tracing::{instrument, warn};
 
+use crate::core::config::get_snapshot_include_untracked;
 use crate::core::effects::{Effects, OperationType};
 use crate::core::eventlog::EventTransactionId;
 use crate::core::formatting::Glyphs;
@@ -39,10 +40,15 @@ use crate::git::tree::{dehydrate_tree, get_changed_paths_between_trees, hydrate_
 use crate::git::{Branch, BranchType, Commit, Reference, ReferenceName};
 
 use super::index::{Index, IndexEntry};
+use super::sign::Signer;
 use super::snapshot::WorkingCopySnapshot;
 use super::status::FileMode;
 use super::{tree, Diff, StatusEntry};
 
+/// How long to wait for another process to release its lock on the SQLite
+/// database before giving up.
+const DB_BUSY_TIMEOUT: Duration = Duration::from_secs(30);
+
 #[allow(missing_docs)]
 #[derive(Debug, Error)]
 pub enum Error {
@@ -55,6 +61,9 @@ pub enum Error {
     #[error("could not open repository: {0}")]
     UnsupportedExtensionWorktreeConfig(#[source] git2::Error),
 
+    #[error("could not set up in-memory object database: {0}")]
+    AddMempackBackend(#[source] git2::Error),
+
     #[error("could not read index: {0}")]
     ReadIndex(#[source] git2::Error),
 
@@ -110,6 +119,9 @@ pub enum Error {
     #[error("could not create commit: {0}")]
     CreateCommit(#[source] git2::Error),
 
+    #[error("could not sign commit: {source}")]
+    SignCommit { source: eyre::Error },
+
     #[error("could not cherry-pick commit {commit} onto {onto}: {source}")]
     CherryPickCommit {
         source: git2::Error,
@@ -117,6 +129,13 @@ pub enum Error {
         onto: NonZeroOid,
     },
 
+    #[error("could not merge tree {theirs} into {ours}: {source}")]
+    MergeTrees {
+        source: git2::Error,
+        ours: NonZeroOid,
+        theirs: NonZeroOid,
+    },
+
     #[error("could not fast-cherry-pick commit {commit} onto {onto}: {source}")]
     CherryPickFast {
         source: git2::Error,
@@ -308,6 +327,72 @@ pub fn message_prettify(message: &str, comment_char: Option<char>) -> Result<Str
     Ok(message)
 }
 
+/// Add the given trailers (such as `Signed-off-by: Name <email>`) to the end of
+/// a commit message, skipping any which are already present in the message.
+/// The trailers are appended to the message's trailer block if it already has
+/// one, and otherwise are added as a new paragraph. An empty message is
+/// returned unchanged, so that it can still be used to abort the operation.
+pub fn message_add_trailers(message: &str, trailers: &[String]) -> String {
+    fn is_trailer_line(line: &str) -> bool {
+        match line.split_once(": ") {
+            Some((key, _value)) => {
+                !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            }
+            None => false,
+        }
+    }
+
+    let trimmed_message = message.trim_end();
+    if trimmed_message.is_empty() {
+        return message.to_owned();
+    }
+    let existing_lines: HashSet<&str> = trimmed_message.lines().map(str::trim).collect();
+    let new_trailers = trailers
+        .iter()
+        .map(|trailer| trailer.trim())
+        .filter(|trailer| !existing_lines.contains(trailer))
+        .unique()
+        .collect_vec();
+    if new_trailers.is_empty() {
+        return message.to_owned();
+    }
+
+    let has_trailer_block = match trimmed_message.rsplit_once("\n\n") {
+        Some((_body, last_paragraph)) => last_paragraph.lines().all(is_trailer_line),
+        None => false,
+    };
+    let separator = if has_trailer_block { "\n" } else { "\n\n" };
+    format!(
+        "{trimmed_message}{separator}{}\n",
+        new_trailers.into_iter().join("\n")
+    )
+}
+
+/// Create a commit whose contents are signed by `signer`, and return its OID.
+pub(super) fn create_signed_commit(
+    repo: &git2::Repository,
+    author: &git2::Signature,
+    committer: &git2::Signature,
+    message: &str,
+    tree: &git2::Tree,
+    parents: &[&git2::Commit],
+    signer: &Signer,
+) -> Result<NonZeroOid> {
+    let buffer = repo
+        .commit_create_buffer(author, committer, message, tree, parents)
+        .map_err(Error::CreateCommit)?;
+    let buffer = buffer.as_str().ok_or_else(|| Error::SignCommit {
+        source: eyre::eyre!("commit contents are not valid UTF-8"),
+    })?;
+    let signature = signer
+        .sign(buffer.as_bytes())
+        .map_err(|source| Error::SignCommit { source })?;
+    let oid = repo
+        .commit_signed(buffer, &signature, None)
+        .map_err(Error::CreateCommit)?;
+    Ok(make_non_zero_oid(oid))
+}
+
 /// A snapshot of information about a certain reference. Updates to the
 /// reference after this value is obtained are not reflected.
 ///
@@ -394,6 +479,27 @@ pub struct CherryPickFastOptions {
     pub reuse_parent_tree_if_possible: bool,
 }
 
+/// Which side of a merge to take when the same hunk was changed on both sides.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeFavor {
+    /// Take the hunk from the tree being merged into.
+    Ours,
+
+    /// Take the hunk from the tree being merged in.
+    Theirs,
+}
+
+/// Options for `Repo::merge_trees_fast`.
+#[derive(Clone, Debug, Default)]
+pub struct MergeTreesFastOptions {
+    /// If set, conflicting hunks are resolved by taking that side's version
+    /// instead of failing with a merge conflict. Binary files are taken whole
+    /// from that side. Conflicts which don't involve file contents, such as a
+    /// file being modified on one side and deleted on the other, are still
+    /// reported as merge conflicts.
+    pub favor: Option<MergeFavor>,
+}
+
 /// An error raised when attempting to create create a commit via
 /// `Repo::cherry_pick_fast`.
 #[allow(missing_docs)]
@@ -419,6 +525,15 @@ pub enum CreateCommitFastError {
         item: &'static str,
     },
 
+    #[error(
+        "merge commit {commit} has {expected} parents, but {actual} new parents were provided"
+    )]
+    ParentCountMismatch {
+        commit: NonZeroOid,
+        expected: usize,
+        actual: usize,
+    },
+
     #[error(transparent)]
     HydrateTree(tree::Error),
 
@@ -505,6 +620,21 @@ impl Repo {
         Ok(Repo { inner: repo })
     }
 
+    /// Open a new copy of the repository in which newly-created objects are
+    /// only kept in memory, rather than being written to the object database on
+    /// disk. Existing objects can still be read as usual.
+    #[instrument]
+    pub fn try_clone_in_memory(&self) -> Result<Self> {
+        let repo = self.try_clone()?;
+        {
+            let odb = repo.inner.odb().map_err(Error::AddMempackBackend)?;
+            // The backend with the highest priority is used for writing objects.
+            odb.add_new_mempack_backend(1000)
+                .map_err(Error::AddMempackBackend)?;
+        }
+        Ok(repo)
+    }
+
     /// Get the path to the `.git` directory for the repository.
     pub fn get_path(&self) -> &Path {
         self.inner.path()
@@ -595,6 +725,21 @@ impl Repo {
         Ok(Some(parent_repo))
     }
 
+    /// If this repository is a secondary worktree for another "parent"
+    /// repository, return the name of the worktree. This is the name which Git
+    /// uses to refer to its per-worktree references, such as
+    /// `worktrees/<name>/HEAD`.
+    #[instrument]
+    pub fn get_worktree_name(&self) -> Option<String> {
+        if !self.inner.is_worktree() {
+            return None;
+        }
+        let worktree_info_dir = self.get_path();
+        worktree_info_dir
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+    }
+
     /// Get the configuration object for the repository.
     ///
     /// **Warning**: This object should only be used for read operations. Write
@@ -606,6 +751,14 @@ impl Repo {
         Ok(Config::from(config))
     }
 
+    /// Get a signature for the current user, as configured by `user.name` and
+    /// `user.email`, with the current time.
+    #[instrument]
+    pub fn get_current_user_signature(&self) -> Result<Signature<'static>> {
+        let signature = self.inner.signature().map_err(Error::CreateSignature)?;
+        Ok(Signature { inner: signature })
+    }
+
     /// Get the directory where all repo-specific git-branchless state is stored.
     pub fn get_branchless_dir(&self) -> Result<PathBuf> {
         let maybe_worktree_parent_repo = self.open_worktree_parent_repo()?;
@@ -663,6 +816,13 @@ impl Repo {
             source: err,
             path: path.clone(),
         })?;
+        // The database is shared between all worktrees of the repository, so
+        // wait for writers in other worktrees to finish rather than failing.
+        conn.busy_timeout(DB_BUSY_TIMEOUT)
+            .map_err(|err| Error::OpenDatabase {
+                source: err,
+                path: path.clone(),
+            })?;
         Ok(conn)
     }
 
@@ -989,11 +1149,22 @@ impl Repo {
         let (effects, _progress) = effects.start_operation(OperationType::QueryWorkingCopy);
         let _effects = effects;
 
+        let include_untracked =
+            get_snapshot_include_untracked(self).map_err(Error::CreateSnapshot)?;
         let output = git_run_info
             .run_silent(
                 self,
                 event_tx_id,
-                &["status", "--porcelain=v2", "--untracked-files=no", "-z"],
+                &[
+                    "status",
+                    "--porcelain=v2",
+                    if include_untracked {
+                        "--untracked-files=all"
+                    } else {
+                        "--untracked-files=no"
+                    },
+                    "-z",
+                ],
                 Default::default(),
             )
             .map_err(Error::ExecGit)?
@@ -1001,6 +1172,7 @@ impl Repo {
 
         let not_null_terminator = |c: &u8| *c != 0_u8;
         let mut statuses = Vec::new();
+        let mut untracked_paths = Vec::new();
         let mut status_bytes = output.into_iter().peekable();
 
         // Iterate over the status entries in the output.
@@ -1028,6 +1200,23 @@ impl Repo {
                     line.extend(status_bytes.by_ref().take_while(not_null_terminator));
                     line
                 }
+                // Untracked entry. These are only requested when they should
+                // be included in the snapshot, and aren't reported as part of
+                // the status entries.
+                b'?' => {
+                    let line = status_bytes
+                        .by_ref()
+                        .take_while(not_null_terminator)
+                        .collect_vec();
+                    let path = line
+                        .get(2..)
+                        .unwrap_or_default()
+                        .to_vec()
+                        .into_path_buf()
+                        .map_err(|err| Error::ParseStatusEntry(err.into()))?;
+                    untracked_paths.push(path);
+                    continue;
+                }
                 // Skip header lines
                 b'#' => continue,
                 _ => {
@@ -1043,8 +1232,18 @@ impl Repo {
             statuses.push(entry);
         }
 
-        let snapshot = WorkingCopySnapshot::create(self, index, head_info, &statuses)
-            .map_err(Error::CreateSnapshot)?;
+        let snapshot = WorkingCopySnapshot::create(
+            self,
+            index,
+            head_info,
+            &statuses,
+            if include_untracked {
+                Some(untracked_paths.as_slice())
+            } else {
+                None
+            },
+        )
+        .map_err(Error::CreateSnapshot)?;
         Ok((snapshot, statuses))
     }
 
@@ -1279,6 +1478,39 @@ impl Repo {
         Ok(make_non_zero_oid(oid))
     }
 
+    /// Create a new commit, signing it with `signer` if provided. Unlike
+    /// `create_commit`, this doesn't update any reference to point to the new
+    /// commit.
+    #[instrument]
+    pub fn create_commit_with_signer(
+        &self,
+        author: &Signature,
+        committer: &Signature,
+        message: &str,
+        tree: &Tree,
+        parents: Vec<&Commit>,
+        signer: Option<&Signer>,
+    ) -> Result<NonZeroOid> {
+        match signer {
+            None => self.create_commit(None, author, committer, message, tree, parents),
+            Some(signer) => {
+                let parents = parents
+                    .iter()
+                    .map(|commit| &commit.inner)
+                    .collect::<Vec<_>>();
+                create_signed_commit(
+                    &self.inner,
+                    &author.inner,
+                    &committer.inner,
+                    message,
+                    &tree.inner,
+                    parents.as_slice(),
+                    signer,
+                )
+            }
+        }
+    }
+
     /// Cherry-pick a commit in memory and return the resulting index.
     #[instrument]
     pub fn cherry_pick_commit(
@@ -1343,90 +1575,288 @@ impl Repo {
 
         let rebased_index =
             self.cherry_pick_commit(&dehydrated_patch_commit, &dehydrated_target_commit, 0)?;
-        let rebased_tree = {
-            if rebased_index.has_conflicts() {
-                let conflicting_paths = {
-                    let mut result = HashSet::new();
-                    for conflict in rebased_index.inner.conflicts().map_err(|err| {
-                        CreateCommitFastError::GetConflicts {
-                            source: err,
-                            commit: patch_commit.get_oid(),
-                            onto: target_commit.get_oid(),
-                        }
-                    })? {
-                        let conflict =
-                            conflict.map_err(|err| CreateCommitFastError::GetConflicts {
+        let rebased_tree_oid = self.hydrate_merged_index(
+            &rebased_index,
+            changed_pathbufs,
+            &target_commit.get_tree()?,
+            patch_commit.get_oid(),
+            target_commit.get_oid(),
+        )?;
+        Ok(self.find_tree_or_fail(rebased_tree_oid)?)
+    }
+
+    /// Check the result of an in-memory merge of the dehydrated versions of
+    /// `changed_pathbufs` for conflicts, and, if there were none, add back the
+    /// unchanged entries from `target_tree` and return the OID of the
+    /// resulting tree. `commit` and `onto` identify the objects being merged
+    /// for error messages.
+    #[instrument]
+    fn hydrate_merged_index(
+        &self,
+        merged_index: &Index,
+        changed_pathbufs: Vec<PathBuf>,
+        target_tree: &Tree,
+        commit: NonZeroOid,
+        onto: NonZeroOid,
+    ) -> std::result::Result<NonZeroOid, CreateCommitFastError> {
+        if merged_index.has_conflicts() {
+            let conflicting_paths = {
+                let mut result = HashSet::new();
+                for conflict in merged_index.inner.conflicts().map_err(|err| {
+                    CreateCommitFastError::GetConflicts {
+                        source: err,
+                        commit,
+                        onto,
+                    }
+                })? {
+                    let conflict = conflict.map_err(|err| CreateCommitFastError::GetConflicts {
+                        source: err,
+                        commit,
+                        onto,
+                    })?;
+                    if let Some(ancestor) = conflict.ancestor {
+                        result.insert(ancestor.path.into_path_buf().map_err(|err| {
+                            CreateCommitFastError::DecodePath {
                                 source: err,
-                                commit: patch_commit.get_oid(),
-                                onto: target_commit.get_oid(),
-                            })?;
-                        if let Some(ancestor) = conflict.ancestor {
-                            result.insert(ancestor.path.into_path_buf().map_err(|err| {
-                                CreateCommitFastError::DecodePath {
-                                    source: err,
-                                    item: "ancestor",
-                                }
-                            })?);
-                        }
-                        if let Some(our) = conflict.our {
-                            result.insert(our.path.into_path_buf().map_err(|err| {
-                                CreateCommitFastError::DecodePath {
-                                    source: err,
-                                    item: "our",
-                                }
-                            })?);
-                        }
-                        if let Some(their) = conflict.their {
-                            result.insert(their.path.into_path_buf().map_err(|err| {
-                                CreateCommitFastError::DecodePath {
-                                    source: err,
-                                    item: "their",
-                                }
-                            })?);
-                        }
+                                item: "ancestor",
+                            }
+                        })?);
+                    }
+                    if let Some(our) = conflict.our {
+                        result.insert(our.path.into_path_buf().map_err(|err| {
+                            CreateCommitFastError::DecodePath {
+                                source: err,
+                                item: "our",
+                            }
+                        })?);
                     }
-                    result
+                    if let Some(their) = conflict.their {
+                        result.insert(their.path.into_path_buf().map_err(|err| {
+                            CreateCommitFastError::DecodePath {
+                                source: err,
+                                item: "their",
+                            }
+                        })?);
+                    }
+                }
+                result
+            };
+
+            if conflicting_paths.is_empty() {
+                warn!("BUG: A merge conflict was detected, but there were no entries in `conflicting_paths`. Maybe the wrong index entry was used?")
+            }
+
+            return Err(CreateCommitFastError::MergeConflict { conflicting_paths });
+        }
+        let merged_entries: HashMap<PathBuf, Option<(NonZeroOid, FileMode)>> = changed_pathbufs
+            .into_iter()
+            .map(|changed_path| {
+                let value = match merged_index.get_entry(&changed_path) {
+                    Some(IndexEntry {
+                        oid: MaybeZeroOid::Zero,
+                        file_mode: _,
+                    }) => {
+                        warn!(
+                            ?commit,
+                            ?changed_path,
+                            "BUG: index entry was zero. \
+                            This probably indicates that a removed path \
+                            was not handled correctly."
+                        );
+                        None
+                    }
+                    Some(IndexEntry {
+                        oid: MaybeZeroOid::NonZero(oid),
+                        file_mode,
+                    }) => Some((oid, file_mode)),
+                    None => None,
                 };
+                (changed_path, value)
+            })
+            .collect();
+        let merged_tree_oid = hydrate_tree(self, Some(target_tree), merged_entries)
+            .map_err(CreateCommitFastError::HydrateTree)?;
+        Ok(merged_tree_oid)
+    }
 
-                if conflicting_paths.is_empty() {
-                    warn!("BUG: A merge conflict was detected, but there were no entries in `conflicting_paths`. Maybe the wrong index entry was used?")
+    /// Rebase a merge commit in memory onto the given new parents and return
+    /// the resulting tree.
+    ///
+    /// For a two-parent merge, the original parents and the new parents are
+    /// each merged automatically, and the difference between the original
+    /// automatic merge and the original merge commit (i.e. any conflict
+    /// resolutions or other changes made as part of the merge) is applied on
+    /// top of the new automatic merge. If either automatic merge conflicts,
+    /// or for octopus merges, the changes between each original parent and its
+    /// replacement are instead applied to the original merge commit's tree in
+    /// turn.
+    #[instrument]
+    pub fn rebase_merge_fast(
+        &self,
+        merge_commit: &Commit,
+        new_parent_oids: &[NonZeroOid],
+    ) -> std::result::Result<Tree, CreateCommitFastError> {
+        let original_parent_oids = merge_commit.get_parent_oids();
+        if original_parent_oids.len() != new_parent_oids.len() {
+            return Err(CreateCommitFastError::ParentCountMismatch {
+                commit: merge_commit.get_oid(),
+                expected: original_parent_oids.len(),
+                actual: new_parent_oids.len(),
+            });
+        }
+
+        let original_tree_oid = merge_commit.get_tree()?.get_oid();
+        if original_parent_oids.as_slice() == new_parent_oids {
+            return Ok(self.find_tree_or_fail(original_tree_oid)?);
+        }
+
+        if let ([lhs_oid, rhs_oid], [new_lhs_oid, new_rhs_oid]) =
+            (original_parent_oids.as_slice(), new_parent_oids)
+        {
+            let original_auto_merge_tree_oid = self.auto_merge_tree_fast(*lhs_oid, *rhs_oid);
+            let new_auto_merge_tree_oid = self.auto_merge_tree_fast(*new_lhs_oid, *new_rhs_oid);
+            match (original_auto_merge_tree_oid, new_auto_merge_tree_oid) {
+                (Ok(Some(original_auto_merge_tree_oid)), Ok(Some(new_auto_merge_tree_oid))) => {
+                    let tree_oid = self.merge_trees_fast(
+                        original_auto_merge_tree_oid,
+                        new_auto_merge_tree_oid,
+                        original_tree_oid,
+                        &MergeTreesFastOptions::default(),
+                    )?;
+                    return Ok(self.find_tree_or_fail(tree_oid)?);
                 }
+                (Err(CreateCommitFastError::MergeConflict { .. }) | Ok(None), _)
+                | (_, Err(CreateCommitFastError::MergeConflict { .. }) | Ok(None)) => {
+                    // Fall back to applying each parent's changes in turn.
+                }
+                (Err(err), _) | (_, Err(err)) => return Err(err),
+            }
+        }
 
-                return Err(CreateCommitFastError::MergeConflict { conflicting_paths });
+        let mut tree_oid = original_tree_oid;
+        for (original_parent_oid, new_parent_oid) in original_parent_oids
+            .into_iter()
+            .zip(new_parent_oids.iter().copied())
+        {
+            if original_parent_oid == new_parent_oid {
+                continue;
             }
-            let rebased_entries: HashMap<PathBuf, Option<(NonZeroOid, FileMode)>> =
-                changed_pathbufs
-                    .into_iter()
-                    .map(|changed_path| {
-                        let value = match rebased_index.get_entry(&changed_path) {
-                            Some(IndexEntry {
-                                oid: MaybeZeroOid::Zero,
-                                file_mode: _,
-                            }) => {
-                                warn!(
-                                    ?patch_commit,
-                                    ?changed_path,
-                                    "BUG: index entry was zero. \
-                                This probably indicates that a removed path \
-                                was not handled correctly."
-                                );
-                                None
-                            }
-                            Some(IndexEntry {
-                                oid: MaybeZeroOid::NonZero(oid),
-                                file_mode,
-                            }) => Some((oid, file_mode)),
-                            None => None,
-                        };
-                        (changed_path, value)
-                    })
-                    .collect();
-            let rebased_tree_oid =
-                hydrate_tree(self, Some(&target_commit.get_tree()?), rebased_entries)
-                    .map_err(CreateCommitFastError::HydrateTree)?;
-            self.find_tree_or_fail(rebased_tree_oid)?
+            let original_parent_tree_oid = self
+                .find_commit_or_fail(original_parent_oid)?
+                .get_tree()?
+                .get_oid();
+            let new_parent_tree_oid = self
+                .find_commit_or_fail(new_parent_oid)?
+                .get_tree()?
+                .get_oid();
+            tree_oid = self.merge_trees_fast(
+                original_parent_tree_oid,
+                tree_oid,
+                new_parent_tree_oid,
+                &MergeTreesFastOptions::default(),
+            )?;
+        }
+        Ok(self.find_tree_or_fail(tree_oid)?)
+    }
+
+    /// Merge the two given commits in memory using their merge base, and
+    /// return the OID of the resulting tree. Returns `None` if the commits
+    /// have no merge base.
+    #[instrument]
+    pub fn auto_merge_tree_fast(
+        &self,
+        lhs_oid: NonZeroOid,
+        rhs_oid: NonZeroOid,
+    ) -> std::result::Result<Option<NonZeroOid>, CreateCommitFastError> {
+        let merge_base_oid = match self.find_merge_base(lhs_oid, rhs_oid)? {
+            Some(merge_base_oid) => merge_base_oid,
+            None => return Ok(None),
         };
-        Ok(rebased_tree)
+        let merge_base_tree_oid = self
+            .find_commit_or_fail(merge_base_oid)?
+            .get_tree()?
+            .get_oid();
+        let lhs_tree_oid = self.find_commit_or_fail(lhs_oid)?.get_tree()?.get_oid();
+        let rhs_tree_oid = self.find_commit_or_fail(rhs_oid)?.get_tree()?.get_oid();
+        let tree_oid = self.merge_trees_fast(
+            merge_base_tree_oid,
+            lhs_tree_oid,
+            rhs_tree_oid,
+            &MergeTreesFastOptions::default(),
+        )?;
+        Ok(Some(tree_oid))
+    }
+
+    /// Apply the changes between `base_tree_oid` and `theirs_tree_oid` to
+    /// `ours_tree_oid` in memory, and return the OID of the resulting tree.
+    ///
+    /// As with `cherry_pick_fast`, only the paths which differ between the
+    /// base and `theirs` trees are passed to `libgit2`; every other entry is
+    /// taken from `ours`.
+    #[instrument]
+    pub fn merge_trees_fast(
+        &self,
+        base_tree_oid: NonZeroOid,
+        ours_tree_oid: NonZeroOid,
+        theirs_tree_oid: NonZeroOid,
+        options: &MergeTreesFastOptions,
+    ) -> std::result::Result<NonZeroOid, CreateCommitFastError> {
+        let MergeTreesFastOptions { favor } = options;
+
+        let base_tree = self.find_tree_or_fail(base_tree_oid)?;
+        let ours_tree = self.find_tree_or_fail(ours_tree_oid)?;
+        let theirs_tree = self.find_tree_or_fail(theirs_tree_oid)?;
+        let changed_pathbufs =
+            get_changed_paths_between_trees(self, Some(&base_tree), Some(&theirs_tree))
+                .map_err(Error::GetChangedPaths)?
+                .into_iter()
+                .collect_vec();
+        if changed_pathbufs.is_empty() {
+            return Ok(ours_tree_oid);
+        }
+        let changed_paths = changed_pathbufs.iter().map(PathBuf::borrow).collect_vec();
+
+        let dehydrate = |tree: &Tree| -> Result<Tree> {
+            let dehydrated_tree_oid = dehydrate_tree(self, tree, changed_paths.as_slice())
+                .map_err(Error::DehydrateTree)?;
+            self.find_tree_or_fail(dehydrated_tree_oid)
+        };
+        let dehydrated_base_tree = dehydrate(&base_tree)?;
+        let dehydrated_ours_tree = dehydrate(&ours_tree)?;
+        let dehydrated_theirs_tree = dehydrate(&theirs_tree)?;
+
+        let mut merge_options = git2::MergeOptions::new();
+        match favor {
+            None => {}
+            Some(MergeFavor::Ours) => {
+                merge_options.file_favor(git2::FileFavor::Ours);
+            }
+            Some(MergeFavor::Theirs) => {
+                merge_options.file_favor(git2::FileFavor::Theirs);
+            }
+        }
+        let merged_index = self
+            .inner
+            .merge_trees(
+                &dehydrated_base_tree.inner,
+                &dehydrated_ours_tree.inner,
+                &dehydrated_theirs_tree.inner,
+                Some(&merge_options),
+            )
+            .map_err(|err| Error::MergeTrees {
+                source: err,
+                ours: ours_tree_oid,
+                theirs: theirs_tree_oid,
+            })?;
+        self.hydrate_merged_index(
+            &Index {
+                inner: merged_index,
+            },
+            changed_pathbufs,
+            &ours_tree,
+            theirs_tree_oid,
+            ours_tree_oid,
+        )
     }
 
     #[instrument]
@@ -1645,6 +2075,7 @@ impl std::fmt::Debug for Signature<'_> {
 }
 
 impl<'repo> Signature<'repo> {
+    /// Create the signature used for commits made automatically by git-branchless.
     #[instrument]
     pub fn automated() -> Result<Self> {
         Ok(Signature {
@@ -1687,6 +2118,13 @@ impl<'repo> Signature<'repo> {
         Ok(Signature { inner: signature })
     }
 
+    /// Make a copy of this signature which doesn't borrow from the repository.
+    pub fn to_owned(&self) -> Signature<'static> {
+        Signature {
+            inner: self.inner.to_owned(),
+        }
+    }
+
     /// Get the time when this signature was applied.
     pub fn get_time(&self) -> Time {
         Time {
@@ -1694,10 +2132,12 @@ impl<'repo> Signature<'repo> {
         }
     }
 
+    /// Get the name of the person who made this signature, if it is valid UTF-8.
     pub fn get_name(&self) -> Option<&str> {
         self.inner.name()
     }
 
+    /// Get the email of the person who made this signature, if it is valid UTF-8.
     pub fn get_email(&self) -> Option<&str> {
         self.inner.email()
     }
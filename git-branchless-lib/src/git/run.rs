@@ -12,6 +12,7 @@ use eyre::{eyre, Context};
 use itertools::Itertools;
 use tracing::{instrument, warn};
 
+use crate::core::config::env_vars::{get_git_exec_path, get_path_to_git};
 use crate::core::config::get_main_worktree_hooks_dir;
 use crate::core::effects::{Effects, OperationType};
 use crate::core::eventlog::{EventTransactionId, BRANCHLESS_TRANSACTION_ID_ENV_VAR};
@@ -86,6 +87,24 @@ impl std::fmt::Debug for GitRunResult {
 }
 
 impl GitRunInfo {
+    /// Construct the `GitRunInfo` for the current process, which runs Git in
+    /// the current working directory with the current environment.
+    pub fn from_env() -> eyre::Result<Self> {
+        let path_to_git = get_path_to_git().unwrap_or_else(|_| PathBuf::from("git"));
+        let working_directory =
+            std::env::current_dir().wrap_err("Getting current working directory")?;
+        let mut env: HashMap<OsString, OsString> = std::env::vars_os().collect();
+        if let Ok(git_exec_path) = get_git_exec_path() {
+            env.entry("GIT_EXEC_PATH".into())
+                .or_insert(git_exec_path.into());
+        }
+        Ok(GitRunInfo {
+            path_to_git,
+            working_directory,
+            env,
+        })
+    }
+
     fn spawn_writer_thread<
         InputStream: Read + Send + 'static,
         OutputStream: Write + Send + 'static,
@@ -0,0 +1,181 @@
+//! Signing of commits which are created directly by git-branchless (such as
+//! during an in-memory rebase), rather than by invoking `git commit`.
+//!
+//! This honors the same configuration as Git itself: `commit.gpgSign`,
+//! `gpg.format`, `gpg.program`/`gpg.<format>.program`, and
+//! `user.signingKey`.
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use eyre::Context;
+use tracing::instrument;
+
+use super::{ConfigRead, Repo};
+
+/// The kind of signature to produce. Corresponds to Git's `gpg.format`
+/// setting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignatureFormat {
+    /// Sign with GnuPG (the default).
+    OpenPgp,
+
+    /// Sign with an X.509 certificate via `gpgsm`.
+    X509,
+
+    /// Sign with an SSH key via `ssh-keygen`.
+    Ssh,
+}
+
+/// Produces signatures for commits according to the user's Git
+/// configuration.
+#[derive(Clone, Debug)]
+pub struct Signer {
+    format: SignatureFormat,
+    program: String,
+    key: Option<String>,
+}
+
+impl Signer {
+    /// Construct a signer from the repository configuration. Returns `None` if
+    /// commits should not be signed, i.e. if `commit.gpgSign` is not set.
+    #[instrument]
+    pub fn from_config(repo: &Repo) -> eyre::Result<Option<Self>> {
+        let config = repo.get_readonly_config()?;
+        if !config.get_or("commit.gpgSign", false)? {
+            return Ok(None);
+        }
+
+        let format: String = config.get_or_else("gpg.format", || "openpgp".to_string())?;
+        let (format, format_name, default_program) = match format.as_str() {
+            "openpgp" => (SignatureFormat::OpenPgp, "openpgp", "gpg"),
+            "x509" => (SignatureFormat::X509, "x509", "gpgsm"),
+            "ssh" => (SignatureFormat::Ssh, "ssh", "ssh-keygen"),
+            other => eyre::bail!("Unsupported value for gpg.format: {other}"),
+        };
+
+        let program: Option<String> = config.get(format!("gpg.{format_name}.program"))?;
+        let program = match program {
+            Some(program) => program,
+            None if format == SignatureFormat::OpenPgp => {
+                config.get_or_else("gpg.program", || default_program.to_string())?
+            }
+            None => default_program.to_string(),
+        };
+
+        let key: Option<String> = config.get("user.signingKey")?;
+        if format == SignatureFormat::Ssh && key.is_none() {
+            eyre::bail!("user.signingKey must be set to sign commits when gpg.format is ssh");
+        }
+
+        Ok(Some(Self {
+            format,
+            program,
+            key,
+        }))
+    }
+
+    /// Sign the given commit contents, returning the ASCII-armored signature
+    /// to store in the commit's `gpgsig` header.
+    #[instrument]
+    pub fn sign(&self, buffer: &[u8]) -> eyre::Result<String> {
+        match self.format {
+            SignatureFormat::OpenPgp | SignatureFormat::X509 => self.sign_gpg(buffer),
+            SignatureFormat::Ssh => self.sign_ssh(buffer),
+        }
+    }
+
+    fn sign_gpg(&self, buffer: &[u8]) -> eyre::Result<String> {
+        let mut command = Command::new(&self.program);
+        command.arg("--status-fd=2");
+        match &self.key {
+            Some(key) => command.args(["-bsau", key.as_str()]),
+            None => command.arg("-bsa"),
+        };
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .wrap_err_with(|| format!("Spawning signing program: {}", self.program))?;
+        child
+            .stdin
+            .take()
+            .expect("stdin should have been piped")
+            .write_all(buffer)
+            .wrap_err("Writing commit contents to signing program")?;
+        let output = child
+            .wait_with_output()
+            .wrap_err("Waiting for signing program")?;
+        if !output.status.success() {
+            eyre::bail!(
+                "{} failed to sign the data: {}",
+                self.program,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        let signature = String::from_utf8(output.stdout).wrap_err("Decoding signature as UTF-8")?;
+        Ok(signature)
+    }
+
+    fn sign_ssh(&self, buffer: &[u8]) -> eyre::Result<String> {
+        let key = self
+            .key
+            .as_deref()
+            .expect("SSH signer should have been constructed with a key");
+
+        // Like Git, accept either a path to a key file or a literal public
+        // key, in which case the private key is looked up in the SSH agent.
+        let literal_key = key
+            .strip_prefix("key::")
+            .or_else(|| key.starts_with("ssh-").then_some(key));
+        let key_file = match literal_key {
+            Some(literal_key) => {
+                let mut key_file = tempfile::NamedTempFile::new()?;
+                key_file.write_all(literal_key.as_bytes())?;
+                Some(key_file)
+            }
+            None => None,
+        };
+        let key_path = match &key_file {
+            Some(key_file) => key_file.path().to_path_buf(),
+            None => match (key.strip_prefix("~/"), std::env::var_os("HOME")) {
+                (Some(relative_path), Some(home)) => PathBuf::from(home).join(relative_path),
+                _ => PathBuf::from(key),
+            },
+        };
+
+        let mut buffer_file = tempfile::NamedTempFile::new()?;
+        buffer_file.write_all(buffer)?;
+        buffer_file.flush()?;
+
+        let mut command = Command::new(&self.program);
+        command
+            .args(["-Y", "sign", "-n", "git", "-f"])
+            .arg(&key_path);
+        if key_file.is_some() {
+            command.arg("-U");
+        }
+        let output = command
+            .arg(buffer_file.path())
+            .stdin(Stdio::null())
+            .output()
+            .wrap_err_with(|| format!("Spawning signing program: {}", self.program))?;
+        if !output.status.success() {
+            eyre::bail!(
+                "{} failed to sign the data: {}",
+                self.program,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        let mut signature_path = buffer_file.path().as_os_str().to_owned();
+        signature_path.push(".sig");
+        let signature_path = PathBuf::from(signature_path);
+        let signature = std::fs::read_to_string(&signature_path)
+            .wrap_err_with(|| format!("Reading signature from {signature_path:?}"))?;
+        std::fs::remove_file(&signature_path)?;
+        Ok(signature)
+    }
+}
@@ -7,9 +7,11 @@
 //! contents of the working copy, including staged changes and files with merge
 //! conflicts.
 //!
-//! Untracked changes are not handled by this module. The changes might contain
-//! sensitive data which we don't want to accidentally store in Git, or might be
-//! very large and cause performance issues if committed.
+//! Untracked changes are not handled by this module by default. The changes
+//! might contain sensitive data which we don't want to accidentally store in
+//! Git, or might be very large and cause performance issues if committed. Users
+//! can opt into including untracked (but not ignored) files by setting
+//! `branchless.snapshot.includeUntracked`.
 //!
 //! There are two main reasons to implement working copy snapshots:
 //!
@@ -23,6 +25,7 @@
 
 use itertools::Itertools;
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::str::FromStr;
 
 use tracing::instrument;
@@ -41,6 +44,7 @@ use super::{
 const BRANCHLESS_HEAD_TRAILER: &str = "Branchless-head";
 const BRANCHLESS_HEAD_REF_TRAILER: &str = "Branchless-head-ref";
 const BRANCHLESS_UNSTAGED_TRAILER: &str = "Branchless-unstaged";
+const BRANCHLESS_UNTRACKED_TRAILER: &str = "Branchless-untracked";
 
 /// A special `Commit` which represents the status of the working copy at a
 /// given point in time. This means that it can include changes in any stage.
@@ -70,6 +74,10 @@ pub struct WorkingCopySnapshot<'repo> {
     /// The unstaged changes in the working copy.
     pub commit_unstaged: Commit<'repo>,
 
+    /// The untracked files in the working copy, if they were requested to be
+    /// included in the snapshot. Ignored files are never included.
+    pub commit_untracked: Option<Commit<'repo>>,
+
     /// The index contents at stage 0 (normal staged changes).
     pub commit_stage0: Commit<'repo>,
 
@@ -109,6 +117,7 @@ impl<'repo> WorkingCopySnapshot<'repo> {
         index: &Index,
         head_info: &ResolvedReferenceInfo,
         status_entries: &[StatusEntry],
+        untracked_paths: Option<&[PathBuf]>,
     ) -> eyre::Result<Self> {
         let head_commit = match head_info.oid {
             Some(oid) => Some(repo.find_commit_or_fail(oid)?),
@@ -123,6 +132,14 @@ impl<'repo> WorkingCopySnapshot<'repo> {
         let commit_unstaged_oid: NonZeroOid = {
             Self::create_commit_for_unstaged_changes(repo, head_commit.as_ref(), status_entries)?
         };
+        let commit_untracked_oid: Option<NonZeroOid> = match untracked_paths {
+            Some(untracked_paths) => Some(Self::create_commit_for_untracked_files(
+                repo,
+                head_commit.as_ref(),
+                untracked_paths,
+            )?),
+            None => None,
+        };
 
         let commit_stage0 = Self::create_commit_for_stage(
             repo,
@@ -168,6 +185,12 @@ impl<'repo> WorkingCopySnapshot<'repo> {
                 (Stage::Stage2.get_trailer(), commit_stage2.to_string()),
                 (Stage::Stage3.get_trailer(), commit_stage3.to_string()),
             ]);
+            if let Some(commit_untracked_oid) = commit_untracked_oid {
+                result.push((
+                    BRANCHLESS_UNTRACKED_TRAILER,
+                    commit_untracked_oid.to_string(),
+                ));
+            }
             result
         };
         let signature = Signature::automated()?;
@@ -196,6 +219,9 @@ branchless: automated working copy snapshot
         let commit_stage1 = repo.find_commit_or_fail(commit_stage1)?;
         let commit_stage2 = repo.find_commit_or_fail(commit_stage2)?;
         let commit_stage3 = repo.find_commit_or_fail(commit_stage3)?;
+        let commit_untracked = commit_untracked_oid
+            .map(|oid| repo.find_commit_or_fail(oid))
+            .transpose()?;
         let parents = {
             // Add these commits as parents to ensure that they're kept live for
             // as long as the snapshot commit itself is live.
@@ -205,6 +231,9 @@ branchless: automated working copy snapshot
                 &commit_stage2,
                 &commit_stage3,
             ];
+            if let Some(commit_untracked) = &commit_untracked {
+                parents.push(commit_untracked);
+            }
             if let Some(head_commit) = &head_commit {
                 // Make the head commit the first parent, since that's
                 // conventionally the mainline parent.
@@ -220,6 +249,7 @@ branchless: automated working copy snapshot
             head_commit: head_commit.clone(),
             head_reference_name,
             commit_unstaged: repo.find_commit_or_fail(commit_unstaged_oid)?,
+            commit_untracked,
             commit_stage0,
             commit_stage1,
             commit_stage2,
@@ -259,6 +289,9 @@ branchless: automated working copy snapshot
             Some(commit) => commit,
             None => return Ok(None),
         };
+        // Snapshots created without `branchless.snapshot.includeUntracked` (or
+        // before it existed) don't have this trailer.
+        let commit_untracked = find_commit(BRANCHLESS_UNTRACKED_TRAILER)?;
         let head_reference_name = trailers.iter().find_map(|(k, v)| {
             if k == BRANCHLESS_HEAD_REF_TRAILER {
                 Some(ReferenceName::from(v.as_str()))
@@ -289,6 +322,7 @@ branchless: automated working copy snapshot
             head_commit,
             head_reference_name,
             commit_unstaged,
+            commit_untracked,
             commit_stage0,
             commit_stage1,
             commit_stage2,
@@ -374,6 +408,51 @@ branchless: automated working copy snapshot
         Ok(commit)
     }
 
+    #[instrument]
+    fn create_commit_for_untracked_files(
+        repo: &Repo,
+        head_commit: Option<&Commit>,
+        untracked_paths: &[PathBuf],
+    ) -> eyre::Result<NonZeroOid> {
+        let hydrate_entries = {
+            let mut result = HashMap::new();
+            for path in untracked_paths {
+                // The file may have been removed since the status was
+                // computed, in which case there's nothing to store.
+                if let Some(blob_oid) = repo.create_blob_from_path(path)? {
+                    result.insert(path.clone(), Some((blob_oid, FileMode::Blob)));
+                }
+            }
+            result
+        };
+        let num_files = hydrate_entries.len();
+
+        let head_tree = head_commit.map(|commit| commit.get_tree()).transpose()?;
+        let tree_untracked = {
+            let tree_oid = hydrate_tree(repo, head_tree.as_ref(), hydrate_entries)?;
+            repo.find_tree_or_fail(tree_oid)?
+        };
+
+        let signature = Signature::automated()?;
+        let message = format!(
+            "branchless: working copy snapshot data: {}",
+            Pluralize {
+                determiner: None,
+                amount: num_files,
+                unit: ("untracked file", "untracked files"),
+            }
+        );
+        let commit = repo.create_commit(
+            None,
+            &signature,
+            &signature,
+            &message,
+            &tree_untracked,
+            Vec::from_iter(head_commit),
+        )?;
+        Ok(commit)
+    }
+
     #[instrument]
     fn create_commit_for_stage(
         repo: &Repo,
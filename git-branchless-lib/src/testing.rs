@@ -427,6 +427,9 @@ then you can only run tests in the main `git-branchless` and \
             "branchless.commitDescriptors.relativeTime",
             "false",
         ])?;
+        // Change IDs are derived from commit OIDs, so they're deterministic,
+        // but showing them would make every smartlog snapshot harder to read.
+        self.run(&["config", "branchless.commitDescriptors.changeId", "false"])?;
         self.run(&["config", "branchless.restack.preserveTimestamps", "true"])?;
 
         // Disable warnings of the following form on Windows:
@@ -925,6 +928,19 @@ pub mod pty {
         branchless_subcommand: &str,
         args: &[&str],
         inputs: &[PtyAction],
+    ) -> eyre::Result<ExitStatus> {
+        run_in_pty_with_env(git, branchless_subcommand, args, &[], inputs)
+    }
+
+    /// Like [`run_in_pty`], but with the provided environment variables set in
+    /// addition to (or overriding) the base test environment.
+    #[track_caller]
+    pub fn run_in_pty_with_env(
+        git: &Git,
+        branchless_subcommand: &str,
+        args: &[&str],
+        env: &[(&str, &str)],
+        inputs: &[PtyAction],
     ) -> eyre::Result<ExitStatus> {
         // Use the native pty implementation for the system
         let pty_system = native_pty_system();
@@ -944,6 +960,9 @@ pub mod pty {
             cmd.env(k, v);
         }
         cmd.env("TERM", "xterm");
+        for (k, v) in env {
+            cmd.env(k, v);
+        }
         cmd.arg("branchless");
         cmd.arg(branchless_subcommand);
         cmd.args(args);
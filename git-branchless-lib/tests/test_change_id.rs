@@ -0,0 +1,74 @@
+use branchless::core::change_id::{ChangeId, ChangeIdDb};
+use branchless::git::NonZeroOid;
+use branchless::testing::make_git;
+
+#[test]
+fn test_change_id_prefix_lookup() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.run(&["commit", "--amend", "-m", "amend test1"])?;
+    let amended_oid = git.get_repo()?.get_head_info()?.oid.unwrap();
+
+    let repo = git.get_repo()?;
+    let conn = repo.get_db_conn()?;
+    let change_id_db = ChangeIdDb::new(&conn)?;
+    let change_id = ChangeId::from_commit_oid(test1_oid);
+    assert_eq!(change_id_db.get_change_id(amended_oid)?, change_id);
+
+    for len in [1, 4, change_id.as_str().len()] {
+        let prefix = &change_id.as_str()[..len];
+        assert_eq!(
+            change_id_db.get_recorded_commits_with_prefix(prefix)?,
+            vec![amended_oid],
+        );
+    }
+    assert_eq!(
+        change_id_db.get_recorded_commits_with_prefix("0123")?,
+        Vec::<NonZeroOid>::new(),
+    );
+    assert_eq!(
+        ChangeId::to_oid_prefix(change_id.get_short()),
+        Some(test1_oid.to_string()[..change_id.get_short().len()].to_owned()),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_change_id_prune() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.run(&["commit", "--amend", "-m", "amend test1"])?;
+    git.run(&["commit", "--amend", "-m", "amend test1 again"])?;
+    let amended_oid = git.get_repo()?.get_head_info()?.oid.unwrap();
+
+    let repo = git.get_repo()?;
+    let conn = repo.get_db_conn()?;
+    let change_id_db = ChangeIdDb::new(&conn)?;
+    let change_id = ChangeId::from_commit_oid(test1_oid);
+    assert_eq!(
+        change_id_db
+            .get_recorded_commits_with_prefix(change_id.as_str())?
+            .len(),
+        2
+    );
+
+    // Delete the intermediate commit from the repository.
+    git.branchless("gc", &[])?;
+    git.run(&["reflog", "expire", "--expire=now", "--all"])?;
+    git.run(&["gc", "--prune=now"])?;
+
+    git.branchless("gc", &[])?;
+    assert_eq!(
+        change_id_db.get_recorded_commits_with_prefix(change_id.as_str())?,
+        vec![amended_oid],
+    );
+
+    Ok(())
+}
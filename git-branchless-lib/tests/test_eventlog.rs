@@ -1,4 +1,5 @@
 use std::str::FromStr;
+use std::time::{Duration, SystemTime};
 
 use branchless::core::eventlog::testing::{new_event_cursor, new_event_transaction_id};
 use branchless::core::eventlog::{
@@ -42,6 +43,85 @@ fn test_drop_non_meaningful_events() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_prune_events() -> eyre::Result<()> {
+    let conn = rusqlite::Connection::open_in_memory()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_tx_id = new_event_transaction_id(1);
+    let abc = NonZeroOid::from_str("abc")?;
+    let def = NonZeroOid::from_str("def")?;
+    let new_abc = NonZeroOid::from_str("123")?;
+
+    let old_events = vec![
+        Event::CommitEvent {
+            timestamp: 1.0,
+            event_tx_id,
+            commit_oid: abc,
+        },
+        Event::CommitEvent {
+            timestamp: 1.0,
+            event_tx_id,
+            commit_oid: def,
+        },
+        Event::RefUpdateEvent {
+            timestamp: 1.0,
+            event_tx_id,
+            ref_name: ReferenceName::from("refs/heads/foo"),
+            old_oid: MaybeZeroOid::Zero,
+            new_oid: MaybeZeroOid::NonZero(abc),
+            message: None,
+        },
+        Event::WorkingCopySnapshot {
+            timestamp: 1.0,
+            event_tx_id,
+            head_oid: MaybeZeroOid::NonZero(abc),
+            commit_oid: NonZeroOid::from_str("456")?,
+            ref_name: None,
+        },
+    ];
+    let rewrite_event = Event::RewriteEvent {
+        timestamp: 2.0,
+        event_tx_id,
+        old_commit_oid: MaybeZeroOid::NonZero(abc),
+        new_commit_oid: MaybeZeroOid::NonZero(new_abc),
+    };
+    let ref_update_event = Event::RefUpdateEvent {
+        timestamp: 2.0,
+        event_tx_id,
+        ref_name: ReferenceName::from("refs/heads/foo"),
+        old_oid: MaybeZeroOid::NonZero(abc),
+        new_oid: MaybeZeroOid::NonZero(new_abc),
+        message: None,
+    };
+    let obsolete_event = Event::ObsoleteEvent {
+        timestamp: 2.0,
+        event_tx_id,
+        commit_oid: def,
+    };
+    let new_event = Event::CommitEvent {
+        timestamp: 10.0,
+        event_tx_id,
+        commit_oid: NonZeroOid::from_str("789")?,
+    };
+    event_log_db.add_events(old_events)?;
+    event_log_db.add_events(vec![
+        rewrite_event.clone(),
+        ref_update_event.clone(),
+        obsolete_event,
+        new_event.clone(),
+    ])?;
+
+    let pruned_events =
+        event_log_db.prune_events(SystemTime::UNIX_EPOCH + Duration::from_secs(5))?;
+    assert_eq!(pruned_events.len(), 5);
+    assert_eq!(
+        event_log_db.get_events()?,
+        vec![rewrite_event, ref_update_event, new_event]
+    );
+
+    Ok(())
+}
+
 #[test]
 fn test_different_event_transaction_ids() -> eyre::Result<()> {
     let git = make_git()?;
@@ -0,0 +1,52 @@
+use std::path::PathBuf;
+
+use branchless::core::path_index::PathIndexDb;
+use branchless::testing::make_git;
+use itertools::Itertools;
+
+#[test]
+fn test_path_index() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    let test1_oid = git.commit_file("test1", 1)?;
+    let test2_oid = git.commit_file("test2", 2)?;
+
+    let repo = git.get_repo()?;
+    let path_index = PathIndexDb::new(repo.get_db_conn()?)?;
+    let test1_commit = repo.find_commit_or_fail(test1_oid)?;
+    let test2_commit = repo.find_commit_or_fail(test2_oid)?;
+
+    let num_indexed_commits = || -> eyre::Result<i64> {
+        let conn = repo.get_db_conn()?;
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM path_index_commits", [], |row| {
+            row.get(0)
+        })?;
+        Ok(count)
+    };
+    assert_eq!(num_indexed_commits()?, 0);
+
+    let paths = path_index.get_paths_touched_by_commit(&repo, &test1_commit)?;
+    assert_eq!(
+        paths.into_iter().collect_vec(),
+        vec![PathBuf::from("test1.txt")]
+    );
+    assert_eq!(num_indexed_commits()?, 1);
+
+    // Reading the paths again should use the index.
+    let paths = path_index.get_paths_touched_by_commit(&repo, &test1_commit)?;
+    assert_eq!(
+        paths.into_iter().collect_vec(),
+        vec![PathBuf::from("test1.txt")]
+    );
+    assert_eq!(num_indexed_commits()?, 1);
+
+    let paths = path_index.get_paths_touched_by_commit(&repo, &test2_commit)?;
+    assert_eq!(
+        paths.into_iter().collect_vec(),
+        vec![PathBuf::from("test2.txt")]
+    );
+    assert_eq!(num_indexed_commits()?, 2);
+
+    Ok(())
+}
@@ -1,8 +1,8 @@
 use std::path::PathBuf;
 
 use branchless::git::{
-    AmendFastOptions, BranchType, CherryPickFastOptions, FileMode, FileStatus, GitVersion, Repo,
-    StatusEntry,
+    message_add_trailers, AmendFastOptions, BranchType, CherryPickFastOptions, FileMode,
+    FileStatus, GitVersion, Repo, StatusEntry,
 };
 use branchless::testing::{make_git, make_git_worktree, GitWorktreeWrapper};
 
@@ -36,6 +36,38 @@ fn test_parse_git_version_output() {
     );
 }
 
+#[test]
+fn test_message_add_trailers() {
+    let trailers = vec![
+        "Reviewed-by: Foo <foo@example.com>".to_string(),
+        "Signed-off-by: Bar <bar@example.com>".to_string(),
+    ];
+
+    insta::assert_snapshot!(message_add_trailers("subject\n", &trailers), @r###"
+    subject
+
+    Reviewed-by: Foo <foo@example.com>
+    Signed-off-by: Bar <bar@example.com>
+    "###);
+
+    // Trailers are added to an existing trailer block, and not duplicated.
+    insta::assert_snapshot!(
+        message_add_trailers(
+            "subject\n\nbody\n\nSigned-off-by: Bar <bar@example.com>\n",
+            &trailers
+        ),
+        @r###"
+    subject
+
+    body
+
+    Signed-off-by: Bar <bar@example.com>
+    Reviewed-by: Foo <foo@example.com>
+    "###);
+
+    assert_eq!(message_add_trailers("", &trailers), "");
+    assert_eq!(message_add_trailers("subject\n", &[]), "subject\n");
+}
 #[test]
 fn test_cherry_pick_fast() -> eyre::Result<()> {
     let git = make_git()?;
@@ -12,8 +12,9 @@ use branchless::core::eventlog::{EventLogDb, EventReplayer};
 use branchless::core::formatting::Glyphs;
 use branchless::core::repo_ext::RepoExt;
 use branchless::core::rewrite::{
-    execute_rebase_plan, BuildRebasePlanOptions, ExecuteRebasePlanOptions, ExecuteRebasePlanResult,
-    RebasePlan, RebasePlanBuilder, RepoResource,
+    execute_rebase_plan, BuildRebasePlanOptions, EmptyCommitsMode, ExecuteRebasePlanOptions,
+    ExecuteRebasePlanResult, OidOrLabel, RebaseCommand, RebasePlan, RebasePlanBuilder,
+    RepoResource,
 };
 use branchless::testing::{make_git, Git};
 
@@ -640,16 +641,16 @@ fn test_plan_moving_subtree_with_merge_commit() -> eyre::Result<()> {
     |
     o 96d1c37 create test2.txt
     |
-    o 70deb1e create test3.txt
+    o b8f27a8 create test3.txt
     |\
-    | o 355e173 create test4.txt
-    | & (merge) 8fb706a Merge commit '355e173bf9c5d2efac2e451da0cdad3fb82b869a' into HEAD
+    | @ 2b47b50 create test5.txt
+    | |
+    | | & (merge) 22cf458 create test4.txt
+    | |/
+    | o d26203c Merge commit '355e173bf9c5d2efac2e451da0cdad3fb82b869a' into HEAD
     |
-    @ 9ea1b36 create test5.txt
-    |
-    | & (merge) 355e173 create test4.txt
-    |/
-    o 8fb706a Merge commit '355e173bf9c5d2efac2e451da0cdad3fb82b869a' into HEAD
+    o 22cf458 create test4.txt
+    & (merge) d26203c Merge commit '355e173bf9c5d2efac2e451da0cdad3fb82b869a' into HEAD
     "###);
 
     Ok(())
@@ -709,6 +710,83 @@ fn test_plan_fixup_parent_into_child() -> eyre::Result<()> {
 
 /// Helper function to handle the boilerplate involved in creating, building
 /// and executing the rebase plan.
+#[test]
+fn test_plan_merge_commit_first_parent_dropped_as_empty() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.run(&["checkout", &test1_oid.to_string()])?;
+    let test3_oid = git.commit_file("test3", 3)?;
+    git.run(&["checkout", &test2_oid.to_string()])?;
+    git.run(&["merge", &test3_oid.to_string()])?;
+    let merge_oid = git.get_repo()?.get_head_info()?.oid.unwrap();
+
+    // Make the same change as `test2` on the destination, but together with
+    // another change, so that `test2` becomes empty when rebased.
+    git.run(&["checkout", "master"])?;
+    git.write_file_txt("test2", "test2 contents\n")?;
+    git.write_file_txt("test4", "test4 contents\n")?;
+    git.run(&["add", "."])?;
+    git.run(&["commit", "-m", "create test2.txt and test4.txt"])?;
+    let dest_oid = git.get_repo()?.get_head_info()?.oid.unwrap();
+
+    // Refer to the first parent of the merge commit by its OID rather than by
+    // a label.
+    let rebase_plan = RebasePlan {
+        first_dest_oid: dest_oid,
+        commands: vec![
+            RebaseCommand::Pick {
+                original_commit_oid: test1_oid,
+                commits_to_apply_oids: vec![test1_oid],
+            },
+            RebaseCommand::CreateLabel {
+                label_name: "test1".to_string(),
+            },
+            RebaseCommand::Pick {
+                original_commit_oid: test2_oid,
+                commits_to_apply_oids: vec![test2_oid],
+            },
+            RebaseCommand::Reset {
+                target: OidOrLabel::Label("test1".to_string()),
+            },
+            RebaseCommand::Pick {
+                original_commit_oid: test3_oid,
+                commits_to_apply_oids: vec![test3_oid],
+            },
+            RebaseCommand::CreateLabel {
+                label_name: "test3".to_string(),
+            },
+            RebaseCommand::Reset {
+                target: OidOrLabel::Oid(test2_oid),
+            },
+            RebaseCommand::Merge {
+                commit_oid: merge_oid,
+                commits_to_merge: vec![OidOrLabel::Label("test3".to_string())],
+            },
+        ],
+    };
+    execute_plan(&git, &rebase_plan)?;
+
+    let stdout = git.smartlog()?;
+    insta::assert_snapshot!(stdout, @r###"
+    :
+    @ 3d8a47f (> master) create test2.txt and test4.txt
+    |
+    o 5ddc820 create test1.txt
+    |\
+    | o 272bdf6 create test3.txt
+    | & (merge) d961fb8 Merge commit '4838e49b08954becdd17c0900c1179c2c654c627' into HEAD
+    |
+    | & (merge) 272bdf6 create test3.txt
+    |/
+    o d961fb8 Merge commit '4838e49b08954becdd17c0900c1179c2c654c627' into HEAD
+    "###);
+
+    Ok(())
+}
+
 fn create_and_execute_plan(
     git: &Git,
     builder_callback_fn: impl Fn(&mut RebasePlanBuilder) -> eyre::Result<()>,
@@ -752,6 +830,15 @@ fn create_and_execute_plan(
         }
     };
 
+    execute_plan(git, &rebase_plan)
+}
+
+fn execute_plan(git: &Git, rebase_plan: &RebasePlan) -> eyre::Result<()> {
+    let effects = Effects::new_suppress_for_test(Glyphs::text());
+    let repo = git.get_repo()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+
     let now = SystemTime::UNIX_EPOCH;
     let options = ExecuteRebasePlanOptions {
         now,
@@ -760,11 +847,15 @@ fn create_and_execute_plan(
         force_in_memory: false,
         force_on_disk: false,
         resolve_merge_conflicts: true,
+        reset_author: false,
+        committer_as_author: false,
+        signature_commit_oids: None,
         check_out_commit_options: CheckOutCommitOptions {
             additional_args: Default::default(),
             reset: false,
             render_smartlog: false,
         },
+        empty_commits: EmptyCommitsMode::Drop,
     };
     let git_run_info = git.get_git_run_info();
     let result = execute_rebase_plan(
@@ -772,7 +863,7 @@ fn create_and_execute_plan(
         &git_run_info,
         &repo,
         &event_log_db,
-        &rebase_plan,
+        rebase_plan,
         &options,
     )?;
     assert!(matches!(
@@ -1,10 +1,12 @@
 use std::time::SystemTime;
 
+use branchless::core::check_out::restore_snapshot;
 use branchless::core::effects::Effects;
 use branchless::core::eventlog::EventLogDb;
 use branchless::core::formatting::Glyphs;
 use branchless::git::WorkingCopyChangesType;
 use branchless::testing::{make_git, GitRunOptions};
+use itertools::Itertools;
 
 #[test]
 fn test_has_conflicts() -> eyre::Result<()> {
@@ -58,3 +60,56 @@ fn test_has_conflicts() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_snapshot_untracked_files() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.run(&["config", "branchless.snapshot.includeUntracked", "true"])?;
+
+    std::fs::write(git.repo_path.join(".git/info/exclude"), "ignored.txt\n")?;
+    git.write_file_txt("untracked", "untracked contents\n")?;
+    git.write_file_txt("ignored", "ignored contents\n")?;
+
+    let glyphs = Glyphs::text();
+    let effects = Effects::new_suppress_for_test(glyphs);
+    let git_run_info = git.get_git_run_info();
+    let repo = git.get_repo()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_tx_id = event_log_db.make_transaction_id(SystemTime::now(), "testing")?;
+    let (snapshot, status) = repo.get_status(
+        &effects,
+        &git_run_info,
+        &repo.get_index()?,
+        &repo.get_head_info()?,
+        Some(event_tx_id),
+    )?;
+    assert_eq!(status, vec![]);
+
+    let commit_untracked = snapshot
+        .commit_untracked
+        .as_ref()
+        .expect("Snapshot should include untracked files");
+    let untracked_paths = repo
+        .get_paths_touched_by_commit(commit_untracked)?
+        .into_iter()
+        .sorted()
+        .collect_vec();
+    insta::assert_debug_snapshot!(untracked_paths, @r###"
+    [
+        "untracked.txt",
+    ]
+    "###);
+
+    git.delete_file("untracked")?;
+    let result = restore_snapshot(&effects, &git_run_info, &repo, event_tx_id, &snapshot)?;
+    assert!(result.is_ok());
+    assert_eq!(
+        std::fs::read_to_string(git.repo_path.join("untracked.txt"))?,
+        "untracked contents\n"
+    );
+
+    Ok(())
+}
@@ -154,6 +154,7 @@ fn test_get_status() -> eyre::Result<()> {
                     summary: "branchless: working copy snapshot data: 0 unstaged changes",
                 },
             },
+            commit_untracked: None,
             commit_stage0: Commit {
                 inner: Commit {
                     id: a4edb48b44f5b19d0c2c25fd65251d0bfaba68c1,
@@ -251,6 +252,7 @@ fn test_get_status() -> eyre::Result<()> {
                     summary: "branchless: working copy snapshot data: 4 unstaged changes",
                 },
             },
+            commit_untracked: None,
             commit_stage0: Commit {
                 inner: Commit {
                     id: ccfd588cf59116f67664ac718c404b09fc9e35d2,
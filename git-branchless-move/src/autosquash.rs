@@ -0,0 +1,277 @@
+//! Squash `fixup!` and `squash!` commits into the commits that they refer to,
+//! like `git rebase --autosquash`, but without touching the working copy.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::time::SystemTime;
+
+use rayon::ThreadPoolBuilder;
+use tracing::instrument;
+
+use git_branchless_opts::{MoveOptions, ResolveRevsetOptions, Revset};
+use git_branchless_revset::resolve_commits;
+use lib::core::config::get_restack_preserve_timestamps;
+use lib::core::dag::{sorted_commit_set, union_all, CommitSet, Dag};
+use lib::core::effects::Effects;
+use lib::core::eventlog::{EventLogDb, EventReplayer};
+use lib::core::formatting::Pluralize;
+use lib::core::repo_ext::RepoExt;
+use lib::core::rewrite::{
+    execute_rebase_plan, BuildRebasePlanOptions, EmptyCommitsMode, ExecuteRebasePlanOptions,
+    ExecuteRebasePlanResult, MergeConflictRemediation, RebasePlanBuilder, RebasePlanPermissions,
+    RepoResource,
+};
+use lib::git::{Commit, GitRunInfo, NonZeroOid, Repo};
+use lib::util::{ExitCode, EyreExitOr};
+
+/// Strip any leading `fixup! ` and `squash! ` prefixes from a commit summary,
+/// returning the rest of the summary. Returns `None` if the summary didn't
+/// have any such prefix.
+fn strip_autosquash_prefix(summary: &str) -> Option<&str> {
+    let mut result = None;
+    let mut summary = summary;
+    loop {
+        let rest = match summary.strip_prefix("fixup! ") {
+            Some(rest) => rest,
+            None => match summary.strip_prefix("squash! ") {
+                Some(rest) => rest,
+                None => break,
+            },
+        };
+        summary = rest.trim_start();
+        result = Some(summary);
+    }
+    result
+}
+
+fn get_summary(commit: &Commit) -> eyre::Result<String> {
+    Ok(String::from_utf8_lossy(&commit.get_summary()?).into_owned())
+}
+
+/// Find the `fixup!` and `squash!` commits in `commit_set`, along with the
+/// commits that they should be squashed into. Returns pairs of the form
+/// `(fixup_commit_oid, target_commit_oid)`.
+///
+/// As with `git rebase --autosquash`, the target of a fixup commit is the
+/// earliest of its ancestors in `commit_set` whose summary or OID matches the
+/// rest of the fixup commit's summary. Fixup commits without such a target are
+/// left alone.
+#[instrument]
+pub(crate) fn find_autosquash_targets(
+    repo: &Repo,
+    dag: &Dag,
+    commit_set: &CommitSet,
+) -> eyre::Result<Vec<(NonZeroOid, NonZeroOid)>> {
+    let commits = sorted_commit_set(repo, dag, commit_set)?;
+    let summaries: Vec<String> = commits.iter().map(get_summary).collect::<Result<_, _>>()?;
+
+    let mut result = Vec::new();
+    for (i, commit) in commits.iter().enumerate() {
+        let target_summary = match strip_autosquash_prefix(&summaries[i]) {
+            Some(target_summary) if !target_summary.is_empty() => target_summary,
+            _ => continue,
+        };
+
+        let mut candidates = Vec::new();
+        for (candidate, candidate_summary) in commits[..i].iter().zip(summaries[..i].iter()) {
+            if strip_autosquash_prefix(candidate_summary).is_none()
+                && dag.query_is_ancestor(candidate.get_oid(), commit.get_oid())?
+            {
+                candidates.push((candidate.get_oid(), candidate_summary));
+            }
+        }
+
+        // Prefer an exact match of the summary or OID, and fall back to a
+        // prefix match of the summary.
+        let target_oid = candidates
+            .iter()
+            .find(|(candidate_oid, candidate_summary)| {
+                candidate_summary.as_str() == target_summary
+                    || (target_summary.len() >= 4
+                        && candidate_oid.to_string().starts_with(target_summary))
+            })
+            .or_else(|| {
+                candidates
+                    .iter()
+                    .find(|(_, candidate_summary)| candidate_summary.starts_with(target_summary))
+            })
+            .map(|(candidate_oid, _)| *candidate_oid);
+        if let Some(target_oid) = target_oid {
+            result.push((commit.get_oid(), target_oid));
+        }
+    }
+    Ok(result)
+}
+
+/// Add constraints to squash each fixup commit into its target commit. Targets
+/// whose parents are all in `commit_set` are explicitly kept on top of their
+/// parents, so that they follow their parents if those are rewritten.
+#[instrument(skip(builder))]
+pub(crate) fn add_autosquash_constraints(
+    builder: &mut RebasePlanBuilder,
+    repo: &Repo,
+    dag: &Dag,
+    commit_set: &CommitSet,
+    targets: &[(NonZeroOid, NonZeroOid)],
+) -> eyre::Result<()> {
+    let mut seen_target_oids = HashSet::new();
+    for (_, target_oid) in targets {
+        if !seen_target_oids.insert(*target_oid) {
+            continue;
+        }
+        let parent_oids = repo.find_commit_or_fail(*target_oid)?.get_parent_oids();
+        let mut all_parents_in_set = true;
+        for parent_oid in parent_oids.iter() {
+            if !dag.set_contains(commit_set, *parent_oid)? {
+                all_parents_in_set = false;
+            }
+        }
+        if all_parents_in_set {
+            builder.move_subtree(*target_oid, parent_oids)?;
+        }
+    }
+
+    for (fixup_oid, target_oid) in targets {
+        builder.fixup_commit(*fixup_oid, *target_oid)?;
+    }
+    Ok(())
+}
+
+/// Squash any `fixup!` and `squash!` commits in the given commits into their
+/// target commits, without moving the target commits.
+#[instrument]
+pub fn squash_fixups(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    revsets: Vec<Revset>,
+    resolve_revset_options: &ResolveRevsetOptions,
+    move_options: &MoveOptions,
+) -> EyreExitOr<()> {
+    let repo = Repo::from_current_dir()?;
+    let references_snapshot = repo.get_references_snapshot()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let mut dag = Dag::open_and_sync(
+        effects,
+        &repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+
+    let commit_set =
+        match resolve_commits(effects, &repo, &mut dag, &revsets, resolve_revset_options) {
+            Ok(commit_sets) => union_all(&commit_sets),
+            Err(err) => {
+                err.describe(effects)?;
+                return Ok(Err(ExitCode(1)));
+            }
+        };
+    let commit_set = dag.filter_visible_commits(commit_set)?;
+    let targets = find_autosquash_targets(&repo, &dag, &commit_set)?;
+    if targets.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "No fixup! or squash! commits to squash."
+        )?;
+        return Ok(Ok(()));
+    }
+
+    let MoveOptions {
+        force_rewrite_public_commits,
+        force_in_memory,
+        force_on_disk,
+        detect_duplicate_commits_via_patch_id,
+        resolve_merge_conflicts,
+        dump_rebase_constraints,
+        dump_rebase_plan,
+        preserve_timestamps,
+        reset_author,
+        committer_as_author,
+    } = *move_options;
+    let now = SystemTime::now();
+    let event_tx_id = event_log_db.make_transaction_id(now, "squash-fixups")?;
+    let pool = ThreadPoolBuilder::new().build()?;
+    let repo_pool = RepoResource::new_pool(&repo)?;
+    let build_options = BuildRebasePlanOptions {
+        force_rewrite_public_commits,
+        dump_rebase_constraints,
+        dump_rebase_plan,
+        detect_duplicate_commits_via_patch_id,
+    };
+    let commits_to_rewrite: CommitSet = targets.iter().map(|(_, target_oid)| *target_oid).collect();
+    let permissions = match RebasePlanPermissions::verify_rewrite_set(
+        &dag,
+        build_options,
+        &commits_to_rewrite,
+    )? {
+        Ok(permissions) => permissions,
+        Err(err) => {
+            err.describe(effects, &repo, &dag)?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+    let rebase_plan = {
+        let mut builder = RebasePlanBuilder::new(&dag, permissions);
+        add_autosquash_constraints(&mut builder, &repo, &dag, &commit_set, &targets)?;
+        builder.build(effects, &pool, &repo_pool)?
+    };
+
+    let result = match rebase_plan {
+        Ok(None) => {
+            writeln!(effects.get_output_stream(), "Nothing to do.")?;
+            return Ok(Ok(()));
+        }
+        Ok(Some(rebase_plan)) => {
+            let options = ExecuteRebasePlanOptions {
+                now,
+                event_tx_id,
+                preserve_timestamps: preserve_timestamps || get_restack_preserve_timestamps(&repo)?,
+                force_in_memory,
+                force_on_disk,
+                resolve_merge_conflicts,
+                reset_author,
+                committer_as_author,
+                signature_commit_oids: None,
+                check_out_commit_options: Default::default(),
+                empty_commits: EmptyCommitsMode::Drop,
+            };
+            execute_rebase_plan(
+                effects,
+                git_run_info,
+                &repo,
+                &event_log_db,
+                &rebase_plan,
+                &options,
+            )?
+        }
+        Err(err) => {
+            err.describe(effects, &repo, &dag)?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+
+    match result {
+        ExecuteRebasePlanResult::Succeeded { rewritten_oids: _ } => {
+            writeln!(
+                effects.get_output_stream(),
+                "Squashed {}.",
+                Pluralize {
+                    determiner: None,
+                    amount: targets.len(),
+                    unit: ("fixup commit", "fixup commits"),
+                }
+            )?;
+            Ok(Ok(()))
+        }
+
+        ExecuteRebasePlanResult::DeclinedToMerge { failed_merge_info } => {
+            failed_merge_info.describe(effects, &repo, MergeConflictRemediation::Retry)?;
+            Ok(Err(ExitCode(1)))
+        }
+
+        ExecuteRebasePlanResult::Failed { exit_code } => Ok(Err(exit_code)),
+    }
+}
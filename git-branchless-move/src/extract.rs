@@ -0,0 +1,344 @@
+//! Split the changes to a path out of a stack of commits, into their own
+//! stack on top of the destination.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+use bstr::{BString, ByteSlice};
+use eyre::Context;
+use tracing::instrument;
+
+use lib::core::dag::{CommitSet, Dag};
+use lib::core::effects::Effects;
+use lib::core::eventlog::{Event, EventLogDb, EventTransactionId};
+use lib::core::formatting::Pluralize;
+use lib::core::gc::mark_commit_reachable;
+use lib::core::rerere::{get_file_version, FileVersion};
+use lib::core::rewrite::{
+    check_out_updated_head, move_branches, BuildRebasePlanOptions, RebasePlanPermissions,
+};
+use lib::git::{
+    hydrate_tree, CherryPickFastOptions, Commit, CreateCommitFastError, GitRunInfo, MaybeZeroOid,
+    NonZeroOid, Repo,
+};
+use lib::util::{ExitCode, EyreExitOr};
+
+/// Remove `.` components and trailing slashes, so that the path can be compared
+/// against the paths of files in the repository.
+fn normalize_path(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| !matches!(component, Component::CurDir))
+        .collect()
+}
+
+/// Create a commit with the same metadata as `original_commit`, but with the
+/// given tree and parent.
+fn create_split_commit(
+    repo: &Repo,
+    original_commit: &Commit,
+    tree_oid: NonZeroOid,
+    parent: &Commit,
+    now: SystemTime,
+    preserve_timestamps: bool,
+) -> eyre::Result<NonZeroOid> {
+    let committer_signature = if preserve_timestamps {
+        original_commit.get_committer()
+    } else {
+        original_commit.get_committer().update_timestamp(now)?
+    };
+    let commit_message = original_commit.get_message_raw();
+    let commit_message = commit_message.to_str().with_context(|| {
+        eyre::eyre!(
+            "Could not decode commit message for commit: {:?}",
+            original_commit.get_oid()
+        )
+    })?;
+    let tree = repo.find_tree_or_fail(tree_oid)?;
+    let commit_oid = repo
+        .create_commit(
+            None,
+            &original_commit.get_author(),
+            &committer_signature,
+            commit_message,
+            &tree,
+            vec![parent],
+        )
+        .wrap_err("Creating split commit")?;
+    Ok(commit_oid)
+}
+
+/// Split each commit in the linear stack rooted at `source_oids` into a commit
+/// with its changes under `path` and a commit with the rest of its changes.
+/// The commits with the changes under `path` are applied on top of `dest_oid`
+/// as a new stack, and the commits with the rest of the changes replace the
+/// original commits. Commits which would become empty are omitted.
+#[instrument]
+pub(crate) fn extract_path_into_stack(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    event_log_db: &EventLogDb,
+    dag: &Dag,
+    source_oids: &CommitSet,
+    dest_oid: NonZeroOid,
+    path: &Path,
+    force_rewrite_public_commits: bool,
+    preserve_timestamps: bool,
+    now: SystemTime,
+    event_tx_id: EventTransactionId,
+) -> EyreExitOr<()> {
+    let path = normalize_path(path);
+    let stack = dag.filter_visible_commits(dag.query_descendants(source_oids.clone())?)?;
+    if dag.set_contains(&stack, dest_oid)? {
+        writeln!(
+            effects.get_error_stream(),
+            "The destination for --extract-path can't be one of the commits being split: {dest_oid}"
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+    match RebasePlanPermissions::verify_rewrite_set(
+        dag,
+        BuildRebasePlanOptions {
+            force_rewrite_public_commits,
+            dump_rebase_constraints: false,
+            dump_rebase_plan: false,
+            detect_duplicate_commits_via_patch_id: false,
+        },
+        &stack,
+    )? {
+        Ok(_permissions) => {}
+        Err(err) => {
+            err.describe(effects, repo, dag)?;
+            return Ok(Err(ExitCode(1)));
+        }
+    }
+
+    let stack_oids = dag.sort(&stack)?;
+    let stack_commits = stack_oids
+        .iter()
+        .map(|commit_oid| repo.find_commit_or_fail(*commit_oid))
+        .collect::<Result<Vec<_>, _>>()?;
+    let base_oid = match stack_commits.first() {
+        Some(root_commit) => match root_commit.get_parent_oids().as_slice() {
+            [only_parent_oid] => *only_parent_oid,
+            _ => {
+                writeln!(
+                    effects.get_error_stream(),
+                    "The --extract-path flag can only be used on a linear stack of commits."
+                )?;
+                return Ok(Err(ExitCode(1)));
+            }
+        },
+        None => {
+            writeln!(effects.get_output_stream(), "No commits to split.")?;
+            return Ok(Ok(()));
+        }
+    };
+    let is_linear = stack_commits
+        .iter()
+        .zip(std::iter::once(base_oid).chain(stack_oids.iter().copied()))
+        .all(|(commit, expected_parent_oid)| commit.get_parent_oids() == [expected_parent_oid]);
+    if !is_linear {
+        writeln!(
+            effects.get_error_stream(),
+            "The --extract-path flag can only be used on a linear stack of commits."
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+
+    let mut touched_paths: Vec<HashSet<PathBuf>> = Vec::new();
+    for commit in stack_commits.iter() {
+        let paths: HashSet<PathBuf> = repo
+            .get_paths_touched_by_commit(commit)?
+            .into_iter()
+            .filter(|touched_path| touched_path.starts_with(&path))
+            .collect();
+        touched_paths.push(paths);
+    }
+    if touched_paths.iter().all(|paths| paths.is_empty()) {
+        writeln!(
+            effects.get_error_stream(),
+            "None of the commits to split change any files under: {}",
+            path.display()
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+
+    // The commits which keep the rest of the changes should leave the files
+    // under the path as they were before the stack.
+    let base_commit = repo.find_commit_or_fail(base_oid)?;
+    let base_entries: HashMap<PathBuf, FileVersion> = {
+        let base_tree = base_commit.get_tree()?;
+        touched_paths
+            .iter()
+            .flatten()
+            .map(|touched_path| -> eyre::Result<_> {
+                Ok((
+                    touched_path.clone(),
+                    get_file_version(&base_tree, touched_path)?,
+                ))
+            })
+            .collect::<eyre::Result<_>>()?
+    };
+
+    let mut extracted_oids: Vec<NonZeroOid> = Vec::new();
+    let mut rewritten_oids: HashMap<NonZeroOid, MaybeZeroOid> = HashMap::new();
+    let mut extracted_parent = repo.find_commit_or_fail(dest_oid)?;
+    let mut rest_parent = base_commit;
+    for (commit, paths) in stack_commits.iter().zip(touched_paths.iter()) {
+        if !paths.is_empty() {
+            let parent = repo.find_commit_or_fail(commit.get_parent_oids()[0])?;
+            let commit_tree = commit.get_tree()?;
+            let patch_entries: HashMap<PathBuf, FileVersion> = paths
+                .iter()
+                .map(|touched_path| -> eyre::Result<_> {
+                    Ok((
+                        touched_path.clone(),
+                        get_file_version(&commit_tree, touched_path)?,
+                    ))
+                })
+                .collect::<eyre::Result<_>>()?;
+            let patch_tree_oid = hydrate_tree(repo, Some(&parent.get_tree()?), patch_entries)?;
+            let patch_tree = repo.find_tree_or_fail(patch_tree_oid)?;
+            let patch_commit_oid = repo.create_commit(
+                None,
+                &commit.get_author(),
+                &commit.get_committer(),
+                "",
+                &patch_tree,
+                vec![&parent],
+            )?;
+            let patch_commit = repo.find_commit_or_fail(patch_commit_oid)?;
+            let extracted_tree_oid = match repo.cherry_pick_fast(
+                &patch_commit,
+                &extracted_parent,
+                &CherryPickFastOptions {
+                    reuse_parent_tree_if_possible: true,
+                },
+            ) {
+                Ok(extracted_tree) => extracted_tree.get_oid(),
+                Err(CreateCommitFastError::MergeConflict { conflicting_paths }) => {
+                    writeln!(
+                        effects.get_error_stream(),
+                        "There was a merge conflict, which prevented extracting the changes to {} from: {}",
+                        path.display(),
+                        effects
+                            .get_glyphs()
+                            .render(commit.friendly_describe(effects.get_glyphs())?)?,
+                    )?;
+                    let mut conflicting_paths: Vec<_> = conflicting_paths.into_iter().collect();
+                    conflicting_paths.sort();
+                    for path in conflicting_paths {
+                        writeln!(effects.get_error_stream(), "  {}", path.display())?;
+                    }
+                    return Ok(Err(ExitCode(1)));
+                }
+                Err(err) => return Err(err.into()),
+            };
+            let extracted_oid = create_split_commit(
+                repo,
+                commit,
+                extracted_tree_oid,
+                &extracted_parent,
+                now,
+                preserve_timestamps,
+            )?;
+            extracted_oids.push(extracted_oid);
+            extracted_parent = repo.find_commit_or_fail(extracted_oid)?;
+        }
+
+        let rest_tree_oid = hydrate_tree(repo, Some(&commit.get_tree()?), base_entries.clone())?;
+        if MaybeZeroOid::NonZero(rest_tree_oid) != rest_parent.get_tree_oid() {
+            let rest_oid = create_split_commit(
+                repo,
+                commit,
+                rest_tree_oid,
+                &rest_parent,
+                now,
+                preserve_timestamps,
+            )?;
+            rest_parent = repo.find_commit_or_fail(rest_oid)?;
+        }
+        if rest_parent.get_oid() != commit.get_oid() {
+            rewritten_oids.insert(
+                commit.get_oid(),
+                MaybeZeroOid::NonZero(rest_parent.get_oid()),
+            );
+        }
+    }
+
+    for commit_oid in extracted_oids.iter() {
+        mark_commit_reachable(repo, *commit_oid)?;
+    }
+    for new_oid in rewritten_oids.values() {
+        if let MaybeZeroOid::NonZero(new_oid) = new_oid {
+            mark_commit_reachable(repo, *new_oid)?;
+        }
+    }
+    let timestamp = now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64();
+    event_log_db.add_events(
+        extracted_oids
+            .iter()
+            .map(|commit_oid| Event::CommitEvent {
+                timestamp,
+                event_tx_id,
+                commit_oid: *commit_oid,
+            })
+            .collect(),
+    )?;
+
+    let head_info = repo.get_head_info()?;
+    if head_info.oid.is_some() {
+        // Avoid moving the branch which HEAD points to, or else the index will
+        // show a lot of changes in the working copy.
+        repo.detach_head(&head_info)?;
+    }
+    move_branches(effects, git_run_info, repo, event_tx_id, &rewritten_oids)?;
+
+    let mut post_rewrite_stdin = String::new();
+    for (old_oid, new_oid) in rewritten_oids.iter() {
+        writeln!(post_rewrite_stdin, "{old_oid} {new_oid}")?;
+    }
+    git_run_info.run_hook(
+        effects,
+        repo,
+        "post-rewrite",
+        event_tx_id,
+        &["rebase"],
+        Some(BString::from(post_rewrite_stdin)),
+    )?;
+
+    writeln!(
+        effects.get_output_stream(),
+        "Extracted changes to {} into {}:",
+        path.display(),
+        Pluralize {
+            determiner: None,
+            amount: extracted_oids.len(),
+            unit: ("commit", "commits"),
+        },
+    )?;
+    for commit_oid in extracted_oids.iter() {
+        let commit = repo.find_commit_or_fail(*commit_oid)?;
+        writeln!(
+            effects.get_output_stream(),
+            "  {}",
+            effects
+                .get_glyphs()
+                .render(commit.friendly_describe(effects.get_glyphs())?)?,
+        )?;
+    }
+    check_out_updated_head(
+        effects,
+        git_run_info,
+        repo,
+        event_log_db,
+        event_tx_id,
+        &rewritten_oids,
+        &head_info,
+        None,
+        &Default::default(),
+    )
+}
@@ -0,0 +1,247 @@
+//! Edit the plan for a move interactively before executing it.
+//!
+//! The plan is presented as a list of commands, similar to the todo list for
+//! `git rebase --interactive`, but expressed in terms of the commits being
+//! moved and the commits that they'll be moved onto:
+//!
+//! - `pick <commit>` moves the commit onto the most recently picked commit, or
+//!   onto the most recent `onto` target.
+//! - `drop <commit>` leaves the commit out of the move, so that it stays where
+//!   it is. Removing the line entirely has the same effect.
+//! - `onto <commit>` causes the following picked commits to be moved onto
+//!   the given commit instead.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use lib::core::dag::{sorted_commit_set, CommitSet, Dag};
+use lib::core::effects::Effects;
+use lib::core::formatting::Pluralize;
+use lib::git::{Commit, NonZeroOid, Repo};
+use tracing::instrument;
+
+/// An error produced when parsing an edited move plan.
+#[derive(Debug)]
+pub enum ParseMovePlanError {
+    /// A line contained a command that isn't recognized.
+    UnknownCommand { line_num: usize, command: String },
+
+    /// A command was missing its commit argument.
+    MissingCommit { line_num: usize, command: String },
+
+    /// A commit argument couldn't be resolved to a commit.
+    InvalidCommit { line_num: usize, commit: String },
+
+    /// A commit was picked or dropped which wasn't part of the original move.
+    CommitNotInPlan { line_num: usize, commit: String },
+
+    /// A commit was picked or dropped more than once.
+    DuplicateCommit { line_num: usize, commit: String },
+}
+
+impl ParseMovePlanError {
+    /// Describe the error to the user.
+    pub fn describe(&self, effects: &Effects) -> eyre::Result<()> {
+        match self {
+            ParseMovePlanError::UnknownCommand { line_num, command } => {
+                writeln!(
+                    effects.get_error_stream(),
+                    "Unknown command on line {line_num} of move plan: {command}"
+                )?;
+            }
+            ParseMovePlanError::MissingCommit { line_num, command } => {
+                writeln!(
+                    effects.get_error_stream(),
+                    "Missing commit for `{command}` command on line {line_num} of move plan"
+                )?;
+            }
+            ParseMovePlanError::InvalidCommit { line_num, commit } => {
+                writeln!(
+                    effects.get_error_stream(),
+                    "Could not resolve commit on line {line_num} of move plan: {commit}"
+                )?;
+            }
+            ParseMovePlanError::CommitNotInPlan { line_num, commit } => {
+                writeln!(
+                    effects.get_error_stream(),
+                    "Commit on line {line_num} of move plan is not one of the commits being moved: {commit}"
+                )?;
+            }
+            ParseMovePlanError::DuplicateCommit { line_num, commit } => {
+                writeln!(
+                    effects.get_error_stream(),
+                    "Commit on line {line_num} of move plan was already listed: {commit}"
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PlanCommand {
+    Pick,
+    Drop,
+    Onto,
+}
+
+impl std::fmt::Display for PlanCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlanCommand::Pick => write!(f, "pick"),
+            PlanCommand::Drop => write!(f, "drop"),
+            PlanCommand::Onto => write!(f, "onto"),
+        }
+    }
+}
+
+pub(crate) fn describe_commit(commit: &Commit) -> eyre::Result<String> {
+    Ok(format!(
+        "{} {}",
+        commit.get_short_oid()?,
+        String::from_utf8_lossy(&commit.get_summary()?)
+    ))
+}
+
+/// Render the default move plan, in which each commit in `commit_set` is
+/// moved onto its nearest ancestor in `commit_set`, or onto `dest_oid` if
+/// there is no such ancestor.
+#[instrument]
+pub fn make_move_plan(
+    repo: &Repo,
+    dag: &Dag,
+    dest_oid: NonZeroOid,
+    commit_set: &CommitSet,
+    comment_char: char,
+) -> eyre::Result<String> {
+    let commits = sorted_commit_set(repo, dag, commit_set)?;
+
+    let mut plan = String::new();
+    let mut current_oid = dest_oid;
+    for commit in commits.iter() {
+        let proper_ancestors = dag
+            .query_ancestors(CommitSet::from(commit.get_oid()))?
+            .difference(&CommitSet::from(commit.get_oid()));
+        let parent_oid =
+            match dag.set_first(&dag.query_heads(proper_ancestors.intersection(commit_set))?)? {
+                Some(parent_oid) => NonZeroOid::try_from(parent_oid)?,
+                None => dest_oid,
+            };
+        if parent_oid != current_oid {
+            let parent_commit = repo.find_commit_or_fail(parent_oid)?;
+            writeln!(plan, "onto {}", describe_commit(&parent_commit)?)?;
+        }
+        writeln!(plan, "pick {}", describe_commit(commit)?)?;
+        current_oid = commit.get_oid();
+    }
+
+    let dest_commit = repo.find_commit_or_fail(dest_oid)?;
+    write!(
+        plan,
+        "\
+{c}
+{c} Move {num_commits} onto {dest}.
+{c}
+{c} Commands:
+{c} p, pick <commit> = move commit onto the previously picked commit
+{c} d, drop <commit> = don't move commit, leaving it where it is
+{c} o, onto <commit> = move the following picked commits onto <commit>
+{c}
+{c} The first picked commit is moved onto the destination commit unless it's
+{c} preceded by an `onto` command. These lines are executed from top to bottom,
+{c} and can be re-ordered. If you remove a line here, that commit won't be
+{c} moved. If you remove everything, the move will be aborted.
+",
+        c = comment_char,
+        num_commits = Pluralize {
+            determiner: None,
+            amount: commits.len(),
+            unit: ("commit", "commits"),
+        },
+        dest = describe_commit(&dest_commit)?,
+    )?;
+    Ok(plan)
+}
+
+/// Parse an edited move plan into a list of commits to move and the commits
+/// to move them onto, in the order that they were picked.
+#[instrument]
+pub fn parse_move_plan(
+    repo: &Repo,
+    dest_oid: NonZeroOid,
+    commit_oids: &HashSet<NonZeroOid>,
+    plan: &str,
+    comment_char: char,
+) -> eyre::Result<Result<Vec<(NonZeroOid, NonZeroOid)>, ParseMovePlanError>> {
+    let mut moves = Vec::new();
+    let mut seen_oids = HashSet::new();
+    let mut current_oid = dest_oid;
+    for (line_num, line) in plan.lines().enumerate() {
+        let line_num = line_num + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(comment_char) {
+            continue;
+        }
+
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some(command) => command,
+            None => continue,
+        };
+        let command = match command {
+            "p" | "pick" => PlanCommand::Pick,
+            "d" | "drop" => PlanCommand::Drop,
+            "o" | "onto" => PlanCommand::Onto,
+            command => {
+                return Ok(Err(ParseMovePlanError::UnknownCommand {
+                    line_num,
+                    command: command.to_string(),
+                }))
+            }
+        };
+        let commit = match words.next() {
+            Some(commit) => commit,
+            None => {
+                return Ok(Err(ParseMovePlanError::MissingCommit {
+                    line_num,
+                    command: command.to_string(),
+                }))
+            }
+        };
+        let commit_oid = match repo.revparse_single_commit(commit)? {
+            Some(commit) => commit.get_oid(),
+            None => {
+                return Ok(Err(ParseMovePlanError::InvalidCommit {
+                    line_num,
+                    commit: commit.to_string(),
+                }))
+            }
+        };
+
+        match command {
+            PlanCommand::Onto => {
+                current_oid = commit_oid;
+            }
+
+            PlanCommand::Pick | PlanCommand::Drop => {
+                if !commit_oids.contains(&commit_oid) {
+                    return Ok(Err(ParseMovePlanError::CommitNotInPlan {
+                        line_num,
+                        commit: commit.to_string(),
+                    }));
+                }
+                if !seen_oids.insert(commit_oid) {
+                    return Ok(Err(ParseMovePlanError::DuplicateCommit {
+                        line_num,
+                        commit: commit.to_string(),
+                    }));
+                }
+                if command == PlanCommand::Pick {
+                    moves.push((commit_oid, current_oid));
+                    current_oid = commit_oid;
+                }
+            }
+        }
+    }
+    Ok(Ok(moves))
+}
@@ -12,30 +12,54 @@
 )]
 #![allow(clippy::too_many_arguments, clippy::blocks_in_if_conditions)]
 
-use std::collections::HashMap;
+mod autosquash;
+mod extract;
+mod interactive;
+mod merge;
+mod preview;
+mod reorder;
+mod resolve;
+
+use std::collections::{HashMap, HashSet};
 use std::fmt::Write;
+use std::path::PathBuf;
 use std::time::SystemTime;
 
 use eden_dag::VertexName;
 use lib::core::repo_ext::RepoExt;
+use lib::try_exit_code;
 use lib::util::{ExitCode, EyreExitOr};
 use rayon::ThreadPoolBuilder;
 use tracing::instrument;
 
+use autosquash::{add_autosquash_constraints, find_autosquash_targets};
+use extract::extract_path_into_stack;
 use git_branchless_opts::{MoveOptions, ResolveRevsetOptions, Revset};
-use git_branchless_revset::resolve_commits;
+use git_branchless_revset::{
+    is_parameterized_revset, resolve_commits, resolve_parameterized_commits,
+};
+use git_branchless_reword::edit_message;
+use interactive::{make_move_plan, parse_move_plan};
 use lib::core::config::{
-    get_hint_enabled, get_hint_string, get_restack_preserve_timestamps,
-    print_hint_suppression_notice, Hint,
+    get_comment_char, get_hint_enabled, get_hint_string, get_restack_autostash,
+    get_restack_preserve_timestamps, print_hint_suppression_notice, Hint,
 };
 use lib::core::dag::{sorted_commit_set, union_all, CommitSet, Dag};
 use lib::core::effects::Effects;
 use lib::core::eventlog::{EventLogDb, EventReplayer};
 use lib::core::rewrite::{
-    execute_rebase_plan, BuildRebasePlanOptions, ExecuteRebasePlanOptions, ExecuteRebasePlanResult,
+    abort_saved_rebase_plan, continue_saved_rebase_plan, execute_rebase_plan,
+    BuildRebasePlanOptions, EmptyCommitsMode, ExecuteRebasePlanOptions, ExecuteRebasePlanResult,
     MergeConflictRemediation, RebasePlanBuilder, RebasePlanPermissions, RepoResource,
 };
+use lib::core::stash::with_autostash;
 use lib::git::{GitRunInfo, NonZeroOid, Repo};
+use merge::create_merge_commit;
+use preview::preview_rebase_plan;
+
+pub use autosquash::squash_fixups;
+pub use reorder::reorder;
+pub use resolve::{execute_rebase_plan_resolving_conflicts, resolve_merge_conflict_interactively};
 
 #[instrument]
 fn resolve_base_commit(
@@ -61,6 +85,45 @@ fn resolve_base_commit(
     }
 }
 
+/// Options which select how `r#move` carries out the move.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MoveModeOptions {
+    /// Combine the moved commits and squash them into the destination commit.
+    pub fixup: bool,
+
+    /// Insert the moved subtree between the destination and its children.
+    pub insert: bool,
+
+    /// Edit the plan for the move before executing it.
+    pub interactive: bool,
+
+    /// Squash any `fixup!` and `squash!` commits being moved into the commits
+    /// that they refer to.
+    pub autosquash: bool,
+
+    /// Only check whether the move would succeed, and preview the result.
+    pub dry_run: bool,
+
+    /// Resolve merge conflicts interactively in memory.
+    pub resolve: bool,
+
+    /// Stash uncommitted changes before an on-disk move and restore them
+    /// afterwards.
+    pub autostash: bool,
+
+    /// Drop commits which become empty without asking.
+    pub skip_empty: bool,
+
+    /// Create a merge commit with the destination instead of moving commits.
+    pub create_merge: bool,
+
+    /// Continue an interrupted on-disk move.
+    pub continue_move: bool,
+
+    /// Abort an interrupted on-disk move.
+    pub abort_move: bool,
+}
+
 /// Move a subtree from one place to another.
 #[instrument]
 pub fn r#move(
@@ -72,16 +135,46 @@ pub fn r#move(
     exacts: Vec<Revset>,
     resolve_revset_options: &ResolveRevsetOptions,
     move_options: &MoveOptions,
-    fixup: bool,
-    insert: bool,
+    mode_options: &MoveModeOptions,
+    insert_after: Option<Revset>,
+    insert_before: Option<Revset>,
+    extract_path: Option<PathBuf>,
 ) -> EyreExitOr<()> {
+    let MoveModeOptions {
+        fixup,
+        insert,
+        interactive,
+        autosquash,
+        dry_run,
+        resolve,
+        autostash,
+        skip_empty,
+        create_merge,
+        continue_move,
+        abort_move,
+    } = *mode_options;
     let sources_provided = !sources.is_empty();
     let bases_provided = !bases.is_empty();
     let exacts_provided = !exacts.is_empty();
     let dest_provided = dest.is_some();
     let should_sources_default_to_head = !sources_provided && !bases_provided && !exacts_provided;
 
+    // `--insert-after` and `--insert-before` are resolved like `--dest`, but
+    // for `--insert-before`, the destination is adjusted to the parent of the
+    // resolved commit below.
+    let is_insert_before = insert_before.is_some();
+    let (dest, insert) = match (insert_after, insert_before) {
+        (Some(target), _) | (_, Some(target)) => (Some(target), true),
+        (None, None) => (dest, insert),
+    };
+
     let repo = Repo::from_current_dir()?;
+    if continue_move {
+        return continue_saved_rebase_plan(effects, git_run_info, &repo);
+    }
+    if abort_move {
+        return abort_saved_rebase_plan(effects, git_run_info, &repo);
+    }
     let head_oid = repo.get_head_info()?.oid;
 
     let dest = match dest {
@@ -174,30 +267,72 @@ pub fn r#move(
         }
     };
 
-    let dest_oid: NonZeroOid = match resolve_commits(
-        effects,
-        &repo,
-        &mut dag,
-        &[dest.clone()],
-        resolve_revset_options,
-    ) {
-        Ok(commit_sets) => match dag.commit_set_to_vec(&commit_sets[0])?.as_slice() {
-            [only_commit_oid] => *only_commit_oid,
+    // If the destination refers to `$1`, then it's resolved separately for
+    // each source root below. Until then, resolve bases relative to the main
+    // branch.
+    let is_per_root_dest = is_parameterized_revset(&dest);
+    if is_per_root_dest
+        && (fixup
+            || insert
+            || interactive
+            || exacts_provided
+            || create_merge
+            || extract_path.is_some())
+    {
+        writeln!(
+            effects.get_error_stream(),
+            "A destination which refers to $1 can only be used to move subtrees with --source or --base."
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+    let dest_oid: NonZeroOid = if is_per_root_dest {
+        repo.get_main_branch_oid()?
+    } else {
+        match resolve_commits(
+            effects,
+            &repo,
+            &mut dag,
+            &[dest.clone()],
+            resolve_revset_options,
+        ) {
+            Ok(commit_sets) => match dag.commit_set_to_vec(&commit_sets[0])?.as_slice() {
+                [only_commit_oid] => *only_commit_oid,
+                other => {
+                    let Revset(expr) = dest;
+                    writeln!(
+                        effects.get_error_stream(),
+                        "Expected revset to expand to exactly 1 commit (got {}): {}",
+                        other.len(),
+                        expr,
+                    )?;
+                    return Ok(Err(ExitCode(1)));
+                }
+            },
+            Err(err) => {
+                err.describe(effects)?;
+                return Ok(Err(ExitCode(1)));
+            }
+        }
+    };
+
+    // The commits which will be moved on top of the inserted commits, if
+    // inserting.
+    let (dest_oid, dest_children) = if is_insert_before {
+        let dest_parents = dag.query_parents(CommitSet::from(dest_oid))?;
+        match dag.commit_set_to_vec(&dest_parents)?.as_slice() {
+            [only_parent_oid] => (*only_parent_oid, CommitSet::from(dest_oid)),
             other => {
-                let Revset(expr) = dest;
                 writeln!(
                     effects.get_error_stream(),
-                    "Expected revset to expand to exactly 1 commit (got {}): {}",
+                    "The --insert-before flag can only be used with a commit with exactly 1 parent, but {} has {} parents.",
+                    dest_oid,
                     other.len(),
-                    expr,
                 )?;
                 return Ok(Err(ExitCode(1)));
             }
-        },
-        Err(err) => {
-            err.describe(effects)?;
-            return Ok(Err(ExitCode(1)));
         }
+    } else {
+        (dest_oid, dag.query_children(CommitSet::from(dest_oid))?)
     };
 
     let base_oids = if should_sources_default_to_head {
@@ -237,7 +372,7 @@ pub fn r#move(
                 )?;
             }
 
-            let should_warn_dest = dest_provided && dest_oid == head_oid;
+            let should_warn_dest = dest_provided && !is_per_root_dest && dest_oid == head_oid;
             if should_warn_dest {
                 writeln!(
                     effects.get_output_stream(),
@@ -261,9 +396,41 @@ pub fn r#move(
         resolve_merge_conflicts,
         dump_rebase_constraints,
         dump_rebase_plan,
+        preserve_timestamps,
+        reset_author,
+        committer_as_author,
     } = *move_options;
     let now = SystemTime::now();
     let event_tx_id = event_log_db.make_transaction_id(now, "move")?;
+    if create_merge {
+        return create_merge_commit(
+            effects,
+            git_run_info,
+            &repo,
+            &event_log_db,
+            &dag,
+            &source_oids,
+            dest_oid,
+            now,
+            event_tx_id,
+        );
+    }
+    if let Some(extract_path) = extract_path {
+        return extract_path_into_stack(
+            effects,
+            git_run_info,
+            &repo,
+            &event_log_db,
+            &dag,
+            &source_oids,
+            dest_oid,
+            &extract_path,
+            force_rewrite_public_commits,
+            preserve_timestamps || get_restack_preserve_timestamps(&repo)?,
+            now,
+            event_tx_id,
+        );
+    }
     let pool = ThreadPoolBuilder::new().build()?;
     let repo_pool = RepoResource::new_pool(&repo)?;
     let rebase_plan = {
@@ -273,13 +440,52 @@ pub fn r#move(
             dump_rebase_plan,
             detect_duplicate_commits_via_patch_id,
         };
+        // Destinations which refer to each source root are evaluated before
+        // the builder is constructed, since evaluation needs to update the DAG.
+        let source_roots = dag.query_roots(source_oids.clone())?;
+        let source_roots = dag.commit_set_to_vec(&source_roots)?;
+        let mut source_root_dest_oids: HashMap<NonZeroOid, NonZeroOid> = HashMap::new();
+        if is_per_root_dest {
+            let dest_sets = match resolve_parameterized_commits(
+                effects,
+                &repo,
+                &mut dag,
+                &dest,
+                &source_roots,
+                resolve_revset_options,
+            ) {
+                Ok(dest_sets) => dest_sets,
+                Err(err) => {
+                    err.describe(effects)?;
+                    return Ok(Err(ExitCode(1)));
+                }
+            };
+            for (source_root, dest_set) in source_roots.iter().zip(dest_sets) {
+                match dag.commit_set_to_vec(&dest_set)?.as_slice() {
+                    [only_commit_oid] => {
+                        source_root_dest_oids.insert(*source_root, *only_commit_oid);
+                    }
+                    other => {
+                        let Revset(expr) = &dest;
+                        writeln!(
+                            effects.get_error_stream(),
+                            "Expected revset to expand to exactly 1 commit for source {} (got {}): {}",
+                            source_root,
+                            other.len(),
+                            expr,
+                        )?;
+                        return Ok(Err(ExitCode(1)));
+                    }
+                }
+            }
+        }
         let permissions = {
             let commits_to_move = &source_oids;
             let commits_to_move = commits_to_move.union(&union_all(
                 &exact_components.values().cloned().collect::<Vec<_>>(),
             ));
             let commits_to_move = if insert || fixup {
-                commits_to_move.union(&dag.query_children(CommitSet::from(dest_oid))?)
+                commits_to_move.union(&dest_children)
             } else {
                 commits_to_move
             };
@@ -295,179 +501,236 @@ pub fn r#move(
         };
         let mut builder = RebasePlanBuilder::new(&dag, permissions);
 
-        let source_roots = dag.query_roots(source_oids.clone())?;
-        for source_root in dag.commit_set_to_vec(&source_roots)? {
-            if fixup {
-                let commits = dag.query_descendants(CommitSet::from(source_root))?;
-                let commits = dag.commit_set_to_vec(&commits)?;
-                for commit in commits.iter() {
-                    builder.fixup_commit(*commit, dest_oid)?;
+        if interactive {
+            let exact_oids = union_all(&exact_components.values().cloned().collect::<Vec<_>>());
+            let commits_to_move = dag
+                .query_descendants(source_oids.clone())?
+                .union(&exact_oids);
+            let commits_to_move = dag.filter_visible_commits(commits_to_move)?;
+            for commit_oid in dag.commit_set_to_vec(&commits_to_move)? {
+                let commit = repo.find_commit_or_fail(commit_oid)?;
+                if commit.get_parent_oids().len() > 1 {
+                    writeln!(
+                        effects.get_error_stream(),
+                        "The --interactive flag can't be used to move merge commits, but {} is a merge commit.",
+                        effects
+                            .get_glyphs()
+                            .render(commit.friendly_describe(effects.get_glyphs())?)?,
+                    )?;
+                    return Ok(Err(ExitCode(1)));
                 }
-            } else {
-                builder.move_subtree(source_root, vec![dest_oid])?;
             }
-        }
 
-        let component_roots: CommitSet = exact_components.keys().cloned().collect();
-        let component_roots: Vec<NonZeroOid> = sorted_commit_set(&repo, &dag, &component_roots)?
-            .iter()
-            .map(|commit| commit.get_oid())
-            .collect();
-        for component_root in component_roots.iter().cloned() {
-            let component = exact_components.get(&component_root).unwrap();
-
-            // Find the non-inclusive ancestor components of the current root
-            let mut possible_destinations: Vec<NonZeroOid> = vec![];
-            for root in component_roots.iter().cloned() {
-                let component = exact_components.get(&root).unwrap();
-                if !dag.set_contains(component, component_root)?
-                    && dag.query_is_ancestor(root, component_root)?
-                {
-                    possible_destinations.push(root);
+            let comment_char = get_comment_char(&repo)?;
+            let plan = make_move_plan(&repo, &dag, dest_oid, &commits_to_move, comment_char)?;
+            let plan = edit_message(git_run_info, &repo, &plan)?;
+            let commit_oids: HashSet<NonZeroOid> = dag
+                .commit_set_to_vec(&commits_to_move)?
+                .into_iter()
+                .collect();
+            let moves = match parse_move_plan(&repo, dest_oid, &commit_oids, &plan, comment_char)? {
+                Ok(moves) => moves,
+                Err(err) => {
+                    err.describe(effects)?;
+                    return Ok(Err(ExitCode(1)));
+                }
+            };
+            for (commit_oid, parent_oid) in moves {
+                builder.move_commit(commit_oid, parent_oid)?;
+            }
+        } else {
+            for source_root in source_roots {
+                if fixup {
+                    let commits = dag.query_descendants(CommitSet::from(source_root))?;
+                    let commits = dag.commit_set_to_vec(&commits)?;
+                    for commit in commits.iter() {
+                        builder.fixup_commit(*commit, dest_oid)?;
+                    }
+                } else {
+                    let source_root_dest_oid = source_root_dest_oids
+                        .get(&source_root)
+                        .copied()
+                        .unwrap_or(dest_oid);
+                    builder.move_subtree(source_root, vec![source_root_dest_oid])?;
                 }
             }
 
-            let component_dest_oid = if possible_destinations.is_empty() {
-                dest_oid
-            } else {
-                // If there was a merge commit somewhere outside of the selected
-                // components, then it's possible that the current component
-                // could have multiple possible parents.
-                //
-                // To check for this, we can confirm that the nearest
-                // destination component is an ancestor of the previous (ie next
-                // nearest). This works because possible_destinations is made
-                // from component_roots, which has been sorted topologically; so
-                // each included component should "come after" the previous
-                // component.
-                for i in 1..possible_destinations.len() {
-                    if !dag
-                        .query_is_ancestor(possible_destinations[i - 1], possible_destinations[i])?
+            let component_roots: CommitSet = exact_components.keys().cloned().collect();
+            let component_roots: Vec<NonZeroOid> =
+                sorted_commit_set(&repo, &dag, &component_roots)?
+                    .iter()
+                    .map(|commit| commit.get_oid())
+                    .collect();
+            for component_root in component_roots.iter().cloned() {
+                let component = exact_components.get(&component_root).unwrap();
+
+                // Find the non-inclusive ancestor components of the current root
+                let mut possible_destinations: Vec<NonZeroOid> = vec![];
+                for root in component_roots.iter().cloned() {
+                    let component = exact_components.get(&root).unwrap();
+                    if !dag.set_contains(component, component_root)?
+                        && dag.query_is_ancestor(root, component_root)?
                     {
-                        writeln!(
-                            effects.get_output_stream(),
-                            "This operation cannot be completed because the {} at {}\n\
-                              has multiple possible parents also being moved. Please retry this operation\n\
-                              without this {}, or with only 1 possible parent.",
-                            if dag.set_count(component)? == 1 {
-                                "commit"
-                            } else {
-                                "range of commits rooted"
-                            },
-                            component_root,
-                            if dag.set_count(component)? == 1 {
-                                "commit"
-                            } else {
-                                "range of commits"
-                            },
-                        )?;
-                        return Ok(Err(ExitCode(1)));
+                        possible_destinations.push(root);
                     }
                 }
 
-                let nearest_component = exact_components
-                    .get(&possible_destinations[possible_destinations.len() - 1])
-                    .unwrap();
-                // The current component could be descended from any commit
-                // in nearest_component, not just it's head.
-                let dest_ancestor = dag
-                    .query_ancestors(CommitSet::from(component_root))?
-                    .intersection(nearest_component);
-                match dag.set_first(&dag.query_heads(dest_ancestor.clone())?)? {
-                    Some(head) => NonZeroOid::try_from(head)?,
-                    None => dest_oid,
+                let component_dest_oid = if possible_destinations.is_empty() {
+                    dest_oid
+                } else {
+                    // If there was a merge commit somewhere outside of the selected
+                    // components, then it's possible that the current component
+                    // could have multiple possible parents.
+                    //
+                    // To check for this, we can confirm that the nearest
+                    // destination component is an ancestor of the previous (ie next
+                    // nearest). This works because possible_destinations is made
+                    // from component_roots, which has been sorted topologically; so
+                    // each included component should "come after" the previous
+                    // component.
+                    for i in 1..possible_destinations.len() {
+                        if !dag.query_is_ancestor(
+                            possible_destinations[i - 1],
+                            possible_destinations[i],
+                        )? {
+                            writeln!(
+                                effects.get_output_stream(),
+                                "This operation cannot be completed because the {} at {}\n\
+                                  has multiple possible parents also being moved. Please retry this operation\n\
+                                  without this {}, or with only 1 possible parent.",
+                                if dag.set_count(component)? == 1 {
+                                    "commit"
+                                } else {
+                                    "range of commits rooted"
+                                },
+                                component_root,
+                                if dag.set_count(component)? == 1 {
+                                    "commit"
+                                } else {
+                                    "range of commits"
+                                },
+                            )?;
+                            return Ok(Err(ExitCode(1)));
+                        }
+                    }
+
+                    let nearest_component = exact_components
+                        .get(&possible_destinations[possible_destinations.len() - 1])
+                        .unwrap();
+                    // The current component could be descended from any commit
+                    // in nearest_component, not just it's head.
+                    let dest_ancestor = dag
+                        .query_ancestors(CommitSet::from(component_root))?
+                        .intersection(nearest_component);
+                    match dag.set_first(&dag.query_heads(dest_ancestor.clone())?)? {
+                        Some(head) => NonZeroOid::try_from(head)?,
+                        None => dest_oid,
+                    }
+                };
+
+                // Again, we've already confirmed that each component has but 1 parent
+                let component_parent = NonZeroOid::try_from(
+                    dag.set_first(&dag.query_parents(CommitSet::from(component_root))?)?
+                        .unwrap(),
+                )?;
+                let component_children: CommitSet =
+                    dag.query_children(component.clone())?.difference(component);
+                let component_children = dag.filter_visible_commits(component_children)?;
+
+                for component_child in dag.commit_set_to_vec(&component_children)? {
+                    // If the range being extracted has any child commits, then we
+                    // need to move each of those subtrees up to the parent commit
+                    // of the range. If, however, we're inserting the range and the
+                    // destination commit is in one of those subtrees, then we
+                    // should only move the commits from the root of that child
+                    // subtree up to (and including) the destination commmit.
+                    if insert && dag.query_is_ancestor(component_child, component_dest_oid)? {
+                        builder.move_range(
+                            component_child,
+                            component_dest_oid,
+                            component_parent,
+                        )?;
+                    } else {
+                        builder.move_subtree(component_child, vec![component_parent])?;
+                    }
                 }
-            };
 
-            // Again, we've already confirmed that each component has but 1 parent
-            let component_parent = NonZeroOid::try_from(
-                dag.set_first(&dag.query_parents(CommitSet::from(component_root))?)?
-                    .unwrap(),
-            )?;
-            let component_children: CommitSet =
-                dag.query_children(component.clone())?.difference(component);
-            let component_children = dag.filter_visible_commits(component_children)?;
-
-            for component_child in dag.commit_set_to_vec(&component_children)? {
-                // If the range being extracted has any child commits, then we
-                // need to move each of those subtrees up to the parent commit
-                // of the range. If, however, we're inserting the range and the
-                // destination commit is in one of those subtrees, then we
-                // should only move the commits from the root of that child
-                // subtree up to (and including) the destination commmit.
-                if insert && dag.query_is_ancestor(component_child, component_dest_oid)? {
-                    builder.move_range(component_child, component_dest_oid, component_parent)?;
+                if fixup {
+                    let commits = dag.commit_set_to_vec(component)?;
+                    for commit in commits.iter() {
+                        builder.fixup_commit(*commit, dest_oid)?;
+                    }
                 } else {
-                    builder.move_subtree(component_child, vec![component_parent])?;
+                    builder.move_subtree(component_root, vec![component_dest_oid])?;
                 }
             }
 
-            if fixup {
-                let commits = dag.commit_set_to_vec(component)?;
-                for commit in commits.iter() {
-                    builder.fixup_commit(*commit, dest_oid)?;
-                }
-            } else {
-                builder.move_subtree(component_root, vec![component_dest_oid])?;
-            }
-        }
+            if insert {
+                let source_head = {
+                    let exact_head = if component_roots.is_empty() {
+                        CommitSet::empty()
+                    } else {
+                        // As long as component_roots has been sorted topologically,
+                        // we only need to compare adjacent elements to confirm a
+                        // single lineage.
+                        for i in 1..component_roots.len() {
+                            if !dag.query_is_ancestor(component_roots[i - 1], component_roots[i])? {
+                                writeln!(
+                                    effects.get_output_stream(),
+                                    "The --insert and --exact flags can only be used together when moving commits or\n\
+                                     ranges that form a single lineage, but {} is not an ancestor of {}.",
+                                    component_roots[i - 1],
+                                    component_roots[i]
+                                )?;
+                                return Ok(Err(ExitCode(1)));
+                            }
+                        }
 
-        if insert {
-            let source_head = {
-                let exact_head = if component_roots.is_empty() {
-                    CommitSet::empty()
-                } else {
-                    // As long as component_roots has been sorted topologically,
-                    // we only need to compare adjacent elements to confirm a
-                    // single lineage.
-                    for i in 1..component_roots.len() {
-                        if !dag.query_is_ancestor(component_roots[i - 1], component_roots[i])? {
+                        let head_component = exact_components
+                            .get(&component_roots[component_roots.len() - 1])
+                            .unwrap()
+                            .clone();
+                        dag.query_heads(head_component)?
+                    };
+                    let source_heads: CommitSet = dag
+                        .query_heads(dag.query_descendants(source_oids.clone())?)?
+                        .union(&exact_head);
+                    match dag.commit_set_to_vec(&source_heads)?.as_slice() {
+                        [oid] => *oid,
+                        _ => {
                             writeln!(
                                 effects.get_output_stream(),
-                                "The --insert and --exact flags can only be used together when moving commits or\n\
-                                 ranges that form a single lineage, but {} is not an ancestor of {}.",
-                                component_roots[i - 1],
-                                component_roots[i]
+                                "The --insert flag cannot be used when moving subtrees or ranges with multiple heads."
                             )?;
                             return Ok(Err(ExitCode(1)));
                         }
                     }
-
-                    let head_component = exact_components
-                        .get(&component_roots[component_roots.len() - 1])
-                        .unwrap()
-                        .clone();
-                    dag.query_heads(head_component)?
                 };
-                let source_heads: CommitSet = dag
-                    .query_heads(dag.query_descendants(source_oids.clone())?)?
-                    .union(&exact_head);
-                match dag.commit_set_to_vec(&source_heads)?.as_slice() {
-                    [oid] => *oid,
-                    _ => {
-                        writeln!(
-                            effects.get_output_stream(),
-                            "The --insert flag cannot be used when moving subtrees or ranges with multiple heads."
-                        )?;
-                        return Ok(Err(ExitCode(1)));
-                    }
+
+                let exact_components = exact_components
+                    .values()
+                    .cloned()
+                    .collect::<Vec<CommitSet>>();
+                let exact_oids = union_all(&exact_components);
+                // Children of dest_oid that are not themselves being moved.
+                let dest_children: CommitSet = dest_children
+                    .difference(&source_oids)
+                    .difference(&exact_oids);
+                let dest_children = dag.filter_visible_commits(dest_children)?;
+
+                for dest_child in dag.commit_set_to_vec(&dest_children)? {
+                    builder.move_subtree(dest_child, vec![source_head])?;
                 }
-            };
+            }
 
-            let exact_components = exact_components
-                .values()
-                .cloned()
-                .collect::<Vec<CommitSet>>();
-            let exact_oids = union_all(&exact_components);
-            // Children of dest_oid that are not themselves being moved.
-            let dest_children: CommitSet = dag
-                .query_children(CommitSet::from(dest_oid))?
-                .difference(&source_oids)
-                .difference(&exact_oids);
-            let dest_children = dag.filter_visible_commits(dest_children)?;
-
-            for dest_child in dag.commit_set_to_vec(&dest_children)? {
-                builder.move_subtree(dest_child, vec![source_head])?;
+            if autosquash {
+                let exact_oids = union_all(&exact_components.values().cloned().collect::<Vec<_>>());
+                let commits_to_move = dag
+                    .query_descendants(source_oids.clone())?
+                    .union(&exact_oids);
+                let commits_to_move = dag.filter_visible_commits(commits_to_move)?;
+                let targets = find_autosquash_targets(&repo, &dag, &commits_to_move)?;
+                add_autosquash_constraints(&mut builder, &repo, &dag, &commits_to_move, &targets)?;
             }
         }
         builder.build(effects, &pool, &repo_pool)?
@@ -481,20 +744,52 @@ pub fn r#move(
             let options = ExecuteRebasePlanOptions {
                 now,
                 event_tx_id,
-                preserve_timestamps: get_restack_preserve_timestamps(&repo)?,
+                preserve_timestamps: preserve_timestamps || get_restack_preserve_timestamps(&repo)?,
                 force_in_memory,
                 force_on_disk,
                 resolve_merge_conflicts,
+                reset_author,
+                committer_as_author,
+                signature_commit_oids: None,
                 check_out_commit_options: Default::default(),
+                empty_commits: if skip_empty {
+                    EmptyCommitsMode::Drop
+                } else {
+                    EmptyCommitsMode::Prompt
+                },
             };
-            execute_rebase_plan(
+            if dry_run {
+                return preview_rebase_plan(effects, &repo, &dag, &rebase_plan, &options);
+            }
+
+            try_exit_code!(with_autostash(
                 effects,
                 git_run_info,
                 &repo,
                 &event_log_db,
-                &rebase_plan,
-                &options,
-            )?
+                autostash || get_restack_autostash(&repo)?,
+                || -> EyreExitOr<ExecuteRebasePlanResult> {
+                    if resolve {
+                        Ok(Ok(try_exit_code!(execute_rebase_plan_resolving_conflicts(
+                            effects,
+                            git_run_info,
+                            &repo,
+                            &event_log_db,
+                            &rebase_plan,
+                            &options,
+                        )?)))
+                    } else {
+                        Ok(Ok(execute_rebase_plan(
+                            effects,
+                            git_run_info,
+                            &repo,
+                            &event_log_db,
+                            &rebase_plan,
+                            &options,
+                        )?))
+                    }
+                },
+            )?)
         }
         Err(err) => {
             err.describe(effects, &repo, &dag)?;
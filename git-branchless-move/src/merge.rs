@@ -0,0 +1,166 @@
+//! Join a stack of commits into the destination with a merge commit, rather
+//! than moving the stack on top of the destination.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::SystemTime;
+
+use tracing::instrument;
+
+use lib::core::dag::{CommitSet, Dag};
+use lib::core::effects::Effects;
+use lib::core::eventlog::{Event, EventLogDb, EventTransactionId};
+use lib::core::formatting::Pluralize;
+use lib::core::gc::mark_commit_reachable;
+use lib::core::repo_ext::RepoExt;
+use lib::core::rewrite::{check_out_updated_head, move_branches};
+use lib::git::{
+    CategorizedReferenceName, CreateCommitFastError, GitRunInfo, GitRunOpts, MaybeZeroOid,
+    NonZeroOid, Repo,
+};
+use lib::util::{ExitCode, EyreExitOr};
+
+/// Create a merge commit whose first parent is `dest_oid` and whose second
+/// parent is the head of the stack rooted at `source_oids`, and move any
+/// branches pointing to `dest_oid` to it.
+///
+/// The merge commit is created with `git commit-tree`, so that the user's
+/// identity and commit signing settings are respected.
+#[instrument]
+pub(crate) fn create_merge_commit(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    event_log_db: &EventLogDb,
+    dag: &Dag,
+    source_oids: &CommitSet,
+    dest_oid: NonZeroOid,
+    now: SystemTime,
+    event_tx_id: EventTransactionId,
+) -> EyreExitOr<()> {
+    let stack = dag.filter_visible_commits(dag.query_descendants(source_oids.clone())?)?;
+    let stack_head_oid = match dag.commit_set_to_vec(&dag.query_heads(stack)?)?.as_slice() {
+        [only_head_oid] => *only_head_oid,
+        other => {
+            writeln!(
+                effects.get_error_stream(),
+                "The --create-merge flag can only be used to merge commits with exactly 1 head, but got {}.",
+                Pluralize {
+                    determiner: None,
+                    amount: other.len(),
+                    unit: ("head", "heads"),
+                },
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+    let stack_head = repo.find_commit_or_fail(stack_head_oid)?;
+    if dag.query_is_ancestor(stack_head_oid, dest_oid)? {
+        writeln!(
+            effects.get_error_stream(),
+            "Already merged into the destination: {}",
+            effects
+                .get_glyphs()
+                .render(stack_head.friendly_describe(effects.get_glyphs())?)?,
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+
+    let tree_oid = match repo.auto_merge_tree_fast(dest_oid, stack_head_oid) {
+        Ok(Some(tree_oid)) => tree_oid,
+        Ok(None) => {
+            writeln!(
+                effects.get_error_stream(),
+                "The destination and {} have no common ancestor, so they can't be merged.",
+                effects
+                    .get_glyphs()
+                    .render(stack_head.friendly_describe(effects.get_glyphs())?)?,
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+        Err(CreateCommitFastError::MergeConflict { conflicting_paths }) => {
+            writeln!(
+                effects.get_error_stream(),
+                "There was a merge conflict, which prevented creating the merge commit: {}",
+                effects
+                    .get_glyphs()
+                    .render(stack_head.friendly_describe(effects.get_glyphs())?)?,
+            )?;
+            let mut conflicting_paths: Vec<_> = conflicting_paths.into_iter().collect();
+            conflicting_paths.sort();
+            for path in conflicting_paths {
+                writeln!(effects.get_error_stream(), "  {}", path.display())?;
+            }
+            return Ok(Err(ExitCode(1)));
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    let branch_oid_to_names = repo.get_branch_oid_to_names()?;
+    let message = match branch_oid_to_names
+        .get(&stack_head_oid)
+        .and_then(|names| names.iter().min())
+    {
+        Some(branch_name) => format!(
+            "Merge branch '{}'",
+            CategorizedReferenceName::new(branch_name).render_suffix()
+        ),
+        None => format!("Merge commit '{stack_head_oid}'"),
+    };
+    let tree_oid = tree_oid.to_string();
+    let dest_oid_str = dest_oid.to_string();
+    let stack_head_oid_str = stack_head_oid.to_string();
+    let result = git_run_info.run_silent(
+        repo,
+        Some(event_tx_id),
+        &[
+            "commit-tree",
+            tree_oid.as_str(),
+            "-p",
+            dest_oid_str.as_str(),
+            "-p",
+            stack_head_oid_str.as_str(),
+            "-m",
+            message.as_str(),
+        ],
+        GitRunOpts::default(),
+    )?;
+    let merge_commit_oid: NonZeroOid = String::from_utf8(result.stdout)?.trim().parse()?;
+
+    mark_commit_reachable(repo, merge_commit_oid)?;
+    event_log_db.add_events(vec![Event::CommitEvent {
+        timestamp: now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64(),
+        event_tx_id,
+        commit_oid: merge_commit_oid,
+    }])?;
+
+    let head_info = repo.get_head_info()?;
+    if head_info.oid.is_some() {
+        // Avoid moving the branch which HEAD points to, or else the index will
+        // show a lot of changes in the working copy.
+        repo.detach_head(&head_info)?;
+    }
+    let merged_oids: HashMap<NonZeroOid, MaybeZeroOid> =
+        HashMap::from([(dest_oid, MaybeZeroOid::NonZero(merge_commit_oid))]);
+    move_branches(effects, git_run_info, repo, event_tx_id, &merged_oids)?;
+
+    let merge_commit = repo.find_commit_or_fail(merge_commit_oid)?;
+    writeln!(
+        effects.get_output_stream(),
+        "Created merge commit: {}",
+        effects
+            .get_glyphs()
+            .render(merge_commit.friendly_describe(effects.get_glyphs())?)?,
+    )?;
+    check_out_updated_head(
+        effects,
+        git_run_info,
+        repo,
+        event_log_db,
+        event_tx_id,
+        &merged_oids,
+        &head_info,
+        None,
+        &Default::default(),
+    )
+}
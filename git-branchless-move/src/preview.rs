@@ -0,0 +1,131 @@
+//! Preview the results of a move without carrying it out.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use tracing::instrument;
+
+use git_branchless_smartlog::{make_smartlog_graph_from_commits, render_graph};
+use lib::core::dag::Dag;
+use lib::core::effects::Effects;
+use lib::core::node_descriptors::{
+    BranchesDescriptor, CommitMessageDescriptor, CommitOidDescriptor, EmptyCommitDescriptor,
+    Redactor,
+};
+use lib::core::repo_ext::RepoExt;
+use lib::core::rewrite::{
+    dry_run_rebase_plan, ExecuteRebasePlanOptions, MergeConflictRemediation, RebasePlan,
+};
+use lib::git::{MaybeZeroOid, NonZeroOid, Repo, ResolvedReferenceInfo};
+use lib::util::{ExitCode, EyreExitOr};
+
+/// Rebase the commits in memory and render a smartlog of the commits that
+/// would result, without creating any commits or moving any references. If the
+/// rebase would cause a merge conflict, describe it and return an error exit
+/// code instead.
+#[instrument]
+pub(crate) fn preview_rebase_plan(
+    effects: &Effects,
+    repo: &Repo,
+    dag: &Dag,
+    rebase_plan: &RebasePlan,
+    options: &ExecuteRebasePlanOptions,
+) -> EyreExitOr<()> {
+    let in_memory_repo = repo.try_clone_in_memory()?;
+    let rewritten_oids = match dry_run_rebase_plan(effects, &in_memory_repo, rebase_plan, options)?
+    {
+        Ok(rewritten_oids) => rewritten_oids,
+        Err(failed_merge_info) => {
+            failed_merge_info.describe(effects, repo, MergeConflictRemediation::Retry)?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+    let get_new_oid = |oid: NonZeroOid| -> NonZeroOid {
+        match rewritten_oids.get(&oid) {
+            Some(MaybeZeroOid::NonZero(new_oid)) => *new_oid,
+            Some(MaybeZeroOid::Zero) | None => oid,
+        }
+    };
+
+    // Render the rewritten commits along with the commits that they would be
+    // attached to.
+    let new_oids: HashSet<NonZeroOid> = rewritten_oids
+        .values()
+        .filter_map(|new_oid| match new_oid {
+            MaybeZeroOid::NonZero(new_oid) => Some(*new_oid),
+            MaybeZeroOid::Zero => None,
+        })
+        .collect();
+    let mut commits = Vec::new();
+    let mut root_oids = HashSet::new();
+    for new_oid in new_oids.iter() {
+        let commit = in_memory_repo.find_commit_or_fail(*new_oid)?;
+        for parent_oid in commit.get_parent_oids() {
+            if !new_oids.contains(&parent_oid) && root_oids.insert(parent_oid) {
+                commits.push(in_memory_repo.find_commit_or_fail(parent_oid)?);
+            }
+        }
+        commits.push(commit);
+    }
+    let mut main_commit_oids = HashSet::new();
+    for root_oid in root_oids {
+        if dag.is_public_commit(root_oid)? {
+            main_commit_oids.insert(root_oid);
+        }
+    }
+    let graph = make_smartlog_graph_from_commits(commits, &main_commit_oids);
+
+    // Show branches and `HEAD` where they would be after the move.
+    let head_info = repo.get_head_info()?;
+    let head_info = ResolvedReferenceInfo {
+        oid: head_info.oid.map(get_new_oid),
+        reference_name: head_info.reference_name,
+    };
+    let references_snapshot = {
+        let mut references_snapshot = repo.get_references_snapshot()?;
+        let mut branch_oid_to_names: HashMap<NonZeroOid, HashSet<_>> = HashMap::new();
+        for (oid, names) in references_snapshot.branch_oid_to_names {
+            branch_oid_to_names
+                .entry(get_new_oid(oid))
+                .or_default()
+                .extend(names);
+        }
+        references_snapshot.branch_oid_to_names = branch_oid_to_names;
+        references_snapshot
+    };
+
+    let lines = render_graph(
+        effects,
+        &in_memory_repo,
+        dag,
+        &graph,
+        head_info.oid,
+        &mut [
+            &mut CommitOidDescriptor::new(true)?,
+            &mut BranchesDescriptor::new(
+                repo,
+                &head_info,
+                &references_snapshot,
+                &Redactor::Disabled,
+            )?,
+            &mut EmptyCommitDescriptor::new(repo)?,
+            &mut CommitMessageDescriptor::new(&Redactor::Disabled)?,
+        ],
+    )?;
+    writeln!(
+        effects.get_output_stream(),
+        "This operation would result in:"
+    )?;
+    for line in lines {
+        writeln!(
+            effects.get_output_stream(),
+            "{}",
+            effects.get_glyphs().render(line)?
+        )?;
+    }
+    writeln!(
+        effects.get_output_stream(),
+        "(This was a dry-run; run without --dry-run to apply changes.)"
+    )?;
+    Ok(Ok(()))
+}
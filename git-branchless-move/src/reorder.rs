@@ -0,0 +1,270 @@
+//! Reorder a contiguous range of commits within a stack.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::time::SystemTime;
+
+use rayon::ThreadPoolBuilder;
+use tracing::instrument;
+
+use git_branchless_opts::{MoveOptions, ResolveRevsetOptions, Revset};
+use git_branchless_revset::resolve_commits;
+use git_branchless_reword::edit_message;
+use lib::core::config::{get_comment_char, get_restack_preserve_timestamps};
+use lib::core::dag::{
+    linear_commit_range, sorted_commit_set, union_all, CommitSet, Dag, LinearCommitRange,
+};
+use lib::core::effects::Effects;
+use lib::core::eventlog::{EventLogDb, EventReplayer};
+use lib::core::formatting::Pluralize;
+use lib::core::repo_ext::RepoExt;
+use lib::core::rewrite::{
+    execute_rebase_plan, BuildRebasePlanOptions, EmptyCommitsMode, ExecuteRebasePlanOptions,
+    ExecuteRebasePlanResult, MergeConflictRemediation, RebasePlanBuilder, RebasePlanPermissions,
+    RepoResource,
+};
+use lib::git::{GitRunInfo, NonZeroOid, Repo};
+use lib::util::{ExitCode, EyreExitOr};
+
+use crate::interactive::describe_commit;
+
+/// Parse the edited list of commits into the new order of the commits. Returns
+/// a message describing the problem if the list isn't a permutation of the
+/// original commits.
+fn parse_commit_order(
+    repo: &Repo,
+    commit_oids: &[NonZeroOid],
+    text: &str,
+    comment_char: char,
+) -> eyre::Result<Result<Vec<NonZeroOid>, String>> {
+    let mut new_order = Vec::new();
+    let mut seen_oids = HashSet::new();
+    for (line_num, line) in text.lines().enumerate() {
+        let line_num = line_num + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(comment_char) {
+            continue;
+        }
+
+        let commit = line.split_whitespace().next().unwrap_or_default();
+        let commit_oid = match repo.revparse_single_commit(commit)? {
+            Some(commit) => commit.get_oid(),
+            None => {
+                return Ok(Err(format!(
+                    "Could not resolve commit on line {line_num}: {commit}"
+                )))
+            }
+        };
+        if !commit_oids.contains(&commit_oid) {
+            return Ok(Err(format!(
+                "Commit on line {line_num} is not one of the commits being reordered: {commit}"
+            )));
+        }
+        if !seen_oids.insert(commit_oid) {
+            return Ok(Err(format!(
+                "Commit on line {line_num} was already listed: {commit}"
+            )));
+        }
+        new_order.push(commit_oid);
+    }
+
+    if new_order.len() != commit_oids.len() {
+        return Ok(Err(format!(
+            "Expected all {} to be listed, but only {} listed",
+            Pluralize {
+                determiner: None,
+                amount: commit_oids.len(),
+                unit: ("commit", "commits"),
+            },
+            new_order.len()
+        )));
+    }
+    Ok(Ok(new_order))
+}
+
+/// Reorder a contiguous range of commits by editing their order in an editor,
+/// and then rebasing them in the new order. Any descendants of the last commit
+/// in the range are moved on top of the new last commit.
+#[instrument]
+pub fn reorder(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    revsets: Vec<Revset>,
+    resolve_revset_options: &ResolveRevsetOptions,
+    move_options: &MoveOptions,
+) -> EyreExitOr<()> {
+    let repo = Repo::from_current_dir()?;
+    let references_snapshot = repo.get_references_snapshot()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let mut dag = Dag::open_and_sync(
+        effects,
+        &repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+
+    let commit_set =
+        match resolve_commits(effects, &repo, &mut dag, &revsets, resolve_revset_options) {
+            Ok(commit_sets) => union_all(&commit_sets),
+            Err(err) => {
+                err.describe(effects)?;
+                return Ok(Err(ExitCode(1)));
+            }
+        };
+    let commits = sorted_commit_set(&repo, &dag, &commit_set)?;
+    if commits.len() < 2 {
+        writeln!(
+            effects.get_output_stream(),
+            "Nothing to reorder: expected at least 2 commits, but got {}.",
+            commits.len()
+        )?;
+        return Ok(Ok(()));
+    }
+
+    // The commits must form a single linear range, so that there's an
+    // unambiguous order to edit.
+    let LinearCommitRange {
+        base_oid,
+        root_oid: _,
+        head_oid: last_oid,
+    } = match linear_commit_range(effects, &commits, "reorder")? {
+        Ok(range) => range,
+        Err(exit_code) => return Ok(Err(exit_code)),
+    };
+    let commit_oids: Vec<NonZeroOid> = commits.iter().map(|commit| commit.get_oid()).collect();
+
+    let comment_char = get_comment_char(&repo)?;
+    let mut text = String::new();
+    for commit in commits.iter() {
+        writeln!(text, "{}", describe_commit(commit)?)?;
+    }
+    write!(
+        text,
+        "\
+{c}
+{c} Reorder {num_commits} onto {base}.
+{c}
+{c} These lines can be re-ordered; the commits will be applied from top to
+{c} bottom. Every commit must remain listed exactly once.
+",
+        c = comment_char,
+        num_commits = Pluralize {
+            determiner: None,
+            amount: commits.len(),
+            unit: ("commit", "commits"),
+        },
+        base = describe_commit(&repo.find_commit_or_fail(base_oid)?)?,
+    )?;
+    let text = edit_message(git_run_info, &repo, &text)?;
+    let new_order = match parse_commit_order(&repo, &commit_oids, &text, comment_char)? {
+        Ok(new_order) => new_order,
+        Err(message) => {
+            writeln!(effects.get_error_stream(), "{message}")?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+    if new_order == commit_oids {
+        writeln!(effects.get_output_stream(), "Nothing to do.")?;
+        return Ok(Ok(()));
+    }
+
+    let MoveOptions {
+        force_rewrite_public_commits,
+        force_in_memory,
+        force_on_disk,
+        detect_duplicate_commits_via_patch_id,
+        resolve_merge_conflicts,
+        dump_rebase_constraints,
+        dump_rebase_plan,
+        preserve_timestamps,
+        reset_author,
+        committer_as_author,
+    } = *move_options;
+    let now = SystemTime::now();
+    let event_tx_id = event_log_db.make_transaction_id(now, "reorder")?;
+    let pool = ThreadPoolBuilder::new().build()?;
+    let repo_pool = RepoResource::new_pool(&repo)?;
+    let build_options = BuildRebasePlanOptions {
+        force_rewrite_public_commits,
+        dump_rebase_constraints,
+        dump_rebase_plan,
+        detect_duplicate_commits_via_patch_id,
+    };
+    let permissions =
+        match RebasePlanPermissions::verify_rewrite_set(&dag, build_options, &commit_set)? {
+            Ok(permissions) => permissions,
+            Err(err) => {
+                err.describe(effects, &repo, &dag)?;
+                return Ok(Err(ExitCode(1)));
+            }
+        };
+    let rebase_plan = {
+        let mut builder = RebasePlanBuilder::new(&dag, permissions);
+        let mut dest_oid = base_oid;
+        for commit_oid in new_order.iter().copied() {
+            builder.move_subtree(commit_oid, vec![dest_oid])?;
+            dest_oid = commit_oid;
+        }
+
+        // Commits downstream of the range are moved on top of the new last
+        // commit, rather than following the commit they were originally
+        // attached to.
+        let last_children = dag
+            .query_children(CommitSet::from(last_oid))?
+            .difference(&commit_set);
+        let last_children = dag.filter_visible_commits(last_children)?;
+        for child_oid in dag.commit_set_to_vec(&last_children)? {
+            builder.move_subtree(child_oid, vec![dest_oid])?;
+        }
+        builder.build(effects, &pool, &repo_pool)?
+    };
+
+    let result = match rebase_plan {
+        Ok(None) => {
+            writeln!(effects.get_output_stream(), "Nothing to do.")?;
+            return Ok(Ok(()));
+        }
+        Ok(Some(rebase_plan)) => {
+            let options = ExecuteRebasePlanOptions {
+                now,
+                event_tx_id,
+                preserve_timestamps: preserve_timestamps || get_restack_preserve_timestamps(&repo)?,
+                force_in_memory,
+                force_on_disk,
+                resolve_merge_conflicts,
+                reset_author,
+                committer_as_author,
+                signature_commit_oids: None,
+                check_out_commit_options: Default::default(),
+                empty_commits: EmptyCommitsMode::Drop,
+            };
+            execute_rebase_plan(
+                effects,
+                git_run_info,
+                &repo,
+                &event_log_db,
+                &rebase_plan,
+                &options,
+            )?
+        }
+        Err(err) => {
+            err.describe(effects, &repo, &dag)?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+
+    match result {
+        ExecuteRebasePlanResult::Succeeded { rewritten_oids: _ } => Ok(Ok(())),
+
+        ExecuteRebasePlanResult::DeclinedToMerge { failed_merge_info } => {
+            failed_merge_info.describe(effects, &repo, MergeConflictRemediation::Retry)?;
+            Ok(Err(ExitCode(1)))
+        }
+
+        ExecuteRebasePlanResult::Failed { exit_code } => Ok(Err(exit_code)),
+    }
+}
@@ -0,0 +1,285 @@
+//! Resolve merge conflicts interactively without touching the working copy.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use tracing::{instrument, warn};
+
+use lib::core::effects::{Effects, OperationType};
+use lib::core::eventlog::EventLogDb;
+use lib::core::rerere::{get_file_version, FileVersion, ResolutionDb};
+use lib::core::rewrite::{
+    execute_rebase_plan, ExecuteRebasePlanOptions, ExecuteRebasePlanResult, FailedMergeInfo,
+    MergeConflictRemediation, RebasePlan,
+};
+use lib::git::{
+    dehydrate_tree, process_diff_for_record, CreateCommitFastError, FileMode, GitRunInfo,
+    MergeFavor, MergeTreesFastOptions, Repo,
+};
+use lib::try_exit_code;
+use lib::util::{ExitCode, EyreExitOr};
+use scm_record::helpers::CrosstermInput;
+use scm_record::{
+    Event, RecordError, RecordInput, RecordState, Recorder, SelectedContents, TerminalKind,
+};
+
+struct Input;
+
+impl RecordInput for Input {
+    fn terminal_kind(&self) -> TerminalKind {
+        TerminalKind::Crossterm
+    }
+
+    fn next_events(&mut self) -> Result<Vec<Event>, RecordError> {
+        CrosstermInput.next_events()
+    }
+
+    fn edit_commit_message(&mut self, message: &str) -> Result<String, RecordError> {
+        Ok(message.to_owned())
+    }
+}
+
+/// Open the interactive change selector on the files which conflicted in
+/// `failed_merge_info`. The changes from both sides are first merged against
+/// their merge base, once favoring each side, so that each conflicting file is
+/// shown as the changes from the version favoring the commit being applied
+/// onto to the version favoring the commit being applied. Non-conflicting
+/// changes from either side are therefore kept as-is, and only the conflicting
+/// hunks need to be chosen; the selected changes form the resolution of the
+/// conflict. Binary files can't be merged, so they're resolved to the version
+/// from the commit being applied only if their change is selected, and to the
+/// version from the commit being applied onto otherwise.
+///
+/// The resolutions are recorded in the database, so that retrying the
+/// in-memory rebase reuses them instead of failing with the same conflict.
+#[instrument]
+pub fn resolve_merge_conflict_interactively(
+    effects: &Effects,
+    repo: &Repo,
+    failed_merge_info: &FailedMergeInfo,
+) -> EyreExitOr<()> {
+    let (commit_oid, onto_oid, conflicting_paths) = match failed_merge_info {
+        FailedMergeInfo::Conflict {
+            commit_oid,
+            onto_oid,
+            conflicting_paths,
+        } => (*commit_oid, *onto_oid, conflicting_paths),
+        FailedMergeInfo::CannotRebaseMergeInMemory { commit_oid: _ } => {
+            failed_merge_info.describe(effects, repo, MergeConflictRemediation::Retry)?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+    let commit = repo.find_commit_or_fail(commit_oid)?;
+    let patch_parent = match commit.get_only_parent() {
+        Some(patch_parent) => patch_parent,
+        None => {
+            writeln!(
+                effects.get_output_stream(),
+                "Merge conflicts in merge commits can't be resolved interactively: {}",
+                effects
+                    .get_glyphs()
+                    .render(commit.friendly_describe(effects.get_glyphs())?)?,
+            )?;
+            failed_merge_info.describe(effects, repo, MergeConflictRemediation::Retry)?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+    let ancestor_tree = patch_parent.get_tree()?;
+    let onto_commit = repo.find_commit_or_fail(onto_oid)?;
+    let our_tree = onto_commit.get_tree()?;
+    let their_tree = commit.get_tree()?;
+
+    let mut conflicting_paths: Vec<&PathBuf> = conflicting_paths.iter().collect();
+    conflicting_paths.sort();
+    let paths: Vec<&Path> = conflicting_paths
+        .iter()
+        .map(|path| path.as_path())
+        .collect();
+    let (our_merged_tree_oid, their_merged_tree_oid) = {
+        let merge_favoring = |favor: MergeFavor| {
+            repo.merge_trees_fast(
+                ancestor_tree.get_oid(),
+                our_tree.get_oid(),
+                their_tree.get_oid(),
+                &MergeTreesFastOptions { favor: Some(favor) },
+            )
+        };
+        match (
+            merge_favoring(MergeFavor::Ours),
+            merge_favoring(MergeFavor::Theirs),
+        ) {
+            (Ok(our_merged_tree_oid), Ok(their_merged_tree_oid)) => {
+                (our_merged_tree_oid, their_merged_tree_oid)
+            }
+            // Conflicts such as a file being modified on one side and deleted
+            // on the other can't be resolved by favoring a side, so fall back
+            // to choosing between the two sides' versions directly.
+            (Err(CreateCommitFastError::MergeConflict { .. }), _)
+            | (_, Err(CreateCommitFastError::MergeConflict { .. })) => {
+                (our_tree.get_oid(), their_tree.get_oid())
+            }
+            (Err(err), _) | (_, Err(err)) => return Err(err.into()),
+        }
+    };
+    let our_merged_tree = repo.find_tree_or_fail(dehydrate_tree(
+        repo,
+        &repo.find_tree_or_fail(our_merged_tree_oid)?,
+        &paths,
+    )?)?;
+    let their_merged_tree = repo.find_tree_or_fail(dehydrate_tree(
+        repo,
+        &repo.find_tree_or_fail(their_merged_tree_oid)?,
+        &paths,
+    )?)?;
+    let files = {
+        let (effects, _progress) = effects.start_operation(OperationType::CalculateDiff);
+        let diff =
+            repo.get_diff_between_trees(&effects, Some(&our_merged_tree), &their_merged_tree, 0)?;
+        process_diff_for_record(repo, &diff)?
+    };
+
+    writeln!(
+        effects.get_output_stream(),
+        "Resolving merge conflict in: {}",
+        effects
+            .get_glyphs()
+            .render(commit.friendly_describe(effects.get_glyphs())?)?,
+    )?;
+    let record_state = RecordState {
+        is_read_only: false,
+        commits: Default::default(),
+        files,
+    };
+    let mut input = Input;
+    let recorder = Recorder::new(record_state, &mut input);
+    let RecordState {
+        is_read_only: _,
+        commits: _,
+        files,
+    } = match recorder.run() {
+        Ok(result) => result,
+        Err(RecordError::Cancelled) => {
+            writeln!(effects.get_output_stream(), "Aborted.")?;
+            return Ok(Err(ExitCode(1)));
+        }
+        Err(RecordError::Bug(message)) => {
+            writeln!(effects.get_output_stream(), "BUG: {message}")?;
+            writeln!(
+                effects.get_output_stream(),
+                "This is a bug. Please report it."
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+        Err(
+            err @ (RecordError::SetUpTerminal(_)
+            | RecordError::CleanUpTerminal(_)
+            | RecordError::ReadInput(_)
+            | RecordError::RenderFrame(_)
+            | RecordError::SerializeJson(_)
+            | RecordError::WriteFile(_)
+            | RecordError::Other(_)),
+        ) => {
+            writeln!(effects.get_output_stream(), "Error: {err}")?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+
+    let resolution_db = ResolutionDb::new(repo.get_db_conn()?)?;
+    for path in paths {
+        let ancestor = get_file_version(&ancestor_tree, path)?;
+        let ours = get_file_version(&our_tree, path)?;
+        let theirs = get_file_version(&their_tree, path)?;
+        let our_merged = get_file_version(&our_merged_tree, path)?;
+        let their_merged = get_file_version(&their_merged_tree, path)?;
+        let resolved: FileVersion = match files.iter().find(|file| file.path == path) {
+            // The merged file is the same on both sides, so there's nothing
+            // to choose.
+            None => our_merged,
+            Some(file) => {
+                let (selected, _unselected) = file.get_selected_contents();
+                match selected {
+                    SelectedContents::Absent => None,
+                    // This includes binary files whose change wasn't selected.
+                    SelectedContents::Unchanged => our_merged,
+                    // The binary change was explicitly selected.
+                    SelectedContents::Binary {
+                        old_description: _,
+                        new_description: _,
+                    } => their_merged,
+                    SelectedContents::Present { contents } => {
+                        let oid = repo.create_blob_from_contents(contents.as_bytes())?;
+                        let default_mode = match our_merged.or(their_merged) {
+                            Some((_, file_mode)) => file_mode,
+                            None => FileMode::Blob,
+                        };
+                        let file_mode = match file.get_file_mode() {
+                            None => default_mode,
+                            Some(mode) => match i32::try_from(mode) {
+                                Ok(mode) => FileMode::from(mode),
+                                Err(err) => {
+                                    warn!(
+                                        ?mode,
+                                        ?default_mode,
+                                        ?err,
+                                        "File mode did not fit into i32, using default"
+                                    );
+                                    default_mode
+                                }
+                            },
+                        };
+                        Some((oid, file_mode))
+                    }
+                }
+            }
+        };
+        resolution_db.record_resolution(&ancestor, &ours, &theirs, &resolved)?;
+    }
+    Ok(Ok(()))
+}
+
+/// Execute the rebase plan, and each time that it fails with a merge conflict,
+/// resolve the conflict interactively with
+/// `resolve_merge_conflict_interactively` and try again.
+///
+/// If the same conflict occurs again, then the recorded resolution couldn't
+/// be used, so the `DeclinedToMerge` result is returned to the caller rather
+/// than asking again.
+#[instrument]
+pub fn execute_rebase_plan_resolving_conflicts(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    event_log_db: &EventLogDb,
+    rebase_plan: &RebasePlan,
+    options: &ExecuteRebasePlanOptions,
+) -> EyreExitOr<ExecuteRebasePlanResult> {
+    let mut resolved_conflicts = HashSet::new();
+    loop {
+        let result = execute_rebase_plan(
+            effects,
+            git_run_info,
+            repo,
+            event_log_db,
+            rebase_plan,
+            options,
+        )?;
+        match &result {
+            ExecuteRebasePlanResult::DeclinedToMerge {
+                failed_merge_info:
+                    failed_merge_info @ FailedMergeInfo::Conflict {
+                        commit_oid,
+                        onto_oid,
+                        conflicting_paths: _,
+                    },
+            } if resolved_conflicts.insert((*commit_oid, *onto_oid)) => {
+                try_exit_code!(resolve_merge_conflict_interactively(
+                    effects,
+                    repo,
+                    failed_merge_info
+                )?);
+            }
+            _ => return Ok(Ok(result)),
+        }
+    }
+}
@@ -11,6 +11,7 @@
 
 pub mod prompt;
 
+use std::borrow::Cow;
 use std::collections::HashSet;
 
 use std::ffi::OsString;
@@ -25,19 +26,23 @@ use lib::core::repo_ext::RepoExt;
 use lib::util::{ExitCode, EyreExitOr};
 use tracing::{instrument, warn};
 
-use git_branchless_opts::{SwitchOptions, TraverseCommitsOptions};
-use git_branchless_revset::resolve_default_smartlog_commits;
+use git_branchless_opts::{ResolveRevsetOptions, Revset, SwitchOptions, TraverseCommitsOptions};
+use git_branchless_revset::{eval, resolve_commits, resolve_default_smartlog_commits, Expr};
 use git_branchless_smartlog::make_smartlog_graph;
-use lib::core::config::get_next_interactive;
-use lib::core::dag::{sorted_commit_set, CommitSet, Dag};
+use lib::core::config::{get_navigation_skip_obsolete, get_next_interactive};
+use lib::core::dag::{sorted_commit_set, union_all, CommitSet, Dag};
 use lib::core::effects::Effects;
-use lib::core::eventlog::{EventLogDb, EventReplayer};
-use lib::core::formatting::Pluralize;
+use lib::core::eventlog::{EventCursor, EventLogDb, EventReplayer};
+use lib::core::formatting::{Pluralize, StyledStringBuilder};
 use lib::core::node_descriptors::{
     BranchesDescriptor, CommitMessageDescriptor, CommitOidDescriptor,
     DifferentialRevisionDescriptor, NodeDescriptor, Redactor, RelativeTimeDescriptor,
 };
-use lib::git::{GitRunInfo, NonZeroOid, Repo};
+use lib::core::rewrite::find_rewrite_target;
+use lib::git::{
+    CherryPickFastOptions, Commit, CreateCommitFastError, GitRunInfo, MaybeZeroOid, NonZeroOid,
+    Repo,
+};
 
 use crate::prompt::prompt_select_commit;
 
@@ -86,6 +91,111 @@ pub enum Towards {
     Interactive,
 }
 
+/// A kind of commit which `git next` can go directly to, rather than
+/// traversing a certain number of commits.
+#[derive(Clone, Copy, Debug)]
+pub enum NextTarget {
+    /// The nearest descendant commit which would have a merge conflict if it
+    /// were restacked onto the newest version of its rewritten parent.
+    Conflict,
+
+    /// The nearest descendant commit with a failing cached result for the most
+    /// recent `git test run` command.
+    Failed,
+}
+
+/// Replace any obsolete commits in `commits` with their nearest non-obsolete
+/// children (for `git next`) or parents (for `git prev`), printing a warning
+/// for each commit skipped.
+fn skip_obsolete_commits(
+    effects: &Effects,
+    repo: &Repo,
+    dag: &Dag,
+    command: Command,
+    commits: CommitSet,
+) -> eyre::Result<CommitSet> {
+    let obsolete_commits = dag.query_obsolete_commits();
+    let mut result = CommitSet::empty();
+    let mut skipped_commits = CommitSet::empty();
+    let mut frontier = commits;
+    while !dag.set_is_empty(&frontier)? {
+        let obsolete_frontier = frontier.intersection(&obsolete_commits);
+        result = result.union(&frontier.difference(&obsolete_commits));
+        skipped_commits = skipped_commits.union(&obsolete_frontier);
+        frontier = match command {
+            Command::Next => dag.filter_visible_commits(dag.query_children(obsolete_frontier)?)?,
+            Command::Prev => dag.query_parents(obsolete_frontier)?,
+        };
+    }
+
+    let glyphs = effects.get_glyphs();
+    for commit in sorted_commit_set(repo, dag, &skipped_commits)? {
+        writeln!(
+            effects.get_output_stream(),
+            "{}",
+            glyphs.render(
+                StyledStringBuilder::new()
+                    .append_styled("Skipped obsolete commit: ", BaseColor::Yellow.light())
+                    .append(commit.friendly_describe(glyphs)?)
+                    .build()
+            )?
+        )?;
+    }
+
+    Ok(result)
+}
+
+/// Choose between multiple candidate commits to go to according to `towards`.
+/// If the choice is ambiguous, print the candidates under `header` and return
+/// `None`.
+fn select_candidate(
+    effects: &Effects,
+    repo: &Repo,
+    commit_descriptors: &mut [&mut dyn NodeDescriptor],
+    towards: Option<Towards>,
+    header: &str,
+    candidate_commits: Vec<Commit>,
+) -> eyre::Result<Option<NonZeroOid>> {
+    let glyphs = effects.get_glyphs();
+    let oid = match (towards, candidate_commits.as_slice()) {
+        (_, []) => None,
+        (_, [only_child]) => Some(only_child.get_oid()),
+        (Some(Towards::Newest), [.., newest_child]) => Some(newest_child.get_oid()),
+        (Some(Towards::Oldest), [oldest_child, ..]) => Some(oldest_child.get_oid()),
+        (Some(Towards::Interactive), [_, _, ..]) => prompt_select_commit(
+            effects,
+            repo,
+            Some(header),
+            "",
+            candidate_commits,
+            commit_descriptors,
+        )?,
+        (None, [_, _, ..]) => {
+            writeln!(effects.get_output_stream(), "{header}")?;
+            for (j, child) in (0..).zip(candidate_commits.iter()) {
+                let descriptor = if j == 0 {
+                    " (oldest)"
+                } else if j + 1 == candidate_commits.len() {
+                    " (newest)"
+                } else {
+                    ""
+                };
+
+                writeln!(
+                    effects.get_output_stream(),
+                    "  {} {}{}",
+                    glyphs.bullet_point,
+                    glyphs.render(child.friendly_describe(glyphs)?)?,
+                    descriptor
+                )?;
+            }
+            writeln!(effects.get_output_stream(), "(Pass --oldest (-o), --newest (-n), or --interactive (-i) to select between ambiguous commits)")?;
+            None
+        }
+    };
+    Ok(oid)
+}
+
 #[instrument(skip(commit_descriptors))]
 fn advance(
     effects: &Effects,
@@ -96,18 +206,8 @@ fn advance(
     command: Command,
     distance: Distance,
     towards: Option<Towards>,
+    skip_obsolete: bool,
 ) -> eyre::Result<Option<NonZeroOid>> {
-    let towards = match towards {
-        Some(towards) => Some(towards),
-        None => {
-            if get_next_interactive(repo)? {
-                Some(Towards::Interactive)
-            } else {
-                None
-            }
-        }
-    };
-
     let public_commits = dag.query_ancestors(dag.main_branch_commit.clone())?;
 
     let glyphs = effects.get_glyphs();
@@ -137,7 +237,14 @@ fn advance(
                     | Distance::NumCommits {
                         amount: _,
                         move_by_branches: false,
-                    } => child_commits()?,
+                    } => {
+                        let children = child_commits()?;
+                        if skip_obsolete {
+                            skip_obsolete_commits(effects, repo, dag, command, children)?
+                        } else {
+                            children
+                        }
+                    }
 
                     Distance::AllTheWay {
                         move_by_branches: true,
@@ -173,6 +280,11 @@ fn advance(
                         // their current *commit stack*. We filter out commits which
                         // aren't part of the commit stack so that we stop early here.
                         let parents = parent_commits()?;
+                        let parents = if skip_obsolete {
+                            skip_obsolete_commits(effects, repo, dag, command, parents)?
+                        } else {
+                            parents
+                        };
                         parents.difference(&public_commits)
                     }
 
@@ -187,7 +299,14 @@ fn advance(
                     Distance::NumCommits {
                         amount: _,
                         move_by_branches: false,
-                    } => parent_commits()?,
+                    } => {
+                        let parents = parent_commits()?;
+                        if skip_obsolete {
+                            skip_obsolete_commits(effects, repo, dag, command, parents)?
+                        } else {
+                            parents
+                        }
+                    }
 
                     Distance::NumCommits {
                         amount: _,
@@ -236,73 +355,235 @@ fn advance(
             pluralize.unit.0, pluralize,
         );
 
-        current_oid = match (towards, candidate_commits.as_slice()) {
-            (_, []) => {
+        if candidate_commits.is_empty() {
+            writeln!(
+                effects.get_output_stream(),
+                "{}",
+                glyphs.render(StyledString::styled(
+                    format!(
+                        "No more {} commits to go to after traversing {}.",
+                        pluralize.unit.0, pluralize,
+                    ),
+                    BaseColor::Yellow.light()
+                ))?
+            )?;
+
+            if i == 0 {
+                // If we didn't succeed in traversing any commits, then
+                // treat the operation as a failure. Otherwise, assume that
+                // the user just meant to go as many commits as possible.
+                return Ok(None);
+            } else {
+                break;
+            }
+        }
+
+        current_oid = match select_candidate(
+            effects,
+            repo,
+            commit_descriptors,
+            towards,
+            &header,
+            candidate_commits,
+        )? {
+            Some(oid) => oid,
+            None => return Ok(None),
+        };
+
+        i += 1;
+    }
+    Ok(Some(current_oid))
+}
+
+/// Find the commits after `head_oid` which would have a merge conflict if they
+/// were restacked onto the newest version of their rewritten parent commit.
+/// This includes the abandoned children of previous versions of `HEAD`, since
+/// they would be restacked on top of `HEAD`.
+fn find_conflicting_descendants(
+    repo: &Repo,
+    dag: &Dag,
+    event_replayer: &EventReplayer,
+    event_cursor: EventCursor,
+    head_oid: NonZeroOid,
+) -> eyre::Result<CommitSet> {
+    let obsolete_commits = dag.query_obsolete_commits();
+    let abandoned_children = dag
+        .filter_visible_commits(dag.query_children(obsolete_commits.clone())?)?
+        .difference(&obsolete_commits);
+
+    let mut conflicting_commit_oids = Vec::new();
+    for commit_oid in dag.commit_set_to_vec(&abandoned_children)? {
+        let commit = repo.find_commit_or_fail(commit_oid)?;
+        let parent_oid = match commit.get_only_parent_oid() {
+            Some(parent_oid) => parent_oid,
+            None => continue,
+        };
+        let new_parent_oid = match find_rewrite_target(event_replayer, event_cursor, parent_oid) {
+            Some(MaybeZeroOid::NonZero(new_parent_oid)) => new_parent_oid,
+            Some(MaybeZeroOid::Zero) | None => continue,
+        };
+        let is_after_head = (commit_oid != head_oid
+            && dag.query_is_ancestor(head_oid, commit_oid)?)
+            || dag.query_is_ancestor(head_oid, new_parent_oid)?;
+        if !is_after_head {
+            continue;
+        }
+
+        let new_parent = repo.find_commit_or_fail(new_parent_oid)?;
+        let result = repo.cherry_pick_fast(
+            &commit,
+            &new_parent,
+            &CherryPickFastOptions {
+                reuse_parent_tree_if_possible: false,
+            },
+        );
+        match result {
+            Ok(_) => {}
+            Err(CreateCommitFastError::MergeConflict {
+                conflicting_paths: _,
+            }) => conflicting_commit_oids.push(commit_oid),
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(conflicting_commit_oids.into_iter().collect())
+}
+
+#[instrument(skip(commit_descriptors))]
+fn go_to_target(
+    effects: &Effects,
+    repo: &Repo,
+    dag: &mut Dag,
+    event_replayer: &EventReplayer,
+    event_cursor: EventCursor,
+    commit_descriptors: &mut [&mut dyn NodeDescriptor],
+    head_oid: NonZeroOid,
+    target: NextTarget,
+    towards: Option<Towards>,
+) -> eyre::Result<Option<NonZeroOid>> {
+    let (target_commits, description) = match target {
+        NextTarget::Conflict => (
+            find_conflicting_descendants(repo, dag, event_replayer, event_cursor, head_oid)?,
+            "with merge conflicts",
+        ),
+        NextTarget::Failed => {
+            let revset = Revset("(descendants(.) - .) & tests.failed()".to_string());
+            match resolve_commits(
+                effects,
+                repo,
+                dag,
+                &[revset],
+                &ResolveRevsetOptions::default(),
+            ) {
+                Ok(commit_sets) => (union_all(&commit_sets), "with failing tests"),
+                Err(err) => {
+                    err.describe(effects)?;
+                    return Ok(None);
+                }
+            }
+        }
+    };
+
+    // Only go as far as the nearest matching commits in each stack.
+    let candidate_commits = sorted_commit_set(repo, dag, &dag.query_roots(target_commits)?)?;
+    if candidate_commits.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "{}",
+            effects.get_glyphs().render(StyledString::styled(
+                format!("No descendant commits {description} to go to."),
+                BaseColor::Yellow.light()
+            ))?
+        )?;
+        return Ok(None);
+    }
+    select_candidate(
+        effects,
+        repo,
+        commit_descriptors,
+        towards,
+        &format!("Found multiple possible commits {description} to go to:"),
+        candidate_commits,
+    )
+}
+
+/// Move between the children of the current commit's parent: towards newer
+/// siblings for `git next`, and towards older siblings for `git prev`.
+#[instrument]
+fn advance_sideways(
+    effects: &Effects,
+    repo: &Repo,
+    dag: &Dag,
+    current_oid: NonZeroOid,
+    command: Command,
+    distance: Distance,
+) -> eyre::Result<Option<NonZeroOid>> {
+    let siblings = {
+        let parents = dag.query_parents(CommitSet::from(current_oid))?;
+        let siblings = dag.query_children(parents)?;
+        let siblings = dag.filter_visible_commits(siblings)?;
+
+        // Don't treat the next main branch commit as a sibling of a stack
+        // which is based on the main branch.
+        let public_commits = dag.query_ancestors(dag.main_branch_commit.clone())?;
+        if dag.set_contains(&public_commits, current_oid)? {
+            siblings
+        } else {
+            siblings.difference(&public_commits)
+        }
+    }
+    .union(&CommitSet::from(current_oid));
+    let siblings = sorted_commit_set(repo, dag, &siblings)?;
+    let current_index = match siblings
+        .iter()
+        .position(|sibling| sibling.get_oid() == current_oid)
+    {
+        Some(current_index) => current_index,
+        None => eyre::bail!("Commit {current_oid:?} is not one of its own siblings"),
+    };
+
+    let num_available = match command {
+        Command::Next => siblings.len() - current_index - 1,
+        Command::Prev => current_index,
+    };
+    let num_to_move = match distance {
+        Distance::AllTheWay {
+            move_by_branches: _,
+        } => num_available,
+        Distance::NumCommits {
+            amount,
+            move_by_branches: _,
+        } => {
+            if amount > num_available {
                 writeln!(
                     effects.get_output_stream(),
                     "{}",
-                    glyphs.render(StyledString::styled(
+                    effects.get_glyphs().render(StyledString::styled(
                         format!(
-                            "No more {} commits to go to after traversing {}.",
-                            pluralize.unit.0, pluralize,
+                            "No more sibling commits to go to after traversing {}.",
+                            Pluralize {
+                                determiner: None,
+                                amount: num_available,
+                                unit: ("sibling", "siblings"),
+                            },
                         ),
                         BaseColor::Yellow.light()
                     ))?
                 )?;
-
-                if i == 0 {
-                    // If we didn't succeed in traversing any commits, then
-                    // treat the operation as a failure. Otherwise, assume that
-                    // the user just meant to go as many commits as possible.
+                if num_available == 0 {
+                    // As with `advance`, only treat the operation as a
+                    // failure if we couldn't traverse any commits at all.
                     return Ok(None);
-                } else {
-                    break;
-                }
-            }
-
-            (_, [only_child]) => only_child.get_oid(),
-            (Some(Towards::Newest), [.., newest_child]) => newest_child.get_oid(),
-            (Some(Towards::Oldest), [oldest_child, ..]) => oldest_child.get_oid(),
-            (Some(Towards::Interactive), [_, _, ..]) => {
-                match prompt_select_commit(
-                    Some(&header),
-                    "",
-                    candidate_commits,
-                    commit_descriptors,
-                )? {
-                    Some(oid) => oid,
-                    None => {
-                        return Ok(None);
-                    }
-                }
-            }
-            (None, [_, _, ..]) => {
-                writeln!(effects.get_output_stream(), "{header}")?;
-                for (j, child) in (0..).zip(candidate_commits.iter()) {
-                    let descriptor = if j == 0 {
-                        " (oldest)"
-                    } else if j + 1 == candidate_commits.len() {
-                        " (newest)"
-                    } else {
-                        ""
-                    };
-
-                    writeln!(
-                        effects.get_output_stream(),
-                        "  {} {}{}",
-                        glyphs.bullet_point,
-                        glyphs.render(child.friendly_describe(glyphs)?)?,
-                        descriptor
-                    )?;
                 }
-                writeln!(effects.get_output_stream(), "(Pass --oldest (-o), --newest (-n), or --interactive (-i) to select between ambiguous commits)")?;
-                return Ok(None);
             }
-        };
+            amount.min(num_available)
+        }
+    };
 
-        i += 1;
-    }
-    Ok(Some(current_oid))
+    let target_index = match command {
+        Command::Next => current_index + num_to_move,
+        Command::Prev => current_index - num_to_move,
+    };
+    Ok(Some(siblings[target_index].get_oid()))
 }
 
 /// Go forward or backward a certain number of commits.
@@ -311,12 +592,15 @@ pub fn traverse_commits(
     effects: &Effects,
     git_run_info: &GitRunInfo,
     command: Command,
+    target: Option<NextTarget>,
     options: &TraverseCommitsOptions,
 ) -> EyreExitOr<()> {
     let TraverseCommitsOptions {
         num_commits,
         all_the_way,
         move_by_branches,
+        sibling,
+        skip_obsolete,
         oldest,
         newest,
         interactive,
@@ -367,7 +651,7 @@ pub fn traverse_commits(
     )?;
     let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
     let event_cursor = event_replayer.make_default_cursor();
-    let dag = Dag::open_and_sync(
+    let mut dag = Dag::open_and_sync(
         effects,
         &repo,
         &event_replayer,
@@ -382,27 +666,56 @@ pub fn traverse_commits(
         }
     };
 
-    let current_oid = advance(
-        effects,
-        &repo,
-        &dag,
-        &mut [
-            &mut CommitOidDescriptor::new(true)?,
-            &mut RelativeTimeDescriptor::new(&repo, SystemTime::now())?,
-            &mut BranchesDescriptor::new(
-                &repo,
-                &head_info,
-                &references_snapshot,
-                &Redactor::Disabled,
-            )?,
-            &mut DifferentialRevisionDescriptor::new(&repo, &Redactor::Disabled)?,
-            &mut CommitMessageDescriptor::new(&Redactor::Disabled)?,
-        ],
-        head_oid,
-        command,
-        distance,
-        towards,
-    )?;
+    let towards = match towards {
+        Some(towards) => Some(towards),
+        None => {
+            if get_next_interactive(&repo)? {
+                Some(Towards::Interactive)
+            } else {
+                None
+            }
+        }
+    };
+    let skip_obsolete = skip_obsolete || get_navigation_skip_obsolete(&repo)?;
+    let mut commit_oid_descriptor = CommitOidDescriptor::new(true)?;
+    let mut relative_time_descriptor = RelativeTimeDescriptor::new(&repo, SystemTime::now())?;
+    let mut branches_descriptor =
+        BranchesDescriptor::new(&repo, &head_info, &references_snapshot, &Redactor::Disabled)?;
+    let mut differential_revision_descriptor =
+        DifferentialRevisionDescriptor::new(&repo, &Redactor::Disabled)?;
+    let mut commit_message_descriptor = CommitMessageDescriptor::new(&Redactor::Disabled)?;
+    let commit_descriptors: &mut [&mut dyn NodeDescriptor] = &mut [
+        &mut commit_oid_descriptor,
+        &mut relative_time_descriptor,
+        &mut branches_descriptor,
+        &mut differential_revision_descriptor,
+        &mut commit_message_descriptor,
+    ];
+    let current_oid = match target {
+        Some(target) => go_to_target(
+            effects,
+            &repo,
+            &mut dag,
+            &event_replayer,
+            event_cursor,
+            commit_descriptors,
+            head_oid,
+            target,
+            towards,
+        )?,
+        None if sibling => advance_sideways(effects, &repo, &dag, head_oid, command, distance)?,
+        None => advance(
+            effects,
+            &repo,
+            &dag,
+            commit_descriptors,
+            head_oid,
+            command,
+            distance,
+            towards,
+            skip_obsolete,
+        )?,
+    };
     let current_oid = match current_oid {
         None => return Ok(Err(ExitCode(1))),
         Some(current_oid) => current_oid,
@@ -480,6 +793,7 @@ pub fn switch(
         merge,
         target,
         detach,
+        message,
     } = switch_options;
 
     let now = SystemTime::now();
@@ -499,16 +813,27 @@ pub fn switch(
         &references_snapshot,
     )?;
 
-    let commits = resolve_default_smartlog_commits(effects, &repo, &mut dag)?;
-    let graph = make_smartlog_graph(
-        effects,
-        &repo,
-        &dag,
-        &event_replayer,
-        event_cursor,
-        &commits,
-        false,
-    )?;
+    let prompt = |header: Option<&str>, initial_query: &str, commits: Vec<Commit>| {
+        prompt_select_commit(
+            effects,
+            &repo,
+            header,
+            initial_query,
+            commits,
+            &mut [
+                &mut CommitOidDescriptor::new(true)?,
+                &mut RelativeTimeDescriptor::new(&repo, SystemTime::now())?,
+                &mut BranchesDescriptor::new(
+                    &repo,
+                    &head_info,
+                    &references_snapshot,
+                    &Redactor::Disabled,
+                )?,
+                &mut DifferentialRevisionDescriptor::new(&repo, &Redactor::Disabled)?,
+                &mut CommitMessageDescriptor::new(&Redactor::Disabled)?,
+            ],
+        )
+    };
 
     let initial_query = match switch_options {
         SwitchOptions {
@@ -517,6 +842,7 @@ pub fn switch(
             force: _,
             merge: _,
             detach: _,
+            message: _,
             target,
         } => Some(target.clone().unwrap_or_default()),
         SwitchOptions {
@@ -525,29 +851,60 @@ pub fn switch(
             force: _,
             merge: _,
             detach: _,
+            message: _,
             target: _,
         } => None,
     };
-    let target: Option<CheckoutTarget> = match initial_query {
-        None => target.clone().map(CheckoutTarget::Unknown),
-        Some(initial_query) => {
-            match prompt_select_commit(
-                None,
-                &initial_query,
-                graph.get_commits(),
-                &mut [
-                    &mut CommitOidDescriptor::new(true)?,
-                    &mut RelativeTimeDescriptor::new(&repo, SystemTime::now())?,
-                    &mut BranchesDescriptor::new(
-                        &repo,
-                        &head_info,
-                        &references_snapshot,
-                        &Redactor::Disabled,
-                    )?,
-                    &mut DifferentialRevisionDescriptor::new(&repo, &Redactor::Disabled)?,
-                    &mut CommitMessageDescriptor::new(&Redactor::Disabled)?,
-                ],
-            )? {
+    let target: Option<CheckoutTarget> = match (message, initial_query) {
+        (Some(pattern), _) => {
+            let expr = Expr::FunctionCall(
+                Cow::Borrowed("message"),
+                vec![Expr::Name(Cow::Borrowed(pattern.as_str()))],
+            );
+            let matching_commits = match eval(effects, &repo, &mut dag, &expr) {
+                Ok(commits) => commits.intersection(dag.query_draft_commits()?),
+                Err(err) => {
+                    writeln!(
+                        effects.get_error_stream(),
+                        "Invalid message pattern '{pattern}': {err}"
+                    )?;
+                    return Ok(Err(ExitCode(1)));
+                }
+            };
+            let mut matching_commits = sorted_commit_set(&repo, &dag, &matching_commits)?;
+            if matching_commits.is_empty() {
+                writeln!(
+                    effects.get_output_stream(),
+                    "No draft commits have a message matching '{pattern}'."
+                )?;
+                return Ok(Err(ExitCode(1)));
+            } else if matching_commits.len() == 1 {
+                let commit = matching_commits.remove(0);
+                Some(CheckoutTarget::Oid(commit.get_oid()))
+            } else {
+                let header =
+                    format!("Found multiple draft commits with a message matching '{pattern}':");
+                match prompt(Some(&header), "", matching_commits)? {
+                    Some(oid) => Some(CheckoutTarget::Oid(oid)),
+                    None => return Ok(Err(ExitCode(1))),
+                }
+            }
+        }
+
+        (None, None) => target.clone().map(CheckoutTarget::Unknown),
+
+        (None, Some(initial_query)) => {
+            let commits = resolve_default_smartlog_commits(effects, &repo, &mut dag)?;
+            let graph = make_smartlog_graph(
+                effects,
+                &repo,
+                &dag,
+                &event_replayer,
+                event_cursor,
+                &commits,
+                false,
+            )?;
+            match prompt(None, &initial_query, graph.get_commits())? {
                 Some(oid) => Some(CheckoutTarget::Oid(oid)),
                 None => return Ok(Err(ExitCode(1))),
             }
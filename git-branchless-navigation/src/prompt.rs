@@ -1,22 +1,37 @@
 //! An interactive prompt to select a commit.
 
+use lib::core::effects::Effects;
 use lib::core::node_descriptors::NodeDescriptor;
-use lib::git::{Commit, NonZeroOid};
+use lib::git::{Commit, NonZeroOid, Repo};
 
 /// Prompt the user to select a commit from the provided list
 /// of commits, and returns the OID of the selected commit.
+///
+/// The selected commit's message and diffstat are shown in a preview pane,
+/// which can be toggled to show the commit's full diff instead.
 #[cfg(unix)]
 pub fn prompt_select_commit(
+    effects: &Effects,
+    repo: &Repo,
     header: Option<&str>,
     initial_query: &str,
     commits: Vec<Commit>,
     commit_descriptors: &mut [&mut dyn NodeDescriptor],
 ) -> eyre::Result<Option<NonZeroOid>> {
-    skim::prompt_skim(header, initial_query, commits, commit_descriptors)
+    skim::prompt_skim(
+        effects,
+        repo,
+        header,
+        initial_query,
+        commits,
+        commit_descriptors,
+    )
 }
 
 #[cfg(not(unix))]
 pub fn prompt_select_commit(
+    effects: &Effects,
+    repo: &Repo,
     header: Option<&str>,
     initial_query: &str,
     commits: Vec<Commit>,
@@ -33,13 +48,16 @@ mod skim {
 
     use itertools::Itertools;
 
-    use lib::core::formatting::Glyphs;
+    use cursive::utils::markup::StyledString;
+    use lib::core::effects::Effects;
+    use lib::core::formatting::{Glyphs, StyledStringBuilder};
     use lib::core::node_descriptors::{render_node_descriptors, NodeDescriptor, NodeObject};
-    use lib::git::{Commit, NonZeroOid};
+    use lib::git::{Commit, NonZeroOid, Repo};
 
     use skim::{
-        prelude::SkimOptionsBuilder, AnsiString, DisplayContext, ItemPreview, Matches,
-        PreviewContext, Skim, SkimItem, SkimItemReceiver, SkimItemSender,
+        prelude::{Key, SkimOptionsBuilder},
+        AnsiString, DisplayContext, ItemPreview, Matches, PreviewContext, Skim, SkimItem,
+        SkimItemReceiver, SkimItemSender,
     };
 
     #[derive(Debug)]
@@ -94,10 +112,60 @@ mod skim {
         }
     }
 
+    /// The key which switches the preview pane between the diffstat and the
+    /// full diff of the selected commit.
+    const TOGGLE_DIFF_KEY: &str = "ctrl-o";
+
+    /// What to show about the selected commit in the preview pane, in addition
+    /// to its message.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    enum PreviewMode {
+        /// Show the number of lines changed in each file.
+        Stat,
+
+        /// Show the full diff.
+        Patch,
+    }
+
+    impl PreviewMode {
+        fn toggle(self) -> Self {
+            match self {
+                PreviewMode::Stat => PreviewMode::Patch,
+                PreviewMode::Patch => PreviewMode::Stat,
+            }
+        }
+    }
+
+    fn render_preview(
+        effects: &Effects,
+        repo: &Repo,
+        commit: &Commit,
+        preview_mode: PreviewMode,
+    ) -> eyre::Result<StyledString> {
+        let message = commit.friendly_preview()?;
+        let diff = match repo.get_patch_for_commit(effects, commit)? {
+            Some(diff) => diff,
+            // Merge commits don't have a single diff to show.
+            None => return Ok(message),
+        };
+        let diff = match preview_mode {
+            PreviewMode::Stat => StyledString::plain(diff.render_stat(80)?),
+            PreviewMode::Patch => diff.render_patch()?,
+        };
+        Ok(StyledStringBuilder::from_lines(vec![
+            message,
+            StyledString::new(),
+            diff,
+        ]))
+    }
+
     impl CommitSkimItem {
         fn from_descriptors(
+            effects: &Effects,
+            repo: &Repo,
             commit: &Commit,
             commit_descriptors: &mut [&mut dyn NodeDescriptor],
+            preview_mode: PreviewMode,
         ) -> eyre::Result<Self> {
             let glyphs = Glyphs::pretty();
             let styled_summary = render_node_descriptors(
@@ -111,54 +179,90 @@ mod skim {
             Ok(CommitSkimItem {
                 oid: commit.get_oid(),
                 styled_summary: glyphs.render(styled_summary)?,
-                styled_preview: Glyphs::pretty().render(commit.friendly_preview()?)?,
+                styled_preview: glyphs.render(render_preview(
+                    effects,
+                    repo,
+                    commit,
+                    preview_mode,
+                )?)?,
             })
         }
     }
 
     #[cfg(unix)]
     pub fn prompt_skim(
+        effects: &Effects,
+        repo: &Repo,
         header: Option<&str>,
         initial_query: &str,
         commits: Vec<Commit>,
         commit_descriptors: &mut [&mut dyn NodeDescriptor],
     ) -> eyre::Result<Option<NonZeroOid>> {
-        let options = SkimOptionsBuilder::default()
-            .height(Some("100%"))
-            .preview(Some(""))
-            .preview_window(Some("up:70%"))
-            .sync(true) // Consume all items before displaying selector.
-            .bind(vec!["Enter:accept"])
-            .header(header)
-            .query(Some(initial_query))
-            .build()
-            .map_err(|e| eyre!("building Skim options failed: {}", e))?;
-
-        let items: Vec<CommitSkimItem> = commits
-            .iter()
-            .map(|commit| CommitSkimItem::from_descriptors(commit, commit_descriptors))
-            .try_collect()?;
-
-        let rx_item = {
-            let (tx_item, rx_item): (SkimItemSender, SkimItemReceiver) = skim::prelude::unbounded();
-            for i in items {
-                tx_item.send(Arc::new(i))?;
+        let header = {
+            let toggle_hint = format!("({TOGGLE_DIFF_KEY} to toggle the full diff)");
+            match header {
+                Some(header) => format!("{header} {toggle_hint}"),
+                None => toggle_hint,
             }
-            rx_item
         };
 
-        match Skim::run_with(&options, Some(rx_item)) {
-            Some(result) => {
-                if result.is_abort {
-                    return Ok(None);
+        let mut preview_mode = PreviewMode::Stat;
+        let mut query = initial_query.to_owned();
+        loop {
+            let options = SkimOptionsBuilder::default()
+                .height(Some("100%"))
+                .preview(Some(""))
+                .preview_window(Some("up:70%"))
+                .sync(true) // Consume all items before displaying selector.
+                .bind(vec!["Enter:accept"])
+                // Skim can't change the contents of the preview pane in
+                // response to a key press, so exit and re-prompt instead.
+                .expect(Some(TOGGLE_DIFF_KEY.to_owned()))
+                .header(Some(header.as_str()))
+                .query(Some(query.as_str()))
+                .build()
+                .map_err(|e| eyre!("building Skim options failed: {}", e))?;
+
+            let items: Vec<CommitSkimItem> = commits
+                .iter()
+                .map(|commit| {
+                    CommitSkimItem::from_descriptors(
+                        effects,
+                        repo,
+                        commit,
+                        commit_descriptors,
+                        preview_mode,
+                    )
+                })
+                .try_collect()?;
+
+            let rx_item = {
+                let (tx_item, rx_item): (SkimItemSender, SkimItemReceiver) =
+                    skim::prelude::unbounded();
+                for i in items {
+                    tx_item.send(Arc::new(i))?;
+                }
+                rx_item
+            };
+
+            match Skim::run_with(&options, Some(rx_item)) {
+                Some(result) => {
+                    if result.is_abort {
+                        return Ok(None);
+                    }
+                    if result.final_key == Key::Ctrl('o') {
+                        preview_mode = preview_mode.toggle();
+                        query = result.query;
+                        continue;
+                    }
+                    let selected = result
+                        .selected_items
+                        .first()
+                        .and_then(|item| (*item).as_any().downcast_ref::<CommitSkimItem>());
+                    return Ok(selected.map(|c| c.oid));
                 }
-                let selected = result
-                    .selected_items
-                    .first()
-                    .and_then(|item| (*item).as_any().downcast_ref::<CommitSkimItem>());
-                Ok(selected.map(|c| c.oid))
+                None => return Ok(None),
             }
-            None => Ok(None),
         }
     }
 }
@@ -18,7 +18,6 @@ use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use clap::{Args, Command as ClapCommand, CommandFactory, Parser, ValueEnum};
-use lib::git::NonZeroOid;
 
 /// A revset expression. Can be a commit hash, branch name, or one of the
 /// various revset functions.
@@ -39,6 +38,35 @@ impl Display for Revset {
     }
 }
 
+/// A length of time, such as `30s`, `5m`, `1h`, or `2d`.
+#[derive(Clone, Copy, Debug)]
+pub struct Duration(pub std::time::Duration);
+
+impl FromStr for Duration {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (amount, multiplier) = if let Some(amount) = s.strip_suffix('d') {
+            (amount, 24 * 60 * 60)
+        } else if let Some(amount) = s.strip_suffix('h') {
+            (amount, 60 * 60)
+        } else if let Some(amount) = s.strip_suffix('m') {
+            (amount, 60)
+        } else if let Some(amount) = s.strip_suffix('s') {
+            (amount, 1)
+        } else {
+            (s, 1)
+        };
+        let amount: u64 = amount
+            .parse()
+            .map_err(|_| format!("invalid duration (expected e.g. `30s`, `5m`, `1h`): {s}"))?;
+        let seconds = amount
+            .checked_mul(multiplier)
+            .ok_or_else(|| format!("duration is too long: {s}"))?;
+        Ok(Self(std::time::Duration::from_secs(seconds)))
+    }
+}
+
 /// A command wrapped by `git-branchless wrap`. The arguments are forwarded to
 /// `git`.
 #[derive(Debug, Parser)]
@@ -94,6 +122,33 @@ pub struct MoveOptions {
     /// executing it.
     #[clap(action, long = "debug-dump-rebase-plan")]
     pub dump_rebase_plan: bool,
+
+    /// Keep the original committer timestamps of rewritten commits, rather
+    /// than updating them to the current time. This can also be enabled for
+    /// all commands with the `branchless.restack.preserveTimestamps` config
+    /// option.
+    #[clap(action, long = "preserve-timestamps")]
+    pub preserve_timestamps: bool,
+
+    /// Set the author of rewritten commits to the current user, and the
+    /// author timestamp to the current time. When amending, only the amended
+    /// commit is changed. Requires an in-memory rebase.
+    #[clap(
+        action,
+        long = "reset-author",
+        conflicts_with_all(&["force_on_disk", "merge"])
+    )]
+    pub reset_author: bool,
+
+    /// Set the committer of rewritten commits to be the same as their author,
+    /// including the timestamp. When amending, only the amended commit is
+    /// changed. Requires an in-memory rebase.
+    #[clap(
+        action,
+        long = "committer-as-author",
+        conflicts_with_all(&["force_on_disk", "merge"])
+    )]
+    pub committer_as_author: bool,
 }
 
 /// Options for traversing commits.
@@ -113,6 +168,19 @@ pub struct TraverseCommitsOptions {
     #[clap(action, short = 'b', long = "branch")]
     pub move_by_branches: bool,
 
+    /// Move between sibling commits (other children of the current commit's
+    /// parent) rather than between parent and child commits. `git next` goes
+    /// to newer siblings and `git prev` goes to older siblings.
+    #[clap(action, long = "sibling", conflicts_with("move_by_branches"))]
+    pub sibling: bool,
+
+    /// Don't stop at obsolete commits (old versions of commits which have
+    /// since been rewritten), but continue past them to the next commit which
+    /// isn't obsolete. Can be enabled by default with the
+    /// `branchless.navigation.skipObsolete` config option.
+    #[clap(action, long = "skip-obsolete", conflicts_with("move_by_branches"))]
+    pub skip_obsolete: bool,
+
     /// When encountering multiple next commits, choose the oldest.
     #[clap(action, short = 'o', long = "oldest")]
     pub oldest: bool,
@@ -170,6 +238,17 @@ pub struct SwitchOptions {
     #[clap(action, short = 'd', long = "detach")]
     pub detach: bool,
 
+    /// Check out the draft commit whose message matches the provided pattern.
+    /// If several commits match, interactively prompt which to check out.
+    ///
+    /// The pattern uses the same syntax as the `message()` revset function.
+    #[clap(
+        value_parser,
+        long = "message",
+        conflicts_with_all(&["interactive", "target"])
+    )]
+    pub message: Option<String>,
+
     /// The commit or branch to check out.
     ///
     /// If this is not provided, then interactive commit selection starts as
@@ -295,6 +374,53 @@ pub struct QueryArgs {
     /// stable for use in scripts.
     #[clap(action, short = 'r', long = "raw", conflicts_with("show_branches"))]
     pub raw: bool,
+
+    /// With `--raw`, terminate each OID with a NUL character rather than a
+    /// newline.
+    #[clap(action, short = 'z', requires("raw"))]
+    pub null_terminated: bool,
+
+    /// Print each matching commit according to the given template, one per
+    /// line. The placeholders `{oid}`, `{short_oid}`, `{message}` (the first
+    /// line of the commit message), and `{branches}` (space-separated) are
+    /// replaced with the corresponding values for each commit. Use `{{` and
+    /// `}}` for literal braces.
+    #[clap(
+        value_parser,
+        long = "format",
+        conflicts_with_all(&["show_branches", "raw"])
+    )]
+    pub format: Option<String>,
+
+    /// Print the resulting commits as a JSON array of objects, each
+    /// containing the commit's OID, parents, message, author, branches, and
+    /// whether it's a draft commit.
+    #[clap(
+        action,
+        long = "json",
+        conflicts_with_all(&["show_branches", "raw", "format"])
+    )]
+    pub json: bool,
+
+    /// Render the resulting commits as a graph, in the same style as the
+    /// smartlog. Commits connecting the resulting commits which don't match
+    /// the query are elided.
+    #[clap(
+        action,
+        long = "graph",
+        conflicts_with_all(&["show_branches", "raw", "format", "json"])
+    )]
+    pub graph: bool,
+
+    /// Run the given shell command once for each resulting commit, in
+    /// topological order, with `{oid}` in the command replaced by the commit's
+    /// OID. Stops at the first command which fails.
+    #[clap(
+        value_parser,
+        long = "exec",
+        conflicts_with_all(&["show_branches", "raw", "format", "json", "graph"])
+    )]
+    pub exec: Option<String>,
 }
 
 /// Create a commit by interactively selecting which changes to include.
@@ -310,9 +436,36 @@ pub struct RecordArgs {
     #[clap(action, short = 'i', long = "interactive")]
     pub interactive: bool,
 
+    /// Instead of creating a new commit, amend the selected changes into an
+    /// existing draft commit in the current stack, chosen after selecting
+    /// the changes. The selector is then shown again for the remaining
+    /// changes, so that they can be amended into other commits, until there
+    /// are no changes left or the selection is cancelled.
+    #[clap(
+        action,
+        long = "amend",
+        requires("interactive"),
+        conflicts_with_all(&["messages", "create", "detach", "insert", "stash", "allow_empty"])
+    )]
+    pub amend: bool,
+
+    /// After selecting changes interactively, open the selected contents of
+    /// each changed file in your editor before committing. This makes it
+    /// possible to commit only part of a changed line, or to otherwise
+    /// adjust the exact text to be committed. The working copy is not
+    /// modified.
+    #[clap(action, long = "edit-selection", requires("interactive"))]
+    pub edit_selection: bool,
+
     /// Create and switch to a new branch with the given name before
-    /// committing.
-    #[clap(action, short = 'c', long = "create")]
+    /// committing, so that the branch points to the new commit.
+    #[clap(
+        action,
+        short = 'c',
+        long = "create",
+        visible_short_alias = 'b',
+        visible_alias = "branch"
+    )]
     pub create: Option<String>,
 
     /// Detach the current branch before committing.
@@ -327,6 +480,65 @@ pub struct RecordArgs {
     /// After making the new commit, switch back to the previous commit.
     #[clap(action, short = 's', long = "stash", conflicts_with_all(&["create", "detach"]))]
     pub stash: bool,
+
+    /// Create the commit even if there are no changes to commit. This can be
+    /// useful to create a marker commit in a stack.
+    #[clap(action, long = "allow-empty", conflicts_with("interactive"))]
+    pub allow_empty: bool,
+
+    /// Don't add the trailers configured with `branchless.commit.trailer`,
+    /// `branchless.commit.branchTrailer`, and `branchless.commit.signoff` to
+    /// the commit message.
+    #[clap(action, long = "no-trailers")]
+    pub no_trailers: bool,
+}
+
+/// Absorb changes in the working copy into the commits in the current stack.
+#[derive(Debug, Parser)]
+pub struct AbsorbArgs {
+    /// Only print which commit each change would be absorbed into, without
+    /// modifying any commits.
+    #[clap(action, long = "dry-run")]
+    pub dry_run: bool,
+
+    /// Options for moving commits.
+    #[clap(flatten)]
+    pub move_options: MoveOptions,
+}
+
+/// Split a commit in two by interactively selecting which of its changes to
+/// keep in the first commit.
+#[derive(Debug, Parser)]
+pub struct SplitArgs {
+    /// The commit to split.
+    #[clap(value_parser, default_value = ".")]
+    pub revset: Revset,
+
+    /// Options for resolving revset expressions.
+    #[clap(flatten)]
+    pub resolve_revset_options: ResolveRevsetOptions,
+
+    /// Options for moving commits.
+    #[clap(flatten)]
+    pub move_options: MoveOptions,
+}
+
+/// Edit a stack of commits by editing a list of commands in an editor, similar
+/// to `git rebase --interactive`.
+#[derive(Debug, Parser)]
+pub struct EditArgs {
+    /// The commits to edit. These must form a single range of commits without
+    /// any gaps or merge commits.
+    #[clap(value_parser, default_value = "stack()")]
+    pub revsets: Vec<Revset>,
+
+    /// Options for resolving revset expressions.
+    #[clap(flatten)]
+    pub resolve_revset_options: ResolveRevsetOptions,
+
+    /// Options for moving commits.
+    #[clap(flatten)]
+    pub move_options: MoveOptions,
 }
 
 /// Display a nice graph of the commits you've recently worked on.
@@ -366,16 +578,62 @@ pub enum ForgeKind {
     Branch,
 
     /// Force-push branches to the remote and create a pull request for each
-    /// branch using the `gh` command-line tool. WARNING: likely buggy!
+    /// branch using the `gh` command-line tool. Each pull request is based on
+    /// the pull request of the previous commit in the stack. To authenticate
+    /// with a token instead of the `gh` login, set
+    /// `branchless.submit.github.token`. WARNING: likely buggy!
     Github,
 
+    /// Force-push branches to the remote and create a merge request for each
+    /// branch using the `glab` command-line tool. Each merge request targets
+    /// the branch of the previous commit in the stack. To authenticate with a
+    /// token instead of the `glab` login, set `branchless.submit.gitlab.token`.
+    Gitlab,
+
     /// Submit code reviews to Phabricator using the `arc` command-line tool.
+    /// To call the Conduit API directly instead, set
+    /// `branchless.submit.phabricator.conduitUri` and
+    /// `branchless.submit.phabricator.conduitToken`.
     Phabricator,
+
+    /// Push commits to `refs/for/<main branch>` on the default push remote to
+    /// create or update a Gerrit change for each commit. A `Change-Id` trailer
+    /// is added to each commit's message if it doesn't already have one.
+    Gerrit,
+
+    /// Invoke an external `branchless-forge-<name>` executable on `PATH` to
+    /// create and update code reviews, where the name is set with
+    /// `branchless.submit.externalForge`. If that option is set, this forge is
+    /// used by default.
+    External,
+
+    /// Format the stack as a patch series with `git format-patch` and mail it
+    /// with `git send-email`, which reads the recipients from the
+    /// `sendemail.*` config options. Each submission sends the next version of
+    /// the series as a reply to the previous one. With `--draft`, the patches
+    /// are marked as RFC.
+    SendEmail,
+}
+
+/// How to report the results of submitting commits.
+#[derive(Clone, Debug, ValueEnum)]
+pub enum SubmitOutputFormat {
+    /// Print a human-readable summary.
+    Text,
+
+    /// Print a JSON object describing the result for each commit to stdout.
+    /// All other output is printed to stderr instead.
+    Json,
 }
 
 /// Push commits to a remote.
 #[derive(Debug, Parser)]
+#[clap(args_conflicts_with_subcommands = true)]
 pub struct SubmitArgs {
+    /// The subcommand to run instead of submitting commits, if any.
+    #[clap(subcommand)]
+    pub subcommand: Option<SubmitSubcommand>,
+
     /// The commits to push to the forge. Unless `--create` is passed, this will
     /// only push commits that already have associated remote objects on the
     /// forge.
@@ -400,9 +658,24 @@ pub struct SubmitArgs {
     pub create: bool,
 
     /// If the forge supports it, create code reviews in "draft" mode.
-    #[clap(action, short = 'd', long = "draft")]
+    #[clap(action, short = 'd', long = "draft", conflicts_with = "undraft")]
     pub draft: bool,
 
+    /// If the forge supports it, mark any existing code reviews for the
+    /// commits which are in "draft" mode as ready for review.
+    #[clap(action, long = "undraft")]
+    pub undraft: bool,
+
+    /// Instead of pushing any commits, check which of them have code reviews
+    /// which were merged or closed on the forge, and hide those commits
+    /// locally and delete their branches.
+    #[clap(
+        action,
+        long = "sync-status",
+        conflicts_with_all(&["create", "draft", "undraft"])
+    )]
+    pub sync_status: bool,
+
     /// If the forge supports it, an optional message to include with the create
     /// or update operation.
     #[clap(short = 'm', long = "message")]
@@ -419,9 +692,64 @@ pub struct SubmitArgs {
     pub execution_strategy: Option<TestExecutionStrategy>,
 
     /// Don't push or create anything. Instead, report what would be pushed or
-    /// created. (This may still trigger fetching information from the forge.)
+    /// created for each commit, and why any commits would be skipped. (This
+    /// may still trigger fetching information from the forge.)
     #[clap(short = 'n', long = "dry-run")]
     pub dry_run: bool,
+
+    /// Before pushing anything, list the commits which would be created or
+    /// updated in an editor, so that some of them can be deselected.
+    #[clap(short = 'i', long = "interactive", conflicts_with = "sync_status")]
+    pub interactive: bool,
+
+    /// The format to report the results in. With `json`, the result for each
+    /// commit includes its name, the URL of its code review (if known), what
+    /// was done to it, and any error.
+    #[clap(
+        long = "output",
+        default_value = "text",
+        conflicts_with = "sync_status"
+    )]
+    pub output_format: SubmitOutputFormat,
+}
+
+/// `submit` subcommands.
+#[derive(Debug, Parser)]
+pub enum SubmitSubcommand {
+    /// Query the forge for the code review of each commit, and print whether
+    /// it's open, its review state, and its CI status. The results are cached
+    /// and shown in the smartlog.
+    Status {
+        /// The commits to query.
+        #[clap(value_parser, default_value = "stack()")]
+        revsets: Vec<Revset>,
+
+        /// Options for resolving revset expressions.
+        #[clap(flatten)]
+        resolve_revset_options: ResolveRevsetOptions,
+
+        /// The Git hosting provider to query. If not provided, it's detected
+        /// in the same way as when submitting commits.
+        #[clap(short = 'F', long = "forge")]
+        forge_kind: Option<ForgeKind>,
+
+        /// Print the statuses cached by the last query instead of querying
+        /// the forge, such as when offline.
+        #[clap(long = "cached", conflicts_with = "forge_kind")]
+        cached: bool,
+    },
+}
+
+/// Check out a GitHub pull request and the pull requests it's stacked on.
+#[derive(Debug, Parser)]
+pub struct CheckoutPrArgs {
+    /// The number or URL of the pull request to check out.
+    #[clap(value_parser)]
+    pub pull_request: String,
+
+    /// Create the local branches without checking out the pull request.
+    #[clap(long = "no-checkout")]
+    pub no_checkout: bool,
 }
 
 /// Run a command on each commit in a given set and aggregate the results.
@@ -435,6 +763,12 @@ pub struct TestArgs {
 /// FIXME: write man-page text
 #[derive(Debug, Parser)]
 pub enum Command {
+    /// Amend each change in the working copy into the draft commit in the
+    /// current stack which last modified the lines that it touches, and
+    /// restack any descendant commits. Changes which can't be attributed to
+    /// exactly one commit are left in the working copy.
+    Absorb(AbsorbArgs),
+
     /// Amend the current HEAD commit.
     Amend {
         /// Options for moving commits.
@@ -446,16 +780,70 @@ pub enum Command {
         /// formatting or refactoring changes.
         #[clap(long)]
         reparent: bool,
+
+        /// Amend the changes into the given ancestor commit of HEAD instead
+        /// of HEAD itself, and restack the commits between them and any other
+        /// descendants in memory.
+        #[clap(value_parser, long = "to")]
+        to: Option<Revset>,
+
+        /// If restacking the descendant commits causes a merge conflict,
+        /// resolve each conflicting commit interactively in memory, so that
+        /// the whole stack is restacked. The working copy isn't touched.
+        #[clap(action, long = "resolve", conflicts_with_all(&["force_on_disk", "merge"]))]
+        resolve: bool,
+    },
+
+    /// Move to the first draft commit of the current stack. This is the same
+    /// as `git prev --all`.
+    Bottom {
+        /// If the local changes conflict with the destination commit, attempt
+        /// to merge them.
+        #[clap(action, short = 'm', long = "merge")]
+        merge: bool,
+
+        /// If the local changes conflict with the destination commit, discard
+        /// them. (Use with caution!)
+        #[clap(action, short = 'f', long = "force", conflicts_with("merge"))]
+        force: bool,
     },
 
     /// Gather information about recent operations to upload as part of a bug
     /// report.
     BugReport,
 
+    /// Fetch a GitHub pull request, along with any pull requests that it's
+    /// stacked on, and create a local branch for each of them, so that the
+    /// stack can be reviewed with commands such as `git next` and `git prev`.
+    CheckoutPr(CheckoutPrArgs),
+
+    /// Internal use. Print the completions for the last word of a partially
+    /// typed revset expression, for use by shell completion scripts.
+    #[clap(hide = true)]
+    CompleteRevset {
+        /// The partially typed revset expression.
+        #[clap(value_parser, default_value = "")]
+        word: String,
+    },
+
     /// Use the partial commit selector UI as a Git-compatible difftool; see
     /// git-difftool(1) for more information on Git difftools.
     Difftool(scm_diff_editor::Opts),
 
+    /// Reorder, squash, split, drop, and reword the commits in a stack by
+    /// editing a list of commands in an editor, similar to `git rebase
+    /// --interactive`. The edited stack is constructed in memory and can be
+    /// reverted with `git undo`.
+    Edit(EditArgs),
+
+    /// Export or import the event log, such as to back it up or to migrate it
+    /// to a new clone of the repository.
+    EventLog {
+        /// The subcommand to run.
+        #[clap(subcommand)]
+        subcommand: EventLogSubcommand,
+    },
+
     /// Run internal garbage collection.
     Gc,
 
@@ -487,6 +875,24 @@ pub enum Command {
     /// Initialize the branchless workflow for this repository.
     Init(InitArgs),
 
+    /// Show a chronological history of the operations recorded in the event
+    /// log, grouped by the command which caused them.
+    Journal {
+        /// Only show events which happened at most this long ago (for example,
+        /// `30m`, `2h`, or `1d`).
+        #[clap(value_parser, long = "since")]
+        since: Option<Duration>,
+
+        /// Only show events which happened at least this long ago (for
+        /// example, `30m`, `2h`, or `1d`).
+        #[clap(value_parser, long = "until")]
+        until: Option<Duration>,
+
+        /// Only show events of the given type. May be passed multiple times.
+        #[clap(value_enum, short = 't', long = "type")]
+        event_types: Vec<JournalEventType>,
+    },
+
     /// Install git-branchless's man-pages to the given path.
     InstallManPages(InstallManPagesArgs),
 
@@ -501,7 +907,8 @@ pub enum Command {
     /// `post-commit` hooks are not called during in-memory rebases.
     Move {
         /// The source commit to move. This commit, and all of its descendants,
-        /// will be moved.
+        /// will be moved. Can be passed multiple times to move several subtrees
+        /// onto the destination as part of the same operation.
         #[clap(action(clap::ArgAction::Append), short = 's', long = "source")]
         source: Vec<Revset>,
 
@@ -518,7 +925,7 @@ pub enum Command {
 
         /// A set of specific commits to move. These will be removed from their
         /// current locations and any unmoved children will be moved to their
-        /// nearest unmoved ancestor.
+        /// nearest unmoved ancestor. Can be passed multiple times.
         #[clap(
             action(clap::ArgAction::Append),
             short = 'x',
@@ -529,6 +936,10 @@ pub enum Command {
 
         /// The destination commit to move all source commits onto. If not
         /// provided, defaults to the current commit.
+        ///
+        /// If the expression refers to `$1`, then it's evaluated separately
+        /// for each root of the source commits, with `$1` standing for that
+        /// root, so that each subtree can be moved onto its own destination.
         #[clap(value_parser, short = 'd', long = "dest")]
         dest: Option<Revset>,
 
@@ -548,6 +959,161 @@ pub enum Command {
         /// Only supported if the moved subtree has a single head.
         #[clap(action, short = 'I', long = "insert")]
         insert: bool,
+
+        /// Insert the subtree between the given commit and its children, if
+        /// any. Equivalent to `--dest <commit> --insert`.
+        #[clap(
+            value_parser,
+            long = "insert-after",
+            conflicts_with_all(&["dest", "fixup", "insert", "insert_before"])
+        )]
+        insert_after: Option<Revset>,
+
+        /// Insert the subtree between the given commit and its parent, moving
+        /// the given commit and its descendants on top of the subtree. Only
+        /// supported if the moved subtree has a single head.
+        #[clap(
+            value_parser,
+            long = "insert-before",
+            conflicts_with_all(&["dest", "fixup", "insert"])
+        )]
+        insert_before: Option<Revset>,
+
+        /// Open the plan for the move in an editor before executing it, so that
+        /// individual commits can be dropped, reordered, or moved onto
+        /// different parents.
+        #[clap(
+            action,
+            short = 'i',
+            long = "interactive",
+            conflicts_with_all(&["fixup", "insert", "insert_after", "insert_before"])
+        )]
+        interactive: bool,
+
+        /// Squash any `fixup!` and `squash!` commits being moved into the
+        /// commits that they refer to, like `git rebase --autosquash`.
+        #[clap(
+            action,
+            long = "autosquash",
+            conflicts_with_all(&["fixup", "interactive"])
+        )]
+        autosquash: bool,
+
+        /// Don't move any commits. Instead, check whether the move would cause
+        /// any merge conflicts, and print a preview of the resulting commit
+        /// graph.
+        #[clap(
+            action,
+            short = 'n',
+            long = "dry-run",
+            conflicts_with_all(&["force_on_disk", "merge"])
+        )]
+        dry_run: bool,
+
+        /// If a merge conflict occurs, resolve it interactively in memory
+        /// instead of aborting the operation. The resolution is recorded and
+        /// reused if the same conflict occurs again. The working copy isn't
+        /// touched.
+        #[clap(
+            action,
+            long = "resolve",
+            conflicts_with_all(&["force_on_disk", "merge", "dry_run"])
+        )]
+        resolve: bool,
+
+        /// If the working copy has uncommitted changes, stash them before
+        /// moving commits and restore them afterwards, rather than refusing
+        /// to modify the working copy. Can also be enabled with
+        /// `branchless.restack.autoStash`.
+        #[clap(action, long = "autostash", conflicts_with = "dry_run")]
+        autostash: bool,
+
+        /// Drop commits which become empty as a result of being moved without
+        /// asking. Otherwise, you're asked whether to drop them when running
+        /// interactively. Commits which were already empty are always kept.
+        #[clap(action, long = "skip-empty")]
+        skip_empty: bool,
+
+        /// Instead of moving the source commits onto the destination, create a
+        /// merge commit whose parents are the destination and the head of the
+        /// source commits. Any branches pointing to the destination are moved
+        /// to the merge commit. Only supported if the source commits have a
+        /// single head.
+        #[clap(
+            action,
+            long = "create-merge",
+            conflicts_with_all(&[
+                "exact",
+                "fixup",
+                "insert",
+                "insert_after",
+                "insert_before",
+                "interactive",
+                "autosquash",
+                "dry_run",
+                "resolve",
+            ])
+        )]
+        create_merge: bool,
+
+        /// Instead of moving the source commits onto the destination, split
+        /// each of them into a commit with its changes under the given path
+        /// and a commit with the rest of its changes. The commits with the
+        /// changes under the path form a new stack on top of the destination,
+        /// and the commits with the rest of the changes stay where they were.
+        /// Only supported if the source commits form a linear stack.
+        #[clap(
+            value_parser,
+            long = "extract-path",
+            value_name = "PATH",
+            conflicts_with_all(&[
+                "exact",
+                "fixup",
+                "insert",
+                "insert_after",
+                "insert_before",
+                "interactive",
+                "autosquash",
+                "dry_run",
+                "resolve",
+                "create_merge",
+            ])
+        )]
+        extract_path: Option<PathBuf>,
+
+        /// Continue a move which was interrupted during an on-disk rebase (by
+        /// a merge conflict, a crash, etc.). If the rebase is no longer in
+        /// progress, the entire move is started again from the beginning.
+        #[clap(
+            action,
+            long = "continue",
+            conflicts_with_all(&[
+                "source",
+                "base",
+                "exact",
+                "dest",
+                "insert_after",
+                "insert_before",
+                "abort_move",
+            ])
+        )]
+        continue_move: bool,
+
+        /// Abort a move which was interrupted during an on-disk rebase, and
+        /// restore the repository to how it was before the move started.
+        #[clap(
+            action,
+            long = "abort",
+            conflicts_with_all(&[
+                "source",
+                "base",
+                "exact",
+                "dest",
+                "insert_after",
+                "insert_before",
+            ])
+        )]
+        abort_move: bool,
     },
 
     /// Move to a later commit in the current stack.
@@ -555,6 +1121,44 @@ pub enum Command {
         /// Options for traversing commits.
         #[clap(flatten)]
         traverse_commits_options: TraverseCommitsOptions,
+
+        /// Go to the nearest descendant commit which would have a merge
+        /// conflict if it were restacked onto the newest version of its
+        /// rewritten parent commit.
+        #[clap(
+            action,
+            long = "conflict",
+            conflicts_with_all(&[
+                "num_commits",
+                "all_the_way",
+                "move_by_branches",
+                "sibling",
+                "failed"
+            ])
+        )]
+        conflict: bool,
+
+        /// Go to the nearest descendant commit with a failing cached result for
+        /// the most recent `git test run` command.
+        #[clap(
+            action,
+            long = "failed",
+            conflicts_with_all(&["num_commits", "all_the_way", "move_by_branches", "sibling"])
+        )]
+        failed: bool,
+    },
+
+    /// Pin commits so that `git sync` and `git restack` don't rebase any stack
+    /// containing them, such as for work which is intentionally based on an
+    /// old release branch. Pinned commits stay pinned after they're amended.
+    Pin {
+        /// The commits to pin.
+        #[clap(value_parser, default_value = "@")]
+        revsets: Vec<Revset>,
+
+        /// Options for resolving revset expressions.
+        #[clap(flatten)]
+        resolve_revset_options: ResolveRevsetOptions,
     },
 
     /// Move to an earlier commit in the current stack.
@@ -601,6 +1205,23 @@ pub enum Command {
     /// Create a commit by interactively selecting which changes to include.
     Record(RecordArgs),
 
+    /// Reorder a range of commits within a stack by editing their order in an
+    /// editor.
+    Reorder {
+        /// The commits to reorder. These must form a single range of commits
+        /// without any gaps or merge commits.
+        #[clap(value_parser, default_value = "stack()")]
+        revsets: Vec<Revset>,
+
+        /// Options for resolving revset expressions.
+        #[clap(flatten)]
+        resolve_revset_options: ResolveRevsetOptions,
+
+        /// Options for moving commits.
+        #[clap(flatten)]
+        move_options: MoveOptions,
+    },
+
     /// Reword commits.
     Reword {
         /// Zero or more commits to reword.
@@ -637,6 +1258,24 @@ pub enum Command {
         /// use with `git rebase --autosquash`) targeting the supplied commit.
         #[clap(value_parser, long = "fixup", conflicts_with_all(&["messages", "discard"]))]
         commit_to_fixup: Option<Revset>,
+
+        /// Don't add the trailers configured with `branchless.commit.trailer`,
+        /// `branchless.commit.branchTrailer`, and `branchless.commit.signoff`
+        /// to the reworded commit messages.
+        #[clap(action, long = "no-trailers")]
+        no_trailers: bool,
+
+        /// Set the author of the reworded commits to the current user, and
+        /// the author timestamp to the current time. Descendants which are
+        /// rewritten as a result keep their original authors.
+        #[clap(action, long = "reset-author")]
+        reset_author: bool,
+
+        /// Set the committer of the reworded commits to be the same as their
+        /// author, including the timestamp. Descendants which are rewritten
+        /// as a result keep their original committers.
+        #[clap(action, long = "committer-as-author")]
+        committer_as_author: bool,
     },
 
     /// `smartlog` command.
@@ -650,6 +1289,41 @@ pub enum Command {
         subcommand: SnapshotSubcommand,
     },
 
+    /// Split a commit in two by interactively selecting which of its changes
+    /// to keep in the first commit. The remaining changes are left in a second
+    /// commit on top of it, and any descendant commits are restacked onto the
+    /// second commit. To split a commit into more than two commits, split the
+    /// second commit again.
+    Split(SplitArgs),
+
+    /// Squash any `fixup!` and `squash!` commits into the commits that they
+    /// refer to, like `git rebase --autosquash`, but without touching the
+    /// working copy.
+    SquashFixups {
+        /// The commits to search for `fixup!` and `squash!` commits and their
+        /// targets.
+        #[clap(value_parser, default_value = "stack()")]
+        revsets: Vec<Revset>,
+
+        /// Options for resolving revset expressions.
+        #[clap(flatten)]
+        resolve_revset_options: ResolveRevsetOptions,
+
+        /// Options for moving commits.
+        #[clap(flatten)]
+        move_options: MoveOptions,
+    },
+
+    /// Shelve working copy changes as a working copy snapshot, and restore
+    /// them later. Unlike `git stash`, each operation is recorded in the
+    /// event log, so it shows up in `git branchless journal` and can be
+    /// reverted with `git undo`.
+    Stash {
+        /// The subcommand to run.
+        #[clap(subcommand)]
+        subcommand: StashSubcommand,
+    },
+
     /// Push commits to a remote.
     Submit(SubmitArgs),
 
@@ -663,7 +1337,8 @@ pub enum Command {
     /// Move any local commit stacks on top of the main branch.
     Sync {
         /// Run `git fetch` to update remote references before carrying out the
-        /// sync.
+        /// sync. If the main branch has diverged from its upstream branch, it's
+        /// rebased or merged according to `branchless.sync.strategy`.
         #[clap(
             action,
             short = 'p',
@@ -673,12 +1348,51 @@ pub enum Command {
         )]
         pull: bool,
 
+        /// When pulling, fetch only from this remote and update the main
+        /// branch from the branch of the same name on this remote, rather
+        /// than from the upstream branch it tracks. Defaults to the value of
+        /// `branchless.sync.remote`, if set.
+        #[clap(value_parser, long = "remote", requires = "pull")]
+        remote: Option<String>,
+
+        /// Delete local branches which have landed in the main branch, either
+        /// because they're now ancestors of the main branch or because their
+        /// patches were applied to it, and hide their commits. Can also be
+        /// enabled with `branchless.sync.pruneMerged`.
+        #[clap(action, long = "prune-merged")]
+        prune_merged: bool,
+
+        /// Don't change anything. Instead, report which stacks would be synced,
+        /// which would have merge conflicts, and which branches would be
+        /// updated or deleted. (This still fetches if `--pull` is passed.)
+        #[clap(action, short = 'n', long = "dry-run", conflicts_with = "resume")]
+        dry_run: bool,
+
+        /// If the working copy has uncommitted changes, stash them before
+        /// syncing and restore them afterwards, rather than refusing to modify
+        /// the working copy. Can also be enabled with
+        /// `branchless.restack.autoStash`.
+        #[clap(action, long = "autostash", conflicts_with = "dry_run")]
+        autostash: bool,
+
         /// Options for moving commits.
         #[clap(flatten)]
         move_options: MoveOptions,
 
-        /// The commits whose stacks will be moved on top of the main branch. If
-        /// no commits are provided, all draft commits will be synced.
+        /// Stop syncing once this much time has elapsed (for example, `30s`,
+        /// `5m`, or `1h`). Any stacks which haven't been synced yet are
+        /// recorded, and can be synced later with `--continue`.
+        #[clap(value_parser, long = "max-duration")]
+        max_duration: Option<Duration>,
+
+        /// Resume a sync which was previously stopped due to `--max-duration`.
+        #[clap(action, long = "continue", conflicts_with = "revsets")]
+        resume: bool,
+
+        /// The commits whose stacks will be moved on top of the main branch,
+        /// such as branch names or `'stack() & paths.changed("backend/")'`. Only
+        /// the stacks containing these commits are synced. If no commits are
+        /// provided, all draft commits will be synced.
         #[clap(value_parser)]
         revsets: Vec<Revset>,
 
@@ -690,6 +1404,43 @@ pub enum Command {
     /// Run a command on each commit in a given set and aggregate the results.
     Test(TestArgs),
 
+    /// Move to the last commit of the current stack. If the stack forks,
+    /// interactively prompt which commit to move to. This is the same as
+    /// `git next --all --interactive`.
+    Top {
+        /// If the local changes conflict with the destination commit, attempt
+        /// to merge them.
+        #[clap(action, short = 'm', long = "merge")]
+        merge: bool,
+
+        /// If the local changes conflict with the destination commit, discard
+        /// them. (Use with caution!)
+        #[clap(action, short = 'f', long = "force", conflicts_with("merge"))]
+        force: bool,
+    },
+
+    /// Remove a commit from history, leaving its changes in the working copy.
+    /// This is the inverse of `git record`. Any descendants of the commit are
+    /// rebased onto its parent.
+    Uncommit {
+        /// The commit to uncommit. It must be `HEAD` or an ancestor of `HEAD`.
+        #[clap(value_parser, default_value = "@")]
+        revset: Revset,
+
+        /// Options for resolving revset expressions.
+        #[clap(flatten)]
+        resolve_revset_options: ResolveRevsetOptions,
+
+        /// Options for moving commits.
+        #[clap(flatten)]
+        move_options: MoveOptions,
+
+        /// Leave the uncommitted changes staged, rather than only in the
+        /// working copy.
+        #[clap(action, short = 's', long = "staged")]
+        staged: bool,
+    },
+
     /// Browse or return to a previous state of the repository.
     Undo {
         /// Interactively browse through previous states of the repository
@@ -700,6 +1451,17 @@ pub enum Command {
         /// Skip confirmation and apply changes immediately.
         #[clap(action, short = 'y', long = "yes")]
         yes: bool,
+
+        /// Only undo changes to the given branches or commits, and leave
+        /// other changes made by the same operations in place. May be passed
+        /// multiple times.
+        #[clap(value_parser, long = "only")]
+        only: Vec<Revset>,
+
+        /// Undo even if it would move a branch so that it no longer includes
+        /// commits which were already pushed to its upstream branch.
+        #[clap(action, long = "force")]
+        force: bool,
     },
 
     /// Unhide previously-hidden commits from the smartlog.
@@ -717,6 +1479,17 @@ pub enum Command {
         recursive: bool,
     },
 
+    /// Unpin commits which were pinned with `git branchless pin`.
+    Unpin {
+        /// The commits to unpin.
+        #[clap(value_parser, default_value = "@")]
+        revsets: Vec<Revset>,
+
+        /// Options for resolving revset expressions.
+        #[clap(flatten)]
+        resolve_revset_options: ResolveRevsetOptions,
+    },
+
     /// Wrap a Git command inside a branchless transaction.
     Wrap {
         /// The `git` executable to invoke.
@@ -799,6 +1572,25 @@ pub struct Opts {
     pub command: Command,
 }
 
+/// `event-log` subcommands.
+#[derive(Debug, Parser)]
+pub enum EventLogSubcommand {
+    /// Write the contents of the event log to the provided file.
+    Export {
+        /// The path to write the exported event log to.
+        #[clap(value_parser)]
+        path: PathBuf,
+    },
+
+    /// Append the contents of a previously-exported event log to the event
+    /// log for this repository.
+    Import {
+        /// The path to read the exported event log from.
+        #[clap(value_parser)]
+        path: PathBuf,
+    },
+}
+
 /// `snapshot` subcommands.
 #[derive(Debug, Parser)]
 pub enum SnapshotSubcommand {
@@ -810,9 +1602,52 @@ pub enum SnapshotSubcommand {
 
     /// Restore the working copy contents from the provided snapshot.
     Restore {
-        /// The commit hash for the snapshot.
+        /// The commit hash for the snapshot, or the event ID of a snapshot
+        /// event in the event log. If `--file` is passed, defaults to the most
+        /// recent snapshot.
+        #[clap(value_parser, required_unless_present = "file")]
+        snapshot: Option<String>,
+
+        /// Only restore the contents of this file from the snapshot, leaving the
+        /// rest of the working copy intact.
+        #[clap(value_parser, long = "file")]
+        file: Option<PathBuf>,
+    },
+}
+
+/// `stash` subcommands.
+#[derive(Debug, Parser)]
+pub enum StashSubcommand {
+    /// Take a snapshot of the working copy and the index, add it to the top
+    /// of the stash list, and then reset the working copy to `HEAD`.
+    Push {
+        /// A description of the stashed changes. Defaults to a description of
+        /// the current `HEAD` commit.
+        #[clap(value_parser, short = 'm', long = "message")]
+        message: Option<String>,
+    },
+
+    /// Restore the given stash into the working copy and remove it from the
+    /// stash list. If `HEAD` has moved since the stash was created, the
+    /// stashed changes are merged onto the current `HEAD` as unstaged
+    /// changes; if this would cause a merge conflict, the stash is kept and
+    /// nothing is changed.
+    Pop {
+        /// The stash to restore, as an index like `0` or `stash@{0}`. Defaults
+        /// to the most recent stash.
+        #[clap(value_parser)]
+        stash: Option<String>,
+    },
+
+    /// List the stashes, most recent first.
+    List,
+
+    /// Remove the given stash from the stash list without restoring it.
+    Drop {
+        /// The stash to remove, as an index like `0` or `stash@{0}`. Defaults
+        /// to the most recent stash.
         #[clap(value_parser)]
-        snapshot_oid: NonZeroOid,
+        stash: Option<String>,
     },
 }
 
@@ -949,6 +1784,28 @@ pub enum TestSubcommand {
     },
 }
 
+/// A kind of event recorded in the event log.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum JournalEventType {
+    /// A commit was created.
+    Commit,
+
+    /// A commit was rewritten, such as by amending or rebasing it.
+    Rewrite,
+
+    /// A reference, such as `HEAD` or a branch, was updated.
+    Ref,
+
+    /// A commit was hidden.
+    Hide,
+
+    /// A commit was unhidden.
+    Unhide,
+
+    /// A snapshot of the working copy was taken.
+    Snapshot,
+}
+
 /// Generate and write man-pages into the specified directory.
 ///
 /// The generated files are named things like `man1/git-branchless-smartlog.1`,
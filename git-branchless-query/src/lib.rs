@@ -1,17 +1,21 @@
 use std::fmt::Write;
+use std::process::Command;
 
+use eyre::Context;
 use git_branchless_invoke::CommandContext;
 use itertools::Itertools;
 use lib::core::dag::Dag;
 use lib::core::effects::{Effects, OperationType};
 use lib::core::eventlog::{EventLogDb, EventReplayer};
-use lib::core::repo_ext::RepoExt;
-use lib::git::{CategorizedReferenceName, GitRunInfo, Repo};
-use lib::util::{ExitCode, EyreExitOr};
+use lib::core::repo_ext::{RepoExt, RepoReferencesSnapshot};
+use lib::git::{CategorizedReferenceName, GitRunInfo, NonZeroOid, Repo};
+use lib::util::{get_sh, ExitCode, EyreExitOr};
+use serde::Serialize;
 use tracing::instrument;
 
 use git_branchless_opts::{QueryArgs, ResolveRevsetOptions, Revset};
-use git_branchless_revset::resolve_commits;
+use git_branchless_revset::resolve_ordered_commits;
+use git_branchless_smartlog::{smartlog, SmartlogOptions};
 
 /// `query` command.
 #[instrument]
@@ -25,26 +29,270 @@ pub fn command_main(ctx: CommandContext, args: QueryArgs) -> EyreExitOr<()> {
         resolve_revset_options,
         show_branches,
         raw,
+        null_terminated,
+        format,
+        json,
+        graph,
+        exec,
     } = args;
+    if graph {
+        return smartlog(
+            &effects,
+            &git_run_info,
+            SmartlogOptions {
+                revset: Some(revset),
+                resolve_revset_options,
+                exact: true,
+                ..Default::default()
+            },
+        );
+    }
     query(
         &effects,
         &git_run_info,
         revset,
         &resolve_revset_options,
-        show_branches,
-        raw,
+        &QueryOutputOptions {
+            show_branches,
+            raw,
+            null_terminated,
+            format: format.as_deref(),
+            json,
+            exec: exec.as_deref(),
+        },
     )
 }
 
+/// Options for how to print the results of a query.
+#[derive(Debug)]
+struct QueryOutputOptions<'a> {
+    show_branches: bool,
+    raw: bool,
+    null_terminated: bool,
+    format: Option<&'a str>,
+    json: bool,
+    exec: Option<&'a str>,
+}
+
+/// The author of a commit, as printed by `--json`.
+#[derive(Debug, Serialize)]
+struct JsonAuthor {
+    name: Option<String>,
+    email: Option<String>,
+    date: Option<String>,
+}
+
+/// A commit, as printed by `--json`.
+#[derive(Debug, Serialize)]
+struct JsonCommit {
+    oid: String,
+    parents: Vec<String>,
+    message: String,
+    author: JsonAuthor,
+    branches: Vec<String>,
+    draft: bool,
+}
+
+fn make_json_commit(
+    repo: &Repo,
+    dag: &Dag,
+    references_snapshot: &RepoReferencesSnapshot,
+    commit_oid: NonZeroOid,
+) -> eyre::Result<JsonCommit> {
+    let commit = repo.find_commit_or_fail(commit_oid)?;
+    let author = commit.get_author();
+    let branches = match references_snapshot.branch_oid_to_names.get(&commit_oid) {
+        Some(branch_names) => branch_names
+            .iter()
+            .sorted()
+            .map(|branch_name| CategorizedReferenceName::new(branch_name).render_suffix())
+            .collect(),
+        None => Vec::new(),
+    };
+    Ok(JsonCommit {
+        oid: commit_oid.to_string(),
+        parents: commit
+            .get_parent_oids()
+            .into_iter()
+            .map(|oid| oid.to_string())
+            .collect(),
+        message: String::from_utf8_lossy(&commit.get_message_raw()).into_owned(),
+        author: JsonAuthor {
+            name: author.get_name().map(|name| name.to_owned()),
+            email: author.get_email().map(|email| email.to_owned()),
+            date: author
+                .get_time()
+                .to_date_time()
+                .map(|date_time| date_time.to_rfc3339()),
+        },
+        branches,
+        draft: dag.set_contains(dag.query_draft_commits()?, commit_oid)?,
+    })
+}
+
+/// A placeholder in a `--format` template.
+#[derive(Clone, Copy, Debug)]
+enum Placeholder {
+    Oid,
+    ShortOid,
+    Message,
+    Branches,
+}
+
+/// A piece of a parsed `--format` template.
+#[derive(Clone, Debug)]
+enum FormatPart {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+/// Parse a `--format` template into its literal and placeholder parts.
+///
+/// Returns: The parsed template, or a message describing why it was invalid.
+fn parse_format(format: &str) -> Result<Vec<FormatPart>, String> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                literal.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                literal.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err("Unmatched `{` in format string".to_string()),
+                    }
+                }
+                let placeholder = match name.as_str() {
+                    "oid" => Placeholder::Oid,
+                    "short_oid" => Placeholder::ShortOid,
+                    "message" => Placeholder::Message,
+                    "branches" => Placeholder::Branches,
+                    _ => return Err(format!("Unknown placeholder in format string: {{{name}}}")),
+                };
+                if !literal.is_empty() {
+                    parts.push(FormatPart::Literal(std::mem::take(&mut literal)));
+                }
+                parts.push(FormatPart::Placeholder(placeholder));
+            }
+            '}' => return Err("Unmatched `}` in format string".to_string()),
+            c => literal.push(c),
+        }
+    }
+    if !literal.is_empty() {
+        parts.push(FormatPart::Literal(literal));
+    }
+    Ok(parts)
+}
+
+fn render_format(
+    repo: &Repo,
+    references_snapshot: &RepoReferencesSnapshot,
+    format_parts: &[FormatPart],
+    commit_oid: NonZeroOid,
+) -> eyre::Result<String> {
+    let commit = repo.find_commit_or_fail(commit_oid)?;
+    let mut result = String::new();
+    for part in format_parts {
+        match part {
+            FormatPart::Literal(literal) => result.push_str(literal),
+            FormatPart::Placeholder(Placeholder::Oid) => result.push_str(&commit_oid.to_string()),
+            FormatPart::Placeholder(Placeholder::ShortOid) => {
+                result.push_str(&commit.get_short_oid()?)
+            }
+            FormatPart::Placeholder(Placeholder::Message) => {
+                result.push_str(&String::from_utf8_lossy(&commit.get_summary()?))
+            }
+            FormatPart::Placeholder(Placeholder::Branches) => {
+                let branch_names = match references_snapshot.branch_oid_to_names.get(&commit_oid) {
+                    Some(branch_names) => branch_names
+                        .iter()
+                        .sorted()
+                        .map(|branch_name| {
+                            CategorizedReferenceName::new(branch_name).render_suffix()
+                        })
+                        .join(" "),
+                    None => String::new(),
+                };
+                result.push_str(&branch_names);
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Run `command` in a shell once for each of the given commits, replacing
+/// `{oid}` with the commit's OID.
+#[instrument]
+fn exec_for_commits(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    command: &str,
+    commit_oids: Vec<NonZeroOid>,
+) -> EyreExitOr<()> {
+    let shell_path = match get_sh() {
+        Some(shell_path) => shell_path,
+        None => {
+            writeln!(
+                effects.get_error_stream(),
+                "branchless: could not determine path to shell"
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+
+    for commit_oid in commit_oids {
+        let commit_command = command.replace("{oid}", &commit_oid.to_string());
+        let exit_status = Command::new(&shell_path)
+            .arg("-c")
+            .arg(&commit_command)
+            .current_dir(&git_run_info.working_directory)
+            .status()
+            .wrap_err("Running command")?;
+        if !exit_status.success() {
+            writeln!(
+                effects.get_error_stream(),
+                "branchless: command failed ({exit_status}): {commit_command}"
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+    }
+    Ok(Ok(()))
+}
+
 #[instrument]
 fn query(
     effects: &Effects,
     git_run_info: &GitRunInfo,
     query: Revset,
     resolve_revset_options: &ResolveRevsetOptions,
-    show_branches: bool,
-    raw: bool,
+    output_options: &QueryOutputOptions,
 ) -> EyreExitOr<()> {
+    let QueryOutputOptions {
+        show_branches,
+        raw,
+        null_terminated,
+        format,
+        json,
+        exec,
+    } = *output_options;
+    let format_parts = match format.map(parse_format).transpose() {
+        Ok(format_parts) => format_parts,
+        Err(message) => {
+            writeln!(effects.get_error_stream(), "{message}")?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+
     let repo = Repo::from_current_dir()?;
     let conn = repo.get_db_conn()?;
     let event_log_db = EventLogDb::new(&conn)?;
@@ -59,9 +307,9 @@ fn query(
         &references_snapshot,
     )?;
 
-    let commit_set =
-        match resolve_commits(effects, &repo, &mut dag, &[query], resolve_revset_options) {
-            Ok(commit_sets) => commit_sets[0].clone(),
+    let (commit_set, is_ordered) =
+        match resolve_ordered_commits(effects, &repo, &mut dag, &query, resolve_revset_options) {
+            Ok(result) => result,
             Err(err) => {
                 err.describe(effects)?;
                 return Ok(Err(ExitCode(1)));
@@ -93,10 +341,34 @@ fn query(
         let commit_oids = {
             let (effects, _progress) = effects.start_operation(OperationType::SortCommits);
             let _effects = effects;
-            dag.sort(&commit_set)?
+            if is_ordered {
+                dag.commit_set_to_vec(&commit_set)?
+            } else {
+                dag.sort(&commit_set)?
+            }
         };
+        if json {
+            let json_commits = commit_oids
+                .into_iter()
+                .map(|commit_oid| make_json_commit(&repo, &dag, &references_snapshot, commit_oid))
+                .collect::<eyre::Result<Vec<_>>>()?;
+            writeln!(
+                effects.get_output_stream(),
+                "{}",
+                serde_json::to_string_pretty(&json_commits)?
+            )?;
+            return Ok(Ok(()));
+        }
+        if let Some(command) = exec {
+            return exec_for_commits(effects, git_run_info, command, commit_oids);
+        }
         for commit_oid in commit_oids {
-            if raw {
+            if let Some(format_parts) = &format_parts {
+                let line = render_format(&repo, &references_snapshot, format_parts, commit_oid)?;
+                writeln!(effects.get_output_stream(), "{line}")?;
+            } else if raw && null_terminated {
+                write!(effects.get_output_stream(), "{commit_oid}\0")?;
+            } else if raw {
                 writeln!(effects.get_output_stream(), "{commit_oid}")?;
             } else {
                 let commit = repo.find_commit_or_fail(commit_oid)?;
@@ -1,3 +1,6 @@
+use std::path::PathBuf;
+
+use lib::git::GitVersion;
 use lib::testing::{make_git, GitRunOptions};
 
 #[test]
@@ -87,7 +90,7 @@ fn test_query_eval_error() -> eyre::Result<()> {
             },
         )?;
         insta::assert_snapshot!(stderr, @r###"
-        Evaluation error for expression 'foo()': no function with the name 'foo' could be found; these functions are available: all, ancestors, ancestors.nth, author.date, author.email, author.name, branches, children, committer.date, committer.email, committer.name, current, descendants, difference, draft, exactly, heads, intersection, main, merges, message, none, not, only, parents, parents.nth, paths.changed, public, range, roots, siblings, stack, tests.failed, tests.fixable, tests.passed, union
+        Evaluation error for expression 'foo()': no function with the name 'foo' could be found; these functions are available: all, ancestors, ancestors.nth, author.date, author.email, author.name, branches, changeid, children, committer.date, committer.email, committer.name, conflictswith, current, descendants, difference, draft, exactly, exactly.paths, forks, heads, intersection, limit, main, merges, message, message.regex, nearest.ancestor, nearest.descendant, needsresubmit, none, not, only, parents, parents.nth, paths.changed, public, range, roots, siblings, signed, sort, stack, submitted, tests.failed, tests.fixable, tests.passed, tests.untested, union, unsubmitted, verified
         "###);
         insta::assert_snapshot!(stdout, @"");
     }
@@ -173,6 +176,24 @@ fn test_query_branches() -> eyre::Result<()> {
         "###);
     }
 
+    git.run(&["branch", "release/1.0", "HEAD^"])?;
+    git.run(&["branch", "release/2.0"])?;
+
+    {
+        let (stdout, _stderr) = git.branchless("query", &["branches('release/*')"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        96d1c37 create test2.txt
+        70deb1e create test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("query", &["branches('regex:^release/1')"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        96d1c37 create test2.txt
+        "###);
+    }
+
     Ok(())
 }
 
@@ -208,3 +229,525 @@ fn test_query_hidden_commits() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_query_format() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.commit_file("test1", 1)?;
+    git.run(&["branch", "foo"])?;
+    git.run(&["branch", "bar"])?;
+    git.commit_file("test2", 2)?;
+
+    {
+        let (stdout, stderr) = git.branchless(
+            "query",
+            &[
+                ".^::",
+                "--format",
+                "{short_oid} {message} [{branches}] {{oid}}",
+            ],
+        )?;
+        insta::assert_snapshot!(stderr, @"");
+        insta::assert_snapshot!(stdout, @r###"
+        62fc20d create test1.txt [bar foo] {oid}
+        96d1c37 create test2.txt [master] {oid}
+        "###);
+    }
+
+    {
+        let (stdout, stderr) = git.branchless("query", &[".", "--format", "{oid}"])?;
+        insta::assert_snapshot!(stderr, @"");
+        insta::assert_snapshot!(stdout, @r###"
+        96d1c37a3d4363611c49f7e52186e189a04c531f
+        "###);
+    }
+
+    {
+        let (stdout, stderr) = git.branchless_with_options(
+            "query",
+            &[".", "--format", "{author}"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @r###"
+        Unknown placeholder in format string: {author}
+        "###);
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_query_json() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.detach_head()?;
+    git.commit_file("test3", 3)?;
+
+    {
+        let (stdout, stderr) = git.branchless("query", &["master::", "--json"])?;
+        insta::assert_snapshot!(stderr, @"");
+        insta::assert_snapshot!(stdout, @r###"
+        [
+          {
+            "oid": "96d1c37a3d4363611c49f7e52186e189a04c531f",
+            "parents": [
+              "62fc20d2a290daea0d52bdc2ed2ad4be6491010e"
+            ],
+            "message": "create test2.txt\n",
+            "author": {
+              "name": "Testy McTestface",
+              "email": "test@example.com",
+              "date": "2020-10-29T14:34:56+00:00"
+            },
+            "branches": [
+              "master"
+            ],
+            "draft": false
+          },
+          {
+            "oid": "70deb1e28791d8e7dd5a1f0c871a51b91282562f",
+            "parents": [
+              "96d1c37a3d4363611c49f7e52186e189a04c531f"
+            ],
+            "message": "create test3.txt\n",
+            "author": {
+              "name": "Testy McTestface",
+              "email": "test@example.com",
+              "date": "2020-10-29T15:34:56+00:00"
+            },
+            "branches": [],
+            "draft": true
+          }
+        ]
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_query_conflicts_with() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    git.run(&["checkout", "master"])?;
+    git.write_file_txt("test1", "conflicting contents\n")?;
+    git.run(&["add", "test1.txt"])?;
+    git.run(&["commit", "-m", "conflicting change to test1.txt"])?;
+
+    {
+        let (stdout, stderr) = git.branchless("query", &["conflictswith(main())"])?;
+        insta::assert_snapshot!(stderr, @"");
+        insta::assert_snapshot!(stdout, @r###"
+        62fc20d create test1.txt
+        "###);
+    }
+
+    {
+        let (stdout, stderr) = git.branchless("query", &["conflictswith(.)"])?;
+        insta::assert_snapshot!(stderr, @"");
+        insta::assert_snapshot!(stdout, @r###"
+        62fc20d create test1.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_query_sort_limit() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    // Commit dates run against the topological order, so that sorting by date
+    // is distinguishable from the default topological order.
+    git.commit_file("test1", 3)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 1)?;
+
+    {
+        let (stdout, _stderr) = git.branchless("query", &["sort(.~2::, committer.date)"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        dd135ad create test3.txt
+        8d5a7a6 create test2.txt
+        f0542e4 create test1.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("query", &["sort(.~2::, -topological)"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        dd135ad create test3.txt
+        8d5a7a6 create test2.txt
+        f0542e4 create test1.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("query", &["limit(sort(.~2::, author.date), 2)"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        dd135ad create test3.txt
+        8d5a7a6 create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("query", &["sort(.~2::, author.date) & .~1::"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        dd135ad create test3.txt
+        8d5a7a6 create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("query", &["sort(.~1::, author.date) | .~2"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        dd135ad create test3.txt
+        8d5a7a6 create test2.txt
+        f0542e4 create test1.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("query", &[".~1:: & sort(.~2::, author.date)"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        8d5a7a6 create test2.txt
+        dd135ad create test3.txt
+        "###);
+    }
+
+    git.run(&[
+        "config",
+        "branchless.revsets.alias.byDate",
+        "sort($1, author.date)",
+    ])?;
+    git.run(&["config", "branchless.revsets.alias.recent", "byDate(.~2::)"])?;
+    {
+        let (stdout, _stderr) = git.branchless("query", &["recent"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        dd135ad create test3.txt
+        8d5a7a6 create test2.txt
+        f0542e4 create test1.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("query", &["limit(recent() - ., 1)"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        8d5a7a6 create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, stderr) = git.branchless_with_options(
+            "query",
+            &["sort(., foo)"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @r###"
+        Evaluation error for expression 'sort(., foo)': unknown sort key 'foo'; expected one of: author.date, committer.date, topological
+        "###);
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_query_graph() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.commit_file("test1", 1)?;
+    git.detach_head()?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.commit_file("test4", 4)?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test5", 5)?;
+    git.detach_head()?;
+    git.commit_file("test6", 6)?;
+    git.run(&["checkout", "96d1c37"])?;
+
+    {
+        let (stdout, stderr) = git.branchless("query", &["--graph", "heads(draft())"])?;
+        insta::assert_snapshot!(stderr, @"");
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d create test1.txt
+        |\
+        : # 2 omitted commits
+        : :
+        : o 355e173 create test4.txt
+        :
+        o da42aeb create test6.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_query_nearest() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.commit_file("test1", 1)?;
+    git.detach_head()?;
+    git.commit_file("test2", 2)?;
+    git.run(&["branch", "foo"])?;
+    git.commit_file("test3", 3)?;
+    git.commit_file("test4", 4)?;
+
+    {
+        let (stdout, _stderr) = git.branchless("query", &["nearest.ancestor(., branches())"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        96d1c37 create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("query", &["nearest.ancestor(foo, branches())"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        96d1c37 create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless(
+            "query",
+            &["nearest.descendant(master, branches() - master)"],
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        96d1c37 create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_query_forks() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.commit_file("test1", 1)?;
+    git.detach_head()?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "HEAD^"])?;
+    git.commit_file("test4", 4)?;
+    git.run(&["checkout", "master"])?;
+    git.detach_head()?;
+    git.commit_file("test5", 5)?;
+
+    {
+        let (stdout, _stderr) = git.branchless("query", &["forks()"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        62fc20d create test1.txt
+        96d1c37 create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_query_null_terminated_and_exec() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+
+    {
+        let (stdout, _stderr) = git.branchless("query", &[".^::", "--raw", "-z"])?;
+        assert_eq!(
+            stdout,
+            "96d1c37a3d4363611c49f7e52186e189a04c531f\0\
+            70deb1e28791d8e7dd5a1f0c871a51b91282562f\0"
+        );
+    }
+
+    {
+        let (stdout, _stderr) =
+            git.branchless("query", &[".^::", "--exec", "git log -1 --format=%s {oid}"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test2.txt
+        create test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, stderr) = git.branchless_with_options(
+            "query",
+            &[
+                ".^::",
+                "--exec",
+                "echo {oid} && test {oid} != 70deb1e28791d8e7dd5a1f0c871a51b91282562f",
+            ],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @r###"
+        branchless: command failed (exit status: 1): echo 70deb1e28791d8e7dd5a1f0c871a51b91282562f && test 70deb1e28791d8e7dd5a1f0c871a51b91282562f != 70deb1e28791d8e7dd5a1f0c871a51b91282562f
+        "###);
+        insta::assert_snapshot!(stdout, @r###"
+        96d1c37a3d4363611c49f7e52186e189a04c531f
+        70deb1e28791d8e7dd5a1f0c871a51b91282562f
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_query_change_id() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    // The change ID of `96d1c37` before it's rewritten.
+    let change_id = "qtmynwsp";
+    {
+        let (stdout, _stderr) = git.branchless("query", &[&format!("changeid({change_id})")])?;
+        insta::assert_snapshot!(stdout, @r###"
+        96d1c37 create test2.txt
+        "###);
+    }
+
+    git.run(&["commit", "--amend", "-m", "amend test2"])?;
+    git.run(&["commit", "--amend", "-m", "amend test2 again"])?;
+    {
+        let (stdout, _stderr) = git.branchless(
+            "query",
+            &[&format!("changeid({change_id})"), "--format", "{message}"],
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        amend test2 again
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("query", &["changeid(qtmynwsp)", "--raw"])?;
+        let (head_oid, _stderr) = git.run(&["rev-parse", "HEAD"])?;
+        assert_eq!(stdout, head_oid);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_query_signed_verified() -> eyre::Result<()> {
+    let git = make_git()?;
+    if git.get_version()? < GitVersion(2, 34, 0) {
+        // SSH signing was added in Git v2.34.
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    let git_dir = git.repo_path.join(".git");
+    let make_key = |name: &str| -> eyre::Result<Option<PathBuf>> {
+        let key_path = git_dir.join(name);
+        let status = std::process::Command::new("ssh-keygen")
+            .args(["-q", "-t", "ed25519", "-N", "", "-C", "test", "-f"])
+            .arg(&key_path)
+            .status();
+        match status {
+            Ok(status) if status.success() => Ok(Some(key_path)),
+            _ => Ok(None),
+        }
+    };
+    let (trusted_key, untrusted_key) = match (make_key("trusted_key")?, make_key("untrusted_key")?)
+    {
+        (Some(trusted_key), Some(untrusted_key)) => (trusted_key, untrusted_key),
+        // `ssh-keygen` isn't available.
+        _ => return Ok(()),
+    };
+    let trusted_public_key = std::fs::read_to_string(trusted_key.with_extension("pub"))?;
+    let allowed_signers_path = git_dir.join("allowed_signers");
+    std::fs::write(
+        &allowed_signers_path,
+        format!("test@example.com {trusted_public_key}"),
+    )?;
+    git.run(&["config", "gpg.format", "ssh"])?;
+    git.run(&[
+        "config",
+        "gpg.ssh.allowedSignersFile",
+        allowed_signers_path.to_str().unwrap(),
+    ])?;
+
+    git.commit_file("test1", 1)?;
+    git.run(&["config", "commit.gpgSign", "true"])?;
+    git.run(&["config", "user.signingKey", trusted_key.to_str().unwrap()])?;
+    git.commit_file("test2", 2)?;
+    git.run(&["config", "user.signingKey", untrusted_key.to_str().unwrap()])?;
+    git.commit_file("test3", 3)?;
+
+    {
+        let (stdout, _stderr) = git.branchless("query", &["signed()", "--format", "{message}"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test2.txt
+        create test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) =
+            git.branchless("query", &["verified()", "--format", "{message}"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_query_change_id_undo() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.commit_file("test1", 1)?;
+    git.detach_head()?;
+    git.commit_file("test2", 2)?;
+    git.run(&["commit", "--amend", "-m", "amend test2"])?;
+    {
+        let (stdout, _stderr) = git.branchless("query", &["--hidden", "changeid(qtmynwsp)"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        96d1c37 create test2.txt
+        5ff9fd3 amend test2
+        "###);
+    }
+
+    git.branchless("undo", &["-y"])?;
+    {
+        let (stdout, _stderr) = git.branchless("query", &["--hidden", "changeid(qtmynwsp)"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        96d1c37 create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,695 @@
+//! Absorb changes in the working copy into the commits in the current stack.
+//!
+//! Each changed hunk in the working copy is amended into the draft commit which
+//! last modified the lines that it touches, similarly to `hg absorb`. Hunks
+//! which can't be attributed to exactly one commit, such as changes to lines
+//! which weren't modified in the current stack, are left in the working copy.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use eyre::Context;
+use git_branchless_invoke::CommandContext;
+use git_branchless_opts::{AbsorbArgs, MoveOptions};
+use itertools::Itertools;
+use lib::core::check_out::{check_out_commit, CheckOutCommitOptions, CheckoutTarget};
+use lib::core::config::get_restack_preserve_timestamps;
+use lib::core::dag::{CommitSet, Dag};
+use lib::core::effects::{Effects, OperationType};
+use lib::core::eventlog::{Event, EventLogDb, EventReplayer};
+use lib::core::formatting::Pluralize;
+use lib::core::gc::mark_commit_reachable;
+use lib::core::repo_ext::RepoExt;
+use lib::core::rewrite::rewrite_hooks::run_post_rewrite_user_hook;
+use lib::core::rewrite::{
+    execute_rebase_plan, move_branches, BuildRebasePlanOptions, EmptyCommitsMode,
+    ExecuteRebasePlanOptions, ExecuteRebasePlanResult, MergeConflictRemediation, RebasePlanBuilder,
+    RebasePlanPermissions, RepoResource,
+};
+use lib::git::{
+    hydrate_tree, process_diff_for_record, GitRunInfo, MaybeZeroOid, NonZeroOid, Repo,
+    ResolvedReferenceInfo, Signer, Tree,
+};
+use lib::try_exit_code;
+use lib::util::{ExitCode, EyreExitOr};
+use rayon::ThreadPoolBuilder;
+use scm_record::{ChangeType, File, Section};
+use tracing::instrument;
+
+/// A contiguous run of changed lines in a diff. Line numbers are zero-indexed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ChangedRegion {
+    old_start: usize,
+    old_len: usize,
+    new_start: usize,
+    added_lines: Vec<String>,
+}
+
+impl ChangedRegion {
+    fn new_end(&self) -> usize {
+        self.new_start + self.added_lines.len()
+    }
+}
+
+/// The changed regions of each file modified between two trees. A file maps to
+/// `None` if it was changed in a way that can't be attributed to individual
+/// lines, such as a binary or file mode change.
+type FileChanges = HashMap<PathBuf, Option<Vec<ChangedRegion>>>;
+
+fn get_changed_regions(file: &File) -> Option<Vec<ChangedRegion>> {
+    let mut result = Vec::new();
+    let mut old_line = 0;
+    let mut new_line = 0;
+    for section in file.sections.iter() {
+        match section {
+            Section::Unchanged { lines } => {
+                old_line += lines.len();
+                new_line += lines.len();
+            }
+            Section::Changed { lines } => {
+                let old_len = lines
+                    .iter()
+                    .filter(|line| matches!(line.change_type, ChangeType::Removed))
+                    .count();
+                let added_lines = lines
+                    .iter()
+                    .filter(|line| matches!(line.change_type, ChangeType::Added))
+                    .map(|line| line.line.to_string())
+                    .collect_vec();
+                let region = ChangedRegion {
+                    old_start: old_line,
+                    old_len,
+                    new_start: new_line,
+                    added_lines,
+                };
+                old_line += region.old_len;
+                new_line = region.new_end();
+                result.push(region);
+            }
+
+            // A newly-created file can still be attributed line-by-line.
+            Section::FileMode {
+                is_checked: _,
+                before,
+                after: _,
+            } if before == &scm_record::FileMode::absent() => {}
+
+            Section::FileMode { .. } | Section::Binary { .. } => return None,
+        }
+    }
+    Some(result)
+}
+
+fn get_file_changes(
+    effects: &Effects,
+    repo: &Repo,
+    old_tree: &Tree,
+    new_tree: &Tree,
+) -> eyre::Result<FileChanges> {
+    let files = {
+        let (effects, _progress) = effects.start_operation(OperationType::CalculateDiff);
+        let diff = repo.get_diff_between_trees(&effects, Some(old_tree), new_tree, 0)?;
+        process_diff_for_record(repo, &diff)?
+    };
+    Ok(files
+        .into_iter()
+        .map(|file| {
+            let regions = get_changed_regions(&file);
+            (file.path.into_owned(), regions)
+        })
+        .collect())
+}
+
+/// Find the commit which should absorb a change to the lines `[start, start +
+/// len)` of the file at `path` in the commit at the top of the stack.
+///
+/// The stack is given as the changes of each commit, from the top of the stack
+/// downwards. Returns the position of the changed lines in each of those
+/// commits, down to and including the commit which should absorb the change,
+/// or `None` if no single commit can absorb the change.
+fn find_absorb_target(
+    stack_changes: &[FileChanges],
+    path: &Path,
+    start: usize,
+    len: usize,
+) -> Option<Vec<(usize, usize)>> {
+    let mut positions = Vec::new();
+    let mut start = start;
+    for changes in stack_changes {
+        positions.push((start, len));
+        let regions = match changes.get(path) {
+            None => continue,
+            Some(None) => return None,
+            Some(Some(regions)) => regions,
+        };
+
+        let end = start + len;
+        let mut old_start = start;
+        for region in regions {
+            let region_start = region.new_start;
+            let region_end = region.new_end();
+            if len == 0 {
+                // An insertion touches the lines on either side of it.
+                if region_start < region_end && region_start <= start && start <= region_end {
+                    return Some(positions);
+                }
+            } else if region_start <= start && end <= region_end {
+                return Some(positions);
+            } else if (region_start < end && start < region_end)
+                || (region_start == region_end && start < region_start && region_start < end)
+            {
+                // The change only partially overlaps the lines modified by this
+                // commit, or spans lines which were deleted by this commit.
+                return None;
+            }
+
+            if region_end <= start {
+                old_start = region.old_start + region.old_len + (start - region_end);
+            }
+        }
+        start = old_start;
+    }
+    None
+}
+
+/// A change in the working copy which will be absorbed into a commit in the
+/// stack.
+#[derive(Debug)]
+struct AbsorbedChange {
+    path: PathBuf,
+
+    /// The position of the changed lines in each commit, from the top of the
+    /// stack down to the commit which absorbs the change.
+    positions: Vec<(usize, usize)>,
+
+    added_lines: Vec<String>,
+}
+
+impl AbsorbedChange {
+    fn target_index(&self) -> usize {
+        self.positions.len() - 1
+    }
+}
+
+/// Absorb changes in the working copy into the commits in the current stack.
+#[instrument]
+pub fn command_main(ctx: CommandContext, args: AbsorbArgs) -> EyreExitOr<()> {
+    let CommandContext {
+        effects,
+        git_run_info,
+    } = ctx;
+    let AbsorbArgs {
+        dry_run,
+        move_options,
+    } = args;
+    absorb(&effects, &git_run_info, dry_run, &move_options)
+}
+
+#[instrument]
+fn absorb(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    dry_run: bool,
+    move_options: &MoveOptions,
+) -> EyreExitOr<()> {
+    let now = SystemTime::now();
+    let timestamp = now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64();
+    let repo = Repo::from_current_dir()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let references_snapshot = repo.get_references_snapshot()?;
+    let mut dag = Dag::open_and_sync(
+        effects,
+        &repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+
+    let head_info = repo.get_head_info()?;
+    let head_oid = match head_info.oid {
+        Some(oid) => oid,
+        None => {
+            writeln!(
+                effects.get_output_stream(),
+                "No commit is currently checked out. Check out a commit to absorb into and then try again.",
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+
+    let index = repo.get_index()?;
+    if index.has_conflicts() {
+        writeln!(
+            effects.get_output_stream(),
+            "Cannot absorb, because there are unresolved merge conflicts. Resolve the merge conflicts and try again."
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+
+    // The draft commits from `HEAD` downwards, along with their parents.
+    let mut stack = Vec::new();
+    let mut stack_changes = Vec::new();
+    {
+        let draft_commits = dag.query_draft_commits()?.clone();
+        let mut commit = repo.find_commit_or_fail(head_oid)?;
+        while dag.set_contains(&draft_commits, commit.get_oid())? {
+            let parent = match commit.get_only_parent() {
+                Some(parent) => parent,
+                None => break,
+            };
+            stack_changes.push(get_file_changes(
+                effects,
+                &repo,
+                &parent.get_tree()?,
+                &commit.get_tree()?,
+            )?);
+            stack.push((commit, parent.clone()));
+            commit = parent;
+        }
+    }
+    if stack.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "There are no draft commits in the current stack to absorb changes into."
+        )?;
+        return Ok(Ok(()));
+    }
+
+    let event_tx_id = event_log_db.make_transaction_id(now, "absorb")?;
+    let (snapshot, _status) =
+        repo.get_status(effects, git_run_info, &index, &head_info, Some(event_tx_id))?;
+    let working_copy_changes = get_file_changes(
+        effects,
+        &repo,
+        &stack[0].0.get_tree()?,
+        &snapshot.commit_unstaged.get_tree()?,
+    )?;
+    if working_copy_changes.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "There are no uncommitted or staged changes. Nothing to absorb."
+        )?;
+        return Ok(Ok(()));
+    }
+
+    let mut absorbed_changes = Vec::new();
+    let mut num_unabsorbed_changes = 0;
+    for (path, regions) in working_copy_changes
+        .into_iter()
+        .sorted_by(|(lhs, _), (rhs, _)| lhs.cmp(rhs))
+    {
+        let regions = match regions {
+            Some(regions) => regions,
+            None => {
+                num_unabsorbed_changes += 1;
+                continue;
+            }
+        };
+        for region in regions {
+            match find_absorb_target(&stack_changes, &path, region.old_start, region.old_len) {
+                Some(positions) => absorbed_changes.push(AbsorbedChange {
+                    path: path.clone(),
+                    positions,
+                    added_lines: region.added_lines,
+                }),
+                None => num_unabsorbed_changes += 1,
+            }
+        }
+    }
+    let max_target_index = match absorbed_changes
+        .iter()
+        .map(|absorbed_change| absorbed_change.target_index())
+        .max()
+    {
+        Some(max_target_index) => max_target_index,
+        None => {
+            writeln!(
+                effects.get_output_stream(),
+                "Could not find a commit in the current stack to absorb any of the changes into."
+            )?;
+            return Ok(Ok(()));
+        }
+    };
+
+    let describe_absorbed = |new_oids: &HashMap<NonZeroOid, NonZeroOid>| -> eyre::Result<()> {
+        let verb = if dry_run { "Would absorb" } else { "Absorbed" };
+        for (target_index, (commit, _parent)) in stack.iter().enumerate().rev() {
+            let num_changes = absorbed_changes
+                .iter()
+                .filter(|absorbed_change| absorbed_change.target_index() == target_index)
+                .count();
+            if num_changes == 0 {
+                continue;
+            }
+            let commit = match new_oids.get(&commit.get_oid()) {
+                Some(new_oid) => repo.find_commit_or_fail(*new_oid)?,
+                None => commit.clone(),
+            };
+            writeln!(
+                effects.get_output_stream(),
+                "{verb} {} into: {}",
+                Pluralize {
+                    determiner: None,
+                    amount: num_changes,
+                    unit: ("change", "changes"),
+                },
+                effects
+                    .get_glyphs()
+                    .render(commit.friendly_describe(effects.get_glyphs())?)?,
+            )?;
+        }
+        if num_unabsorbed_changes > 0 {
+            writeln!(
+                effects.get_output_stream(),
+                "Left {} in the working copy.",
+                Pluralize {
+                    determiner: None,
+                    amount: num_unabsorbed_changes,
+                    unit: ("change", "changes"),
+                },
+            )?;
+        }
+        Ok(())
+    };
+    if dry_run {
+        describe_absorbed(&HashMap::new())?;
+        return Ok(Ok(()));
+    }
+
+    let build_options = BuildRebasePlanOptions {
+        force_rewrite_public_commits: move_options.force_rewrite_public_commits,
+        dump_rebase_constraints: move_options.dump_rebase_constraints,
+        dump_rebase_plan: move_options.dump_rebase_plan,
+        detect_duplicate_commits_via_patch_id: move_options.detect_duplicate_commits_via_patch_id,
+    };
+    let commits_to_verify =
+        dag.query_descendants(CommitSet::from(stack[max_target_index].0.get_oid()))?;
+    let commits_to_verify = dag.filter_visible_commits(commits_to_verify)?;
+    if let Err(err) =
+        RebasePlanPermissions::verify_rewrite_set(&dag, build_options.clone(), &commits_to_verify)?
+    {
+        err.describe(effects, &repo, &dag)?;
+        return Ok(Err(ExitCode(1)));
+    };
+
+    {
+        let ResolvedReferenceInfo {
+            oid,
+            reference_name,
+        } = &head_info;
+        event_log_db.add_events(vec![Event::WorkingCopySnapshot {
+            timestamp,
+            event_tx_id,
+            head_oid: MaybeZeroOid::from(*oid),
+            commit_oid: snapshot.base_commit.get_oid(),
+            ref_name: reference_name.clone(),
+        }])?;
+    }
+
+    // Rewrite each commit from the lowest commit which absorbs a change up to
+    // `HEAD`, applying every change absorbed by that commit or the commits
+    // below it.
+    let preserve_timestamps =
+        move_options.preserve_timestamps || get_restack_preserve_timestamps(&repo)?;
+    let mut new_oids: HashMap<NonZeroOid, NonZeroOid> = HashMap::new();
+    let signer = Signer::from_config(&repo)?;
+    let mut new_parent = stack[max_target_index].1.clone();
+    for (index, (commit, _parent)) in stack.iter().enumerate().take(max_target_index + 1).rev() {
+        let tree = commit.get_tree()?;
+        let mut replacements_by_path: HashMap<&Path, Vec<_>> = HashMap::new();
+        for absorbed_change in absorbed_changes.iter() {
+            if let Some((start, len)) = absorbed_change.positions.get(index) {
+                replacements_by_path
+                    .entry(absorbed_change.path.as_path())
+                    .or_default()
+                    .push((*start, *len, absorbed_change.added_lines.as_slice()));
+            }
+        }
+
+        let mut entries = HashMap::new();
+        for (path, mut replacements) in replacements_by_path {
+            let entry = tree.get_path(path)?.ok_or_else(|| {
+                eyre::eyre!(
+                    "Could not find path {:?} in commit {:?}",
+                    path,
+                    commit.get_oid()
+                )
+            })?;
+            let blob = repo.find_blob_or_fail(entry.get_oid())?;
+            let contents = std::str::from_utf8(blob.get_content())
+                .wrap_err_with(|| format!("Decoding contents of path {path:?}"))?;
+            let lines = contents.split_inclusive('\n').collect_vec();
+
+            replacements.sort_by_key(|(start, _len, _added_lines)| *start);
+            let mut new_contents = String::new();
+            let mut line_idx = 0;
+            for (start, len, added_lines) in replacements {
+                new_contents.extend(lines[line_idx..start].iter().copied());
+                new_contents.extend(added_lines.iter().map(String::as_str));
+                line_idx = start + len;
+            }
+            new_contents.extend(lines[line_idx..].iter().copied());
+
+            let blob_oid = repo.create_blob_from_contents(new_contents.as_bytes())?;
+            entries.insert(path.to_owned(), Some((blob_oid, entry.get_filemode())));
+        }
+        let new_tree = repo.find_tree_or_fail(hydrate_tree(&repo, Some(&tree), entries)?)?;
+
+        let committer = if preserve_timestamps {
+            commit.get_committer()
+        } else {
+            commit.get_committer().update_timestamp(now)?
+        };
+        let message = commit.get_message_raw();
+        let message = std::str::from_utf8(&message).with_context(|| {
+            eyre::eyre!("Could not decode commit message for commit: {:?}", commit)
+        })?;
+        let new_oid = repo.create_commit_with_signer(
+            &commit.get_author(),
+            &committer,
+            message,
+            &new_tree,
+            vec![&new_parent],
+            signer.as_ref(),
+        )?;
+        new_oids.insert(commit.get_oid(), new_oid);
+        new_parent = repo.find_commit_or_fail(new_oid)?;
+    }
+    let new_head_oid = new_parent.get_oid();
+
+    // As with `git amend`, switch to the new commits and move any branches
+    // before restacking, since this should always succeed.
+    {
+        let mut events = Vec::new();
+        for (old_oid, new_oid) in new_oids.iter().sorted() {
+            mark_commit_reachable(&repo, *new_oid)
+                .wrap_err("Marking commit as reachable for GC purposes.")?;
+            events.push(Event::RewriteEvent {
+                timestamp,
+                event_tx_id,
+                old_commit_oid: MaybeZeroOid::NonZero(*old_oid),
+                new_commit_oid: MaybeZeroOid::NonZero(*new_oid),
+            });
+        }
+        event_log_db.add_events(events)?;
+        dag.sync_from_oids(
+            effects,
+            &repo,
+            CommitSet::empty(),
+            CommitSet::from(new_head_oid),
+        )?;
+        let rewritten_oids: HashMap<NonZeroOid, MaybeZeroOid> = new_oids
+            .iter()
+            .map(|(old_oid, new_oid)| (*old_oid, MaybeZeroOid::NonZero(*new_oid)))
+            .collect();
+        move_branches(effects, git_run_info, &repo, event_tx_id, &rewritten_oids)?;
+
+        let target = match &head_info.reference_name {
+            Some(name) => CheckoutTarget::Reference(name.clone()),
+            None => CheckoutTarget::Oid(new_head_oid),
+        };
+        try_exit_code!(check_out_commit(
+            effects,
+            git_run_info,
+            &repo,
+            &event_log_db,
+            event_tx_id,
+            Some(target),
+            &CheckOutCommitOptions {
+                additional_args: Default::default(),
+                reset: true,
+                render_smartlog: false,
+            },
+        )?);
+
+        run_post_rewrite_user_hook(
+            effects,
+            git_run_info,
+            &repo,
+            event_tx_id,
+            "rebase",
+            &rewritten_oids,
+        )?;
+    }
+    describe_absorbed(&new_oids)?;
+
+    let rebase_plan = {
+        let rewritten_commits: CommitSet = new_oids.keys().copied().collect();
+        let descendants = dag.query_descendants(rewritten_commits.clone())?;
+        let descendants = descendants.difference(&rewritten_commits);
+        let descendants = dag.filter_visible_commits(descendants)?;
+        let permissions =
+            match RebasePlanPermissions::verify_rewrite_set(&dag, build_options, &descendants)? {
+                Ok(permissions) => permissions,
+                Err(err) => {
+                    err.describe(effects, &repo, &dag)?;
+                    return Ok(Err(ExitCode(1)));
+                }
+            };
+
+        let mut builder = RebasePlanBuilder::new(&dag, permissions);
+        for descendant_oid in dag.commit_set_to_vec(&descendants)? {
+            let descendant_commit = repo.find_commit_or_fail(descendant_oid)?;
+            let parent_oids = descendant_commit
+                .get_parent_oids()
+                .into_iter()
+                .map(|parent_oid| new_oids.get(&parent_oid).copied().unwrap_or(parent_oid))
+                .collect_vec();
+            builder.move_subtree(descendant_oid, parent_oids)?;
+        }
+
+        let thread_pool = ThreadPoolBuilder::new().build()?;
+        let repo_pool = RepoResource::new_pool(&repo)?;
+        match builder.build(effects, &thread_pool, &repo_pool)? {
+            Ok(rebase_plan) => rebase_plan,
+            Err(err) => {
+                err.describe(effects, &repo, &dag)?;
+                return Ok(Err(ExitCode(1)));
+            }
+        }
+    };
+
+    if let Some(rebase_plan) = rebase_plan {
+        let execute_options = ExecuteRebasePlanOptions {
+            now,
+            event_tx_id,
+            force_in_memory: move_options.force_in_memory,
+            force_on_disk: move_options.force_on_disk,
+            preserve_timestamps,
+            resolve_merge_conflicts: move_options.resolve_merge_conflicts,
+            reset_author: move_options.reset_author,
+            committer_as_author: move_options.committer_as_author,
+            signature_commit_oids: None,
+            check_out_commit_options: CheckOutCommitOptions {
+                additional_args: Default::default(),
+                reset: false,
+                render_smartlog: false,
+            },
+            empty_commits: EmptyCommitsMode::Drop,
+        };
+        match execute_rebase_plan(
+            effects,
+            git_run_info,
+            &repo,
+            &event_log_db,
+            &rebase_plan,
+            &execute_options,
+        )? {
+            ExecuteRebasePlanResult::Succeeded {
+                rewritten_oids: None,
+            } => {}
+
+            ExecuteRebasePlanResult::Succeeded {
+                rewritten_oids: Some(rewritten_oids),
+            } => {
+                writeln!(
+                    effects.get_output_stream(),
+                    "Restacked {}.",
+                    Pluralize {
+                        determiner: None,
+                        amount: rewritten_oids.len(),
+                        unit: ("commit", "commits")
+                    }
+                )?;
+            }
+
+            ExecuteRebasePlanResult::DeclinedToMerge { failed_merge_info } => {
+                failed_merge_info.describe(effects, &repo, MergeConflictRemediation::Restack)?;
+                writeln!(
+                    effects.get_output_stream(),
+                    "Absorbed changes without restacking descendant commits."
+                )?;
+            }
+
+            ExecuteRebasePlanResult::Failed { exit_code } => {
+                return Ok(Err(exit_code));
+            }
+        }
+    }
+
+    Ok(Ok(()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(old_start: usize, old_len: usize, new_start: usize, new_len: usize) -> ChangedRegion {
+        ChangedRegion {
+            old_start,
+            old_len,
+            new_start,
+            added_lines: vec!["line\n".to_string(); new_len],
+        }
+    }
+
+    #[test]
+    fn test_find_absorb_target() {
+        let path = Path::new("foo.txt");
+        let stack_changes: Vec<FileChanges> = vec![
+            // Top commit: replaces line 5 with two lines.
+            [(path.to_owned(), Some(vec![region(5, 1, 5, 2)]))]
+                .into_iter()
+                .collect(),
+            // Middle commit: doesn't touch the file.
+            FileChanges::new(),
+            // Bottom commit: inserts lines 0-2.
+            [(path.to_owned(), Some(vec![region(0, 0, 0, 3)]))]
+                .into_iter()
+                .collect(),
+        ];
+
+        // Lines modified by the top commit.
+        assert_eq!(
+            find_absorb_target(&stack_changes, path, 5, 2),
+            Some(vec![(5, 2)])
+        );
+        // Lines modified by the bottom commit.
+        assert_eq!(
+            find_absorb_target(&stack_changes, path, 1, 1),
+            Some(vec![(1, 1), (1, 1), (1, 1)])
+        );
+        // Insertion directly after the lines modified by the bottom commit.
+        assert_eq!(
+            find_absorb_target(&stack_changes, path, 3, 0),
+            Some(vec![(3, 0), (3, 0), (3, 0)])
+        );
+        // Lines which were shifted by the top commit's change, but which
+        // weren't modified in the stack.
+        assert_eq!(find_absorb_target(&stack_changes, path, 7, 1), None);
+        // Partially overlaps lines modified by the top commit.
+        assert_eq!(find_absorb_target(&stack_changes, path, 4, 2), None);
+        // Lines not modified by any commit in the stack.
+        assert_eq!(find_absorb_target(&stack_changes, path, 4, 1), None);
+        // Files not modified by any commit in the stack.
+        assert_eq!(
+            find_absorb_target(&stack_changes, Path::new("bar.txt"), 0, 1),
+            None
+        );
+    }
+}
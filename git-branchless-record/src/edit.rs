@@ -0,0 +1,745 @@
+//! Edit a stack of commits by editing a list of commands, similar to
+//! `git rebase --interactive`.
+//!
+//! The commands are:
+//!
+//! - `pick <commit>` keeps the commit.
+//! - `reword <commit>` keeps the commit, but edits its message.
+//! - `squash <commit>` combines the commit into the previous commit, and edits
+//!   the combined message.
+//! - `fixup <commit>` combines the commit into the previous commit, and
+//!   discards its message.
+//! - `split <commit>` keeps the commit, but interactively splits it into two
+//!   commits.
+//! - `drop <commit>` removes the commit.
+//!
+//! Unlike `git rebase --interactive`, the edited commits are constructed in
+//! memory without touching the working copy, and then any descendants are
+//! restacked on top of them, all as a single operation which can be reverted
+//! with `git undo`.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::time::SystemTime;
+
+use eyre::Context;
+use git_branchless_invoke::CommandContext;
+use git_branchless_opts::{EditArgs, MoveOptions, ResolveRevsetOptions, Revset};
+use git_branchless_revset::resolve_commits;
+use git_branchless_reword::edit_message;
+use itertools::Itertools;
+use lib::core::check_out::{check_out_commit, CheckOutCommitOptions, CheckoutTarget};
+use lib::core::config::{get_comment_char, get_restack_preserve_timestamps};
+use lib::core::dag::{
+    linear_commit_range, sorted_commit_set, union_all, CommitSet, Dag, LinearCommitRange,
+};
+use lib::core::effects::Effects;
+use lib::core::eventlog::{Event, EventLogDb, EventReplayer};
+use lib::core::formatting::Pluralize;
+use lib::core::gc::mark_commit_reachable;
+use lib::core::repo_ext::RepoExt;
+use lib::core::rewrite::rewrite_hooks::run_post_rewrite_user_hook;
+use lib::core::rewrite::{
+    execute_rebase_plan, move_branches, BuildRebasePlanOptions, EmptyCommitsMode,
+    ExecuteRebasePlanOptions, ExecuteRebasePlanResult, MergeConflictRemediation, RebasePlanBuilder,
+    RebasePlanPermissions, RepoResource,
+};
+use lib::git::{
+    message_prettify, CherryPickFastOptions, Commit, CreateCommitFastError, GitRunInfo,
+    MaybeZeroOid, NonZeroOid, Repo, Signer,
+};
+use lib::try_exit_code;
+use lib::util::{ExitCode, EyreExitOr};
+use rayon::ThreadPoolBuilder;
+use tracing::instrument;
+
+use crate::split::select_changes_to_split;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum EditCommand {
+    Pick,
+    Reword,
+    Squash,
+    Fixup,
+    Split,
+    Drop,
+}
+
+impl std::fmt::Display for EditCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EditCommand::Pick => write!(f, "pick"),
+            EditCommand::Reword => write!(f, "reword"),
+            EditCommand::Squash => write!(f, "squash"),
+            EditCommand::Fixup => write!(f, "fixup"),
+            EditCommand::Split => write!(f, "split"),
+            EditCommand::Drop => write!(f, "drop"),
+        }
+    }
+}
+
+/// A commit which is kept in the edited stack, along with the commits which
+/// are squashed into it.
+#[derive(Debug)]
+struct EditGroup {
+    command: EditCommand,
+    commit_oid: NonZeroOid,
+    squash_oids: Vec<(EditCommand, NonZeroOid)>,
+}
+
+fn describe_commit(commit: &Commit) -> eyre::Result<String> {
+    Ok(format!(
+        "{} {}",
+        commit.get_short_oid()?,
+        String::from_utf8_lossy(&commit.get_summary()?)
+    ))
+}
+
+fn get_message(commit: &Commit) -> String {
+    String::from_utf8_lossy(&commit.get_message_raw()).into_owned()
+}
+
+/// Parse the edited list of commands. Returns a message describing the
+/// problem if the commands are invalid.
+fn parse_edit_plan(
+    repo: &Repo,
+    commit_oids: &[NonZeroOid],
+    text: &str,
+    comment_char: char,
+) -> eyre::Result<Result<Vec<(EditCommand, NonZeroOid)>, String>> {
+    let mut steps = Vec::new();
+    let mut seen_oids = HashSet::new();
+    let mut has_kept_commit = false;
+    for (line_num, line) in text.lines().enumerate() {
+        let line_num = line_num + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(comment_char) {
+            continue;
+        }
+
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some(command) => command,
+            None => continue,
+        };
+        let command = match command {
+            "p" | "pick" => EditCommand::Pick,
+            "r" | "reword" => EditCommand::Reword,
+            "s" | "squash" => EditCommand::Squash,
+            "f" | "fixup" => EditCommand::Fixup,
+            "split" => EditCommand::Split,
+            "d" | "drop" => EditCommand::Drop,
+            command => {
+                return Ok(Err(format!(
+                    "Unknown command on line {line_num}: {command}"
+                )))
+            }
+        };
+        let commit = match words.next() {
+            Some(commit) => commit,
+            None => {
+                return Ok(Err(format!(
+                    "Missing commit for `{command}` command on line {line_num}"
+                )))
+            }
+        };
+        let commit_oid = match repo.revparse_single_commit(commit)? {
+            Some(commit) => commit.get_oid(),
+            None => {
+                return Ok(Err(format!(
+                    "Could not resolve commit on line {line_num}: {commit}"
+                )))
+            }
+        };
+        if !commit_oids.contains(&commit_oid) {
+            return Ok(Err(format!(
+                "Commit on line {line_num} is not one of the commits being edited: {commit}"
+            )));
+        }
+        if !seen_oids.insert(commit_oid) {
+            return Ok(Err(format!(
+                "Commit on line {line_num} was already listed: {commit}"
+            )));
+        }
+
+        match command {
+            EditCommand::Pick | EditCommand::Reword | EditCommand::Split => {
+                has_kept_commit = true;
+            }
+            EditCommand::Squash | EditCommand::Fixup => {
+                if !has_kept_commit {
+                    return Ok(Err(format!(
+                        "Cannot {command} commit on line {line_num} without a previous commit to combine it into: {commit}"
+                    )));
+                }
+            }
+            EditCommand::Drop => {}
+        }
+        steps.push((command, commit_oid));
+    }
+
+    if !steps.is_empty() && steps.len() != commit_oids.len() {
+        return Ok(Err(format!(
+            "Expected all {} to be listed, but only {} listed",
+            Pluralize {
+                determiner: None,
+                amount: commit_oids.len(),
+                unit: ("commit", "commits"),
+            },
+            steps.len()
+        )));
+    }
+    Ok(Ok(steps))
+}
+
+/// Apply the changes of `commit` on top of `onto`, returning the resulting
+/// tree. Prints the conflicting paths if the changes can't be applied without
+/// merge conflicts.
+fn apply_commit(
+    effects: &Effects,
+    repo: &Repo,
+    commit: &Commit,
+    onto: &Commit,
+) -> EyreExitOr<NonZeroOid> {
+    match repo.cherry_pick_fast(
+        commit,
+        onto,
+        &CherryPickFastOptions {
+            reuse_parent_tree_if_possible: true,
+        },
+    ) {
+        Ok(tree) => Ok(Ok(tree.get_oid())),
+        Err(CreateCommitFastError::MergeConflict { conflicting_paths }) => {
+            writeln!(
+                effects.get_output_stream(),
+                "Could not apply {} without merge conflicts in these paths:",
+                effects
+                    .get_glyphs()
+                    .render(commit.friendly_describe(effects.get_glyphs())?)?,
+            )?;
+            for path in conflicting_paths.iter().sorted() {
+                writeln!(effects.get_output_stream(), "  {}", path.display())?;
+            }
+            writeln!(effects.get_output_stream(), "No commits were edited.")?;
+            Ok(Err(ExitCode(1)))
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Edit a stack of commits.
+#[instrument]
+pub fn command_main(ctx: CommandContext, args: EditArgs) -> EyreExitOr<()> {
+    let CommandContext {
+        effects,
+        git_run_info,
+    } = ctx;
+    let EditArgs {
+        revsets,
+        resolve_revset_options,
+        move_options,
+    } = args;
+    edit(
+        &effects,
+        &git_run_info,
+        revsets,
+        &resolve_revset_options,
+        &move_options,
+    )
+}
+
+#[instrument]
+fn edit(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    revsets: Vec<Revset>,
+    resolve_revset_options: &ResolveRevsetOptions,
+    move_options: &MoveOptions,
+) -> EyreExitOr<()> {
+    let now = SystemTime::now();
+    let timestamp = now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64();
+    let repo = Repo::from_current_dir()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let references_snapshot = repo.get_references_snapshot()?;
+    let mut dag = Dag::open_and_sync(
+        effects,
+        &repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+
+    let commit_set =
+        match resolve_commits(effects, &repo, &mut dag, &revsets, resolve_revset_options) {
+            Ok(commit_sets) => union_all(&commit_sets),
+            Err(err) => {
+                err.describe(effects)?;
+                return Ok(Err(ExitCode(1)));
+            }
+        };
+    let commits = sorted_commit_set(&repo, &dag, &commit_set)?;
+    if commits.is_empty() {
+        writeln!(effects.get_output_stream(), "Nothing to edit.")?;
+        return Ok(Ok(()));
+    }
+
+    // The commits must form a single linear range, so that there's an
+    // unambiguous order to edit.
+    let LinearCommitRange {
+        base_oid,
+        root_oid: _,
+        head_oid: last_oid,
+    } = match linear_commit_range(effects, &commits, "edit")? {
+        Ok(range) => range,
+        Err(exit_code) => return Ok(Err(exit_code)),
+    };
+    let commit_oids: Vec<NonZeroOid> = commits.iter().map(|commit| commit.get_oid()).collect();
+
+    let build_options = BuildRebasePlanOptions {
+        force_rewrite_public_commits: move_options.force_rewrite_public_commits,
+        dump_rebase_constraints: move_options.dump_rebase_constraints,
+        dump_rebase_plan: move_options.dump_rebase_plan,
+        detect_duplicate_commits_via_patch_id: move_options.detect_duplicate_commits_via_patch_id,
+    };
+    let commits_to_verify = dag.query_descendants(commit_set.clone())?;
+    let commits_to_verify = dag.filter_visible_commits(commits_to_verify)?;
+    if let Err(err) =
+        RebasePlanPermissions::verify_rewrite_set(&dag, build_options.clone(), &commits_to_verify)?
+    {
+        err.describe(effects, &repo, &dag)?;
+        return Ok(Err(ExitCode(1)));
+    };
+
+    let comment_char = get_comment_char(&repo)?;
+    let mut text = String::new();
+    for commit in commits.iter() {
+        writeln!(text, "pick {}", describe_commit(commit)?)?;
+    }
+    write!(
+        text,
+        "\
+{c}
+{c} Edit {num_commits} onto {base}.
+{c}
+{c} Commands:
+{c} p, pick <commit> = use commit
+{c} r, reword <commit> = use commit, but edit its message
+{c} s, squash <commit> = combine commit into the previous commit, and edit the
+{c}                      combined message
+{c} f, fixup <commit> = combine commit into the previous commit, and discard its
+{c}                     message
+{c} split <commit> = use commit, but interactively split it into two commits
+{c} d, drop <commit> = remove commit
+{c}
+{c} These lines can be re-ordered; they are executed from top to bottom. Every
+{c} commit must remain listed exactly once. If you remove everything, the edit
+{c} will be aborted.
+",
+        c = comment_char,
+        num_commits = Pluralize {
+            determiner: None,
+            amount: commits.len(),
+            unit: ("commit", "commits"),
+        },
+        base = describe_commit(&repo.find_commit_or_fail(base_oid)?)?,
+    )?;
+    let text = edit_message(git_run_info, &repo, &text)?;
+    let steps = match parse_edit_plan(&repo, &commit_oids, &text, comment_char)? {
+        Ok(steps) => steps,
+        Err(message) => {
+            writeln!(effects.get_error_stream(), "{message}")?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+    if steps.is_empty() {
+        writeln!(effects.get_output_stream(), "Aborted.")?;
+        return Ok(Ok(()));
+    }
+    if steps
+        .iter()
+        .copied()
+        .eq(commit_oids.iter().map(|oid| (EditCommand::Pick, *oid)))
+    {
+        writeln!(effects.get_output_stream(), "Nothing to do.")?;
+        return Ok(Ok(()));
+    }
+
+    // Each dropped commit is associated with the index of the group preceding
+    // it, so that any branches pointing to it can be moved there.
+    let mut groups: Vec<EditGroup> = Vec::new();
+    let mut dropped_oids: Vec<(NonZeroOid, Option<usize>)> = Vec::new();
+    for (command, commit_oid) in steps {
+        match command {
+            EditCommand::Pick | EditCommand::Reword | EditCommand::Split => {
+                groups.push(EditGroup {
+                    command,
+                    commit_oid,
+                    squash_oids: Vec::new(),
+                });
+            }
+            EditCommand::Squash | EditCommand::Fixup => match groups.last_mut() {
+                Some(group) => group.squash_oids.push((command, commit_oid)),
+                None => eyre::bail!("BUG: Squashed commit without a previous commit"),
+            },
+            EditCommand::Drop => {
+                dropped_oids.push((commit_oid, groups.len().checked_sub(1)));
+            }
+        }
+    }
+
+    // Ask for all the commit messages up-front, before constructing any
+    // commits.
+    let mut messages = Vec::new();
+    for group in groups.iter() {
+        let commit = repo.find_commit_or_fail(group.commit_oid)?;
+        let mut message = get_message(&commit);
+        let mut should_edit = group.command == EditCommand::Reword;
+        for (command, squash_oid) in group.squash_oids.iter() {
+            if *command == EditCommand::Squash {
+                let squash_commit = repo.find_commit_or_fail(*squash_oid)?;
+                if !message.ends_with('\n') {
+                    message.push('\n');
+                }
+                message.push('\n');
+                message.push_str(&get_message(&squash_commit));
+                should_edit = true;
+            }
+        }
+        if should_edit {
+            let edited_message = edit_message(git_run_info, &repo, &message)?;
+            message = message_prettify(&edited_message, Some(comment_char))?;
+            if message.trim().is_empty() {
+                writeln!(
+                    effects.get_error_stream(),
+                    "Aborting edit due to empty commit message for: {}",
+                    effects
+                        .get_glyphs()
+                        .render(commit.friendly_describe(effects.get_glyphs())?)?,
+                )?;
+                return Ok(Err(ExitCode(1)));
+            }
+        }
+        messages.push(message);
+    }
+
+    let preserve_timestamps =
+        move_options.preserve_timestamps || get_restack_preserve_timestamps(&repo)?;
+    let signer = Signer::from_config(&repo)?;
+    let mut created_oids: Vec<NonZeroOid> = Vec::new();
+    let mut group_new_oids: Vec<NonZeroOid> = Vec::new();
+    let mut rewritten_oids: HashMap<NonZeroOid, MaybeZeroOid> = HashMap::new();
+    let mut parent_oid = base_oid;
+    for (group, message) in groups.iter().zip(messages) {
+        let commit = repo.find_commit_or_fail(group.commit_oid)?;
+        let mut parent = repo.find_commit_or_fail(parent_oid)?;
+        let author = commit.get_author();
+        let committer = if preserve_timestamps {
+            commit.get_committer()
+        } else {
+            commit.get_committer().update_timestamp(now)?
+        };
+
+        let mut tree_oid = try_exit_code!(apply_commit(effects, &repo, &commit, &parent)?);
+        for (_, squash_oid) in group.squash_oids.iter() {
+            let squash_commit = repo.find_commit_or_fail(*squash_oid)?;
+            let intermediate_oid = repo.create_commit(
+                None,
+                &author,
+                &committer,
+                &message,
+                &repo.find_tree_or_fail(tree_oid)?,
+                vec![&parent],
+            )?;
+            let intermediate_commit = repo.find_commit_or_fail(intermediate_oid)?;
+            tree_oid = try_exit_code!(apply_commit(
+                effects,
+                &repo,
+                &squash_commit,
+                &intermediate_commit
+            )?);
+        }
+
+        let tree = repo.find_tree_or_fail(tree_oid)?;
+        let mut message = message;
+        if group.command == EditCommand::Split {
+            let split = try_exit_code!(select_changes_to_split(
+                effects,
+                git_run_info,
+                &repo,
+                &parent.get_tree()?,
+                &tree,
+                &message,
+            )?);
+            match split {
+                Some((first_tree_oid, first_message, second_message)) => {
+                    let first_tree = repo.find_tree_or_fail(first_tree_oid)?;
+                    let first_commit_oid = repo.create_commit_with_signer(
+                        &author,
+                        &committer,
+                        &first_message,
+                        &first_tree,
+                        vec![&parent],
+                        signer.as_ref(),
+                    )?;
+                    created_oids.push(first_commit_oid);
+                    parent = repo.find_commit_or_fail(first_commit_oid)?;
+                    message = second_message;
+                }
+                None => {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "Select some, but not all, of the changes to split the commit into two commits.\nNot splitting: {}",
+                        effects
+                            .get_glyphs()
+                            .render(commit.friendly_describe(effects.get_glyphs())?)?,
+                    )?;
+                }
+            }
+        }
+
+        let new_commit_oid = repo.create_commit_with_signer(
+            &author,
+            &committer,
+            &message,
+            &tree,
+            vec![&parent],
+            signer.as_ref(),
+        )?;
+        rewritten_oids.insert(group.commit_oid, MaybeZeroOid::NonZero(new_commit_oid));
+        for (_, squash_oid) in group.squash_oids.iter() {
+            rewritten_oids.insert(*squash_oid, MaybeZeroOid::NonZero(new_commit_oid));
+        }
+        group_new_oids.push(new_commit_oid);
+        parent_oid = new_commit_oid;
+    }
+    let new_last_oid = parent_oid;
+
+    // Dropped commits are hidden, but any branches pointing to them are moved
+    // to the preceding commit in the edited stack.
+    let moved_oids: HashMap<NonZeroOid, MaybeZeroOid> = {
+        let mut result = rewritten_oids.clone();
+        for (dropped_oid, group_index) in dropped_oids.iter() {
+            let new_oid = match group_index {
+                Some(group_index) => group_new_oids[*group_index],
+                None => base_oid,
+            };
+            result.insert(*dropped_oid, MaybeZeroOid::NonZero(new_oid));
+        }
+        result
+    };
+
+    let event_tx_id = event_log_db.make_transaction_id(now, "edit")?;
+    let head_info = repo.get_head_info()?;
+    let new_head_oid = match head_info.oid {
+        Some(head_oid) => match moved_oids.get(&head_oid) {
+            Some(MaybeZeroOid::NonZero(new_head_oid)) => Some(*new_head_oid),
+            Some(MaybeZeroOid::Zero) | None => None,
+        },
+        None => None,
+    };
+    if let Some(new_head_oid) = new_head_oid {
+        // Check out the new commit before moving any branches, so that the
+        // working copy is updated even if `HEAD` points to one of the
+        // branches.
+        try_exit_code!(check_out_commit(
+            effects,
+            git_run_info,
+            &repo,
+            &event_log_db,
+            event_tx_id,
+            Some(CheckoutTarget::Oid(new_head_oid)),
+            &CheckOutCommitOptions {
+                additional_args: Default::default(),
+                reset: false,
+                render_smartlog: false,
+            },
+        )?);
+    }
+
+    {
+        let mut events = Vec::new();
+        for commit_oid in created_oids.iter().chain(group_new_oids.iter()) {
+            mark_commit_reachable(&repo, *commit_oid)
+                .wrap_err("Marking commit as reachable for GC purposes.")?;
+        }
+        for commit_oid in created_oids.iter() {
+            events.push(Event::CommitEvent {
+                timestamp,
+                event_tx_id,
+                commit_oid: *commit_oid,
+            });
+        }
+        for commit_oid in commit_oids.iter() {
+            if let Some(new_commit_oid) = rewritten_oids.get(commit_oid) {
+                events.push(Event::RewriteEvent {
+                    timestamp,
+                    event_tx_id,
+                    old_commit_oid: MaybeZeroOid::NonZero(*commit_oid),
+                    new_commit_oid: *new_commit_oid,
+                });
+            }
+        }
+        for (dropped_oid, _) in dropped_oids.iter() {
+            events.push(Event::ObsoleteEvent {
+                timestamp,
+                event_tx_id,
+                commit_oid: *dropped_oid,
+            });
+        }
+        event_log_db.add_events(events)?;
+    }
+    move_branches(effects, git_run_info, &repo, event_tx_id, &moved_oids)?;
+    if let (Some(_), Some(reference_name)) = (new_head_oid, &head_info.reference_name) {
+        try_exit_code!(check_out_commit(
+            effects,
+            git_run_info,
+            &repo,
+            &event_log_db,
+            event_tx_id,
+            Some(CheckoutTarget::Reference(reference_name.clone())),
+            &CheckOutCommitOptions {
+                additional_args: Default::default(),
+                reset: false,
+                render_smartlog: false,
+            },
+        )?);
+    }
+    run_post_rewrite_user_hook(
+        effects,
+        git_run_info,
+        &repo,
+        event_tx_id,
+        "rebase",
+        &rewritten_oids,
+    )?;
+
+    writeln!(
+        effects.get_output_stream(),
+        "Edited {}.",
+        Pluralize {
+            determiner: None,
+            amount: commit_oids.len(),
+            unit: ("commit", "commits"),
+        }
+    )?;
+
+    let rebase_plan = {
+        let new_commit_set: CommitSet = created_oids
+            .iter()
+            .chain(group_new_oids.iter())
+            .copied()
+            .collect();
+        dag.sync_from_oids(effects, &repo, CommitSet::empty(), new_commit_set)?;
+
+        // Commits downstream of the last commit are moved on top of the new
+        // last commit, rather than following the commit they were originally
+        // attached to.
+        let children = dag
+            .query_children(commit_set.clone())?
+            .difference(&commit_set);
+        let children = dag.filter_visible_commits(children)?;
+        let descendants = dag.query_descendants(children.clone())?;
+        let descendants = dag.filter_visible_commits(descendants)?;
+        let permissions =
+            match RebasePlanPermissions::verify_rewrite_set(&dag, build_options, &descendants)? {
+                Ok(permissions) => permissions,
+                Err(err) => {
+                    err.describe(effects, &repo, &dag)?;
+                    return Ok(Err(ExitCode(1)));
+                }
+            };
+
+        let mut builder = RebasePlanBuilder::new(&dag, permissions);
+        for child_oid in dag.commit_set_to_vec(&children)? {
+            let child_commit = repo.find_commit_or_fail(child_oid)?;
+            let parent_oids = child_commit
+                .get_parent_oids()
+                .into_iter()
+                .map(|parent_oid| {
+                    if parent_oid == last_oid {
+                        new_last_oid
+                    } else {
+                        match moved_oids.get(&parent_oid) {
+                            Some(MaybeZeroOid::NonZero(new_parent_oid)) => *new_parent_oid,
+                            Some(MaybeZeroOid::Zero) | None => parent_oid,
+                        }
+                    }
+                })
+                .collect_vec();
+            builder.move_subtree(child_oid, parent_oids)?;
+        }
+
+        let thread_pool = ThreadPoolBuilder::new().build()?;
+        let repo_pool = RepoResource::new_pool(&repo)?;
+        match builder.build(effects, &thread_pool, &repo_pool)? {
+            Ok(rebase_plan) => rebase_plan,
+            Err(err) => {
+                err.describe(effects, &repo, &dag)?;
+                return Ok(Err(ExitCode(1)));
+            }
+        }
+    };
+
+    if let Some(rebase_plan) = rebase_plan {
+        let execute_options = ExecuteRebasePlanOptions {
+            now,
+            event_tx_id,
+            force_in_memory: move_options.force_in_memory,
+            force_on_disk: move_options.force_on_disk,
+            preserve_timestamps,
+            resolve_merge_conflicts: move_options.resolve_merge_conflicts,
+            reset_author: move_options.reset_author,
+            committer_as_author: move_options.committer_as_author,
+            signature_commit_oids: None,
+            check_out_commit_options: CheckOutCommitOptions {
+                additional_args: Default::default(),
+                reset: false,
+                render_smartlog: false,
+            },
+            empty_commits: EmptyCommitsMode::Drop,
+        };
+        match execute_rebase_plan(
+            effects,
+            git_run_info,
+            &repo,
+            &event_log_db,
+            &rebase_plan,
+            &execute_options,
+        )? {
+            ExecuteRebasePlanResult::Succeeded {
+                rewritten_oids: None,
+            } => {}
+
+            ExecuteRebasePlanResult::Succeeded {
+                rewritten_oids: Some(rewritten_oids),
+            } => {
+                writeln!(
+                    effects.get_output_stream(),
+                    "Restacked {}.",
+                    Pluralize {
+                        determiner: None,
+                        amount: rewritten_oids.len(),
+                        unit: ("commit", "commits")
+                    }
+                )?;
+            }
+
+            ExecuteRebasePlanResult::DeclinedToMerge { failed_merge_info } => {
+                failed_merge_info.describe(effects, &repo, MergeConflictRemediation::Restack)?;
+                writeln!(
+                    effects.get_output_stream(),
+                    "Edited without restacking descendant commits."
+                )?;
+            }
+
+            ExecuteRebasePlanResult::Failed { exit_code } => {
+                return Ok(Err(exit_code));
+            }
+        }
+    }
+
+    Ok(Ok(()))
+}
@@ -9,26 +9,34 @@
 )]
 #![allow(clippy::too_many_arguments, clippy::blocks_in_if_conditions)]
 
+pub mod absorb;
+pub mod edit;
+pub mod split;
+
 use std::collections::HashSet;
 use std::ffi::OsString;
 use std::fmt::Write;
 use std::time::SystemTime;
 
 use git_branchless_invoke::CommandContext;
+use git_branchless_navigation::prompt::prompt_select_commit;
 use git_branchless_opts::RecordArgs;
-use git_branchless_reword::edit_message;
+use git_branchless_reword::{edit_file_contents, edit_message};
 use itertools::Itertools;
 use lib::core::check_out::{check_out_commit, CheckOutCommitOptions, CheckoutTarget};
-use lib::core::config::{get_commit_template, get_restack_preserve_timestamps};
-use lib::core::dag::{CommitSet, Dag};
+use lib::core::config::{
+    get_commit_template, get_commit_trailers, get_restack_preserve_timestamps,
+};
+use lib::core::dag::{sorted_commit_set, CommitSet, Dag};
 use lib::core::effects::{Effects, OperationType};
 use lib::core::eventlog::{EventLogDb, EventReplayer, EventTransactionId};
 use lib::core::formatting::Pluralize;
+use lib::core::node_descriptors::{CommitMessageDescriptor, CommitOidDescriptor, Redactor};
 use lib::core::repo_ext::RepoExt;
 use lib::core::rewrite::{
-    execute_rebase_plan, BuildRebasePlanError, BuildRebasePlanOptions, ExecuteRebasePlanOptions,
-    ExecuteRebasePlanResult, MergeConflictRemediation, RebasePlanBuilder, RebasePlanPermissions,
-    RepoResource,
+    execute_rebase_plan, BuildRebasePlanError, BuildRebasePlanOptions, EmptyCommitsMode,
+    ExecuteRebasePlanOptions, ExecuteRebasePlanResult, MergeConflictRemediation, RebasePlanBuilder,
+    RebasePlanPermissions, RepoResource,
 };
 use lib::git::{
     process_diff_for_record, update_index, CategorizedReferenceName, FileMode, GitRunInfo,
@@ -54,20 +62,28 @@ pub fn command_main(ctx: CommandContext, args: RecordArgs) -> EyreExitOr<()> {
     let RecordArgs {
         messages,
         interactive,
+        amend,
+        edit_selection,
         create,
         detach,
         insert,
         stash,
+        allow_empty,
+        no_trailers,
     } = args;
     record(
         &effects,
         &git_run_info,
         messages,
         interactive,
+        amend,
+        edit_selection,
         create,
         detach,
         insert,
         stash,
+        allow_empty,
+        no_trailers,
     )
 }
 
@@ -77,10 +93,14 @@ fn record(
     git_run_info: &GitRunInfo,
     messages: Vec<String>,
     interactive: bool,
+    amend: bool,
+    edit_selection: bool,
     branch_name: Option<String>,
     detach: bool,
     insert: bool,
     stash: bool,
+    allow_empty: bool,
+    no_trailers: bool,
 ) -> EyreExitOr<()> {
     let now = SystemTime::now();
     let repo = Repo::from_dir(&git_run_info.working_directory)?;
@@ -96,6 +116,7 @@ fn record(
 
         let working_copy_changes_type = snapshot.get_working_copy_changes_type()?;
         match working_copy_changes_type {
+            WorkingCopyChangesType::None if allow_empty => {}
             WorkingCopyChangesType::None => {
                 writeln!(
                     effects.get_output_stream(),
@@ -135,6 +156,13 @@ fn record(
         )?);
     }
 
+    let trailers = if no_trailers {
+        Vec::new()
+    } else {
+        let head_info = repo.get_head_info()?;
+        get_commit_trailers(&repo, head_info.get_branch_name()?)?
+    };
+
     if interactive {
         if working_copy_changes_type == WorkingCopyChangesType::Staged {
             writeln!(
@@ -146,6 +174,15 @@ fn record(
                 "Either commit or unstage your changes and try again. Aborting."
             )?;
             return Ok(Err(ExitCode(1)));
+        } else if amend {
+            try_exit_code!(record_interactive_amend(
+                effects,
+                git_run_info,
+                &repo,
+                &event_log_db,
+                event_tx_id,
+                edit_selection,
+            )?);
         } else {
             try_exit_code!(record_interactive(
                 effects,
@@ -154,15 +191,25 @@ fn record(
                 &snapshot,
                 event_tx_id,
                 messages,
+                &trailers,
+                edit_selection,
             )?);
         }
     } else {
         let args = {
             let mut args = vec!["commit"];
             args.extend(messages.iter().flat_map(|message| ["--message", message]));
+            if allow_empty {
+                args.push("--allow-empty");
+            }
             if working_copy_changes_type == WorkingCopyChangesType::Unstaged {
                 args.push("--all");
             }
+            args.extend(
+                trailers
+                    .iter()
+                    .flat_map(|trailer| ["--trailer", trailer.as_str()]),
+            );
             args
         };
         try_exit_code!(git_run_info.run_direct_no_wrapping(Some(event_tx_id), &args)?);
@@ -246,6 +293,36 @@ fn record(
     Ok(Ok(()))
 }
 
+/// Drives the interactive change selector in the terminal, and edits commit
+/// messages with the user's editor.
+pub(crate) struct Input<'a> {
+    pub(crate) git_run_info: &'a GitRunInfo,
+    pub(crate) repo: &'a Repo,
+}
+
+impl RecordInput for Input<'_> {
+    fn terminal_kind(&self) -> TerminalKind {
+        TerminalKind::Crossterm
+    }
+
+    fn next_events(&mut self) -> Result<Vec<Event>, RecordError> {
+        CrosstermInput.next_events()
+    }
+
+    fn edit_commit_message(&mut self, message: &str) -> Result<String, RecordError> {
+        let Self { git_run_info, repo } = self;
+        let commit_template = get_commit_template(repo).map_err(|err| {
+            RecordError::Other(format!("Could not read commit message template: {err}",))
+        })?;
+        let message = if message.is_empty() {
+            commit_template.as_deref().unwrap_or("")
+        } else {
+            message
+        };
+        edit_message(git_run_info, repo, message).map_err(|err| RecordError::Other(err.to_string()))
+    }
+}
+
 #[instrument]
 fn record_interactive(
     effects: &Effects,
@@ -254,7 +331,162 @@ fn record_interactive(
     snapshot: &WorkingCopySnapshot,
     event_tx_id: EventTransactionId,
     messages: Vec<String>,
+    trailers: &[String],
+    edit_selection: bool,
 ) -> EyreExitOr<()> {
+    let (message, update_index_script, _has_selected) = try_exit_code!(select_changes_interactive(
+        effects,
+        git_run_info,
+        repo,
+        snapshot,
+        Some(messages.iter().join("\n\n")),
+        edit_selection,
+    )?);
+    let index = repo.get_index()?;
+    update_index(
+        git_run_info,
+        repo,
+        &index,
+        event_tx_id,
+        &update_index_script,
+    )?;
+
+    let args = {
+        let mut args = vec!["commit"];
+        if !message.is_empty() {
+            args.extend(["--message", &message]);
+        }
+        args.extend(
+            trailers
+                .iter()
+                .flat_map(|trailer| ["--trailer", trailer.as_str()]),
+        );
+        args
+    };
+    git_run_info.run_direct_no_wrapping(Some(event_tx_id), &args)
+}
+
+/// Repeatedly select changes interactively and amend them into a draft commit
+/// in the current stack, chosen with the commit selector, until there are no
+/// changes left or no changes are selected.
+#[instrument]
+fn record_interactive_amend(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    event_log_db: &EventLogDb,
+    event_tx_id: EventTransactionId,
+    edit_selection: bool,
+) -> EyreExitOr<()> {
+    loop {
+        let head_info = repo.get_head_info()?;
+        let head_oid = match head_info.oid {
+            Some(head_oid) => head_oid,
+            None => {
+                writeln!(
+                    effects.get_output_stream(),
+                    "No commit is currently checked out. Check out a commit to amend into and then try again."
+                )?;
+                return Ok(Err(ExitCode(1)));
+            }
+        };
+
+        // The commits are rewritten by each amend, so the stack has to be
+        // recomputed every time.
+        let stack_commits = {
+            let event_replayer = EventReplayer::from_event_log_db(effects, repo, event_log_db)?;
+            let event_cursor = event_replayer.make_default_cursor();
+            let references_snapshot = repo.get_references_snapshot()?;
+            let dag = Dag::open_and_sync(
+                effects,
+                repo,
+                &event_replayer,
+                event_cursor,
+                &references_snapshot,
+            )?;
+            let stack = dag
+                .query_ancestors(CommitSet::from(head_oid))?
+                .intersection(dag.query_draft_commits()?);
+            let mut stack_commits = sorted_commit_set(repo, &dag, &stack)?;
+            stack_commits.reverse();
+            stack_commits
+        };
+        if stack_commits.is_empty() {
+            writeln!(
+                effects.get_output_stream(),
+                "There are no draft commits in the current stack to amend changes into."
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+
+        let index = repo.get_index()?;
+        let (snapshot, _status) =
+            repo.get_status(effects, git_run_info, &index, &head_info, Some(event_tx_id))?;
+        if snapshot.get_working_copy_changes_type()? == WorkingCopyChangesType::None {
+            return Ok(Ok(()));
+        }
+
+        let (_message, update_index_script, has_selected) =
+            try_exit_code!(select_changes_interactive(
+                effects,
+                git_run_info,
+                repo,
+                &snapshot,
+                None,
+                edit_selection
+            )?);
+        if !has_selected {
+            return Ok(Ok(()));
+        }
+
+        let target_oid = match prompt_select_commit(
+            effects,
+            repo,
+            Some("Amend the selected changes into:"),
+            "",
+            stack_commits,
+            &mut [
+                &mut CommitOidDescriptor::new(true)?,
+                &mut CommitMessageDescriptor::new(&Redactor::Disabled)?,
+            ],
+        )? {
+            Some(target_oid) => target_oid,
+            None => {
+                writeln!(effects.get_output_stream(), "Aborted.")?;
+                return Ok(Err(ExitCode(1)));
+            }
+        };
+
+        update_index(
+            git_run_info,
+            repo,
+            &index,
+            event_tx_id,
+            &update_index_script,
+        )?;
+        let target_oid = target_oid.to_string();
+        let mut args = vec!["branchless", "amend"];
+        if target_oid != head_oid.to_string() {
+            args.extend(["--to", &target_oid]);
+        }
+        try_exit_code!(git_run_info.run_direct_no_wrapping(Some(event_tx_id), &args)?);
+    }
+}
+
+/// Interactively select which of the changes in the working copy to include.
+/// If `edit_selection` is set, the selected contents of each file are then
+/// opened in the user's editor, so that they can be adjusted before being
+/// staged. Returns the edited message for the first commit, the commands to
+/// stage the selected changes, and whether any changes were selected.
+#[instrument]
+fn select_changes_interactive(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    snapshot: &WorkingCopySnapshot,
+    message: Option<String>,
+    edit_selection: bool,
+) -> EyreExitOr<(String, Vec<UpdateIndexCommand>, bool)> {
     let old_tree = snapshot.commit_stage0.get_tree()?;
     let new_tree = snapshot.commit_unstaged.get_tree()?;
     let files = {
@@ -270,42 +502,10 @@ fn record_interactive(
     };
     let record_state = RecordState {
         is_read_only: false,
-        commits: vec![
-            Commit {
-                message: Some(messages.iter().join("\n\n")),
-            },
-            Commit { message: None },
-        ],
+        commits: vec![Commit { message }, Commit { message: None }],
         files,
     };
 
-    struct Input<'a> {
-        git_run_info: &'a GitRunInfo,
-        repo: &'a Repo,
-    }
-    impl RecordInput for Input<'_> {
-        fn terminal_kind(&self) -> TerminalKind {
-            TerminalKind::Crossterm
-        }
-
-        fn next_events(&mut self) -> Result<Vec<Event>, RecordError> {
-            CrosstermInput.next_events()
-        }
-
-        fn edit_commit_message(&mut self, message: &str) -> Result<String, RecordError> {
-            let Self { git_run_info, repo } = self;
-            let commit_template = get_commit_template(repo).map_err(|err| {
-                RecordError::Other(format!("Could not read commit message template: {err}",))
-            })?;
-            let message = if message.is_empty() {
-                commit_template.as_deref().unwrap_or("")
-            } else {
-                message
-            };
-            edit_message(git_run_info, repo, message)
-                .map_err(|err| RecordError::Other(err.to_string()))
-        }
-    }
     let mut input = Input { git_run_info, repo };
     let recorder = Recorder::new(record_state, &mut input);
     let result = recorder.run();
@@ -339,6 +539,8 @@ fn record_interactive(
     };
     let message = commits[0].message.clone().unwrap_or_default();
 
+    let mut has_selected = false;
+
     let update_index_script: Vec<UpdateIndexCommand> = result
         .into_iter()
         .map(|file| -> eyre::Result<UpdateIndexCommand> {
@@ -369,6 +571,9 @@ fn record_interactive(
             };
 
             let (selected, _unselected) = file.get_selected_contents();
+            if !matches!(selected, SelectedContents::Unchanged) {
+                has_selected = true;
+            }
             let oid = match selected {
                 SelectedContents::Absent => MaybeZeroOid::Zero,
                 SelectedContents::Unchanged => {
@@ -378,6 +583,14 @@ fn record_interactive(
                     old_description: _,
                     new_description: _,
                 } => new_tree.get_oid_for_path(&file.path)?.unwrap(),
+                SelectedContents::Present { contents } if edit_selection => {
+                    let extension = match file.path.extension() {
+                        Some(extension) => format!(".{}", extension.to_string_lossy()),
+                        None => ".txt".to_string(),
+                    };
+                    let contents = edit_file_contents(git_run_info, repo, &contents, &extension)?;
+                    MaybeZeroOid::NonZero(repo.create_blob_from_contents(contents.as_bytes())?)
+                }
                 SelectedContents::Present { contents } => {
                     MaybeZeroOid::NonZero(repo.create_blob_from_contents(contents.as_bytes())?)
                 }
@@ -396,23 +609,7 @@ fn record_interactive(
             Ok(command)
         })
         .try_collect()?;
-    let index = repo.get_index()?;
-    update_index(
-        git_run_info,
-        repo,
-        &index,
-        event_tx_id,
-        &update_index_script,
-    )?;
-
-    let args = {
-        let mut args = vec!["commit"];
-        if !message.is_empty() {
-            args.extend(["--message", &message]);
-        }
-        args
-    };
-    git_run_info.run_direct_no_wrapping(Some(event_tx_id), &args)
+    Ok(Ok((message, update_index_script, has_selected)))
 }
 
 #[instrument]
@@ -547,7 +744,11 @@ To proceed anyways, run: git move -f -s 'siblings(.)",
         force_in_memory: true,
         force_on_disk: false,
         resolve_merge_conflicts: false,
+        reset_author: false,
+        committer_as_author: false,
+        signature_commit_oids: None,
         check_out_commit_options: Default::default(),
+        empty_commits: EmptyCommitsMode::Drop,
     };
     let result = execute_rebase_plan(
         effects,
@@ -0,0 +1,504 @@
+//! Split a commit into two commits.
+//!
+//! The changes of the commit are presented in the interactive change selector.
+//! The selected changes are committed as the first commit, and the remaining
+//! changes are committed on top of it as the second commit. Any descendants
+//! are then restacked onto the second commit.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use eyre::Context;
+use git_branchless_invoke::CommandContext;
+use git_branchless_opts::{MoveOptions, ResolveRevsetOptions, Revset, SplitArgs};
+use git_branchless_revset::resolve_commits;
+use itertools::Itertools;
+use lib::core::check_out::{check_out_commit, CheckOutCommitOptions, CheckoutTarget};
+use lib::core::config::get_restack_preserve_timestamps;
+use lib::core::dag::{union_all, CommitSet, Dag};
+use lib::core::effects::{Effects, OperationType};
+use lib::core::eventlog::{Event, EventLogDb, EventReplayer};
+use lib::core::formatting::Pluralize;
+use lib::core::gc::mark_commit_reachable;
+use lib::core::repo_ext::RepoExt;
+use lib::core::rewrite::rewrite_hooks::run_post_rewrite_user_hook;
+use lib::core::rewrite::{
+    execute_rebase_plan, move_branches, BuildRebasePlanOptions, EmptyCommitsMode,
+    ExecuteRebasePlanOptions, ExecuteRebasePlanResult, MergeConflictRemediation, RebasePlanBuilder,
+    RebasePlanPermissions, RepoResource,
+};
+use lib::git::{
+    hydrate_tree, process_diff_for_record, FileMode, GitRunInfo, MaybeZeroOid, NonZeroOid, Repo,
+    Signer, Tree,
+};
+use lib::try_exit_code;
+use lib::util::{ExitCode, EyreExitOr};
+use rayon::ThreadPoolBuilder;
+use scm_record::{Commit, RecordError, RecordState, Recorder, SelectedContents};
+use tracing::{instrument, warn};
+
+use crate::Input;
+
+/// Split a commit into two commits.
+#[instrument]
+pub fn command_main(ctx: CommandContext, args: SplitArgs) -> EyreExitOr<()> {
+    let CommandContext {
+        effects,
+        git_run_info,
+    } = ctx;
+    let SplitArgs {
+        revset,
+        resolve_revset_options,
+        move_options,
+    } = args;
+    split(
+        &effects,
+        &git_run_info,
+        revset,
+        &resolve_revset_options,
+        &move_options,
+    )
+}
+
+#[instrument]
+fn split(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    revset: Revset,
+    resolve_revset_options: &ResolveRevsetOptions,
+    move_options: &MoveOptions,
+) -> EyreExitOr<()> {
+    let now = SystemTime::now();
+    let timestamp = now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64();
+    let repo = Repo::from_current_dir()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let references_snapshot = repo.get_references_snapshot()?;
+    let mut dag = Dag::open_and_sync(
+        effects,
+        &repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+
+    let commit_set = match resolve_commits(
+        effects,
+        &repo,
+        &mut dag,
+        &[revset.clone()],
+        resolve_revset_options,
+    ) {
+        Ok(commit_sets) => union_all(&commit_sets),
+        Err(err) => {
+            err.describe(effects)?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+    let commit_oid = match dag.commit_set_to_vec(&commit_set)?.as_slice() {
+        [commit_oid] => *commit_oid,
+        commit_oids => {
+            writeln!(
+                effects.get_output_stream(),
+                "Expected exactly 1 commit to split, but '{}' evaluated to {}.",
+                revset,
+                commit_oids.len()
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+    let commit = repo.find_commit_or_fail(commit_oid)?;
+    let parent = match commit.get_only_parent() {
+        Some(parent) => parent,
+        None => {
+            writeln!(
+                effects.get_output_stream(),
+                "Cannot split {}, because it does not have exactly 1 parent.",
+                effects
+                    .get_glyphs()
+                    .render(commit.friendly_describe(effects.get_glyphs())?)?,
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+
+    let build_options = BuildRebasePlanOptions {
+        force_rewrite_public_commits: move_options.force_rewrite_public_commits,
+        dump_rebase_constraints: move_options.dump_rebase_constraints,
+        dump_rebase_plan: move_options.dump_rebase_plan,
+        detect_duplicate_commits_via_patch_id: move_options.detect_duplicate_commits_via_patch_id,
+    };
+    let commits_to_verify = dag.query_descendants(CommitSet::from(commit_oid))?;
+    let commits_to_verify = dag.filter_visible_commits(commits_to_verify)?;
+    if let Err(err) =
+        RebasePlanPermissions::verify_rewrite_set(&dag, build_options.clone(), &commits_to_verify)?
+    {
+        err.describe(effects, &repo, &dag)?;
+        return Ok(Err(ExitCode(1)));
+    };
+
+    let old_tree = parent.get_tree()?;
+    let new_tree = commit.get_tree()?;
+    let message = String::from_utf8_lossy(&commit.get_message_raw()).into_owned();
+    let (first_tree_oid, first_message, second_message) = match try_exit_code!(
+        select_changes_to_split(effects, git_run_info, &repo, &old_tree, &new_tree, &message)?
+    ) {
+        Some(result) => result,
+        None => {
+            writeln!(
+                effects.get_output_stream(),
+                "\
+Select some, but not all, of the changes to split the commit into two commits.
+Not splitting: {}",
+                effects
+                    .get_glyphs()
+                    .render(commit.friendly_describe(effects.get_glyphs())?)?,
+            )?;
+            return Ok(Ok(()));
+        }
+    };
+
+    let (author, committer) = (commit.get_author(), commit.get_committer());
+    let preserve_timestamps =
+        move_options.preserve_timestamps || get_restack_preserve_timestamps(&repo)?;
+    let committer = if preserve_timestamps {
+        committer
+    } else {
+        committer.update_timestamp(now)?
+    };
+
+    let first_tree = repo.find_tree_or_fail(first_tree_oid)?;
+    let signer = Signer::from_config(&repo)?;
+    let first_commit_oid = repo.create_commit_with_signer(
+        &author,
+        &committer,
+        &first_message,
+        &first_tree,
+        vec![&parent],
+        signer.as_ref(),
+    )?;
+    let first_commit = repo.find_commit_or_fail(first_commit_oid)?;
+    let second_commit_oid = repo.create_commit_with_signer(
+        &author,
+        &committer,
+        &second_message,
+        &new_tree,
+        vec![&first_commit],
+        signer.as_ref(),
+    )?;
+
+    let event_tx_id = event_log_db.make_transaction_id(now, "split")?;
+    {
+        mark_commit_reachable(&repo, first_commit_oid)
+            .wrap_err("Marking commit as reachable for GC purposes.")?;
+        mark_commit_reachable(&repo, second_commit_oid)
+            .wrap_err("Marking commit as reachable for GC purposes.")?;
+        event_log_db.add_events(vec![
+            Event::CommitEvent {
+                timestamp,
+                event_tx_id,
+                commit_oid: first_commit_oid,
+            },
+            Event::RewriteEvent {
+                timestamp,
+                event_tx_id,
+                old_commit_oid: MaybeZeroOid::NonZero(commit_oid),
+                new_commit_oid: MaybeZeroOid::NonZero(second_commit_oid),
+            },
+        ])?;
+        dag.sync_from_oids(
+            effects,
+            &repo,
+            CommitSet::empty(),
+            CommitSet::from(second_commit_oid),
+        )?;
+        let rewritten_oids = {
+            let mut result = HashMap::new();
+            result.insert(commit_oid, MaybeZeroOid::NonZero(second_commit_oid));
+            result
+        };
+        move_branches(effects, git_run_info, &repo, event_tx_id, &rewritten_oids)?;
+
+        let head_info = repo.get_head_info()?;
+        if head_info.oid == Some(commit_oid) {
+            let target = match &head_info.reference_name {
+                Some(name) => CheckoutTarget::Reference(name.clone()),
+                None => CheckoutTarget::Oid(second_commit_oid),
+            };
+            try_exit_code!(check_out_commit(
+                effects,
+                git_run_info,
+                &repo,
+                &event_log_db,
+                event_tx_id,
+                Some(target),
+                &CheckOutCommitOptions {
+                    additional_args: Default::default(),
+                    reset: true,
+                    render_smartlog: false,
+                },
+            )?);
+        }
+
+        run_post_rewrite_user_hook(
+            effects,
+            git_run_info,
+            &repo,
+            event_tx_id,
+            "amend",
+            &rewritten_oids,
+        )?;
+    }
+
+    writeln!(
+        effects.get_output_stream(),
+        "Split {} into: {}, {}",
+        effects
+            .get_glyphs()
+            .render(commit.friendly_describe_oid(effects.get_glyphs())?)?,
+        effects
+            .get_glyphs()
+            .render(first_commit.friendly_describe_oid(effects.get_glyphs())?)?,
+        effects.get_glyphs().render(
+            repo.find_commit_or_fail(second_commit_oid)?
+                .friendly_describe_oid(effects.get_glyphs())?
+        )?,
+    )?;
+
+    let rebase_plan = {
+        let children = dag.query_children(CommitSet::from(commit_oid))?;
+        let descendants = dag.query_descendants(children)?;
+        let descendants = dag.filter_visible_commits(descendants)?;
+        let permissions =
+            match RebasePlanPermissions::verify_rewrite_set(&dag, build_options, &descendants)? {
+                Ok(permissions) => permissions,
+                Err(err) => {
+                    err.describe(effects, &repo, &dag)?;
+                    return Ok(Err(ExitCode(1)));
+                }
+            };
+
+        let mut builder = RebasePlanBuilder::new(&dag, permissions);
+        for descendant_oid in dag.commit_set_to_vec(&descendants)? {
+            let descendant_commit = repo.find_commit_or_fail(descendant_oid)?;
+            let parent_oids = descendant_commit
+                .get_parent_oids()
+                .into_iter()
+                .map(|parent_oid| {
+                    if parent_oid == commit_oid {
+                        second_commit_oid
+                    } else {
+                        parent_oid
+                    }
+                })
+                .collect_vec();
+            builder.move_subtree(descendant_oid, parent_oids)?;
+        }
+
+        let thread_pool = ThreadPoolBuilder::new().build()?;
+        let repo_pool = RepoResource::new_pool(&repo)?;
+        match builder.build(effects, &thread_pool, &repo_pool)? {
+            Ok(rebase_plan) => rebase_plan,
+            Err(err) => {
+                err.describe(effects, &repo, &dag)?;
+                return Ok(Err(ExitCode(1)));
+            }
+        }
+    };
+
+    if let Some(rebase_plan) = rebase_plan {
+        let execute_options = ExecuteRebasePlanOptions {
+            now,
+            event_tx_id,
+            force_in_memory: move_options.force_in_memory,
+            force_on_disk: move_options.force_on_disk,
+            preserve_timestamps,
+            resolve_merge_conflicts: move_options.resolve_merge_conflicts,
+            reset_author: move_options.reset_author,
+            committer_as_author: move_options.committer_as_author,
+            signature_commit_oids: None,
+            check_out_commit_options: CheckOutCommitOptions {
+                additional_args: Default::default(),
+                reset: false,
+                render_smartlog: false,
+            },
+            empty_commits: EmptyCommitsMode::Drop,
+        };
+        match execute_rebase_plan(
+            effects,
+            git_run_info,
+            &repo,
+            &event_log_db,
+            &rebase_plan,
+            &execute_options,
+        )? {
+            ExecuteRebasePlanResult::Succeeded {
+                rewritten_oids: None,
+            } => {}
+
+            ExecuteRebasePlanResult::Succeeded {
+                rewritten_oids: Some(rewritten_oids),
+            } => {
+                writeln!(
+                    effects.get_output_stream(),
+                    "Restacked {}.",
+                    Pluralize {
+                        determiner: None,
+                        amount: rewritten_oids.len(),
+                        unit: ("commit", "commits")
+                    }
+                )?;
+            }
+
+            ExecuteRebasePlanResult::DeclinedToMerge { failed_merge_info } => {
+                failed_merge_info.describe(effects, &repo, MergeConflictRemediation::Restack)?;
+                writeln!(
+                    effects.get_output_stream(),
+                    "Splitting without restacking descendant commits."
+                )?;
+            }
+
+            ExecuteRebasePlanResult::Failed { exit_code } => {
+                return Ok(Err(exit_code));
+            }
+        }
+    }
+
+    Ok(Ok(()))
+}
+
+/// Interactively select which of the changes between `old_tree` and `new_tree`
+/// to keep in the first of two commits, and edit the messages of both commits.
+/// Returns the tree of the first commit and the messages of the two commits, or
+/// `None` if the selection didn't split the changes.
+pub(crate) fn select_changes_to_split(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    old_tree: &Tree,
+    new_tree: &Tree,
+    message: &str,
+) -> EyreExitOr<Option<(NonZeroOid, String, String)>> {
+    let files = {
+        let (effects, _progress) = effects.start_operation(OperationType::CalculateDiff);
+        let diff = repo.get_diff_between_trees(
+            &effects,
+            Some(old_tree),
+            new_tree,
+            // We manually add context to the git-record output, so suppress the context lines here.
+            0,
+        )?;
+        process_diff_for_record(repo, &diff)?
+    };
+    let record_state = RecordState {
+        is_read_only: false,
+        commits: vec![
+            Commit {
+                message: Some(message.to_owned()),
+            },
+            Commit {
+                message: Some(message.to_owned()),
+            },
+        ],
+        files,
+    };
+
+    let mut input = Input { git_run_info, repo };
+    let recorder = Recorder::new(record_state, &mut input);
+    let result = recorder.run();
+    let RecordState {
+        is_read_only: _,
+        commits,
+        files: result,
+    } = match result {
+        Ok(result) => result,
+        Err(RecordError::Cancelled) => {
+            println!("Aborted.");
+            return Ok(Err(ExitCode(1)));
+        }
+        Err(RecordError::Bug(message)) => {
+            println!("BUG: {message}");
+            println!("This is a bug. Please report it.");
+            return Ok(Err(ExitCode(1)));
+        }
+        Err(
+            err @ (RecordError::SetUpTerminal(_)
+            | RecordError::CleanUpTerminal(_)
+            | RecordError::ReadInput(_)
+            | RecordError::RenderFrame(_)
+            | RecordError::SerializeJson(_)
+            | RecordError::WriteFile(_)
+            | RecordError::Other(_)),
+        ) => {
+            println!("Error: {err}");
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+
+    let mut has_selected = false;
+    let mut has_unselected = false;
+    let mut entries: HashMap<PathBuf, Option<(NonZeroOid, FileMode)>> = HashMap::new();
+    for file in result {
+        let mode = {
+            let default_mode = FileMode::Blob;
+            match file.get_file_mode() {
+                None => {
+                    warn!(
+                        ?file,
+                        ?default_mode,
+                        "No file mode was set for file, using default"
+                    );
+                    default_mode
+                }
+                Some(mode) => match i32::try_from(mode) {
+                    Ok(mode) => FileMode::from(mode),
+                    Err(err) => {
+                        warn!(
+                            ?mode,
+                            ?default_mode,
+                            ?err,
+                            "File mode did not fit into i32, using default"
+                        );
+                        default_mode
+                    }
+                },
+            }
+        };
+
+        let (selected, unselected) = file.get_selected_contents();
+        if !matches!(selected, SelectedContents::Unchanged) {
+            has_selected = true;
+        }
+        if !matches!(unselected, SelectedContents::Unchanged) {
+            has_unselected = true;
+        }
+        let entry = match selected {
+            SelectedContents::Absent => None,
+            SelectedContents::Unchanged => old_tree
+                .get_path(&file.path)?
+                .map(|entry| (entry.get_oid(), entry.get_filemode())),
+            SelectedContents::Binary {
+                old_description: _,
+                new_description: _,
+            } => new_tree
+                .get_path(&file.path)?
+                .map(|entry| (entry.get_oid(), entry.get_filemode())),
+            SelectedContents::Present { contents } => {
+                Some((repo.create_blob_from_contents(contents.as_bytes())?, mode))
+            }
+        };
+        entries.insert(file.path.clone().into_owned(), entry);
+    }
+    if !has_selected || !has_unselected {
+        return Ok(Ok(None));
+    }
+
+    let first_tree_oid = hydrate_tree(repo, Some(old_tree), entries)?;
+    let first_message = commits[0].message.clone().unwrap_or_default();
+    let second_message = commits[1].message.clone().unwrap_or_default();
+    Ok(Ok(Some((first_tree_oid, first_message, second_message))))
+}
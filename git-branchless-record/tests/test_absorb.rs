@@ -0,0 +1,60 @@
+use lib::testing::make_git;
+
+#[test]
+fn test_absorb() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    git.write_file_txt("test1", "updated test1 contents\n")?;
+    git.write_file_txt("test2", "updated test2 contents\n")?;
+    git.write_file_txt("initial", "updated initial contents\n")?;
+
+    {
+        let (stdout, _stderr) = git.branchless("absorb", &["--dry-run"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Would absorb 1 change into: 62fc20d create test1.txt
+        Would absorb 1 change into: 96d1c37 create test2.txt
+        Left 1 change in the working copy.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("absorb", &[])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> reset 3a231eb7997c7cea54a3ee81fc47ebbb4e78eef7
+        Unstaged changes after reset:
+        M	initial.txt
+        Absorbed 1 change into: a090e14 create test1.txt
+        Absorbed 1 change into: 3a231eb create test2.txt
+        Left 1 change in the working copy.
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        o a090e14 create test1.txt
+        |
+        @ 3a231eb create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["diff", "--stat"])?;
+        insta::assert_snapshot!(stdout, @r###"
+         initial.txt | 2 +-
+         1 file changed, 1 insertion(+), 1 deletion(-)
+        "###);
+    }
+
+    Ok(())
+}
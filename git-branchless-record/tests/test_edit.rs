@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+
+use lib::testing::{make_git, GitRunOptions};
+
+#[test]
+fn test_edit_reorder_squash_drop() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.commit_file("test4", 4)?;
+
+    {
+        // Move `test1` to the end, squash `test3` into `test2`, and drop `test4`.
+        let (_stdout, _stderr) = git.branchless_with_options(
+            "edit",
+            &[],
+            &GitRunOptions {
+                env: HashMap::from([(
+                    "GIT_EDITOR".to_string(),
+                    r"sed -i -e '1{h;d}' -e 's/^pick\(.70deb1e\)/fixup\1/' -e 's/^pick\(.355e173\)/drop\1/' -e 4G".to_string(),
+                )]),
+                ..Default::default()
+            },
+        )?;
+    }
+
+    {
+        // `HEAD` was at the dropped commit, so it's moved to the commit
+        // preceding it in the plan.
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        @ 40918c4 create test2.txt
+        |
+        o 1715392 create test1.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["show", "--format=%s", "--name-only", "HEAD"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test2.txt
+
+        test2.txt
+        test3.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_edit_reword() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    {
+        let (_stdout, _stderr) = git.branchless_with_options(
+            "edit",
+            &[],
+            &GitRunOptions {
+                env: HashMap::from([(
+                    "GIT_EDITOR".to_string(),
+                    r"sed -i -e 's/^pick\(.62fc20d\)/reword\1/' -e 's/^create.test1.txt$/reworded/'"
+                        .to_string(),
+                )]),
+                ..Default::default()
+            },
+        )?;
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["log", "--format=%s", "-2"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test2.txt
+        reworded
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_edit_unchanged() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "edit",
+            &[],
+            &GitRunOptions {
+                env: HashMap::from([("GIT_EDITOR".to_string(), "cat".to_string())]),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        pick 62fc20d create test1.txt
+        pick 96d1c37 create test2.txt
+        #
+        # Edit 2 commits onto f777ecc create initial.txt.
+        #
+        # Commands:
+        # p, pick <commit> = use commit
+        # r, reword <commit> = use commit, but edit its message
+        # s, squash <commit> = combine commit into the previous commit, and edit the
+        #                      combined message
+        # f, fixup <commit> = combine commit into the previous commit, and discard its
+        #                     message
+        # split <commit> = use commit, but interactively split it into two commits
+        # d, drop <commit> = remove commit
+        #
+        # These lines can be re-ordered; they are executed from top to bottom. Every
+        # commit must remain listed exactly once. If you remove everything, the edit
+        # will be aborted.
+        Nothing to do.
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_edit_invalid() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    {
+        let (_stdout, stderr) = git.branchless_with_options(
+            "edit",
+            &[],
+            &GitRunOptions {
+                env: HashMap::from([(
+                    "GIT_EDITOR".to_string(),
+                    r"sed -i -e 's/^pick\(.62fc20d\)/squash\1/'".to_string(),
+                )]),
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @"Cannot squash commit on line 1 without a previous commit to combine it into: 62fc20d");
+    }
+
+    {
+        let (_stdout, stderr) = git.branchless_with_options(
+            "edit",
+            &[],
+            &GitRunOptions {
+                env: HashMap::from([("GIT_EDITOR".to_string(), "sed -i -e 2d".to_string())]),
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @"Expected all 2 commits to be listed, but only 1 listed");
+    }
+
+    Ok(())
+}
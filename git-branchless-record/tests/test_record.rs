@@ -1,4 +1,5 @@
-use lib::testing::pty::{run_in_pty, PtyAction, DOWN_ARROW};
+use lib::git::GitVersion;
+use lib::testing::pty::{run_in_pty, run_in_pty_with_env, PtyAction, DOWN_ARROW};
 use lib::testing::{make_git, GitInitOptions, GitRunOptions};
 
 #[test]
@@ -127,6 +128,65 @@ fn test_record_unstaged_changes_interactive() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_record_interactive_edit_selection() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    git.commit_file("test1", 1)?;
+    git.write_file_txt("test1", "contents1 extra\n")?;
+    {
+        let exit_status = run_in_pty_with_env(
+            &git,
+            "record",
+            &["-i", "--edit-selection", "-m", "foo"],
+            &[("GIT_EDITOR", "sed -i 's/ extra//'")],
+            &[
+                PtyAction::Write("f"), // expand files
+                PtyAction::WaitUntilContains("contents1"),
+                PtyAction::Write(" "),
+                PtyAction::WaitUntilContains("(●)"),
+                PtyAction::Write("c"),
+            ],
+        )?;
+        assert!(exit_status.success());
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["show", "--pretty=format:%s"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        foo
+        diff --git a/test1.txt b/test1.txt
+        index 7432a8f..a024003 100644
+        --- a/test1.txt
+        +++ b/test1.txt
+        @@ -1 +1 @@
+        -test1 contents
+        +contents1
+        "###);
+    }
+
+    {
+        // The edit should only have applied to the commit, not the working copy.
+        let (stdout, _stderr) = git.run(&["diff"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        diff --git a/test1.txt b/test1.txt
+        index a024003..41d3689 100644
+        --- a/test1.txt
+        +++ b/test1.txt
+        @@ -1 +1 @@
+        -contents1
+        +contents1 extra
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_record_staged_changes() -> eyre::Result<()> {
     let git = make_git()?;
@@ -372,6 +432,51 @@ fn test_record_create_branch() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_record_branch_alias() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.commit_file("test1", 1)?;
+    git.write_file_txt("test1", "new contents\n")?;
+    git.branchless("record", &["-b", "foo", "-m", "Update"])?;
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d (master) create test1.txt
+        |
+        @ 836023f (> foo) Update
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_record_amend_no_draft_commits() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.write_file_txt("initial", "new contents\n")?;
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "record",
+            &["-i", "--amend"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        There are no draft commits in the current stack to amend changes into.
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_record_insert() -> eyre::Result<()> {
     let git = make_git()?;
@@ -741,3 +846,86 @@ fn test_record_interactive_commit_message_template() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_record_allow_empty() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    git.commit_file("test1", 1)?;
+    {
+        let (stdout, _stderr) = git.branchless("record", &["-m", "WIP marker"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        There are no changes to tracked files in the working copy to commit.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("record", &["--allow-empty", "-m", "WIP marker"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        [master f1ab5ed] WIP marker
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        @ f1ab5ed (> master) (empty) WIP marker
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_record_trailers() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    // `git commit --trailer` was added in Git v2.32.
+    if git.get_version()? < GitVersion(2, 32, 0) {
+        return Ok(());
+    }
+    git.init_repo()?;
+    git.run(&[
+        "config",
+        "branchless.commit.trailer",
+        "Reviewed-by: Foo <foo@example.com>",
+    ])?;
+    git.run(&["config", "branchless.commit.branchTrailer", "Issue"])?;
+    git.run(&["config", "branchless.commit.signoff", "true"])?;
+
+    git.commit_file("test1", 1)?;
+    git.write_file_txt("test1", "contents1\n")?;
+    git.branchless("record", &["-c", "feature/ABC-123-fix", "-m", "foo"])?;
+    {
+        let (stdout, _stderr) = git.run(&["log", "-n", "1", "--format=%B"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        foo
+
+        Reviewed-by: Foo <foo@example.com>
+        Issue: ABC-123
+        Signed-off-by: Testy McTestface <test@example.com>
+
+        "###);
+    }
+
+    git.write_file_txt("test1", "contents2\n")?;
+    git.branchless("record", &["--no-trailers", "-m", "bar"])?;
+    {
+        let (stdout, _stderr) = git.run(&["log", "-n", "1", "--format=%B"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        bar
+
+        "###);
+    }
+
+    Ok(())
+}
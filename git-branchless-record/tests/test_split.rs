@@ -0,0 +1,81 @@
+use lib::testing::pty::{run_in_pty, PtyAction};
+use lib::testing::{make_git, GitRunOptions};
+
+#[test]
+fn test_split_interactive() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+    git.detach_head()?;
+
+    git.write_file_txt("test1", "contents1\n")?;
+    git.write_file_txt("test2", "contents2\n")?;
+    git.run(&["add", "."])?;
+    git.run(&["commit", "-m", "create test1.txt and test2.txt"])?;
+    git.commit_file("test3", 3)?;
+
+    {
+        run_in_pty(
+            &git,
+            "split",
+            &["HEAD^"],
+            &[
+                PtyAction::WaitUntilContains("create test1.txt and test2.txt"),
+                PtyAction::Write("f"), // expand files
+                PtyAction::WaitUntilContains("contents1"),
+                PtyAction::Write(" "),
+                PtyAction::WaitUntilContains("(●)"),
+                PtyAction::Write("c"),
+            ],
+        )?;
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["log", "--format=%s", "--name-only", "-3"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test3.txt
+
+        test3.txt
+        create test1.txt and test2.txt
+
+        test2.txt
+        create test1.txt and test2.txt
+
+        test1.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_split_multiple_commits() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "split",
+            &["draft()"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Expected exactly 1 commit to split, but 'draft()' evaluated to 2.
+        "###);
+    }
+
+    Ok(())
+}
@@ -46,4 +46,21 @@ impl<'input> Expr<'input> {
             }
         }
     }
+
+    /// Get the number of parameters referenced by this expression when used
+    /// as an alias template, i.e. the largest `N` for which `$N` appears in
+    /// the expression.
+    pub fn get_num_parameters(&self) -> usize {
+        match self {
+            Expr::Name(name) => name
+                .strip_prefix('$')
+                .and_then(|index| index.parse().ok())
+                .unwrap_or_default(),
+            Expr::FunctionCall(_name, args) => args
+                .iter()
+                .map(|arg| arg.get_num_parameters())
+                .max()
+                .unwrap_or_default(),
+        }
+    }
 }
@@ -1,13 +1,16 @@
 use bstr::ByteSlice;
 use eden_dag::nameset::hints::Hints;
 
-use lib::core::dag::CommitSet;
+use lib::core::change_id::{ChangeId, ChangeIdDb};
+use lib::core::dag::{CommitSet, CommitVertex};
 use lib::core::eventlog::{EventLogDb, EventReplayer};
+use lib::core::path_index::PathIndexDb;
 use lib::core::repo_ext::RepoExt;
 use lib::core::rewrite::find_rewrite_target;
 use lib::git::{
-    get_latest_test_command_path, get_test_tree_dir, CategorizedReferenceName, Commit,
-    MaybeZeroOid, Repo, SerializedNonZeroOid, SerializedTestResult, TEST_ABORT_EXIT_CODE,
+    get_latest_test_command_path, get_test_tree_dir, CategorizedReferenceName,
+    CherryPickFastOptions, Commit, CreateCommitFastError, GitRunInfo, GitRunOpts, MaybeZeroOid,
+    Repo, SerializedNonZeroOid, SerializedTestResult, TEST_ABORT_EXIT_CODE,
     TEST_INDETERMINATE_EXIT_CODE, TEST_SUCCESS_EXIT_CODE,
 };
 use std::borrow::Cow;
@@ -18,13 +21,19 @@ use eyre::Context as EyreContext;
 use lazy_static::lazy_static;
 
 use crate::eval::{
-    eval0, eval0_or_1, eval0_or_1_pattern, eval1, eval1_pattern, eval2, eval_number_rhs, Context,
-    EvalError, EvalResult,
+    eval0, eval0_or_1, eval0_or_1_glob_pattern, eval0_or_1_pattern, eval1, eval1_date_pattern,
+    eval1_glob_pattern, eval1_or_2_regex_pattern, eval1_pattern, eval2, eval_number_rhs,
+    eval_text_rhs, Context, EvalError, EvalResult,
 };
 use crate::pattern::{make_pattern_matcher_set, Pattern};
 use crate::pattern::{PatternError, PatternMatcher};
 use crate::Expr;
 
+/// Functions whose results keep the order of their first argument, or which
+/// (in the case of `sort`) choose the order themselves.
+pub(super) const ORDERED_FUNCTIONS: &[&str] =
+    &["sort", "limit", "union", "intersection", "difference"];
+
 type FnType = &'static (dyn Fn(&mut Context, &str, &[Expr]) -> EvalResult + Sync);
 lazy_static! {
     pub(super) static ref FUNCTIONS: HashMap<&'static str, FnType> = {
@@ -40,10 +49,16 @@ lazy_static! {
             ("ancestors", &fn_ancestors),
             ("ancestors.nth", &fn_nthancestor),
             ("descendants", &fn_descendants),
+            ("nearest.ancestor", &fn_nearest_ancestor),
+            ("nearest.descendant", &fn_nearest_descendant),
             ("parents", &fn_parents),
             ("parents.nth", &fn_parents_nth),
             ("children", &fn_children),
             ("siblings", &fn_siblings),
+            ("forks", &fn_forks),
+            ("submitted", &fn_submitted),
+            ("unsubmitted", &fn_unsubmitted),
+            ("needsresubmit", &fn_needs_resubmit),
             ("roots", &fn_roots),
             ("heads", &fn_heads),
             ("branches", &fn_branches),
@@ -51,7 +66,9 @@ lazy_static! {
             ("public", &fn_public),
             ("draft", &fn_draft),
             ("stack", &fn_stack),
+            ("changeid", &fn_change_id),
             ("message", &fn_message),
+            ("message.regex", &fn_message_regex),
             ("paths.changed", &fn_path_changed),
             ("author.name", &fn_author_name),
             ("author.email", &fn_author_email),
@@ -60,11 +77,18 @@ lazy_static! {
             ("committer.email", &fn_committer_email),
             ("committer.date", &fn_committer_date),
             ("exactly", &fn_exactly),
+            ("exactly.paths", &fn_exactly_paths),
             ("current", &fn_current),
             ("merges", &fn_merges),
+            ("signed", &fn_signed),
+            ("verified", &fn_verified),
+            ("conflictswith", &fn_conflicts_with),
+            ("sort", &fn_sort),
+            ("limit", &fn_limit),
             ("tests.passed", &fn_tests_passed),
             ("tests.failed", &fn_tests_failed),
             ("tests.fixable", &fn_tests_fixable),
+            ("tests.untested", &fn_tests_untested),
         ];
         functions.iter().cloned().collect()
     };
@@ -144,6 +168,42 @@ fn fn_descendants(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
     Ok(ctx.dag.query_descendants(expr)?)
 }
 
+/// For each commit in the first set, find the closest ancestors (including
+/// the commit itself) which are in the second set.
+#[instrument]
+fn fn_nearest_ancestor(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
+    let (commits, predicate) = eval2(ctx, name, args)?;
+    let mut result = CommitSet::empty();
+    for commit_oid in ctx
+        .dag
+        .commit_set_to_vec(&commits)
+        .map_err(EvalError::OtherError)?
+    {
+        let ancestors = ctx.dag.query_ancestors(commit_oid.into())?;
+        let nearest = ctx.dag.query_heads(ancestors.intersection(&predicate))?;
+        result = result.union(&nearest);
+    }
+    Ok(result)
+}
+
+/// For each commit in the first set, find the closest descendants (including
+/// the commit itself) which are in the second set.
+#[instrument]
+fn fn_nearest_descendant(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
+    let (commits, predicate) = eval2(ctx, name, args)?;
+    let mut result = CommitSet::empty();
+    for commit_oid in ctx
+        .dag
+        .commit_set_to_vec(&commits)
+        .map_err(EvalError::OtherError)?
+    {
+        let descendants = ctx.dag.query_descendants(commit_oid.into())?;
+        let nearest = ctx.dag.query_roots(descendants.intersection(&predicate))?;
+        result = result.union(&nearest);
+    }
+    Ok(result)
+}
+
 #[instrument]
 fn fn_parents(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
     let expr = eval1(ctx, name, args)?;
@@ -165,6 +225,41 @@ fn fn_siblings(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
     Ok(siblings)
 }
 
+/// Find the commits with more than one draft child, i.e. the points at which
+/// the draft commit graph branches.
+#[instrument]
+fn fn_forks(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
+    eval0(ctx, name, args)?;
+    let draft_commits = ctx
+        .dag
+        .query_draft_commits()
+        .map_err(EvalError::OtherError)?
+        .clone();
+    let mut num_draft_children: HashMap<CommitVertex, usize> = HashMap::new();
+    for commit_oid in ctx
+        .dag
+        .commit_set_to_vec(&draft_commits)
+        .map_err(EvalError::OtherError)?
+    {
+        for parent in ctx.dag.query_parent_names(commit_oid)? {
+            *num_draft_children.entry(parent).or_default() += 1;
+        }
+    }
+    let forks = num_draft_children
+        .into_iter()
+        .filter_map(
+            |(vertex, num_children)| {
+                if num_children > 1 {
+                    Some(vertex)
+                } else {
+                    None
+                }
+            },
+        )
+        .collect::<Vec<_>>();
+    Ok(CommitSet::from_static_names(forks))
+}
+
 #[instrument]
 fn fn_roots(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
     let expr = eval1(ctx, name, args)?;
@@ -179,7 +274,7 @@ fn fn_heads(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
 
 #[instrument]
 fn fn_branches(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
-    let pattern = match eval0_or_1_pattern(ctx, name, args)? {
+    let pattern = match eval0_or_1_glob_pattern(ctx, name, args)? {
         Some(pattern) => pattern,
         None => return Ok(ctx.dag.branch_commits.clone()),
     };
@@ -341,6 +436,21 @@ fn make_pattern_matcher_for_set(
 #[instrument]
 fn fn_message(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
     let pattern = eval1_pattern(ctx, name, args)?;
+    make_message_matcher(ctx, name, args, pattern)
+}
+
+#[instrument]
+fn fn_message_regex(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
+    let pattern = eval1_or_2_regex_pattern(ctx, name, args)?;
+    make_message_matcher(ctx, name, args, pattern)
+}
+
+fn make_message_matcher(
+    ctx: &mut Context,
+    name: &str,
+    args: &[Expr],
+    pattern: Pattern,
+) -> EvalResult {
     make_pattern_matcher(
         ctx,
         name,
@@ -366,15 +476,31 @@ fn fn_message(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
 
 #[instrument]
 fn fn_path_changed(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
-    let pattern = eval1_pattern(ctx, name, args)?;
+    let pattern = eval1_glob_pattern(ctx, name, args)?;
+    make_path_matcher(ctx, name, args, pattern)
+}
+
+#[instrument]
+fn fn_exactly_paths(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
+    // Unprefixed patterns must match the entire path, rather than any
+    // substring of it.
+    let pattern = match eval1_glob_pattern(ctx, name, args)? {
+        Pattern::Substring(path) => Pattern::Exact(path),
+        pattern => pattern,
+    };
+    make_path_matcher(ctx, name, args, pattern)
+}
+
+fn make_path_matcher(ctx: &mut Context, name: &str, args: &[Expr], pattern: Pattern) -> EvalResult {
+    let path_index = PathIndexDb::new(ctx.repo.get_db_conn()?).map_err(EvalError::OtherError)?;
     make_pattern_matcher(
         ctx,
         name,
         args,
         Box::new(move |repo: &Repo, commit: &Commit| {
-            let touched_paths = repo
-                .get_paths_touched_by_commit(commit)
-                .map_err(PatternError::Repo)?;
+            let touched_paths = path_index
+                .get_paths_touched_by_commit(repo, commit)
+                .map_err(PatternError::PathIndex)?;
             let result = touched_paths.into_iter().any(|path| {
                 let path = match path.to_str() {
                     Some(path) => path,
@@ -428,7 +554,7 @@ fn fn_author_email(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
 
 #[instrument]
 fn fn_author_date(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
-    let pattern = eval1_pattern(ctx, name, args)?;
+    let pattern = eval1_date_pattern(ctx, name, args)?;
     make_pattern_matcher(
         ctx,
         name,
@@ -474,7 +600,7 @@ fn fn_committer_email(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResul
 
 #[instrument]
 fn fn_committer_date(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
-    let pattern = eval1_pattern(ctx, name, args)?;
+    let pattern = eval1_date_pattern(ctx, name, args)?;
     make_pattern_matcher(
         ctx,
         name,
@@ -502,6 +628,58 @@ fn fn_exactly(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
     }
 }
 
+#[instrument]
+fn fn_change_id(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
+    let prefix: &str = match args {
+        [Expr::Name(prefix)] => prefix,
+        [Expr::FunctionCall(name, _args)] => {
+            return Err(EvalError::ExpectedPatternNotFunction {
+                function_name: name.clone().into_owned(),
+            })
+        }
+        args => {
+            return Err(EvalError::ArityMismatch {
+                function_name: name.to_string(),
+                expected_arities: vec![1],
+                actual_arity: args.len(),
+            })
+        }
+    };
+
+    let conn = ctx.repo.get_db_conn()?;
+    let change_id_db = ChangeIdDb::new(&conn).map_err(EvalError::OtherError)?;
+    let mut commit_oids = change_id_db
+        .get_recorded_commits_with_prefix(prefix)
+        .map_err(EvalError::OtherError)?;
+
+    // Commits which weren't produced by a rewrite aren't recorded in the
+    // database, but their change IDs are derived from their OIDs, so look up
+    // the commits with the corresponding OID prefix instead.
+    if let Some(oid_prefix) = ChangeId::to_oid_prefix(prefix) {
+        let commits = ctx
+            .dag
+            .query_commits_with_hex_prefix(&oid_prefix)
+            .map_err(EvalError::OtherError)?;
+        for commit_oid in ctx
+            .dag
+            .commit_set_to_vec(&commits)
+            .map_err(EvalError::OtherError)?
+        {
+            let change_id = change_id_db
+                .get_change_id(commit_oid)
+                .map_err(EvalError::OtherError)?;
+            if change_id.as_str().starts_with(prefix) {
+                commit_oids.push(commit_oid);
+            }
+        }
+    }
+
+    let commits: CommitSet = commit_oids.into_iter().collect();
+    ctx.dag
+        .filter_visible_commits(commits)
+        .map_err(EvalError::OtherError)
+}
+
 #[instrument]
 fn fn_current(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
     let mut dag = ctx
@@ -512,6 +690,7 @@ fn fn_current(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
         effects: ctx.effects,
         repo: ctx.repo,
         dag: &mut dag,
+        is_ordered: false,
     };
     let expr = eval1(&mut ctx, name, args)?;
 
@@ -560,6 +739,237 @@ fn fn_merges(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
     )
 }
 
+#[instrument]
+fn fn_signed(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
+    eval0(ctx, name, args)?;
+    make_pattern_matcher(
+        ctx,
+        name,
+        args,
+        Box::new(|_repo, commit| Ok(commit.is_signed())),
+    )
+}
+
+#[instrument]
+fn fn_verified(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
+    eval0(ctx, name, args)?;
+    let git_run_info = GitRunInfo::from_env().map_err(EvalError::OtherError)?;
+    make_pattern_matcher(
+        ctx,
+        name,
+        args,
+        Box::new(move |repo, commit| {
+            if !commit.is_signed() {
+                return Ok(false);
+            }
+            // Defer to Git so that `gpg.program`, `gpg.format`,
+            // `gpg.ssh.allowedSignersFile`, etc. are respected.
+            let commit_oid = commit.get_oid().to_string();
+            let result = git_run_info
+                .run_silent(
+                    repo,
+                    None,
+                    &["verify-commit", commit_oid.as_str()],
+                    GitRunOpts {
+                        treat_git_failure_as_error: false,
+                        stdin: None,
+                    },
+                )
+                .map_err(PatternError::VerifySignature)?;
+            Ok(result.exit_code.is_success())
+        }),
+    )
+}
+
+#[instrument]
+fn fn_conflicts_with(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
+    let targets = eval1(ctx, name, args)?;
+    let target_oids = ctx
+        .dag
+        .commit_set_to_vec(&targets)
+        .map_err(EvalError::OtherError)?;
+    let target_ancestors = ctx.dag.query_ancestors(targets)?;
+    let draft_commits = ctx
+        .dag
+        .query_draft_commits()
+        .map_err(EvalError::OtherError)?;
+    let commits_to_match = draft_commits.difference(&target_ancestors);
+
+    // Each commit is applied individually onto each target commit, so a
+    // commit is only reported if its own changes conflict with the target,
+    // rather than changes introduced by its ancestors in the same stack.
+    make_pattern_matcher_for_set(
+        ctx,
+        name,
+        args,
+        Box::new(move |repo, commit| {
+            if commit.get_parent_count() != 1 {
+                return Ok(false);
+            }
+            for target_oid in target_oids.iter().copied() {
+                let target_commit = repo
+                    .find_commit_or_fail(target_oid)
+                    .map_err(PatternError::Repo)?;
+                let result = repo.cherry_pick_fast(
+                    commit,
+                    &target_commit,
+                    &CherryPickFastOptions {
+                        reuse_parent_tree_if_possible: false,
+                    },
+                );
+                match result {
+                    Ok(_) => {}
+                    Err(CreateCommitFastError::MergeConflict {
+                        conflicting_paths: _,
+                    }) => return Ok(true),
+                    Err(err) => return Err(PatternError::ConstructMatcher(err.into())),
+                }
+            }
+            Ok(false)
+        }),
+        Some(commits_to_match),
+    )
+}
+
+const SORT_KEYS: &[&str] = &["author.date", "committer.date", "topological"];
+
+#[instrument]
+fn fn_sort(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
+    let (commits, key) = eval_text_rhs(ctx, name, args)?;
+    let (key, reverse) = match key.strip_prefix('-') {
+        Some(key) => (key, true),
+        None => (key.as_str(), false),
+    };
+
+    let mut commit_oids = match key {
+        "topological" => ctx.dag.sort(&commits).map_err(EvalError::OtherError)?,
+        "author.date" | "committer.date" => {
+            let commit_oids = ctx
+                .dag
+                .commit_set_to_vec(&commits)
+                .map_err(EvalError::OtherError)?;
+            let mut keyed_oids = Vec::new();
+            for commit_oid in commit_oids {
+                let commit = ctx.repo.find_commit_or_fail(commit_oid)?;
+                let time = if key == "author.date" {
+                    commit.get_author().get_time()
+                } else {
+                    commit.get_committer().get_time()
+                };
+                keyed_oids.push((time.to_date_time(), commit_oid));
+            }
+            keyed_oids.sort();
+            keyed_oids
+                .into_iter()
+                .map(|(_time, commit_oid)| commit_oid)
+                .collect()
+        }
+        key => {
+            return Err(EvalError::UnknownSortKey {
+                key: key.to_owned(),
+                available_keys: SORT_KEYS.to_vec(),
+            })
+        }
+    };
+    if reverse {
+        commit_oids.reverse();
+    }
+    ctx.is_ordered = true;
+    Ok(commit_oids.into_iter().collect())
+}
+
+#[instrument]
+fn fn_limit(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
+    let (commits, limit) = eval_number_rhs(ctx, name, args)?;
+    let commit_oids = ctx
+        .dag
+        .commit_set_to_vec(&commits)
+        .map_err(EvalError::OtherError)?;
+    Ok(commit_oids.into_iter().take(limit).collect())
+}
+
+/// The state of a draft commit, with respect to what was last pushed to the
+/// upstream branches of the local branches pointing to it.
+#[derive(Debug, PartialEq, Eq)]
+enum SubmitState {
+    /// No local branch with an upstream branch points to the commit.
+    Unsubmitted,
+
+    /// The upstream branch points to a commit with the same tree and message.
+    Submitted,
+
+    /// The upstream branch points to a commit with a different tree or
+    /// message.
+    NeedsResubmit,
+}
+
+fn eval_submit_state(ctx: &mut Context, state: SubmitState) -> EvalResult {
+    let mut commit_oids = Vec::new();
+    let mut pushed_commit_oids = Vec::new();
+    for branch in ctx.repo.get_all_local_branches()? {
+        let local_oid = match branch.get_oid()? {
+            Some(local_oid) => local_oid,
+            None => continue,
+        };
+        let upstream_oid = match branch.get_upstream_branch()? {
+            Some(upstream_branch) => upstream_branch.get_oid()?,
+            None => None,
+        };
+        let upstream_oid = match upstream_oid {
+            Some(upstream_oid) => upstream_oid,
+            None => continue,
+        };
+        pushed_commit_oids.push(local_oid);
+
+        let local_commit = ctx.repo.find_commit_or_fail(local_oid)?;
+        let upstream_commit = ctx.repo.find_commit_or_fail(upstream_oid)?;
+        let branch_state = if local_commit.get_tree_oid() == upstream_commit.get_tree_oid()
+            && local_commit.get_message_raw() == upstream_commit.get_message_raw()
+        {
+            SubmitState::Submitted
+        } else {
+            SubmitState::NeedsResubmit
+        };
+        if branch_state == state {
+            commit_oids.push(local_oid);
+        }
+    }
+
+    let draft_commits = ctx
+        .dag
+        .query_draft_commits()
+        .map_err(EvalError::OtherError)?;
+    match state {
+        SubmitState::Unsubmitted => {
+            // Commits without any branch haven't been submitted either.
+            let pushed_commits: CommitSet = pushed_commit_oids.into_iter().collect();
+            Ok(draft_commits.difference(&pushed_commits))
+        }
+        SubmitState::Submitted | SubmitState::NeedsResubmit => {
+            let commits: CommitSet = commit_oids.into_iter().collect();
+            Ok(commits.intersection(draft_commits))
+        }
+    }
+}
+
+#[instrument]
+fn fn_submitted(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
+    eval0(ctx, name, args)?;
+    eval_submit_state(ctx, SubmitState::Submitted)
+}
+
+#[instrument]
+fn fn_unsubmitted(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
+    eval0(ctx, name, args)?;
+    eval_submit_state(ctx, SubmitState::Unsubmitted)
+}
+
+#[instrument]
+fn fn_needs_resubmit(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
+    eval0(ctx, name, args)?;
+    eval_submit_state(ctx, SubmitState::NeedsResubmit)
+}
+
 fn read_all_test_results(repo: &Repo, commit: &Commit) -> Option<Vec<SerializedTestResult>> {
     let commit_test_dir = get_test_tree_dir(repo, commit).ok()?;
     let mut all_results = Vec::new();
@@ -685,3 +1095,31 @@ fn fn_tests_fixable(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult
         }),
     )
 }
+
+#[instrument]
+fn fn_tests_untested(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
+    let pattern = eval_test_command_pattern(ctx, name, args)?;
+    make_pattern_matcher(
+        ctx,
+        name,
+        args,
+        Box::new(move |repo: &Repo, commit: &Commit| {
+            let has_result = read_all_test_results(repo, commit)
+                .unwrap_or_default()
+                .into_iter()
+                .any(|test_result| {
+                    let SerializedTestResult {
+                        command,
+                        exit_code,
+                        head_commit_oid: _,
+                        snapshot_tree_oid: _,
+                        interactive: _,
+                    } = test_result;
+                    exit_code != TEST_INDETERMINATE_EXIT_CODE
+                        && exit_code != TEST_ABORT_EXIT_CODE
+                        && pattern.matches_text(&command.to_string())
+                });
+            Ok(!has_result)
+        }),
+    )
+}
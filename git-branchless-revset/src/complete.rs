@@ -0,0 +1,43 @@
+//! Completion of partially typed revset expressions, for use by shell
+//! completion scripts.
+
+use itertools::Itertools;
+use lib::git::{ConfigRead, Repo};
+
+use crate::builtins::FUNCTIONS;
+
+const ALIAS_PREFIX: &str = "branchless.revsets.alias.";
+
+/// Characters which can't appear in a function name, and so separate the word
+/// being completed from the rest of the expression.
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || "(),|&+%:^~'\"".contains(c)
+}
+
+/// Get the completions for the last word of the given partially typed revset
+/// expression. Each completion is the entire expression with the last word
+/// replaced by the name of a builtin function or configured alias, followed by
+/// an opening parenthesis.
+pub fn complete_revset(repo: &Repo, expr: &str) -> eyre::Result<Vec<String>> {
+    let word_start = match expr.rfind(is_separator) {
+        Some(index) => index + 1,
+        None => 0,
+    };
+    let (prefix, word) = expr.split_at(word_start);
+
+    let aliases = repo
+        .get_readonly_config()?
+        .list(format!("{ALIAS_PREFIX}*"))?
+        .into_iter()
+        .filter_map(|(key, _value)| key.strip_prefix(ALIAS_PREFIX).map(|name| name.to_owned()));
+    let completions = FUNCTIONS
+        .keys()
+        .map(|name| name.to_string())
+        .chain(aliases)
+        .filter(|name| name.starts_with(word))
+        .sorted()
+        .dedup()
+        .map(|name| format!("{prefix}{name}("))
+        .collect();
+    Ok(completions)
+}
@@ -13,7 +13,7 @@ use lib::core::formatting::Pluralize;
 use lib::git::{ConfigRead, Repo, RepoError, ResolvedReferenceInfo};
 use tracing::instrument;
 
-use super::builtins::FUNCTIONS;
+use super::builtins::{FUNCTIONS, ORDERED_FUNCTIONS};
 use super::parser::{parse, ParseError};
 use super::pattern::{Pattern, PatternError};
 use super::Expr;
@@ -23,6 +23,10 @@ pub(super) struct Context<'a> {
     pub effects: &'a Effects,
     pub repo: &'a Repo,
     pub dag: &'a mut Dag,
+
+    /// Whether the most recently evaluated set is in an order chosen by the
+    /// user with `sort()`, rather than in topological order.
+    pub is_ordered: bool,
 }
 
 #[derive(Debug, Error)]
@@ -78,6 +82,15 @@ pub enum EvalError {
     #[error("expected a text-matching pattern, but got a call to function: {function_name}")]
     ExpectedPatternNotFunction { function_name: String },
 
+    #[error(
+        "unknown sort key '{key}'; expected one of: {}",
+        available_keys.join(", "),
+    )]
+    UnknownSortKey {
+        key: String,
+        available_keys: Vec<&'static str>,
+    },
+
     #[error("there was no latest command run with `git test`; try running `git test` first")]
     NoLatestTestCommand,
 
@@ -108,6 +121,20 @@ pub type EvalResult = Result<CommitSet, EvalError>;
 /// Evaluate the provided revset expression.
 #[instrument]
 pub fn eval(effects: &Effects, repo: &Repo, dag: &mut Dag, expr: &Expr) -> EvalResult {
+    let (commits, _is_ordered) = eval_ordered(effects, repo, dag, expr)?;
+    Ok(commits)
+}
+
+/// Evaluate the provided revset expression, and also return whether the
+/// resulting commits are in an order chosen by the user with `sort()`. If so,
+/// they should be displayed in that order rather than topologically.
+#[instrument]
+pub fn eval_ordered(
+    effects: &Effects,
+    repo: &Repo,
+    dag: &mut Dag,
+    expr: &Expr,
+) -> Result<(CommitSet, bool), EvalError> {
     let (effects, _progress) =
         effects.start_operation(OperationType::EvaluateRevset(Arc::new(expr.to_string())));
 
@@ -115,15 +142,20 @@ pub fn eval(effects: &Effects, repo: &Repo, dag: &mut Dag, expr: &Expr) -> EvalR
         effects: &effects,
         repo,
         dag,
+        is_ordered: false,
     };
     let commits = eval_inner(&mut ctx, expr)?;
-    Ok(commits)
+    Ok((commits, ctx.is_ordered))
 }
 
 #[instrument]
 fn eval_inner(ctx: &mut Context, expr: &Expr) -> EvalResult {
     match expr {
-        Expr::Name(name) => eval_name(ctx, name),
+        Expr::Name(name) => {
+            // Aliases set the ordering of their own expansion.
+            ctx.is_ordered = false;
+            eval_name(ctx, name)
+        }
         Expr::FunctionCall(name, args) => {
             let result = eval_fn(ctx, name, args)?;
             let result = ctx
@@ -158,9 +190,20 @@ pub(super) fn eval_name(ctx: &mut Context, name: &str) -> EvalResult {
             commit_set
         }
         Ok(None) | Err(_) => {
+            // A bare name which doesn't refer to a commit may refer to an
+            // alias taking no arguments, so that e.g. `mine` can be used
+            // instead of `mine()`.
+            let is_alias_name = name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if is_alias_name {
+                if let Some(commits) = eval_alias(ctx, name, &[])? {
+                    return Ok(commits);
+                }
+            }
             return Err(EvalError::UnboundName {
                 name: name.to_owned(),
-            })
+            });
         }
     };
 
@@ -178,9 +221,31 @@ pub(super) fn eval_name(ctx: &mut Context, name: &str) -> EvalResult {
 #[instrument]
 pub(super) fn eval_fn(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
     if let Some(function) = FUNCTIONS.get(name) {
-        return function(ctx, name, args);
+        let commits = function(ctx, name, args)?;
+        if !ORDERED_FUNCTIONS.contains(&name) {
+            ctx.is_ordered = false;
+        }
+        return Ok(commits);
+    }
+
+    if let Some(commits) = eval_alias(ctx, name, args)? {
+        return Ok(commits);
     }
 
+    Err(EvalError::UnboundFunction {
+        name: name.to_owned(),
+        available_names: FUNCTIONS.keys().sorted().copied().collect(),
+    })
+}
+
+/// Evaluate the alias with the given name configured in
+/// `branchless.revsets.alias.<name>`, if any.
+#[instrument]
+fn eval_alias(
+    ctx: &mut Context,
+    name: &str,
+    args: &[Expr],
+) -> Result<Option<CommitSet>, EvalError> {
     let alias_key = format!("branchless.revsets.alias.{name}");
     let alias_template: Option<String> = ctx
         .repo
@@ -193,6 +258,14 @@ pub(super) fn eval_fn(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResul
             alias: alias_template.clone(),
             source: err,
         })?;
+        let num_parameters = alias_expr.get_num_parameters();
+        if args.len() != num_parameters {
+            return Err(EvalError::ArityMismatch {
+                function_name: name.to_string(),
+                expected_arities: vec![num_parameters],
+                actual_arity: args.len(),
+            });
+        }
         let arg_map: HashMap<String, Expr> = args
             .iter()
             .enumerate()
@@ -200,13 +273,9 @@ pub(super) fn eval_fn(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResul
             .collect();
         let alias_expr = alias_expr.replace_names(&arg_map);
         let commits = eval_inner(ctx, &alias_expr)?;
-        return Ok(commits);
+        return Ok(Some(commits));
     }
-
-    Err(EvalError::UnboundFunction {
-        name: name.to_owned(),
-        available_names: FUNCTIONS.keys().sorted().copied().collect(),
-    })
+    Ok(None)
 }
 
 #[instrument]
@@ -279,14 +348,13 @@ pub(super) fn eval0_or_1_pattern(
     }
 }
 
-#[instrument]
-pub(super) fn eval1_pattern(
-    _ctx: &mut Context,
+fn eval1_pattern_with(
     function_name: &str,
     args: &[Expr],
+    make_pattern: impl Fn(&str) -> Result<Pattern, PatternError>,
 ) -> Result<Pattern, EvalError> {
     match args {
-        [Expr::Name(pattern)] => Ok(Pattern::new(pattern)?),
+        [Expr::Name(pattern)] => Ok(make_pattern(pattern)?),
 
         [Expr::FunctionCall(name, _args)] => Err(EvalError::ExpectedPatternNotFunction {
             function_name: name.clone().into_owned(),
@@ -300,6 +368,74 @@ pub(super) fn eval1_pattern(
     }
 }
 
+#[instrument]
+pub(super) fn eval0_or_1_glob_pattern(
+    ctx: &mut Context,
+    function_name: &str,
+    args: &[Expr],
+) -> Result<Option<Pattern>, EvalError> {
+    match args {
+        [] => Ok(None),
+        [_] => eval1_glob_pattern(ctx, function_name, args).map(Some),
+        args => Err(EvalError::ArityMismatch {
+            function_name: function_name.to_string(),
+            expected_arities: vec![0, 1],
+            actual_arity: args.len(),
+        }),
+    }
+}
+
+#[instrument]
+pub(super) fn eval1_pattern(
+    _ctx: &mut Context,
+    function_name: &str,
+    args: &[Expr],
+) -> Result<Pattern, EvalError> {
+    eval1_pattern_with(function_name, args, Pattern::new)
+}
+
+#[instrument]
+pub(super) fn eval1_glob_pattern(
+    _ctx: &mut Context,
+    function_name: &str,
+    args: &[Expr],
+) -> Result<Pattern, EvalError> {
+    eval1_pattern_with(function_name, args, Pattern::new_implicit_glob)
+}
+
+#[instrument]
+pub(super) fn eval1_date_pattern(
+    _ctx: &mut Context,
+    function_name: &str,
+    args: &[Expr],
+) -> Result<Pattern, EvalError> {
+    eval1_pattern_with(function_name, args, Pattern::new_date)
+}
+
+#[instrument]
+pub(super) fn eval1_or_2_regex_pattern(
+    _ctx: &mut Context,
+    function_name: &str,
+    args: &[Expr],
+) -> Result<Pattern, EvalError> {
+    match args {
+        [Expr::Name(pattern)] => Ok(Pattern::new_regex(pattern, "")?),
+        [Expr::Name(pattern), Expr::Name(flags)] => Ok(Pattern::new_regex(pattern, flags)?),
+
+        [Expr::FunctionCall(name, _args), ..] | [_, Expr::FunctionCall(name, _args)] => {
+            Err(EvalError::ExpectedPatternNotFunction {
+                function_name: name.clone().into_owned(),
+            })
+        }
+
+        args => Err(EvalError::ArityMismatch {
+            function_name: function_name.to_string(),
+            expected_arities: vec![1, 2],
+            actual_arity: args.len(),
+        }),
+    }
+}
+
 #[instrument]
 pub(super) fn eval2(
     ctx: &mut Context,
@@ -309,7 +445,10 @@ pub(super) fn eval2(
     match args {
         [lhs, rhs] => {
             let lhs = eval_inner(ctx, lhs)?;
+            let is_ordered = ctx.is_ordered;
             let rhs = eval_inner(ctx, rhs)?;
+            // Set operations keep the order of their left-hand side.
+            ctx.is_ordered = is_ordered;
             Ok((lhs, rhs))
         }
 
@@ -346,6 +485,28 @@ pub(super) fn eval_number_rhs(
     }
 }
 
+/// Evaluate the first argument as a set, and return the second argument
+/// unevaluated, as text.
+#[instrument]
+pub(super) fn eval_text_rhs(
+    ctx: &mut Context,
+    function_name: &str,
+    args: &[Expr],
+) -> Result<(CommitSet, String), EvalError> {
+    match args {
+        [lhs, rhs] => {
+            let lhs = eval_inner(ctx, lhs)?;
+            Ok((lhs, rhs.to_string()))
+        }
+
+        args => Err(EvalError::ArityMismatch {
+            function_name: function_name.to_string(),
+            expected_arities: vec![2],
+            actual_arity: args.len(),
+        }),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::borrow::Cow;
@@ -790,6 +951,59 @@ mod tests {
             "###);
         }
 
+        {
+            let expr = Expr::FunctionCall(
+                Cow::Borrowed("message.regex"),
+                vec![Expr::Name(Cow::Borrowed(r"^CREATE TEST[4]\.txt$"))],
+            );
+            insta::assert_debug_snapshot!(eval_and_sort(&effects, &repo, &mut dag, &expr), @r###"
+            Ok(
+                [],
+            )
+            "###);
+        }
+
+        {
+            let expr = Expr::FunctionCall(
+                Cow::Borrowed("message.regex"),
+                vec![
+                    Expr::Name(Cow::Borrowed(r"^CREATE TEST[4]\.txt$")),
+                    Expr::Name(Cow::Borrowed("i")),
+                ],
+            );
+            insta::assert_debug_snapshot!(eval_and_sort(&effects, &repo, &mut dag, &expr), @r###"
+            Ok(
+                [
+                    Commit {
+                        inner: Commit {
+                            id: bf0d52a607f693201512a43b6b5a70b2a275e0ad,
+                            summary: "create test4.txt",
+                        },
+                    },
+                ],
+            )
+            "###);
+        }
+
+        {
+            let expr = Expr::FunctionCall(
+                Cow::Borrowed("message.regex"),
+                vec![
+                    Expr::Name(Cow::Borrowed("test4")),
+                    Expr::Name(Cow::Borrowed("q")),
+                ],
+            );
+            insta::assert_debug_snapshot!(eval_and_sort(&effects, &repo, &mut dag, &expr), @r###"
+            Err(
+                PatternError(
+                    ConstructMatcher(
+                        "unknown regex flag: q",
+                    ),
+                ),
+            )
+            "###);
+        }
+
         {
             let expr = Expr::FunctionCall(
                 Cow::Borrowed("paths.changed"),
@@ -821,6 +1035,62 @@ mod tests {
             "###);
         }
 
+        {
+            let expr = Expr::FunctionCall(
+                Cow::Borrowed("paths.changed"),
+                vec![Expr::Name(Cow::Borrowed("test[2-3].*"))],
+            );
+            insta::assert_debug_snapshot!(eval_and_sort(&effects, &repo, &mut dag, &expr), @r###"
+            Ok(
+                [
+                    Commit {
+                        inner: Commit {
+                            id: 96d1c37a3d4363611c49f7e52186e189a04c531f,
+                            summary: "create test2.txt",
+                        },
+                    },
+                    Commit {
+                        inner: Commit {
+                            id: 70deb1e28791d8e7dd5a1f0c871a51b91282562f,
+                            summary: "create test3.txt",
+                        },
+                    },
+                ],
+            )
+            "###);
+        }
+
+        {
+            let expr = Expr::FunctionCall(
+                Cow::Borrowed("exactly.paths"),
+                vec![Expr::Name(Cow::Borrowed("test1"))],
+            );
+            insta::assert_debug_snapshot!(eval_and_sort(&effects, &repo, &mut dag, &expr), @r###"
+            Ok(
+                [],
+            )
+            "###);
+        }
+
+        {
+            let expr = Expr::FunctionCall(
+                Cow::Borrowed("exactly.paths"),
+                vec![Expr::Name(Cow::Borrowed("test1.txt"))],
+            );
+            insta::assert_debug_snapshot!(eval_and_sort(&effects, &repo, &mut dag, &expr), @r###"
+            Ok(
+                [
+                    Commit {
+                        inner: Commit {
+                            id: 62fc20d2a290daea0d52bdc2ed2ad4be6491010e,
+                            summary: "create test1.txt",
+                        },
+                    },
+                ],
+            )
+            "###);
+        }
+
         {
             let expr = Expr::FunctionCall(
                 Cow::Borrowed("exactly"),
@@ -1018,6 +1288,80 @@ mod tests {
             "###);
         }
 
+        {
+            let expr = Expr::FunctionCall(
+                Cow::Borrowed("author.date"),
+                vec![Expr::Name(Cow::Borrowed("2020-10-01..2020-11-01"))],
+            );
+            insta::assert_debug_snapshot!(eval_and_sort(&effects, &repo, &mut dag, &expr), @r###"
+            Ok(
+                [
+                    Commit {
+                        inner: Commit {
+                            id: f777ecc9b0db5ed372b2615695191a8a17f79f24,
+                            summary: "create initial.txt",
+                        },
+                    },
+                    Commit {
+                        inner: Commit {
+                            id: 9ee1994c0737c221efc07acd8d73590d336ee46d,
+                            summary: "test1",
+                        },
+                    },
+                    Commit {
+                        inner: Commit {
+                            id: 05ff2fc6b3e7917ac6800b18077c211e173e8fb4,
+                            summary: "test2",
+                        },
+                    },
+                ],
+            )
+            "###);
+        }
+
+        {
+            let expr = Expr::FunctionCall(
+                Cow::Borrowed("author.date"),
+                vec![Expr::Name(Cow::Borrowed("1 week ago.."))],
+            );
+            insta::assert_debug_snapshot!(eval_and_sort(&effects, &repo, &mut dag, &expr), @r###"
+            Ok(
+                [],
+            )
+            "###);
+        }
+
+        {
+            let expr = Expr::FunctionCall(
+                Cow::Borrowed("committer.date"),
+                vec![Expr::Name(Cow::Borrowed("..today"))],
+            );
+            insta::assert_debug_snapshot!(eval_and_sort(&effects, &repo, &mut dag, &expr), @r###"
+            Ok(
+                [
+                    Commit {
+                        inner: Commit {
+                            id: f777ecc9b0db5ed372b2615695191a8a17f79f24,
+                            summary: "create initial.txt",
+                        },
+                    },
+                    Commit {
+                        inner: Commit {
+                            id: 9ee1994c0737c221efc07acd8d73590d336ee46d,
+                            summary: "test1",
+                        },
+                    },
+                    Commit {
+                        inner: Commit {
+                            id: 05ff2fc6b3e7917ac6800b18077c211e173e8fb4,
+                            summary: "test2",
+                        },
+                    },
+                ],
+            )
+            "###);
+        }
+
         {
             let expr = Expr::FunctionCall(
                 Cow::Borrowed("committer.name"),
@@ -1417,6 +1761,28 @@ mod tests {
             "###);
         }
 
+        {
+            git.run(&[
+                "config",
+                "branchless.revsets.alias.stackRoot",
+                "roots(stack())",
+            ])?;
+
+            let expr = Expr::Name(Cow::Borrowed("stackRoot"));
+            insta::assert_debug_snapshot!(eval_and_sort(&effects, &repo, &mut dag, &expr), @r###"
+            Ok(
+                [
+                    Commit {
+                        inner: Commit {
+                            id: 62fc20d2a290daea0d52bdc2ed2ad4be6491010e,
+                            summary: "create test1.txt",
+                        },
+                    },
+                ],
+            )
+            "###);
+        }
+
         {
             git.run(&["config", "branchless.revsets.alias.parseError", "foo("])?;
 
@@ -1467,8 +1833,62 @@ mod tests {
             );
             insta::assert_debug_snapshot!(eval_and_sort(&effects, &repo, &mut dag, &expr), @r###"
             Err(
-                UnboundName {
-                    name: "$2",
+                ArityMismatch {
+                    function_name: "outerAlias",
+                    expected_arities: [
+                        1,
+                    ],
+                    actual_arity: 2,
+                },
+            )
+            "###);
+        }
+
+        {
+            git.run(&[
+                "config",
+                "branchless.revsets.alias.stackof",
+                "descendants(roots(draft() & ::$1))",
+            ])?;
+
+            let expr = Expr::FunctionCall(
+                Cow::Borrowed("stackof"),
+                vec![Expr::Name(Cow::Borrowed("HEAD"))],
+            );
+            insta::assert_debug_snapshot!(eval_and_sort(&effects, &repo, &mut dag, &expr), @r###"
+            Ok(
+                [
+                    Commit {
+                        inner: Commit {
+                            id: 62fc20d2a290daea0d52bdc2ed2ad4be6491010e,
+                            summary: "create test1.txt",
+                        },
+                    },
+                    Commit {
+                        inner: Commit {
+                            id: 96d1c37a3d4363611c49f7e52186e189a04c531f,
+                            summary: "create test2.txt",
+                        },
+                    },
+                    Commit {
+                        inner: Commit {
+                            id: 70deb1e28791d8e7dd5a1f0c871a51b91282562f,
+                            summary: "create test3.txt",
+                        },
+                    },
+                ],
+            )
+            "###);
+
+            let expr = Expr::FunctionCall(Cow::Borrowed("stackof"), vec![]);
+            insta::assert_debug_snapshot!(eval_and_sort(&effects, &repo, &mut dag, &expr), @r###"
+            Err(
+                ArityMismatch {
+                    function_name: "stackof",
+                    expected_arities: [
+                        1,
+                    ],
+                    actual_arity: 0,
                 },
             )
             "###);
@@ -12,15 +12,20 @@
 
 mod ast;
 mod builtins;
+mod complete;
 mod eval;
 mod parser;
 mod pattern;
 mod resolve;
 
 pub use ast::Expr;
+pub use complete::complete_revset;
 pub use eval::eval;
 pub use parser::parse;
-pub use resolve::{check_revset_syntax, resolve_commits, resolve_default_smartlog_commits};
+pub use resolve::{
+    check_revset_syntax, is_parameterized_revset, resolve_commits,
+    resolve_default_smartlog_commits, resolve_ordered_commits, resolve_parameterized_commits,
+};
 
 use lalrpop_util::lalrpop_mod;
 lalrpop_mod!(
@@ -11,7 +11,7 @@ use lib::core::effects::{Effects, OperationType};
 use lib::core::rewrite::RepoResource;
 use lib::git::{Commit, NonZeroOid, Repo, RepoError, Time};
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
-use regex::Regex;
+use regex::{Regex, RegexBuilder};
 use thiserror::Error;
 
 use crate::eval::make_dag_backend_error;
@@ -24,6 +24,7 @@ pub(super) enum Pattern {
     Regex(regex::Regex),
     Before(DateTime<Local>),
     After(DateTime<Local>),
+    Between(DateTime<Local>, DateTime<Local>),
 }
 
 #[derive(Debug, Error)]
@@ -40,6 +41,12 @@ pub enum PatternError {
     #[error("failed to query repo: {0}")]
     Repo(#[source] RepoError),
 
+    #[error("failed to query path index: {0}")]
+    PathIndex(#[source] eyre::Error),
+
+    #[error("failed to verify commit signature: {0}")]
+    VerifySignature(#[source] eyre::Error),
+
     #[error("failed to construct matcher object: {0}")]
     ConstructMatcher(#[source] eyre::Error),
 
@@ -55,7 +62,7 @@ impl Pattern {
             Pattern::Substring(pattern) => subject.contains(pattern),
             Pattern::Glob(pattern) => pattern.matches(subject),
             Pattern::Regex(pattern) => pattern.is_match(subject),
-            Pattern::Before(_) | Pattern::After(_) => false,
+            Pattern::Before(_) | Pattern::After(_) | Pattern::Between(_, _) => false,
         }
     }
 
@@ -72,6 +79,10 @@ impl Pattern {
                 Some(time) => &time >= date,
                 None => false,
             },
+            Pattern::Between(start, end) => match time.to_date_time() {
+                Some(time) => &time >= start && &time <= end,
+                None => false,
+            },
         }
     }
 
@@ -94,24 +105,6 @@ impl Pattern {
             return Ok(Pattern::Regex(pattern));
         }
 
-        fn parse_date(pattern: &str) -> Result<DateTime<Local>, PatternError> {
-            if let Ok(date) = parse_date_string(pattern, Local::now(), Dialect::Us) {
-                return Ok(date.with_timezone(&Local));
-            }
-            if let Ok(interval) = parse_duration(pattern) {
-                let delta = match interval {
-                    Interval::Seconds(seconds) => RelativeDuration::seconds(seconds.into()),
-                    Interval::Days(days) => RelativeDuration::days(days.into()),
-                    Interval::Months(months) => RelativeDuration::months(months),
-                };
-                let date = Local::now() + delta;
-                return Ok(date);
-            }
-            Err(PatternError::ConstructMatcher(eyre::eyre!(
-                "cannot parse date: {pattern}"
-            )))
-        }
-
         if let Some(pattern) = pattern.strip_prefix("before:") {
             let date = parse_date(pattern)?;
             return Ok(Pattern::Before(date));
@@ -123,6 +116,82 @@ impl Pattern {
 
         Ok(Pattern::Substring(pattern.to_owned()))
     }
+
+    /// Compile `pattern` as a regular expression, applying the given flags.
+    /// Each character of `flags` enables one option: `i` (case-insensitive),
+    /// `m` (multi-line), `s` (`.` matches newlines), or `x` (ignore
+    /// whitespace).
+    pub fn new_regex(pattern: &str, flags: &str) -> Result<Self, PatternError> {
+        let mut builder = RegexBuilder::new(pattern);
+        for flag in flags.chars() {
+            match flag {
+                'i' => builder.case_insensitive(true),
+                'm' => builder.multi_line(true),
+                's' => builder.dot_matches_new_line(true),
+                'x' => builder.ignore_whitespace(true),
+                flag => {
+                    return Err(PatternError::ConstructMatcher(eyre::eyre!(
+                        "unknown regex flag: {flag}"
+                    )))
+                }
+            };
+        }
+        let pattern = builder.build()?;
+        Ok(Pattern::Regex(pattern))
+    }
+
+    /// Like `Pattern::new`, but also accepts date ranges of the form
+    /// `since..until`, where either endpoint may be omitted.
+    pub fn new_date(pattern: &str) -> Result<Self, PatternError> {
+        if pattern.starts_with("before:") || pattern.starts_with("after:") {
+            return Pattern::new(pattern);
+        }
+        match pattern.split_once("..") {
+            None => Pattern::new(pattern),
+            Some((since, until)) => match (since.trim(), until.trim()) {
+                ("", "") => Err(PatternError::ConstructMatcher(eyre::eyre!(
+                    "date range must have a start or an end: {pattern}"
+                ))),
+                (since, "") => Ok(Pattern::After(parse_date(since)?)),
+                ("", until) => Ok(Pattern::Before(parse_date(until)?)),
+                (since, until) => Ok(Pattern::Between(parse_date(since)?, parse_date(until)?)),
+            },
+        }
+    }
+
+    /// Like `Pattern::new`, but a pattern without an explicit prefix is
+    /// interpreted as a glob if it contains any glob metacharacters, so that
+    /// paths like `src/**/*.rs` or branch names like `release/*` can be
+    /// matched directly.
+    pub fn new_implicit_glob(pattern: &str) -> Result<Self, PatternError> {
+        match Pattern::new(pattern)? {
+            Pattern::Substring(substring)
+                if substring == pattern && pattern.contains(['*', '?', '[']) =>
+            {
+                let pattern = glob::Pattern::new(pattern)?;
+                Ok(Pattern::Glob(pattern))
+            }
+            pattern => Ok(pattern),
+        }
+    }
+}
+
+fn parse_date(pattern: &str) -> Result<DateTime<Local>, PatternError> {
+    if let Ok(date) = parse_date_string(pattern, Local::now(), Dialect::Us) {
+        return Ok(date.with_timezone(&Local));
+    }
+    if let Ok(interval) = parse_duration(pattern) {
+        let delta = match interval {
+            Interval::Seconds(seconds) => RelativeDuration::seconds(seconds.into()),
+            Interval::Days(days) => RelativeDuration::days(days.into()),
+            Interval::Months(months) => RelativeDuration::months(months),
+        };
+        let date = Local::now() + delta;
+        return Ok(date);
+    }
+    Err(PatternError::ConstructMatcher(eyre::eyre!(
+        "cannot parse date: {pattern}"
+    )))
 }
 
 pub(super) trait PatternMatcher: Sync + Send {
@@ -1,3 +1,5 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fmt::Write;
 
 use eyre::WrapErr;
@@ -5,10 +7,11 @@ use git_branchless_opts::{ResolveRevsetOptions, Revset};
 use lib::core::config::get_smartlog_default_revset;
 use lib::core::dag::{CommitSet, Dag};
 use lib::core::effects::Effects;
-use lib::git::Repo;
+use lib::git::{NonZeroOid, Repo};
 use thiserror::Error;
 use tracing::instrument;
 
+use crate::eval::eval_ordered;
 use crate::eval::EvalError;
 use crate::parser::ParseError;
 use crate::Expr;
@@ -116,6 +119,98 @@ pub fn resolve_commits(
     Ok(commit_sets)
 }
 
+/// Resolve a single revset, and also return whether the resulting commits are
+/// in an order chosen by the user with `sort()`, such as through an alias or
+/// as the left-hand side of a set operation.
+#[instrument]
+pub fn resolve_ordered_commits(
+    effects: &Effects,
+    repo: &Repo,
+    dag: &mut Dag,
+    revset: &Revset,
+    options: &ResolveRevsetOptions,
+) -> Result<(CommitSet, bool), ResolveError> {
+    let mut dag_with_obsolete = if options.show_hidden_commits {
+        Some(
+            dag.clear_obsolete_commits(repo)
+                .map_err(|err| ResolveError::OtherError { source: err })?,
+        )
+    } else {
+        None
+    };
+    let dag = dag_with_obsolete.as_mut().unwrap_or(dag);
+
+    let Revset(revset) = revset;
+    if let Ok(Some(commit)) = repo.revparse_single_commit(revset) {
+        let commit_set = CommitSet::from(commit.get_oid());
+        dag.sync_from_oids(effects, repo, CommitSet::empty(), commit_set.clone())
+            .map_err(|err| ResolveError::OtherError { source: err })?;
+        return Ok((commit_set, false));
+    }
+
+    let expr = parse(revset).map_err(|err| ResolveError::ParseError {
+        expr: revset.clone(),
+        source: err,
+    })?;
+    eval_ordered(effects, repo, dag, &expr).map_err(|err| ResolveError::EvalError {
+        expr: revset.clone(),
+        source: err,
+    })
+}
+
+/// Whether the given revset refers to the parameter `$1`, in which case it
+/// should be resolved with [`resolve_parameterized_commits`].
+pub fn is_parameterized_revset(revset: &Revset) -> bool {
+    let Revset(revset) = revset;
+    match parse(revset) {
+        Ok(expr) => expr.get_num_parameters() > 0,
+        Err(_) => false,
+    }
+}
+
+/// Resolve the given revset once for each of `parameter_oids`, substituting
+/// each OID for `$1` in the expression, as in revset alias templates.
+#[instrument]
+pub fn resolve_parameterized_commits(
+    effects: &Effects,
+    repo: &Repo,
+    dag: &mut Dag,
+    revset: &Revset,
+    parameter_oids: &[NonZeroOid],
+    options: &ResolveRevsetOptions,
+) -> Result<Vec<CommitSet>, ResolveError> {
+    let mut dag_with_obsolete = if options.show_hidden_commits {
+        Some(
+            dag.clear_obsolete_commits(repo)
+                .map_err(|err| ResolveError::OtherError { source: err })?,
+        )
+    } else {
+        None
+    };
+    let dag = dag_with_obsolete.as_mut().unwrap_or(dag);
+
+    let Revset(revset) = revset;
+    let expr = parse(revset).map_err(|err| ResolveError::ParseError {
+        expr: revset.clone(),
+        source: err,
+    })?;
+    let mut commit_sets = Vec::new();
+    for parameter_oid in parameter_oids {
+        let arg_map: HashMap<String, Expr> = HashMap::from([(
+            "$1".to_string(),
+            Expr::Name(Cow::Owned(parameter_oid.to_string())),
+        )]);
+        let commits = eval(effects, repo, dag, &expr.replace_names(&arg_map)).map_err(|err| {
+            ResolveError::EvalError {
+                expr: revset.clone(),
+                source: err,
+            }
+        })?;
+        commit_sets.push(commits);
+    }
+    Ok(commit_sets)
+}
+
 /// Resolve the set of commits that would appear in the smartlog by default (if
 /// the user doesn't specify a revset).
 pub fn resolve_default_smartlog_commits(
@@ -29,7 +29,8 @@ use eyre::Context;
 use tracing::{instrument, warn};
 
 use lib::core::config::{
-    get_comment_char, get_commit_template, get_editor, get_restack_preserve_timestamps,
+    get_comment_char, get_commit_template, get_commit_trailers, get_editor,
+    get_restack_preserve_timestamps,
 };
 use lib::core::dag::{sorted_commit_set, union_all, CommitSet, Dag};
 use lib::core::effects::Effects;
@@ -37,10 +38,12 @@ use lib::core::eventlog::{EventLogDb, EventReplayer};
 use lib::core::formatting::{Glyphs, Pluralize};
 use lib::core::node_descriptors::{render_node_descriptors, CommitOidDescriptor, NodeObject};
 use lib::core::rewrite::{
-    execute_rebase_plan, BuildRebasePlanOptions, ExecuteRebasePlanOptions, ExecuteRebasePlanResult,
-    RebasePlanBuilder, RebasePlanPermissions, RepoResource,
+    execute_rebase_plan, BuildRebasePlanOptions, EmptyCommitsMode, ExecuteRebasePlanOptions,
+    ExecuteRebasePlanResult, RebasePlanBuilder, RebasePlanPermissions, RepoResource,
+};
+use lib::git::{
+    message_add_trailers, message_prettify, Commit, GitRunInfo, MaybeZeroOid, NonZeroOid, Repo,
 };
-use lib::git::{message_prettify, Commit, GitRunInfo, MaybeZeroOid, NonZeroOid, Repo};
 
 use git_branchless_opts::{ResolveRevsetOptions, Revset};
 use git_branchless_revset::resolve_commits;
@@ -61,6 +64,30 @@ pub enum InitialCommitMessages {
 /// Open the user's configured commit editor seeded with the provided message.
 #[instrument]
 pub fn edit_message(git_run_info: &GitRunInfo, repo: &Repo, message: &str) -> eyre::Result<String> {
+    run_editor(git_run_info, repo, message, |_editor| {})
+}
+
+/// Open the user's configured editor seeded with the provided file contents.
+/// Unlike [`edit_message`], trailing newlines are preserved, and the temporary
+/// file is given the provided extension (such as `.rs`).
+#[instrument]
+pub fn edit_file_contents(
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    contents: &str,
+    extension: &str,
+) -> eyre::Result<String> {
+    run_editor(git_run_info, repo, contents, |editor| {
+        editor.trim_newlines(false).extension(extension);
+    })
+}
+
+fn run_editor(
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    text: &str,
+    configure: impl FnOnce(&mut Editor),
+) -> eyre::Result<String> {
     let (mut editor, editor_program) = match get_editor(git_run_info, repo)? {
         Some(editor_program) => {
             let mut editor = Editor::new();
@@ -71,11 +98,12 @@ pub fn edit_message(git_run_info: &GitRunInfo, repo: &Repo, message: &str) -> ey
     };
     if editor_program == ":" {
         // Special case in Git: treat `:` as a no-op editor.
-        return Ok(message.to_string());
+        return Ok(text.to_string());
     }
+    configure(&mut editor);
     let result = editor
         .require_save(false)
-        .edit(message)
+        .edit(text)
         .with_context(|| format!("Invoking editor: '{}'", editor_program.to_string_lossy()))?
         .expect("`Editor::edit` should not return `None` when `require_save` is `false`");
     Ok(result)
@@ -90,6 +118,9 @@ pub fn reword(
     messages: InitialCommitMessages,
     git_run_info: &GitRunInfo,
     force_rewrite_public_commits: bool,
+    no_trailers: bool,
+    reset_author: bool,
+    committer_as_author: bool,
 ) -> EyreExitOr<()> {
     let repo = Repo::from_current_dir()?;
     let references_snapshot = repo.get_references_snapshot()?;
@@ -252,6 +283,19 @@ pub fn reword(
             return Ok(Err(ExitCode(1)));
         }
     };
+    let messages = if no_trailers {
+        messages
+    } else {
+        let branch_name = repo
+            .get_head_info()?
+            .get_branch_name()?
+            .map(|branch_name| branch_name.to_owned());
+        let trailers = get_commit_trailers(&repo, branch_name.as_deref())?;
+        messages
+            .into_iter()
+            .map(|(oid, message)| (oid, message_add_trailers(&message, &trailers)))
+            .collect()
+    };
 
     let rebase_plan = {
         let pool = ThreadPoolBuilder::new().build()?;
@@ -290,11 +334,16 @@ pub fn reword(
         force_in_memory: true,
         force_on_disk: false,
         resolve_merge_conflicts: false,
+        reset_author,
+        committer_as_author,
+        // Descendants which are restacked keep their original authors.
+        signature_commit_oids: Some(commits.iter().map(|commit| commit.get_oid()).collect()),
         check_out_commit_options: CheckOutCommitOptions {
             additional_args: Default::default(),
             reset: false,
             render_smartlog: false,
         },
+        empty_commits: EmptyCommitsMode::Drop,
     };
     let result = execute_rebase_plan(
         effects,
@@ -464,6 +513,17 @@ fn prepare_messages(
         message.push_str(msg.as_str());
     }
 
+    if commits.len() > 1 {
+        message.push_str(
+            format!(
+                "\
+                    {comment_char} Each message follows the '++ reword <commit>' line for its commit.\n\
+                    {comment_char} Keep these lines so that the messages can be applied to the right\n\
+                    {comment_char} commits. All commits are reworded at once after saving.\n"
+            )
+            .as_str(),
+        );
+    }
     message.push_str(
         format!(
             "\
@@ -590,11 +650,22 @@ fn parse_bulk_edit_message(
         _ => message,
     };
 
-    // split the bulk message into (hash, msg) tuples
-    let msgs = message
-        .split("++ reword")
-        .filter_map(|msg| msg.split_once('\n'))
-        .map(|(hash, msg)| (hash.trim(), msg));
+    // Split the bulk message into (hash, msg) tuples. Only lines starting with
+    // the marker begin a new message, so that messages can mention it.
+    let mut msgs: Vec<(&str, String)> = Vec::new();
+    for line in message.split_inclusive('\n') {
+        match line.strip_prefix("++ reword") {
+            Some(header) => {
+                let hash = header.split_whitespace().next().unwrap_or_default();
+                msgs.push((hash, String::new()));
+            }
+            None => match msgs.last_mut() {
+                Some((_hash, msg)) => msg.push_str(line),
+                None if line.trim().is_empty() => {}
+                None => msgs.push((line.trim(), String::new())),
+            },
+        }
+    }
 
     let mut duplicates = Vec::new();
     let mut messages = HashMap::new();
@@ -611,7 +682,7 @@ fn parse_bulk_edit_message(
             duplicates.push(hash.to_string());
             continue;
         }
-        messages.insert(oid, message_prettify(msg, Some(comment_char))?);
+        messages.insert(oid, message_prettify(&msg, Some(comment_char))?);
     }
 
     Ok(ParseMessageResult {
@@ -796,6 +867,9 @@ This is a template!
                     ++ reword 96d1c37
                     create test2.txt
 
+                    # Each message follows the '++ reword <commit>' line for its commit.
+                    # Keep these lines so that the messages can be applied to the right
+                    # commits. All commits are reworded at once after saving.
                     # Rewording: Please enter the commit messages to apply to these 2 commits.
                     # Lines starting with '#' will be ignored, and an empty message aborts
                     # rewording.
@@ -856,6 +930,46 @@ This is a template!
         Ok(())
     }
 
+    #[test]
+    fn test_reword_parses_markers_only_at_line_start() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let repo = git.get_repo()?;
+
+        let test1_oid = git.commit_file("test1", 1)?;
+        let test2_oid = git.commit_file("test2", 2)?;
+        let test1_commit = repo.find_commit_or_fail(test1_oid)?;
+        let test2_commit = repo.find_commit_or_fail(test2_oid)?;
+
+        {
+            let result = parse_bulk_edit_message(
+                String::from(
+                    "++ reword 62fc20d create test1.txt\n\
+                update test1.txt\n\
+                \n\
+                Mention a ++ reword marker in the body.\n\
+                \n\
+                ++ reword 96d1c37\n\
+                update test2.txt\n",
+                ),
+                &[test1_commit.clone(), test2_commit.clone()],
+                '#',
+            )?;
+
+            let messages: BTreeMap<_, _> = result.messages.iter().collect();
+            insta::assert_debug_snapshot!(messages, @r###"
+            {
+                NonZeroOid(62fc20d2a290daea0d52bdc2ed2ad4be6491010e): "update test1.txt\n\nMention a ++ reword marker in the body.\n",
+                NonZeroOid(96d1c37a3d4363611c49f7e52186e189a04c531f): "update test2.txt\n",
+            }
+            "###);
+            assert!(result.duplicates.is_empty());
+            assert!(result.unexpected.is_empty());
+        };
+
+        Ok(())
+    }
+
     #[test]
     fn test_reword_parses_unexpected_and_duplicate_commit_hashs() -> eyre::Result<()> {
         let git = make_git()?;
@@ -32,19 +32,19 @@ use lib::core::effects::Effects;
 use lib::core::eventlog::{EventLogDb, EventReplayer};
 use lib::core::formatting::Pluralize;
 use lib::core::node_descriptors::{
-    BranchesDescriptor, CommitMessageDescriptor, CommitOidDescriptor,
-    DifferentialRevisionDescriptor, ObsolescenceExplanationDescriptor, Redactor,
-    RelativeTimeDescriptor,
+    BranchesDescriptor, ChangeIdDescriptor, CommitMessageDescriptor, CommitOidDescriptor,
+    DifferentialRevisionDescriptor, EmptyCommitDescriptor, ObsolescenceExplanationDescriptor,
+    Redactor, RelativeTimeDescriptor, ReviewStatusDescriptor,
 };
 use lib::git::{GitRunInfo, Repo};
 
-pub use graph::{make_smartlog_graph, SmartlogGraph};
+pub use graph::{make_smartlog_graph, make_smartlog_graph_from_commits, SmartlogGraph};
 pub use render::{render_graph, SmartlogOptions};
 
 use git_branchless_revset::resolve_commits;
 
 mod graph {
-    use std::collections::HashMap;
+    use std::collections::{HashMap, HashSet};
 
     use lib::core::gc::mark_commit_reachable;
     use tracing::instrument;
@@ -337,6 +337,60 @@ mod graph {
         }
     }
 
+    /// Construct a smartlog graph directly from the given commits, linking each
+    /// commit to those of its parents which are also in `commits`.
+    ///
+    /// Unlike [`make_smartlog_graph`], the commits don't need to be in the DAG,
+    /// so this can be used to render commits which only exist in memory, such
+    /// as the would-be results of a rebase. Commits in `main_commit_oids` are
+    /// rendered as main branch commits.
+    #[instrument]
+    pub fn make_smartlog_graph_from_commits<'repo>(
+        commits: Vec<Commit<'repo>>,
+        main_commit_oids: &HashSet<NonZeroOid>,
+    ) -> SmartlogGraph<'repo> {
+        let mut nodes: HashMap<NonZeroOid, Node> = HashMap::new();
+        let mut links: Vec<(NonZeroOid, NonZeroOid, bool)> = Vec::new();
+        for commit in commits.iter() {
+            for (i, parent_oid) in commit.get_parent_oids().into_iter().enumerate() {
+                if commits.iter().any(|commit| commit.get_oid() == parent_oid) {
+                    links.push((commit.get_oid(), parent_oid, i > 0));
+                }
+            }
+        }
+        for commit in commits {
+            let oid = commit.get_oid();
+            nodes.insert(
+                oid,
+                Node {
+                    object: NodeObject::Commit { commit },
+                    parents: Vec::new(),  // populated below
+                    children: Vec::new(), // populated below
+                    ancestor_info: None,
+                    descendants: Vec::new(),
+                    is_main: main_commit_oids.contains(&oid),
+                    is_obsolete: false,
+                    num_omitted_descendants: 0,
+                },
+            );
+        }
+        for (child_oid, parent_oid, is_merge_link) in links {
+            nodes.get_mut(&child_oid).unwrap().parents.push(parent_oid);
+            nodes
+                .get_mut(&parent_oid)
+                .unwrap()
+                .children
+                .push(ChildInfo {
+                    oid: child_oid,
+                    is_merge_child: is_merge_link,
+                });
+        }
+
+        let mut graph = SmartlogGraph { nodes };
+        sort_children(&mut graph);
+        graph
+    }
+
     /// Construct the smartlog graph for the repo.
     #[instrument]
     pub fn make_smartlog_graph<'repo>(
@@ -833,6 +887,7 @@ pub fn smartlog(
         references_snapshot.head_oid,
         &mut [
             &mut CommitOidDescriptor::new(true)?,
+            &mut ChangeIdDescriptor::new(&repo, &conn)?,
             &mut RelativeTimeDescriptor::new(&repo, SystemTime::now())?,
             &mut ObsolescenceExplanationDescriptor::new(
                 &event_replayer,
@@ -845,6 +900,8 @@ pub fn smartlog(
                 &Redactor::Disabled,
             )?,
             &mut DifferentialRevisionDescriptor::new(&repo, &Redactor::Disabled)?,
+            &mut ReviewStatusDescriptor::new(&repo, &conn)?,
+            &mut EmptyCommitDescriptor::new(&repo)?,
             &mut CommitMessageDescriptor::new(&Redactor::Disabled)?,
         ],
     )?
@@ -812,3 +812,27 @@ fn test_exact() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_smartlog_change_id() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.commit_file("test1", 1)?;
+    git.detach_head()?;
+    git.commit_file("test2", 2)?;
+    git.run(&["commit", "--amend", "-m", "amend test2"])?;
+    git.run(&["config", "branchless.commitDescriptors.changeId", "true"])?;
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d txknxzmx (master) create test1.txt
+        |
+        @ 5ff9fd3 qtmynwsp amend test2
+        "###);
+    }
+
+    Ok(())
+}
@@ -2,6 +2,7 @@ use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fmt::Write;
 use std::time::SystemTime;
 
+use eyre::Context;
 use itertools::Itertools;
 use lib::core::config::get_main_branch_name;
 use lib::core::dag::{CommitSet, Dag};
@@ -17,6 +18,92 @@ use tracing::{instrument, warn};
 
 use crate::{CommitStatus, CreateStatus, Forge, SubmitOptions, SubmitStatus};
 
+/// Stores the OID which each remote branch was last known to point to, as of
+/// when it was last pushed by `git submit` or last found to be up-to-date.
+/// This is used to detect when somebody else has pushed to the branch since,
+/// even if the remote-tracking branch has been updated by a fetch in the
+/// meantime.
+struct PushedBranchDb<'conn> {
+    conn: &'conn rusqlite::Connection,
+}
+
+impl<'conn> PushedBranchDb<'conn> {
+    fn new(conn: &'conn rusqlite::Connection) -> eyre::Result<Self> {
+        conn.execute(
+            "
+CREATE TABLE IF NOT EXISTS submit_pushed_branches (
+    remote_name TEXT NOT NULL,
+    branch_name TEXT NOT NULL,
+    commit_oid TEXT NOT NULL,
+    PRIMARY KEY (remote_name, branch_name)
+)
+",
+            rusqlite::params![],
+        )
+        .wrap_err("Creating `submit_pushed_branches` table")?;
+        Ok(Self { conn })
+    }
+
+    fn get(&self, remote_name: &str, branch_name: &str) -> eyre::Result<Option<NonZeroOid>> {
+        let mut stmt = self.conn.prepare(
+            "
+SELECT commit_oid
+FROM submit_pushed_branches
+WHERE remote_name = ? AND branch_name = ?
+",
+        )?;
+        let mut rows = stmt
+            .query_map(rusqlite::params![remote_name, branch_name], |row| {
+                let commit_oid: String = row.get("commit_oid")?;
+                Ok(commit_oid)
+            })
+            .wrap_err("Querying pushed branch")?;
+        match rows.next().transpose()? {
+            Some(commit_oid) => Ok(Some(commit_oid.parse()?)),
+            None => Ok(None),
+        }
+    }
+
+    fn set(
+        &self,
+        remote_name: &str,
+        branch_name: &str,
+        commit_oid: NonZeroOid,
+    ) -> eyre::Result<()> {
+        self.conn
+            .execute(
+                "
+INSERT OR REPLACE INTO submit_pushed_branches
+    (remote_name, branch_name, commit_oid)
+VALUES (?, ?, ?)
+",
+                rusqlite::params![remote_name, branch_name, commit_oid.to_string()],
+            )
+            .wrap_err("Saving pushed branch")?;
+        Ok(())
+    }
+
+    /// Record the current target of each of the given local branches as the
+    /// last-known target of its remote branch.
+    fn record_local_branches<'a>(
+        &self,
+        repo: &Repo,
+        remote_name: &str,
+        branch_names: impl IntoIterator<Item = &'a String>,
+    ) -> eyre::Result<()> {
+        for branch_name in branch_names {
+            let branch_oid = match repo.find_branch(branch_name, BranchType::Local)? {
+                Some(branch) => branch.get_oid()?,
+                None => None,
+            };
+            if let Some(branch_oid) = branch_oid {
+                self.set(remote_name, branch_name, branch_oid)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub struct BranchForge<'a> {
     pub effects: &'a Effects,
@@ -133,6 +220,8 @@ impl Forge for BranchForge<'_> {
         }
 
         // Determine status of each commit/branch.
+        let conn = self.repo.get_db_conn()?;
+        let pushed_branch_db = PushedBranchDb::new(&conn)?;
         let mut commit_statuses = HashMap::new();
         for (commit_oid, branches) in &self.references_snapshot.branch_oid_to_names {
             let branch_infos = branches
@@ -161,16 +250,24 @@ impl Forge for BranchForge<'_> {
                         remote_commit_name: None,
                     },
 
-                    Some(upstream_branch) => CommitStatus {
-                        submit_status: if branch.get_oid()? == upstream_branch.get_oid()? {
+                    Some(upstream_branch) => {
+                        let branch_oid = branch.get_oid()?;
+                        let submit_status = if branch_oid == upstream_branch.get_oid()? {
+                            if let (Some(remote_name), Some(branch_oid)) = (remote_name, branch_oid)
+                            {
+                                pushed_branch_db.set(remote_name, branch_name, branch_oid)?;
+                            }
                             SubmitStatus::UpToDate
                         } else {
                             SubmitStatus::NeedsUpdate
-                        },
-                        remote_name: remote_name.clone(),
-                        local_commit_name: Some(branch_name.clone()),
-                        remote_commit_name: Some(upstream_branch.get_name()?.to_owned()),
-                    },
+                        };
+                        CommitStatus {
+                            submit_status,
+                            remote_name: remote_name.clone(),
+                            local_commit_name: Some(branch_name.clone()),
+                            remote_commit_name: Some(upstream_branch.get_name()?.to_owned()),
+                        }
+                    }
                 },
 
                 _branch_infos => CommitStatus {
@@ -247,6 +344,12 @@ These remotes are available: {}",
             try_exit_code!(self
                 .git_run_info
                 .run(self.effects, Some(event_tx_id), &args)?);
+            let conn = self.repo.get_db_conn()?;
+            PushedBranchDb::new(&conn)?.record_local_branches(
+                self.repo,
+                &push_remote,
+                &unsubmitted_branch_names,
+            )?;
             Ok(Ok(commits
                 .into_iter()
                 .filter_map(|(commit_oid, commit_status)| {
@@ -292,6 +395,57 @@ These remotes are available: {}",
             .map(|(k, v)| (k, v.into_iter().collect::<BTreeSet<_>>()))
             .collect();
 
+        // Refuse to overwrite commits which somebody else pushed since the
+        // branch was last submitted. Once this check passes, the
+        // remote-tracking branch is known to match the last-submitted OID (if
+        // any), so `--force-with-lease` leases against that OID.
+        let conn = self.repo.get_db_conn()?;
+        let pushed_branch_db = PushedBranchDb::new(&conn)?;
+        let mut num_conflicts = 0;
+        for (remote_name, branch_names) in branches_by_remote.iter() {
+            for branch_name in branch_names {
+                let branch = match self.repo.find_branch(branch_name, BranchType::Local)? {
+                    Some(branch) => branch,
+                    None => continue,
+                };
+                let (branch_oid, remote_oid, pushed_oid) = match (
+                    branch.get_oid()?,
+                    branch.get_upstream_branch_target()?,
+                    pushed_branch_db.get(remote_name, branch_name)?,
+                ) {
+                    (Some(branch_oid), Some(remote_oid), Some(pushed_oid)) => {
+                        (branch_oid, remote_oid, pushed_oid)
+                    }
+                    _ => continue,
+                };
+                if remote_oid == pushed_oid
+                    || self.repo.find_merge_base(remote_oid, branch_oid)? == Some(remote_oid)
+                {
+                    continue;
+                }
+
+                num_conflicts += 1;
+                let upstream_branch_name = branch
+                    .get_upstream_branch()?
+                    .map(|upstream_branch| upstream_branch.get_name().map(ToOwned::to_owned))
+                    .transpose()?
+                    .unwrap_or_else(|| format!("{remote_name}/{branch_name}"));
+                writeln!(
+                    self.effects.get_output_stream(),
+                    "\
+Refusing to push branch {branch_name}: {upstream_branch_name} was updated to {} since it was last
+submitted as {}, so somebody else may have pushed to it.
+To keep their changes, rebase onto {upstream_branch_name} and submit again. To discard them,
+run: git push --force {remote_name} {branch_name}",
+                    self.describe_oid(remote_oid)?,
+                    self.describe_oid(pushed_oid)?,
+                )?;
+            }
+        }
+        if num_conflicts > 0 {
+            return Ok(Err(ExitCode(1)));
+        }
+
         let now = SystemTime::now();
         let event_tx_id = self.event_log_db.make_transaction_id(now, "submit")?;
         let (effects, progress) = self.effects.start_operation(OperationType::PushCommits);
@@ -314,9 +468,34 @@ These remotes are available: {}",
                     return Ok(Err(exit_code));
                 }
             }
+            pushed_branch_db.record_local_branches(self.repo, &remote_name, &branch_names)?;
             progress.notify_progress_inc(branch_names.len());
         }
 
         Ok(Ok(()))
     }
+
+    #[instrument]
+    fn mark_ready(&mut self, _commits: HashMap<NonZeroOid, CommitStatus>) -> EyreExitOr<()> {
+        // Branches can't be drafts, so there's nothing to do.
+        Ok(Ok(()))
+    }
+
+    #[instrument]
+    fn query_closed(&mut self, _commit_set: CommitSet) -> EyreExitOr<HashMap<NonZeroOid, String>> {
+        // There's no code review to check, and a branch which was merged is
+        // instead detected by `git sync` once the main branch is updated.
+        Ok(Ok(Default::default()))
+    }
+}
+
+impl BranchForge<'_> {
+    /// Describe the given OID by its short OID, if the commit is available
+    /// locally.
+    fn describe_oid(&self, oid: NonZeroOid) -> eyre::Result<String> {
+        match self.repo.find_commit(oid)? {
+            Some(commit) => Ok(commit.get_short_oid()?),
+            None => Ok(oid.to_string()),
+        }
+    }
 }
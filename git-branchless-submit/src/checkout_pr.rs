@@ -0,0 +1,288 @@
+//! Check out a stack of GitHub pull requests for local review.
+//!
+//! The pull request is fetched along with each pull request that it's stacked
+//! on, as determined by following base branches until reaching the main
+//! branch. A local branch is created for each pull request, and its commits
+//! are recorded in the event log as draft commits, so that the stack shows up
+//! in the smartlog and can be navigated like a local stack.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::time::SystemTime;
+
+use bstr::BString;
+use cursive_core::theme::Effect;
+use cursive_core::utils::markup::StyledString;
+use git_branchless_invoke::CommandContext;
+use git_branchless_opts::CheckoutPrArgs;
+use lazy_static::lazy_static;
+use lib::core::check_out::{check_out_commit, CheckOutCommitOptions, CheckoutTarget};
+use lib::core::config::get_main_branch_name;
+use lib::core::dag::{CommitSet, Dag};
+use lib::core::effects::Effects;
+use lib::core::eventlog::{Event, EventLogDb, EventReplayer};
+use lib::core::gc::mark_commit_reachable;
+use lib::core::repo_ext::RepoExt;
+use lib::git::{BranchType, GitRunInfo, MaybeZeroOid, NonZeroOid, ReferenceName, Repo};
+use lib::try_exit_code;
+use lib::util::{ExitCode, EyreExitOr};
+use regex::Regex;
+use tracing::instrument;
+
+use crate::github::client::PullRequestInfo;
+use crate::github::{github_push_remote, GithubForge};
+
+lazy_static! {
+    static ref PULL_REQUEST_RE: Regex =
+        Regex::new(r"^(?:#?|.*/pull/)(\d+)/?$").expect("Pull request regex");
+}
+
+/// Parse the pull request number out of a number such as `123` or `#123`, or
+/// a URL such as `https://github.com/owner/repo/pull/123`.
+fn parse_pull_request_number(pull_request: &str) -> Option<usize> {
+    let captures = PULL_REQUEST_RE.captures(pull_request.trim())?;
+    captures[1].parse().ok()
+}
+
+/// `checkout-pr` command.
+pub fn command_main(ctx: CommandContext, args: CheckoutPrArgs) -> EyreExitOr<()> {
+    let CommandContext {
+        effects,
+        git_run_info,
+    } = ctx;
+    let CheckoutPrArgs {
+        pull_request,
+        no_checkout,
+    } = args;
+    checkout_pr(&effects, &git_run_info, &pull_request, no_checkout)
+}
+
+#[instrument]
+fn checkout_pr(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    pull_request: &str,
+    no_checkout: bool,
+) -> EyreExitOr<()> {
+    let number = match parse_pull_request_number(pull_request) {
+        Some(number) => number,
+        None => {
+            writeln!(
+                effects.get_output_stream(),
+                "Could not parse pull request number or URL: {pull_request}"
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+
+    let repo = Repo::from_current_dir()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let references_snapshot = repo.get_references_snapshot()?;
+    let mut dag = Dag::open_and_sync(
+        effects,
+        &repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+    let now = SystemTime::now();
+    let event_tx_id = event_log_db.make_transaction_id(now, "checkout-pr")?;
+
+    let remote_name = match github_push_remote(&repo)? {
+        Some(remote_name) => remote_name,
+        None => match repo.get_default_push_remote()? {
+            Some(remote_name) => remote_name,
+            None => {
+                writeln!(
+                    effects.get_output_stream(),
+                    "No default push repository configured. To configure, run: {}",
+                    effects.get_glyphs().render(StyledString::styled(
+                        "gh repo set-default <repo>",
+                        Effect::Bold,
+                    ))?
+                )?;
+                return Ok(Err(ExitCode(1)));
+            }
+        },
+    };
+
+    let client = GithubForge::client(git_run_info.clone(), &repo)?;
+    let main_branch_name = get_main_branch_name(&repo)?;
+    let mut pull_request_infos = vec![try_exit_code!(
+        client.query_pull_request_info(effects, number)?
+    )];
+    let mut seen_branch_names: HashSet<String> = pull_request_infos
+        .iter()
+        .map(|pull_request_info| pull_request_info.head_ref_name.clone())
+        .collect();
+    while let Some(base_ref_name) = pull_request_infos
+        .last()
+        .map(|pull_request_info| pull_request_info.base_ref_name.clone())
+    {
+        if base_ref_name == main_branch_name || !seen_branch_names.insert(base_ref_name.clone()) {
+            break;
+        }
+        match try_exit_code!(client.query_pull_request_info_for_branch(effects, &base_ref_name)?) {
+            Some(pull_request_info) => pull_request_infos.push(pull_request_info),
+            None => break,
+        }
+    }
+    // Order from the bottom of the stack to the top.
+    pull_request_infos.reverse();
+
+    let mut fetch_args = vec!["fetch".to_owned(), remote_name];
+    fetch_args.extend(
+        pull_request_infos
+            .iter()
+            .map(|pull_request_info| pull_request_info.head_ref_name.clone()),
+    );
+    try_exit_code!(git_run_info.run(effects, Some(event_tx_id), &fetch_args)?);
+
+    let mut branch_updates: Vec<(MaybeZeroOid, NonZeroOid, ReferenceName)> = Vec::new();
+    for pull_request_info in pull_request_infos.iter() {
+        let PullRequestInfo {
+            number,
+            head_ref_name,
+            head_ref_oid,
+            ..
+        } = pull_request_info;
+        let head_ref_oid = head_ref_oid.0;
+        if repo.find_commit(head_ref_oid)?.is_none() {
+            writeln!(
+                effects.get_output_stream(),
+                "\
+Could not find commit {head_ref_oid} for pull request #{number} after fetching.
+It may have been pushed to a fork."
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+
+        let old_oid = match repo.find_branch(head_ref_name, BranchType::Local)? {
+            Some(branch) => match branch.get_oid()? {
+                Some(branch_oid) if branch_oid == head_ref_oid => continue,
+                Some(_) => {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "\
+Branch {} already exists and does not match pull
+request #{number}. Delete or rename it, then try again.",
+                        effects
+                            .get_glyphs()
+                            .render(StyledString::styled(head_ref_name, Effect::Bold))?,
+                    )?;
+                    return Ok(Err(ExitCode(1)));
+                }
+                None => MaybeZeroOid::Zero,
+            },
+            None => MaybeZeroOid::Zero,
+        };
+        branch_updates.push((
+            old_oid,
+            head_ref_oid,
+            ReferenceName::from(format!("refs/heads/{head_ref_name}")),
+        ));
+    }
+
+    let head_oids: CommitSet = pull_request_infos
+        .iter()
+        .map(|pull_request_info| pull_request_info.head_ref_oid.0)
+        .collect();
+    dag.sync_from_oids(effects, &repo, CommitSet::empty(), head_oids.clone())?;
+    let draft_commits = dag.query_only(head_oids, dag.main_branch_commit.clone())?;
+    let draft_commit_oids = dag.commit_set_to_vec(&draft_commits)?;
+    for commit_oid in draft_commit_oids.iter() {
+        mark_commit_reachable(&repo, *commit_oid)?;
+    }
+    let timestamp = now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64();
+    event_log_db.add_events(
+        draft_commit_oids
+            .iter()
+            .map(|commit_oid| Event::CommitEvent {
+                timestamp,
+                event_tx_id,
+                commit_oid: *commit_oid,
+            })
+            .collect(),
+    )?;
+
+    for (_old_oid, new_oid, reference_name) in branch_updates.iter() {
+        repo.create_reference(reference_name, *new_oid, true, "checkout-pr")?;
+    }
+    #[allow(clippy::format_collect)]
+    let branch_updates_stdin: String = branch_updates
+        .iter()
+        .map(|(old_oid, new_oid, reference_name)| {
+            format!("{old_oid} {new_oid} {}\n", reference_name.as_str())
+        })
+        .collect();
+    git_run_info.run_hook(
+        effects,
+        &repo,
+        "reference-transaction",
+        event_tx_id,
+        &["committed"],
+        Some(BString::from(branch_updates_stdin)),
+    )?;
+
+    for pull_request_info in pull_request_infos.iter() {
+        let PullRequestInfo {
+            number,
+            head_ref_name,
+            title,
+            ..
+        } = pull_request_info;
+        writeln!(
+            effects.get_output_stream(),
+            "Checked out pull request #{number} as branch {}: {title}",
+            effects
+                .get_glyphs()
+                .render(StyledString::styled(head_ref_name, Effect::Bold))?,
+        )?;
+    }
+
+    if no_checkout {
+        return Ok(Ok(()));
+    }
+    let top_branch_name = match pull_request_infos.last() {
+        Some(pull_request_info) => &pull_request_info.head_ref_name,
+        None => return Ok(Ok(())),
+    };
+    check_out_commit(
+        effects,
+        git_run_info,
+        &repo,
+        &event_log_db,
+        event_tx_id,
+        Some(CheckoutTarget::Reference(ReferenceName::from(format!(
+            "refs/heads/{top_branch_name}"
+        )))),
+        &CheckOutCommitOptions::default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pull_request_number() {
+        assert_eq!(parse_pull_request_number("123"), Some(123));
+        assert_eq!(parse_pull_request_number("#123"), Some(123));
+        assert_eq!(
+            parse_pull_request_number("https://github.com/owner/repo/pull/123"),
+            Some(123)
+        );
+        assert_eq!(
+            parse_pull_request_number("https://github.com/owner/repo/pull/123/"),
+            Some(123)
+        );
+        assert_eq!(parse_pull_request_number("my-branch"), None);
+        assert_eq!(
+            parse_pull_request_number("https://github.com/owner/repo/issues/123"),
+            None
+        );
+    }
+}
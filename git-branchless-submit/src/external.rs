@@ -0,0 +1,475 @@
+//! Backend for submitting patch stacks with an external executable, for
+//! integrating with code review systems which aren't supported natively.
+//!
+//! The executable is named `branchless-forge-<name>` and must be on `PATH`,
+//! where `<name>` is set with the `branchless.submit.externalForge` config
+//! option. For each operation, it's invoked in the root of the working copy as
+//! `branchless-forge-<name> <operation>`, with a JSON request written to its
+//! stdin. It should write a JSON response to stdout and exit with status 0.
+//! Anything it writes to stderr is shown to the user, and a nonzero exit code
+//! aborts the submit with that exit code.
+//!
+//! Commit OIDs are written as full hexadecimal strings. A commit status is an
+//! object like `{"status": "needs_update", "remote_name": "origin",
+//! "local_commit_name": "my-branch", "remote_commit_name": "my-branch"}`, where
+//! `status` is one of `local`, `unsubmitted`, `unknown`, `up_to_date`, or
+//! `needs_update`, and the other fields are optional. The operations are:
+//!
+//! - `query-status`: the request is `{"commits": [{"commit_oid": ...,
+//!   "branch_names": [...]}]}`, and the response is `{"statuses": {<commit
+//!   OID>: <commit status>}}`.
+//! - `create`: the request is `{"commits": {<commit OID>: <commit status>},
+//!   "draft": false, "message": null}`, and the response is `{"created":
+//!   {<commit OID>: {"final_commit_oid": ..., "local_commit_name": ...}}}`. If
+//!   the executable amends a commit, such as to add a trailer to its message,
+//!   it should report the amended commit as `final_commit_oid`, and any
+//!   branches and `HEAD` will be moved to it.
+//! - `update`: the request is the same as for `create`, and the response is
+//!   `{}`.
+//! - `mark-ready`: the request is `{"commits": {<commit OID>: <commit
+//!   status>}}`, and the response is `{}`.
+//! - `query-closed`: the request is the same as for `query-status`, and the
+//!   response is `{"closed": {<commit OID>: <reason>}}`, where `<reason>` is a
+//!   description of why the commit is no longer needed, such as `"D123 was
+//!   landed"`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Debug, Write};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use eyre::Context;
+use lib::core::check_out::CheckOutCommitOptions;
+use lib::core::dag::{CommitSet, Dag};
+use lib::core::effects::{Effects, OperationType};
+use lib::core::eventlog::{Event, EventLogDb};
+use lib::core::gc::mark_commit_reachable;
+use lib::core::repo_ext::RepoReferencesSnapshot;
+use lib::core::rewrite::{check_out_updated_head, move_branches};
+use lib::git::{CategorizedReferenceName, ConfigRead, GitRunInfo, MaybeZeroOid, NonZeroOid, Repo};
+use lib::try_exit_code;
+use lib::util::{ExitCode, EyreExitOr};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{CommitStatus, CreateStatus, Forge, SubmitOptions, SubmitStatus};
+
+/// Get the name of the external forge to use, as set by
+/// `branchless.submit.externalForge`.
+pub fn external_forge_name(repo: &Repo) -> eyre::Result<Option<String>> {
+    let config = repo.get_readonly_config()?;
+    let name: Option<String> = config.get("branchless.submit.externalForge")?;
+    Ok(name)
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ExternalSubmitStatus {
+    Local,
+    Unsubmitted,
+    Unknown,
+    UpToDate,
+    NeedsUpdate,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct ExternalCommitStatus {
+    status: ExternalSubmitStatus,
+    #[serde(default)]
+    remote_name: Option<String>,
+    #[serde(default)]
+    local_commit_name: Option<String>,
+    #[serde(default)]
+    remote_commit_name: Option<String>,
+}
+
+impl From<CommitStatus> for ExternalCommitStatus {
+    fn from(commit_status: CommitStatus) -> Self {
+        let CommitStatus {
+            submit_status,
+            remote_name,
+            local_commit_name,
+            remote_commit_name,
+        } = commit_status;
+        let status = match submit_status {
+            SubmitStatus::Local => ExternalSubmitStatus::Local,
+            SubmitStatus::Unsubmitted => ExternalSubmitStatus::Unsubmitted,
+            SubmitStatus::Unknown => ExternalSubmitStatus::Unknown,
+            SubmitStatus::UpToDate => ExternalSubmitStatus::UpToDate,
+            SubmitStatus::NeedsUpdate => ExternalSubmitStatus::NeedsUpdate,
+        };
+        Self {
+            status,
+            remote_name,
+            local_commit_name,
+            remote_commit_name,
+        }
+    }
+}
+
+impl From<ExternalCommitStatus> for CommitStatus {
+    fn from(commit_status: ExternalCommitStatus) -> Self {
+        let ExternalCommitStatus {
+            status,
+            remote_name,
+            local_commit_name,
+            remote_commit_name,
+        } = commit_status;
+        let submit_status = match status {
+            ExternalSubmitStatus::Local => SubmitStatus::Local,
+            ExternalSubmitStatus::Unsubmitted => SubmitStatus::Unsubmitted,
+            ExternalSubmitStatus::Unknown => SubmitStatus::Unknown,
+            ExternalSubmitStatus::UpToDate => SubmitStatus::UpToDate,
+            ExternalSubmitStatus::NeedsUpdate => SubmitStatus::NeedsUpdate,
+        };
+        Self {
+            submit_status,
+            remote_name,
+            local_commit_name,
+            remote_commit_name,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ExternalCommitInfo {
+    commit_oid: String,
+    branch_names: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct QueryCommitsRequest {
+    commits: Vec<ExternalCommitInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct QueryStatusResponse {
+    statuses: HashMap<String, ExternalCommitStatus>,
+}
+
+#[derive(Debug, Serialize)]
+struct SubmitRequest {
+    commits: BTreeMap<String, ExternalCommitStatus>,
+    draft: bool,
+    message: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExternalCreateStatus {
+    final_commit_oid: String,
+    local_commit_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateResponse {
+    created: HashMap<String, ExternalCreateStatus>,
+}
+
+#[derive(Debug, Serialize)]
+struct MarkReadyRequest {
+    commits: BTreeMap<String, ExternalCommitStatus>,
+}
+
+#[derive(Debug, Deserialize)]
+struct QueryClosedResponse {
+    closed: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmptyResponse {}
+
+fn parse_oid(oid: &str) -> eyre::Result<NonZeroOid> {
+    oid.parse()
+        .wrap_err_with(|| format!("Parsing commit OID from external forge: {oid:?}"))
+}
+
+fn to_external_commit_statuses(
+    commits: HashMap<NonZeroOid, CommitStatus>,
+) -> BTreeMap<String, ExternalCommitStatus> {
+    commits
+        .into_iter()
+        .map(|(commit_oid, commit_status)| (commit_oid.to_string(), commit_status.into()))
+        .collect()
+}
+
+/// A code review system implemented by an external `branchless-forge-<name>`
+/// executable. See the module documentation for the protocol.
+#[allow(missing_docs)]
+#[derive(Debug)]
+pub struct ExternalForge<'a> {
+    pub effects: &'a Effects,
+    pub git_run_info: &'a GitRunInfo,
+    pub repo: &'a Repo,
+    pub dag: &'a mut Dag,
+    pub event_log_db: &'a EventLogDb<'a>,
+    pub references_snapshot: &'a RepoReferencesSnapshot,
+    pub name: String,
+}
+
+impl Forge for ExternalForge<'_> {
+    #[instrument]
+    fn query_status(
+        &mut self,
+        commit_set: CommitSet,
+    ) -> EyreExitOr<HashMap<NonZeroOid, CommitStatus>> {
+        let request = self.make_query_commits_request(&commit_set)?;
+        let QueryStatusResponse { statuses } =
+            try_exit_code!(self.run_executable("query-status", &request)?);
+        let mut result = HashMap::new();
+        for (commit_oid, commit_status) in statuses {
+            result.insert(parse_oid(&commit_oid)?, commit_status.into());
+        }
+        Ok(Ok(result))
+    }
+
+    #[instrument]
+    fn create(
+        &mut self,
+        commits: HashMap<NonZeroOid, CommitStatus>,
+        options: &SubmitOptions,
+    ) -> EyreExitOr<HashMap<NonZeroOid, CreateStatus>> {
+        if commits.is_empty() {
+            return Ok(Ok(Default::default()));
+        }
+        let request = Self::make_submit_request(commits, options);
+        let CreateResponse { created } = try_exit_code!(self.run_executable("create", &request)?);
+
+        let mut create_statuses = HashMap::new();
+        let mut rewritten_oids = HashMap::new();
+        for (commit_oid, create_status) in created {
+            let ExternalCreateStatus {
+                final_commit_oid,
+                local_commit_name,
+            } = create_status;
+            let commit_oid = parse_oid(&commit_oid)?;
+            let final_commit_oid = parse_oid(&final_commit_oid)?;
+            if final_commit_oid != commit_oid {
+                rewritten_oids.insert(commit_oid, MaybeZeroOid::NonZero(final_commit_oid));
+            }
+            create_statuses.insert(
+                commit_oid,
+                CreateStatus {
+                    final_commit_oid,
+                    local_commit_name,
+                },
+            );
+        }
+        try_exit_code!(self.record_rewrites(&rewritten_oids)?);
+        Ok(Ok(create_statuses))
+    }
+
+    #[instrument]
+    fn update(
+        &mut self,
+        commits: HashMap<NonZeroOid, CommitStatus>,
+        options: &SubmitOptions,
+    ) -> EyreExitOr<()> {
+        if commits.is_empty() {
+            return Ok(Ok(()));
+        }
+        let request = Self::make_submit_request(commits, options);
+        let EmptyResponse {} = try_exit_code!(self.run_executable("update", &request)?);
+        Ok(Ok(()))
+    }
+
+    #[instrument]
+    fn mark_ready(&mut self, commits: HashMap<NonZeroOid, CommitStatus>) -> EyreExitOr<()> {
+        let request = MarkReadyRequest {
+            commits: to_external_commit_statuses(commits),
+        };
+        let EmptyResponse {} = try_exit_code!(self.run_executable("mark-ready", &request)?);
+        Ok(Ok(()))
+    }
+
+    #[instrument]
+    fn query_closed(&mut self, commit_set: CommitSet) -> EyreExitOr<HashMap<NonZeroOid, String>> {
+        let request = self.make_query_commits_request(&commit_set)?;
+        let QueryClosedResponse { closed } =
+            try_exit_code!(self.run_executable("query-closed", &request)?);
+        let mut result = HashMap::new();
+        for (commit_oid, reason) in closed {
+            result.insert(parse_oid(&commit_oid)?, reason);
+        }
+        Ok(Ok(result))
+    }
+}
+
+impl ExternalForge<'_> {
+    fn make_query_commits_request(
+        &self,
+        commit_set: &CommitSet,
+    ) -> eyre::Result<QueryCommitsRequest> {
+        let commits = self
+            .dag
+            .sort(commit_set)?
+            .into_iter()
+            .map(|commit_oid| {
+                let branch_names = self
+                    .references_snapshot
+                    .branch_oid_to_names
+                    .get(&commit_oid)
+                    .into_iter()
+                    .flatten()
+                    .map(|branch_name| CategorizedReferenceName::new(branch_name).render_suffix())
+                    .collect();
+                ExternalCommitInfo {
+                    commit_oid: commit_oid.to_string(),
+                    branch_names,
+                }
+            })
+            .collect();
+        Ok(QueryCommitsRequest { commits })
+    }
+
+    fn make_submit_request(
+        commits: HashMap<NonZeroOid, CommitStatus>,
+        options: &SubmitOptions,
+    ) -> SubmitRequest {
+        let SubmitOptions {
+            create: _,
+            draft,
+            execution_strategy: _,
+            num_jobs: _,
+            message,
+        } = options;
+        SubmitRequest {
+            commits: to_external_commit_statuses(commits),
+            draft: *draft,
+            message: message.clone(),
+        }
+    }
+
+    /// Invoke the external executable for the given operation, and parse its
+    /// response.
+    #[instrument]
+    fn run_executable<Request: Debug + Serialize, Response: DeserializeOwned>(
+        &self,
+        operation: &str,
+        request: &Request,
+    ) -> EyreExitOr<Response> {
+        let exe = format!("branchless-forge-{}", self.name);
+        let exe_invocation = format!("{exe} {operation}");
+        debug!(?exe_invocation, ?request, "Invoking external forge");
+        let (effects, progress) = self
+            .effects
+            .start_operation(OperationType::RunTests(Arc::new(exe_invocation.clone())));
+        let _progress = progress;
+
+        let request = serde_json::to_vec(request)?;
+        let mut command = Command::new(&exe);
+        command
+            .arg(operation)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit());
+        if let Some(working_copy_path) = self.repo.get_working_copy_path() {
+            command.current_dir(working_copy_path);
+        }
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(err) => {
+                writeln!(
+                    effects.get_output_stream(),
+                    "Could not invoke external forge `{exe}`: {err}"
+                )?;
+                return Ok(Err(ExitCode(1)));
+            }
+        };
+        {
+            use std::io::Write;
+            let mut stdin = child.stdin.take().unwrap();
+            stdin
+                .write_all(&request)
+                .wrap_err_with(|| format!("Writing request to `{exe_invocation}`"))?;
+        }
+        let output = child
+            .wait_with_output()
+            .wrap_err_with(|| format!("Waiting for `{exe_invocation}`"))?;
+        if !output.status.success() {
+            writeln!(
+                effects.get_output_stream(),
+                "Call to `{exe_invocation}` failed"
+            )?;
+            return Ok(Err(ExitCode::try_from(output.status)?));
+        }
+        let response = serde_json::from_slice(&output.stdout)
+            .wrap_err_with(|| format!("Deserializing output from `{exe_invocation}`"))?;
+        Ok(Ok(response))
+    }
+
+    /// Move branches and `HEAD` to the commits which were amended by the
+    /// external executable, and record the rewrites so that they can be
+    /// undone.
+    fn record_rewrites(
+        &mut self,
+        rewritten_oids: &HashMap<NonZeroOid, MaybeZeroOid>,
+    ) -> EyreExitOr<()> {
+        if rewritten_oids.is_empty() {
+            return Ok(Ok(()));
+        }
+
+        let now = SystemTime::now();
+        let timestamp = now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64();
+        let event_tx_id = self
+            .event_log_db
+            .make_transaction_id(now, "external forge create")?;
+        let mut events = Vec::new();
+        for (old_commit_oid, new_commit_oid) in rewritten_oids {
+            if let MaybeZeroOid::NonZero(new_commit_oid) = new_commit_oid {
+                mark_commit_reachable(self.repo, *new_commit_oid)?;
+            }
+            events.push(Event::RewriteEvent {
+                timestamp,
+                event_tx_id,
+                old_commit_oid: MaybeZeroOid::NonZero(*old_commit_oid),
+                new_commit_oid: *new_commit_oid,
+            });
+        }
+        self.event_log_db.add_events(events)?;
+
+        let head_info = self.repo.get_head_info()?;
+        if head_info.oid.is_some() {
+            // Avoid moving the branch which HEAD points to, or else the index
+            // will show a lot of changes in the working copy.
+            self.repo.detach_head(&head_info)?;
+        }
+        move_branches(
+            self.effects,
+            self.git_run_info,
+            self.repo,
+            event_tx_id,
+            rewritten_oids,
+        )?;
+        try_exit_code!(check_out_updated_head(
+            self.effects,
+            self.git_run_info,
+            self.repo,
+            self.event_log_db,
+            event_tx_id,
+            rewritten_oids,
+            &head_info,
+            None,
+            &CheckOutCommitOptions {
+                render_smartlog: false,
+                ..Default::default()
+            },
+        )?);
+
+        let final_commit_oids: CommitSet = rewritten_oids
+            .values()
+            .filter_map(|new_commit_oid| match new_commit_oid {
+                MaybeZeroOid::NonZero(new_commit_oid) => Some(*new_commit_oid),
+                MaybeZeroOid::Zero => None,
+            })
+            .collect();
+        self.dag.sync_from_oids(
+            self.effects,
+            self.repo,
+            CommitSet::empty(),
+            final_commit_oids,
+        )?;
+        Ok(Ok(()))
+    }
+}
@@ -0,0 +1,369 @@
+//! Gerrit backend for submitting patch stacks.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Write};
+use std::time::SystemTime;
+
+use cursive_core::theme::Effect;
+use cursive_core::utils::markup::StyledString;
+use itertools::Itertools;
+use lazy_static::lazy_static;
+use lib::core::check_out::CheckOutCommitOptions;
+use lib::core::config::get_main_branch_name;
+use lib::core::dag::{CommitSet, Dag};
+use lib::core::effects::{Effects, OperationType};
+use lib::core::eventlog::EventLogDb;
+use lib::core::repo_ext::RepoExt;
+use lib::core::rewrite::{
+    execute_rebase_plan, BuildRebasePlanOptions, EmptyCommitsMode, ExecuteRebasePlanOptions,
+    ExecuteRebasePlanResult, RebasePlanBuilder, RebasePlanPermissions, RepoResource,
+};
+use lib::git::{Commit, GitRunInfo, GitRunOpts, MaybeZeroOid, NonZeroOid, Repo};
+use lib::try_exit_code;
+use lib::util::{ExitCode, EyreExitOr};
+use rayon::ThreadPoolBuilder;
+use regex::bytes::Regex;
+use tracing::{instrument, warn};
+
+use crate::{CommitStatus, CreateStatus, Forge, SubmitOptions, SubmitStatus};
+
+lazy_static! {
+    static ref CHANGE_ID_RE: Regex = Regex::new(r"(?m)^Change-Id: (I[0-9a-f]{40})\s*$").unwrap();
+}
+
+/// Get the Gerrit `Change-Id` trailer of the given commit, if any.
+pub fn get_change_id(commit: &Commit) -> Option<String> {
+    let message = commit.get_message_raw();
+    CHANGE_ID_RE
+        .captures_iter(&message)
+        .last()
+        .map(|captures| String::from_utf8_lossy(&captures[1]).into_owned())
+}
+
+/// Generate a `Change-Id` for a commit which doesn't have one yet. It's derived
+/// from the commit's original OID, so that it's unique per commit. Once it's
+/// been added to the commit message, it's preserved when the commit is amended
+/// or rebased.
+fn make_change_id(commit_oid: NonZeroOid) -> String {
+    format!("I{commit_oid}")
+}
+
+fn short_change_id(change_id: &str) -> String {
+    change_id.chars().take(8).collect()
+}
+
+/// An option to set on the changes when pushing them for review.
+#[derive(Clone, Copy, Debug)]
+enum PushOption {
+    /// Mark the changes as work in progress.
+    Wip,
+
+    /// Mark the changes as ready for review.
+    Ready,
+}
+
+/// The [Gerrit](https://www.gerritcodereview.com/) code review system.
+///
+/// Each commit is uploaded as its own change, identified by the `Change-Id`
+/// trailer in its commit message. Commits are uploaded by pushing them to
+/// `refs/for/<main branch>` on the default push remote.
+#[allow(missing_docs)]
+#[derive(Debug)]
+pub struct GerritForge<'a> {
+    pub effects: &'a Effects,
+    pub git_run_info: &'a GitRunInfo,
+    pub repo: &'a Repo,
+    pub dag: &'a mut Dag,
+    pub event_log_db: &'a EventLogDb<'a>,
+}
+
+impl Forge for GerritForge<'_> {
+    #[instrument]
+    fn query_status(
+        &mut self,
+        commit_set: CommitSet,
+    ) -> EyreExitOr<HashMap<NonZeroOid, CommitStatus>> {
+        let push_remote = try_exit_code!(self.get_push_remote()?);
+        let uploaded_oids = self.query_uploaded_commit_oids(&push_remote)?;
+
+        let mut result = HashMap::new();
+        for commit_oid in self.dag.commit_set_to_vec(&commit_set)? {
+            let commit = self.repo.find_commit_or_fail(commit_oid)?;
+            let change_id = get_change_id(&commit);
+            let submit_status = match &change_id {
+                None => SubmitStatus::Unsubmitted,
+                Some(_) if uploaded_oids.contains(&commit_oid) => SubmitStatus::UpToDate,
+                Some(_) => SubmitStatus::NeedsUpdate,
+            };
+            result.insert(
+                commit_oid,
+                CommitStatus {
+                    submit_status,
+                    remote_name: Some(push_remote.clone()),
+                    local_commit_name: change_id.as_deref().map(short_change_id),
+                    remote_commit_name: change_id,
+                },
+            );
+        }
+        Ok(Ok(result))
+    }
+
+    #[instrument]
+    fn create(
+        &mut self,
+        commits: HashMap<NonZeroOid, CommitStatus>,
+        options: &SubmitOptions,
+    ) -> EyreExitOr<HashMap<NonZeroOid, CreateStatus>> {
+        let commit_set: CommitSet = commits.keys().copied().collect();
+        let commit_oids = self.dag.sort(&commit_set)?;
+        if commit_oids.is_empty() {
+            return Ok(Ok(Default::default()));
+        }
+
+        let now = SystemTime::now();
+        let event_tx_id = self
+            .event_log_db
+            .make_transaction_id(now, "gerrit add change IDs")?;
+        let build_options = BuildRebasePlanOptions {
+            force_rewrite_public_commits: false,
+            dump_rebase_constraints: false,
+            dump_rebase_plan: false,
+            detect_duplicate_commits_via_patch_id: false,
+        };
+        let execute_options = ExecuteRebasePlanOptions {
+            now,
+            event_tx_id,
+            preserve_timestamps: true,
+            force_in_memory: true,
+            force_on_disk: false,
+            resolve_merge_conflicts: false,
+            reset_author: false,
+            committer_as_author: false,
+            signature_commit_oids: None,
+            check_out_commit_options: CheckOutCommitOptions {
+                render_smartlog: false,
+                ..Default::default()
+            },
+            empty_commits: EmptyCommitsMode::Drop,
+        };
+        let permissions = match RebasePlanPermissions::verify_rewrite_set(
+            self.dag,
+            build_options,
+            &commit_set,
+        )? {
+            Ok(permissions) => permissions,
+            Err(err) => {
+                err.describe(self.effects, self.repo, self.dag)?;
+                return Ok(Err(ExitCode(1)));
+            }
+        };
+
+        let rebase_plan = {
+            let mut builder = RebasePlanBuilder::new(self.dag, permissions);
+            for commit_oid in commit_oids.iter().copied() {
+                let commit = self.repo.find_commit_or_fail(commit_oid)?;
+                let message = commit.get_message_raw();
+                let message = String::from_utf8_lossy(&message);
+                let message = format!(
+                    "{}\n\nChange-Id: {}\n",
+                    message.trim_end(),
+                    make_change_id(commit_oid)
+                );
+                let replacement_commit_oid =
+                    commit.amend_commit(None, None, None, Some(message.as_str()), None)?;
+                builder.move_subtree(commit_oid, commit.get_parent_oids())?;
+                builder.replace_commit(commit_oid, replacement_commit_oid)?;
+            }
+
+            let pool = ThreadPoolBuilder::new().build()?;
+            let repo_pool = RepoResource::new_pool(self.repo)?;
+            match builder.build(self.effects, &pool, &repo_pool)? {
+                Ok(Some(rebase_plan)) => rebase_plan,
+                Ok(None) => return Ok(Ok(Default::default())),
+                Err(err) => {
+                    err.describe(self.effects, self.repo, self.dag)?;
+                    return Ok(Err(ExitCode(1)));
+                }
+            }
+        };
+
+        let rewritten_oids = match execute_rebase_plan(
+            self.effects,
+            self.git_run_info,
+            self.repo,
+            self.event_log_db,
+            &rebase_plan,
+            &execute_options,
+        )? {
+            ExecuteRebasePlanResult::Succeeded {
+                rewritten_oids: Some(rewritten_oids),
+            } => rewritten_oids,
+            ExecuteRebasePlanResult::Succeeded {
+                rewritten_oids: None,
+            } => {
+                warn!("No rewritten commit OIDs were produced by rebase plan execution");
+                Default::default()
+            }
+            ExecuteRebasePlanResult::DeclinedToMerge {
+                failed_merge_info: _,
+            } => {
+                writeln!(
+                    self.effects.get_error_stream(),
+                    "BUG: Merge failed, but adding change IDs shouldn't cause any merge failures."
+                )?;
+                return Ok(Err(ExitCode(1)));
+            }
+            ExecuteRebasePlanResult::Failed { exit_code } => {
+                return Ok(Err(exit_code));
+            }
+        };
+
+        let mut create_statuses = HashMap::new();
+        for commit_oid in commit_oids {
+            let final_commit_oid = match rewritten_oids.get(&commit_oid) {
+                Some(MaybeZeroOid::NonZero(commit_oid)) => *commit_oid,
+                Some(MaybeZeroOid::Zero) => {
+                    warn!(?commit_oid, "Commit was rewritten to the zero OID");
+                    commit_oid
+                }
+                None => commit_oid,
+            };
+            create_statuses.insert(
+                commit_oid,
+                CreateStatus {
+                    final_commit_oid,
+                    local_commit_name: short_change_id(&make_change_id(commit_oid)),
+                },
+            );
+        }
+
+        let final_commit_oids: CommitSet = create_statuses
+            .values()
+            .map(|create_status| create_status.final_commit_oid)
+            .collect();
+        self.dag.sync_from_oids(
+            self.effects,
+            self.repo,
+            CommitSet::empty(),
+            final_commit_oids.clone(),
+        )?;
+        let SubmitOptions {
+            create: _,
+            draft,
+            execution_strategy: _,
+            num_jobs: _,
+            message: _,
+        } = options;
+        try_exit_code!(self.push_for_review(&final_commit_oids, draft.then_some(PushOption::Wip))?);
+        Ok(Ok(create_statuses))
+    }
+
+    #[instrument]
+    fn update(
+        &mut self,
+        commits: HashMap<NonZeroOid, CommitStatus>,
+        _options: &SubmitOptions,
+    ) -> EyreExitOr<()> {
+        let commit_set: CommitSet = commits.keys().copied().collect();
+        // Uploading a new patchset shouldn't change whether the change is a
+        // work in progress.
+        self.push_for_review(&commit_set, None)
+    }
+
+    #[instrument]
+    fn mark_ready(&mut self, commits: HashMap<NonZeroOid, CommitStatus>) -> EyreExitOr<()> {
+        let commit_set: CommitSet = commits.keys().copied().collect();
+        self.push_for_review(&commit_set, Some(PushOption::Ready))
+    }
+
+    #[instrument]
+    fn query_closed(&mut self, _commit_set: CommitSet) -> EyreExitOr<HashMap<NonZeroOid, String>> {
+        // Gerrit doesn't expose the state of changes via Git. Merged changes
+        // are instead detected by `git sync` once the main branch is updated.
+        Ok(Ok(Default::default()))
+    }
+}
+
+impl GerritForge<'_> {
+    fn get_push_remote(&self) -> EyreExitOr<String> {
+        match self.repo.get_default_push_remote()? {
+            Some(push_remote) => Ok(Ok(push_remote)),
+            None => {
+                writeln!(
+                    self.effects.get_output_stream(),
+                    "No default push repository configured. To configure, run: {}",
+                    self.effects.get_glyphs().render(StyledString::styled(
+                        "git config remote.pushDefault <remote>",
+                        Effect::Bold,
+                    ))?
+                )?;
+                Ok(Err(ExitCode(1)))
+            }
+        }
+    }
+
+    /// Get the OIDs of all patchsets which have been uploaded to Gerrit, by
+    /// listing the `refs/changes/*` references on the remote.
+    #[instrument]
+    fn query_uploaded_commit_oids(&self, push_remote: &str) -> eyre::Result<HashSet<NonZeroOid>> {
+        let result = self.git_run_info.run_silent(
+            self.repo,
+            None,
+            &["ls-remote", push_remote, "refs/changes/*"],
+            GitRunOpts::default(),
+        )?;
+        let stdout = String::from_utf8_lossy(&result.stdout);
+        let uploaded_oids = stdout
+            .lines()
+            .filter_map(|line| line.split_whitespace().next())
+            .filter_map(|oid| oid.parse().ok())
+            .collect();
+        Ok(uploaded_oids)
+    }
+
+    /// Push the heads of `commit_set` to `refs/for/<main branch>`, which
+    /// creates or updates a change for each commit which isn't already on the
+    /// main branch. If `push_option` is set, the work-in-progress state of the
+    /// changes is also set.
+    #[instrument]
+    fn push_for_review(
+        &self,
+        commit_set: &CommitSet,
+        push_option: Option<PushOption>,
+    ) -> EyreExitOr<()> {
+        let head_oids = self
+            .dag
+            .commit_set_to_vec(&self.dag.query_heads(commit_set.clone())?)?;
+        if head_oids.is_empty() {
+            return Ok(Ok(()));
+        }
+        let push_remote = try_exit_code!(self.get_push_remote()?);
+        let target_ref = {
+            let mut result = format!("refs/for/{}", get_main_branch_name(self.repo)?);
+            match push_option {
+                Some(PushOption::Wip) => result.push_str("%wip"),
+                Some(PushOption::Ready) => result.push_str("%ready"),
+                None => {}
+            }
+            result
+        };
+
+        let event_tx_id = self
+            .event_log_db
+            .make_transaction_id(SystemTime::now(), "gerrit push")?;
+        let (effects, progress) = self.effects.start_operation(OperationType::PushCommits);
+        progress.notify_progress(0, head_oids.len());
+        // Each head has to be pushed separately, since Git won't update the
+        // same reference more than once in a single push.
+        for head_oid in head_oids.into_iter().sorted() {
+            let args = [
+                "push".to_owned(),
+                push_remote.clone(),
+                format!("{head_oid}:{target_ref}"),
+            ];
+            try_exit_code!(self.git_run_info.run(&effects, Some(event_tx_id), &args)?);
+            progress.notify_progress_inc(1);
+        }
+        Ok(Ok(()))
+    }
+}
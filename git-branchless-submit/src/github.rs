@@ -17,12 +17,13 @@ use lib::core::effects::OperationType;
 use lib::core::eventlog::EventLogDb;
 use lib::core::repo_ext::RepoExt;
 use lib::core::repo_ext::RepoReferencesSnapshot;
+use lib::core::review_status::{ReviewState, ReviewStatus};
 use lib::git::CategorizedReferenceName;
 use lib::git::GitErrorCode;
 use lib::git::GitRunInfo;
 use lib::git::RepoError;
 use lib::git::{BranchType, ConfigRead};
-use lib::git::{NonZeroOid, Repo};
+use lib::git::{Commit, NonZeroOid, Repo};
 use lib::try_exit_code;
 use lib::util::ExitCode;
 use lib::util::EyreExitOr;
@@ -32,15 +33,17 @@ use tracing::instrument;
 use tracing::warn;
 
 use crate::branch_forge::BranchForge;
+use crate::gerrit::get_change_id;
+use crate::retry::RetryPolicy;
 use crate::SubmitStatus;
-use crate::{CommitStatus, CreateStatus, Forge, SubmitOptions};
+use crate::{CommitStatus, CreateStatus, Forge, ReviewAssignment, SubmitOptions};
 
 /// Testing environment variable. When this is set, the executable will use the
 /// mock Github implementation. This should be set to the path of an existing
 /// repository that represents the remote/Github.
 pub const MOCK_REMOTE_REPO_PATH_ENV_KEY: &str = "BRANCHLESS_SUBMIT_GITHUB_MOCK_REMOTE_REPO_PATH";
 
-fn commit_summary_slug(summary: &str) -> String {
+pub(crate) fn commit_summary_slug(summary: &str) -> String {
     let summary_slug: String = summary
         .chars()
         .map(|c| if c.is_alphanumeric() { c } else { '-' })
@@ -58,7 +61,52 @@ fn commit_summary_slug(summary: &str) -> String {
     }
 }
 
-fn singleton<K: Debug + Eq + Hash, V: Clone>(
+/// The template for names of branches created for commits which don't have a
+/// branch yet, if `branchless.submit.branchTemplate` isn't set.
+const DEFAULT_BRANCH_TEMPLATE: &str = "{user}/{slug}";
+
+/// Fill in the placeholders of a branch name template:
+///
+/// - `{user}`: the username on the forge.
+/// - `{slug}`: the commit summary, slugified by [`commit_summary_slug`].
+/// - `{changeid-or-slug}`: the commit's Gerrit `Change-Id`, if it has one,
+///   and otherwise the same as `{slug}`.
+pub(crate) fn render_branch_template(
+    template: &str,
+    username: &str,
+    summary_slug: &str,
+    change_id: Option<&str>,
+) -> String {
+    template
+        .replace("{user}", username)
+        .replace("{slug}", summary_slug)
+        .replace("{changeid-or-slug}", change_id.unwrap_or(summary_slug))
+}
+
+/// Generate the base name of the branch to create for `commit`, according to
+/// the `branchless.submit.branchTemplate` setting.
+pub(crate) fn make_branch_name_base(
+    repo: &Repo,
+    commit: &Commit,
+    username: &str,
+) -> eyre::Result<String> {
+    let template: String = repo.get_readonly_config()?.get_or(
+        "branchless.submit.branchTemplate",
+        DEFAULT_BRANCH_TEMPLATE.to_owned(),
+    )?;
+    let summary = commit.get_summary()?;
+    let summary = String::from_utf8_lossy(&summary);
+    let summary_slug = commit_summary_slug(&summary);
+    let change_id = get_change_id(commit);
+    Ok(render_branch_template(
+        &template,
+        username,
+        &summary_slug,
+        change_id.as_deref(),
+    ))
+}
+
+pub(crate) fn singleton<K: Debug + Eq + Hash, V: Clone>(
     map: &HashMap<K, V>,
     key: K,
     f: impl Fn(V) -> V,
@@ -103,6 +151,13 @@ pub fn github_push_remote(repo: &Repo) -> eyre::Result<Option<String>> {
     Ok(None)
 }
 
+/// Get the token to authenticate to GitHub with, if configured. If not set,
+/// the `gh` command-line utility uses the credentials from `gh auth login`.
+pub fn github_token(repo: &Repo) -> eyre::Result<Option<String>> {
+    repo.get_readonly_config()?
+        .get("branchless.submit.github.token")
+}
+
 /// The [GitHub](https://en.wikipedia.org/wiki/GitHub) code hosting platform.
 /// This forge integrates specifically with the `gh` command-line utility.
 #[allow(missing_docs)]
@@ -250,10 +305,8 @@ impl Forge for GithubForge<'_> {
             let local_branch_name = match &commit_status.local_commit_name {
                 Some(local_branch_name) => local_branch_name.clone(),
                 None => {
-                    let summary = commit.get_summary()?;
-                    let summary = String::from_utf8_lossy(&summary);
-                    let summary_slug = commit_summary_slug(&summary);
-                    let new_branch_name_base = format!("{github_username}/{summary_slug}");
+                    let new_branch_name_base =
+                        make_branch_name_base(self.repo, &commit, &github_username)?;
                     let mut new_branch_name = new_branch_name_base.clone();
                     for i in 2.. {
                         if i > 6 {
@@ -353,6 +406,7 @@ impl Forge for GithubForge<'_> {
                     head_ref_name: local_branch_name.clone(),
                     title,
                     body,
+                    review_assignment: ReviewAssignment::for_commit(self.repo, &commit)?,
                 },
                 options
             )?);
@@ -390,8 +444,10 @@ impl Forge for GithubForge<'_> {
             references_snapshot: &references_snapshot,
         };
 
+        let main_branch_name = get_main_branch_name(self.repo)?;
         let commit_set: CommitSet = commit_statuses.keys().copied().collect();
         let commit_oids = self.dag.sort(&commit_set)?;
+        let mut any_landed_in_stack = false;
         {
             let (effects, progress) = effects.start_operation(OperationType::UpdateCommits);
             progress.notify_progress(0, commit_oids.len());
@@ -451,6 +507,39 @@ impl Forge for GithubForge<'_> {
                     title,
                     body,
                 } = updated_pull_request_info;
+
+                // If the pull request was based on the branch of a parent
+                // commit whose pull request has since been merged or closed,
+                // then that branch may be deleted at any time, so the pull
+                // request needs to be retargeted.
+                let old_base_ref_name = &pull_request_info.base_ref_name;
+                if &base_ref_name != old_base_ref_name
+                    && old_base_ref_name != &main_branch_name
+                    && !pull_request_infos.contains_key(old_base_ref_name)
+                {
+                    // The parent commit may still be in the local stack, if
+                    // it landed as a different commit.
+                    if let Some(old_base_branch) = self
+                        .repo
+                        .find_branch(old_base_ref_name, BranchType::Local)?
+                    {
+                        if let Some(old_base_oid) = old_base_branch.get_oid()? {
+                            if self.dag.query_is_ancestor(old_base_oid, commit_oid)?
+                                && !self.dag.query_is_ancestor(
+                                    old_base_oid,
+                                    references_snapshot.main_branch_oid,
+                                )?
+                            {
+                                any_landed_in_stack = true;
+                            }
+                        }
+                    }
+                    writeln!(
+                        effects.get_output_stream(),
+                        "Retargeting pull request #{} onto {base_ref_name} since the pull request for {old_base_ref_name} is no longer open",
+                        pull_request_info.number,
+                    )?;
+                }
                 writeln!(
                     effects.get_output_stream(),
                     "Updating pull request ({updated_fields}) for commit {}",
@@ -488,14 +577,181 @@ impl Forge for GithubForge<'_> {
             }
         }
 
+        if any_landed_in_stack {
+            writeln!(
+                effects.get_output_stream(),
+                "To move your local commits on top of the commits which landed, run: {}",
+                effects
+                    .get_glyphs()
+                    .render(StyledString::styled("git sync --pull", Effect::Bold,))?
+            )?;
+        }
+
         Ok(Ok(()))
     }
+
+    #[instrument]
+    fn mark_ready(&mut self, commits: HashMap<NonZeroOid, CommitStatus>) -> EyreExitOr<()> {
+        let effects = self.effects;
+        let pull_request_infos =
+            try_exit_code!(self.client.query_repo_pull_request_infos(effects)?);
+        let commit_set: CommitSet = commits.keys().copied().collect();
+        for commit_oid in self.dag.sort(&commit_set)? {
+            let pull_request_info = match commits
+                .get(&commit_oid)
+                .and_then(|commit_status| commit_status.remote_commit_name.as_ref())
+                .and_then(|remote_branch_name| pull_request_infos.get(remote_branch_name))
+            {
+                Some(pull_request_info) if pull_request_info.is_draft => pull_request_info,
+                Some(_) | None => continue,
+            };
+            writeln!(
+                effects.get_output_stream(),
+                "Marking pull request ready for review for commit {}",
+                effects.get_glyphs().render(
+                    self.repo
+                        .friendly_describe_commit_from_oid(effects.get_glyphs(), commit_oid)?
+                )?
+            )?;
+            try_exit_code!(self
+                .client
+                .mark_pull_request_ready(effects, pull_request_info.number)?);
+        }
+        Ok(Ok(()))
+    }
+
+    #[instrument]
+    fn query_closed(&mut self, commit_set: CommitSet) -> EyreExitOr<HashMap<NonZeroOid, String>> {
+        let effects = self.effects;
+        let open_pull_request_infos =
+            try_exit_code!(self.client.query_repo_pull_request_infos(effects)?);
+        let closed_pull_request_infos =
+            try_exit_code!(self.client.query_closed_pull_request_infos(effects)?);
+
+        let mut result = HashMap::new();
+        for branch in self.repo.get_all_local_branches()? {
+            let local_branch_oid = match branch.get_oid()? {
+                Some(branch_oid) => branch_oid,
+                None => continue,
+            };
+            if !self.dag.set_contains(&commit_set, local_branch_oid)? {
+                continue;
+            }
+            let remote_branch_name =
+                match branch.get_upstream_branch_name_without_push_remote_name()? {
+                    Some(remote_branch_name) => remote_branch_name,
+                    None => continue,
+                };
+            // The branch may have been submitted again after its previous pull
+            // request was closed.
+            if let Some(pull_request_info) = open_pull_request_infos.get(&remote_branch_name) {
+                if !pull_request_info.closed {
+                    continue;
+                }
+            }
+            if let Some(pull_request_info) = closed_pull_request_infos.get(&remote_branch_name) {
+                result.insert(
+                    local_branch_oid,
+                    format!("pull request #{} was closed", pull_request_info.number),
+                );
+            }
+        }
+        Ok(Ok(result))
+    }
+
+    #[instrument]
+    fn query_urls(&mut self, commit_set: CommitSet) -> EyreExitOr<HashMap<NonZeroOid, String>> {
+        let effects = self.effects;
+        let pull_request_infos =
+            try_exit_code!(self.client.query_repo_pull_request_infos(effects)?);
+
+        let mut result = HashMap::new();
+        for branch in self.repo.get_all_local_branches()? {
+            let local_branch_oid = match branch.get_oid()? {
+                Some(branch_oid) => branch_oid,
+                None => continue,
+            };
+            if !self.dag.set_contains(&commit_set, local_branch_oid)? {
+                continue;
+            }
+            if let Some(pull_request_info) = branch
+                .get_upstream_branch_name_without_push_remote_name()?
+                .and_then(|remote_branch_name| pull_request_infos.get(&remote_branch_name))
+            {
+                result.insert(local_branch_oid, pull_request_info.url.clone());
+            }
+        }
+        Ok(Ok(result))
+    }
+
+    #[instrument]
+    fn query_review_statuses(
+        &mut self,
+        commit_set: CommitSet,
+    ) -> EyreExitOr<HashMap<NonZeroOid, ReviewStatus>> {
+        let effects = self.effects;
+        let open_pull_request_infos =
+            try_exit_code!(self.client.query_repo_pull_request_infos(effects)?);
+        let closed_pull_request_infos =
+            try_exit_code!(self.client.query_closed_pull_request_infos(effects)?);
+        let pull_request_checks = try_exit_code!(self.client.query_pull_request_checks(effects)?);
+
+        let mut result = HashMap::new();
+        for branch in self.repo.get_all_local_branches()? {
+            let local_branch_oid = match branch.get_oid()? {
+                Some(branch_oid) => branch_oid,
+                None => continue,
+            };
+            if !self.dag.set_contains(&commit_set, local_branch_oid)? {
+                continue;
+            }
+            let remote_branch_name =
+                match branch.get_upstream_branch_name_without_push_remote_name()? {
+                    Some(remote_branch_name) => remote_branch_name,
+                    None => continue,
+                };
+
+            let review_status = if let Some(pull_request_info) =
+                open_pull_request_infos.get(&remote_branch_name)
+            {
+                let checks = pull_request_checks.get(&remote_branch_name);
+                ReviewStatus {
+                    review_name: format!("#{}", pull_request_info.number),
+                    url: Some(pull_request_info.url.clone()),
+                    state: if pull_request_info.is_draft {
+                        ReviewState::Draft
+                    } else {
+                        ReviewState::Open
+                    },
+                    review_decision: checks.and_then(|checks| checks.describe_review_decision()),
+                    ci_status: checks.and_then(|checks| checks.summarize_checks()),
+                }
+            } else if let Some(pull_request_info) =
+                closed_pull_request_infos.get(&remote_branch_name)
+            {
+                ReviewStatus {
+                    review_name: format!("#{}", pull_request_info.number),
+                    url: Some(pull_request_info.url.clone()),
+                    state: ReviewState::Closed,
+                    review_decision: None,
+                    ci_status: None,
+                }
+            } else {
+                continue;
+            };
+            result.insert(local_branch_oid, review_status);
+        }
+        Ok(Ok(result))
+    }
 }
 
 impl GithubForge<'_> {
     /// Construct a real or mock GitHub client according to the environment.
-    pub fn client(git_run_info: GitRunInfo) -> Box<dyn client::GithubClient> {
-        match env::var(MOCK_REMOTE_REPO_PATH_ENV_KEY) {
+    pub fn client(
+        git_run_info: GitRunInfo,
+        repo: &Repo,
+    ) -> eyre::Result<Box<dyn client::GithubClient>> {
+        let client: Box<dyn client::GithubClient> = match env::var(MOCK_REMOTE_REPO_PATH_ENV_KEY) {
             Ok(path) => Box::new(client::MockGithubClient {
                 remote_repo_path: path.into(),
             }),
@@ -510,9 +766,14 @@ impl GithubForge<'_> {
                     working_directory: working_directory.clone(),
                     env: env.clone(),
                 };
-                Box::new(client::RealGithubClient { gh_run_info })
+                Box::new(client::RealGithubClient {
+                    gh_run_info,
+                    token: github_token(repo)?,
+                    retry_policy: RetryPolicy::from_repo(repo)?,
+                })
             }
-        }
+        };
+        Ok(client)
     }
 
     #[instrument]
@@ -639,10 +900,18 @@ impl GithubForge<'_> {
 
         let stack_list = {
             let mut result = String::new();
-            for stack_pull_request_info in stack_pull_request_infos.values() {
+            for (stack_commit_oid, stack_pull_request_info) in stack_pull_request_infos.iter() {
                 // Github will render a lone pull request URL as a title and
                 // open/closed status.
-                writeln!(result, "* {}", stack_pull_request_info.url)?;
+                if *stack_commit_oid == commit_oid {
+                    writeln!(
+                        result,
+                        "* {} \u{2190} you are here",
+                        stack_pull_request_info.url
+                    )?;
+                } else {
+                    writeln!(result, "* {}", stack_pull_request_info.url)?;
+                }
             }
             result
         };
@@ -653,16 +922,16 @@ impl GithubForge<'_> {
         let title = format!("[{stack_index}/{stack_size}] {commit_summary}");
         let commit_message = commit.get_message_pretty();
         let commit_message = String::from_utf8_lossy(&commit_message);
+        // The stack section is regenerated on every submit, so that it stays
+        // in sync as commits are reordered, added, or removed.
         let body = format!(
             "\
-**Stack:**
-
-{stack_list}
-
+{commit_message}
 ---
 
-{commit_message}
-"
+**Stack:**
+
+{stack_list}"
         );
 
         let stack_ancestor_oids = {
@@ -697,7 +966,7 @@ impl GithubForge<'_> {
     }
 }
 
-mod client {
+pub(crate) mod client {
     use std::collections::{BTreeMap, HashMap};
     use std::fmt::{Debug, Write};
     use std::fs::{self, File};
@@ -719,7 +988,8 @@ mod client {
     use tempfile::NamedTempFile;
     use tracing::{debug, instrument};
 
-    use crate::SubmitOptions;
+    use crate::retry::{run_with_retries, RetryPolicy};
+    use crate::{ReviewAssignment, SubmitOptions};
 
     #[derive(Clone, Debug, Deserialize, Serialize)]
     pub struct PullRequestInfo {
@@ -743,12 +1013,85 @@ mod client {
         pub body: String,
     }
 
+    /// The outcome of the reviews and CI checks for a pull request, as
+    /// reported by `gh pr list`.
+    #[derive(Clone, Debug, Deserialize, Serialize)]
+    pub struct PullRequestChecks {
+        #[serde(rename = "headRefName")]
+        pub head_ref_name: String,
+        #[serde(rename = "reviewDecision", default)]
+        pub review_decision: Option<String>,
+        #[serde(rename = "statusCheckRollup", default)]
+        pub status_check_rollup: Vec<CheckInfo>,
+    }
+
+    /// A single check run or commit status for a pull request. Check runs
+    /// report `status` and `conclusion`, while commit statuses report `state`.
+    #[derive(Clone, Debug, Deserialize, Serialize)]
+    pub struct CheckInfo {
+        #[serde(rename = "status", default)]
+        pub status: Option<String>,
+        #[serde(rename = "conclusion", default)]
+        pub conclusion: Option<String>,
+        #[serde(rename = "state", default)]
+        pub state: Option<String>,
+    }
+
+    impl PullRequestChecks {
+        /// Describe the review decision, such as `approved`, if any.
+        pub fn describe_review_decision(&self) -> Option<String> {
+            match self.review_decision.as_deref() {
+                None | Some("") => None,
+                Some("APPROVED") => Some("approved".to_string()),
+                Some("CHANGES_REQUESTED") => Some("changes requested".to_string()),
+                Some("REVIEW_REQUIRED") => Some("review required".to_string()),
+                Some(other) => Some(other.to_lowercase().replace('_', " ")),
+            }
+        }
+
+        /// Summarize the CI checks as `failing`, `pending`, or `passing`, or
+        /// `None` if there are no checks.
+        pub fn summarize_checks(&self) -> Option<String> {
+            if self.status_check_rollup.is_empty() {
+                return None;
+            }
+
+            let is_failing = |check: &CheckInfo| {
+                matches!(
+                    check.conclusion.as_deref(),
+                    Some(
+                        "FAILURE"
+                            | "CANCELLED"
+                            | "TIMED_OUT"
+                            | "ACTION_REQUIRED"
+                            | "STARTUP_FAILURE"
+                    )
+                ) || matches!(check.state.as_deref(), Some("FAILURE" | "ERROR"))
+            };
+            let is_pending = |check: &CheckInfo| match (&check.status, &check.state) {
+                (Some(status), _) => status != "COMPLETED",
+                (None, Some(state)) => matches!(state.as_str(), "PENDING" | "EXPECTED"),
+                (None, None) => false,
+            };
+
+            let summary = if self.status_check_rollup.iter().any(is_failing) {
+                "failing"
+            } else if self.status_check_rollup.iter().any(is_pending) {
+                "pending"
+            } else {
+                "passing"
+            };
+            Some(summary.to_string())
+        }
+    }
+
     #[derive(Debug)]
     pub struct CreatePullRequestArgs {
         pub head_ref_oid: NonZeroOid,
         pub head_ref_name: String,
         pub title: String,
         pub body: String,
+        pub review_assignment: ReviewAssignment,
     }
 
     #[derive(Debug, Eq, PartialEq)]
@@ -822,12 +1165,54 @@ mod client {
             args: UpdatePullRequestArgs,
             submit_options: &super::SubmitOptions,
         ) -> EyreExitOr<()>;
+
+        /// Take the given pull request out of draft mode.
+        fn mark_pull_request_ready(&self, effects: &Effects, number: usize) -> EyreExitOr<()>;
+
+        /// Get the details of all pull requests for the currently-logged-in
+        /// user in the current repository which were merged or closed. The
+        /// resulting map is keyed by remote branch name.
+        fn query_closed_pull_request_infos(
+            &self,
+            effects: &Effects,
+        ) -> EyreExitOr<HashMap<String, PullRequestInfo>>;
+
+        /// Get the review decision and CI checks of all open pull requests for
+        /// the currently-logged-in user in the current repository. The
+        /// resulting map is keyed by remote branch name.
+        fn query_pull_request_checks(
+            &self,
+            effects: &Effects,
+        ) -> EyreExitOr<HashMap<String, PullRequestChecks>>;
+
+        /// Get the details of the pull request with the given number, by any
+        /// author.
+        fn query_pull_request_info(
+            &self,
+            effects: &Effects,
+            number: usize,
+        ) -> EyreExitOr<PullRequestInfo>;
+
+        /// Get the details of the open pull request whose head is the given
+        /// remote branch, by any author, if there is one.
+        fn query_pull_request_info_for_branch(
+            &self,
+            effects: &Effects,
+            head_ref_name: &str,
+        ) -> EyreExitOr<Option<PullRequestInfo>>;
     }
 
     #[derive(Debug)]
     pub struct RealGithubClient {
         #[allow(dead_code)] // FIXME: destructure and use in `run_gh`?
         pub gh_run_info: GitRunInfo,
+
+        /// The token to pass to `gh` in the `GH_TOKEN` environment variable,
+        /// instead of using the credentials stored by `gh auth login`.
+        pub token: Option<String>,
+
+        /// How to retry `gh` invocations which were rate-limited.
+        pub retry_policy: RetryPolicy,
     }
 
     impl RealGithubClient {
@@ -840,16 +1225,23 @@ mod client {
                 effects.start_operation(OperationType::RunTests(Arc::new(exe_invocation.clone())));
             let _progress = progress;
 
-            let child = Command::new("gh")
-                .args(args)
-                .stdin(Stdio::piped())
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .spawn()
-                .context("Invoking `gh` command-line executable")?;
-            let output = child
-                .wait_with_output()
-                .context("Waiting for `gh` invocation")?;
+            let output = run_with_retries(&effects, &self.retry_policy, &exe_invocation, || {
+                let mut command = Command::new("gh");
+                command
+                    .args(args)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped());
+                if let Some(token) = &self.token {
+                    command.env("GH_TOKEN", token);
+                }
+                let child = command
+                    .spawn()
+                    .context("Invoking `gh` command-line executable")?;
+                child
+                    .wait_with_output()
+                    .context("Waiting for `gh` invocation")
+            })?;
             if !output.status.success() {
                 writeln!(
                     effects.get_output_stream(),
@@ -933,6 +1325,7 @@ mod client {
                 head_ref_name,
                 title,
                 body,
+                review_assignment: ReviewAssignment { reviewers, labels },
             } = args;
             let body_file = self.write_body_file(&body)?;
             let mut args = vec![
@@ -945,6 +1338,14 @@ mod client {
                 "--body-file",
                 body_file.path().to_str().unwrap(),
             ];
+            let reviewers = reviewers.join(",");
+            if !reviewers.is_empty() {
+                args.extend(["--reviewer", reviewers.as_str()]);
+            }
+            let labels = labels.join(",");
+            if !labels.is_empty() {
+                args.extend(["--label", labels.as_str()]);
+            }
 
             let SubmitOptions {
                 create: _,
@@ -1002,6 +1403,106 @@ mod client {
             )?);
             Ok(Ok(()))
         }
+
+        fn mark_pull_request_ready(&self, effects: &Effects, number: usize) -> EyreExitOr<()> {
+            try_exit_code!(self.run_gh(effects, &["pr", "ready", &number.to_string()])?);
+            Ok(Ok(()))
+        }
+
+        #[instrument]
+        fn query_closed_pull_request_infos(
+            &self,
+            effects: &Effects,
+        ) -> EyreExitOr<HashMap<String, PullRequestInfo>> {
+            let output = try_exit_code!(self.run_gh(
+                effects,
+                &[
+                    "pr",
+                    "list",
+                    "--author",
+                    "@me",
+                    "--state",
+                    "closed",
+                    "--json",
+                    "number,url,headRefName,headRefOid,baseRefName,closed,isDraft,title,body",
+                ]
+            )?);
+            let pull_request_infos: Vec<PullRequestInfo> =
+                serde_json::from_slice(&output).wrap_err("Deserializing output from gh pr list")?;
+            let pull_request_infos = pull_request_infos
+                .into_iter()
+                .map(|item| (item.head_ref_name.clone(), item))
+                .collect();
+            Ok(Ok(pull_request_infos))
+        }
+
+        #[instrument]
+        fn query_pull_request_checks(
+            &self,
+            effects: &Effects,
+        ) -> EyreExitOr<HashMap<String, PullRequestChecks>> {
+            let output = try_exit_code!(self.run_gh(
+                effects,
+                &[
+                    "pr",
+                    "list",
+                    "--author",
+                    "@me",
+                    "--json",
+                    "headRefName,reviewDecision,statusCheckRollup",
+                ]
+            )?);
+            let pull_request_checks: Vec<PullRequestChecks> =
+                serde_json::from_slice(&output).wrap_err("Deserializing output from gh pr list")?;
+            let pull_request_checks = pull_request_checks
+                .into_iter()
+                .map(|item| (item.head_ref_name.clone(), item))
+                .collect();
+            Ok(Ok(pull_request_checks))
+        }
+
+        #[instrument]
+        fn query_pull_request_info(
+            &self,
+            effects: &Effects,
+            number: usize,
+        ) -> EyreExitOr<PullRequestInfo> {
+            let output = try_exit_code!(self.run_gh(
+                effects,
+                &[
+                    "pr",
+                    "view",
+                    &number.to_string(),
+                    "--json",
+                    "number,url,headRefName,headRefOid,baseRefName,closed,isDraft,title,body",
+                ]
+            )?);
+            let pull_request_info: PullRequestInfo =
+                serde_json::from_slice(&output).wrap_err("Deserializing output from gh pr view")?;
+            Ok(Ok(pull_request_info))
+        }
+
+        #[instrument]
+        fn query_pull_request_info_for_branch(
+            &self,
+            effects: &Effects,
+            head_ref_name: &str,
+        ) -> EyreExitOr<Option<PullRequestInfo>> {
+            let output = try_exit_code!(self.run_gh(
+                effects,
+                &[
+                    "pr",
+                    "list",
+                    "--head",
+                    head_ref_name,
+                    "--json",
+                    "number,url,headRefName,headRefOid,baseRefName,closed,isDraft,title,body",
+                ]
+            )?);
+            let pull_request_infos: Vec<PullRequestInfo> =
+                serde_json::from_slice(&output).wrap_err("Deserializing output from gh pr list")?;
+            Ok(Ok(pull_request_infos.into_iter().next()))
+        }
     }
 
     /// The mock state on disk, representing the remote Github repository and
@@ -1014,6 +1515,11 @@ mod client {
         /// Information about all pull requests open for the repository. Sorted
         /// for determinism when dumping state for testing.
         pub pull_requests: BTreeMap<String, PullRequestInfo>,
+
+        /// The reviewers and labels assigned to each pull request when it was
+        /// created, keyed by pull request number.
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        pub review_assignments: BTreeMap<usize, ReviewAssignment>,
     }
 
     impl MockState {
@@ -1094,9 +1600,11 @@ mod client {
             _effects: &Effects,
         ) -> EyreExitOr<HashMap<String, PullRequestInfo>> {
             let pull_requests_infos = self.with_state_mut(|state| {
+                // Like `gh pr list`, only list open pull requests.
                 let pull_request_infos = state
                     .pull_requests
                     .values()
+                    .filter(|pull_request_info| !pull_request_info.closed)
                     .cloned()
                     .map(|pull_request_info| {
                         (pull_request_info.head_ref_name.clone(), pull_request_info)
@@ -1120,6 +1628,7 @@ mod client {
                     head_ref_name,
                     title,
                     body,
+                    review_assignment,
                 } = args;
                 let SubmitOptions {
                     create,
@@ -1147,6 +1656,11 @@ mod client {
                     body,
                 };
                 state.pull_requests.insert(head_ref_name, pull_request_info);
+                if !review_assignment.is_empty() {
+                    state
+                        .review_assignments
+                        .insert(state.pull_request_index, review_assignment);
+                }
                 Ok(url)
             })?;
             Ok(Ok(url))
@@ -1184,6 +1698,83 @@ mod client {
             })?;
             Ok(Ok(()))
         }
+
+        fn mark_pull_request_ready(&self, _effects: &Effects, number: usize) -> EyreExitOr<()> {
+            self.with_state_mut(|state| -> eyre::Result<()> {
+                match state
+                    .pull_requests
+                    .values_mut()
+                    .find(|pull_request_info| pull_request_info.number == number)
+                {
+                    Some(pull_request_info) => {
+                        pull_request_info.is_draft = false;
+                        Ok(())
+                    }
+                    None => {
+                        eyre::bail!("Could not find pull request with number {number}");
+                    }
+                }
+            })?;
+            Ok(Ok(()))
+        }
+
+        fn query_closed_pull_request_infos(
+            &self,
+            _effects: &Effects,
+        ) -> EyreExitOr<HashMap<String, PullRequestInfo>> {
+            let pull_requests_infos = self.with_state_mut(|state| {
+                let pull_request_infos = state
+                    .pull_requests
+                    .values()
+                    .filter(|pull_request_info| pull_request_info.closed)
+                    .cloned()
+                    .map(|pull_request_info| {
+                        (pull_request_info.head_ref_name.clone(), pull_request_info)
+                    })
+                    .collect();
+                Ok(pull_request_infos)
+            })?;
+            Ok(Ok(pull_requests_infos))
+        }
+
+        fn query_pull_request_checks(
+            &self,
+            _effects: &Effects,
+        ) -> EyreExitOr<HashMap<String, PullRequestChecks>> {
+            // The mock repository doesn't run any reviews or CI checks.
+            Ok(Ok(Default::default()))
+        }
+
+        fn query_pull_request_info(
+            &self,
+            _effects: &Effects,
+            number: usize,
+        ) -> EyreExitOr<PullRequestInfo> {
+            let pull_request_info = self.with_state_mut(|state| {
+                state
+                    .pull_requests
+                    .values()
+                    .find(|pull_request_info| pull_request_info.number == number)
+                    .cloned()
+                    .ok_or_else(|| eyre::eyre!("Could not find pull request #{number}"))
+            })?;
+            Ok(Ok(pull_request_info))
+        }
+
+        fn query_pull_request_info_for_branch(
+            &self,
+            _effects: &Effects,
+            head_ref_name: &str,
+        ) -> EyreExitOr<Option<PullRequestInfo>> {
+            let pull_request_info = self.with_state_mut(|state| {
+                Ok(state
+                    .pull_requests
+                    .get(head_ref_name)
+                    .filter(|pull_request_info| !pull_request_info.closed)
+                    .cloned())
+            })?;
+            Ok(Ok(pull_request_info))
+        }
     }
 
     impl MockGithubClient {
@@ -1242,4 +1833,65 @@ mod tests {
         assert_eq!(commit_summary_slug("foo_~_bar"), "foo-bar");
         assert_eq!(commit_summary_slug("!!!"), "to-review")
     }
+
+    #[test]
+    fn test_render_branch_template() {
+        assert_eq!(
+            render_branch_template(DEFAULT_BRANCH_TEMPLATE, "me", "foo-bar", Some("I123")),
+            "me/foo-bar"
+        );
+        assert_eq!(
+            render_branch_template("review/{user}/{changeid-or-slug}", "me", "foo-bar", None),
+            "review/me/foo-bar"
+        );
+        assert_eq!(
+            render_branch_template("{user}/{changeid-or-slug}", "me", "foo-bar", Some("I123")),
+            "me/I123"
+        );
+    }
+
+    #[test]
+    fn test_summarize_checks() {
+        let make_checks =
+            |checks: &[(Option<&str>, Option<&str>, Option<&str>)]| client::PullRequestChecks {
+                head_ref_name: "foo".to_string(),
+                review_decision: None,
+                status_check_rollup: checks
+                    .iter()
+                    .map(|(status, conclusion, state)| client::CheckInfo {
+                        status: status.map(ToString::to_string),
+                        conclusion: conclusion.map(ToString::to_string),
+                        state: state.map(ToString::to_string),
+                    })
+                    .collect(),
+            };
+        assert_eq!(make_checks(&[]).summarize_checks(), None);
+        assert_eq!(
+            make_checks(&[
+                (Some("COMPLETED"), Some("SUCCESS"), None),
+                (None, None, Some("SUCCESS")),
+            ])
+            .summarize_checks()
+            .as_deref(),
+            Some("passing")
+        );
+        assert_eq!(
+            make_checks(&[
+                (Some("IN_PROGRESS"), None, None),
+                (Some("COMPLETED"), Some("SUCCESS"), None),
+            ])
+            .summarize_checks()
+            .as_deref(),
+            Some("pending")
+        );
+        assert_eq!(
+            make_checks(&[
+                (Some("IN_PROGRESS"), None, None),
+                (None, None, Some("ERROR")),
+            ])
+            .summarize_checks()
+            .as_deref(),
+            Some("failing")
+        );
+    }
 }
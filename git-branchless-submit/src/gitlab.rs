@@ -0,0 +1,1153 @@
+//! GitLab backend for submitting patch stacks.
+
+use std::collections::HashMap;
+use std::env;
+use std::fmt::{Debug, Write};
+
+use cursive_core::theme::Effect;
+use cursive_core::utils::markup::StyledString;
+use itertools::Itertools;
+use lib::core::config::get_main_branch_name;
+use lib::core::dag::{CommitSet, Dag};
+use lib::core::effects::{Effects, OperationType};
+use lib::core::eventlog::EventLogDb;
+use lib::core::repo_ext::{RepoExt, RepoReferencesSnapshot};
+use lib::core::review_status::{ReviewState, ReviewStatus};
+use lib::git::{
+    BranchType, CategorizedReferenceName, ConfigRead, GitErrorCode, GitRunInfo, NonZeroOid, Repo,
+    RepoError,
+};
+use lib::try_exit_code;
+use lib::util::{ExitCode, EyreExitOr};
+use tracing::{debug, instrument, warn};
+
+use crate::branch_forge::BranchForge;
+use crate::github::{make_branch_name_base, singleton};
+use crate::retry::RetryPolicy;
+use crate::{CommitStatus, CreateStatus, Forge, ReviewAssignment, SubmitOptions, SubmitStatus};
+
+/// Testing environment variable. When this is set, the executable will use the
+/// mock GitLab implementation. This should be set to the path of an existing
+/// repository that represents the remote/GitLab.
+pub const MOCK_REMOTE_REPO_PATH_ENV_KEY: &str = "BRANCHLESS_SUBMIT_GITLAB_MOCK_REMOTE_REPO_PATH";
+
+/// Get the token to authenticate to GitLab with, if configured. If not set,
+/// the `glab` command-line utility uses the credentials from `glab auth login`.
+pub fn gitlab_token(repo: &Repo) -> eyre::Result<Option<String>> {
+    repo.get_readonly_config()?
+        .get::<String, _>("branchless.submit.gitlab.token")
+}
+
+/// The [GitLab](https://en.wikipedia.org/wiki/GitLab) code hosting platform.
+/// This forge integrates specifically with the `glab` command-line utility.
+///
+/// Each commit is pushed to its own branch, and a merge request is opened for
+/// each branch. The target branch of each merge request is the branch of the
+/// previous commit in the stack, so that each merge request only shows the
+/// changes of its own commit.
+#[allow(missing_docs)]
+#[derive(Debug)]
+pub struct GitlabForge<'a> {
+    pub effects: &'a Effects,
+    pub git_run_info: &'a GitRunInfo,
+    pub repo: &'a Repo,
+    pub event_log_db: &'a EventLogDb<'a>,
+    pub dag: &'a Dag,
+    pub client: Box<dyn client::GitlabClient>,
+}
+
+impl Forge for GitlabForge<'_> {
+    #[instrument]
+    fn query_status(
+        &mut self,
+        commit_set: CommitSet,
+    ) -> EyreExitOr<HashMap<NonZeroOid, CommitStatus>> {
+        let effects = self.effects;
+        let merge_request_infos =
+            try_exit_code!(self.client.query_repo_merge_request_infos(effects)?);
+        let references_snapshot = self.repo.get_references_snapshot()?;
+
+        let mut result = HashMap::new();
+        for branch in self.repo.get_all_local_branches()? {
+            let local_branch_oid = match branch.get_oid()? {
+                Some(branch_oid) => branch_oid,
+                None => continue,
+            };
+            if !self.dag.set_contains(&commit_set, local_branch_oid)? {
+                continue;
+            }
+
+            let local_branch_name = branch.get_name()?;
+            let remote_name = branch.get_push_remote_name()?;
+            let remote_branch_name = branch.get_upstream_branch_name_without_push_remote_name()?;
+
+            let submit_status = match remote_branch_name
+                .as_ref()
+                .and_then(|remote_branch_name| merge_request_infos.get(remote_branch_name))
+            {
+                None => SubmitStatus::Unsubmitted,
+                Some(merge_request_info) => {
+                    let updated_merge_request_info = self.make_updated_merge_request_info(
+                        &references_snapshot,
+                        &merge_request_infos,
+                        local_branch_oid,
+                    )?;
+                    if updated_merge_request_info
+                        .fields_to_update(merge_request_info)
+                        .is_empty()
+                    {
+                        SubmitStatus::UpToDate
+                    } else {
+                        SubmitStatus::NeedsUpdate
+                    }
+                }
+            };
+            result.insert(
+                local_branch_oid,
+                CommitStatus {
+                    submit_status,
+                    remote_name,
+                    local_commit_name: Some(local_branch_name.to_owned()),
+                    remote_commit_name: remote_branch_name,
+                },
+            );
+        }
+
+        for commit_oid in self.dag.commit_set_to_vec(&commit_set)? {
+            result.entry(commit_oid).or_insert(CommitStatus {
+                submit_status: SubmitStatus::Unsubmitted,
+                remote_name: None,
+                local_commit_name: None,
+                remote_commit_name: None,
+            });
+        }
+
+        Ok(Ok(result))
+    }
+
+    #[instrument]
+    fn create(
+        &mut self,
+        commits: HashMap<NonZeroOid, CommitStatus>,
+        options: &SubmitOptions,
+    ) -> EyreExitOr<HashMap<NonZeroOid, CreateStatus>> {
+        let effects = self.effects;
+        let commit_oids = self.dag.sort(&commits.keys().copied().collect())?;
+
+        let references_snapshot = self.repo.get_references_snapshot()?;
+        let mut branch_forge = BranchForge {
+            effects,
+            git_run_info: self.git_run_info,
+            dag: self.dag,
+            repo: self.repo,
+            event_log_db: self.event_log_db,
+            references_snapshot: &references_snapshot,
+        };
+        let push_remote_name = match self.repo.get_default_push_remote()? {
+            Some(remote_name) => remote_name,
+            None => {
+                writeln!(
+                    effects.get_output_stream(),
+                    "No default push repository configured. To configure, run: {}",
+                    effects.get_glyphs().render(StyledString::styled(
+                        "git config remote.pushDefault <remote>",
+                        Effect::Bold,
+                    ))?
+                )?;
+                return Ok(Err(ExitCode(1)));
+            }
+        };
+        let gitlab_username = try_exit_code!(self.client.query_gitlab_username(effects)?);
+
+        let commits_to_create = commit_oids
+            .into_iter()
+            .map(|commit_oid| (commit_oid, commits.get(&commit_oid).unwrap()))
+            .filter(|(_commit_oid, commit_status)| {
+                matches!(commit_status.submit_status, SubmitStatus::Unsubmitted)
+            })
+            .collect_vec();
+        let mut created_branches = HashMap::new();
+        for (commit_oid, commit_status) in commits_to_create.iter().copied() {
+            let commit = self.repo.find_commit_or_fail(commit_oid)?;
+            let local_branch_name = match &commit_status.local_commit_name {
+                Some(local_branch_name) => local_branch_name.clone(),
+                None => {
+                    let new_branch_name_base =
+                        make_branch_name_base(self.repo, &commit, &gitlab_username)?;
+                    let mut new_branch_name = new_branch_name_base.clone();
+                    for i in 2.. {
+                        if i > 6 {
+                            writeln!(
+                                effects.get_output_stream(),
+                                "Could not generate fresh branch name for commit: {}",
+                                effects
+                                    .get_glyphs()
+                                    .render(commit.friendly_describe(effects.get_glyphs())?)?,
+                            )?;
+                            return Ok(Err(ExitCode(1)));
+                        }
+                        match self.repo.find_branch(&new_branch_name, BranchType::Local)? {
+                            Some(_) => {
+                                new_branch_name = format!("{new_branch_name_base}-{i}");
+                            }
+                            None => break,
+                        }
+                    }
+                    match self.repo.create_branch(&new_branch_name, &commit, false) {
+                        Ok(_branch) => {}
+                        Err(RepoError::CreateBranch { source, name: _ })
+                            if source.code() == GitErrorCode::Exists => {}
+                        Err(err) => return Err(err.into()),
+                    };
+                    new_branch_name
+                }
+            };
+
+            let created_branch = try_exit_code!(branch_forge.create(
+                singleton(&commits, commit_oid, |commit_status| CommitStatus {
+                    local_commit_name: Some(local_branch_name.clone()),
+                    ..commit_status.clone()
+                }),
+                options
+            )?);
+            created_branches.extend(created_branch);
+        }
+
+        // Create all the merge requests against the main branch first, and
+        // then retarget them with `update`, since the branch of the previous
+        // commit in the stack might not have had a merge request yet.
+        let mut commit_statuses = HashMap::new();
+        for (commit_oid, commit_status) in commits_to_create {
+            let local_branch_name = match created_branches.get(&commit_oid) {
+                Some(CreateStatus {
+                    final_commit_oid: _,
+                    local_commit_name,
+                }) => local_commit_name.clone(),
+                None => {
+                    commit_statuses.insert(commit_oid, commit_status.clone());
+                    continue;
+                }
+            };
+
+            let commit = self.repo.find_commit_or_fail(commit_oid)?;
+            let title = String::from_utf8_lossy(&commit.get_summary()?).into_owned();
+            let description = String::from_utf8_lossy(&commit.get_message_pretty()).into_owned();
+            try_exit_code!(self.client.create_merge_request(
+                effects,
+                client::CreateMergeRequestArgs {
+                    source_branch: local_branch_name.clone(),
+                    sha: commit_oid,
+                    target_branch: get_main_branch_name(self.repo)?,
+                    title,
+                    description,
+                    review_assignment: ReviewAssignment::for_commit(self.repo, &commit)?,
+                },
+                options
+            )?);
+            commit_statuses.insert(
+                commit_oid,
+                CommitStatus {
+                    // To be updated below:
+                    submit_status: SubmitStatus::NeedsUpdate,
+                    remote_name: Some(push_remote_name.clone()),
+                    local_commit_name: Some(local_branch_name.clone()),
+                    // Expecting this to be the same as the local branch name (for now):
+                    remote_commit_name: Some(local_branch_name),
+                },
+            );
+        }
+
+        try_exit_code!(self.update(commit_statuses, options)?);
+
+        Ok(Ok(created_branches))
+    }
+
+    #[instrument]
+    fn update(
+        &mut self,
+        commit_statuses: HashMap<NonZeroOid, CommitStatus>,
+        options: &SubmitOptions,
+    ) -> EyreExitOr<()> {
+        let effects = self.effects;
+        let merge_request_infos =
+            try_exit_code!(self.client.query_repo_merge_request_infos(effects)?);
+        let references_snapshot = self.repo.get_references_snapshot()?;
+        let mut branch_forge = BranchForge {
+            effects,
+            git_run_info: self.git_run_info,
+            dag: self.dag,
+            repo: self.repo,
+            event_log_db: self.event_log_db,
+            references_snapshot: &references_snapshot,
+        };
+
+        let commit_set: CommitSet = commit_statuses.keys().copied().collect();
+        let commit_oids = self.dag.sort(&commit_set)?;
+        let (effects, progress) = effects.start_operation(OperationType::UpdateCommits);
+        progress.notify_progress(0, commit_oids.len());
+        for commit_oid in commit_oids {
+            let merge_request_info = match commit_statuses
+                .get(&commit_oid)
+                .and_then(|commit_status| commit_status.remote_commit_name.as_ref())
+                .and_then(|remote_branch_name| merge_request_infos.get(remote_branch_name))
+            {
+                Some(merge_request_info) => merge_request_info,
+                None => {
+                    warn!(
+                        ?commit_oid,
+                        ?commit_statuses,
+                        "Commit does not have merge request"
+                    );
+                    continue;
+                }
+            };
+
+            let updated_merge_request_info = self.make_updated_merge_request_info(
+                &references_snapshot,
+                &merge_request_infos,
+                commit_oid,
+            )?;
+            let updated_fields = updated_merge_request_info.fields_to_update(merge_request_info);
+            if updated_fields.is_empty() {
+                progress.notify_progress_inc(1);
+                continue;
+            }
+            writeln!(
+                effects.get_output_stream(),
+                "Updating merge request ({}) for commit {}",
+                updated_fields.join(", "),
+                effects.get_glyphs().render(
+                    self.repo
+                        .find_commit_or_fail(commit_oid)?
+                        .friendly_describe(effects.get_glyphs())?
+                )?
+            )?;
+
+            // Push the branch before retargeting the merge request, so that
+            // GitLab never sees the merge request with its new target branch
+            // but its old commits.
+            try_exit_code!(
+                branch_forge.update(singleton(&commit_statuses, commit_oid, |x| x), options)?
+            );
+            try_exit_code!(self.client.update_merge_request(
+                &effects,
+                merge_request_info,
+                updated_merge_request_info,
+            )?);
+            progress.notify_progress_inc(1);
+        }
+
+        Ok(Ok(()))
+    }
+
+    #[instrument]
+    fn mark_ready(&mut self, commits: HashMap<NonZeroOid, CommitStatus>) -> EyreExitOr<()> {
+        let effects = self.effects;
+        let merge_request_infos =
+            try_exit_code!(self.client.query_repo_merge_request_infos(effects)?);
+        let commit_set: CommitSet = commits.keys().copied().collect();
+        for commit_oid in self.dag.sort(&commit_set)? {
+            let merge_request_info = match commits
+                .get(&commit_oid)
+                .and_then(|commit_status| commit_status.remote_commit_name.as_ref())
+                .and_then(|remote_branch_name| merge_request_infos.get(remote_branch_name))
+            {
+                Some(merge_request_info) if merge_request_info.draft => merge_request_info,
+                Some(_) | None => continue,
+            };
+            writeln!(
+                effects.get_output_stream(),
+                "Marking merge request ready for review for commit {}",
+                effects.get_glyphs().render(
+                    self.repo
+                        .friendly_describe_commit_from_oid(effects.get_glyphs(), commit_oid)?
+                )?
+            )?;
+            try_exit_code!(self
+                .client
+                .mark_merge_request_ready(effects, merge_request_info.iid)?);
+        }
+        Ok(Ok(()))
+    }
+
+    #[instrument]
+    fn query_closed(&mut self, commit_set: CommitSet) -> EyreExitOr<HashMap<NonZeroOid, String>> {
+        let effects = self.effects;
+        let open_merge_request_infos =
+            try_exit_code!(self.client.query_repo_merge_request_infos(effects)?);
+        let closed_merge_request_infos =
+            try_exit_code!(self.client.query_closed_merge_request_infos(effects)?);
+
+        let mut result = HashMap::new();
+        for branch in self.repo.get_all_local_branches()? {
+            let local_branch_oid = match branch.get_oid()? {
+                Some(branch_oid) => branch_oid,
+                None => continue,
+            };
+            if !self.dag.set_contains(&commit_set, local_branch_oid)? {
+                continue;
+            }
+            let remote_branch_name =
+                match branch.get_upstream_branch_name_without_push_remote_name()? {
+                    Some(remote_branch_name) => remote_branch_name,
+                    None => continue,
+                };
+            // The branch may have been submitted again after its previous
+            // merge request was closed.
+            if open_merge_request_infos.contains_key(&remote_branch_name) {
+                continue;
+            }
+            if let Some((merge_request_info, state)) =
+                closed_merge_request_infos.get(&remote_branch_name)
+            {
+                let verb = match state {
+                    client::MergeRequestState::Merged => "merged",
+                    client::MergeRequestState::Closed => "closed",
+                };
+                result.insert(
+                    local_branch_oid,
+                    format!("merge request !{} was {verb}", merge_request_info.iid),
+                );
+            }
+        }
+        Ok(Ok(result))
+    }
+
+    #[instrument]
+    fn query_urls(&mut self, commit_set: CommitSet) -> EyreExitOr<HashMap<NonZeroOid, String>> {
+        let effects = self.effects;
+        let merge_request_infos =
+            try_exit_code!(self.client.query_repo_merge_request_infos(effects)?);
+
+        let mut result = HashMap::new();
+        for branch in self.repo.get_all_local_branches()? {
+            let local_branch_oid = match branch.get_oid()? {
+                Some(branch_oid) => branch_oid,
+                None => continue,
+            };
+            if !self.dag.set_contains(&commit_set, local_branch_oid)? {
+                continue;
+            }
+            if let Some(merge_request_info) = branch
+                .get_upstream_branch_name_without_push_remote_name()?
+                .and_then(|remote_branch_name| merge_request_infos.get(&remote_branch_name))
+            {
+                result.insert(local_branch_oid, merge_request_info.web_url.clone());
+            }
+        }
+        Ok(Ok(result))
+    }
+
+    #[instrument]
+    fn query_review_statuses(
+        &mut self,
+        commit_set: CommitSet,
+    ) -> EyreExitOr<HashMap<NonZeroOid, ReviewStatus>> {
+        let effects = self.effects;
+        let open_merge_request_infos =
+            try_exit_code!(self.client.query_repo_merge_request_infos(effects)?);
+        let closed_merge_request_infos =
+            try_exit_code!(self.client.query_closed_merge_request_infos(effects)?);
+
+        let mut result = HashMap::new();
+        for branch in self.repo.get_all_local_branches()? {
+            let local_branch_oid = match branch.get_oid()? {
+                Some(branch_oid) => branch_oid,
+                None => continue,
+            };
+            if !self.dag.set_contains(&commit_set, local_branch_oid)? {
+                continue;
+            }
+            let remote_branch_name =
+                match branch.get_upstream_branch_name_without_push_remote_name()? {
+                    Some(remote_branch_name) => remote_branch_name,
+                    None => continue,
+                };
+
+            let (merge_request_info, state) = if let Some(merge_request_info) =
+                open_merge_request_infos.get(&remote_branch_name)
+            {
+                let state = if merge_request_info.draft {
+                    ReviewState::Draft
+                } else {
+                    ReviewState::Open
+                };
+                (merge_request_info, state)
+            } else if let Some((merge_request_info, state)) =
+                closed_merge_request_infos.get(&remote_branch_name)
+            {
+                let state = match state {
+                    client::MergeRequestState::Merged => ReviewState::Merged,
+                    client::MergeRequestState::Closed => ReviewState::Closed,
+                };
+                (merge_request_info, state)
+            } else {
+                continue;
+            };
+            result.insert(
+                local_branch_oid,
+                ReviewStatus {
+                    review_name: format!("!{}", merge_request_info.iid),
+                    url: Some(merge_request_info.web_url.clone()),
+                    state,
+                    review_decision: None,
+                    ci_status: None,
+                },
+            );
+        }
+        Ok(Ok(result))
+    }
+}
+
+impl GitlabForge<'_> {
+    /// Construct a real or mock GitLab client according to the environment.
+    pub fn client(
+        git_run_info: GitRunInfo,
+        repo: &Repo,
+    ) -> eyre::Result<Box<dyn client::GitlabClient>> {
+        let client: Box<dyn client::GitlabClient> = match env::var(MOCK_REMOTE_REPO_PATH_ENV_KEY) {
+            Ok(path) => Box::new(client::MockGitlabClient {
+                remote_repo_path: path.into(),
+            }),
+            Err(_) => Box::new(client::RealGitlabClient {
+                working_directory: git_run_info.working_directory,
+                token: gitlab_token(repo)?,
+                retry_policy: RetryPolicy::from_repo(repo)?,
+            }),
+        };
+        Ok(client)
+    }
+
+    /// Get the merge request metadata that the given commit should have: its
+    /// title and description come from its commit message, and its target
+    /// branch is the source branch of the merge request for the nearest
+    /// ancestor in the stack, or the main branch if there is none.
+    #[instrument]
+    fn make_updated_merge_request_info(
+        &self,
+        references_snapshot: &RepoReferencesSnapshot,
+        merge_request_infos: &HashMap<String, client::MergeRequestInfo>,
+        commit_oid: NonZeroOid,
+    ) -> eyre::Result<client::UpdateMergeRequestArgs> {
+        let get_merge_request_info =
+            |commit_oid: NonZeroOid| -> eyre::Result<Option<&client::MergeRequestInfo>> {
+                let branch_names = match references_snapshot.branch_oid_to_names.get(&commit_oid) {
+                    Some(branch_names) => branch_names,
+                    None => return Ok(None),
+                };
+                for branch_name in branch_names.iter().sorted() {
+                    let local_branch = match self.repo.find_branch(
+                        &CategorizedReferenceName::new(branch_name).render_suffix(),
+                        BranchType::Local,
+                    )? {
+                        Some(local_branch) => local_branch,
+                        None => continue,
+                    };
+                    if let Some(merge_request_info) = local_branch
+                        .get_upstream_branch_name_without_push_remote_name()?
+                        .and_then(|remote_branch_name| merge_request_infos.get(&remote_branch_name))
+                    {
+                        return Ok(Some(merge_request_info));
+                    }
+                }
+                Ok(None)
+            };
+
+        let stack_ancestor_oids = {
+            let main_branch_oid = CommitSet::from(references_snapshot.main_branch_oid);
+            let stack_ancestor_oids = self
+                .dag
+                .query_only(CommitSet::from(commit_oid), main_branch_oid)?
+                .difference(&CommitSet::from(commit_oid));
+            self.dag.commit_set_to_vec(&stack_ancestor_oids)?
+        };
+        let mut target_branch = None;
+        for stack_ancestor_oid in stack_ancestor_oids.into_iter().rev() {
+            if let Some(merge_request_info) = get_merge_request_info(stack_ancestor_oid)? {
+                debug!(
+                    ?commit_oid,
+                    ?merge_request_info,
+                    "Found merge request of nearest ancestor"
+                );
+                target_branch = Some(merge_request_info.source_branch.clone());
+                break;
+            }
+        }
+        let target_branch = match target_branch {
+            Some(target_branch) => target_branch,
+            None => get_main_branch_name(self.repo)?,
+        };
+
+        let commit = self.repo.find_commit_or_fail(commit_oid)?;
+        let title = String::from_utf8_lossy(&commit.get_summary()?).into_owned();
+        let description = String::from_utf8_lossy(&commit.get_message_pretty()).into_owned();
+        Ok(client::UpdateMergeRequestArgs {
+            sha: commit_oid,
+            target_branch,
+            title,
+            description,
+        })
+    }
+}
+
+mod client {
+    use std::collections::{BTreeMap, HashMap};
+    use std::fmt::{Debug, Write};
+    use std::fs::{self, File};
+    use std::path::{Path, PathBuf};
+    use std::process::{Command, Stdio};
+    use std::sync::Arc;
+
+    use eyre::Context;
+    use lib::core::effects::{Effects, OperationType};
+    use lib::git::{NonZeroOid, SerializedNonZeroOid};
+    use lib::try_exit_code;
+    use lib::util::{ExitCode, EyreExitOr};
+    use serde::{Deserialize, Serialize};
+    use tracing::{debug, instrument};
+
+    use crate::retry::{run_with_retries, RetryPolicy};
+    use crate::{ReviewAssignment, SubmitOptions};
+
+    /// GitLab prefixes the titles of draft merge requests with this string.
+    const DRAFT_TITLE_PREFIX: &str = "Draft: ";
+
+    #[derive(Clone, Debug, Deserialize, Serialize)]
+    pub struct MergeRequestInfo {
+        pub iid: usize,
+        pub web_url: String,
+        pub source_branch: String,
+        pub target_branch: String,
+        pub sha: SerializedNonZeroOid,
+        pub draft: bool,
+        pub title: String,
+        pub description: String,
+    }
+
+    /// How a merge request which is no longer open was closed.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub enum MergeRequestState {
+        Merged,
+        Closed,
+    }
+
+    #[derive(Debug)]
+    pub struct CreateMergeRequestArgs {
+        pub source_branch: String,
+        pub sha: NonZeroOid,
+        pub target_branch: String,
+        pub title: String,
+        pub description: String,
+        pub review_assignment: ReviewAssignment,
+    }
+
+    #[derive(Debug, Eq, PartialEq)]
+    pub struct UpdateMergeRequestArgs {
+        pub sha: NonZeroOid,
+        pub target_branch: String,
+        pub title: String,
+        pub description: String,
+    }
+
+    impl UpdateMergeRequestArgs {
+        pub fn fields_to_update(&self, merge_request_info: &MergeRequestInfo) -> Vec<&'static str> {
+            let MergeRequestInfo {
+                iid: _,
+                web_url: _,
+                source_branch: _,
+                target_branch: old_target_branch,
+                sha: SerializedNonZeroOid(old_sha),
+                draft: _,
+                title: old_title,
+                description: old_description,
+            } = merge_request_info;
+            let Self {
+                sha: new_sha,
+                target_branch: new_target_branch,
+                title: new_title,
+                description: new_description,
+            } = self;
+
+            let mut updated_fields = Vec::new();
+            if old_sha != new_sha {
+                updated_fields.push("commit");
+            }
+            if old_target_branch != new_target_branch {
+                updated_fields.push("target branch");
+            }
+            if old_title != new_title {
+                updated_fields.push("title");
+            }
+            if old_description != new_description {
+                updated_fields.push("description");
+            }
+            updated_fields
+        }
+    }
+
+    pub trait GitlabClient: Debug {
+        /// Get the username of the currently-logged-in user.
+        fn query_gitlab_username(&self, effects: &Effects) -> EyreExitOr<String>;
+
+        /// Get the details of all open merge requests for the
+        /// currently-logged-in user in the current project. The resulting map
+        /// is keyed by source branch name. Titles are returned without the
+        /// draft prefix.
+        fn query_repo_merge_request_infos(
+            &self,
+            effects: &Effects,
+        ) -> EyreExitOr<HashMap<String, MergeRequestInfo>>;
+
+        fn create_merge_request(
+            &self,
+            effects: &Effects,
+            args: CreateMergeRequestArgs,
+            submit_options: &SubmitOptions,
+        ) -> EyreExitOr<String>;
+
+        /// Update the metadata of a merge request. Its draft status is
+        /// preserved.
+        fn update_merge_request(
+            &self,
+            effects: &Effects,
+            merge_request_info: &MergeRequestInfo,
+            args: UpdateMergeRequestArgs,
+        ) -> EyreExitOr<()>;
+
+        /// Take the given merge request out of draft mode.
+        fn mark_merge_request_ready(&self, effects: &Effects, iid: usize) -> EyreExitOr<()>;
+
+        /// Get the details of all merge requests for the currently-logged-in
+        /// user in the current project which were merged or closed. The
+        /// resulting map is keyed by source branch name.
+        fn query_closed_merge_request_infos(
+            &self,
+            effects: &Effects,
+        ) -> EyreExitOr<HashMap<String, (MergeRequestInfo, MergeRequestState)>>;
+    }
+
+    #[derive(Debug)]
+    pub struct RealGitlabClient {
+        /// The directory to run `glab` in, so that it can infer the project
+        /// from the repository's remotes.
+        pub working_directory: PathBuf,
+
+        /// The token to pass to `glab` in the `GITLAB_TOKEN` environment
+        /// variable, instead of using the credentials stored by `glab auth
+        /// login`.
+        pub token: Option<String>,
+
+        /// How to retry `glab` invocations which were rate-limited.
+        pub retry_policy: RetryPolicy,
+    }
+
+    impl RealGitlabClient {
+        #[instrument]
+        fn run_glab(&self, effects: &Effects, args: &[&str]) -> EyreExitOr<Vec<u8>> {
+            let exe = "glab";
+            let exe_invocation = format!("{exe} {}", args.join(" "));
+            debug!(?exe_invocation, "Invoking glab");
+            let (effects, progress) =
+                effects.start_operation(OperationType::RunTests(Arc::new(exe_invocation.clone())));
+            let _progress = progress;
+
+            let output = run_with_retries(&effects, &self.retry_policy, &exe_invocation, || {
+                let mut command = Command::new(exe);
+                command
+                    .args(args)
+                    .current_dir(&self.working_directory)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped());
+                if let Some(token) = &self.token {
+                    command.env("GITLAB_TOKEN", token);
+                }
+                let child = command
+                    .spawn()
+                    .context("Invoking `glab` command-line executable")?;
+                child
+                    .wait_with_output()
+                    .context("Waiting for `glab` invocation")
+            })?;
+            if !output.status.success() {
+                writeln!(
+                    effects.get_output_stream(),
+                    "Call to `{exe_invocation}` failed",
+                )?;
+                writeln!(effects.get_output_stream(), "Stdout:")?;
+                writeln!(
+                    effects.get_output_stream(),
+                    "{}",
+                    String::from_utf8_lossy(&output.stdout)
+                )?;
+                writeln!(effects.get_output_stream(), "Stderr:")?;
+                writeln!(
+                    effects.get_output_stream(),
+                    "{}",
+                    String::from_utf8_lossy(&output.stderr)
+                )?;
+                return Ok(Err(ExitCode::try_from(output.status)?));
+            }
+            Ok(Ok(output.stdout))
+        }
+    }
+
+    impl GitlabClient for RealGitlabClient {
+        #[instrument]
+        fn query_gitlab_username(&self, effects: &Effects) -> EyreExitOr<String> {
+            #[derive(Deserialize)]
+            struct User {
+                username: String,
+            }
+            let output = try_exit_code!(self.run_glab(effects, &["api", "user"])?);
+            let user: User =
+                serde_json::from_slice(&output).wrap_err("Deserializing output from glab api")?;
+            Ok(Ok(user.username))
+        }
+
+        #[instrument]
+        fn query_repo_merge_request_infos(
+            &self,
+            effects: &Effects,
+        ) -> EyreExitOr<HashMap<String, MergeRequestInfo>> {
+            let output = try_exit_code!(self.run_glab(
+                effects,
+                &["mr", "list", "--author", "@me", "--output", "json"]
+            )?);
+            let merge_request_infos: Vec<MergeRequestInfo> = serde_json::from_slice(&output)
+                .wrap_err("Deserializing output from glab mr list")?;
+            let merge_request_infos = merge_request_infos
+                .into_iter()
+                .map(|mut item| {
+                    if let Some(title) = item.title.strip_prefix(DRAFT_TITLE_PREFIX) {
+                        item.title = title.to_owned();
+                    }
+                    (item.source_branch.clone(), item)
+                })
+                .collect();
+            Ok(Ok(merge_request_infos))
+        }
+
+        #[instrument]
+        fn create_merge_request(
+            &self,
+            effects: &Effects,
+            args: CreateMergeRequestArgs,
+            submit_options: &SubmitOptions,
+        ) -> EyreExitOr<String> {
+            let CreateMergeRequestArgs {
+                source_branch,
+                sha: _,
+                target_branch,
+                title,
+                description,
+                review_assignment: ReviewAssignment { reviewers, labels },
+            } = args;
+            let mut args = vec![
+                "mr",
+                "create",
+                "--yes",
+                "--source-branch",
+                &source_branch,
+                "--target-branch",
+                &target_branch,
+                "--title",
+                &title,
+                "--description",
+                &description,
+            ];
+            if submit_options.draft {
+                args.push("--draft");
+            }
+            let reviewers = reviewers.join(",");
+            if !reviewers.is_empty() {
+                args.extend(["--reviewer", reviewers.as_str()]);
+            }
+            let labels = labels.join(",");
+            if !labels.is_empty() {
+                args.extend(["--label", labels.as_str()]);
+            }
+
+            let stdout = try_exit_code!(self.run_glab(effects, &args)?);
+            // `glab mr create` prints the URL of the merge request on the last
+            // line of its output.
+            let stdout = String::from_utf8_lossy(&stdout);
+            let merge_request_url = stdout.lines().last().unwrap_or_default().trim();
+            Ok(Ok(merge_request_url.to_owned()))
+        }
+
+        #[instrument]
+        fn update_merge_request(
+            &self,
+            effects: &Effects,
+            merge_request_info: &MergeRequestInfo,
+            args: UpdateMergeRequestArgs,
+        ) -> EyreExitOr<()> {
+            let UpdateMergeRequestArgs {
+                sha: _, // branch should have been pushed by caller
+                target_branch,
+                title,
+                description,
+            } = args;
+            let iid = merge_request_info.iid.to_string();
+            let mut args = vec![
+                "mr",
+                "update",
+                &iid,
+                "--yes",
+                "--target-branch",
+                &target_branch,
+                "--title",
+                &title,
+                "--description",
+                &description,
+            ];
+            // Setting the title without the draft prefix would otherwise mark
+            // the merge request as ready.
+            if merge_request_info.draft {
+                args.push("--draft");
+            }
+            try_exit_code!(self.run_glab(effects, &args)?);
+            Ok(Ok(()))
+        }
+
+        #[instrument]
+        fn mark_merge_request_ready(&self, effects: &Effects, iid: usize) -> EyreExitOr<()> {
+            try_exit_code!(self.run_glab(
+                effects,
+                &["mr", "update", &iid.to_string(), "--yes", "--ready"]
+            )?);
+            Ok(Ok(()))
+        }
+
+        #[instrument]
+        fn query_closed_merge_request_infos(
+            &self,
+            effects: &Effects,
+        ) -> EyreExitOr<HashMap<String, (MergeRequestInfo, MergeRequestState)>> {
+            let mut result = HashMap::new();
+            for (flag, state) in [
+                ("--merged", MergeRequestState::Merged),
+                ("--closed", MergeRequestState::Closed),
+            ] {
+                let output = try_exit_code!(self.run_glab(
+                    effects,
+                    &["mr", "list", "--author", "@me", flag, "--output", "json"]
+                )?);
+                let merge_request_infos: Vec<MergeRequestInfo> = serde_json::from_slice(&output)
+                    .wrap_err("Deserializing output from glab mr list")?;
+                result.extend(
+                    merge_request_infos
+                        .into_iter()
+                        .map(|item| (item.source_branch.clone(), (item, state))),
+                );
+            }
+            Ok(Ok(result))
+        }
+    }
+
+    /// The mock state on disk, representing the remote GitLab project and
+    /// server.
+    #[derive(Debug, Default, Deserialize, Serialize)]
+    pub struct MockState {
+        /// The next IID to assign a newly-created merge request.
+        pub merge_request_index: usize,
+
+        /// Information about all merge requests open for the project, keyed by
+        /// source branch. Sorted for determinism when dumping state for
+        /// testing.
+        pub merge_requests: BTreeMap<String, MergeRequestInfo>,
+
+        /// The reviewers and labels assigned to each merge request when it was
+        /// created, keyed by IID.
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        pub review_assignments: BTreeMap<usize, ReviewAssignment>,
+    }
+
+    impl MockState {
+        fn load(path: &Path) -> eyre::Result<Self> {
+            let file = match File::open(path) {
+                Ok(file) => file,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                    return Ok(Default::default());
+                }
+                Err(err) => return Err(err).wrap_err("Opening mock GitLab client state file"),
+            };
+            let state = serde_json::from_reader(file)?;
+            Ok(state)
+        }
+
+        fn save(&self, path: &Path) -> eyre::Result<()> {
+            let state = serde_json::to_string_pretty(self)?;
+            fs::write(path, state)?;
+            Ok(())
+        }
+    }
+
+    /// A mock client representing the remote GitLab project and server.
+    #[derive(Debug)]
+    pub struct MockGitlabClient {
+        /// The path to the remote repository on disk.
+        pub remote_repo_path: PathBuf,
+    }
+
+    impl GitlabClient for MockGitlabClient {
+        fn query_gitlab_username(&self, _effects: &Effects) -> EyreExitOr<String> {
+            Ok(Ok(Self::username().to_owned()))
+        }
+
+        fn query_repo_merge_request_infos(
+            &self,
+            _effects: &Effects,
+        ) -> EyreExitOr<HashMap<String, MergeRequestInfo>> {
+            let merge_request_infos = self.with_state_mut(|state| {
+                Ok(state
+                    .merge_requests
+                    .iter()
+                    .map(|(source_branch, merge_request_info)| {
+                        (source_branch.clone(), merge_request_info.clone())
+                    })
+                    .collect())
+            })?;
+            Ok(Ok(merge_request_infos))
+        }
+
+        fn create_merge_request(
+            &self,
+            _effects: &Effects,
+            args: CreateMergeRequestArgs,
+            submit_options: &SubmitOptions,
+        ) -> EyreExitOr<String> {
+            let url = self.with_state_mut(|state| {
+                state.merge_request_index += 1;
+                let CreateMergeRequestArgs {
+                    source_branch,
+                    sha,
+                    target_branch,
+                    title,
+                    description,
+                    review_assignment,
+                } = args;
+                let url = format!(
+                    "https://example.com/{}/{}/-/merge_requests/{}",
+                    Self::username(),
+                    Self::project_name(),
+                    state.merge_request_index
+                );
+                let merge_request_info = MergeRequestInfo {
+                    iid: state.merge_request_index,
+                    web_url: url.clone(),
+                    source_branch: source_branch.clone(),
+                    target_branch,
+                    sha: SerializedNonZeroOid(sha),
+                    draft: submit_options.draft,
+                    title,
+                    description,
+                };
+                state
+                    .merge_requests
+                    .insert(source_branch, merge_request_info);
+                if !review_assignment.is_empty() {
+                    state
+                        .review_assignments
+                        .insert(state.merge_request_index, review_assignment);
+                }
+                Ok(url)
+            })?;
+            Ok(Ok(url))
+        }
+
+        fn update_merge_request(
+            &self,
+            _effects: &Effects,
+            merge_request_info: &MergeRequestInfo,
+            args: UpdateMergeRequestArgs,
+        ) -> EyreExitOr<()> {
+            self.with_state_mut(|state| -> eyre::Result<()> {
+                let UpdateMergeRequestArgs {
+                    sha,
+                    target_branch,
+                    title,
+                    description,
+                } = args;
+                let iid = merge_request_info.iid;
+                let merge_request_info = match state
+                    .merge_requests
+                    .values_mut()
+                    .find(|merge_request_info| merge_request_info.iid == iid)
+                {
+                    Some(merge_request_info) => merge_request_info,
+                    None => {
+                        eyre::bail!("Could not find merge request with IID {iid}");
+                    }
+                };
+                merge_request_info.sha = SerializedNonZeroOid(sha);
+                merge_request_info.target_branch = target_branch;
+                merge_request_info.title = title;
+                merge_request_info.description = description;
+                Ok(())
+            })?;
+            Ok(Ok(()))
+        }
+
+        fn mark_merge_request_ready(&self, _effects: &Effects, iid: usize) -> EyreExitOr<()> {
+            self.with_state_mut(|state| -> eyre::Result<()> {
+                match state
+                    .merge_requests
+                    .values_mut()
+                    .find(|merge_request_info| merge_request_info.iid == iid)
+                {
+                    Some(merge_request_info) => {
+                        merge_request_info.draft = false;
+                        Ok(())
+                    }
+                    None => {
+                        eyre::bail!("Could not find merge request with IID {iid}");
+                    }
+                }
+            })?;
+            Ok(Ok(()))
+        }
+
+        fn query_closed_merge_request_infos(
+            &self,
+            _effects: &Effects,
+        ) -> EyreExitOr<HashMap<String, (MergeRequestInfo, MergeRequestState)>> {
+            // Merge requests are never merged or closed in the mock.
+            Ok(Ok(Default::default()))
+        }
+    }
+
+    impl MockGitlabClient {
+        fn username() -> &'static str {
+            "mock-gitlab-username"
+        }
+
+        fn project_name() -> &'static str {
+            "mock-gitlab-project"
+        }
+
+        /// Get the path on disk where the mock state is stored.
+        pub fn state_path(&self) -> PathBuf {
+            self.remote_repo_path.join("mock-gitlab-client-state.json")
+        }
+
+        /// Load the mock state from disk, run the given function, and then save
+        /// the state back to disk.
+        pub fn with_state_mut<T>(
+            &self,
+            f: impl FnOnce(&mut MockState) -> eyre::Result<T>,
+        ) -> eyre::Result<T> {
+            let state_path = self.state_path();
+            let mut state = MockState::load(&state_path)?;
+            let result = f(&mut state)?;
+            state.save(&state_path)?;
+            Ok(result)
+        }
+    }
+}
+
+/// Testing utilities.
+pub mod testing {
+    pub use super::client::MockGitlabClient;
+}
@@ -0,0 +1,157 @@
+//! Select which commits to submit by editing a list of them.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use git_branchless_reword::edit_message;
+use lib::core::config::get_comment_char;
+use lib::core::effects::Effects;
+use lib::core::formatting::Pluralize;
+use lib::git::{GitRunInfo, NonZeroOid, Repo};
+use lib::util::{ExitCode, EyreExitOr};
+use tracing::instrument;
+
+use crate::{CommitStatus, SubmitStatus};
+
+const CHECKED: &str = "[x]";
+const UNCHECKED: &str = "[ ]";
+
+/// Parse the edited list of commits into the set of commits which are still
+/// selected. Returns a message describing the problem if a line couldn't be
+/// understood.
+fn parse_selection(
+    repo: &Repo,
+    commit_oids: &[NonZeroOid],
+    text: &str,
+    comment_char: char,
+) -> eyre::Result<Result<HashSet<NonZeroOid>, String>> {
+    let mut selected_oids = HashSet::new();
+    for (line_num, line) in text.lines().enumerate() {
+        let line_num = line_num + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(comment_char) {
+            continue;
+        }
+
+        let (is_checked, rest) = if let Some(rest) = line.strip_prefix(UNCHECKED) {
+            (false, rest)
+        } else if let Some(rest) = line
+            .strip_prefix(CHECKED)
+            .or_else(|| line.strip_prefix("[X]"))
+        {
+            (true, rest)
+        } else {
+            return Ok(Err(format!(
+                "Expected line {line_num} to start with {CHECKED} or {UNCHECKED}: {line}"
+            )));
+        };
+        if !is_checked {
+            continue;
+        }
+
+        // The first word is the operation, such as `create`.
+        let commit = rest.split_whitespace().nth(1).unwrap_or_default();
+        let commit_oid = match repo.revparse_single_commit(commit)? {
+            Some(commit) => commit.get_oid(),
+            None => {
+                return Ok(Err(format!(
+                    "Could not resolve commit on line {line_num}: {commit}"
+                )))
+            }
+        };
+        if !commit_oids.contains(&commit_oid) {
+            return Ok(Err(format!(
+                "Commit on line {line_num} is not one of the commits being submitted: {commit}"
+            )));
+        }
+        selected_oids.insert(commit_oid);
+    }
+    Ok(Ok(selected_oids))
+}
+
+/// For `--interactive`, open the list of commits which would be created or
+/// updated in an editor, and return `statuses` with the commits which were
+/// deselected marked as [`SubmitStatus::Local`], so that they're left alone.
+#[instrument]
+pub fn select_commits_interactively(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    create: bool,
+    commit_oids: &[NonZeroOid],
+    mut statuses: HashMap<NonZeroOid, CommitStatus>,
+) -> EyreExitOr<HashMap<NonZeroOid, CommitStatus>> {
+    let candidates: Vec<(NonZeroOid, &str)> = commit_oids
+        .iter()
+        .filter_map(|commit_oid| {
+            let operation = match statuses.get(commit_oid)?.submit_status {
+                SubmitStatus::Unsubmitted if create => "create",
+                SubmitStatus::NeedsUpdate => "update",
+                SubmitStatus::Local
+                | SubmitStatus::Unsubmitted
+                | SubmitStatus::Unknown
+                | SubmitStatus::UpToDate => return None,
+            };
+            Some((*commit_oid, operation))
+        })
+        .collect();
+    if candidates.is_empty() {
+        return Ok(Ok(statuses));
+    }
+
+    let comment_char = get_comment_char(repo)?;
+    let mut text = String::new();
+    for (commit_oid, operation) in candidates.iter() {
+        let commit = repo.find_commit_or_fail(*commit_oid)?;
+        writeln!(
+            text,
+            "{CHECKED} {operation} {} {}",
+            commit.get_short_oid()?,
+            String::from_utf8_lossy(&commit.get_summary()?)
+        )?;
+    }
+    write!(
+        text,
+        "\
+{c}
+{c} Submit {num_commits}.
+{c}
+{c} To skip submitting a commit, replace its {CHECKED} with {UNCHECKED} or delete its
+{c} line.
+",
+        c = comment_char,
+        num_commits = Pluralize {
+            determiner: None,
+            amount: candidates.len(),
+            unit: ("commit", "commits"),
+        },
+    )?;
+    let text = edit_message(git_run_info, repo, &text)?;
+    let candidate_oids: Vec<NonZeroOid> = candidates
+        .iter()
+        .map(|(commit_oid, _operation)| *commit_oid)
+        .collect();
+    let selected_oids = match parse_selection(repo, &candidate_oids, &text, comment_char)? {
+        Ok(selected_oids) => selected_oids,
+        Err(message) => {
+            writeln!(effects.get_error_stream(), "{message}")?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+    if selected_oids.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "No commits were selected. Aborted."
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+
+    for commit_oid in candidate_oids {
+        if !selected_oids.contains(&commit_oid) {
+            if let Some(commit_status) = statuses.get_mut(&commit_oid) {
+                commit_status.submit_status = SubmitStatus::Local;
+            }
+        }
+    }
+    Ok(Ok(statuses))
+}
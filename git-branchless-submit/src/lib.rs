@@ -10,8 +10,15 @@
 #![allow(clippy::too_many_arguments, clippy::blocks_in_if_conditions)]
 
 mod branch_forge;
+pub mod checkout_pr;
+pub mod external;
+pub mod gerrit;
 pub mod github;
+pub mod gitlab;
+mod interactive;
 pub mod phabricator;
+mod retry;
+pub mod send_email;
 
 use std::collections::{BTreeSet, HashMap};
 use std::fmt::{Debug, Write};
@@ -19,25 +26,36 @@ use std::time::SystemTime;
 
 use branch_forge::BranchForge;
 use cursive_core::theme::{BaseColor, Effect, Style};
+use external::{external_forge_name, ExternalForge};
+use gerrit::GerritForge;
 use git_branchless_invoke::CommandContext;
 use git_branchless_test::{RawTestOptions, ResolvedTestOptions, Verbosity};
 use github::GithubForge;
+use gitlab::GitlabForge;
+use interactive::select_commits_interactively;
 use itertools::Itertools;
 use lazy_static::lazy_static;
 use lib::core::dag::{union_all, CommitSet, Dag};
 use lib::core::effects::Effects;
-use lib::core::eventlog::{EventLogDb, EventReplayer};
+use lib::core::eventlog::{Event, EventLogDb, EventReplayer};
 use lib::core::formatting::{Pluralize, StyledStringBuilder};
 use lib::core::repo_ext::{RepoExt, RepoReferencesSnapshot};
-use lib::git::{GitRunInfo, NonZeroOid, Repo};
+use lib::core::review_status::{ReviewStatus, ReviewStatusDb};
+use lib::core::rewrite::move_branches;
+use lib::git::{
+    CategorizedReferenceName, Commit, ConfigRead, GitRunInfo, MaybeZeroOid, NonZeroOid, Repo,
+};
 use lib::try_exit_code;
 use lib::util::{ExitCode, EyreExitOr};
 
 use git_branchless_opts::{
-    ForgeKind, ResolveRevsetOptions, Revset, SubmitArgs, TestExecutionStrategy,
+    ForgeKind, ResolveRevsetOptions, Revset, SubmitArgs, SubmitOutputFormat, SubmitSubcommand,
+    TestExecutionStrategy,
 };
 use git_branchless_revset::resolve_commits;
 use phabricator::PhabricatorForge;
+use send_email::SendEmailForge;
+use serde::{Deserialize, Serialize};
 use tracing::{debug, info, instrument, warn};
 
 use crate::github::github_push_remote;
@@ -155,6 +173,69 @@ pub struct CreateStatus {
     pub local_commit_name: String,
 }
 
+/// The reviewers and labels to assign to a newly-created code review.
+///
+/// These are read from the commit's `Reviewers:` and `Labels:` trailers, whose
+/// values are comma-separated lists, so that review routing is recorded in the
+/// commits themselves. If a commit has no `Reviewers:` trailer, then the
+/// reviewers in `branchless.submit.defaultReviewers` are used instead.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ReviewAssignment {
+    /// The usernames of the reviewers to request.
+    pub reviewers: Vec<String>,
+
+    /// The labels to apply. Depending on the forge, these may correspond to
+    /// labels, hashtags, projects, etc.
+    pub labels: Vec<String>,
+}
+
+impl ReviewAssignment {
+    /// Get the reviewers and labels to assign to the code review for `commit`.
+    pub fn for_commit(repo: &Repo, commit: &Commit) -> eyre::Result<Self> {
+        let mut reviewers: Option<Vec<String>> = None;
+        let mut labels = Vec::new();
+        for (key, value) in commit.get_trailers()? {
+            if key.eq_ignore_ascii_case("Reviewers") {
+                reviewers
+                    .get_or_insert_with(Default::default)
+                    .extend(split_list(&value));
+            } else if key.eq_ignore_ascii_case("Labels") {
+                labels.extend(split_list(&value));
+            }
+        }
+        let reviewers = match reviewers {
+            Some(reviewers) => reviewers,
+            None => {
+                let default_reviewers: Option<String> = repo
+                    .get_readonly_config()?
+                    .get("branchless.submit.defaultReviewers")?;
+                default_reviewers
+                    .map(|default_reviewers| split_list(&default_reviewers))
+                    .unwrap_or_default()
+            }
+        };
+        Ok(Self {
+            reviewers: reviewers.into_iter().unique().collect(),
+            labels: labels.into_iter().unique().collect(),
+        })
+    }
+
+    /// Whether there is nothing to assign.
+    pub fn is_empty(&self) -> bool {
+        let Self { reviewers, labels } = self;
+        reviewers.is_empty() && labels.is_empty()
+    }
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim())
+        .filter(|item| !item.is_empty())
+        .map(|item| item.to_owned())
+        .collect()
+}
+
 /// "Forge" refers to a Git hosting provider, such as GitHub, GitLab, etc.
 /// Commits can be pushed for review to a forge.
 pub trait Forge: Debug {
@@ -177,6 +258,32 @@ pub trait Forge: Debug {
         commits: HashMap<NonZeroOid, CommitStatus>,
         options: &SubmitOptions,
     ) -> EyreExitOr<()>;
+
+    /// Mark the code reviews for the provided commits as ready for review, if
+    /// they were submitted as drafts.
+    fn mark_ready(&mut self, commits: HashMap<NonZeroOid, CommitStatus>) -> EyreExitOr<()>;
+
+    /// Find which of the provided commits have code reviews which were closed
+    /// on the forge, such as by landing them. The result maps each such commit
+    /// to a description of why it's no longer needed locally.
+    fn query_closed(&mut self, commit_set: CommitSet) -> EyreExitOr<HashMap<NonZeroOid, String>>;
+
+    /// Get the URLs of the code reviews for the provided commits. Commits
+    /// without a code review, or for forges which don't have URLs for code
+    /// reviews, are omitted from the result.
+    fn query_urls(&mut self, _commit_set: CommitSet) -> EyreExitOr<HashMap<NonZeroOid, String>> {
+        Ok(Ok(Default::default()))
+    }
+
+    /// Get the status of the code reviews for the provided commits, including
+    /// open, merged, and closed code reviews. Commits without a code review, or
+    /// for forges which don't have code reviews, are omitted from the result.
+    fn query_review_statuses(
+        &mut self,
+        _commit_set: CommitSet,
+    ) -> EyreExitOr<HashMap<NonZeroOid, ReviewStatus>> {
+        Ok(Ok(Default::default()))
+    }
 }
 
 /// `submit` command.
@@ -186,16 +293,38 @@ pub fn command_main(ctx: CommandContext, args: SubmitArgs) -> EyreExitOr<()> {
         git_run_info,
     } = ctx;
     let SubmitArgs {
+        subcommand,
         revsets,
         resolve_revset_options,
         forge_kind,
         create,
         draft,
+        undraft,
+        sync_status,
         message,
         num_jobs,
         execution_strategy,
         dry_run,
+        interactive,
+        output_format,
     } = args;
+    if let Some(subcommand) = subcommand {
+        return match subcommand {
+            SubmitSubcommand::Status {
+                revsets,
+                resolve_revset_options,
+                forge_kind,
+                cached,
+            } => submit_status(
+                &effects,
+                &git_run_info,
+                revsets,
+                &resolve_revset_options,
+                forge_kind,
+                cached,
+            ),
+        };
+    }
     submit(
         &effects,
         &git_run_info,
@@ -204,10 +333,14 @@ pub fn command_main(ctx: CommandContext, args: SubmitArgs) -> EyreExitOr<()> {
         forge_kind,
         create,
         draft,
+        undraft,
+        sync_status,
         message,
         num_jobs,
         execution_strategy,
         dry_run,
+        interactive,
+        output_format,
     )
 }
 
@@ -219,11 +352,22 @@ fn submit(
     forge_kind: Option<ForgeKind>,
     create: bool,
     draft: bool,
+    undraft: bool,
+    sync_status: bool,
     message: Option<String>,
     num_jobs: Option<usize>,
     execution_strategy: Option<TestExecutionStrategy>,
     dry_run: bool,
+    interactive: bool,
+    output_format: SubmitOutputFormat,
 ) -> EyreExitOr<()> {
+    // With JSON output, stdout is reserved for the report.
+    let report_effects = effects;
+    let effects = &match output_format {
+        SubmitOutputFormat::Text => effects.clone(),
+        SubmitOutputFormat::Json => effects.redirect_output_to_stderr(),
+    };
+
     let repo = Repo::from_current_dir()?;
     let conn = repo.get_db_conn()?;
     let event_log_db = EventLogDb::new(&conn)?;
@@ -293,8 +437,9 @@ fn submit(
         message,
     };
 
+    let commit_oids = dag.sort(&commit_set)?;
     let unioned_revset = Revset(revsets.iter().map(|Revset(inner)| inner).join(" + "));
-    let mut forge = select_forge(
+    let (forge_kind, mut forge) = select_forge(
         effects,
         git_run_info,
         &repo,
@@ -304,8 +449,37 @@ fn submit(
         &unioned_revset,
         forge_kind,
     )?;
+    if sync_status {
+        let closed_commits = try_exit_code!(forge.query_closed(commit_set)?);
+        return hide_closed_commits(
+            effects,
+            git_run_info,
+            &repo,
+            &event_log_db,
+            &references_snapshot,
+            &commit_oids,
+            closed_commits,
+            dry_run,
+        );
+    }
+
     let statuses = try_exit_code!(forge.query_status(commit_set)?);
     debug!(?statuses, "Commit statuses");
+    let statuses = if interactive {
+        try_exit_code!(select_commits_interactively(
+            effects,
+            git_run_info,
+            &repo,
+            create,
+            &commit_oids,
+            statuses,
+        )?)
+    } else {
+        statuses
+    };
+    if dry_run {
+        render_dry_run_plan(effects, &repo, &forge_kind, create, &commit_oids, &statuses)?;
+    }
 
     #[allow(clippy::type_complexity)]
     let (_local_commits, unsubmitted_commits, commits_to_update, commits_to_skip): (
@@ -313,63 +487,84 @@ fn submit(
         HashMap<NonZeroOid, CommitStatus>,
         HashMap<NonZeroOid, CommitStatus>,
         HashMap<NonZeroOid, CommitStatus>,
-    ) = statuses.into_iter().fold(Default::default(), |acc, elem| {
-        let (mut local, mut unsubmitted, mut to_update, mut to_skip) = acc;
-        let (commit_oid, commit_status) = elem;
-        match commit_status {
-            CommitStatus {
-                submit_status: SubmitStatus::Local,
-                remote_name: _,
-                local_commit_name: _,
-                remote_commit_name: _,
-            } => {
-                local.insert(commit_oid, commit_status);
-            }
+    ) = statuses
+        .clone()
+        .into_iter()
+        .fold(Default::default(), |acc, elem| {
+            let (mut local, mut unsubmitted, mut to_update, mut to_skip) = acc;
+            let (commit_oid, commit_status) = elem;
+            match commit_status {
+                CommitStatus {
+                    submit_status: SubmitStatus::Local,
+                    remote_name: _,
+                    local_commit_name: _,
+                    remote_commit_name: _,
+                } => {
+                    local.insert(commit_oid, commit_status);
+                }
 
-            CommitStatus {
-                submit_status: SubmitStatus::Unsubmitted,
-                remote_name: _,
-                local_commit_name: _,
-                remote_commit_name: _,
-            } => {
-                unsubmitted.insert(commit_oid, commit_status);
-            }
+                CommitStatus {
+                    submit_status: SubmitStatus::Unsubmitted,
+                    remote_name: _,
+                    local_commit_name: _,
+                    remote_commit_name: _,
+                } => {
+                    unsubmitted.insert(commit_oid, commit_status);
+                }
 
-            CommitStatus {
-                submit_status: SubmitStatus::NeedsUpdate,
-                remote_name: _,
-                local_commit_name: _,
-                remote_commit_name: _,
-            } => {
-                to_update.insert(commit_oid, commit_status);
-            }
+                CommitStatus {
+                    submit_status: SubmitStatus::NeedsUpdate,
+                    remote_name: _,
+                    local_commit_name: _,
+                    remote_commit_name: _,
+                } => {
+                    to_update.insert(commit_oid, commit_status);
+                }
 
-            CommitStatus {
-                submit_status: SubmitStatus::UpToDate,
-                remote_name: _,
-                local_commit_name: Some(_),
-                remote_commit_name: _,
-            } => {
-                to_skip.insert(commit_oid, commit_status);
-            }
+                CommitStatus {
+                    submit_status: SubmitStatus::UpToDate,
+                    remote_name: _,
+                    local_commit_name: Some(_),
+                    remote_commit_name: _,
+                } => {
+                    to_skip.insert(commit_oid, commit_status);
+                }
 
-            // Don't know what to do in these cases 🙃.
-            CommitStatus {
-                submit_status: SubmitStatus::Unknown,
-                remote_name: _,
-                local_commit_name: _,
-                remote_commit_name: _,
+                // Don't know what to do in these cases 🙃.
+                CommitStatus {
+                    submit_status: SubmitStatus::Unknown,
+                    remote_name: _,
+                    local_commit_name: _,
+                    remote_commit_name: _,
+                }
+                | CommitStatus {
+                    submit_status: SubmitStatus::UpToDate,
+                    remote_name: _,
+                    local_commit_name: None,
+                    remote_commit_name: _,
+                } => {}
             }
-            | CommitStatus {
-                submit_status: SubmitStatus::UpToDate,
-                remote_name: _,
-                local_commit_name: None,
-                remote_commit_name: _,
-            } => {}
-        }
-        (local, unsubmitted, to_update, to_skip)
-    });
+            (local, unsubmitted, to_update, to_skip)
+        });
+
+    let create_commit_oids: BTreeSet<NonZeroOid> = if create {
+        unsubmitted_commits.keys().copied().collect()
+    } else {
+        Default::default()
+    };
+    let update_commit_oids: BTreeSet<NonZeroOid> = commits_to_update.keys().copied().collect();
+    let mark_ready_commit_oids: BTreeSet<NonZeroOid> = if undraft {
+        commits_to_update
+            .keys()
+            .chain(commits_to_skip.keys())
+            .copied()
+            .collect()
+    } else {
+        Default::default()
+    };
+    let mut failure: Option<ForgeFailure> = None;
 
+    let mut create_statuses: HashMap<NonZeroOid, CreateStatus> = Default::default();
     let (submitted_commit_names, unsubmitted_commit_names): (BTreeSet<String>, BTreeSet<String>) = {
         let unsubmitted_commit_names: BTreeSet<String> = unsubmitted_commits
             .values()
@@ -379,15 +574,21 @@ fn submit(
             let created_commit_names = if dry_run {
                 unsubmitted_commit_names.clone()
             } else {
-                let create_statuses =
-                    try_exit_code!(forge.create(unsubmitted_commits, &submit_options)?);
+                match try_exit_code!(handle_forge_failure(
+                    &output_format,
+                    ForgeOperation::Create,
+                    forge.create(unsubmitted_commits, &submit_options),
+                )?) {
+                    Ok(statuses) => create_statuses = statuses,
+                    Err(forge_failure) => failure = Some(forge_failure),
+                }
                 create_statuses
-                    .into_values()
+                    .values()
                     .map(
                         |CreateStatus {
                              final_commit_oid: _,
                              local_commit_name,
-                         }| local_commit_name,
+                         }| local_commit_name.clone(),
                     )
                     .collect()
             };
@@ -407,12 +608,85 @@ fn submit(
             .flat_map(|(_commit_oid, commit_status)| commit_status.local_commit_name.clone())
             .collect();
 
-        if !dry_run {
-            try_exit_code!(forge.update(commits_to_update, &submit_options)?);
+        // Existing code reviews which are up-to-date may still need to be
+        // marked as ready for review.
+        let commits_to_mark_ready: HashMap<NonZeroOid, CommitStatus> = if undraft {
+            commits_to_update
+                .iter()
+                .chain(commits_to_skip.iter())
+                .map(|(commit_oid, commit_status)| (*commit_oid, commit_status.clone()))
+                .collect()
+        } else {
+            Default::default()
+        };
+        if !dry_run && failure.is_none() {
+            if let Err(forge_failure) = try_exit_code!(handle_forge_failure(
+                &output_format,
+                ForgeOperation::Update,
+                forge.update(commits_to_update, &submit_options),
+            )?) {
+                failure = Some(forge_failure);
+            }
+        }
+        if !dry_run && failure.is_none() && !commits_to_mark_ready.is_empty() {
+            if let Err(forge_failure) = try_exit_code!(handle_forge_failure(
+                &output_format,
+                ForgeOperation::MarkReady,
+                forge.mark_ready(commits_to_mark_ready),
+            )?) {
+                failure = Some(forge_failure);
+            }
         }
         (updated_commit_names, skipped_commit_names)
     };
 
+    if let SubmitOutputFormat::Json = output_format {
+        let final_commit_oids: CommitSet = commit_oids
+            .iter()
+            .map(|commit_oid| {
+                create_statuses
+                    .get(commit_oid)
+                    .map(|create_status| create_status.final_commit_oid)
+                    .unwrap_or(*commit_oid)
+            })
+            .collect();
+        let urls = try_exit_code!(forge.query_urls(final_commit_oids)?);
+        let report = SubmitReport {
+            dry_run,
+            commits: commit_oids
+                .iter()
+                .map(|commit_oid| {
+                    let operations = [
+                        (ForgeOperation::Create, &create_commit_oids),
+                        (ForgeOperation::Update, &update_commit_oids),
+                        (ForgeOperation::MarkReady, &mark_ready_commit_oids),
+                    ]
+                    .into_iter()
+                    .filter(|(_operation, commit_oids)| commit_oids.contains(commit_oid))
+                    .map(|(operation, _commit_oids)| operation)
+                    .collect_vec();
+                    make_report_entry(
+                        *commit_oid,
+                        statuses.get(commit_oid),
+                        create_statuses.get(commit_oid),
+                        &urls,
+                        &operations,
+                        failure.as_ref(),
+                    )
+                })
+                .collect(),
+        };
+        writeln!(
+            report_effects.get_output_stream(),
+            "{}",
+            serde_json::to_string_pretty(&report)?
+        )?;
+        return match failure {
+            Some(ForgeFailure { exit_code, .. }) => Ok(Err(exit_code)),
+            None => Ok(Ok(())),
+        };
+    }
+
     if !submitted_commit_names.is_empty() {
         writeln!(
             effects.get_output_stream(),
@@ -517,6 +791,434 @@ repository. To submit them, retry this operation with the --create option.",
     Ok(Ok(()))
 }
 
+/// A forge operation carried out by `submit`. Operations are carried out in
+/// the order listed here.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum ForgeOperation {
+    Create,
+    Update,
+    MarkReady,
+}
+
+/// A forge operation which failed, to be included in the JSON report.
+#[derive(Debug)]
+struct ForgeFailure {
+    operation: ForgeOperation,
+    exit_code: ExitCode,
+    message: String,
+}
+
+/// For text output, propagate the failure of a forge operation as usual. For
+/// JSON output, return the failure instead, so that it can be reported for the
+/// affected commits.
+fn handle_forge_failure<T>(
+    output_format: &SubmitOutputFormat,
+    operation: ForgeOperation,
+    result: EyreExitOr<T>,
+) -> EyreExitOr<Result<T, ForgeFailure>> {
+    match (output_format, result) {
+        (_, Ok(Ok(value))) => Ok(Ok(Ok(value))),
+        (SubmitOutputFormat::Text, Ok(Err(exit_code))) => Ok(Err(exit_code)),
+        (SubmitOutputFormat::Text, Err(err)) => Err(err),
+        (SubmitOutputFormat::Json, Ok(Err(exit_code))) => Ok(Ok(Err(ForgeFailure {
+            operation,
+            exit_code,
+            message: format!("failed with exit code {}", exit_code.0),
+        }))),
+        (SubmitOutputFormat::Json, Err(err)) => Ok(Ok(Err(ForgeFailure {
+            operation,
+            exit_code: ExitCode(1),
+            message: format!("{err:#}"),
+        }))),
+    }
+}
+
+/// The report printed for `--output json`.
+#[derive(Debug, Serialize)]
+struct SubmitReport {
+    /// Whether this was a dry run, in which case each result describes what
+    /// would have been done.
+    dry_run: bool,
+
+    /// The results for each commit, in topological order.
+    commits: Vec<SubmitReportEntry>,
+}
+
+/// What was done to a commit when submitting it.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum SubmitResult {
+    /// A new remote branch or code review was created.
+    Created,
+
+    /// The existing remote branch or code review was updated.
+    Updated,
+
+    /// The existing remote branch or code review was already up-to-date.
+    UpToDate,
+
+    /// The commit isn't on the remote yet, and `--create` wasn't passed.
+    Unsubmitted,
+
+    /// The commit isn't associated with anything to submit, or its remote
+    /// status couldn't be determined.
+    Excluded,
+
+    /// The operation for this commit failed; see `error`.
+    Failed,
+
+    /// The operation for this commit wasn't attempted because an earlier
+    /// operation failed.
+    NotAttempted,
+}
+
+/// The result of submitting a single commit, for `--output json`.
+#[derive(Debug, Serialize)]
+struct SubmitReportEntry {
+    commit_oid: String,
+    name: Option<String>,
+    url: Option<String>,
+    result: SubmitResult,
+    error: Option<String>,
+}
+
+fn make_report_entry(
+    commit_oid: NonZeroOid,
+    commit_status: Option<&CommitStatus>,
+    create_status: Option<&CreateStatus>,
+    urls: &HashMap<NonZeroOid, String>,
+    operations: &[ForgeOperation],
+    failure: Option<&ForgeFailure>,
+) -> SubmitReportEntry {
+    let final_commit_oid = create_status
+        .map(|create_status| create_status.final_commit_oid)
+        .unwrap_or(commit_oid);
+    let name = match create_status {
+        Some(create_status) => Some(create_status.local_commit_name.clone()),
+        None => commit_status.and_then(|commit_status| commit_status.local_commit_name.clone()),
+    };
+    let error = failure
+        .filter(|failure| operations.contains(&failure.operation))
+        .map(|failure| failure.message.clone());
+
+    let result = match commit_status.map(|commit_status| &commit_status.submit_status) {
+        None | Some(SubmitStatus::Local) | Some(SubmitStatus::Unknown) => SubmitResult::Excluded,
+        Some(SubmitStatus::UpToDate) => SubmitResult::UpToDate,
+        Some(SubmitStatus::Unsubmitted) if !operations.contains(&ForgeOperation::Create) => {
+            SubmitResult::Unsubmitted
+        }
+        Some(SubmitStatus::Unsubmitted) | Some(SubmitStatus::NeedsUpdate) => {
+            let (operation, succeeded_result) = if operations.contains(&ForgeOperation::Create) {
+                (ForgeOperation::Create, SubmitResult::Created)
+            } else {
+                (ForgeOperation::Update, SubmitResult::Updated)
+            };
+            match failure {
+                Some(failure) if failure.operation == operation => SubmitResult::Failed,
+                Some(failure) if failure.operation < operation => SubmitResult::NotAttempted,
+                Some(_) | None => succeeded_result,
+            }
+        }
+    };
+
+    SubmitReportEntry {
+        commit_oid: commit_oid.to_string(),
+        name,
+        url: urls.get(&final_commit_oid).cloned(),
+        result,
+        error,
+    }
+}
+
+/// For `git submit status`, print the status of the code review for each
+/// commit, and cache it for display in the smartlog. With `cached`, print the
+/// statuses from the last query instead of querying the forge.
+fn submit_status(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    revsets: Vec<Revset>,
+    resolve_revset_options: &ResolveRevsetOptions,
+    forge_kind: Option<ForgeKind>,
+    cached: bool,
+) -> EyreExitOr<()> {
+    let repo = Repo::from_current_dir()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let references_snapshot = repo.get_references_snapshot()?;
+    let mut dag = Dag::open_and_sync(
+        effects,
+        &repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+
+    let commit_set =
+        match resolve_commits(effects, &repo, &mut dag, &revsets, resolve_revset_options) {
+            Ok(commit_sets) => union_all(&commit_sets),
+            Err(err) => {
+                err.describe(effects)?;
+                return Ok(Err(ExitCode(1)));
+            }
+        };
+    let commit_oids = dag.sort(&commit_set)?;
+
+    let review_status_db = ReviewStatusDb::new(&conn)?;
+    if !cached {
+        let unioned_revset = Revset(revsets.iter().map(|Revset(inner)| inner).join(" + "));
+        let (_forge_kind, mut forge) = select_forge(
+            effects,
+            git_run_info,
+            &repo,
+            &mut dag,
+            &event_log_db,
+            &references_snapshot,
+            &unioned_revset,
+            forge_kind,
+        )?;
+        let review_statuses = try_exit_code!(forge.query_review_statuses(commit_set)?);
+        for commit_oid in commit_oids.iter() {
+            review_status_db.set(*commit_oid, review_statuses.get(commit_oid))?;
+        }
+    }
+
+    let glyphs = effects.get_glyphs();
+    for commit_oid in commit_oids {
+        let description = match review_status_db.get(commit_oid)? {
+            Some(review_status) => review_status.describe(),
+            None => "no code review".to_string(),
+        };
+        writeln!(
+            effects.get_output_stream(),
+            "{}: {}",
+            glyphs.render(repo.friendly_describe_commit_from_oid(glyphs, commit_oid)?)?,
+            description,
+        )?;
+    }
+    Ok(Ok(()))
+}
+
+/// For `--sync-status`, hide the commits whose code reviews were closed on the
+/// forge, and delete the branches pointing to them.
+fn hide_closed_commits(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    event_log_db: &EventLogDb,
+    references_snapshot: &RepoReferencesSnapshot,
+    commit_oids: &[NonZeroOid],
+    closed_commits: HashMap<NonZeroOid, String>,
+    dry_run: bool,
+) -> EyreExitOr<()> {
+    let closed_commit_oids = commit_oids
+        .iter()
+        .copied()
+        .filter(|commit_oid| closed_commits.contains_key(commit_oid))
+        .collect_vec();
+    if closed_commit_oids.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "No commits have code reviews which were merged or closed."
+        )?;
+        return Ok(Ok(()));
+    }
+
+    let glyphs = effects.get_glyphs();
+    for commit_oid in closed_commit_oids.iter().copied() {
+        writeln!(
+            effects.get_output_stream(),
+            "{} commit: {} ({})",
+            if dry_run { "Would hide" } else { "Hid" },
+            glyphs.render(repo.friendly_describe_commit_from_oid(glyphs, commit_oid)?)?,
+            closed_commits[&commit_oid],
+        )?;
+    }
+    let closed_branch_names: Vec<String> = closed_commit_oids
+        .iter()
+        .filter_map(|commit_oid| references_snapshot.branch_oid_to_names.get(commit_oid))
+        .flatten()
+        .map(|branch_name| CategorizedReferenceName::new(branch_name).render_suffix())
+        .sorted()
+        .collect();
+    if dry_run {
+        if !closed_branch_names.is_empty() {
+            writeln!(
+                effects.get_output_stream(),
+                "Would delete {}: {}",
+                Pluralize {
+                    determiner: None,
+                    amount: closed_branch_names.len(),
+                    unit: ("branch", "branches"),
+                },
+                closed_branch_names.join(", ")
+            )?;
+        }
+        return Ok(Ok(()));
+    }
+
+    let now = SystemTime::now();
+    let timestamp = now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64();
+    let event_tx_id = event_log_db.make_transaction_id(now, "submit sync status")?;
+    event_log_db.add_events(
+        closed_commit_oids
+            .iter()
+            .map(|commit_oid| Event::ObsoleteEvent {
+                timestamp,
+                event_tx_id,
+                commit_oid: *commit_oid,
+            })
+            .collect(),
+    )?;
+
+    // Save the current HEAD info *before* deleting any branches.
+    let head_info = repo.get_head_info()?;
+    let abandoned_branches: HashMap<NonZeroOid, MaybeZeroOid> = closed_commit_oids
+        .iter()
+        .map(|commit_oid| (*commit_oid, MaybeZeroOid::Zero))
+        .collect();
+    if let Some(head_oid) = head_info.oid {
+        if abandoned_branches.contains_key(&head_oid) {
+            repo.detach_head(&head_info)?;
+        }
+    }
+    move_branches(
+        effects,
+        git_run_info,
+        repo,
+        event_tx_id,
+        &abandoned_branches,
+    )?;
+
+    if !closed_branch_names.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "Deleted {}: {}",
+            Pluralize {
+                determiner: None,
+                amount: closed_branch_names.len(),
+                unit: ("branch", "branches"),
+            },
+            closed_branch_names.join(", ")
+        )?;
+    }
+    writeln!(
+        effects.get_output_stream(),
+        "To unhide {}, run: git undo",
+        Pluralize {
+            determiner: Some(("this", "these")),
+            amount: closed_commit_oids.len(),
+            unit: ("commit", "commits"),
+        },
+    )?;
+    Ok(Ok(()))
+}
+
+/// For `--dry-run`, print what would happen to each of the commits to submit,
+/// in topological order, including why any commits would be excluded.
+fn render_dry_run_plan(
+    effects: &Effects,
+    repo: &Repo,
+    forge_kind: &ForgeKind,
+    create: bool,
+    commit_oids: &[NonZeroOid],
+    statuses: &HashMap<NonZeroOid, CommitStatus>,
+) -> eyre::Result<()> {
+    if commit_oids.is_empty() {
+        return Ok(());
+    }
+
+    let glyphs = effects.get_glyphs();
+    writeln!(effects.get_output_stream(), "Submission plan:")?;
+    for commit_oid in commit_oids.iter().copied() {
+        let status = statuses.get(&commit_oid);
+        let name = status
+            .and_then(|status| status.local_commit_name.as_deref())
+            .map(|name| {
+                glyphs
+                    .render(
+                        StyledStringBuilder::new()
+                            .append_styled(name, *STYLE_PUSHED)
+                            .build(),
+                    )
+                    .expect("Rendering commit name")
+            });
+        let action = match (status.map(|status| &status.submit_status), forge_kind) {
+            (None, _) | (Some(SubmitStatus::Local), _) => {
+                "skip (not associated with anything to submit)".to_string()
+            }
+            (Some(SubmitStatus::Unknown), _) => {
+                "skip (could not determine remote status)".to_string()
+            }
+            (Some(SubmitStatus::UpToDate), _) => match name {
+                Some(name) => format!("skip {name} (already up-to-date)"),
+                None => "skip (already up-to-date)".to_string(),
+            },
+            (Some(SubmitStatus::Unsubmitted), _) if !create => match name {
+                Some(name) => format!("skip {name} (not yet on remote; pass --create to submit)"),
+                None => "skip (not yet on remote; pass --create to submit)".to_string(),
+            },
+
+            (Some(SubmitStatus::Unsubmitted), ForgeKind::Branch) => {
+                format!("push new branch {}", name.unwrap_or_default())
+            }
+            (Some(SubmitStatus::Unsubmitted), ForgeKind::Github) => format!(
+                "push new branch {} and create a pull request",
+                name.unwrap_or_default()
+            ),
+            (Some(SubmitStatus::Unsubmitted), ForgeKind::Gitlab) => format!(
+                "push new branch {} and create a merge request",
+                name.unwrap_or_default()
+            ),
+            (Some(SubmitStatus::Unsubmitted), ForgeKind::Gerrit) => {
+                "add a Change-Id and upload a new change".to_string()
+            }
+            (Some(SubmitStatus::Unsubmitted), ForgeKind::Phabricator) => {
+                "create a new revision".to_string()
+            }
+            (Some(SubmitStatus::Unsubmitted), ForgeKind::External) => match name {
+                Some(name) => format!("create a new code review for {name}"),
+                None => "create a new code review".to_string(),
+            },
+            (Some(SubmitStatus::Unsubmitted), ForgeKind::SendEmail) => {
+                "send as a new patch".to_string()
+            }
+
+            (Some(SubmitStatus::NeedsUpdate), ForgeKind::Branch) => {
+                format!("force-push branch {}", name.unwrap_or_default())
+            }
+            (Some(SubmitStatus::NeedsUpdate), ForgeKind::Github) => format!(
+                "force-push branch {} and update its pull request",
+                name.unwrap_or_default()
+            ),
+            (Some(SubmitStatus::NeedsUpdate), ForgeKind::Gitlab) => format!(
+                "force-push branch {} and update its merge request",
+                name.unwrap_or_default()
+            ),
+            (Some(SubmitStatus::NeedsUpdate), ForgeKind::Gerrit) => format!(
+                "upload a new patchset for change {}",
+                name.unwrap_or_default()
+            ),
+            (Some(SubmitStatus::NeedsUpdate), ForgeKind::Phabricator) => {
+                format!("update revision {}", name.unwrap_or_default())
+            }
+            (Some(SubmitStatus::NeedsUpdate), ForgeKind::External) => {
+                format!("update code review {}", name.unwrap_or_default())
+            }
+            (Some(SubmitStatus::NeedsUpdate), ForgeKind::SendEmail) => {
+                format!("send a new version of patch {}", name.unwrap_or_default())
+            }
+        };
+        writeln!(
+            effects.get_output_stream(),
+            "- {}: {action}",
+            glyphs.render(repo.friendly_describe_commit_from_oid(glyphs, commit_oid)?)?,
+        )?;
+    }
+    Ok(())
+}
+
 #[instrument]
 fn select_forge<'a>(
     effects: &'a Effects,
@@ -527,7 +1229,7 @@ fn select_forge<'a>(
     references_snapshot: &'a RepoReferencesSnapshot,
     revset: &'a Revset,
     forge_kind: Option<ForgeKind>,
-) -> eyre::Result<Box<dyn Forge + 'a>> {
+) -> eyre::Result<(ForgeKind, Box<dyn Forge + 'a>)> {
     // Check if explicitly set:
     let forge_kind = match forge_kind {
         Some(forge_kind) => {
@@ -537,6 +1239,13 @@ fn select_forge<'a>(
         None => None,
     };
 
+    // Check external forge:
+    let external_forge_name = external_forge_name(repo)?;
+    let forge_kind = match forge_kind {
+        Some(forge_kind) => Some(forge_kind),
+        None => external_forge_name.as_ref().map(|_| ForgeKind::External),
+    };
+
     // Check Phabricator:
     let forge_kind = match forge_kind {
         Some(forge_kind) => Some(forge_kind),
@@ -572,7 +1281,7 @@ fn select_forge<'a>(
     let forge_kind = forge_kind.unwrap_or(ForgeKind::Branch);
 
     info!(?forge_kind, "Selected forge kind");
-    let forge: Box<dyn Forge> = match forge_kind {
+    let forge: Box<dyn Forge> = match &forge_kind {
         ForgeKind::Branch => Box::new(BranchForge {
             effects,
             git_run_info,
@@ -588,9 +1297,44 @@ fn select_forge<'a>(
             repo,
             dag,
             event_log_db,
-            client: GithubForge::client(git_run_info.clone()),
+            client: GithubForge::client(git_run_info.clone(), repo)?,
         }),
 
+        ForgeKind::Gitlab => Box::new(GitlabForge {
+            effects,
+            git_run_info,
+            repo,
+            dag,
+            event_log_db,
+            client: GitlabForge::client(git_run_info.clone(), repo)?,
+        }),
+
+        ForgeKind::Gerrit => Box::new(GerritForge {
+            effects,
+            git_run_info,
+            repo,
+            dag,
+            event_log_db,
+        }),
+
+        ForgeKind::External => {
+            let name = match external_forge_name {
+                Some(name) => name,
+                None => eyre::bail!(
+                    "The external forge requires the `branchless.submit.externalForge` config option to be set"
+                ),
+            };
+            Box::new(ExternalForge {
+                effects,
+                git_run_info,
+                repo,
+                dag,
+                event_log_db,
+                references_snapshot,
+                name,
+            })
+        }
+
         ForgeKind::Phabricator => Box::new(PhabricatorForge {
             effects,
             git_run_info,
@@ -599,6 +1343,16 @@ fn select_forge<'a>(
             event_log_db,
             revset,
         }),
+
+        ForgeKind::SendEmail => Box::new(SendEmailForge {
+            effects,
+            git_run_info,
+            repo,
+            dag,
+            event_log_db,
+            series_commit_oids: Default::default(),
+            is_sent: false,
+        }),
     };
-    Ok(forge)
+    Ok((forge_kind, forge))
 }
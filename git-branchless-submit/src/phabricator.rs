@@ -10,6 +10,7 @@ use std::time::SystemTime;
 
 use cursive_core::theme::Effect;
 use cursive_core::utils::markup::StyledString;
+use eyre::Context;
 use git_branchless_opts::Revset;
 use git_branchless_test::{
     run_tests, FixInfo, ResolvedTestOptions, TestOutput, TestResults, TestStatus,
@@ -22,20 +23,28 @@ use lib::core::dag::{CommitSet, Dag};
 use lib::core::effects::{Effects, OperationType, WithProgress};
 use lib::core::eventlog::EventLogDb;
 use lib::core::formatting::StyledStringBuilder;
+use lib::core::review_status::{ReviewState, ReviewStatus};
 use lib::core::rewrite::{
-    execute_rebase_plan, BuildRebasePlanError, BuildRebasePlanOptions, ExecuteRebasePlanOptions,
-    ExecuteRebasePlanResult, RebasePlanBuilder, RebasePlanPermissions, RepoResource,
+    execute_rebase_plan, BuildRebasePlanError, BuildRebasePlanOptions, EmptyCommitsMode,
+    ExecuteRebasePlanOptions, ExecuteRebasePlanResult, RebasePlanBuilder, RebasePlanPermissions,
+    RepoResource,
+};
+use lib::git::{
+    Commit, ConfigRead, GitRunInfo, GitRunOpts, MaybeZeroOid, NonZeroOid, Repo, RepoError,
+    TestCommand,
 };
-use lib::git::{Commit, GitRunInfo, MaybeZeroOid, NonZeroOid, Repo, RepoError, TestCommand};
 use lib::try_exit_code;
 use lib::util::{ExitCode, EyreExitOr};
 use rayon::ThreadPoolBuilder;
 use regex::bytes::Regex;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tracing::{instrument, warn};
 
-use crate::{CommitStatus, CreateStatus, Forge, SubmitOptions, SubmitStatus, STYLE_PUSHED};
+use crate::{
+    CommitStatus, CreateStatus, Forge, ReviewAssignment, SubmitOptions, SubmitStatus, STYLE_PUSHED,
+};
 
 /// Wrapper around the Phabricator "ID" type. (This is *not* a PHID, just a
 /// regular ID).
@@ -60,17 +69,62 @@ struct DifferentialQueryRequest {
     phids: Vec<Phid>,
 }
 
-#[derive(Debug, Serialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Serialize, Eq, PartialEq)]
 struct DifferentialEditRequest {
-    #[serde(rename = "objectIdentifier")]
-    id: Id, // could also be a PHID
+    /// The revision to edit, or `None` to create a new revision.
+    #[serde(rename = "objectIdentifier", skip_serializing_if = "Option::is_none")]
+    id: Option<Id>, // could also be a PHID
     transactions: Vec<DifferentialEditTransaction>,
 }
 
-#[derive(Debug, Default, Serialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Serialize, Eq, PartialEq)]
 struct DifferentialEditTransaction {
     r#type: String,
-    value: Vec<Phid>,
+    value: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct DifferentialEditResponse {
+    object: DifferentialEditObjectResponse,
+}
+
+#[derive(Debug, Deserialize)]
+struct DifferentialEditObjectResponse {
+    id: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Eq, PartialEq)]
+struct DifferentialCreateRawDiffRequest {
+    diff: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DifferentialCreateRawDiffResponse {
+    phid: Phid,
+}
+
+#[derive(Clone, Debug, Serialize, Eq, PartialEq)]
+struct SearchRequest {
+    constraints: SearchConstraints,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Eq, PartialEq)]
+struct SearchConstraints {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    usernames: Vec<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    slugs: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    data: Vec<SearchResultResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResultResponse {
+    phid: Phid,
 }
 
 #[derive(Debug, Deserialize)]
@@ -96,6 +150,29 @@ impl<T> ConduitResponse<T> {
     }
 }
 
+/// The response format of the Conduit HTTP API, which differs from the format
+/// printed by `arc call-conduit`.
+#[derive(Debug, Deserialize)]
+struct HttpConduitResponse<T> {
+    result: Option<T>,
+    error_code: Option<String>,
+    error_info: Option<String>,
+}
+
+impl<T> HttpConduitResponse<T> {
+    fn check_err(self) -> std::result::Result<T, String> {
+        let Self {
+            result,
+            error_code,
+            error_info,
+        } = self;
+        match (error_code, error_info) {
+            (Some(error_code), error_info) => Err(error_info.unwrap_or(error_code)),
+            (None, _) => result.ok_or_else(|| "(no error message)".to_string()),
+        }
+    }
+}
+
 impl<T: Default> Default for ConduitResponse<T> {
     fn default() -> Self {
         Self {
@@ -113,6 +190,12 @@ struct DifferentialQueryRevisionResponse {
     #[serde(default)]
     hashes: Vec<(String, String)>,
 
+    #[serde(default, rename = "statusName")]
+    status_name: String,
+
+    #[serde(default)]
+    uri: Option<String>,
+
     #[serde(default)]
     auxiliary: DifferentialQueryAuxiliaryResponse,
 }
@@ -177,6 +260,47 @@ pub enum Error {
         args: Vec<String>,
     },
 
+    #[error("could not serialize request for Conduit method {method}: {source}")]
+    SerializeConduitRequest {
+        source: serde_json::Error,
+        method: String,
+    },
+
+    #[error("could not connect to the Conduit API for method {method}: {source}")]
+    ConduitHttpTransport {
+        source: Box<ureq::Transport>,
+        method: String,
+    },
+
+    #[error("could not call Conduit method {method} (HTTP status {status}): {message}")]
+    ConduitHttpStatus {
+        status: u16,
+        message: String,
+        method: String,
+    },
+
+    #[error("could not read response from Conduit method {method}: {source}")]
+    ReadConduitResponse { source: io::Error, method: String },
+
+    #[error("could not call Conduit method {method} (exit code {exit_code}): {message}")]
+    CallConduit {
+        exit_code: i32,
+        message: String,
+        method: String,
+    },
+
+    #[error(
+        "could not parse response from Conduit method {method}: {source}; with output: {output}"
+    )]
+    ParseConduitResponse {
+        source: serde_json::Error,
+        output: String,
+        method: String,
+    },
+
+    #[error("could not read Conduit API configuration: {source}")]
+    ReadConfig { source: eyre::Error },
+
     #[error("could not parse response when running `arc {args}`: {source}; with output: {output}", args = args.join(" "))]
     ParseResponse {
         source: serde_json::Error,
@@ -223,6 +347,110 @@ fn should_mock() -> bool {
     std::env::var_os(SHOULD_MOCK_ENV_KEY).is_some()
 }
 
+/// The location of and credentials for the Conduit API of a Phabricator
+/// instance.
+#[derive(Clone, Debug)]
+pub struct ConduitApi {
+    /// The base URL of the Phabricator instance, such as
+    /// `https://phabricator.example.com`.
+    pub uri: String,
+
+    /// The Conduit API token to authenticate with.
+    pub token: String,
+}
+
+/// Get the Conduit API to call directly, if configured with
+/// `branchless.submit.phabricator.conduitUri` and
+/// `branchless.submit.phabricator.conduitToken`. If not configured, the `arc`
+/// command-line utility is used instead.
+pub fn native_conduit_api(repo: &Repo) -> eyre::Result<Option<ConduitApi>> {
+    let config = repo.get_readonly_config()?;
+    let uri: Option<String> = config.get("branchless.submit.phabricator.conduitUri")?;
+    let token: Option<String> = config.get("branchless.submit.phabricator.conduitToken")?;
+    match (uri, token) {
+        (Some(uri), Some(token)) => Ok(Some(ConduitApi {
+            uri: uri.trim_end_matches('/').to_owned(),
+            token,
+        })),
+        (Some(_), None) | (None, Some(_)) => {
+            warn!("Both the Conduit URI and token must be set to call the Conduit API directly");
+            Ok(None)
+        }
+        (None, None) => Ok(None),
+    }
+}
+
+/// Stores the contents of each commit as of when it was last submitted to its
+/// Differential revision, so that commits which haven't changed since then
+/// can be skipped.
+struct SubmittedContentDb<'conn> {
+    conn: &'conn rusqlite::Connection,
+}
+
+/// The contents of a commit which determine whether its revision needs to be
+/// updated: the OID of its tree and the hash of its message.
+#[derive(Debug, Eq, PartialEq)]
+struct SubmittedContent {
+    tree_oid: String,
+    message_hash: String,
+}
+
+impl<'conn> SubmittedContentDb<'conn> {
+    fn new(conn: &'conn rusqlite::Connection) -> eyre::Result<Self> {
+        conn.execute(
+            "
+CREATE TABLE IF NOT EXISTS phabricator_submitted_contents (
+    revision_id TEXT PRIMARY KEY NOT NULL,
+    tree_oid TEXT NOT NULL,
+    message_hash TEXT NOT NULL
+)
+",
+            rusqlite::params![],
+        )
+        .wrap_err("Creating `phabricator_submitted_contents` table")?;
+        Ok(Self { conn })
+    }
+
+    fn get(&self, id: &Id) -> eyre::Result<Option<SubmittedContent>> {
+        let Id(id) = id;
+        let mut stmt = self.conn.prepare(
+            "
+SELECT tree_oid, message_hash
+FROM phabricator_submitted_contents
+WHERE revision_id = ?
+",
+        )?;
+        let mut rows = stmt
+            .query_map(rusqlite::params![id], |row| {
+                Ok(SubmittedContent {
+                    tree_oid: row.get("tree_oid")?,
+                    message_hash: row.get("message_hash")?,
+                })
+            })
+            .wrap_err("Querying submitted contents")?;
+        Ok(rows.next().transpose()?)
+    }
+
+    fn set(&self, id: &Id, content: &SubmittedContent) -> eyre::Result<()> {
+        let Id(id) = id;
+        let SubmittedContent {
+            tree_oid,
+            message_hash,
+        } = content;
+        self.conn
+            .execute(
+                "
+INSERT OR REPLACE INTO phabricator_submitted_contents
+    (revision_id, tree_oid, message_hash)
+VALUES (?, ?, ?)
+",
+                rusqlite::params![id, tree_oid, message_hash],
+            )
+            .wrap_err("Saving submitted contents")?;
+        Ok(())
+    }
+}
+
 /// The [Phabricator](https://en.wikipedia.org/wiki/Phabricator) code review system.
 ///
 /// Note that Phabricator is no longer actively maintained, but many
@@ -246,10 +474,10 @@ impl Forge for PhabricatorForge<'_> {
     ) -> eyre::Result<std::result::Result<HashMap<NonZeroOid, CommitStatus>, ExitCode>> {
         let commit_oids = self.dag.commit_set_to_vec(&commit_set)?;
         let commit_oid_to_revision: HashMap<NonZeroOid, Option<Id>> = commit_oids
-            .into_iter()
+            .iter()
             .map(|commit_oid| -> eyre::Result<_> {
-                let revision_id = self.get_revision_id(commit_oid)?;
-                Ok((commit_oid, revision_id))
+                let revision_id = self.get_revision_id(*commit_oid)?;
+                Ok((*commit_oid, revision_id))
             })
             .try_collect()?;
 
@@ -257,7 +485,10 @@ impl Forge for PhabricatorForge<'_> {
             Default::default()
         } else {
             self.query_revisions(&DifferentialQueryRequest {
-                ids: commit_oid_to_revision.values().flatten().cloned().collect(),
+                ids: commit_oids
+                    .iter()
+                    .filter_map(|commit_oid| commit_oid_to_revision[commit_oid].clone())
+                    .collect(),
                 phids: Default::default(),
             })?
         };
@@ -286,33 +517,43 @@ impl Forge for PhabricatorForge<'_> {
             })
             .collect();
 
-        let statuses = commit_oid_to_revision
-            .into_iter()
-            .map(|(commit_oid, id)| {
-                let status = CommitStatus {
-                    submit_status: match id {
-                        Some(id) => match commit_hashes.get(&id) {
-                            Some(remote_commit_oid) => {
-                                if remote_commit_oid == &commit_oid {
-                                    SubmitStatus::UpToDate
-                                } else {
-                                    SubmitStatus::NeedsUpdate
-                                }
-                            }
-                            None => {
+        let conn = self.repo.get_db_conn()?;
+        let submitted_content_db = SubmittedContentDb::new(&conn)?;
+        let mut statuses = HashMap::new();
+        for (commit_oid, id) in commit_oid_to_revision {
+            let submit_status = match &id {
+                Some(id) => match commit_hashes.get(id) {
+                    Some(remote_commit_oid) if remote_commit_oid == &commit_oid => {
+                        SubmitStatus::UpToDate
+                    }
+                    remote_commit_oid => {
+                        // The commit may have been rewritten without changing
+                        // its contents, such as by amending its timestamp, in
+                        // which case there's no need to submit it again.
+                        if submitted_content_db.get(id)?
+                            == Some(self.get_submitted_content(commit_oid)?)
+                        {
+                            SubmitStatus::UpToDate
+                        } else {
+                            if remote_commit_oid.is_none() {
                                 warn!(?commit_oid, ?id, "No remote commit hash found for commit");
-                                SubmitStatus::NeedsUpdate
                             }
-                        },
-                        None => SubmitStatus::Unsubmitted,
-                    },
+                            SubmitStatus::NeedsUpdate
+                        }
+                    }
+                },
+                None => SubmitStatus::Unsubmitted,
+            };
+            statuses.insert(
+                commit_oid,
+                CommitStatus {
+                    submit_status,
                     remote_name: None,
-                    local_commit_name: None,
+                    local_commit_name: id.map(|id| id.to_string()),
                     remote_commit_name: None,
-                };
-                (commit_oid, status)
-            })
-            .collect();
+                },
+            );
+        }
         Ok(Ok(statuses))
     }
 
@@ -325,8 +566,8 @@ impl Forge for PhabricatorForge<'_> {
         let SubmitOptions {
             create: _,
             draft,
-            execution_strategy,
-            num_jobs,
+            execution_strategy: _,
+            num_jobs: _,
             message: _,
         } = options;
 
@@ -355,153 +596,33 @@ impl Forge for PhabricatorForge<'_> {
             force_in_memory: true,
             force_on_disk: false,
             resolve_merge_conflicts: false,
+            reset_author: false,
+            committer_as_author: false,
+            signature_commit_oids: None,
             check_out_commit_options: CheckOutCommitOptions {
                 render_smartlog: false,
                 ..Default::default()
             },
+            empty_commits: EmptyCommitsMode::Drop,
         };
         let permissions =
             RebasePlanPermissions::verify_rewrite_set(self.dag, build_options, &commit_set)
                 .map_err(|err| Error::VerifyPermissions { source: err })?
                 .map_err(Error::BuildRebasePlan)?;
-        let command = if !should_mock() {
-            let mut args = vec!["arc", "diff", "--create", "--verbatim", "--allow-untracked"];
-            if *draft {
-                args.push("--draft");
-            }
-            args.extend(["--", "HEAD^"]);
-            TestCommand::Args(args.into_iter().map(ToString::to_string).collect())
-        } else {
-            TestCommand::String(
-                r#"git commit --amend --message "$(git show --no-patch --format=%B HEAD)
-
-Differential Revision: https://phabricator.example.com/D000$(git rev-list --count HEAD)
-            "
-            "#
-                .to_string(),
-            )
-        };
-
-        let test_results = match run_tests(
-            now,
-            self.effects,
-            self.git_run_info,
-            self.dag,
-            self.repo,
-            self.event_log_db,
-            self.revset,
-            &commits,
-            &ResolvedTestOptions {
-                command,
-                execution_strategy: *execution_strategy,
-                search_strategy: None,
-                is_dry_run: false,
-                use_cache: false,
-                is_interactive: false,
-                num_jobs: *num_jobs,
-                verbosity: Verbosity::None,
-                fix_options: Some((execute_options.clone(), permissions.clone())),
-            },
-        ) {
-            Ok(Ok(test_results)) => test_results,
-            Ok(Err(exit_code)) => return Ok(Err(exit_code)),
-            Err(err) => return Err(Error::ExecuteArcDiff { source: err }.into()),
-        };
-
-        let TestResults {
-            search_bounds: _,
-            test_outputs,
-            testing_aborted_error,
-        } = test_results;
-        if let Some(testing_aborted_error) = testing_aborted_error {
-            let TestingAbortedError {
-                commit_oid,
-                exit_code,
-            } = testing_aborted_error;
-            writeln!(
-                self.effects.get_output_stream(),
-                "Uploading was aborted with exit code {exit_code} due to commit {}",
-                self.effects.get_glyphs().render(
-                    self.repo
-                        .friendly_describe_commit_from_oid(self.effects.get_glyphs(), commit_oid)?
-                )?,
-            )?;
-            return Ok(Err(ExitCode(1)));
-        }
-
-        let rebase_plan = {
-            let mut builder = RebasePlanBuilder::new(self.dag, permissions);
-            for (commit_oid, test_output) in test_outputs {
-                let head_commit_oid = match test_output.test_status {
-                    TestStatus::CheckoutFailed
-                    | TestStatus::SpawnTestFailed(_)
-                    | TestStatus::TerminatedBySignal
-                    | TestStatus::AlreadyInProgress
-                    | TestStatus::ReadCacheFailed(_)
-                    | TestStatus::Indeterminate { .. }
-                    | TestStatus::Abort { .. }
-                    | TestStatus::Failed { .. } => {
-                        self.render_failed_test(commit_oid, &test_output)?;
-                        return Ok(Err(ExitCode(1)));
-                    }
-                    TestStatus::Passed {
-                        cached: _,
-                        fix_info:
-                            FixInfo {
-                                head_commit_oid,
-                                snapshot_tree_oid: _,
-                            },
-                        interactive: _,
-                    } => head_commit_oid,
-                };
-
-                let commit = self.repo.find_commit_or_fail(commit_oid)?;
-                builder.move_subtree(commit.get_oid(), commit.get_parent_oids())?;
-                builder.replace_commit(commit.get_oid(), head_commit_oid.unwrap_or(commit_oid))?;
-            }
-
-            let pool = ThreadPoolBuilder::new().build()?;
-            let repo_pool = RepoResource::new_pool(self.repo)?;
-            match builder.build(self.effects, &pool, &repo_pool)? {
-                Ok(Some(rebase_plan)) => rebase_plan,
-                Ok(None) => return Ok(Ok(Default::default())),
-                Err(err) => {
-                    err.describe(self.effects, self.repo, self.dag)?;
-                    return Ok(Err(ExitCode(1)));
-                }
-            }
-        };
-
-        let rewritten_oids = match execute_rebase_plan(
-            self.effects,
-            self.git_run_info,
-            self.repo,
-            self.event_log_db,
-            &rebase_plan,
-            &execute_options,
-        )? {
-            ExecuteRebasePlanResult::Succeeded {
-                rewritten_oids: Some(rewritten_oids),
-            } => rewritten_oids,
-            ExecuteRebasePlanResult::Succeeded {
-                rewritten_oids: None,
-            } => {
-                warn!("No rewritten commit OIDs were produced by rebase plan execution");
-                Default::default()
-            }
-            ExecuteRebasePlanResult::DeclinedToMerge {
-                failed_merge_info: _,
-            } => {
-                writeln!(
-                    self.effects.get_error_stream(),
-                    "BUG: Merge failed, but rewording shouldn't cause any merge failures."
-                )?;
-                return Ok(Err(ExitCode(1)));
-            }
-            ExecuteRebasePlanResult::Failed { exit_code } => {
-                return Ok(Err(exit_code));
+        let replacements = match native_conduit_api(self.repo)? {
+            Some(conduit_api) => {
+                self.create_revisions_natively(&conduit_api, &commit_oids, *draft)?
             }
+            None => try_exit_code!(self.create_revisions_with_arc(
+                now,
+                &commits,
+                options,
+                &execute_options,
+                &permissions,
+            )?),
         };
+        let rewritten_oids =
+            try_exit_code!(self.rewrite_commits(replacements, permissions, &execute_options)?);
 
         let mut create_statuses = HashMap::new();
         for commit_oid in commit_oids {
@@ -540,6 +661,10 @@ Differential Revision: https://phabricator.example.com/D000$(git rev-list --coun
             );
         }
 
+        if !should_mock() {
+            self.assign_reviewers(&commits, &create_statuses)?;
+        }
+
         let final_commit_oids: CommitSet = create_statuses
             .values()
             .map(|create_status| {
@@ -560,6 +685,7 @@ Differential Revision: https://phabricator.example.com/D000$(git rev-list --coun
             Ok(()) => {}
             Err(exit_code) => return Ok(Err(exit_code)),
         }
+        self.record_submitted_content(&self.dag.commit_set_to_vec(&final_commit_oids)?)?;
 
         Ok(Ok(create_statuses))
     }
@@ -581,9 +707,17 @@ Differential Revision: https://phabricator.example.com/D000$(git rev-list --coun
         let commit_set = commits.keys().copied().collect();
         // Sort for consistency with `update_dependencies`.
         let commit_oids = self.dag.sort(&commit_set)?;
+        let reworded_commit_oids = self.find_reworded_commits(&commit_oids)?;
+        if native_conduit_api(self.repo)?.is_some() {
+            self.update_revisions_natively(&commit_oids, message.as_deref())?;
+            try_exit_code!(self.update_dependencies(&commit_set, &CommitSet::empty())?);
+            self.update_revision_messages(&reworded_commit_oids)?;
+            self.record_submitted_content(&commit_oids)?;
+            return Ok(Ok(()));
+        }
         let commits: Vec<_> = commit_oids
-            .into_iter()
-            .map(|commit_oid| self.repo.find_commit_or_fail(commit_oid))
+            .iter()
+            .map(|commit_oid| self.repo.find_commit_or_fail(*commit_oid))
             .try_collect()?;
 
         let now = SystemTime::now();
@@ -603,10 +737,14 @@ Differential Revision: https://phabricator.example.com/D000$(git rev-list --coun
             force_in_memory: true,
             force_on_disk: false,
             resolve_merge_conflicts: false,
+            reset_author: false,
+            committer_as_author: false,
+            signature_commit_oids: None,
             check_out_commit_options: CheckOutCommitOptions {
                 render_smartlog: false,
                 ..Default::default()
             },
+            empty_commits: EmptyCommitsMode::Drop,
         };
         let permissions =
             RebasePlanPermissions::verify_rewrite_set(self.dag, build_options, &commit_set)
@@ -700,78 +838,770 @@ Differential Revision: https://phabricator.example.com/D000$(git rev-list --coun
             return Ok(Err(ExitCode(1)));
         }
 
+        let success_commit_oids = success_commits
+            .into_iter()
+            .map(|(commit_oid, _test_output)| commit_oid)
+            .collect_vec();
         try_exit_code!(self.update_dependencies(
-            &success_commits
-                .into_iter()
-                .map(|(commit_oid, _test_output)| commit_oid)
-                .collect(),
+            &success_commit_oids.iter().copied().collect(),
             &CommitSet::empty()
         )?);
+        let reworded_commit_oids = reworded_commit_oids
+            .into_iter()
+            .filter(|commit_oid| success_commit_oids.contains(commit_oid))
+            .collect_vec();
+        self.update_revision_messages(&reworded_commit_oids)?;
+        self.record_submitted_content(&success_commit_oids)?;
         Ok(Ok(()))
     }
-}
 
-impl PhabricatorForge<'_> {
-    fn query_revisions(
-        &self,
-        request: &DifferentialQueryRequest,
-    ) -> Result<Vec<DifferentialQueryRevisionResponse>> {
-        // The API call seems to hang if we don't specify any IDs; perhaps it's
-        // fetching everything?
-        if request == &DifferentialQueryRequest::default() {
-            return Ok(Default::default());
+    #[instrument]
+    fn mark_ready(&mut self, commits: HashMap<NonZeroOid, CommitStatus>) -> EyreExitOr<()> {
+        if should_mock() {
+            return Ok(Ok(()));
         }
 
-        let args = vec![
-            "call-conduit".to_string(),
-            "--".to_string(),
-            "differential.query".to_string(),
-        ];
-        let mut child = Command::new("arc")
-            .args(&args)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::inherit())
-            .spawn()
-            .map_err(|err| Error::InvokeArc {
-                source: err,
-                args: args.clone(),
-            })?;
-        serde_json::to_writer_pretty(child.stdin.take().unwrap(), request).map_err(|err| {
-            Error::CommunicateWithArc {
-                source: err,
-                args: args.clone(),
+        let commit_set = commits.keys().copied().collect();
+        let mut commit_ids = Vec::new();
+        for commit_oid in self.dag.sort(&commit_set)? {
+            if let Some(id) = self.get_revision_id(commit_oid)? {
+                commit_ids.push((commit_oid, id));
             }
-        })?;
-        let result = child.wait_with_output().map_err(|err| Error::InvokeArc {
-            source: err,
-            args: args.clone(),
-        })?;
-        if !result.status.success() {
-            return Err(Error::QueryDependencies {
-                exit_code: result.status.code().unwrap_or(-1),
-                message: String::from_utf8_lossy(&result.stdout).into_owned(),
-                args,
-            });
         }
+        let draft_ids: HashSet<Id> = self
+            .query_revisions(&DifferentialQueryRequest {
+                ids: commit_ids
+                    .iter()
+                    .map(|(_commit_oid, id)| id.clone())
+                    .collect(),
+                phids: Default::default(),
+            })?
+            .into_iter()
+            .filter(|revision| revision.status_name == "Draft")
+            .map(|revision| revision.id)
+            .collect();
 
-        let output: ConduitResponse<Vec<DifferentialQueryRevisionResponse>> =
-            serde_json::from_slice(&result.stdout).map_err(|err| Error::ParseResponse {
-                source: err,
-                output: String::from_utf8_lossy(&result.stdout).into_owned(),
-                args: args.clone(),
-            })?;
-        let response = output.check_err().map_err(|message| Error::Conduit {
-            request: Box::new(request.clone()),
-            message,
-        })?;
-        Ok(response)
-    }
-
-    /// Query the dependencies of a set of commits from Phabricator (not locally).
-    pub fn query_remote_dependencies(
-        &self,
-        commit_oids: HashSet<NonZeroOid>,
+        for (commit_oid, id) in commit_ids {
+            if !draft_ids.contains(&id) {
+                continue;
+            }
+            writeln!(
+                self.effects.get_output_stream(),
+                "Requesting review of {} for commit {}",
+                self.effects.get_glyphs().render(Self::render_id(&id))?,
+                self.effects.get_glyphs().render(
+                    self.repo
+                        .friendly_describe_commit_from_oid(self.effects.get_glyphs(), commit_oid)?
+                )?,
+            )?;
+            let _response: serde_json::Value = self.call_conduit(
+                "differential.revision.edit",
+                &DifferentialEditRequest {
+                    id: Some(id),
+                    transactions: vec![DifferentialEditTransaction {
+                        r#type: "request-review".to_string(),
+                        value: serde_json::Value::Bool(true),
+                    }],
+                },
+            )?;
+        }
+        Ok(Ok(()))
+    }
+
+    #[instrument]
+    fn query_closed(&mut self, commit_set: CommitSet) -> EyreExitOr<HashMap<NonZeroOid, String>> {
+        if should_mock() {
+            return Ok(Ok(Default::default()));
+        }
+
+        let mut commit_ids = Vec::new();
+        for commit_oid in self.dag.commit_set_to_vec(&commit_set)? {
+            if let Some(id) = self.get_revision_id(commit_oid)? {
+                commit_ids.push((commit_oid, id));
+            }
+        }
+        let status_names: HashMap<Id, String> = self
+            .query_revisions(&DifferentialQueryRequest {
+                ids: commit_ids
+                    .iter()
+                    .map(|(_commit_oid, id)| id.clone())
+                    .collect(),
+                phids: Default::default(),
+            })?
+            .into_iter()
+            .map(|revision| (revision.id, revision.status_name))
+            .collect();
+
+        let mut result = HashMap::new();
+        for (commit_oid, id) in commit_ids {
+            let verb = match status_names
+                .get(&id)
+                .map(|status_name| status_name.as_str())
+            {
+                Some("Closed") => "landed",
+                Some("Abandoned") => "abandoned",
+                Some(_) | None => continue,
+            };
+            result.insert(commit_oid, format!("{id} was {verb}"));
+        }
+        Ok(Ok(result))
+    }
+
+    #[instrument]
+    fn query_review_statuses(
+        &mut self,
+        commit_set: CommitSet,
+    ) -> EyreExitOr<HashMap<NonZeroOid, ReviewStatus>> {
+        if should_mock() {
+            return Ok(Ok(Default::default()));
+        }
+
+        let mut commit_ids = Vec::new();
+        for commit_oid in self.dag.commit_set_to_vec(&commit_set)? {
+            if let Some(id) = self.get_revision_id(commit_oid)? {
+                commit_ids.push((commit_oid, id));
+            }
+        }
+        let revisions: HashMap<Id, DifferentialQueryRevisionResponse> = self
+            .query_revisions(&DifferentialQueryRequest {
+                ids: commit_ids
+                    .iter()
+                    .map(|(_commit_oid, id)| id.clone())
+                    .collect(),
+                phids: Default::default(),
+            })?
+            .into_iter()
+            .map(|revision| (revision.id.clone(), revision))
+            .collect();
+
+        let mut result = HashMap::new();
+        for (commit_oid, id) in commit_ids {
+            let revision = match revisions.get(&id) {
+                Some(revision) => revision,
+                None => continue,
+            };
+            let (state, review_decision) = match revision.status_name.as_str() {
+                "Closed" => (ReviewState::Merged, None),
+                "Abandoned" => (ReviewState::Closed, None),
+                "Draft" => (ReviewState::Draft, None),
+                "Accepted" => (ReviewState::Open, Some("accepted")),
+                "Needs Revision" => (ReviewState::Open, Some("changes requested")),
+                "Changes Planned" => (ReviewState::Open, Some("changes planned")),
+                _ => (ReviewState::Open, None),
+            };
+            result.insert(
+                commit_oid,
+                ReviewStatus {
+                    review_name: id.to_string(),
+                    url: revision.uri.clone(),
+                    state,
+                    review_decision: review_decision.map(ToString::to_string),
+                    ci_status: None,
+                },
+            );
+        }
+        Ok(Ok(result))
+    }
+}
+
+impl PhabricatorForge<'_> {
+    /// Upload each commit as a new revision by running `arc diff` on it, which
+    /// amends the commit to link it to the revision. Returns the amended
+    /// commit for each commit.
+    fn create_revisions_with_arc(
+        &self,
+        now: SystemTime,
+        commits: &[Commit],
+        options: &SubmitOptions,
+        execute_options: &ExecuteRebasePlanOptions,
+        permissions: &RebasePlanPermissions,
+    ) -> EyreExitOr<Vec<(NonZeroOid, NonZeroOid)>> {
+        let SubmitOptions {
+            create: _,
+            draft,
+            execution_strategy,
+            num_jobs,
+            message: _,
+        } = options;
+        let command = if !should_mock() {
+            let mut args = vec!["arc", "diff", "--create", "--verbatim", "--allow-untracked"];
+            if *draft {
+                args.push("--draft");
+            }
+            args.extend(["--", "HEAD^"]);
+            TestCommand::Args(args.into_iter().map(ToString::to_string).collect())
+        } else {
+            TestCommand::String(
+                r#"git commit --amend --message "$(git show --no-patch --format=%B HEAD)
+
+Differential Revision: https://phabricator.example.com/D000$(git rev-list --count HEAD)
+            "
+            "#
+                .to_string(),
+            )
+        };
+
+        let test_results = match run_tests(
+            now,
+            self.effects,
+            self.git_run_info,
+            self.dag,
+            self.repo,
+            self.event_log_db,
+            self.revset,
+            commits,
+            &ResolvedTestOptions {
+                command,
+                execution_strategy: *execution_strategy,
+                search_strategy: None,
+                is_dry_run: false,
+                use_cache: false,
+                is_interactive: false,
+                num_jobs: *num_jobs,
+                verbosity: Verbosity::None,
+                fix_options: Some((execute_options.clone(), permissions.clone())),
+            },
+        ) {
+            Ok(Ok(test_results)) => test_results,
+            Ok(Err(exit_code)) => return Ok(Err(exit_code)),
+            Err(err) => return Err(Error::ExecuteArcDiff { source: err }.into()),
+        };
+
+        let TestResults {
+            search_bounds: _,
+            test_outputs,
+            testing_aborted_error,
+        } = test_results;
+        if let Some(testing_aborted_error) = testing_aborted_error {
+            let TestingAbortedError {
+                commit_oid,
+                exit_code,
+            } = testing_aborted_error;
+            writeln!(
+                self.effects.get_output_stream(),
+                "Uploading was aborted with exit code {exit_code} due to commit {}",
+                self.effects.get_glyphs().render(
+                    self.repo
+                        .friendly_describe_commit_from_oid(self.effects.get_glyphs(), commit_oid)?
+                )?,
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+
+        let mut replacements = Vec::new();
+        for (commit_oid, test_output) in test_outputs {
+            let head_commit_oid = match test_output.test_status {
+                TestStatus::CheckoutFailed
+                | TestStatus::SpawnTestFailed(_)
+                | TestStatus::TerminatedBySignal
+                | TestStatus::AlreadyInProgress
+                | TestStatus::ReadCacheFailed(_)
+                | TestStatus::Indeterminate { .. }
+                | TestStatus::Abort { .. }
+                | TestStatus::Failed { .. } => {
+                    self.render_failed_test(commit_oid, &test_output)?;
+                    return Ok(Err(ExitCode(1)));
+                }
+                TestStatus::Passed {
+                    cached: _,
+                    fix_info:
+                        FixInfo {
+                            head_commit_oid,
+                            snapshot_tree_oid: _,
+                        },
+                    interactive: _,
+                } => head_commit_oid,
+            };
+            replacements.push((commit_oid, head_commit_oid.unwrap_or(commit_oid)));
+        }
+        Ok(Ok(replacements))
+    }
+
+    /// Upload each commit as a new revision by calling the Conduit API, and
+    /// link each commit to its revision by amending its message. Returns the
+    /// amended commit for each commit.
+    fn create_revisions_natively(
+        &self,
+        conduit_api: &ConduitApi,
+        commit_oids: &[NonZeroOid],
+        draft: bool,
+    ) -> eyre::Result<Vec<(NonZeroOid, NonZeroOid)>> {
+        let mut replacements = Vec::new();
+        for commit_oid in commit_oids.iter().copied() {
+            let commit = self.repo.find_commit_or_fail(commit_oid)?;
+            let diff_phid = self.create_raw_diff(&commit)?;
+            let message = commit.get_message_pretty();
+            let message = String::from_utf8_lossy(&message);
+            let (title, summary) = message.split_once('\n').unwrap_or((message.as_ref(), ""));
+            let mut transactions = vec![
+                DifferentialEditTransaction {
+                    r#type: "update".to_string(),
+                    value: serde_json::Value::String(diff_phid.0),
+                },
+                DifferentialEditTransaction {
+                    r#type: "title".to_string(),
+                    value: serde_json::Value::String(title.trim().to_string()),
+                },
+                DifferentialEditTransaction {
+                    r#type: "summary".to_string(),
+                    value: serde_json::Value::String(summary.trim().to_string()),
+                },
+            ];
+            if draft {
+                transactions.push(DifferentialEditTransaction {
+                    r#type: "draft".to_string(),
+                    value: serde_json::Value::Bool(true),
+                });
+            }
+            let DifferentialEditResponse {
+                object: DifferentialEditObjectResponse { id },
+            } = self.call_conduit(
+                "differential.revision.edit",
+                &DifferentialEditRequest {
+                    id: None,
+                    transactions,
+                },
+            )?;
+
+            let id = Id(id.to_string());
+            writeln!(
+                self.effects.get_output_stream(),
+                "Created {} for commit {}",
+                self.effects.get_glyphs().render(Self::render_id(&id))?,
+                self.effects
+                    .get_glyphs()
+                    .render(commit.friendly_describe(self.effects.get_glyphs())?)?,
+            )?;
+            let message = format!(
+                "{}\n\nDifferential Revision: {}/{id}\n",
+                message.trim_end(),
+                conduit_api.uri,
+            );
+            let replacement_commit_oid =
+                commit.amend_commit(None, None, None, Some(message.as_str()), None)?;
+            replacements.push((commit_oid, replacement_commit_oid));
+        }
+        Ok(replacements)
+    }
+
+    /// Upload the current version of each commit to its existing revision by
+    /// calling the Conduit API.
+    fn update_revisions_natively(
+        &self,
+        commit_oids: &[NonZeroOid],
+        message: Option<&str>,
+    ) -> eyre::Result<()> {
+        for commit_oid in commit_oids.iter().copied() {
+            let id = match self.get_revision_id(commit_oid)? {
+                Some(id) => id,
+                None => {
+                    warn!(?commit_oid, "No Phabricator commit ID for commit to update");
+                    continue;
+                }
+            };
+            let commit = self.repo.find_commit_or_fail(commit_oid)?;
+            let diff_phid = self.create_raw_diff(&commit)?;
+            let _response: serde_json::Value = self.call_conduit(
+                "differential.revision.edit",
+                &DifferentialEditRequest {
+                    id: Some(id),
+                    transactions: vec![
+                        DifferentialEditTransaction {
+                            r#type: "update".to_string(),
+                            value: serde_json::Value::String(diff_phid.0),
+                        },
+                        DifferentialEditTransaction {
+                            r#type: "comment".to_string(),
+                            value: serde_json::Value::String(
+                                message.unwrap_or("update").to_string(),
+                            ),
+                        },
+                    ],
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Add the reviewers and projects from each commit's [`ReviewAssignment`]
+    /// to its newly-created revision. Reviewers are looked up by username and
+    /// projects by hashtag.
+    fn assign_reviewers(
+        &self,
+        commits: &[Commit],
+        create_statuses: &HashMap<NonZeroOid, CreateStatus>,
+    ) -> eyre::Result<()> {
+        for commit in commits {
+            let ReviewAssignment { reviewers, labels } =
+                ReviewAssignment::for_commit(self.repo, commit)?;
+            let id = match create_statuses.get(&commit.get_oid()) {
+                Some(create_status) => self.get_revision_id(create_status.final_commit_oid)?,
+                None => None,
+            };
+            let id = match id {
+                Some(id) => id,
+                None => continue,
+            };
+
+            let mut transactions = Vec::new();
+            if !reviewers.is_empty() {
+                let phids = self.search_phids(
+                    "user.search",
+                    SearchConstraints {
+                        usernames: reviewers,
+                        ..Default::default()
+                    },
+                )?;
+                transactions.push(DifferentialEditTransaction {
+                    r#type: "reviewers.add".to_string(),
+                    value: serde_json::to_value(phids)?,
+                });
+            }
+            if !labels.is_empty() {
+                let phids = self.search_phids(
+                    "project.search",
+                    SearchConstraints {
+                        slugs: labels,
+                        ..Default::default()
+                    },
+                )?;
+                transactions.push(DifferentialEditTransaction {
+                    r#type: "projects.add".to_string(),
+                    value: serde_json::to_value(phids)?,
+                });
+            }
+            if transactions.is_empty() {
+                continue;
+            }
+            let _response: serde_json::Value = self.call_conduit(
+                "differential.revision.edit",
+                &DifferentialEditRequest {
+                    id: Some(id),
+                    transactions,
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    fn search_phids(&self, method: &str, constraints: SearchConstraints) -> Result<Vec<Phid>> {
+        let SearchResponse { data } = self.call_conduit(method, &SearchRequest { constraints })?;
+        Ok(data
+            .into_iter()
+            .map(|SearchResultResponse { phid }| phid)
+            .collect())
+    }
+
+    /// Upload the changes of the given commit relative to its parent as a new
+    /// diff, and return the diff's PHID.
+    fn create_raw_diff(&self, commit: &Commit) -> eyre::Result<Phid> {
+        let parent_oid = match commit.get_parent_oids().as_slice() {
+            [parent_oid] => *parent_oid,
+            _ => eyre::bail!(
+                "Can only upload commits with exactly one parent, but {:?} has {}",
+                commit.get_oid(),
+                commit.get_parent_count()
+            ),
+        };
+        let result = self.git_run_info.run_silent(
+            self.repo,
+            None,
+            &[
+                "diff".to_string(),
+                "--no-ext-diff".to_string(),
+                "--no-color".to_string(),
+                "--full-index".to_string(),
+                // Include the entire file as context, like `arc diff` does.
+                "--unified=32767".to_string(),
+                parent_oid.to_string(),
+                commit.get_oid().to_string(),
+            ],
+            GitRunOpts::default(),
+        )?;
+        let DifferentialCreateRawDiffResponse { phid } = self.call_conduit(
+            "differential.createrawdiff",
+            &DifferentialCreateRawDiffRequest {
+                diff: String::from_utf8_lossy(&result.stdout).into_owned(),
+            },
+        )?;
+        Ok(phid)
+    }
+
+    /// Replace each of the given commits with its replacement, and rebase
+    /// their descendants on top of the replacements.
+    fn rewrite_commits(
+        &self,
+        replacements: Vec<(NonZeroOid, NonZeroOid)>,
+        permissions: RebasePlanPermissions,
+        execute_options: &ExecuteRebasePlanOptions,
+    ) -> EyreExitOr<HashMap<NonZeroOid, MaybeZeroOid>> {
+        let rebase_plan = {
+            let mut builder = RebasePlanBuilder::new(self.dag, permissions);
+            for (commit_oid, replacement_commit_oid) in replacements {
+                let commit = self.repo.find_commit_or_fail(commit_oid)?;
+                builder.move_subtree(commit.get_oid(), commit.get_parent_oids())?;
+                builder.replace_commit(commit.get_oid(), replacement_commit_oid)?;
+            }
+
+            let pool = ThreadPoolBuilder::new().build()?;
+            let repo_pool = RepoResource::new_pool(self.repo)?;
+            match builder.build(self.effects, &pool, &repo_pool)? {
+                Ok(Some(rebase_plan)) => rebase_plan,
+                Ok(None) => return Ok(Ok(Default::default())),
+                Err(err) => {
+                    err.describe(self.effects, self.repo, self.dag)?;
+                    return Ok(Err(ExitCode(1)));
+                }
+            }
+        };
+
+        match execute_rebase_plan(
+            self.effects,
+            self.git_run_info,
+            self.repo,
+            self.event_log_db,
+            &rebase_plan,
+            execute_options,
+        )? {
+            ExecuteRebasePlanResult::Succeeded {
+                rewritten_oids: Some(rewritten_oids),
+            } => Ok(Ok(rewritten_oids)),
+            ExecuteRebasePlanResult::Succeeded {
+                rewritten_oids: None,
+            } => {
+                warn!("No rewritten commit OIDs were produced by rebase plan execution");
+                Ok(Ok(Default::default()))
+            }
+            ExecuteRebasePlanResult::DeclinedToMerge {
+                failed_merge_info: _,
+            } => {
+                writeln!(
+                    self.effects.get_error_stream(),
+                    "BUG: Merge failed, but rewording shouldn't cause any merge failures."
+                )?;
+                Ok(Err(ExitCode(1)))
+            }
+            ExecuteRebasePlanResult::Failed { exit_code } => Ok(Err(exit_code)),
+        }
+    }
+
+    /// Get the contents of the commit to compare against the contents recorded
+    /// when it was last submitted. The message is hashed by writing it as a
+    /// blob, so that it's hashed the same way as the tree.
+    fn get_submitted_content(&self, commit_oid: NonZeroOid) -> eyre::Result<SubmittedContent> {
+        let commit = self.repo.find_commit_or_fail(commit_oid)?;
+        let message_hash = self
+            .repo
+            .create_blob_from_contents(&commit.get_message_raw())?;
+        Ok(SubmittedContent {
+            tree_oid: commit.get_tree_oid().to_string(),
+            message_hash: message_hash.to_string(),
+        })
+    }
+
+    /// Find the commits whose messages have changed since they were last
+    /// submitted, such as by `git reword`, which means that the titles and
+    /// summaries of their revisions are stale.
+    fn find_reworded_commits(&self, commit_oids: &[NonZeroOid]) -> eyre::Result<Vec<NonZeroOid>> {
+        let conn = self.repo.get_db_conn()?;
+        let submitted_content_db = SubmittedContentDb::new(&conn)?;
+        let mut result = Vec::new();
+        for commit_oid in commit_oids.iter().copied() {
+            let id = match self.get_revision_id(commit_oid)? {
+                Some(id) => id,
+                None => continue,
+            };
+            if let Some(submitted_content) = submitted_content_db.get(&id)? {
+                if submitted_content.message_hash
+                    != self.get_submitted_content(commit_oid)?.message_hash
+                {
+                    result.push(commit_oid);
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Update the title and summary of the revision for each of the given
+    /// commits to match its commit message. (Neither `arc diff` nor the
+    /// `update` transaction do this for existing revisions.)
+    fn update_revision_messages(&self, commit_oids: &[NonZeroOid]) -> eyre::Result<()> {
+        for commit_oid in commit_oids.iter().copied() {
+            let id = match self.get_revision_id(commit_oid)? {
+                Some(id) => id,
+                None => continue,
+            };
+            writeln!(
+                self.effects.get_output_stream(),
+                "Updating title and summary of {id} to match its commit message"
+            )?;
+            if should_mock() {
+                continue;
+            }
+
+            let commit = self.repo.find_commit_or_fail(commit_oid)?;
+            let title = String::from_utf8_lossy(&commit.get_summary()?).into_owned();
+            let message = String::from_utf8_lossy(&commit.get_message_pretty()).into_owned();
+            let summary = message
+                .lines()
+                .skip(1)
+                .filter(|line| !line.starts_with("Differential Revision:"))
+                .join("\n");
+            let _response: serde_json::Value = self.call_conduit(
+                "differential.revision.edit",
+                &DifferentialEditRequest {
+                    id: Some(id),
+                    transactions: vec![
+                        DifferentialEditTransaction {
+                            r#type: "title".to_string(),
+                            value: serde_json::Value::String(title),
+                        },
+                        DifferentialEditTransaction {
+                            r#type: "summary".to_string(),
+                            value: serde_json::Value::String(summary.trim().to_string()),
+                        },
+                    ],
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Record the contents of the given commits as the latest contents
+    /// submitted to their revisions.
+    fn record_submitted_content(&self, commit_oids: &[NonZeroOid]) -> eyre::Result<()> {
+        let conn = self.repo.get_db_conn()?;
+        let submitted_content_db = SubmittedContentDb::new(&conn)?;
+        for commit_oid in commit_oids.iter().copied() {
+            if let Some(id) = self.get_revision_id(commit_oid)? {
+                submitted_content_db.set(&id, &self.get_submitted_content(commit_oid)?)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Call the given Conduit API method, either directly if configured with
+    /// [`native_conduit_api`], or else via `arc call-conduit`.
+    fn call_conduit<Request, Response>(&self, method: &str, request: &Request) -> Result<Response>
+    where
+        Request: Clone + Debug + Serialize + Send + Sync + 'static,
+        Response: DeserializeOwned,
+    {
+        let conduit_api =
+            native_conduit_api(self.repo).map_err(|err| Error::ReadConfig { source: err })?;
+        let response = match conduit_api {
+            None => {
+                let args = vec![
+                    "call-conduit".to_string(),
+                    "--".to_string(),
+                    method.to_string(),
+                ];
+                let mut child = Command::new("arc")
+                    .args(&args)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::inherit())
+                    .spawn()
+                    .map_err(|err| Error::InvokeArc {
+                        source: err,
+                        args: args.clone(),
+                    })?;
+                serde_json::to_writer_pretty(child.stdin.take().unwrap(), request).map_err(
+                    |err| Error::CommunicateWithArc {
+                        source: err,
+                        args: args.clone(),
+                    },
+                )?;
+                let result = child.wait_with_output().map_err(|err| Error::InvokeArc {
+                    source: err,
+                    args: args.clone(),
+                })?;
+                if !result.status.success() {
+                    return Err(Error::CallConduit {
+                        exit_code: result.status.code().unwrap_or(-1),
+                        message: String::from_utf8_lossy(&result.stdout).into_owned(),
+                        method: method.to_string(),
+                    });
+                }
+                let output: ConduitResponse<Response> = serde_json::from_slice(&result.stdout)
+                    .map_err(|err| Error::ParseResponse {
+                        source: err,
+                        output: String::from_utf8_lossy(&result.stdout).into_owned(),
+                        args: args.clone(),
+                    })?;
+                output.check_err()
+            }
+
+            Some(ConduitApi { uri, token }) => {
+                let mut params = serde_json::to_value(request).map_err(|err| {
+                    Error::SerializeConduitRequest {
+                        source: err,
+                        method: method.to_string(),
+                    }
+                })?;
+                if let Some(params) = params.as_object_mut() {
+                    params.insert(
+                        "__conduit__".to_string(),
+                        serde_json::json!({ "token": token }),
+                    );
+                }
+                let params = params.to_string();
+                let url = format!("{uri}/api/{method}");
+                let response = ureq::post(&url)
+                    .send_form(&[
+                        ("params", params.as_str()),
+                        ("output", "json"),
+                        ("__conduit__", "1"),
+                    ])
+                    .map_err(|err| match err {
+                        ureq::Error::Status(status, response) => Error::ConduitHttpStatus {
+                            status,
+                            message: response.into_string().unwrap_or_default(),
+                            method: method.to_string(),
+                        },
+                        ureq::Error::Transport(transport) => Error::ConduitHttpTransport {
+                            source: Box::new(transport),
+                            method: method.to_string(),
+                        },
+                    })?;
+                let output = response
+                    .into_string()
+                    .map_err(|err| Error::ReadConduitResponse {
+                        source: err,
+                        method: method.to_string(),
+                    })?;
+                let output: HttpConduitResponse<Response> =
+                    serde_json::from_str(&output).map_err(|err| Error::ParseConduitResponse {
+                        source: err,
+                        output,
+                        method: method.to_string(),
+                    })?;
+                output.check_err()
+            }
+        };
+        response.map_err(|message| Error::Conduit {
+            request: Box::new(request.clone()),
+            message,
+        })
+    }
+
+    fn query_revisions(
+        &self,
+        request: &DifferentialQueryRequest,
+    ) -> Result<Vec<DifferentialQueryRevisionResponse>> {
+        // The API call seems to hang if we don't specify any IDs; perhaps it's
+        // fetching everything?
+        if request == &DifferentialQueryRequest::default() {
+            return Ok(Default::default());
+        }
+
+        self.call_conduit("differential.query", request)
+    }
+
+    /// Query the dependencies of a set of commits from Phabricator (not locally).
+    pub fn query_remote_dependencies(
+        &self,
+        commit_oids: HashSet<NonZeroOid>,
     ) -> Result<HashMap<NonZeroOid, HashSet<NonZeroOid>>> {
         // Convert commit hashes to IDs.
         let commit_oid_to_id: HashMap<NonZeroOid, Option<Id>> = {
@@ -809,6 +1639,8 @@ impl PhabricatorForge<'_> {
                     id,
                     phid: _,
                     hashes: _,
+                    status_name: _,
+                    uri: _,
                     auxiliary:
                         DifferentialQueryAuxiliaryResponse {
                             phabricator_depends_on,
@@ -832,6 +1664,8 @@ impl PhabricatorForge<'_> {
                         id,
                         phid,
                         hashes: _,
+                        status_name: _,
+                        uri: _,
                         auxiliary: _,
                     } = revision;
                     (phid, id)
@@ -965,55 +1799,27 @@ impl PhabricatorForge<'_> {
             .map(|response| response.phid)
             .collect();
         let request = DifferentialEditRequest {
-            id,
+            id: Some(id),
             transactions: vec![DifferentialEditTransaction {
                 r#type: "parents.set".to_string(),
-                value: parent_revision_phids,
+                value: serde_json::Value::Array(
+                    parent_revision_phids
+                        .into_iter()
+                        .map(|Phid(phid)| serde_json::Value::String(phid))
+                        .collect(),
+                ),
             }],
         };
-
-        let args = vec![
-            "call-conduit".to_string(),
-            "--".to_string(),
-            "differential.revision.edit".to_string(),
-        ];
-        let mut child = Command::new("arc")
-            .args(&args)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::inherit())
-            .spawn()
-            .map_err(|err| Error::InvokeArc {
-                source: err,
-                args: args.clone(),
-            })?;
-        serde_json::to_writer_pretty(child.stdin.take().unwrap(), &request).map_err(|err| {
-            Error::CommunicateWithArc {
-                source: err,
-                args: args.clone(),
+        match self.call_conduit::<_, serde_json::Value>("differential.revision.edit", &request) {
+            Ok(_response) => Ok(Ok(())),
+            Err(err) => {
+                writeln!(
+                    effects.get_output_stream(),
+                    "Could not update dependencies: {err}"
+                )?;
+                Ok(Err(ExitCode(1)))
             }
-        })?;
-        let result = child.wait_with_output().map_err(|err| Error::InvokeArc {
-            source: err,
-            args: args.clone(),
-        })?;
-        if !result.status.success() {
-            let args = args.join(" ");
-            let exit_code = ExitCode::try_from(result.status)?;
-            let ExitCode(exit_code_isize) = exit_code;
-            writeln!(
-                effects.get_output_stream(),
-                "Could not update dependencies when running `arc {args}` (exit code {exit_code_isize}):",
-            )?;
-            writeln!(
-                effects.get_output_stream(),
-                "{}",
-                String::from_utf8_lossy(&result.stdout)
-            )?;
-            return Ok(Err(exit_code));
         }
-
-        Ok(Ok(()))
     }
 
     /// Given a commit for D123, returns a string like "123" by parsing the
@@ -0,0 +1,184 @@
+//! Retrying forge commands which were rejected because of rate limiting.
+//!
+//! Forges such as GitHub impose "secondary" rate limits on creating and
+//! updating many pull requests in quick succession, which are easy to trip when
+//! submitting a large stack. Rather than aborting partway through the stack,
+//! wait and retry the command with exponential backoff, or for as long as the
+//! forge asked us to wait, if it said.
+
+use std::fmt::Write;
+use std::process::Output;
+use std::thread;
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+use lib::core::effects::Effects;
+use lib::git::{ConfigRead, Repo};
+use regex::Regex;
+use tracing::{instrument, warn};
+
+lazy_static! {
+    static ref RATE_LIMITED_RE: Regex =
+        Regex::new(r"(?i)rate limit|HTTP 429|too many requests|abuse detection")
+            .expect("Rate-limiting regex");
+    static ref RETRY_AFTER_RE: Regex =
+        Regex::new(r"(?im)^\s*retry-after:\s*(\d+)\s*$").expect("Retry-After regex");
+}
+
+/// The delay before the first retry. Each subsequent retry doubles it.
+const INITIAL_DELAY: Duration = Duration::from_secs(5);
+
+/// The maximum delay between retries, unless the forge asks for a longer one.
+const MAX_DELAY: Duration = Duration::from_secs(300);
+
+/// How to retry forge commands which were rate-limited.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// The maximum number of times to retry a single command.
+    pub max_retries: usize,
+
+    /// The delay before the first retry.
+    pub initial_delay: Duration,
+
+    /// The maximum delay between retries.
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Read the retry policy from `branchless.submit.maxRetries`.
+    pub fn from_repo(repo: &Repo) -> eyre::Result<Self> {
+        let max_retries: i32 = repo
+            .get_readonly_config()?
+            .get_or("branchless.submit.maxRetries", 5)?;
+        Ok(Self {
+            max_retries: usize::try_from(max_retries).unwrap_or_default(),
+            initial_delay: INITIAL_DELAY,
+            max_delay: MAX_DELAY,
+        })
+    }
+
+    /// Determine how long to wait before retrying a command which failed with
+    /// the given output on its `attempt`th retry (starting at zero). Returns
+    /// `None` if the command shouldn't be retried.
+    pub fn next_delay(&self, attempt: usize, output: &str) -> Option<Duration> {
+        if attempt >= self.max_retries || !is_rate_limited(output) {
+            return None;
+        }
+        if let Some(retry_after) = parse_retry_after(output) {
+            return Some(retry_after);
+        }
+        let factor = 2_u32.saturating_pow(u32::try_from(attempt).unwrap_or(u32::MAX));
+        Some(
+            self.initial_delay
+                .saturating_mul(factor)
+                .min(self.max_delay),
+        )
+    }
+}
+
+/// Whether the output of a failed forge command indicates that it was
+/// rejected because of rate limiting.
+pub fn is_rate_limited(output: &str) -> bool {
+    RATE_LIMITED_RE.is_match(output)
+}
+
+/// Find a `Retry-After` header in the output of a failed forge command, if it
+/// printed its response headers.
+pub fn parse_retry_after(output: &str) -> Option<Duration> {
+    let captures = RETRY_AFTER_RE.captures(output)?;
+    let seconds: u64 = captures[1].parse().ok()?;
+    Some(Duration::from_secs(seconds))
+}
+
+/// Call `run` to invoke a forge command until it succeeds, or until it fails
+/// for a reason other than rate limiting, or until `policy` says to stop
+/// retrying. Returns the output of the last invocation.
+#[instrument(skip(run))]
+pub fn run_with_retries(
+    effects: &Effects,
+    policy: &RetryPolicy,
+    exe_invocation: &str,
+    mut run: impl FnMut() -> eyre::Result<Output>,
+) -> eyre::Result<Output> {
+    let mut attempt = 0;
+    loop {
+        let output = run()?;
+        if output.status.success() {
+            return Ok(output);
+        }
+
+        let combined_output = format!(
+            "{}\n{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let delay = match policy.next_delay(attempt, &combined_output) {
+            Some(delay) => delay,
+            None => {
+                if is_rate_limited(&combined_output) {
+                    warn!(?attempt, "Giving up on rate-limited command");
+                    writeln!(
+                        effects.get_output_stream(),
+                        "\
+Call to `{exe_invocation}` was still rate-limited after {attempt} retries.
+Commits which were already submitted won't be submitted again, so you can
+resume by running the same command again later."
+                    )?;
+                }
+                return Ok(output);
+            }
+        };
+
+        attempt += 1;
+        writeln!(
+            effects.get_output_stream(),
+            "Call to `{exe_invocation}` was rate-limited, retrying in {}s (retry {attempt} of {})",
+            delay.as_secs(),
+            policy.max_retries,
+        )?;
+        thread::sleep(delay);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_delay() {
+        let policy = RetryPolicy {
+            max_retries: 3,
+            initial_delay: Duration::from_secs(5),
+            max_delay: Duration::from_secs(12),
+        };
+        let rate_limited = "HTTP 403: You have exceeded a secondary rate limit.";
+        assert_eq!(policy.next_delay(0, "HTTP 404: Not Found"), None);
+        assert_eq!(
+            policy.next_delay(0, rate_limited),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(
+            policy.next_delay(1, rate_limited),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            policy.next_delay(2, rate_limited),
+            Some(Duration::from_secs(12))
+        );
+        assert_eq!(policy.next_delay(3, rate_limited), None);
+        assert_eq!(
+            policy.next_delay(0, "HTTP/2.0 429 Too Many Requests\nRetry-After: 60\n"),
+            Some(Duration::from_secs(60))
+        );
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(
+            parse_retry_after("HTTP/2.0 403 Forbidden\r\nretry-after: 30\r\n"),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(parse_retry_after("Retry-After: soon"), None);
+        assert_eq!(parse_retry_after("no headers here"), None);
+    }
+}
@@ -0,0 +1,663 @@
+//! Email backend for submitting patch stacks, for projects which accept
+//! patches by email, such as the Linux kernel.
+
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use cursive_core::theme::Effect;
+use cursive_core::utils::markup::StyledString;
+use eyre::Context;
+use lazy_static::lazy_static;
+use lib::core::change_id::{ChangeId, ChangeIdDb};
+use lib::core::dag::{CommitSet, Dag};
+use lib::core::effects::{Effects, OperationType};
+use lib::core::eventlog::EventLogDb;
+use lib::git::{ConfigRead, GitRunInfo, GitRunOpts, NonZeroOid, Repo};
+use lib::try_exit_code;
+use lib::util::{ExitCode, EyreExitOr};
+use regex::Regex;
+use tracing::{instrument, warn};
+
+use crate::{CommitStatus, CreateStatus, Forge, SubmitOptions, SubmitStatus};
+
+/// Testing environment variable. When this is set, the patches are copied into
+/// the directory at this path instead of being sent with `git send-email`.
+pub const MOCK_OUTBOX_PATH_ENV_KEY: &str = "BRANCHLESS_SUBMIT_SEND_EMAIL_MOCK_OUTBOX_PATH";
+
+/// The cover letter template used if
+/// `branchless.submit.sendEmail.coverLetterTemplate` isn't set. The first line
+/// is the subject, and the rest is the body.
+const DEFAULT_COVER_LETTER_TEMPLATE: &str = "{summary}\n\n{message}\n";
+
+lazy_static! {
+    static ref MESSAGE_ID_RE: Regex =
+        Regex::new(r"(?mi)^Message-ID:\s*(<[^>\s]+>)\s*$").expect("Message-ID regex");
+}
+
+/// The patch which was last sent for a given change.
+#[derive(Debug)]
+struct SentPatch {
+    series_id: String,
+    commit_oid: NonZeroOid,
+    message_id: String,
+}
+
+/// The latest version of a patch series which was sent.
+#[derive(Debug)]
+struct SentSeries {
+    version: i64,
+    thread_message_id: String,
+}
+
+/// Stores the patches and patch series which have been sent, so that later
+/// versions of a series can be numbered and threaded as follow-ups.
+struct SentEmailDb<'conn> {
+    conn: &'conn rusqlite::Connection,
+}
+
+impl<'conn> SentEmailDb<'conn> {
+    fn new(conn: &'conn rusqlite::Connection) -> eyre::Result<Self> {
+        conn.execute(
+            "
+CREATE TABLE IF NOT EXISTS send_email_series (
+    series_id TEXT PRIMARY KEY NOT NULL,
+    version INTEGER NOT NULL,
+    thread_message_id TEXT NOT NULL
+)
+",
+            rusqlite::params![],
+        )
+        .wrap_err("Creating `send_email_series` table")?;
+        conn.execute(
+            "
+CREATE TABLE IF NOT EXISTS send_email_patches (
+    change_id TEXT PRIMARY KEY NOT NULL,
+    series_id TEXT NOT NULL,
+    commit_oid TEXT NOT NULL,
+    message_id TEXT NOT NULL
+)
+",
+            rusqlite::params![],
+        )
+        .wrap_err("Creating `send_email_patches` table")?;
+        Ok(Self { conn })
+    }
+
+    fn get_patch(&self, change_id: &ChangeId) -> eyre::Result<Option<SentPatch>> {
+        let mut stmt = self.conn.prepare(
+            "
+SELECT series_id, commit_oid, message_id
+FROM send_email_patches
+WHERE change_id = ?
+",
+        )?;
+        let mut rows = stmt
+            .query_map(rusqlite::params![change_id.as_str()], |row| {
+                let series_id: String = row.get("series_id")?;
+                let commit_oid: String = row.get("commit_oid")?;
+                let message_id: String = row.get("message_id")?;
+                Ok((series_id, commit_oid, message_id))
+            })
+            .wrap_err("Querying sent patch")?;
+        match rows.next().transpose()? {
+            Some((series_id, commit_oid, message_id)) => Ok(Some(SentPatch {
+                series_id,
+                commit_oid: commit_oid.parse()?,
+                message_id,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    fn set_patch(&self, change_id: &ChangeId, patch: &SentPatch) -> eyre::Result<()> {
+        let SentPatch {
+            series_id,
+            commit_oid,
+            message_id,
+        } = patch;
+        self.conn
+            .execute(
+                "
+INSERT OR REPLACE INTO send_email_patches
+    (change_id, series_id, commit_oid, message_id)
+VALUES (?, ?, ?, ?)
+",
+                rusqlite::params![
+                    change_id.as_str(),
+                    series_id,
+                    commit_oid.to_string(),
+                    message_id
+                ],
+            )
+            .wrap_err("Saving sent patch")?;
+        Ok(())
+    }
+
+    fn get_series(&self, series_id: &str) -> eyre::Result<Option<SentSeries>> {
+        let mut stmt = self.conn.prepare(
+            "
+SELECT version, thread_message_id
+FROM send_email_series
+WHERE series_id = ?
+",
+        )?;
+        let mut rows = stmt
+            .query_map(rusqlite::params![series_id], |row| {
+                Ok(SentSeries {
+                    version: row.get("version")?,
+                    thread_message_id: row.get("thread_message_id")?,
+                })
+            })
+            .wrap_err("Querying sent series")?;
+        Ok(rows.next().transpose()?)
+    }
+
+    fn set_series(&self, series_id: &str, series: &SentSeries) -> eyre::Result<()> {
+        let SentSeries {
+            version,
+            thread_message_id,
+        } = series;
+        self.conn
+            .execute(
+                "
+INSERT OR REPLACE INTO send_email_series
+    (series_id, version, thread_message_id)
+VALUES (?, ?, ?)
+",
+                rusqlite::params![series_id, version, thread_message_id],
+            )
+            .wrap_err("Saving sent series")?;
+        Ok(())
+    }
+}
+
+/// Send a stack of commits as a patch series by email, using `git
+/// format-patch` and `git send-email`.
+///
+/// Each time the stack is submitted, all of its commits are sent again as the
+/// next version of the series (`[PATCH v2 1/3]`, etc.), threaded as a reply to
+/// the cover letter of the previous version. Recipients and SMTP settings are
+/// read by `git send-email` from the `sendemail.*` configuration options.
+#[allow(missing_docs)]
+#[derive(Debug)]
+pub struct SendEmailForge<'a> {
+    pub effects: &'a Effects,
+    pub git_run_info: &'a GitRunInfo,
+    pub repo: &'a Repo,
+    pub dag: &'a mut Dag,
+    pub event_log_db: &'a EventLogDb<'a>,
+
+    /// The commits in the series, in topological order, as of the last call
+    /// to `query_status`.
+    pub series_commit_oids: Vec<NonZeroOid>,
+
+    /// Whether a new version of the series has already been sent during this
+    /// submission.
+    pub is_sent: bool,
+}
+
+impl Forge for SendEmailForge<'_> {
+    #[instrument]
+    fn query_status(
+        &mut self,
+        commit_set: CommitSet,
+    ) -> EyreExitOr<HashMap<NonZeroOid, CommitStatus>> {
+        let conn = self.repo.get_db_conn()?;
+        let change_id_db = ChangeIdDb::new(&conn)?;
+        let sent_email_db = SentEmailDb::new(&conn)?;
+
+        let commit_oids = self.dag.sort(&commit_set)?;
+        let mut result = HashMap::new();
+        let mut any_needs_update = false;
+        for commit_oid in commit_oids.iter().copied() {
+            let change_id = change_id_db.get_change_id(commit_oid)?;
+            let (submit_status, message_id) = match sent_email_db.get_patch(&change_id)? {
+                None => (SubmitStatus::Unsubmitted, None),
+                Some(patch) if patch.commit_oid == commit_oid => {
+                    (SubmitStatus::UpToDate, Some(patch.message_id))
+                }
+                Some(patch) => {
+                    any_needs_update = true;
+                    (SubmitStatus::NeedsUpdate, Some(patch.message_id))
+                }
+            };
+            result.insert(
+                commit_oid,
+                CommitStatus {
+                    submit_status,
+                    remote_name: None,
+                    local_commit_name: Some(change_id.get_short().to_owned()),
+                    remote_commit_name: message_id,
+                },
+            );
+        }
+
+        // Every version of a series includes all of its patches, so patches
+        // which haven't changed are sent again once any other patch has.
+        if any_needs_update {
+            for commit_status in result.values_mut() {
+                if let SubmitStatus::UpToDate = commit_status.submit_status {
+                    commit_status.submit_status = SubmitStatus::NeedsUpdate;
+                }
+            }
+        }
+
+        self.series_commit_oids = commit_oids;
+        Ok(Ok(result))
+    }
+
+    #[instrument]
+    fn create(
+        &mut self,
+        commits: HashMap<NonZeroOid, CommitStatus>,
+        options: &SubmitOptions,
+    ) -> EyreExitOr<HashMap<NonZeroOid, CreateStatus>> {
+        if commits.is_empty() {
+            return Ok(Ok(Default::default()));
+        }
+
+        // The new patches are sent along with the rest of the series.
+        let commit_oids = self.series_commit_oids.clone();
+        try_exit_code!(self.send_series(&commit_oids, options)?);
+
+        let conn = self.repo.get_db_conn()?;
+        let change_id_db = ChangeIdDb::new(&conn)?;
+        let mut create_statuses = HashMap::new();
+        for commit_oid in commits.into_keys() {
+            create_statuses.insert(
+                commit_oid,
+                CreateStatus {
+                    final_commit_oid: commit_oid,
+                    local_commit_name: change_id_db
+                        .get_change_id(commit_oid)?
+                        .get_short()
+                        .to_owned(),
+                },
+            );
+        }
+        Ok(Ok(create_statuses))
+    }
+
+    #[instrument]
+    fn update(
+        &mut self,
+        commits: HashMap<NonZeroOid, CommitStatus>,
+        options: &SubmitOptions,
+    ) -> EyreExitOr<()> {
+        if commits.is_empty() || self.is_sent {
+            return Ok(Ok(()));
+        }
+
+        // Without `--create`, only the patches which were sent before are
+        // included in the new version.
+        let conn = self.repo.get_db_conn()?;
+        let change_id_db = ChangeIdDb::new(&conn)?;
+        let sent_email_db = SentEmailDb::new(&conn)?;
+        let mut commit_oids = Vec::new();
+        for commit_oid in self.series_commit_oids.iter().copied() {
+            let change_id = change_id_db.get_change_id(commit_oid)?;
+            if sent_email_db.get_patch(&change_id)?.is_some() {
+                commit_oids.push(commit_oid);
+            }
+        }
+        self.send_series(&commit_oids, options)
+    }
+
+    #[instrument]
+    fn mark_ready(&mut self, _commits: HashMap<NonZeroOid, CommitStatus>) -> EyreExitOr<()> {
+        // Patches sent as RFCs are marked as ready by sending the next version
+        // without `--draft`.
+        Ok(Ok(()))
+    }
+
+    #[instrument]
+    fn query_closed(&mut self, _commit_set: CommitSet) -> EyreExitOr<HashMap<NonZeroOid, String>> {
+        // There's no record of which patches were applied. Applied patches are
+        // instead detected by `git sync` once the main branch is updated.
+        Ok(Ok(Default::default()))
+    }
+}
+
+impl SendEmailForge<'_> {
+    /// Send the given commits as the next version of their patch series, and
+    /// record the message IDs of the sent patches.
+    #[instrument]
+    fn send_series(
+        &mut self,
+        commit_oids: &[NonZeroOid],
+        options: &SubmitOptions,
+    ) -> EyreExitOr<()> {
+        let SubmitOptions {
+            create: _,
+            draft,
+            execution_strategy: _,
+            num_jobs: _,
+            message,
+        } = options;
+        let (first_oid, last_oid) = match (commit_oids.first(), commit_oids.last()) {
+            (Some(first_oid), Some(last_oid)) => (*first_oid, *last_oid),
+            _ => return Ok(Ok(())),
+        };
+
+        // `git format-patch` formats a range of commits, so the series can't
+        // contain gaps or merges.
+        let first_commit = self.repo.find_commit_or_fail(first_oid)?;
+        let base_oid = match first_commit.get_parent_oids().as_slice() {
+            [base_oid] => *base_oid,
+            _ => {
+                writeln!(
+                    self.effects.get_output_stream(),
+                    "The first commit of a patch series must have exactly 1 parent: {}",
+                    self.effects
+                        .get_glyphs()
+                        .render(first_commit.friendly_describe(self.effects.get_glyphs())?)?,
+                )?;
+                return Ok(Err(ExitCode(1)));
+            }
+        };
+        for (parent_oid, child_oid) in commit_oids.iter().zip(commit_oids.iter().skip(1)) {
+            let child = self.repo.find_commit_or_fail(*child_oid)?;
+            if child.get_parent_oids() != vec![*parent_oid] {
+                let glyphs = self.effects.get_glyphs();
+                writeln!(
+                    self.effects.get_output_stream(),
+                    "\
+The commits in a patch series must form a single range without gaps or merges,
+but {} is not the only parent of {}",
+                    glyphs.render(
+                        self.repo
+                            .friendly_describe_commit_from_oid(glyphs, *parent_oid)?
+                    )?,
+                    glyphs.render(child.friendly_describe(glyphs)?)?,
+                )?;
+                return Ok(Err(ExitCode(1)));
+            }
+        }
+
+        let conn = self.repo.get_db_conn()?;
+        let change_id_db = ChangeIdDb::new(&conn)?;
+        let sent_email_db = SentEmailDb::new(&conn)?;
+        let change_ids: Vec<ChangeId> = commit_oids
+            .iter()
+            .map(|commit_oid| change_id_db.get_change_id(*commit_oid))
+            .collect::<eyre::Result<_>>()?;
+        let mut series_id = None;
+        for change_id in change_ids.iter() {
+            if let Some(patch) = sent_email_db.get_patch(change_id)? {
+                series_id = Some(patch.series_id);
+                break;
+            }
+        }
+        let series_id = series_id.unwrap_or_else(|| change_ids[0].to_string());
+        let previous_series = sent_email_db.get_series(&series_id)?;
+        let version = match &previous_series {
+            Some(previous_series) => previous_series.version + 1,
+            None => 1,
+        };
+
+        let output_dir = tempfile::tempdir()?;
+        let has_cover_letter = commit_oids.len() > 1;
+        let mut args = vec![
+            "format-patch".to_owned(),
+            "--numbered".to_owned(),
+            "--thread=shallow".to_owned(),
+            "--output-directory".to_owned(),
+            output_dir.path().to_string_lossy().into_owned(),
+        ];
+        if has_cover_letter {
+            args.push("--cover-letter".to_owned());
+        }
+        if version > 1 {
+            args.push(format!("--reroll-count={version}"));
+        }
+        if *draft {
+            args.push("--rfc".to_owned());
+        }
+        if let Some(previous_series) = &previous_series {
+            args.push(format!(
+                "--in-reply-to={}",
+                previous_series.thread_message_id
+            ));
+        }
+        args.push(format!("{base_oid}..{last_oid}"));
+        let result = self.git_run_info.run_silent(
+            self.repo,
+            None,
+            &args,
+            GitRunOpts {
+                treat_git_failure_as_error: false,
+                ..Default::default()
+            },
+        )?;
+        if !result.exit_code.is_success() {
+            writeln!(
+                self.effects.get_output_stream(),
+                "Failed to format patches: {}",
+                String::from_utf8_lossy(&result.stderr)
+            )?;
+            return Ok(Err(result.exit_code));
+        }
+        let patch_paths: Vec<PathBuf> = String::from_utf8_lossy(&result.stdout)
+            .lines()
+            .map(|line| output_dir.path().join(line.trim()))
+            .collect();
+
+        if has_cover_letter {
+            let template = self.get_cover_letter_template()?;
+            let (subject, body) = render_cover_letter(
+                &template,
+                &CoverLetterVars {
+                    summary: &String::from_utf8_lossy(&first_commit.get_summary()?),
+                    version,
+                    count: commit_oids.len(),
+                    message: message.as_deref().unwrap_or_default(),
+                },
+            );
+            fill_in_cover_letter(&patch_paths[0], &subject, &body)?;
+        }
+
+        let mut message_ids = Vec::new();
+        for patch_path in patch_paths.iter() {
+            let contents = fs::read_to_string(patch_path)
+                .wrap_err_with(|| format!("Reading patch: {patch_path:?}"))?;
+            let message_id = match MESSAGE_ID_RE.captures(&contents) {
+                Some(captures) => captures[1].to_owned(),
+                None => eyre::bail!("No Message-ID header in patch: {patch_path:?}"),
+            };
+            message_ids.push(message_id);
+        }
+
+        try_exit_code!(self.send_patches(&patch_paths)?);
+        writeln!(
+            self.effects.get_output_stream(),
+            "Sent {} of patch series: {}",
+            self.effects
+                .get_glyphs()
+                .render(StyledString::styled(format!("v{version}"), Effect::Bold))?,
+            self.effects.get_glyphs().render(
+                self.repo
+                    .friendly_describe_commit_from_oid(self.effects.get_glyphs(), last_oid)?
+            )?,
+        )?;
+        self.is_sent = true;
+
+        let patch_message_ids = if has_cover_letter {
+            &message_ids[1..]
+        } else {
+            &message_ids[..]
+        };
+        for ((commit_oid, change_id), message_id) in commit_oids
+            .iter()
+            .zip(change_ids.iter())
+            .zip(patch_message_ids.iter())
+        {
+            sent_email_db.set_patch(
+                change_id,
+                &SentPatch {
+                    series_id: series_id.clone(),
+                    commit_oid: *commit_oid,
+                    message_id: message_id.clone(),
+                },
+            )?;
+        }
+        sent_email_db.set_series(
+            &series_id,
+            &SentSeries {
+                version,
+                thread_message_id: message_ids[0].clone(),
+            },
+        )?;
+        Ok(Ok(()))
+    }
+
+    /// Read the cover letter template from the path in
+    /// `branchless.submit.sendEmail.coverLetterTemplate`, if set.
+    fn get_cover_letter_template(&self) -> eyre::Result<String> {
+        let template_path: Option<String> = self
+            .repo
+            .get_readonly_config()?
+            .get("branchless.submit.sendEmail.coverLetterTemplate")?;
+        let template_path = match template_path {
+            Some(template_path) => PathBuf::from(template_path),
+            None => return Ok(DEFAULT_COVER_LETTER_TEMPLATE.to_owned()),
+        };
+        let template_path = match self.repo.get_working_copy_path() {
+            Some(root) if template_path.is_relative() => root.join(template_path),
+            _ => template_path,
+        };
+        fs::read_to_string(&template_path)
+            .wrap_err_with(|| format!("Reading cover letter template: {template_path:?}"))
+    }
+
+    /// Send the formatted patches with `git send-email`.
+    #[instrument]
+    fn send_patches(&self, patch_paths: &[PathBuf]) -> EyreExitOr<()> {
+        if let Ok(outbox_path) = env::var(MOCK_OUTBOX_PATH_ENV_KEY) {
+            for patch_path in patch_paths {
+                if let Some(file_name) = patch_path.file_name() {
+                    fs::copy(patch_path, Path::new(&outbox_path).join(file_name))?;
+                }
+            }
+            return Ok(Ok(()));
+        }
+
+        let config = self.repo.get_readonly_config()?;
+        let to: Option<String> = config.get("sendemail.to")?;
+        let to_cmd: Option<String> = config.get("sendemail.toCmd")?;
+        if to.is_none() && to_cmd.is_none() {
+            writeln!(
+                self.effects.get_output_stream(),
+                "No recipients configured for the patch series. To configure, run: {}",
+                self.effects.get_glyphs().render(StyledString::styled(
+                    "git config sendemail.to <address>",
+                    Effect::Bold,
+                ))?
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+
+        let event_tx_id = self
+            .event_log_db
+            .make_transaction_id(SystemTime::now(), "send email")?;
+        let (effects, progress) = self.effects.start_operation(OperationType::PushCommits);
+        progress.notify_progress(0, patch_paths.len());
+        // The patches are already threaded by `git format-patch`.
+        let mut args = vec![
+            "send-email".to_owned(),
+            "--no-thread".to_owned(),
+            "--confirm=never".to_owned(),
+        ];
+        args.extend(
+            patch_paths
+                .iter()
+                .map(|patch_path| patch_path.to_string_lossy().into_owned()),
+        );
+        try_exit_code!(self.git_run_info.run(&effects, Some(event_tx_id), &args)?);
+        progress.notify_progress_inc(patch_paths.len());
+        Ok(Ok(()))
+    }
+}
+
+/// The values substituted into the cover letter template.
+#[derive(Debug)]
+struct CoverLetterVars<'a> {
+    /// `{summary}`: the summary line of the first commit in the series.
+    summary: &'a str,
+
+    /// `{version}`: the version number of the series.
+    version: i64,
+
+    /// `{count}`: the number of patches in the series.
+    count: usize,
+
+    /// `{message}`: the message passed with `--message`, if any.
+    message: &'a str,
+}
+
+/// Render the cover letter template. Returns the subject and the body.
+fn render_cover_letter(template: &str, vars: &CoverLetterVars) -> (String, String) {
+    let CoverLetterVars {
+        summary,
+        version,
+        count,
+        message,
+    } = vars;
+    let rendered = template
+        .replace("{summary}", summary)
+        .replace("{version}", &version.to_string())
+        .replace("{count}", &count.to_string())
+        .replace("{message}", message);
+    let (subject, body) = rendered.split_once('\n').unwrap_or((&rendered, ""));
+    (subject.trim().to_owned(), body.trim().to_owned())
+}
+
+/// Replace the placeholders which `git format-patch` leaves in the cover
+/// letter with the rendered subject and body.
+fn fill_in_cover_letter(path: &Path, subject: &str, body: &str) -> eyre::Result<()> {
+    let contents =
+        fs::read_to_string(path).wrap_err_with(|| format!("Reading cover letter: {path:?}"))?;
+    if !contents.contains("*** SUBJECT HERE ***") {
+        warn!(?path, "Cover letter did not contain subject placeholder");
+    }
+    let contents = contents
+        .replace("*** SUBJECT HERE ***", subject)
+        .replace("*** BLURB HERE ***", body);
+    fs::write(path, contents).wrap_err_with(|| format!("Writing cover letter: {path:?}"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_cover_letter() {
+        let vars = CoverLetterVars {
+            summary: "Add foo",
+            version: 2,
+            count: 3,
+            message: "",
+        };
+        assert_eq!(
+            render_cover_letter(DEFAULT_COVER_LETTER_TEMPLATE, &vars),
+            ("Add foo".to_owned(), "".to_owned())
+        );
+        assert_eq!(
+            render_cover_letter(
+                "foo: {summary}\n\nThis series adds foo in {count} patches.\n\nv{version}: {message}\n",
+                &CoverLetterVars {
+                    message: "fix bar",
+                    ..vars
+                }
+            ),
+            (
+                "foo: Add foo".to_owned(),
+                "This series adds foo in 3 patches.\n\nv2: fix bar".to_owned()
+            )
+        );
+    }
+}
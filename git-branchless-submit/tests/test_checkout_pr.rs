@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+use lib::git::GitVersion;
+use lib::testing::{make_git_with_remote_repo, Git, GitRunOptions, GitWrapperWithRemoteRepo};
+
+/// Minimum version due to changes in the output of `git push`.
+const MIN_VERSION: GitVersion = GitVersion(2, 36, 0);
+
+fn mock_env(git: &Git) -> HashMap<String, String> {
+    git.get_base_env(0)
+        .into_iter()
+        .map(|(k, v)| {
+            (
+                k.to_str().unwrap().to_string(),
+                v.to_str().unwrap().to_string(),
+            )
+        })
+        .chain([(
+            git_branchless_submit::github::MOCK_REMOTE_REPO_PATH_ENV_KEY.to_string(),
+            git.repo_path.clone().to_str().unwrap().to_owned(),
+        )])
+        .collect()
+}
+
+#[test]
+fn test_checkout_pr_stack() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir,
+        original_repo: remote_repo,
+        cloned_repo: local_repo,
+    } = make_git_with_remote_repo()?;
+    if remote_repo.get_version()? < MIN_VERSION {
+        return Ok(());
+    }
+
+    remote_repo.init_repo()?;
+    remote_repo.clone_repo_into(&local_repo, &[])?;
+
+    local_repo.detach_head()?;
+    local_repo.commit_file("test1", 1)?;
+    local_repo.commit_file("test2", 2)?;
+    local_repo.branchless_with_options(
+        "submit",
+        &["--create", "--forge", "github"],
+        &GitRunOptions {
+            env: mock_env(&remote_repo),
+            ..Default::default()
+        },
+    )?;
+
+    let reviewer_repo = Git {
+        repo_path: temp_dir.path().join("reviewer"),
+        ..local_repo.clone()
+    };
+    std::fs::create_dir_all(&reviewer_repo.repo_path)?;
+    remote_repo.clone_repo_into(&reviewer_repo, &[])?;
+    reviewer_repo.branchless("init", &[])?;
+
+    {
+        let (stdout, _stderr) = reviewer_repo.branchless_with_options(
+            "checkout-pr",
+            &["https://example.com/mock-github-username/mock-github-repo/pull/2"],
+            &GitRunOptions {
+                env: mock_env(&remote_repo),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> fetch origin mock-github-username/create-test1-txt mock-github-username/create-test2-txt
+        branchless: processing 2 updates: branch mock-github-username/create-test1-txt, branch mock-github-username/create-test2-txt
+        Checked out pull request #1 as branch mock-github-username/create-test1-txt: [1/2] create test1.txt
+        Checked out pull request #2 as branch mock-github-username/create-test2-txt: [2/2] create test2.txt
+        branchless: running command: <git-executable> checkout mock-github-username/create-test2-txt
+        O f777ecc (master) create initial.txt
+        |
+        o 62fc20d (mock-github-username/create-test1-txt) create test1.txt
+        |
+        @ 96d1c37 (> mock-github-username/create-test2-txt) create test2.txt
+        "###);
+    }
+
+    {
+        // The stack can be navigated like a local stack.
+        let (stdout, _stderr) = reviewer_repo.branchless("prev", &[])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout mock-github-username/create-test1-txt
+        O f777ecc (master) create initial.txt
+        |
+        @ 62fc20d (> mock-github-username/create-test1-txt) create test1.txt
+        |
+        o 96d1c37 (mock-github-username/create-test2-txt) create test2.txt
+        "###);
+    }
+
+    {
+        // Checking out the same pull request again leaves the branches alone.
+        let (stdout, _stderr) = reviewer_repo.branchless_with_options(
+            "checkout-pr",
+            &["#2", "--no-checkout"],
+            &GitRunOptions {
+                env: mock_env(&remote_repo),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> fetch origin mock-github-username/create-test1-txt mock-github-username/create-test2-txt
+        Checked out pull request #1 as branch mock-github-username/create-test1-txt: [1/2] create test1.txt
+        Checked out pull request #2 as branch mock-github-username/create-test2-txt: [2/2] create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_checkout_pr_existing_branch() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _temp_dir,
+        original_repo: remote_repo,
+        cloned_repo: local_repo,
+    } = make_git_with_remote_repo()?;
+    if remote_repo.get_version()? < MIN_VERSION {
+        return Ok(());
+    }
+
+    remote_repo.init_repo()?;
+    remote_repo.clone_repo_into(&local_repo, &[])?;
+
+    local_repo.detach_head()?;
+    local_repo.commit_file("test1", 1)?;
+    local_repo.branchless_with_options(
+        "submit",
+        &["--create", "--forge", "github"],
+        &GitRunOptions {
+            env: mock_env(&remote_repo),
+            ..Default::default()
+        },
+    )?;
+    local_repo.run(&[
+        "branch",
+        "-f",
+        "mock-github-username/create-test1-txt",
+        "master",
+    ])?;
+
+    {
+        let (stdout, _stderr) = local_repo.branchless_with_options(
+            "checkout-pr",
+            &["1"],
+            &GitRunOptions {
+                env: mock_env(&remote_repo),
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> fetch origin mock-github-username/create-test1-txt
+        Branch mock-github-username/create-test1-txt already exists and does not match pull
+        request #1. Delete or rename it, then try again.
+        "###);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,79 @@
+#![cfg(unix)]
+
+use std::collections::HashMap;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use lib::testing::{make_git, Git, GitRunOptions};
+
+/// Install a `branchless-forge-test` executable into `bin_dir` which records
+/// each request into `log_dir`, and reports that the first commit needs to be
+/// updated.
+fn install_forge_executable(bin_dir: &Path, log_dir: &Path) -> eyre::Result<()> {
+    let path = bin_dir.join("branchless-forge-test");
+    fs::write(
+        &path,
+        format!(
+            r#"#!/bin/sh
+cat >"{}/$1.json"
+case "$1" in
+query-status)
+    echo '{{"statuses": {{"62fc20d2a290daea0d52bdc2ed2ad4be6491010e": {{"status": "needs_update", "local_commit_name": "R1"}}}}}}'
+    ;;
+*)
+    echo '{{}}'
+    ;;
+esac
+"#,
+            log_dir.display()
+        ),
+    )?;
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
+fn forge_env(git: &Git, bin_dir: &Path) -> HashMap<String, String> {
+    let path = std::env::join_paths(
+        std::iter::once(bin_dir.as_os_str().to_owned())
+            .chain(std::env::split_paths(&git.get_path_for_env()).map(|path| path.into())),
+    )
+    .unwrap();
+    [("PATH".to_string(), path.to_str().unwrap().to_string())]
+        .into_iter()
+        .collect()
+}
+
+#[test]
+fn test_external_forge_protocol() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.run(&["config", "branchless.submit.externalForge", "test"])?;
+
+    let bin_dir = tempfile::tempdir()?;
+    let log_dir = tempfile::tempdir()?;
+    install_forge_executable(bin_dir.path(), log_dir.path())?;
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "submit",
+            &[],
+            &GitRunOptions {
+                env: forge_env(&git, bin_dir.path()),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Updated 1 commit: R1
+        "###);
+    }
+
+    let query_status_request = fs::read_to_string(log_dir.path().join("query-status.json"))?;
+    insta::assert_snapshot!(query_status_request, @r###"{"commits":[{"commit_oid":"62fc20d2a290daea0d52bdc2ed2ad4be6491010e","branch_names":[]}]}"###);
+    let update_request = fs::read_to_string(log_dir.path().join("update.json"))?;
+    insta::assert_snapshot!(update_request, @r###"{"commits":{"62fc20d2a290daea0d52bdc2ed2ad4be6491010e":{"status":"needs_update","remote_name":null,"local_commit_name":"R1","remote_commit_name":null}},"draft":false,"message":null}"###);
+
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use lib::testing::{make_git_with_remote_repo, GitWrapperWithRemoteRepo};
+
+#[test]
+fn test_gerrit_forge_adds_change_ids() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _temp_dir,
+        original_repo: remote_repo,
+        cloned_repo: local_repo,
+    } = make_git_with_remote_repo()?;
+
+    remote_repo.init_repo()?;
+    remote_repo.clone_repo_into(&local_repo, &[])?;
+
+    local_repo.detach_head()?;
+    local_repo.commit_file("test1", 1)?;
+    local_repo.commit_file("test2", 2)?;
+    local_repo.branchless("submit", &["--create", "--forge", "gerrit"])?;
+
+    {
+        let (stdout, _stderr) = local_repo.run(&["log", "--format=%B", "-2", "HEAD"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test2.txt
+
+        Change-Id: I96d1c37a3d4363611c49f7e52186e189a04c531f
+
+        create test1.txt
+
+        Change-Id: I62fc20d2a290daea0d52bdc2ed2ad4be6491010e
+        "###);
+    }
+
+    {
+        let (local_head, _stderr) = local_repo.run(&["rev-parse", "HEAD"])?;
+        let (remote_head, _stderr) = remote_repo.run(&["rev-parse", "refs/for/master"])?;
+        assert_eq!(local_head, remote_head);
+    }
+
+    {
+        // Submitting again keeps the existing change IDs.
+        local_repo.branchless("submit", &["--create", "--forge", "gerrit"])?;
+        let (stdout, _stderr) =
+            local_repo.run(&["log", "--format=%(trailers:key=Change-Id)", "-2", "HEAD"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Change-Id: I96d1c37a3d4363611c49f7e52186e189a04c531f
+
+        Change-Id: I62fc20d2a290daea0d52bdc2ed2ad4be6491010e
+        "###);
+    }
+
+    Ok(())
+}
@@ -139,7 +139,7 @@ fn test_github_forge_reorder_commits() -> eyre::Result<()> {
               "closed": false,
               "isDraft": false,
               "title": "[1/2] create test1.txt",
-              "body": "**Stack:**\n\n* https://example.com/mock-github-username/mock-github-repo/pulls/1\n* https://example.com/mock-github-username/mock-github-repo/pulls/2\n\n\n---\n\ncreate test1.txt\n\n"
+              "body": "create test1.txt\n\n---\n\n**Stack:**\n\n* https://example.com/mock-github-username/mock-github-repo/pulls/1 ← you are here\n* https://example.com/mock-github-username/mock-github-repo/pulls/2\n"
             },
             "mock-github-username/create-test2-txt": {
               "number": 2,
@@ -150,7 +150,7 @@ fn test_github_forge_reorder_commits() -> eyre::Result<()> {
               "closed": false,
               "isDraft": false,
               "title": "[2/2] create test2.txt",
-              "body": "**Stack:**\n\n* https://example.com/mock-github-username/mock-github-repo/pulls/1\n* https://example.com/mock-github-username/mock-github-repo/pulls/2\n\n\n---\n\ncreate test2.txt\n\n"
+              "body": "create test2.txt\n\n---\n\n**Stack:**\n\n* https://example.com/mock-github-username/mock-github-repo/pulls/1\n* https://example.com/mock-github-username/mock-github-repo/pulls/2 ← you are here\n"
             }
           }
         }
@@ -210,7 +210,7 @@ fn test_github_forge_reorder_commits() -> eyre::Result<()> {
               "closed": false,
               "isDraft": false,
               "title": "[2/2] create test1.txt",
-              "body": "**Stack:**\n\n* https://example.com/mock-github-username/mock-github-repo/pulls/2\n* https://example.com/mock-github-username/mock-github-repo/pulls/1\n\n\n---\n\ncreate test1.txt\n\n"
+              "body": "create test1.txt\n\n---\n\n**Stack:**\n\n* https://example.com/mock-github-username/mock-github-repo/pulls/2\n* https://example.com/mock-github-username/mock-github-repo/pulls/1 ← you are here\n"
             },
             "mock-github-username/create-test2-txt": {
               "number": 2,
@@ -221,7 +221,7 @@ fn test_github_forge_reorder_commits() -> eyre::Result<()> {
               "closed": false,
               "isDraft": false,
               "title": "[1/2] create test2.txt",
-              "body": "**Stack:**\n\n* https://example.com/mock-github-username/mock-github-repo/pulls/2\n* https://example.com/mock-github-username/mock-github-repo/pulls/1\n\n\n---\n\ncreate test2.txt\n\n"
+              "body": "create test2.txt\n\n---\n\n**Stack:**\n\n* https://example.com/mock-github-username/mock-github-repo/pulls/2 ← you are here\n* https://example.com/mock-github-username/mock-github-repo/pulls/1\n"
             }
           }
         }
@@ -302,7 +302,7 @@ fn test_github_forge_mock_client_closes_pull_requests() -> eyre::Result<()> {
               "closed": false,
               "isDraft": false,
               "title": "[1/2] create test1.txt",
-              "body": "**Stack:**\n\n* https://example.com/mock-github-username/mock-github-repo/pulls/1\n* https://example.com/mock-github-username/mock-github-repo/pulls/2\n\n\n---\n\ncreate test1.txt\n\n"
+              "body": "create test1.txt\n\n---\n\n**Stack:**\n\n* https://example.com/mock-github-username/mock-github-repo/pulls/1 ← you are here\n* https://example.com/mock-github-username/mock-github-repo/pulls/2\n"
             },
             "mock-github-username/create-test2-txt": {
               "number": 2,
@@ -313,7 +313,7 @@ fn test_github_forge_mock_client_closes_pull_requests() -> eyre::Result<()> {
               "closed": false,
               "isDraft": false,
               "title": "[2/2] create test2.txt",
-              "body": "**Stack:**\n\n* https://example.com/mock-github-username/mock-github-repo/pulls/1\n* https://example.com/mock-github-username/mock-github-repo/pulls/2\n\n\n---\n\ncreate test2.txt\n\n"
+              "body": "create test2.txt\n\n---\n\n**Stack:**\n\n* https://example.com/mock-github-username/mock-github-repo/pulls/1\n* https://example.com/mock-github-username/mock-github-repo/pulls/2 ← you are here\n"
             }
           }
         }
@@ -330,6 +330,7 @@ fn test_github_forge_mock_client_closes_pull_requests() -> eyre::Result<()> {
         Attempting rebase in-memory...
         [1/2] Skipped commit (was already applied upstream): 62fc20d create test1.txt
         [2/2] Committed as: fa46633 create test2.txt
+        Skipped 1 commit already applied upstream: 62fc20d
         branchless: running command: <git-executable> checkout mock-github-username/create-test2-txt
         Your branch and 'origin/mock-github-username/create-test2-txt' have diverged,
         and have 2 and 2 different commits each, respectively.
@@ -357,6 +358,7 @@ fn test_github_forge_mock_client_closes_pull_requests() -> eyre::Result<()> {
             },
         )?;
         insta::assert_snapshot!(stdout, @r###"
+        Retargeting pull request #2 onto master since the pull request for mock-github-username/create-test1-txt is no longer open
         Updating pull request (commit, base branch, title, body) for commit fa46633 create test2.txt
         branchless: running command: <git-executable> push --force-with-lease origin mock-github-username/create-test2-txt
         Updated 1 commit: mock-github-username/create-test2-txt
@@ -392,7 +394,7 @@ fn test_github_forge_mock_client_closes_pull_requests() -> eyre::Result<()> {
               "closed": true,
               "isDraft": false,
               "title": "[1/2] create test1.txt",
-              "body": "**Stack:**\n\n* https://example.com/mock-github-username/mock-github-repo/pulls/1\n* https://example.com/mock-github-username/mock-github-repo/pulls/2\n\n\n---\n\ncreate test1.txt\n\n"
+              "body": "create test1.txt\n\n---\n\n**Stack:**\n\n* https://example.com/mock-github-username/mock-github-repo/pulls/1 ← you are here\n* https://example.com/mock-github-username/mock-github-repo/pulls/2\n"
             },
             "mock-github-username/create-test2-txt": {
               "number": 2,
@@ -403,7 +405,7 @@ fn test_github_forge_mock_client_closes_pull_requests() -> eyre::Result<()> {
               "closed": false,
               "isDraft": false,
               "title": "[1/1] create test2.txt",
-              "body": "**Stack:**\n\n* https://example.com/mock-github-username/mock-github-repo/pulls/2\n\n\n---\n\ncreate test2.txt\n\n"
+              "body": "create test2.txt\n\n---\n\n**Stack:**\n\n* https://example.com/mock-github-username/mock-github-repo/pulls/2 ← you are here\n"
             }
           }
         }
@@ -493,7 +495,7 @@ fn test_github_forge_no_include_unsubmitted_commits_in_stack() -> eyre::Result<(
               "closed": false,
               "isDraft": false,
               "title": "[1/1] create test1.txt",
-              "body": "**Stack:**\n\n* https://example.com/mock-github-username/mock-github-repo/pulls/1\n\n\n---\n\ncreate test1.txt\n\n"
+              "body": "create test1.txt\n\n---\n\n**Stack:**\n\n* https://example.com/mock-github-username/mock-github-repo/pulls/1 ← you are here\n"
             }
           }
         }
@@ -587,7 +589,7 @@ fn test_github_forge_multiple_commits_in_pull_request() -> eyre::Result<()> {
               "closed": false,
               "isDraft": false,
               "title": "[1/1] create test3.txt",
-              "body": "**Stack:**\n\n* https://example.com/mock-github-username/mock-github-repo/pulls/1\n\n\n---\n\ncreate test3.txt\n\n"
+              "body": "create test3.txt\n\n---\n\n**Stack:**\n\n* https://example.com/mock-github-username/mock-github-repo/pulls/1 ← you are here\n"
             }
           }
         }
@@ -596,3 +598,520 @@ fn test_github_forge_multiple_commits_in_pull_request() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_github_forge_branch_template() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _temp_dir,
+        original_repo: remote_repo,
+        cloned_repo: local_repo,
+    } = make_git_with_remote_repo()?;
+    if remote_repo.get_version()? < MIN_VERSION {
+        return Ok(());
+    }
+
+    remote_repo.init_repo()?;
+    remote_repo.clone_repo_into(&local_repo, &[])?;
+
+    local_repo.detach_head()?;
+    local_repo.commit_file("test1", 1)?;
+    local_repo.run(&[
+        "config",
+        "branchless.submit.branchTemplate",
+        "review/{user}/{changeid-or-slug}",
+    ])?;
+    {
+        let (stdout, _stderr) = local_repo.branchless_with_options(
+            "submit",
+            &["--create", "--forge", "github"],
+            &GitRunOptions {
+                env: mock_env(&remote_repo),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> push --set-upstream origin review/mock-github-username/create-test1-txt
+        branch 'review/mock-github-username/create-test1-txt' set up to track 'origin/review/mock-github-username/create-test1-txt'.
+        Updating pull request (title, body) for commit 62fc20d create test1.txt
+        branchless: running command: <git-executable> push --force-with-lease origin review/mock-github-username/create-test1-txt
+        Submitted 1 commit: review/mock-github-username/create-test1-txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_github_forge_undraft() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _temp_dir,
+        original_repo: remote_repo,
+        cloned_repo: local_repo,
+    } = make_git_with_remote_repo()?;
+    if remote_repo.get_version()? < MIN_VERSION {
+        return Ok(());
+    }
+
+    remote_repo.init_repo()?;
+    remote_repo.clone_repo_into(&local_repo, &[])?;
+
+    local_repo.detach_head()?;
+    local_repo.commit_file("test1", 1)?;
+    local_repo.branchless_with_options(
+        "submit",
+        &["--create", "--draft", "--forge", "github"],
+        &GitRunOptions {
+            env: mock_env(&remote_repo),
+            ..Default::default()
+        },
+    )?;
+
+    let client = MockGithubClient {
+        remote_repo_path: remote_repo.repo_path.clone(),
+    };
+    let is_draft = |client: &MockGithubClient| {
+        client.with_state_mut(|state| {
+            Ok(state
+                .pull_requests
+                .values()
+                .map(|pull_request_info| pull_request_info.is_draft)
+                .collect::<Vec<_>>())
+        })
+    };
+    assert_eq!(is_draft(&client)?, vec![true]);
+
+    {
+        let (stdout, _stderr) = local_repo.branchless_with_options(
+            "submit",
+            &["--undraft", "--forge", "github"],
+            &GitRunOptions {
+                env: mock_env(&remote_repo),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Marking pull request ready for review for commit 62fc20d create test1.txt
+        Skipped 1 commit (already up-to-date): mock-github-username/create-test1-txt
+        "###);
+    }
+    assert_eq!(is_draft(&client)?, vec![false]);
+
+    Ok(())
+}
+
+#[test]
+fn test_github_forge_sync_status() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _temp_dir,
+        original_repo: remote_repo,
+        cloned_repo: local_repo,
+    } = make_git_with_remote_repo()?;
+    if remote_repo.get_version()? < MIN_VERSION {
+        return Ok(());
+    }
+
+    remote_repo.init_repo()?;
+    remote_repo.clone_repo_into(&local_repo, &[])?;
+
+    local_repo.detach_head()?;
+    local_repo.commit_file("test1", 1)?;
+    local_repo.commit_file("test2", 2)?;
+    local_repo.branchless_with_options(
+        "submit",
+        &["--create", "--forge", "github"],
+        &GitRunOptions {
+            env: mock_env(&remote_repo),
+            ..Default::default()
+        },
+    )?;
+
+    {
+        let (stdout, _stderr) = local_repo.branchless_with_options(
+            "submit",
+            &["--sync-status", "--forge", "github"],
+            &GitRunOptions {
+                env: mock_env(&remote_repo),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        No commits have code reviews which were merged or closed.
+        "###);
+    }
+
+    rebase_and_merge(&remote_repo, "mock-github-username/create-test1-txt")?;
+    {
+        let (stdout, _stderr) = local_repo.branchless_with_options(
+            "submit",
+            &["--sync-status", "--dry-run", "--forge", "github"],
+            &GitRunOptions {
+                env: mock_env(&remote_repo),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Would hide commit: 62fc20d create test1.txt (pull request #1 was closed)
+        Would delete 1 branch: mock-github-username/create-test1-txt
+        "###);
+    }
+    {
+        let (stdout, _stderr) = local_repo.branchless_with_options(
+            "submit",
+            &["--sync-status", "--forge", "github"],
+            &GitRunOptions {
+                env: mock_env(&remote_repo),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Hid commit: 62fc20d create test1.txt (pull request #1 was closed)
+        Deleted 1 branch: mock-github-username/create-test1-txt
+        To unhide this 1 commit, run: git undo
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_github_forge_output_json() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _temp_dir,
+        original_repo: remote_repo,
+        cloned_repo: local_repo,
+    } = make_git_with_remote_repo()?;
+    if remote_repo.get_version()? < MIN_VERSION {
+        return Ok(());
+    }
+
+    remote_repo.init_repo()?;
+    remote_repo.clone_repo_into(&local_repo, &[])?;
+
+    local_repo.detach_head()?;
+    local_repo.commit_file("test1", 1)?;
+    local_repo.commit_file("test2", 2)?;
+
+    {
+        let (stdout, _stderr) = local_repo.branchless_with_options(
+            "submit",
+            &["--forge", "github", "--output", "json"],
+            &GitRunOptions {
+                env: mock_env(&remote_repo),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        {
+          "dry_run": false,
+          "commits": [
+            {
+              "commit_oid": "62fc20d2a290daea0d52bdc2ed2ad4be6491010e",
+              "name": null,
+              "url": null,
+              "result": "unsubmitted",
+              "error": null
+            },
+            {
+              "commit_oid": "96d1c37a3d4363611c49f7e52186e189a04c531f",
+              "name": null,
+              "url": null,
+              "result": "unsubmitted",
+              "error": null
+            }
+          ]
+        }
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = local_repo.branchless_with_options(
+            "submit",
+            &["--create", "--forge", "github", "--output", "json"],
+            &GitRunOptions {
+                env: mock_env(&remote_repo),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        {
+          "dry_run": false,
+          "commits": [
+            {
+              "commit_oid": "62fc20d2a290daea0d52bdc2ed2ad4be6491010e",
+              "name": "mock-github-username/create-test1-txt",
+              "url": "https://example.com/mock-github-username/mock-github-repo/pulls/1",
+              "result": "created",
+              "error": null
+            },
+            {
+              "commit_oid": "96d1c37a3d4363611c49f7e52186e189a04c531f",
+              "name": "mock-github-username/create-test2-txt",
+              "url": "https://example.com/mock-github-username/mock-github-repo/pulls/2",
+              "result": "created",
+              "error": null
+            }
+          ]
+        }
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_github_forge_review_assignment() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _temp_dir,
+        original_repo: remote_repo,
+        cloned_repo: local_repo,
+    } = make_git_with_remote_repo()?;
+    if remote_repo.get_version()? < MIN_VERSION {
+        return Ok(());
+    }
+
+    remote_repo.init_repo()?;
+    remote_repo.clone_repo_into(&local_repo, &[])?;
+    local_repo.run(&["config", "branchless.submit.defaultReviewers", "carol"])?;
+
+    local_repo.detach_head()?;
+    local_repo.commit_file("test1", 1)?;
+    local_repo.commit_file("test2", 2)?;
+    local_repo.run(&[
+        "commit",
+        "--amend",
+        "--message",
+        "create test2.txt\n\nReviewers: alice, bob\nLabels: bug",
+    ])?;
+    local_repo.branchless_with_options(
+        "submit",
+        &["--create", "--forge", "github"],
+        &GitRunOptions {
+            env: mock_env(&remote_repo),
+            ..Default::default()
+        },
+    )?;
+
+    let client = MockGithubClient {
+        remote_repo_path: remote_repo.repo_path.clone(),
+    };
+    let state: serde_json::Value = serde_json::from_str(&fs::read_to_string(client.state_path())?)?;
+    insta::assert_snapshot!(serde_json::to_string_pretty(&state["review_assignments"])?, @r###"
+    {
+      "1": {
+        "labels": [],
+        "reviewers": [
+          "carol"
+        ]
+      },
+      "2": {
+        "labels": [
+          "bug"
+        ],
+        "reviewers": [
+          "alice",
+          "bob"
+        ]
+      }
+    }
+    "###);
+
+    Ok(())
+}
+
+#[test]
+fn test_github_forge_retarget_after_parent_lands() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _temp_dir,
+        original_repo: remote_repo,
+        cloned_repo: local_repo,
+    } = make_git_with_remote_repo()?;
+    if remote_repo.get_version()? < MIN_VERSION {
+        return Ok(());
+    }
+
+    remote_repo.init_repo()?;
+    remote_repo.clone_repo_into(&local_repo, &[])?;
+
+    local_repo.detach_head()?;
+    local_repo.commit_file("test1", 1)?;
+    local_repo.commit_file("test2", 2)?;
+    local_repo.branchless_with_options(
+        "submit",
+        &["--create", "--forge", "github"],
+        &GitRunOptions {
+            env: mock_env(&remote_repo),
+            ..Default::default()
+        },
+    )?;
+
+    // Land the bottom of the stack without syncing the local repository.
+    rebase_and_merge(&remote_repo, "mock-github-username/create-test1-txt")?;
+    {
+        let (stdout, _stderr) = local_repo.branchless_with_options(
+            "submit",
+            &["--forge", "github"],
+            &GitRunOptions {
+                env: mock_env(&remote_repo),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Retargeting pull request #2 onto master since the pull request for mock-github-username/create-test1-txt is no longer open
+        Updating pull request (base branch, title, body) for commit 96d1c37 create test2.txt
+        branchless: running command: <git-executable> push --force-with-lease origin mock-github-username/create-test2-txt
+        To move your local commits on top of the commits which landed, run: git sync --pull
+        Updated 1 commit: mock-github-username/create-test2-txt
+        Skipped 1 commit (not yet on remote): mock-github-username/create-test1-txt
+        These commits were skipped because they were not already associated with a remote
+        repository. To submit them, retry this operation with the --create option.
+        "###);
+    }
+
+    let client = MockGithubClient {
+        remote_repo_path: remote_repo.repo_path.clone(),
+    };
+    let state: serde_json::Value = serde_json::from_str(&fs::read_to_string(client.state_path())?)?;
+    insta::assert_snapshot!(
+        state["pull_requests"]["mock-github-username/create-test2-txt"]["baseRefName"],
+        @r###""master""###
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_github_forge_submit_status() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _temp_dir,
+        original_repo: remote_repo,
+        cloned_repo: local_repo,
+    } = make_git_with_remote_repo()?;
+    if remote_repo.get_version()? < MIN_VERSION {
+        return Ok(());
+    }
+
+    remote_repo.init_repo()?;
+    remote_repo.clone_repo_into(&local_repo, &[])?;
+
+    local_repo.detach_head()?;
+    local_repo.commit_file("test1", 1)?;
+    local_repo.commit_file("test2", 2)?;
+    {
+        let (stdout, _stderr) = local_repo.branchless_with_options(
+            "submit",
+            &["status", "--forge", "github"],
+            &GitRunOptions {
+                env: mock_env(&remote_repo),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        62fc20d create test1.txt: no code review
+        96d1c37 create test2.txt: no code review
+        "###);
+    }
+
+    local_repo.branchless_with_options(
+        "submit",
+        &["--create", "--forge", "github"],
+        &GitRunOptions {
+            env: mock_env(&remote_repo),
+            ..Default::default()
+        },
+    )?;
+    rebase_and_merge(&remote_repo, "mock-github-username/create-test1-txt")?;
+    {
+        let (stdout, _stderr) = local_repo.branchless_with_options(
+            "submit",
+            &["status", "--forge", "github"],
+            &GitRunOptions {
+                env: mock_env(&remote_repo),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        62fc20d create test1.txt: #1 (closed)
+        96d1c37 create test2.txt: #2 (open)
+        "###);
+    }
+
+    // The cached statuses are available without querying the forge.
+    {
+        let (stdout, _stderr) = local_repo.branchless("submit", &["status", "--cached"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        62fc20d create test1.txt: #1 (closed)
+        96d1c37 create test2.txt: #2 (open)
+        "###);
+    }
+    {
+        let stdout = local_repo.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        o 62fc20d (mock-github-username/create-test1-txt) #1 (closed) create test1.txt
+        |
+        @ 96d1c37 (mock-github-username/create-test2-txt) #2 (open) create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_github_forge_interactive() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _temp_dir,
+        original_repo: remote_repo,
+        cloned_repo: local_repo,
+    } = make_git_with_remote_repo()?;
+    if remote_repo.get_version()? < MIN_VERSION {
+        return Ok(());
+    }
+
+    remote_repo.init_repo()?;
+    remote_repo.clone_repo_into(&local_repo, &[])?;
+
+    local_repo.detach_head()?;
+    local_repo.commit_file("test1", 1)?;
+    local_repo.commit_file("test2", 2)?;
+    {
+        // Deselect the second commit.
+        let mut env = mock_env(&remote_repo);
+        env.insert(
+            "GIT_EDITOR".to_string(),
+            r"sed -i -e '2s/^\[x\]/[ ]/'".to_string(),
+        );
+        let (stdout, _stderr) = local_repo.branchless_with_options(
+            "submit",
+            &["--create", "--interactive", "--forge", "github"],
+            &GitRunOptions {
+                env,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> push --set-upstream origin mock-github-username/create-test1-txt
+        branch 'mock-github-username/create-test1-txt' set up to track 'origin/mock-github-username/create-test1-txt'.
+        Updating pull request (title, body) for commit 62fc20d create test1.txt
+        branchless: running command: <git-executable> push --force-with-lease origin mock-github-username/create-test1-txt
+        Submitted 1 commit: mock-github-username/create-test1-txt
+        "###);
+    }
+
+    {
+        let mut env = mock_env(&remote_repo);
+        env.insert("GIT_EDITOR".to_string(), "sed -i -e /create/d".to_string());
+        let (stdout, _stderr) = local_repo.branchless_with_options(
+            "submit",
+            &["--create", "--interactive", "--forge", "github"],
+            &GitRunOptions {
+                env,
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @"No commits were selected. Aborted.");
+    }
+
+    Ok(())
+}
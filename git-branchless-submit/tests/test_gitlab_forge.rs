@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use std::fs;
+
+use git_branchless_submit::gitlab::testing::MockGitlabClient;
+use lib::git::GitVersion;
+use lib::testing::{make_git_with_remote_repo, Git, GitRunOptions, GitWrapperWithRemoteRepo};
+
+/// Minimum version due to changes in the output of `git push`.
+const MIN_VERSION: GitVersion = GitVersion(2, 36, 0);
+
+fn mock_env(git: &Git) -> HashMap<String, String> {
+    git.get_base_env(0)
+        .into_iter()
+        .map(|(k, v)| {
+            (
+                k.to_str().unwrap().to_string(),
+                v.to_str().unwrap().to_string(),
+            )
+        })
+        .chain([(
+            git_branchless_submit::gitlab::MOCK_REMOTE_REPO_PATH_ENV_KEY.to_string(),
+            git.repo_path.clone().to_str().unwrap().to_owned(),
+        )])
+        .collect()
+}
+
+fn dump_merge_requests(remote_repo: &Git) -> String {
+    let client = MockGitlabClient {
+        remote_repo_path: remote_repo.repo_path.clone(),
+    };
+    fs::read_to_string(client.state_path()).unwrap_or_else(|err| format!("Error: {err}"))
+}
+
+#[test]
+fn test_gitlab_forge_stacked_merge_requests() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _temp_dir,
+        original_repo: remote_repo,
+        cloned_repo: local_repo,
+    } = make_git_with_remote_repo()?;
+    if remote_repo.get_version()? < MIN_VERSION {
+        return Ok(());
+    }
+
+    remote_repo.init_repo()?;
+    remote_repo.clone_repo_into(&local_repo, &[])?;
+
+    local_repo.detach_head()?;
+    local_repo.commit_file("test1", 1)?;
+    local_repo.commit_file("test2", 2)?;
+    {
+        let (stdout, _stderr) = local_repo.branchless_with_options(
+            "submit",
+            &["--create", "--draft", "--forge", "gitlab"],
+            &GitRunOptions {
+                env: mock_env(&remote_repo),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> push --set-upstream origin mock-gitlab-username/create-test1-txt
+        branch 'mock-gitlab-username/create-test1-txt' set up to track 'origin/mock-gitlab-username/create-test1-txt'.
+        branchless: running command: <git-executable> push --set-upstream origin mock-gitlab-username/create-test2-txt
+        branch 'mock-gitlab-username/create-test2-txt' set up to track 'origin/mock-gitlab-username/create-test2-txt'.
+        Updating merge request (target branch) for commit 96d1c37 create test2.txt
+        branchless: running command: <git-executable> push --force-with-lease origin mock-gitlab-username/create-test2-txt
+        Submitted 2 commits: mock-gitlab-username/create-test1-txt, mock-gitlab-username/create-test2-txt
+        "###);
+    }
+    insta::assert_snapshot!(dump_merge_requests(&remote_repo), @r###"
+    {
+      "merge_request_index": 2,
+      "merge_requests": {
+        "mock-gitlab-username/create-test1-txt": {
+          "iid": 1,
+          "web_url": "https://example.com/mock-gitlab-username/mock-gitlab-project/-/merge_requests/1",
+          "source_branch": "mock-gitlab-username/create-test1-txt",
+          "target_branch": "master",
+          "sha": "62fc20d2a290daea0d52bdc2ed2ad4be6491010e",
+          "draft": true,
+          "title": "create test1.txt",
+          "description": "create test1.txt\n"
+        },
+        "mock-gitlab-username/create-test2-txt": {
+          "iid": 2,
+          "web_url": "https://example.com/mock-gitlab-username/mock-gitlab-project/-/merge_requests/2",
+          "source_branch": "mock-gitlab-username/create-test2-txt",
+          "target_branch": "mock-gitlab-username/create-test1-txt",
+          "sha": "96d1c37a3d4363611c49f7e52186e189a04c531f",
+          "draft": true,
+          "title": "create test2.txt",
+          "description": "create test2.txt\n"
+        }
+      }
+    }
+    "###);
+
+    local_repo.branchless("reword", &["--message", "update test2.txt"])?;
+    {
+        let (stdout, _stderr) = local_repo.branchless_with_options(
+            "submit",
+            &["--forge", "gitlab"],
+            &GitRunOptions {
+                env: mock_env(&remote_repo),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Updating merge request (commit, title, description) for commit 76dcb5c update test2.txt
+        branchless: running command: <git-executable> push --force-with-lease origin mock-gitlab-username/create-test2-txt
+        Updated 1 commit: mock-gitlab-username/create-test2-txt
+        Skipped 1 commit (already up-to-date): mock-gitlab-username/create-test1-txt
+        "###);
+    }
+    insta::assert_snapshot!(dump_merge_requests(&remote_repo), @r###"
+    {
+      "merge_request_index": 2,
+      "merge_requests": {
+        "mock-gitlab-username/create-test1-txt": {
+          "iid": 1,
+          "web_url": "https://example.com/mock-gitlab-username/mock-gitlab-project/-/merge_requests/1",
+          "source_branch": "mock-gitlab-username/create-test1-txt",
+          "target_branch": "master",
+          "sha": "62fc20d2a290daea0d52bdc2ed2ad4be6491010e",
+          "draft": true,
+          "title": "create test1.txt",
+          "description": "create test1.txt\n"
+        },
+        "mock-gitlab-username/create-test2-txt": {
+          "iid": 2,
+          "web_url": "https://example.com/mock-gitlab-username/mock-gitlab-project/-/merge_requests/2",
+          "source_branch": "mock-gitlab-username/create-test2-txt",
+          "target_branch": "mock-gitlab-username/create-test1-txt",
+          "sha": "76dcb5c5a1450400c8df3e1b00b1c29a974bece3",
+          "draft": true,
+          "title": "update test2.txt",
+          "description": "update test2.txt\n"
+        }
+      }
+    }
+    "###);
+
+    Ok(())
+}
@@ -1,6 +1,10 @@
 use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
 
 use lib::testing::{make_git, GitRunOptions, GitWrapper};
+use regex::Regex;
 
 fn mock_env(git: &GitWrapper) -> HashMap<String, String> {
     git.get_base_env(0)
@@ -18,6 +22,125 @@ fn mock_env(git: &GitWrapper) -> HashMap<String, String> {
         .collect()
 }
 
+fn decode_form_value(value: &str) -> String {
+    let mut result = Vec::new();
+    let mut bytes = value.bytes();
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b'+' => result.push(b' '),
+            b'%' => {
+                let hex: String = bytes.by_ref().take(2).map(char::from).collect();
+                result.push(u8::from_str_radix(&hex, 16).unwrap());
+            }
+            byte => result.push(byte),
+        }
+    }
+    String::from_utf8(result).unwrap()
+}
+
+/// Start a fake Conduit API server, and return its URI and a log of the
+/// methods called on it along with their parameters.
+fn start_conduit_server() -> eyre::Result<(String, Arc<Mutex<Vec<String>>>)> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let uri = format!("http://{}", listener.local_addr()?);
+    let calls: Arc<Mutex<Vec<String>>> = Default::default();
+    let server_calls = Arc::clone(&calls);
+    std::thread::spawn(move || {
+        let mut next_revision_id = 1;
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(&mut stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let method = request_line
+                .split_whitespace()
+                .nth(1)
+                .unwrap()
+                .trim_start_matches("/api/")
+                .to_string();
+            let mut content_length = 0;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                let header = header.trim_end();
+                if header.is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = header.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            let body = String::from_utf8(body).unwrap();
+            let params = body
+                .split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(key, _value)| *key == "params")
+                .map(|(_key, value)| decode_form_value(value))
+                .unwrap();
+            let mut params: serde_json::Value = serde_json::from_str(&params).unwrap();
+            let conduit = params.as_object_mut().unwrap().remove("__conduit__");
+            assert_eq!(conduit, Some(serde_json::json!({ "token": "api-token" })));
+
+            let result = match method.as_str() {
+                "differential.createrawdiff" => serde_json::json!({ "phid": "PHID-DIFF-1" }),
+                "differential.revision.edit" => {
+                    let id = match params.get("objectIdentifier") {
+                        Some(id) => id.as_str().unwrap().parse::<u64>().unwrap(),
+                        None => {
+                            let id = next_revision_id;
+                            next_revision_id += 1;
+                            id
+                        }
+                    };
+                    serde_json::json!({ "object": { "id": id } })
+                }
+                "differential.query" => serde_json::Value::Array(
+                    params["ids"]
+                        .as_array()
+                        .unwrap()
+                        .iter()
+                        .map(|id| {
+                            let id = id.as_str().unwrap();
+                            serde_json::json!({ "id": id, "phid": format!("PHID-DREV-{id}") })
+                        })
+                        .collect(),
+                ),
+                _ => serde_json::Value::Null,
+            };
+            if let Some(diff) = params.get_mut("diff") {
+                // Only keep the file names, since the diff contains object IDs.
+                *diff = diff
+                    .as_str()
+                    .unwrap()
+                    .lines()
+                    .filter(|line| line.starts_with("diff --git"))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+                    .into();
+            }
+            server_calls
+                .lock()
+                .unwrap()
+                .push(format!("{method} {params}"));
+
+            let response =
+                serde_json::json!({ "result": result, "error_code": null, "error_info": null })
+                    .to_string();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response}",
+                response.len()
+            )
+            .unwrap();
+        }
+    });
+    Ok((uri, calls))
+}
+
 #[test]
 fn test_submit_phabricator_strategy_working_copy() -> eyre::Result<()> {
     let git = make_git()?;
@@ -223,8 +346,165 @@ fn test_submit_phabricator_update() -> eyre::Result<()> {
         branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
         branchless: running command: <git-executable> rebase --abort
-        Setting D0002 as stack root (no dependencies)
+        Skipped 2 commits (already up-to-date): D0002, D0003
+        "###);
+    }
+
+    git.write_file_txt("test2", "updated contents\n")?;
+    git.run(&["commit", "--amend", "--all", "--no-edit"])?;
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "submit",
+            &["--forge", "phabricator"],
+            &GitRunOptions {
+                env: mock_env(&git),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> diff --quiet
+        Calling Git for on-disk rebase...
+        branchless: running command: <git-executable> rebase --continue
+        Using command execution strategy: working-copy
+        branchless: running command: <git-executable> rebase --abort
         Stacking D0003 on top of D0002
+        Updated 1 commit: D0003
+        Skipped 1 commit (already up-to-date): D0002
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_submit_phabricator_update_reworded_title() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    git.branchless_with_options(
+        "submit",
+        &["--create", "--forge", "phabricator"],
+        &GitRunOptions {
+            env: mock_env(&git),
+            ..Default::default()
+        },
+    )?;
+
+    git.run(&[
+        "commit",
+        "--amend",
+        "-m",
+        "update test2.txt",
+        "-m",
+        "Differential Revision: https://phabricator.example.com/D0003",
+    ])?;
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "submit",
+            &["--forge", "phabricator"],
+            &GitRunOptions {
+                env: mock_env(&git),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> diff --quiet
+        Calling Git for on-disk rebase...
+        branchless: running command: <git-executable> rebase --continue
+        Using command execution strategy: working-copy
+        branchless: running command: <git-executable> rebase --abort
+        Stacking D0003 on top of D0002
+        Updating title and summary of D0003 to match its commit message
+        Updated 1 commit: D0003
+        Skipped 1 commit (already up-to-date): D0002
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_submit_phabricator_native_conduit() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    let (uri, calls) = start_conduit_server()?;
+    git.run(&["config", "branchless.submit.phabricator.conduitUri", &uri])?;
+    git.run(&[
+        "config",
+        "branchless.submit.phabricator.conduitToken",
+        "api-token",
+    ])?;
+
+    // The commit hashes depend on the server's URI, which is linked in the
+    // commit messages, and its port is chosen at random.
+    let oid_re = Regex::new(r"\b[0-9a-f]{7}([0-9a-f]{33})?\b")?;
+    let redact = |output: &str| -> String {
+        let output = output.replace(&uri, "<conduit-uri>");
+        oid_re.replace_all(&output, "<oid>").into_owned()
+    };
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    {
+        let (stdout, _stderr) =
+            git.branchless("submit", &["--create", "--forge", "phabricator"])?;
+        insta::assert_snapshot!(redact(&stdout), @r###"
+        Created D1 for commit <oid> create test1.txt
+        Created D2 for commit <oid> create test2.txt
+        Attempting rebase in-memory...
+        [1/2] Committed as: <oid> create test1.txt
+        [2/2] Committed as: <oid> create test2.txt
+        branchless: processing 2 rewritten commits
+        branchless: running command: <git-executable> checkout <oid>
+        In-memory rebase succeeded.
+        Setting D1 as stack root (no dependencies)
+        Stacking D2 on top of D1
+        Submitted 2 commits: D1, D2
+        "###);
+        insta::assert_snapshot!(calls.lock().unwrap().drain(..).collect::<Vec<_>>().join("\n"), @r###"
+        differential.createrawdiff {"diff":"diff --git a/test1.txt b/test1.txt"}
+        differential.revision.edit {"transactions":[{"type":"update","value":"PHID-DIFF-1"},{"type":"title","value":"create test1.txt"},{"type":"summary","value":""}]}
+        differential.createrawdiff {"diff":"diff --git a/test2.txt b/test2.txt"}
+        differential.revision.edit {"transactions":[{"type":"update","value":"PHID-DIFF-1"},{"type":"title","value":"create test2.txt"},{"type":"summary","value":""}]}
+        differential.revision.edit {"objectIdentifier":"1","transactions":[{"type":"parents.set","value":[]}]}
+        differential.query {"ids":["1"],"phids":[]}
+        differential.revision.edit {"objectIdentifier":"2","transactions":[{"type":"parents.set","value":["PHID-DREV-1"]}]}
+        "###);
+    }
+
+    git.write_file_txt("test2", "updated contents\n")?;
+    git.run(&["commit", "--amend", "--all", "--no-edit"])?;
+    {
+        let (stdout, _stderr) = git.branchless("submit", &["--forge", "phabricator"])?;
+        insta::assert_snapshot!(redact(&stdout), @r###"
+        Stacking D2 on top of D1
+        Updated 1 commit: D2
+        Skipped 1 commit (already up-to-date): D1
+        "###);
+        insta::assert_snapshot!(calls.lock().unwrap().drain(..).collect::<Vec<_>>().join("\n"), @r###"
+        differential.query {"ids":["2","1"],"phids":[]}
+        differential.createrawdiff {"diff":"diff --git a/test2.txt b/test2.txt"}
+        differential.revision.edit {"objectIdentifier":"2","transactions":[{"type":"update","value":"PHID-DIFF-1"},{"type":"comment","value":"update"}]}
+        differential.query {"ids":["1"],"phids":[]}
+        differential.revision.edit {"objectIdentifier":"2","transactions":[{"type":"parents.set","value":["PHID-DREV-1"]}]}
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(redact(&stdout), @r###"
+        O <oid> (master) create initial.txt
+        |
+        o <oid> D1 create test1.txt
+        |
+        @ <oid> D2 create test2.txt
         "###);
     }
 
@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use git_branchless_submit::send_email::MOCK_OUTBOX_PATH_ENV_KEY;
+use lib::testing::{make_git, Git, GitRunOptions};
+
+fn mock_env(git: &Git, outbox_path: &Path) -> HashMap<String, String> {
+    git.get_base_env(0)
+        .into_iter()
+        .map(|(k, v)| {
+            (
+                k.to_str().unwrap().to_string(),
+                v.to_str().unwrap().to_string(),
+            )
+        })
+        .chain([(
+            MOCK_OUTBOX_PATH_ENV_KEY.to_string(),
+            outbox_path.to_str().unwrap().to_owned(),
+        )])
+        .collect()
+}
+
+/// Get the value of the given header in each patch in the outbox, keyed by
+/// file name.
+fn get_headers(outbox_path: &Path, header: &str) -> eyre::Result<Vec<(String, String)>> {
+    let mut result = Vec::new();
+    for entry in fs::read_dir(outbox_path)? {
+        let path = entry?.path();
+        let contents = fs::read_to_string(&path)?;
+        let value = contents
+            .lines()
+            .find_map(|line| {
+                let (key, value) = line.split_once(": ")?;
+                key.eq_ignore_ascii_case(header).then(|| value.to_owned())
+            })
+            .unwrap_or_default();
+        result.push((
+            path.file_name().unwrap().to_str().unwrap().to_owned(),
+            value,
+        ));
+    }
+    result.sort();
+    Ok(result)
+}
+
+#[test]
+fn test_send_email_forge() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    let outbox_dir = tempfile::tempdir()?;
+    let outbox_path = outbox_dir.path();
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "submit",
+            &["--create", "--forge", "send-email"],
+            &GitRunOptions {
+                env: mock_env(&git, outbox_path),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Sent v1 of patch series: 96d1c37 create test2.txt
+        Submitted 2 commits: qtmynwsp, txknxzmx
+        "###);
+    }
+    {
+        let subjects = get_headers(outbox_path, "Subject")?;
+        insta::assert_debug_snapshot!(subjects, @r###"
+        [
+            (
+                "0000-cover-letter.patch",
+                "[PATCH 0/2] create test1.txt",
+            ),
+            (
+                "0001-create-test1.txt.patch",
+                "[PATCH 1/2] create test1.txt",
+            ),
+            (
+                "0002-create-test2.txt.patch",
+                "[PATCH 2/2] create test2.txt",
+            ),
+        ]
+        "###);
+    }
+
+    {
+        // Submitting again without changes doesn't send anything.
+        let (stdout, _stderr) = git.branchless_with_options(
+            "submit",
+            &["--forge", "send-email"],
+            &GitRunOptions {
+                env: mock_env(&git, outbox_path),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Skipped 2 commits (already up-to-date): qtmynwsp, txknxzmx
+        "###);
+    }
+
+    git.run(&["commit", "--amend", "-m", "updated message"])?;
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "submit",
+            &["--forge", "send-email"],
+            &GitRunOptions {
+                env: mock_env(&git, outbox_path),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Sent v2 of patch series: 358a29b updated message
+        Updated 2 commits: qtmynwsp, txknxzmx
+        "###);
+    }
+    {
+        let subjects = get_headers(outbox_path, "Subject")?;
+        insta::assert_debug_snapshot!(subjects, @r###"
+        [
+            (
+                "0000-cover-letter.patch",
+                "[PATCH 0/2] create test1.txt",
+            ),
+            (
+                "0001-create-test1.txt.patch",
+                "[PATCH 1/2] create test1.txt",
+            ),
+            (
+                "0002-create-test2.txt.patch",
+                "[PATCH 2/2] create test2.txt",
+            ),
+            (
+                "v2-0000-cover-letter.patch",
+                "[PATCH v2 0/2] create test1.txt",
+            ),
+            (
+                "v2-0001-create-test1.txt.patch",
+                "[PATCH v2 1/2] create test1.txt",
+            ),
+            (
+                "v2-0002-updated-message.patch",
+                "[PATCH v2 2/2] updated message",
+            ),
+        ]
+        "###);
+
+        // The new version is sent as a reply to the previous version.
+        let message_ids: HashMap<String, String> = get_headers(outbox_path, "Message-ID")?
+            .into_iter()
+            .collect();
+        let in_reply_tos: HashMap<String, String> = get_headers(outbox_path, "In-Reply-To")?
+            .into_iter()
+            .collect();
+        assert_eq!(
+            in_reply_tos["v2-0000-cover-letter.patch"],
+            message_ids["0000-cover-letter.patch"]
+        );
+    }
+
+    Ok(())
+}
@@ -90,6 +90,9 @@ fn test_submit() -> eyre::Result<()> {
         let (stdout, stderr) = cloned_repo.run(&["submit", "--dry-run"])?;
         insta::assert_snapshot!(stderr, @"");
         insta::assert_snapshot!(stdout, @r###"
+        Submission plan:
+        - f57e36f create test4.txt: skip bar (not yet on remote; pass --create to submit)
+        - 20230db create test5.txt: skip qux (not yet on remote; pass --create to submit)
         Would skip 2 commits (not yet on remote): bar, qux
         These commits would be skipped because they are not already associated with a remote
         repository. To submit them, retry this operation with the --create option.
@@ -100,6 +103,9 @@ fn test_submit() -> eyre::Result<()> {
         let (stdout, stderr) = cloned_repo.run(&["submit", "--create", "--dry-run"])?;
         insta::assert_snapshot!(stderr, @"");
         insta::assert_snapshot!(stdout, @r###"
+        Submission plan:
+        - f57e36f create test4.txt: push new branch bar
+        - 20230db create test5.txt: push new branch qux
         Would submit 2 commits: bar, qux
         "###);
     }
@@ -343,3 +349,145 @@ fn test_submit_up_to_date_branch() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_submit_state_revsets() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _guard,
+        original_repo,
+        cloned_repo,
+    } = make_git_with_remote_repo()?;
+
+    if original_repo.get_version()? < MIN_VERSION {
+        return Ok(());
+    }
+
+    {
+        original_repo.init_repo()?;
+        original_repo.commit_file("test1", 1)?;
+        original_repo.commit_file("test2", 2)?;
+
+        original_repo.clone_repo_into(&cloned_repo, &[])?;
+    }
+
+    cloned_repo.init_repo_with_options(&GitInitOptions {
+        make_initial_commit: false,
+        ..Default::default()
+    })?;
+    cloned_repo.run(&["checkout", "-b", "foo"])?;
+    cloned_repo.commit_file("test3", 3)?;
+    cloned_repo.run(&["checkout", "-b", "bar", "master"])?;
+    cloned_repo.commit_file("test4", 4)?;
+    cloned_repo.run(&["checkout", "-b", "qux"])?;
+    cloned_repo.commit_file("test5", 5)?;
+    cloned_repo.run(&["submit", "--create", "bar"])?;
+
+    {
+        let (stdout, _stderr) = cloned_repo.branchless("query", &["-b", "submitted()"])?;
+        insta::assert_snapshot!(stdout, @"bar
+");
+    }
+    {
+        let (stdout, _stderr) = cloned_repo.branchless("query", &["-b", "unsubmitted()"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        foo
+        qux
+        "###);
+    }
+    {
+        let (stdout, _stderr) = cloned_repo.branchless("query", &["-b", "needsresubmit()"])?;
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    cloned_repo.detach_head()?;
+    cloned_repo.commit_file("test6", 6)?;
+    {
+        let (stdout, _stderr) = cloned_repo.branchless("query", &["unsubmitted()"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        70deb1e create test3.txt
+        20230db create test5.txt
+        2dac0f5 create test6.txt
+        "###);
+    }
+
+    cloned_repo.run(&["branch", "-f", "bar", "qux"])?;
+    {
+        let (stdout, _stderr) = cloned_repo.branchless("query", &["-b", "submitted()"])?;
+        insta::assert_snapshot!(stdout, @"");
+    }
+    {
+        let (stdout, _stderr) = cloned_repo.branchless("query", &["-b", "needsresubmit()"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        bar
+        qux
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_submit_refuses_to_overwrite_remote_changes() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _guard,
+        original_repo,
+        cloned_repo,
+    } = make_git_with_remote_repo()?;
+
+    if original_repo.get_version()? < MIN_VERSION {
+        return Ok(());
+    }
+
+    {
+        original_repo.init_repo()?;
+        original_repo.commit_file("test1", 1)?;
+        original_repo.commit_file("test2", 2)?;
+        original_repo.clone_repo_into(&cloned_repo, &[])?;
+        cloned_repo.init_repo_with_options(&GitInitOptions {
+            make_initial_commit: false,
+            ..Default::default()
+        })?;
+    }
+
+    cloned_repo.run(&["checkout", "-b", "feature"])?;
+    cloned_repo.commit_file("test3", 3)?;
+    cloned_repo.run(&["submit", "--create", "feature"])?;
+
+    // Somebody else pushes to the branch.
+    original_repo.run(&["checkout", "feature"])?;
+    original_repo.commit_file("test4", 4)?;
+    original_repo.run(&["checkout", "master"])?;
+
+    cloned_repo.run(&["commit", "--amend", "-m", "updated message"])?;
+    {
+        let (stdout, _stderr) = cloned_repo.branchless_with_options(
+            "submit",
+            &["feature"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> fetch origin refs/heads/feature
+        Refusing to push branch feature: origin/feature was updated to 355e173 since it was last
+        submitted as 70deb1e, so somebody else may have pushed to it.
+        To keep their changes, rebase onto origin/feature and submit again. To discard them,
+        run: git push --force origin feature
+        "###);
+    }
+
+    // Once their changes are incorporated, the branch can be pushed again.
+    cloned_repo.run(&["reset", "--hard", "origin/feature"])?;
+    cloned_repo.commit_file("test5", 5)?;
+    {
+        let (stdout, _stderr) = cloned_repo.run(&["submit", "feature"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> fetch origin refs/heads/feature
+        branchless: running command: <git-executable> push --force-with-lease origin feature
+        Updated 1 commit: feature
+        "###);
+    }
+
+    Ok(())
+}
@@ -46,8 +46,9 @@ use lib::core::eventlog::{
 use lib::core::formatting::{Glyphs, Pluralize, StyledStringBuilder};
 use lib::core::repo_ext::RepoExt;
 use lib::core::rewrite::{
-    execute_rebase_plan, BuildRebasePlanOptions, ExecuteRebasePlanOptions, ExecuteRebasePlanResult,
-    RebaseCommand, RebasePlan, RebasePlanBuilder, RebasePlanPermissions, RepoResource,
+    execute_rebase_plan, BuildRebasePlanOptions, EmptyCommitsMode, ExecuteRebasePlanOptions,
+    ExecuteRebasePlanResult, RebaseCommand, RebasePlan, RebasePlanBuilder, RebasePlanPermissions,
+    RepoResource,
 };
 use lib::git::{
     get_latest_test_command_path, get_test_locks_dir, get_test_tree_dir, get_test_worktrees_dir,
@@ -388,6 +389,9 @@ BUG: Expected resolved_interactive ({resolved_interactive:?}) to match interacti
                 resolve_merge_conflicts,
                 dump_rebase_constraints,
                 dump_rebase_plan,
+                preserve_timestamps,
+                reset_author,
+                committer_as_author,
             } = move_options;
 
             let force_in_memory = true;
@@ -408,14 +412,18 @@ BUG: Expected resolved_interactive ({resolved_interactive:?}) to match interacti
             let execute_options = ExecuteRebasePlanOptions {
                 now,
                 event_tx_id,
-                preserve_timestamps: get_restack_preserve_timestamps(repo)?,
+                preserve_timestamps: *preserve_timestamps || get_restack_preserve_timestamps(repo)?,
                 force_in_memory,
                 force_on_disk: *force_on_disk,
                 resolve_merge_conflicts: *resolve_merge_conflicts,
+                reset_author: *reset_author,
+                committer_as_author: *committer_as_author,
+                signature_commit_oids: None,
                 check_out_commit_options: CheckOutCommitOptions {
                     render_smartlog: false,
                     ..Default::default()
                 },
+                empty_commits: EmptyCommitsMode::Drop,
             };
             let permissions =
                 match RebasePlanPermissions::verify_rewrite_set(dag, build_options, commits)? {
@@ -727,10 +735,14 @@ fn set_abort_trap(
             force_in_memory: false,
             force_on_disk: true,
             resolve_merge_conflicts: false,
+            reset_author: false,
+            committer_as_author: false,
+            signature_commit_oids: None,
             check_out_commit_options: CheckOutCommitOptions {
                 render_smartlog: false,
                 ..Default::default()
             },
+            empty_commits: EmptyCommitsMode::Drop,
         },
     )? {
         ExecuteRebasePlanResult::Succeeded { rewritten_oids: _ } => {
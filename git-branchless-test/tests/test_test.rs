@@ -1792,6 +1792,24 @@ esac
         "###);
     }
 
+    {
+        let (stdout, _stderr) = git.branchless("query", &["tests.untested()"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        f777ecc create initial.txt
+        70deb1e create test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("query", &["tests.untested('exit 0') & draft()"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        62fc20d create test1.txt
+        96d1c37 create test2.txt
+        70deb1e create test3.txt
+        355e173 create test4.txt
+        "###);
+    }
+
     git.branchless("test", &["run", "--exec", "exit 0"])?;
     {
         let (stdout, _stderr) = git.branchless("query", &["tests.passed()"])?;
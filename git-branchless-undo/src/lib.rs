@@ -14,12 +14,15 @@
 
 pub mod tui;
 
+use std::collections::HashSet;
 use std::fmt::Write;
 use std::io::{stdin, BufRead, BufReader, Read};
+use std::process::Command;
 use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
 use std::time::SystemTime;
 
 use cursive_core::event::Key;
+use cursive_core::theme::BaseColor;
 use cursive_core::traits::Resizable;
 use cursive_core::utils::markup::StyledString;
 use cursive_core::views::{
@@ -28,24 +31,32 @@ use cursive_core::views::{
 use cursive_core::{Cursive, CursiveRunner};
 use eyre::Context;
 use lib::core::check_out::{check_out_commit, CheckOutCommitOptions, CheckoutTarget};
+use lib::core::config::get_hook_post_undo;
 use lib::core::repo_ext::RepoExt;
 use lib::try_exit_code;
-use lib::util::{ExitCode, EyreExitOr};
+use lib::util::{get_sh, ExitCode, EyreExitOr};
 use tracing::instrument;
 
 use crate::tui::{with_siv, SingletonView};
-use git_branchless_revset::resolve_default_smartlog_commits;
+use git_branchless_opts::{ResolveRevsetOptions, Revset};
+use git_branchless_revset::{resolve_commits, resolve_default_smartlog_commits};
 use git_branchless_smartlog::{make_smartlog_graph, render_graph};
-use lib::core::dag::{CommitSet, Dag};
+use lib::core::dag::{union_all, CommitSet, Dag};
 use lib::core::effects::Effects;
-use lib::core::eventlog::{Event, EventCursor, EventLogDb, EventReplayer, EventTransactionId};
+use lib::core::eventlog::{
+    get_head_ref_name, is_head_ref_name, Event, EventCursor, EventLogDb, EventReplayer,
+    EventTransactionId, BRANCHLESS_TRANSACTION_ID_ENV_VAR,
+};
 use lib::core::formatting::{Glyphs, Pluralize, StyledStringBuilder};
 use lib::core::node_descriptors::{
     BranchesDescriptor, CommitMessageDescriptor, CommitOidDescriptor,
     DifferentialRevisionDescriptor, ObsolescenceExplanationDescriptor, Redactor,
     RelativeTimeDescriptor,
 };
-use lib::git::{CategorizedReferenceName, GitRunInfo, MaybeZeroOid, Repo, ResolvedReferenceInfo};
+use lib::git::{
+    BranchType, CategorizedReferenceName, GitRunInfo, MaybeZeroOid, NonZeroOid, ReferenceName,
+    Repo, ResolvedReferenceInfo,
+};
 
 fn render_cursor_smartlog(
     effects: &Effects,
@@ -175,7 +186,7 @@ fn describe_event(glyphs: &Glyphs, repo: &Repo, event: &Event) -> eyre::Result<V
             old_oid: MaybeZeroOid::Zero,
             new_oid: MaybeZeroOid::NonZero(new_oid),
             message: _,
-        } if ref_name.as_str() == "HEAD" => {
+        } if ref_name == &get_head_ref_name(repo) => {
             // Not sure if this can happen. When a repo is created, maybe?
             vec![
                 StyledStringBuilder::new()
@@ -193,7 +204,7 @@ fn describe_event(glyphs: &Glyphs, repo: &Repo, event: &Event) -> eyre::Result<V
             old_oid: MaybeZeroOid::NonZero(old_oid),
             new_oid: MaybeZeroOid::NonZero(new_oid),
             message: _,
-        } if ref_name.as_str() == "HEAD" => {
+        } if ref_name == &get_head_ref_name(repo) => {
             vec![
                 StyledStringBuilder::new()
                     .append_plain("Check out from ")
@@ -426,6 +437,36 @@ fn describe_events_numbered(
     Ok(lines)
 }
 
+/// Starting from `cursor`, move by one transaction at a time in the direction
+/// given by the sign of `direction`, until reaching a transaction whose
+/// description contains `query` (case-insensitively).
+fn find_matching_cursor(
+    glyphs: &Glyphs,
+    repo: &Repo,
+    event_replayer: &EventReplayer,
+    cursor: EventCursor,
+    query: &str,
+    direction: isize,
+) -> eyre::Result<Option<EventCursor>> {
+    let query = query.to_lowercase();
+    let mut cursor = cursor;
+    loop {
+        let next_cursor = event_replayer.advance_cursor_by_transaction(cursor, direction);
+        if next_cursor == cursor {
+            return Ok(None);
+        }
+        cursor = next_cursor;
+
+        if let Some((_event_id, events)) = event_replayer.get_tx_events_before_cursor(cursor) {
+            for line in describe_events_numbered(glyphs, repo, events)? {
+                if line.source().to_lowercase().contains(&query) {
+                    return Ok(Some(cursor));
+                }
+            }
+        }
+    }
+}
+
 #[instrument(skip(siv))]
 fn select_past_event(
     mut siv: CursiveRunner<Cursive>,
@@ -434,13 +475,17 @@ fn select_past_event(
     dag: &Dag,
     event_replayer: &mut EventReplayer,
 ) -> eyre::Result<Option<EventCursor>> {
-    #[derive(Clone, Copy, Debug)]
+    #[derive(Clone, Debug)]
     enum Message {
         Init,
         Next,
         Previous,
         GoToEvent,
         SetEventReplayerCursor { event_id: isize },
+        Search,
+        SetSearchQuery { query: String },
+        NextMatch,
+        PreviousMatch,
         Help,
         Quit,
         SelectEventIdAndQuit,
@@ -459,6 +504,9 @@ fn select_past_event(
         ('?'.into(), Message::Help),
         ('g'.into(), Message::GoToEvent),
         ('G'.into(), Message::GoToEvent),
+        ('/'.into(), Message::Search),
+        (']'.into(), Message::NextMatch),
+        ('['.into(), Message::PreviousMatch),
         ('q'.into(), Message::Quit),
         ('Q'.into(), Message::Quit),
         (
@@ -471,11 +519,12 @@ fn select_past_event(
     .for_each(|(event, message): (cursive_core::event::Event, Message)| {
         siv.add_global_callback(event, {
             let main_tx = main_tx.clone();
-            move |_siv| main_tx.send(message).unwrap()
+            move |_siv| main_tx.send(message.clone()).unwrap()
         });
     });
 
     let mut cursor = event_replayer.make_default_cursor();
+    let mut search_query: Option<String> = None;
     let now = SystemTime::now();
     main_tx.send(Message::Init)?;
     while siv.is_running() {
@@ -541,6 +590,39 @@ fn select_past_event(
             Ok(())
         };
 
+        let jump_to_match = |siv: &mut Cursive,
+                             event_replayer: &mut EventReplayer,
+                             cursor: &mut EventCursor,
+                             search_query: &Option<String>,
+                             direction: isize|
+         -> eyre::Result<()> {
+            let query = match search_query {
+                Some(query) => query,
+                None => {
+                    siv.add_layer(Dialog::info("Press '/' to search for an event first."));
+                    return Ok(());
+                }
+            };
+            match find_matching_cursor(
+                effects.get_glyphs(),
+                repo,
+                event_replayer,
+                *cursor,
+                query,
+                direction,
+            )? {
+                Some(matching_cursor) => {
+                    *cursor = matching_cursor;
+                    redraw(siv, event_replayer, *cursor)?;
+                }
+                None => {
+                    siv.add_layer(Dialog::info(format!("No more events matching: {query}")));
+                }
+            }
+            Ok(())
+        };
+
+        let should_refresh = message.is_ok();
         match message {
             Err(TryRecvError::Disconnected) => break,
 
@@ -610,6 +692,41 @@ fn select_past_event(
                 );
             }
 
+            Ok(Message::Search) => {
+                let main_tx = main_tx.clone();
+                siv.add_layer(
+                    OnEventView::new(
+                        Dialog::new()
+                            .title("Search events")
+                            .content(EditView::new().on_submit(move |siv, text| {
+                                main_tx
+                                    .send(Message::SetSearchQuery {
+                                        query: text.to_string(),
+                                    })
+                                    .unwrap();
+                                siv.pop_layer();
+                            }))
+                            .dismiss_button("Cancel"),
+                    )
+                    .on_event(Key::Esc, |siv| {
+                        siv.pop_layer();
+                    }),
+                );
+            }
+
+            Ok(Message::SetSearchQuery { query }) => {
+                search_query = if query.is_empty() { None } else { Some(query) };
+                jump_to_match(&mut siv, event_replayer, &mut cursor, &search_query, -1)?;
+            }
+
+            Ok(Message::NextMatch) => {
+                jump_to_match(&mut siv, event_replayer, &mut cursor, &search_query, 1)?;
+            }
+
+            Ok(Message::PreviousMatch) => {
+                jump_to_match(&mut siv, event_replayer, &mut cursor, &search_query, -1)?;
+            }
+
             Ok(Message::Help) => {
                 siv.add_layer(
                         Dialog::new()
@@ -621,6 +738,8 @@ h/?: Show this help.
 q: Quit.
 p/n or <left>/<right>: View next/previous state.
 g: Go to a provided event ID.
+/: Search for an earlier event by its description.
+[/]: Go to the previous/next event matching the search.
 <enter>: Revert the repository to the given state (requires confirmation).
 
 You can also copy a commit hash from the past and manually run `git unhide` or `git rebase` on it.
@@ -638,7 +757,7 @@ You can also copy a commit hash from the past and manually run `git unhide` or `
             }
         };
 
-        if message.is_ok() {
+        if should_refresh {
             siv.refresh();
         }
     }
@@ -718,15 +837,170 @@ fn inverse_event(
     Ok(inverse_event)
 }
 
+/// Restricts undoing to the events which affect particular branches or
+/// commits.
+#[derive(Clone, Debug, Default)]
+struct UndoFilter {
+    commit_oids: HashSet<NonZeroOid>,
+    ref_names: HashSet<ReferenceName>,
+}
+
+impl UndoFilter {
+    fn matches_oid(&self, oid: MaybeZeroOid) -> bool {
+        match oid {
+            MaybeZeroOid::NonZero(oid) => self.commit_oids.contains(&oid),
+            MaybeZeroOid::Zero => false,
+        }
+    }
+
+    /// Also match the commits which the selected references pointed to in the
+    /// given events, so that undoing a change to a branch also undoes the
+    /// corresponding changes to its commits.
+    fn with_ref_targets(&self, events: &[Event]) -> Self {
+        let mut result = self.clone();
+        for event in events {
+            if let Event::RefUpdateEvent {
+                timestamp: _,
+                event_tx_id: _,
+                ref_name,
+                old_oid,
+                new_oid,
+                message: _,
+            } = event
+            {
+                if self.ref_names.contains(ref_name) {
+                    for oid in [old_oid, new_oid] {
+                        if let MaybeZeroOid::NonZero(oid) = oid {
+                            result.commit_oids.insert(*oid);
+                        }
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    fn matches(&self, event: &Event) -> bool {
+        match event {
+            Event::CommitEvent {
+                timestamp: _,
+                event_tx_id: _,
+                commit_oid,
+            }
+            | Event::ObsoleteEvent {
+                timestamp: _,
+                event_tx_id: _,
+                commit_oid,
+            }
+            | Event::UnobsoleteEvent {
+                timestamp: _,
+                event_tx_id: _,
+                commit_oid,
+            } => self.commit_oids.contains(commit_oid),
+
+            Event::RewriteEvent {
+                timestamp: _,
+                event_tx_id: _,
+                old_commit_oid,
+                new_commit_oid,
+            } => self.matches_oid(*old_commit_oid) || self.matches_oid(*new_commit_oid),
+
+            Event::RefUpdateEvent {
+                timestamp: _,
+                event_tx_id: _,
+                ref_name,
+                old_oid,
+                new_oid,
+                message: _,
+            } => {
+                self.ref_names.contains(ref_name)
+                    || self.matches_oid(*old_oid)
+                    || self.matches_oid(*new_oid)
+            }
+
+            Event::WorkingCopySnapshot {
+                timestamp: _,
+                event_tx_id: _,
+                head_oid,
+                commit_oid: _,
+                ref_name,
+            } => {
+                ref_name
+                    .as_ref()
+                    .map_or(false, |ref_name| self.ref_names.contains(ref_name))
+                    || self.matches_oid(*head_oid)
+            }
+        }
+    }
+}
+
+/// Build a filter for the provided `--only` arguments. Arguments which name a
+/// branch that appears in the event log are matched against reference
+/// updates by name (so that deleted branches can still be specified); all
+/// other arguments are resolved as revsets.
+fn make_undo_filter(
+    effects: &Effects,
+    repo: &Repo,
+    dag: &mut Dag,
+    event_replayer: &EventReplayer,
+    only: Vec<Revset>,
+) -> EyreExitOr<UndoFilter> {
+    let known_ref_names: HashSet<&ReferenceName> = event_replayer
+        .get_events_since_cursor(event_replayer.make_cursor(0))
+        .iter()
+        .filter_map(|event| match event {
+            Event::RefUpdateEvent { ref_name, .. } => Some(ref_name),
+            _ => None,
+        })
+        .collect();
+
+    let mut ref_names = HashSet::new();
+    let mut revsets = Vec::new();
+    for revset in only {
+        let branch_ref_name = ReferenceName::from(format!("refs/heads/{}", revset.0));
+        if known_ref_names.contains(&branch_ref_name) {
+            ref_names.insert(branch_ref_name);
+        } else {
+            revsets.push(revset);
+        }
+    }
+
+    let commit_sets = match resolve_commits(
+        effects,
+        repo,
+        dag,
+        &revsets,
+        &ResolveRevsetOptions {
+            show_hidden_commits: true,
+        },
+    ) {
+        Ok(commit_sets) => commit_sets,
+        Err(err) => {
+            err.describe(effects)?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+    let commit_oids = dag
+        .commit_set_to_vec(&union_all(&commit_sets))?
+        .into_iter()
+        .collect();
+
+    Ok(Ok(UndoFilter {
+        commit_oids,
+        ref_names,
+    }))
+}
+
 #[derive(Clone, Debug)]
 struct UndoCheckoutTarget {
     target: CheckoutTarget,
     options: CheckOutCommitOptions,
 }
 
-fn extract_checkout_target(
-    events: &[Event],
-) -> eyre::Result<(Option<UndoCheckoutTarget>, Vec<&Event>)> {
+fn extract_checkout_target<'a>(
+    events: &'a [Event],
+    head_ref_name: &ReferenceName,
+) -> eyre::Result<(Option<UndoCheckoutTarget>, Vec<&'a Event>)> {
     let mut new_events = Vec::new();
     let mut checkout_target = None;
     for event in events.iter() {
@@ -738,7 +1012,7 @@ fn extract_checkout_target(
                 old_oid: _,
                 new_oid: MaybeZeroOid::NonZero(new_oid),
                 message: _,
-            } if ref_name.as_str() == "HEAD" => {
+            } if ref_name == head_ref_name => {
                 checkout_target = Some(UndoCheckoutTarget {
                     target: CheckoutTarget::Oid(*new_oid),
                     options: CheckOutCommitOptions {
@@ -779,6 +1053,63 @@ fn extract_checkout_target(
     Ok((checkout_target, new_events))
 }
 
+fn is_ancestor_or_equal(
+    repo: &Repo,
+    ancestor_oid: NonZeroOid,
+    descendant_oid: NonZeroOid,
+) -> eyre::Result<bool> {
+    Ok(repo.find_merge_base(ancestor_oid, descendant_oid)? == Some(ancestor_oid))
+}
+
+/// Find the branches which `inverse_events` would move so that they no longer
+/// include commits which were already pushed to their upstream branches.
+///
+/// Returns: The names of each such branch and its upstream branch.
+fn find_diverging_pushed_branches(
+    repo: &Repo,
+    inverse_events: &[Event],
+) -> eyre::Result<Vec<(String, String)>> {
+    let mut result = Vec::new();
+    for event in inverse_events {
+        let (ref_name, target_oid) = match event {
+            Event::RefUpdateEvent {
+                timestamp: _,
+                event_tx_id: _,
+                ref_name,
+                old_oid: _,
+                new_oid: MaybeZeroOid::NonZero(new_oid),
+                message: _,
+            } if ref_name.as_str().starts_with("refs/heads/") => (ref_name, *new_oid),
+            _ => continue,
+        };
+
+        let branch_name = CategorizedReferenceName::new(ref_name).render_suffix();
+        let branch = match repo.find_branch(&branch_name, BranchType::Local)? {
+            Some(branch) => branch,
+            None => continue,
+        };
+        let branch_oid = match branch.get_oid()? {
+            Some(branch_oid) => branch_oid,
+            None => continue,
+        };
+        let upstream_branch = match branch.get_upstream_branch()? {
+            Some(upstream_branch) => upstream_branch,
+            None => continue,
+        };
+        let upstream_oid = match upstream_branch.get_oid()? {
+            Some(upstream_oid) => upstream_oid,
+            None => continue,
+        };
+
+        let is_pushed = is_ancestor_or_equal(repo, branch_oid, upstream_oid)?;
+        let would_diverge = !is_ancestor_or_equal(repo, upstream_oid, target_oid)?;
+        if is_pushed && would_diverge {
+            result.push((branch_name, upstream_branch.get_name()?.to_owned()));
+        }
+    }
+    Ok(result)
+}
+
 #[instrument(skip(in_))]
 fn undo_events(
     in_: &mut impl Read,
@@ -789,26 +1120,38 @@ fn undo_events(
     event_replayer: &EventReplayer,
     event_cursor: EventCursor,
     skip_confirmation: bool,
+    undo_filter: Option<&UndoFilter>,
+    force: bool,
 ) -> EyreExitOr<()> {
     let now = SystemTime::now();
     let event_tx_id = event_log_db.make_transaction_id(now, "undo")?;
     let head_info = repo.get_head_info()?;
-    let inverse_events: Vec<Event> = event_replayer
-        .get_events_since_cursor(event_cursor)
+    let events = event_replayer.get_events_since_cursor(event_cursor);
+    let undo_filter = undo_filter.map(|undo_filter| undo_filter.with_ref_targets(events));
+    let head_ref_name = event_replayer.get_head_ref_name();
+    let inverse_events: Vec<Event> = events
         .iter()
         .rev()
+        .filter(|event| match event {
+            Event::RefUpdateEvent {
+                timestamp: _,
+                event_tx_id: _,
+                ref_name,
+                old_oid,
+                new_oid: _,
+                message: _,
+            } if is_head_ref_name(ref_name) => {
+                // The `HEAD`s of other worktrees can't be moved from here
+                // without leaving their working copies out of sync, so only
+                // undo checkouts made in this worktree.
+                ref_name == head_ref_name && matches!(old_oid, MaybeZeroOid::NonZero(_))
+            }
+            _ => true,
+        })
         .filter(|event| {
-            !matches!(
-                event,
-                Event::RefUpdateEvent {
-                    timestamp: _,
-                    event_tx_id: _,
-                    ref_name,
-                    old_oid: MaybeZeroOid::Zero,
-                    new_oid: _,
-                    message: _,
-                } if ref_name.as_str() == "HEAD"
-            )
+            undo_filter
+                .as_ref()
+                .map_or(true, |undo_filter| undo_filter.matches(event))
         })
         .map(|event| inverse_event(event.clone(), now, event_tx_id))
         .collect::<eyre::Result<Vec<Event>>>()?;
@@ -830,6 +1173,35 @@ fn undo_events(
         )?;
     }
 
+    let diverging_pushed_branches = find_diverging_pushed_branches(repo, &inverse_events)?;
+    if !diverging_pushed_branches.is_empty() {
+        for (branch_name, upstream_branch_name) in diverging_pushed_branches.iter() {
+            writeln!(
+                effects.get_output_stream(),
+                "{}",
+                effects.get_glyphs().render(StyledString::styled(
+                    format!(
+                        "Warning: branch {branch_name} would no longer include the commits already pushed to {upstream_branch_name}."
+                    ),
+                    BaseColor::Yellow.light(),
+                ))?
+            )?;
+        }
+        writeln!(
+            effects.get_output_stream(),
+            "\
+Your collaborators may already have these commits, so undoing this operation
+could cause you to recreate commits that they already have."
+        )?;
+        if !force {
+            writeln!(
+                effects.get_output_stream(),
+                "Retry with --force to proceed anyways."
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+    }
+
     let confirmed = if skip_confirmation {
         true
     } else {
@@ -856,7 +1228,8 @@ fn undo_events(
     }
     .to_string();
 
-    let (checkout_target, filtered_events) = extract_checkout_target(&inverse_events)?;
+    let (checkout_target, filtered_events) =
+        extract_checkout_target(&inverse_events, head_ref_name)?;
     if checkout_target.is_some() {
         repo.detach_head(&head_info)?;
     }
@@ -938,9 +1311,72 @@ fn undo_events(
     }
 
     writeln!(effects.get_output_stream(), "Applied {num_inverse_events}.")?;
+    run_post_undo_hook(
+        effects,
+        repo,
+        git_run_info,
+        event_tx_id,
+        event_replayer,
+        event_cursor,
+    )?;
     Ok(Ok(()))
 }
 
+/// Run the user's `branchless.hook.postUndo` command, if any, after an undo
+/// transaction has been applied. The range of event IDs which were undone is
+/// passed in environment variables. A failing hook is reported, but doesn't
+/// cause the undo itself to fail, since it has already been applied.
+#[instrument]
+fn run_post_undo_hook(
+    effects: &Effects,
+    repo: &Repo,
+    git_run_info: &GitRunInfo,
+    event_tx_id: EventTransactionId,
+    event_replayer: &EventReplayer,
+    event_cursor: EventCursor,
+) -> eyre::Result<()> {
+    let command = match get_hook_post_undo(repo)? {
+        Some(command) => command,
+        None => return Ok(()),
+    };
+    let shell_path = match get_sh() {
+        Some(shell_path) => shell_path,
+        None => {
+            writeln!(
+                effects.get_error_stream(),
+                "branchless: could not determine path to shell, not running postUndo hook"
+            )?;
+            return Ok(());
+        }
+    };
+
+    let get_event_id = |cursor: EventCursor| -> isize {
+        match event_replayer.get_event_before_cursor(cursor) {
+            Some((event_id, _event)) => event_id,
+            None => 0,
+        }
+    };
+    let first_event_id = get_event_id(event_cursor) + 1;
+    let last_event_id = get_event_id(event_replayer.make_default_cursor());
+
+    let exit_status = Command::new(shell_path)
+        .arg("-c")
+        .arg(&command)
+        .current_dir(&git_run_info.working_directory)
+        .env(BRANCHLESS_TRANSACTION_ID_ENV_VAR, event_tx_id.to_string())
+        .env("BRANCHLESS_UNDO_FIRST_EVENT_ID", first_event_id.to_string())
+        .env("BRANCHLESS_UNDO_LAST_EVENT_ID", last_event_id.to_string())
+        .status()
+        .wrap_err("Running postUndo hook")?;
+    if !exit_status.success() {
+        writeln!(
+            effects.get_error_stream(),
+            "branchless: postUndo hook failed ({exit_status}): {command}"
+        )?;
+    }
+    Ok(())
+}
+
 /// Restore the repository to a previous state interactively.
 #[instrument]
 pub fn undo(
@@ -948,13 +1384,15 @@ pub fn undo(
     git_run_info: &GitRunInfo,
     interactive: bool,
     skip_confirmation: bool,
+    only: Vec<Revset>,
+    force: bool,
 ) -> EyreExitOr<()> {
     let repo = Repo::from_current_dir()?;
     let references_snapshot = repo.get_references_snapshot()?;
     let conn = repo.get_db_conn()?;
     let mut event_log_db = EventLogDb::new(&conn)?;
     let mut event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
-    let dag = {
+    let mut dag = {
         // Don't let `event_cursor` leak from this scope, since we intend to
         // determine a new event cursor below.
         let event_cursor = event_replayer.make_default_cursor();
@@ -968,6 +1406,18 @@ pub fn undo(
         )?
     };
 
+    let undo_filter = if only.is_empty() {
+        None
+    } else {
+        Some(try_exit_code!(make_undo_filter(
+            effects,
+            &repo,
+            &mut dag,
+            &event_replayer,
+            only
+        )?))
+    };
+
     let event_cursor = {
         if interactive {
             let result = with_siv(effects, |effects, siv| {
@@ -991,6 +1441,8 @@ pub fn undo(
         &event_replayer,
         event_cursor,
         skip_confirmation,
+        undo_filter.as_ref(),
+        force,
     )?;
     Ok(result)
 }
@@ -1035,6 +1487,8 @@ pub mod testing {
             event_replayer,
             event_cursor,
             false,
+            None,
+            false,
         )
     }
 }
@@ -1066,7 +1520,7 @@ mod tests {
                 message: None,
             },
         ];
-        insta::assert_debug_snapshot!(extract_checkout_target(&input)?, @r###"
+        insta::assert_debug_snapshot!(extract_checkout_target(&input, &"HEAD".into())?, @r###"
         (
             Some(
                 UndoCheckoutTarget {
@@ -3,6 +3,12 @@
 //! This command amends the HEAD commit with changes to files
 //! that are already tracked in the repo. Following the amend,
 //! the command performs a restack.
+//!
+//! With `--to`, the changes are instead amended into an ancestor of HEAD, and
+//! the commits between it and HEAD are rebased in memory.
+//!
+//! With `--resolve`, any merge conflicts caused by restacking the descendant
+//! commits are resolved interactively in memory.
 
 use std::collections::HashMap;
 
@@ -12,21 +18,28 @@ use std::time::{SystemTime, UNIX_EPOCH};
 use bstr::ByteSlice;
 
 use eyre::Context;
-use git_branchless_opts::{MoveOptions, ResolveRevsetOptions};
+use git_branchless_move::execute_rebase_plan_resolving_conflicts;
+use git_branchless_opts::{MoveOptions, ResolveRevsetOptions, Revset};
+use git_branchless_revset::resolve_commits;
 use itertools::Itertools;
 use lib::core::check_out::{check_out_commit, CheckOutCommitOptions, CheckoutTarget};
 use lib::core::config::get_restack_preserve_timestamps;
-use lib::core::dag::{CommitSet, Dag};
+use lib::core::dag::{union_all, CommitSet, Dag};
 use lib::core::effects::Effects;
-use lib::core::eventlog::{Event, EventLogDb, EventReplayer};
+use lib::core::eventlog::{Event, EventLogDb, EventReplayer, EventTransactionId};
 use lib::core::formatting::Pluralize;
 use lib::core::gc::mark_commit_reachable;
 use lib::core::repo_ext::RepoExt;
+use lib::core::rewrite::rewrite_hooks::run_post_rewrite_user_hook;
 use lib::core::rewrite::{
-    execute_rebase_plan, move_branches, BuildRebasePlanOptions, ExecuteRebasePlanOptions,
-    ExecuteRebasePlanResult, RebasePlanBuilder, RebasePlanPermissions, RepoResource,
+    execute_rebase_plan, move_branches, BuildRebasePlanOptions, EmptyCommitsMode,
+    ExecuteRebasePlanOptions, ExecuteRebasePlanResult, MergeConflictRemediation, RebasePlanBuilder,
+    RebasePlanPermissions, RepoResource,
+};
+use lib::git::{
+    AmendFastOptions, CherryPickFastOptions, Commit, CreateCommitFastError, GitRunInfo,
+    MaybeZeroOid, NonZeroOid, Repo, ResolvedReferenceInfo, Signature, Signer, StatusEntry, Tree,
 };
-use lib::git::{AmendFastOptions, GitRunInfo, MaybeZeroOid, Repo, ResolvedReferenceInfo};
 use lib::try_exit_code;
 use lib::util::{ExitCode, EyreExitOr};
 use rayon::ThreadPoolBuilder;
@@ -40,6 +53,8 @@ pub fn amend(
     resolve_revset_options: &ResolveRevsetOptions,
     move_options: &MoveOptions,
     reparent: bool,
+    to: Option<Revset>,
+    resolve: bool,
 ) -> EyreExitOr<()> {
     let now = SystemTime::now();
     let timestamp = now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64();
@@ -79,13 +94,73 @@ pub fn amend(
         return Ok(Err(ExitCode(1)));
     }
 
+    let target_commit = match &to {
+        None => None,
+        Some(revset) => {
+            let commit_set = match resolve_commits(
+                effects,
+                &repo,
+                &mut dag,
+                &[revset.clone()],
+                resolve_revset_options,
+            ) {
+                Ok(commit_sets) => union_all(&commit_sets),
+                Err(err) => {
+                    err.describe(effects)?;
+                    return Ok(Err(ExitCode(1)));
+                }
+            };
+            let target_oid = match dag.commit_set_to_vec(&commit_set)?.as_slice() {
+                [target_oid] => *target_oid,
+                target_oids => {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "Expected exactly 1 commit to amend into, but '{}' evaluated to {}.",
+                        revset,
+                        target_oids.len()
+                    )?;
+                    return Ok(Err(ExitCode(1)));
+                }
+            };
+
+            if target_oid == head_oid {
+                None
+            } else {
+                let target_commit = repo.find_commit_or_fail(target_oid)?;
+                if !dag.query_is_ancestor(target_oid, head_oid)? {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "Cannot amend into {}, because it is not an ancestor of HEAD.",
+                        effects
+                            .get_glyphs()
+                            .render(target_commit.friendly_describe(effects.get_glyphs())?)?
+                    )?;
+                    return Ok(Err(ExitCode(1)));
+                }
+                if reparent {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "The --reparent option cannot be used when amending into a commit other than HEAD."
+                    )?;
+                    return Ok(Err(ExitCode(1)));
+                }
+                Some(target_commit)
+            }
+        }
+    };
+
     let build_options = BuildRebasePlanOptions {
         force_rewrite_public_commits: move_options.force_rewrite_public_commits,
         dump_rebase_constraints: move_options.dump_rebase_constraints,
         dump_rebase_plan: move_options.dump_rebase_plan,
         detect_duplicate_commits_via_patch_id: move_options.detect_duplicate_commits_via_patch_id,
     };
-    let commits_to_verify = dag.query_descendants(CommitSet::from(head_oid))?;
+    let commits_to_verify = dag.query_descendants(CommitSet::from(
+        target_commit
+            .as_ref()
+            .map(|target_commit| target_commit.get_oid())
+            .unwrap_or(head_oid),
+    ))?;
     let commits_to_verify = dag.filter_visible_commits(commits_to_verify)?;
     if let Err(err) =
         RebasePlanPermissions::verify_rewrite_set(&dag, build_options, &commits_to_verify)?
@@ -146,7 +221,9 @@ pub fn amend(
     let amended_tree = repo.amend_fast(&head_commit, &opts)?;
 
     let (author, committer) = (head_commit.get_author(), head_commit.get_committer());
-    let (author, committer) = if get_restack_preserve_timestamps(&repo)? {
+    let preserve_timestamps =
+        move_options.preserve_timestamps || get_restack_preserve_timestamps(&repo)?;
+    let (author, committer) = if preserve_timestamps {
         (author, committer)
     } else {
         (
@@ -154,13 +231,43 @@ pub fn amend(
             committer.update_timestamp(now)?,
         )
     };
+    let (author, committer) = apply_author_options(&repo, move_options, now, author, committer)?;
 
-    let amended_commit_oid = head_commit.amend_commit(
-        None,
+    if let Some(target_commit) = target_commit {
+        let amended_target_oid = try_exit_code!(amend_into_ancestor(
+            effects,
+            git_run_info,
+            &repo,
+            &dag,
+            &event_log_db,
+            event_tx_id,
+            now,
+            move_options,
+            preserve_timestamps,
+            &head_commit,
+            &target_commit,
+            &amended_tree,
+            resolve,
+        )?);
+        let amended_target_commit = repo.find_commit_or_fail(amended_target_oid)?;
+        writeln!(
+            effects.get_output_stream(),
+            "Amended into: {}",
+            effects
+                .get_glyphs()
+                .render(amended_target_commit.friendly_describe(effects.get_glyphs())?)?
+        )?;
+        write_amended_message(effects, opts, &unstaged_entries)?;
+        return Ok(Ok(()));
+    }
+
+    let amended_commit_oid = head_commit.amend_commit_with_signer(
+        &repo,
         Some(&author),
         Some(&committer),
         None,
         Some(&amended_tree),
+        Signer::from_config(&repo)?.as_ref(),
     )?;
 
     // Switch to the new commit and move any branches. This is kind of a hack:
@@ -184,11 +291,12 @@ pub fn amend(
             CommitSet::empty(),
             CommitSet::from(amended_commit_oid),
         )?;
-        move_branches(effects, git_run_info, &repo, event_tx_id, &{
+        let amended_oids = {
             let mut result = HashMap::new();
             result.insert(head_oid, MaybeZeroOid::NonZero(amended_commit_oid));
             result
-        })?;
+        };
+        move_branches(effects, git_run_info, &repo, event_tx_id, &amended_oids)?;
 
         let target = match &head_info.reference_name {
             Some(name) => CheckoutTarget::Reference(name.clone()),
@@ -207,6 +315,15 @@ pub fn amend(
                 render_smartlog: false,
             },
         )?);
+
+        run_post_rewrite_user_hook(
+            effects,
+            git_run_info,
+            &repo,
+            event_tx_id,
+            "amend",
+            &amended_oids,
+        )?;
     }
 
     let rebase_plan = {
@@ -293,22 +410,52 @@ pub fn amend(
             event_tx_id,
             force_in_memory: move_options.force_in_memory,
             force_on_disk: move_options.force_on_disk,
-            preserve_timestamps: get_restack_preserve_timestamps(&repo)?,
+            preserve_timestamps,
             resolve_merge_conflicts: move_options.resolve_merge_conflicts,
+            // The author options were already applied to the amended commit,
+            // and descendants keep their original authors.
+            reset_author: false,
+            committer_as_author: false,
+            signature_commit_oids: None,
             check_out_commit_options: CheckOutCommitOptions {
                 additional_args: Default::default(),
                 reset: false,
                 render_smartlog: false,
             },
+            empty_commits: EmptyCommitsMode::Drop,
         };
-        match execute_rebase_plan(
-            effects,
-            git_run_info,
-            &repo,
-            &event_log_db,
-            &rebase_plan,
-            &execute_options,
-        )? {
+        let result = if resolve {
+            match execute_rebase_plan_resolving_conflicts(
+                effects,
+                git_run_info,
+                &repo,
+                &event_log_db,
+                &rebase_plan,
+                &execute_options,
+            )? {
+                Ok(result) => result,
+                Err(exit_code) => {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "Amending without restacking descendant commits: {}",
+                        effects
+                            .get_glyphs()
+                            .render(head_commit.friendly_describe(effects.get_glyphs())?)?
+                    )?;
+                    return Ok(Err(exit_code));
+                }
+            }
+        } else {
+            execute_rebase_plan(
+                effects,
+                git_run_info,
+                &repo,
+                &event_log_db,
+                &rebase_plan,
+                &execute_options,
+            )?
+        };
+        match result {
             ExecuteRebasePlanResult::Succeeded {
                 rewritten_oids: None,
             } => {}
@@ -348,6 +495,38 @@ pub fn amend(
         }
     }
 
+    write_amended_message(effects, opts, &unstaged_entries)?;
+
+    Ok(Ok(()))
+}
+
+/// Apply the `--reset-author` and `--committer-as-author` options to the
+/// signatures of an amended commit.
+fn apply_author_options<'a>(
+    repo: &Repo,
+    move_options: &MoveOptions,
+    now: SystemTime,
+    author: Signature<'a>,
+    committer: Signature<'a>,
+) -> eyre::Result<(Signature<'a>, Signature<'a>)> {
+    let author = if move_options.reset_author {
+        repo.get_current_user_signature()?.update_timestamp(now)?
+    } else {
+        author
+    };
+    let committer = if move_options.committer_as_author {
+        author.to_owned()
+    } else {
+        committer
+    };
+    Ok((author, committer))
+}
+
+fn write_amended_message(
+    effects: &Effects,
+    opts: AmendFastOptions,
+    unstaged_entries: &[StatusEntry],
+) -> eyre::Result<()> {
     match opts {
         AmendFastOptions::FromIndex { paths } => {
             let staged_changes = Pluralize {
@@ -378,5 +557,163 @@ pub fn amend(
         }
     }
 
-    Ok(Ok(()))
+    Ok(())
+}
+
+/// Apply the changes in `amended_tree` (relative to `head_commit`) to
+/// `target_commit`, an ancestor of HEAD, and rebase the commits between them
+/// (and any other descendants of `target_commit`) in memory. Returns the OID
+/// of the rewritten target commit.
+fn amend_into_ancestor(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    dag: &Dag,
+    event_log_db: &EventLogDb,
+    event_tx_id: EventTransactionId,
+    now: SystemTime,
+    move_options: &MoveOptions,
+    preserve_timestamps: bool,
+    head_commit: &Commit,
+    target_commit: &Commit,
+    amended_tree: &Tree,
+    resolve: bool,
+) -> EyreExitOr<NonZeroOid> {
+    // Record the changes as a temporary commit on top of HEAD, so that they
+    // can be cherry-picked onto the target commit.
+    let fixup_commit_oid = repo.create_commit(
+        None,
+        &head_commit.get_author(),
+        &head_commit.get_committer(),
+        "fixup",
+        amended_tree,
+        vec![head_commit],
+    )?;
+    let fixup_commit = repo.find_commit_or_fail(fixup_commit_oid)?;
+    let target_tree = match repo.cherry_pick_fast(
+        &fixup_commit,
+        target_commit,
+        &CherryPickFastOptions {
+            reuse_parent_tree_if_possible: false,
+        },
+    ) {
+        Ok(target_tree) => target_tree,
+        Err(CreateCommitFastError::MergeConflict { conflicting_paths }) => {
+            writeln!(
+                effects.get_output_stream(),
+                "Cannot amend into {}, because the changes conflict with it in these paths:",
+                effects
+                    .get_glyphs()
+                    .render(target_commit.friendly_describe(effects.get_glyphs())?)?
+            )?;
+            for path in conflicting_paths.into_iter().sorted() {
+                writeln!(effects.get_output_stream(), "  {}", path.display())?;
+            }
+            return Ok(Err(ExitCode(1)));
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    let (author, committer) = (target_commit.get_author(), target_commit.get_committer());
+    let (author, committer) = if preserve_timestamps {
+        (author, committer)
+    } else {
+        (
+            author.update_timestamp(now)?,
+            committer.update_timestamp(now)?,
+        )
+    };
+    let (author, committer) = apply_author_options(repo, move_options, now, author, committer)?;
+    let amended_target_oid = target_commit.amend_commit(
+        None,
+        Some(&author),
+        Some(&committer),
+        None,
+        Some(&target_tree),
+    )?;
+
+    let rebase_plan = {
+        let build_options = BuildRebasePlanOptions {
+            force_rewrite_public_commits: move_options.force_rewrite_public_commits,
+            detect_duplicate_commits_via_patch_id: move_options
+                .detect_duplicate_commits_via_patch_id,
+            dump_rebase_constraints: move_options.dump_rebase_constraints,
+            dump_rebase_plan: move_options.dump_rebase_plan,
+        };
+        let descendants = dag.query_descendants(CommitSet::from(target_commit.get_oid()))?;
+        let descendants = dag.filter_visible_commits(descendants)?;
+        let permissions =
+            match RebasePlanPermissions::verify_rewrite_set(dag, build_options, &descendants)? {
+                Ok(permissions) => permissions,
+                Err(err) => {
+                    err.describe(effects, repo, dag)?;
+                    return Ok(Err(ExitCode(1)));
+                }
+            };
+
+        let mut builder = RebasePlanBuilder::new(dag, permissions);
+        builder.move_subtree(target_commit.get_oid(), target_commit.get_parent_oids())?;
+        builder.replace_commit(target_commit.get_oid(), amended_target_oid)?;
+
+        let thread_pool = ThreadPoolBuilder::new().build()?;
+        let repo_pool = RepoResource::new_pool(repo)?;
+        match builder.build(effects, &thread_pool, &repo_pool)? {
+            Ok(Some(rebase_plan)) => rebase_plan,
+            Ok(None) => return Ok(Ok(amended_target_oid)),
+            Err(err) => {
+                err.describe(effects, repo, dag)?;
+                return Ok(Err(ExitCode(1)));
+            }
+        }
+    };
+
+    // The rebase must happen in memory, since the working copy still contains
+    // the changes being amended. Reset (rather than check out) to the new HEAD
+    // afterwards, so that those changes no longer show up as uncommitted.
+    let execute_options = ExecuteRebasePlanOptions {
+        now,
+        event_tx_id,
+        force_in_memory: true,
+        force_on_disk: false,
+        preserve_timestamps,
+        resolve_merge_conflicts: false,
+        // The author options were already applied to the amended commit, and
+        // descendants keep their original authors.
+        reset_author: false,
+        committer_as_author: false,
+        signature_commit_oids: None,
+        check_out_commit_options: CheckOutCommitOptions {
+            additional_args: Default::default(),
+            reset: true,
+            render_smartlog: false,
+        },
+        empty_commits: EmptyCommitsMode::Drop,
+    };
+    let result = if resolve {
+        try_exit_code!(execute_rebase_plan_resolving_conflicts(
+            effects,
+            git_run_info,
+            repo,
+            event_log_db,
+            &rebase_plan,
+            &execute_options,
+        )?)
+    } else {
+        execute_rebase_plan(
+            effects,
+            git_run_info,
+            repo,
+            event_log_db,
+            &rebase_plan,
+            &execute_options,
+        )?
+    };
+    match result {
+        ExecuteRebasePlanResult::Succeeded { rewritten_oids: _ } => Ok(Ok(amended_target_oid)),
+        ExecuteRebasePlanResult::DeclinedToMerge { failed_merge_info } => {
+            failed_merge_info.describe(effects, repo, MergeConflictRemediation::Retry)?;
+            Ok(Err(ExitCode(1)))
+        }
+        ExecuteRebasePlanResult::Failed { exit_code } => Ok(Err(exit_code)),
+    }
 }
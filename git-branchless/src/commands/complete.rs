@@ -0,0 +1,19 @@
+//! Print completions for shell completion scripts.
+
+use std::fmt::Write;
+
+use lib::core::effects::Effects;
+use lib::git::Repo;
+use lib::util::EyreExitOr;
+use tracing::instrument;
+
+/// Print the completions for the last word of the given partially typed revset
+/// expression, one per line.
+#[instrument]
+pub fn complete_revset(effects: &Effects, word: &str) -> EyreExitOr<()> {
+    let repo = Repo::from_current_dir()?;
+    for completion in git_branchless_revset::complete_revset(&repo, word)? {
+        writeln!(effects.get_output_stream(), "{completion}")?;
+    }
+    Ok(Ok(()))
+}
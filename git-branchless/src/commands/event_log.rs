@@ -0,0 +1,78 @@
+//! Export and import the event log, so that the history of operations can be
+//! carried over to a new clone of the repository.
+
+use std::fmt::Write;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use eyre::Context;
+use lib::core::effects::Effects;
+use lib::core::eventlog::{EventLogDb, EventReplayer, ExportedEventLog};
+use lib::core::formatting::Pluralize;
+use lib::core::gc::mark_commit_reachable;
+use lib::git::Repo;
+use lib::util::EyreExitOr;
+use tracing::instrument;
+
+/// Write the contents of the event log to the file at `path`.
+#[instrument]
+pub fn export(effects: &Effects, path: &Path) -> EyreExitOr<()> {
+    let repo = Repo::from_current_dir()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let exported_event_log = event_log_db.export_event_log()?;
+
+    let file =
+        File::create(path).wrap_err_with(|| format!("Creating event log export file: {path:?}"))?;
+    serde_json::to_writer_pretty(BufWriter::new(file), &exported_event_log)
+        .wrap_err("Writing event log export")?;
+
+    writeln!(
+        effects.get_output_stream(),
+        "Exported {} to {}",
+        Pluralize {
+            determiner: None,
+            amount: exported_event_log.events.len(),
+            unit: ("event", "events"),
+        },
+        path.display(),
+    )?;
+    Ok(Ok(()))
+}
+
+/// Append the contents of the event log export at `path` to the event log for
+/// the current repository.
+#[instrument]
+pub fn import(effects: &Effects, path: &Path) -> EyreExitOr<()> {
+    let repo = Repo::from_current_dir()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+
+    let file =
+        File::open(path).wrap_err_with(|| format!("Opening event log export file: {path:?}"))?;
+    let exported_event_log: ExportedEventLog =
+        serde_json::from_reader(BufReader::new(file)).wrap_err("Parsing event log export")?;
+    let num_events = event_log_db.import_event_log(exported_event_log)?;
+
+    // The imported events may refer to commits which aren't referenced by any
+    // branch in this repository. Keep the ones which exist alive so that they
+    // aren't garbage-collected by Git.
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    for commit_oid in event_replayer.get_cursor_oids(event_cursor) {
+        mark_commit_reachable(&repo, commit_oid)?;
+    }
+
+    writeln!(
+        effects.get_output_stream(),
+        "Imported {} from {}",
+        Pluralize {
+            determiner: None,
+            amount: num_events,
+            unit: ("event", "events"),
+        },
+        path.display(),
+    )?;
+    Ok(Ok(()))
+}
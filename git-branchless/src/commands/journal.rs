@@ -0,0 +1,230 @@
+//! Implements the `git branchless journal` command.
+
+use std::fmt::Write;
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, Local};
+use cursive_core::utils::markup::StyledString;
+use itertools::Itertools;
+use lib::core::effects::Effects;
+use lib::core::eventlog::{is_head_ref_name, Event, EventLogDb, EventReplayer, EventTransactionId};
+use lib::core::formatting::{Glyphs, StyledStringBuilder};
+use lib::git::{CategorizedReferenceName, MaybeZeroOid, Repo};
+use lib::util::EyreExitOr;
+use tracing::instrument;
+
+use git_branchless_opts::JournalEventType;
+
+fn get_event_type(event: &Event) -> JournalEventType {
+    match event {
+        Event::CommitEvent { .. } => JournalEventType::Commit,
+        Event::RewriteEvent { .. } => JournalEventType::Rewrite,
+        Event::RefUpdateEvent { .. } => JournalEventType::Ref,
+        Event::ObsoleteEvent { .. } => JournalEventType::Hide,
+        Event::UnobsoleteEvent { .. } => JournalEventType::Unhide,
+        Event::WorkingCopySnapshot { .. } => JournalEventType::Snapshot,
+    }
+}
+
+fn describe_event(glyphs: &Glyphs, repo: &Repo, event: &Event) -> eyre::Result<StyledString> {
+    let describe_oid = |oid: MaybeZeroOid| -> eyre::Result<StyledString> {
+        match oid {
+            MaybeZeroOid::NonZero(oid) => Ok(repo.friendly_describe_commit_from_oid(glyphs, oid)?),
+            MaybeZeroOid::Zero => Ok(StyledString::plain("<none>")),
+        }
+    };
+
+    let result = match event {
+        Event::CommitEvent {
+            timestamp: _,
+            event_tx_id: _,
+            commit_oid,
+        } => StyledStringBuilder::new()
+            .append_plain("committed ")
+            .append(repo.friendly_describe_commit_from_oid(glyphs, *commit_oid)?)
+            .build(),
+
+        Event::ObsoleteEvent {
+            timestamp: _,
+            event_tx_id: _,
+            commit_oid,
+        } => StyledStringBuilder::new()
+            .append_plain("hid ")
+            .append(repo.friendly_describe_commit_from_oid(glyphs, *commit_oid)?)
+            .build(),
+
+        Event::UnobsoleteEvent {
+            timestamp: _,
+            event_tx_id: _,
+            commit_oid,
+        } => StyledStringBuilder::new()
+            .append_plain("unhid ")
+            .append(repo.friendly_describe_commit_from_oid(glyphs, *commit_oid)?)
+            .build(),
+
+        Event::RewriteEvent {
+            timestamp: _,
+            event_tx_id: _,
+            old_commit_oid,
+            new_commit_oid: MaybeZeroOid::Zero,
+        } => StyledStringBuilder::new()
+            .append_plain("dropped ")
+            .append(describe_oid(*old_commit_oid)?)
+            .build(),
+
+        Event::RewriteEvent {
+            timestamp: _,
+            event_tx_id: _,
+            old_commit_oid,
+            new_commit_oid,
+        } => StyledStringBuilder::new()
+            .append_plain("rewrote ")
+            .append(describe_oid(*old_commit_oid)?)
+            .append_plain(" as ")
+            .append(describe_oid(*new_commit_oid)?)
+            .build(),
+
+        Event::RefUpdateEvent {
+            timestamp: _,
+            event_tx_id: _,
+            ref_name,
+            old_oid: _,
+            new_oid,
+            message: _,
+        } if is_head_ref_name(ref_name) => {
+            let builder = StyledStringBuilder::new()
+                .append_plain("checked out ")
+                .append(describe_oid(*new_oid)?);
+            let worktree_name = ref_name
+                .as_str()
+                .strip_prefix("worktrees/")
+                .and_then(|ref_name| ref_name.strip_suffix("/HEAD"));
+            match worktree_name {
+                Some(worktree_name) => builder
+                    .append_plain(format!(" in worktree {worktree_name}"))
+                    .build(),
+                None => builder.build(),
+            }
+        }
+
+        Event::RefUpdateEvent {
+            timestamp: _,
+            event_tx_id: _,
+            ref_name,
+            old_oid: MaybeZeroOid::Zero,
+            new_oid,
+            message: _,
+        } => StyledStringBuilder::new()
+            .append_plain("created ")
+            .append_plain(CategorizedReferenceName::new(ref_name).friendly_describe())
+            .append_plain(" at ")
+            .append(describe_oid(*new_oid)?)
+            .build(),
+
+        Event::RefUpdateEvent {
+            timestamp: _,
+            event_tx_id: _,
+            ref_name,
+            old_oid,
+            new_oid: MaybeZeroOid::Zero,
+            message: _,
+        } => StyledStringBuilder::new()
+            .append_plain("deleted ")
+            .append_plain(CategorizedReferenceName::new(ref_name).friendly_describe())
+            .append_plain(" at ")
+            .append(describe_oid(*old_oid)?)
+            .build(),
+
+        Event::RefUpdateEvent {
+            timestamp: _,
+            event_tx_id: _,
+            ref_name,
+            old_oid: _,
+            new_oid,
+            message: _,
+        } => StyledStringBuilder::new()
+            .append_plain("moved ")
+            .append_plain(CategorizedReferenceName::new(ref_name).friendly_describe())
+            .append_plain(" to ")
+            .append(describe_oid(*new_oid)?)
+            .build(),
+
+        Event::WorkingCopySnapshot {
+            timestamp: _,
+            event_tx_id: _,
+            head_oid,
+            commit_oid,
+            ref_name: _,
+        } => StyledStringBuilder::new()
+            .append_plain("snapshotted working copy at ")
+            .append(describe_oid(*head_oid)?)
+            .append_plain(" as ")
+            .append_plain(commit_oid.to_string())
+            .build(),
+    };
+    Ok(result)
+}
+
+fn describe_event_tx_message(event_log_db: &EventLogDb, event_tx_id: EventTransactionId) -> String {
+    match event_log_db.get_transaction_message(event_tx_id) {
+        Ok(message) => message,
+        Err(_) => "<unknown operation>".to_string(),
+    }
+}
+
+/// Print the event log as a chronological list of operations, grouped by
+/// event transaction.
+#[instrument]
+pub fn journal(
+    effects: &Effects,
+    since: Option<Duration>,
+    until: Option<Duration>,
+    event_types: Vec<JournalEventType>,
+) -> EyreExitOr<()> {
+    let repo = Repo::from_current_dir()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let glyphs = effects.get_glyphs();
+
+    let now = SystemTime::now();
+    // A duration reaching back before the earliest representable time covers
+    // the entire event log.
+    let earliest_time =
+        |duration: Duration| now.checked_sub(duration).unwrap_or(SystemTime::UNIX_EPOCH);
+    let start_time = since.map(earliest_time);
+    let end_time = until.map(earliest_time);
+    let events = event_replayer
+        .get_events_since_cursor(event_replayer.make_cursor(0))
+        .iter()
+        .filter(|event| {
+            let timestamp = event.get_timestamp();
+            start_time.map_or(true, |start_time| timestamp >= start_time)
+                && end_time.map_or(true, |end_time| timestamp <= end_time)
+                && (event_types.is_empty() || event_types.contains(&get_event_type(event)))
+        });
+
+    for (event_tx_id, tx_events) in &events.chunk_by(|event| event.get_event_tx_id()) {
+        let tx_events = tx_events.collect_vec();
+        let timestamp = match tx_events.first() {
+            Some(event) => event.get_timestamp(),
+            None => continue,
+        };
+        let timestamp: DateTime<Local> = timestamp.into();
+        writeln!(
+            effects.get_output_stream(),
+            "{} {}",
+            timestamp.format("%Y-%m-%d %H:%M:%S"),
+            describe_event_tx_message(&event_log_db, event_tx_id),
+        )?;
+        for event in tx_events {
+            writeln!(
+                effects.get_output_stream(),
+                "    {}",
+                glyphs.render(describe_event(glyphs, &repo, event)?)?,
+            )?;
+        }
+    }
+
+    Ok(Ok(()))
+}
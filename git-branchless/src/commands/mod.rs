@@ -2,11 +2,17 @@
 
 mod amend;
 mod bug_report;
+mod complete;
+mod event_log;
 mod hide;
+mod journal;
+mod pin;
 mod repair;
 mod restack;
 mod snapshot;
+mod stash;
 mod sync;
+mod uncommit;
 mod wrap;
 
 use git_branchless_invoke::CommandContext;
@@ -16,7 +22,8 @@ use lib::util::ExitCode;
 use lib::{core::gc, util::EyreExitOr};
 
 use git_branchless_opts::{
-    rewrite_args, Command, Opts, ResolveRevsetOptions, SnapshotSubcommand, WrappedCommand,
+    rewrite_args, Command, EventLogSubcommand, Opts, ResolveRevsetOptions, SnapshotSubcommand,
+    StashSubcommand, TraverseCommitsOptions, WrappedCommand,
 };
 use lib::git::GitRunInfo;
 
@@ -30,20 +37,55 @@ fn command_main(ctx: CommandContext, opts: Opts) -> EyreExitOr<()> {
         command,
     } = opts;
 
+    if writes_repository_state(&command) {
+        if let Some(exit_code) = git_branchless_invoke::check_storage_usable(&effects)? {
+            return Ok(Err(exit_code));
+        }
+    }
+
     let exit_code = match command {
+        Command::Absorb(args) => git_branchless_record::absorb::command_main(ctx, args)?,
+
         Command::Amend {
             move_options,
             reparent,
+            to,
+            resolve,
         } => amend::amend(
             &effects,
             &git_run_info,
             &ResolveRevsetOptions::default(),
             &move_options,
             reparent,
+            to,
+            resolve,
+        )?,
+
+        Command::Bottom { merge, force } => git_branchless_navigation::traverse_commits(
+            &effects,
+            &git_run_info,
+            git_branchless_navigation::Command::Prev,
+            None,
+            &TraverseCommitsOptions {
+                num_commits: None,
+                all_the_way: true,
+                move_by_branches: false,
+                sibling: false,
+                skip_obsolete: false,
+                oldest: false,
+                newest: false,
+                interactive: false,
+                merge,
+                force,
+            },
         )?,
 
         Command::BugReport => bug_report::bug_report(&effects, &git_run_info)?,
 
+        Command::CheckoutPr(args) => git_branchless_submit::checkout_pr::command_main(ctx, args)?,
+
+        Command::CompleteRevset { word } => complete::complete_revset(&effects, &word)?,
+
         Command::Difftool(opts) => {
             let result = scm_diff_editor::run(opts);
             match result {
@@ -55,10 +97,17 @@ fn command_main(ctx: CommandContext, opts: Opts) -> EyreExitOr<()> {
             }
         }
 
+        Command::Edit(args) => git_branchless_record::edit::command_main(ctx, args)?,
+
         Command::Switch { switch_options } => {
             git_branchless_navigation::switch(&effects, &git_run_info, &switch_options)?
         }
 
+        Command::EventLog { subcommand } => match subcommand {
+            EventLogSubcommand::Export { path } => event_log::export(&effects, &path)?,
+            EventLogSubcommand::Import { path } => event_log::import(&effects, &path)?,
+        },
+
         Command::Gc => {
             gc::gc(&effects)?;
             Ok(())
@@ -82,6 +131,17 @@ fn command_main(ctx: CommandContext, opts: Opts) -> EyreExitOr<()> {
 
         Command::Init(args) => git_branchless_init::command_main(ctx, args)?,
 
+        Command::Journal {
+            since,
+            until,
+            event_types,
+        } => journal::journal(
+            &effects,
+            since.map(|since| since.0),
+            until.map(|until| until.0),
+            event_types,
+        )?,
+
         Command::InstallManPages(args) => {
             git_branchless_init::command_install_man_pages(ctx, args)?
         }
@@ -95,6 +155,18 @@ fn command_main(ctx: CommandContext, opts: Opts) -> EyreExitOr<()> {
             move_options,
             fixup,
             insert,
+            insert_after,
+            insert_before,
+            interactive,
+            autosquash,
+            dry_run,
+            resolve,
+            autostash,
+            skip_empty,
+            create_merge,
+            extract_path,
+            continue_move,
+            abort_move,
         } => git_branchless_move::r#move(
             &effects,
             &git_run_info,
@@ -104,25 +176,52 @@ fn command_main(ctx: CommandContext, opts: Opts) -> EyreExitOr<()> {
             exact,
             &resolve_revset_options,
             &move_options,
-            fixup,
-            insert,
+            &git_branchless_move::MoveModeOptions {
+                fixup,
+                insert,
+                interactive,
+                autosquash,
+                dry_run,
+                resolve,
+                autostash,
+                skip_empty,
+                create_merge,
+                continue_move,
+                abort_move,
+            },
+            insert_after,
+            insert_before,
+            extract_path,
         )?,
 
         Command::Next {
             traverse_commits_options,
+            conflict,
+            failed,
         } => git_branchless_navigation::traverse_commits(
             &effects,
             &git_run_info,
             git_branchless_navigation::Command::Next,
+            match (conflict, failed) {
+                (true, _) => Some(git_branchless_navigation::NextTarget::Conflict),
+                (false, true) => Some(git_branchless_navigation::NextTarget::Failed),
+                (false, false) => None,
+            },
             &traverse_commits_options,
         )?,
 
+        Command::Pin {
+            revsets,
+            resolve_revset_options,
+        } => pin::pin(&effects, revsets, &resolve_revset_options)?,
+
         Command::Prev {
             traverse_commits_options,
         } => git_branchless_navigation::traverse_commits(
             &effects,
             &git_run_info,
             git_branchless_navigation::Command::Prev,
+            None,
             &traverse_commits_options,
         )?,
 
@@ -145,6 +244,18 @@ fn command_main(ctx: CommandContext, opts: Opts) -> EyreExitOr<()> {
 
         Command::Record(args) => git_branchless_record::command_main(ctx, args)?,
 
+        Command::Reorder {
+            revsets,
+            resolve_revset_options,
+            move_options,
+        } => git_branchless_move::reorder(
+            &effects,
+            &git_run_info,
+            revsets,
+            &resolve_revset_options,
+            &move_options,
+        )?,
+
         Command::Reword {
             revsets,
             resolve_revset_options,
@@ -152,6 +263,9 @@ fn command_main(ctx: CommandContext, opts: Opts) -> EyreExitOr<()> {
             force_rewrite_public_commits,
             discard,
             commit_to_fixup,
+            no_trailers,
+            reset_author,
+            committer_as_author,
         } => {
             let messages = if discard {
                 git_branchless_reword::InitialCommitMessages::Discard
@@ -167,6 +281,9 @@ fn command_main(ctx: CommandContext, opts: Opts) -> EyreExitOr<()> {
                 messages,
                 &git_run_info,
                 force_rewrite_public_commits,
+                no_trailers,
+                reset_author,
+                committer_as_author,
             )?
         }
 
@@ -174,8 +291,36 @@ fn command_main(ctx: CommandContext, opts: Opts) -> EyreExitOr<()> {
 
         Command::Snapshot { subcommand } => match subcommand {
             SnapshotSubcommand::Create => snapshot::create(&effects, &git_run_info)?,
-            SnapshotSubcommand::Restore { snapshot_oid } => {
-                snapshot::restore(&effects, &git_run_info, snapshot_oid)?
+            SnapshotSubcommand::Restore { snapshot, file } => match file {
+                Some(file) => {
+                    snapshot::restore_file(&effects, &git_run_info, snapshot.as_deref(), &file)?
+                }
+                None => snapshot::restore(&effects, &git_run_info, snapshot.as_deref())?,
+            },
+        },
+
+        Command::Split(args) => git_branchless_record::split::command_main(ctx, args)?,
+
+        Command::SquashFixups {
+            revsets,
+            resolve_revset_options,
+            move_options,
+        } => git_branchless_move::squash_fixups(
+            &effects,
+            &git_run_info,
+            revsets,
+            &resolve_revset_options,
+            &move_options,
+        )?,
+
+        Command::Stash { subcommand } => match subcommand {
+            StashSubcommand::Push { message } => stash::push(&effects, &git_run_info, message)?,
+            StashSubcommand::Pop { stash } => {
+                stash::pop(&effects, &git_run_info, stash.as_deref())?
+            }
+            StashSubcommand::List => stash::list(&effects, &git_run_info)?,
+            StashSubcommand::Drop { stash } => {
+                stash::drop_stash(&effects, &git_run_info, stash.as_deref())?
             }
         },
 
@@ -183,23 +328,71 @@ fn command_main(ctx: CommandContext, opts: Opts) -> EyreExitOr<()> {
 
         Command::Sync {
             pull,
+            remote,
+            prune_merged,
+            dry_run,
+            autostash,
             move_options,
+            max_duration,
+            resume,
             revsets,
             resolve_revset_options,
         } => sync::sync(
             &effects,
             &git_run_info,
             pull,
+            remote,
+            prune_merged,
+            dry_run,
+            autostash,
             &move_options,
+            max_duration.map(|max_duration| max_duration.0),
+            resume,
             revsets,
             &resolve_revset_options,
         )?,
 
         Command::Test(args) => git_branchless_test::command_main(ctx, args)?,
 
-        Command::Undo { interactive, yes } => {
-            git_branchless_undo::undo(&effects, &git_run_info, interactive, yes)?
-        }
+        Command::Top { merge, force } => git_branchless_navigation::traverse_commits(
+            &effects,
+            &git_run_info,
+            git_branchless_navigation::Command::Next,
+            None,
+            &TraverseCommitsOptions {
+                num_commits: None,
+                all_the_way: true,
+                move_by_branches: false,
+                sibling: false,
+                skip_obsolete: false,
+                oldest: false,
+                newest: false,
+                interactive: true,
+                merge,
+                force,
+            },
+        )?,
+
+        Command::Uncommit {
+            revset,
+            resolve_revset_options,
+            move_options,
+            staged,
+        } => uncommit::uncommit(
+            &effects,
+            &git_run_info,
+            revset,
+            &resolve_revset_options,
+            &move_options,
+            staged,
+        )?,
+
+        Command::Undo {
+            interactive,
+            yes,
+            only,
+            force,
+        } => git_branchless_undo::undo(&effects, &git_run_info, interactive, yes, only, force)?,
 
         Command::Unhide {
             revsets,
@@ -207,6 +400,11 @@ fn command_main(ctx: CommandContext, opts: Opts) -> EyreExitOr<()> {
             recursive,
         } => hide::unhide(&effects, revsets, &resolve_revset_options, recursive)?,
 
+        Command::Unpin {
+            revsets,
+            resolve_revset_options,
+        } => pin::unpin(&effects, revsets, &resolve_revset_options)?,
+
         Command::Wrap {
             git_executable: explicit_git_executable,
             command: WrappedCommand::WrappedCommand(args),
@@ -225,6 +423,60 @@ fn command_main(ctx: CommandContext, opts: Opts) -> EyreExitOr<()> {
     Ok(exit_code)
 }
 
+/// Whether the command may rewrite commits or write to the event log, and so
+/// should make sure that git-branchless can store its state before starting.
+/// Hooks are excluded because Git runs them at every step of many operations,
+/// and `wrap` because the hooks of the wrapped command do the actual writing.
+fn writes_repository_state(command: &Command) -> bool {
+    match command {
+        Command::Absorb(_)
+        | Command::Amend { .. }
+        | Command::Bottom { .. }
+        | Command::CheckoutPr(_)
+        | Command::Edit(_)
+        | Command::Gc
+        | Command::Hide { .. }
+        | Command::Init(_)
+        | Command::Move { .. }
+        | Command::Next { .. }
+        | Command::Pin { .. }
+        | Command::Prev { .. }
+        | Command::Record(_)
+        | Command::Reorder { .. }
+        | Command::Repair { .. }
+        | Command::Restack { .. }
+        | Command::Reword { .. }
+        | Command::Snapshot { .. }
+        | Command::Split(_)
+        | Command::SquashFixups { .. }
+        | Command::Stash { .. }
+        | Command::Submit(_)
+        | Command::Switch { .. }
+        | Command::Sync { .. }
+        | Command::Test(_)
+        | Command::Top { .. }
+        | Command::Uncommit { .. }
+        | Command::Undo { .. }
+        | Command::Unhide { .. }
+        | Command::Unpin { .. } => true,
+
+        Command::EventLog { subcommand } => match subcommand {
+            EventLogSubcommand::Export { .. } => false,
+            EventLogSubcommand::Import { .. } => true,
+        },
+
+        Command::BugReport
+        | Command::CompleteRevset { .. }
+        | Command::Difftool(_)
+        | Command::Hook(_)
+        | Command::InstallManPages(_)
+        | Command::Journal { .. }
+        | Command::Query { .. }
+        | Command::Smartlog(_)
+        | Command::Wrap { .. } => false,
+    }
+}
+
 /// Execute the main process and exit with the appropriate exit code.
 pub fn main() {
     // Install panic handler.
@@ -0,0 +1,121 @@
+//! Pin commits so that `git sync` and `git restack` leave their stacks in
+//! place.
+
+use std::fmt::Write;
+
+use git_branchless_opts::{ResolveRevsetOptions, Revset};
+use git_branchless_revset::resolve_commits;
+use lib::core::dag::{union_all, Dag};
+use lib::core::effects::Effects;
+use lib::core::eventlog::{EventLogDb, EventReplayer};
+use lib::core::pin::PinDb;
+use lib::core::repo_ext::RepoExt;
+use lib::git::{Commit, Repo};
+use lib::try_exit_code;
+use lib::util::{ExitCode, EyreExitOr};
+use tracing::instrument;
+
+fn resolve_commits_to_pin<'repo>(
+    effects: &Effects,
+    repo: &'repo Repo,
+    revsets: Vec<Revset>,
+    resolve_revset_options: &ResolveRevsetOptions,
+) -> EyreExitOr<Vec<Commit<'repo>>> {
+    let references_snapshot = repo.get_references_snapshot()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let mut dag = Dag::open_and_sync(
+        effects,
+        repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+
+    let commit_sets =
+        match resolve_commits(effects, repo, &mut dag, &revsets, resolve_revset_options) {
+            Ok(commit_sets) => commit_sets,
+            Err(err) => {
+                err.describe(effects)?;
+                return Ok(Err(ExitCode(1)));
+            }
+        };
+    let commits = dag
+        .sort(&union_all(&commit_sets))?
+        .into_iter()
+        .map(|commit_oid| repo.find_commit_or_fail(commit_oid))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Ok(commits))
+}
+
+/// Pin the commits provided on the command-line.
+#[instrument]
+pub fn pin(
+    effects: &Effects,
+    revsets: Vec<Revset>,
+    resolve_revset_options: &ResolveRevsetOptions,
+) -> EyreExitOr<()> {
+    let repo = Repo::from_current_dir()?;
+    let commits = try_exit_code!(resolve_commits_to_pin(
+        effects,
+        &repo,
+        revsets,
+        resolve_revset_options
+    )?);
+
+    let conn = repo.get_db_conn()?;
+    let pin_db = PinDb::new(&conn)?;
+    let glyphs = effects.get_glyphs();
+    for commit in commits {
+        let is_newly_pinned = pin_db.pin(commit.get_oid())?;
+        writeln!(
+            effects.get_output_stream(),
+            "Pinned commit: {}",
+            glyphs.render(commit.friendly_describe(glyphs)?)?,
+        )?;
+        if !is_newly_pinned {
+            writeln!(
+                effects.get_output_stream(),
+                "(It was already pinned, so this operation had no effect.)"
+            )?;
+        }
+    }
+    Ok(Ok(()))
+}
+
+/// Unpin the commits provided on the command-line.
+#[instrument]
+pub fn unpin(
+    effects: &Effects,
+    revsets: Vec<Revset>,
+    resolve_revset_options: &ResolveRevsetOptions,
+) -> EyreExitOr<()> {
+    let repo = Repo::from_current_dir()?;
+    let commits = try_exit_code!(resolve_commits_to_pin(
+        effects,
+        &repo,
+        revsets,
+        resolve_revset_options
+    )?);
+
+    let conn = repo.get_db_conn()?;
+    let pin_db = PinDb::new(&conn)?;
+    let glyphs = effects.get_glyphs();
+    for commit in commits {
+        let was_pinned = pin_db.unpin(commit.get_oid())?;
+        writeln!(
+            effects.get_output_stream(),
+            "Unpinned commit: {}",
+            glyphs.render(commit.friendly_describe(glyphs)?)?,
+        )?;
+        if !was_pinned {
+            writeln!(
+                effects.get_output_stream(),
+                "(It was not pinned, so this operation had no effect.)"
+            )?;
+        }
+    }
+    Ok(Ok(()))
+}
@@ -73,9 +73,10 @@ use lib::core::config::get_restack_preserve_timestamps;
 use lib::core::dag::{union_all, CommitSet, Dag};
 use lib::core::effects::Effects;
 use lib::core::eventlog::{EventCursor, EventLogDb, EventReplayer};
+use lib::core::pin::PinDb;
 use lib::core::rewrite::{
     execute_rebase_plan, find_abandoned_children, find_rewrite_target, move_branches,
-    BuildRebasePlanOptions, ExecuteRebasePlanOptions, ExecuteRebasePlanResult,
+    BuildRebasePlanOptions, EmptyCommitsMode, ExecuteRebasePlanOptions, ExecuteRebasePlanResult,
     MergeConflictRemediation, RebasePlanBuilder, RebasePlanPermissions, RepoPool, RepoResource,
 };
 use lib::git::{GitRunInfo, NonZeroOid, Repo};
@@ -108,15 +109,36 @@ fn restack_commits(
         dest_oid: NonZeroOid,
         abandoned_child_oids: Vec<NonZeroOid>,
     }
+    let conn = repo.get_db_conn()?;
+    let pin_db = PinDb::new(&conn)?;
     let rebases: Vec<RebaseInfo> = {
         let mut result = Vec::new();
         for original_commit_oid in commits {
             let abandoned_children =
                 find_abandoned_children(dag, event_replayer, event_cursor, original_commit_oid)?;
             if let Some((rewritten_oid, abandoned_child_oids)) = abandoned_children {
+                // Leave any stacks containing pinned commits where they are.
+                let mut unpinned_child_oids = Vec::new();
+                for child_oid in abandoned_child_oids {
+                    let stack = dag.filter_visible_commits(
+                        dag.query_descendants(CommitSet::from(child_oid))?,
+                    )?;
+                    if dag.set_is_empty(&pin_db.query_pinned_commits(dag, &stack)?)? {
+                        unpinned_child_oids.push(child_oid);
+                    } else {
+                        let child_commit = repo.find_commit_or_fail(child_oid)?;
+                        writeln!(
+                            effects.get_output_stream(),
+                            "Not restacking pinned stack at {}",
+                            effects
+                                .get_glyphs()
+                                .render(child_commit.friendly_describe(effects.get_glyphs())?)?
+                        )?;
+                    }
+                }
                 result.push(RebaseInfo {
                     dest_oid: rewritten_oid,
-                    abandoned_child_oids,
+                    abandoned_child_oids: unpinned_child_oids,
                 });
             }
         }
@@ -312,6 +334,9 @@ pub fn restack(
         resolve_merge_conflicts,
         dump_rebase_constraints,
         dump_rebase_plan,
+        preserve_timestamps,
+        reset_author,
+        committer_as_author,
     } = *move_options;
     let build_options = BuildRebasePlanOptions {
         force_rewrite_public_commits,
@@ -322,15 +347,19 @@ pub fn restack(
     let execute_options = ExecuteRebasePlanOptions {
         now,
         event_tx_id,
-        preserve_timestamps: get_restack_preserve_timestamps(&repo)?,
+        preserve_timestamps: preserve_timestamps || get_restack_preserve_timestamps(&repo)?,
         force_in_memory,
         force_on_disk,
         resolve_merge_conflicts,
+        reset_author,
+        committer_as_author,
+        signature_commit_oids: None,
         check_out_commit_options: CheckOutCommitOptions {
             additional_args: Default::default(),
             reset: false,
             render_smartlog: false,
         },
+        empty_commits: EmptyCommitsMode::Drop,
     };
     let pool = ThreadPoolBuilder::new().build()?;
     let repo_pool = RepoResource::new_pool(&repo)?;
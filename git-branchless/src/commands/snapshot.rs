@@ -2,6 +2,8 @@
 //! testing and debugging.
 
 use std::fmt::Write;
+use std::path::Path;
+use std::str::FromStr;
 use std::time::SystemTime;
 
 use cursive_core::theme::BaseColor;
@@ -9,7 +11,7 @@ use cursive_core::utils::markup::StyledString;
 use eyre::Context;
 use lib::core::check_out::{create_snapshot, restore_snapshot};
 use lib::core::effects::Effects;
-use lib::core::eventlog::EventLogDb;
+use lib::core::eventlog::{Event, EventLogDb, EventReplayer};
 use lib::git::{GitRunInfo, GitRunResult, NonZeroOid, Repo, WorkingCopySnapshot};
 use lib::util::{ExitCode, EyreExitOr};
 
@@ -54,27 +56,166 @@ pub fn create(effects: &Effects, git_run_info: &GitRunInfo) -> EyreExitOr<()> {
     }
 }
 
+/// Find the snapshot identified by `snapshot`, which may be either a snapshot
+/// commit hash or the ID of a snapshot event in the event log. If not
+/// provided, the most recent snapshot in the event log is used.
+fn resolve_snapshot<'repo>(
+    effects: &Effects,
+    repo: &'repo Repo,
+    event_log_db: &EventLogDb,
+    snapshot: Option<&str>,
+) -> eyre::Result<Result<WorkingCopySnapshot<'repo>, String>> {
+    // Event IDs are checked first, since a short numeric string would also
+    // parse as an (abbreviated) OID.
+    let event_id: Option<isize> = snapshot.and_then(|snapshot| snapshot.parse().ok());
+    let snapshot_oid = match (snapshot, event_id) {
+        (Some(snapshot), None) => match NonZeroOid::from_str(snapshot) {
+            Ok(snapshot_oid) => snapshot_oid,
+            Err(_) => {
+                return Ok(Err(format!(
+                    "Not a snapshot commit hash or event ID: {snapshot}"
+                )))
+            }
+        },
+
+        (_, Some(_)) | (None, None) => {
+            let event_replayer = EventReplayer::from_event_log_db(effects, repo, event_log_db)?;
+            let events = event_replayer.get_events_since_cursor(event_replayer.make_cursor(0));
+            let event = match event_id {
+                Some(event_id) => match usize::try_from(event_id - 1)
+                    .ok()
+                    .and_then(|index| events.get(index))
+                {
+                    Some(event) => event,
+                    None => return Ok(Err(format!("No event with ID: {event_id}"))),
+                },
+                None => match events
+                    .iter()
+                    .rev()
+                    .find(|event| matches!(event, Event::WorkingCopySnapshot { .. }))
+                {
+                    Some(event) => event,
+                    None => return Ok(Err("No snapshots in the event log".to_string())),
+                },
+            };
+            match event {
+                Event::WorkingCopySnapshot { commit_oid, .. } => *commit_oid,
+                _ => {
+                    return Ok(Err(format!(
+                        "Not a snapshot event: {}",
+                        snapshot.unwrap_or_default()
+                    )))
+                }
+            }
+        }
+    };
+
+    let base_commit = repo.find_commit_or_fail(snapshot_oid)?;
+    match WorkingCopySnapshot::try_from_base_commit(repo, &base_commit)? {
+        Some(snapshot) => Ok(Ok(snapshot)),
+        None => Ok(Err(format!("Not a snapshot commit: {snapshot_oid}"))),
+    }
+}
+
 pub fn restore(
     effects: &Effects,
     git_run_info: &GitRunInfo,
-    snapshot_oid: NonZeroOid,
+    snapshot: Option<&str>,
 ) -> EyreExitOr<()> {
     let repo = Repo::from_dir(&git_run_info.working_directory)?;
     let conn = repo.get_db_conn()?;
     let event_log_db = EventLogDb::new(&conn)?;
     let event_tx_id = event_log_db.make_transaction_id(SystemTime::now(), "snapshot restore")?;
 
-    let base_commit = repo.find_commit_or_fail(snapshot_oid)?;
-    let snapshot = match WorkingCopySnapshot::try_from_base_commit(&repo, &base_commit)? {
-        Some(snapshot) => snapshot,
+    let snapshot = match resolve_snapshot(effects, &repo, &event_log_db, snapshot)? {
+        Ok(snapshot) => snapshot,
+        Err(message) => {
+            writeln!(effects.get_error_stream(), "{message}")?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+
+    restore_snapshot(effects, git_run_info, &repo, event_tx_id, &snapshot)
+}
+
+/// Restore the contents of a single file from a snapshot, without touching the
+/// rest of the working copy or the index.
+pub fn restore_file(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    snapshot: Option<&str>,
+    file: &Path,
+) -> EyreExitOr<()> {
+    let repo = Repo::from_dir(&git_run_info.working_directory)?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+
+    let snapshot = match resolve_snapshot(effects, &repo, &event_log_db, snapshot)? {
+        Ok(snapshot) => snapshot,
+        Err(message) => {
+            writeln!(effects.get_error_stream(), "{message}")?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+
+    let working_copy_path = match repo.get_working_copy_path() {
+        Some(working_copy_path) => working_copy_path,
+        None => eyre::bail!("Cannot restore files in a bare repository"),
+    };
+    let file_path = git_run_info.working_directory.join(file);
+    let repo_relative_path = match file_path.strip_prefix(&working_copy_path) {
+        Ok(path) => path.to_owned(),
+        Err(_) => {
+            writeln!(
+                effects.get_error_stream(),
+                "Path is outside of the repository: {}",
+                file.display()
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+
+    // The unstaged changes commit contains the working copy contents of all
+    // tracked files at the time of the snapshot. Untracked files are only
+    // available if they were included in the snapshot.
+    let unstaged_tree = snapshot.commit_unstaged.get_tree()?;
+    let untracked_tree = match &snapshot.commit_untracked {
+        Some(commit_untracked) => Some(commit_untracked.get_tree()?),
+        None => None,
+    };
+    let mut blob_oid = unstaged_tree
+        .get_path(&repo_relative_path)?
+        .map(|tree_entry| tree_entry.get_oid());
+    if blob_oid.is_none() {
+        if let Some(untracked_tree) = &untracked_tree {
+            blob_oid = untracked_tree
+                .get_path(&repo_relative_path)?
+                .map(|tree_entry| tree_entry.get_oid());
+        }
+    }
+    let blob = match blob_oid {
+        Some(blob_oid) => repo.find_blob_or_fail(blob_oid)?,
         None => {
             writeln!(
                 effects.get_error_stream(),
-                "Not a snapshot commit: {snapshot_oid}"
+                "File {} is not present in snapshot {}",
+                repo_relative_path.display(),
+                snapshot.base_commit.get_oid()
             )?;
             return Ok(Err(ExitCode(1)));
         }
     };
 
-    restore_snapshot(effects, git_run_info, &repo, event_tx_id, &snapshot)
+    writeln!(
+        effects.get_error_stream(),
+        "branchless: restoring {} from snapshot",
+        repo_relative_path.display()
+    )?;
+    if let Some(parent) = file_path.parent() {
+        std::fs::create_dir_all(parent)
+            .wrap_err_with(|| format!("Creating directory {parent:?}"))?;
+    }
+    std::fs::write(&file_path, blob.get_content())
+        .wrap_err_with(|| format!("Restoring file {file_path:?}"))?;
+    Ok(Ok(()))
 }
@@ -0,0 +1,224 @@
+//! Shelve working copy changes and restore them later, like `git stash`.
+//!
+//! Stashes are stored as working copy snapshots, and each operation is
+//! recorded in the event log, so that it can be inspected with `git branchless
+//! journal` and reverted with `git undo`.
+
+use std::fmt::Write;
+use std::time::SystemTime;
+
+use cursive_core::theme::BaseColor;
+use cursive_core::utils::markup::StyledString;
+use itertools::Itertools;
+use lib::core::check_out::create_snapshot;
+use lib::core::effects::Effects;
+use lib::core::eventlog::EventLogDb;
+use lib::core::stash::{apply_stash, push_stash, ApplyStashError, StashDb};
+use lib::git::{GitRunInfo, Repo, WorkingCopyChangesType, WorkingCopySnapshot};
+use lib::try_exit_code;
+use lib::util::{ExitCode, EyreExitOr};
+
+/// Parse a stash selector like `0` or `stash@{0}` into an index into the stash
+/// list. Defaults to the most recent stash.
+fn parse_stash_index(stash: Option<&str>) -> Result<usize, String> {
+    let stash = match stash {
+        Some(stash) => stash,
+        None => return Ok(0),
+    };
+    let index = stash
+        .strip_prefix("stash@{")
+        .and_then(|stash| stash.strip_suffix('}'))
+        .unwrap_or(stash);
+    index
+        .parse()
+        .map_err(|_| format!("Not a valid stash: {stash}"))
+}
+
+/// Stash the working copy changes and reset the working copy to `HEAD`.
+pub fn push(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    message: Option<String>,
+) -> EyreExitOr<()> {
+    let now = SystemTime::now();
+    let repo = Repo::from_dir(&git_run_info.working_directory)?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_tx_id = event_log_db.make_transaction_id(now, "stash push")?;
+
+    let stash_entry = match try_exit_code!(push_stash(
+        effects,
+        git_run_info,
+        &repo,
+        &event_log_db,
+        event_tx_id,
+        now,
+        message
+    )?) {
+        Some(stash_entry) => stash_entry,
+        None => {
+            writeln!(effects.get_output_stream(), "No local changes to stash.")?;
+            return Ok(Ok(()));
+        }
+    };
+
+    writeln!(
+        effects.get_output_stream(),
+        "Saved working copy changes to stash@{{0}}: {}",
+        stash_entry.message
+    )?;
+    Ok(Ok(()))
+}
+
+/// List the stashes, most recent first.
+pub fn list(effects: &Effects, git_run_info: &GitRunInfo) -> EyreExitOr<()> {
+    let repo = Repo::from_dir(&git_run_info.working_directory)?;
+    let conn = repo.get_db_conn()?;
+    let stash_db = StashDb::new(&conn)?;
+    for (index, stash_entry) in stash_db.list()?.into_iter().enumerate() {
+        writeln!(
+            effects.get_output_stream(),
+            "stash@{{{index}}}: {}",
+            stash_entry.message
+        )?;
+    }
+    Ok(Ok(()))
+}
+
+/// Restore a stash into the working copy and remove it from the stash list.
+pub fn pop(effects: &Effects, git_run_info: &GitRunInfo, stash: Option<&str>) -> EyreExitOr<()> {
+    let now = SystemTime::now();
+    let repo = Repo::from_dir(&git_run_info.working_directory)?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let stash_db = StashDb::new(&conn)?;
+
+    let index = match parse_stash_index(stash) {
+        Ok(index) => index,
+        Err(message) => {
+            writeln!(effects.get_output_stream(), "{message}")?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+    let stash_entry = match stash_db.list()?.into_iter().nth(index) {
+        Some(stash_entry) => stash_entry,
+        None => {
+            writeln!(
+                effects.get_output_stream(),
+                "No stash found: stash@{{{index}}}"
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+
+    // Snapshot the current working copy first, so that the restore can be
+    // undone with `git undo`.
+    let event_tx_id = event_log_db.make_transaction_id(now, "stash pop")?;
+    let current_snapshot =
+        create_snapshot(effects, git_run_info, &repo, &event_log_db, event_tx_id)?;
+    if current_snapshot.get_working_copy_changes_type()? != WorkingCopyChangesType::None {
+        writeln!(
+            effects.get_output_stream(),
+            "Cannot restore a stash, because there are uncommitted changes. Commit or stash them and try again."
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+
+    let base_commit = repo.find_commit_or_fail(stash_entry.snapshot_oid)?;
+    let snapshot = match WorkingCopySnapshot::try_from_base_commit(&repo, &base_commit)? {
+        Some(snapshot) => snapshot,
+        None => eyre::bail!(
+            "Stashed commit is not a working copy snapshot: {}",
+            stash_entry.snapshot_oid
+        ),
+    };
+
+    match apply_stash(effects, git_run_info, &repo, event_tx_id, &snapshot)? {
+        Ok(()) => {}
+        Err(ApplyStashError::UnbornBranch) => {
+            writeln!(
+                effects.get_output_stream(),
+                "Cannot restore stash@{{{index}}} onto a different commit, because either it or HEAD is on an unborn branch."
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+        Err(ApplyStashError::MergeConflict { conflicting_paths }) => {
+            writeln!(
+                effects.get_output_stream(),
+                "{}",
+                effects.get_glyphs().render(StyledString::styled(
+                    format!("Cannot restore stash@{{{index}}}, because it conflicts with HEAD in these paths:"),
+                    BaseColor::Red.light()
+                ))?
+            )?;
+            for path in conflicting_paths.into_iter().sorted() {
+                writeln!(effects.get_output_stream(), "  {}", path.display())?;
+            }
+            writeln!(
+                effects.get_output_stream(),
+                "The stash was kept. Check out {} to restore it there.",
+                head_commit_description(effects, &snapshot)?
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+        Err(ApplyStashError::Failed { exit_code }) => return Ok(Err(exit_code)),
+    }
+
+    stash_db.remove(index)?;
+    writeln!(
+        effects.get_output_stream(),
+        "Restored stash@{{{index}}}: {}",
+        stash_entry.message
+    )?;
+    Ok(Ok(()))
+}
+
+fn head_commit_description(
+    effects: &Effects,
+    snapshot: &WorkingCopySnapshot,
+) -> eyre::Result<String> {
+    let description = match &snapshot.head_commit {
+        Some(head_commit) => effects
+            .get_glyphs()
+            .render(head_commit.friendly_describe_oid(effects.get_glyphs())?)?,
+        None => "the unborn branch".to_string(),
+    };
+    Ok(description)
+}
+
+/// Remove a stash from the stash list without restoring it.
+pub fn drop_stash(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    stash: Option<&str>,
+) -> EyreExitOr<()> {
+    let repo = Repo::from_dir(&git_run_info.working_directory)?;
+    let conn = repo.get_db_conn()?;
+    let stash_db = StashDb::new(&conn)?;
+
+    let index = match parse_stash_index(stash) {
+        Ok(index) => index,
+        Err(message) => {
+            writeln!(effects.get_output_stream(), "{message}")?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+    match stash_db.remove(index)? {
+        Some(stash_entry) => {
+            writeln!(
+                effects.get_output_stream(),
+                "Dropped stash@{{{index}}}: {} (restore it with: git branchless snapshot restore {})",
+                stash_entry.message,
+                stash_entry.snapshot_oid
+            )?;
+            Ok(Ok(()))
+        }
+        None => {
+            writeln!(
+                effects.get_output_stream(),
+                "No stash found: stash@{{{index}}}"
+            )?;
+            Ok(Err(ExitCode(1)))
+        }
+    }
+}
@@ -2,38 +2,49 @@
 
 use cursive_core::theme::BaseColor;
 use lib::try_exit_code;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Write;
-use std::time::SystemTime;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
 
+use bstr::BString;
+use eyre::Context;
 use itertools::Itertools;
 use lib::core::check_out::CheckOutCommitOptions;
-use lib::core::repo_ext::RepoExt;
+use lib::core::repo_ext::{RepoExt, RepoReferencesSnapshot};
 use lib::util::{ExitCode, EyreExitOr};
+use rayon::prelude::*;
 use rayon::{ThreadPool, ThreadPoolBuilder};
 
 use git_branchless_opts::{MoveOptions, ResolveRevsetOptions, Revset};
 use git_branchless_revset::{check_revset_syntax, resolve_commits};
-use lib::core::config::get_restack_preserve_timestamps;
+use lib::core::config::{
+    get_restack_autostash, get_restack_preserve_timestamps, get_sync_prune_merged, get_sync_remote,
+    get_sync_strategy, SyncStrategy,
+};
 use lib::core::dag::{sorted_commit_set, union_all, CommitSet, Dag};
 use lib::core::effects::{Effects, OperationType, WithProgress};
-use lib::core::eventlog::{EventLogDb, EventReplayer};
+use lib::core::eventlog::{Event, EventLogDb, EventReplayer};
 use lib::core::formatting::{Pluralize, StyledStringBuilder};
+use lib::core::gc::mark_commit_reachable;
+use lib::core::pin::PinDb;
 use lib::core::rewrite::{
-    execute_rebase_plan, BuildRebasePlanError, BuildRebasePlanOptions, ExecuteRebasePlanOptions,
-    ExecuteRebasePlanResult, FailedMergeInfo, RebasePlan, RebasePlanBuilder, RebasePlanPermissions,
-    RepoPool, RepoResource,
+    dry_run_rebase_plan, execute_rebase_plan, execute_rebase_plan_with_in_memory_result,
+    rebase_plan_in_memory, BuildRebasePlanError, BuildRebasePlanOptions, EmptyCommitsMode,
+    ExecuteRebasePlanOptions, ExecuteRebasePlanResult, FailedMergeInfo, InMemoryRebaseResult,
+    RebasePlan, RebasePlanBuilder, RebasePlanPermissions, RepoPool, RepoResource,
 };
+use lib::core::stash::with_autostash;
 use lib::core::task::ResourcePool;
 use lib::git::{
-    CategorizedReferenceName, Commit, GitRunInfo, NonZeroOid, Repo, ResolvedReferenceInfo,
+    Branch, BranchType, CategorizedReferenceName, Commit, ConfigRead, CreateCommitFastError,
+    GitRunInfo, GitRunOpts, MaybeZeroOid, NonZeroOid, PatchId, ReferenceName, Repo,
+    ResolvedReferenceInfo,
 };
 
 fn get_stack_roots(dag: &Dag, commit_sets: Vec<CommitSet>) -> eyre::Result<CommitSet> {
     let draft_commits = dag.query_draft_commits()?;
-
-    // FIXME: if two draft roots are ancestors of a single commit (due to a
-    // merge commit), then the entire unit should be treated as one stack and
-    // moved together, rather than attempting two separate rebases.
     let draft_roots = dag.query_roots(draft_commits.clone())?;
 
     if commit_sets.is_empty() {
@@ -45,16 +56,169 @@ fn get_stack_roots(dag: &Dag, commit_sets: Vec<CommitSet>) -> eyre::Result<Commi
         .map_err(|err| eyre::eyre!("Could not query DAG for stack roots: {err}"))
 }
 
+fn describe_main_branch(
+    effects: &Effects,
+    main_branch_reference_name: &ReferenceName,
+) -> eyre::Result<String> {
+    effects.get_glyphs().render(
+        StyledStringBuilder::new()
+            .append_styled(
+                CategorizedReferenceName::new(main_branch_reference_name).friendly_describe(),
+                BaseColor::Green.dark(),
+            )
+            .build(),
+    )
+}
+
+/// Split the given stack roots into the ones whose stacks can be moved and the
+/// ones whose stacks contain a pinned commit, which should be left in place.
+fn partition_pinned_stacks<'repo>(
+    repo: &Repo,
+    dag: &Dag,
+    root_commits: Vec<Commit<'repo>>,
+) -> eyre::Result<(Vec<Commit<'repo>>, Vec<Commit<'repo>>)> {
+    let conn = repo.get_db_conn()?;
+    let pin_db = PinDb::new(&conn)?;
+    let mut unpinned_root_commits = Vec::new();
+    let mut pinned_root_commits = Vec::new();
+    for root_commit in root_commits {
+        let stack = dag.filter_visible_commits(
+            dag.query_descendants(CommitSet::from(root_commit.get_oid()))?,
+        )?;
+        if dag.set_is_empty(&pin_db.query_pinned_commits(dag, &stack)?)? {
+            unpinned_root_commits.push(root_commit);
+        } else {
+            pinned_root_commits.push(root_commit);
+        }
+    }
+    Ok((unpinned_root_commits, pinned_root_commits))
+}
+
+/// Group the stacks whose roots are ancestors of a common commit (due to a
+/// merge commit), since they have to be moved together in a single rebase.
+/// Otherwise, each rebase would rewrite the shared descendants separately.
+fn group_stack_roots<'repo>(
+    dag: &Dag,
+    root_commits: Vec<Commit<'repo>>,
+) -> eyre::Result<Vec<Vec<Commit<'repo>>>> {
+    let mut groups: Vec<(CommitSet, Vec<Commit<'repo>>)> = Vec::new();
+    for root_commit in root_commits {
+        let descendants = dag.query_descendants(CommitSet::from(root_commit.get_oid()))?;
+        let mut overlapping_group_indices = Vec::new();
+        for (i, (group_descendants, _group)) in groups.iter().enumerate() {
+            if !dag.set_is_empty(&group_descendants.intersection(&descendants))? {
+                overlapping_group_indices.push(i);
+            }
+        }
+
+        match overlapping_group_indices.split_first() {
+            None => groups.push((descendants, vec![root_commit])),
+            Some((first_index, other_indices)) => {
+                // Merge the other overlapping groups into the first one,
+                // removing them from the back so that the indices stay valid.
+                for other_index in other_indices.iter().rev() {
+                    let (other_descendants, other_group) = groups.remove(*other_index);
+                    let (group_descendants, group) = &mut groups[*first_index];
+                    *group_descendants = group_descendants.union(&other_descendants);
+                    group.extend(other_group);
+                }
+                let (group_descendants, group) = &mut groups[*first_index];
+                *group_descendants = group_descendants.union(&descendants);
+                group.push(root_commit);
+            }
+        }
+    }
+    Ok(groups
+        .into_iter()
+        .map(|(_descendants, group)| group)
+        .collect())
+}
+
+/// Get the path to the file which records the stacks that still need to be
+/// synced after a sync was stopped early due to `--max-duration`.
+fn get_sync_state_path(repo: &Repo) -> eyre::Result<PathBuf> {
+    Ok(repo.get_branchless_dir()?.join("sync-remaining"))
+}
+
+fn read_remaining_stack_roots(repo: &Repo) -> eyre::Result<Option<Vec<NonZeroOid>>> {
+    let path = get_sync_state_path(repo)?;
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => {
+            return Err(err).wrap_err_with(|| format!("Reading sync state from {path:?}"));
+        }
+    };
+    let root_oids = contents
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.parse::<NonZeroOid>())
+        .collect::<Result<Vec<_>, _>>()
+        .wrap_err_with(|| format!("Parsing sync state from {path:?}"))?;
+    Ok(Some(root_oids))
+}
+
+fn write_remaining_stack_roots(repo: &Repo, root_oids: &[NonZeroOid]) -> eyre::Result<()> {
+    let path = get_sync_state_path(repo)?;
+    if root_oids.is_empty() {
+        match std::fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err).wrap_err_with(|| format!("Removing sync state at {path:?}")),
+        }
+    } else {
+        let mut contents = root_oids.iter().map(|oid| oid.to_string()).join("\n");
+        contents.push('\n');
+        std::fs::write(&path, contents).wrap_err_with(|| format!("Writing sync state to {path:?}"))
+    }
+}
+
 /// Move all commit stacks on top of the main branch.
 pub fn sync(
     effects: &Effects,
     git_run_info: &GitRunInfo,
     pull: bool,
+    remote: Option<String>,
+    prune_merged: bool,
+    dry_run: bool,
+    autostash: bool,
     move_options: &MoveOptions,
+    max_duration: Option<Duration>,
+    resume: bool,
     revsets: Vec<Revset>,
     resolve_revset_options: &ResolveRevsetOptions,
 ) -> EyreExitOr<()> {
+    let deadline = match max_duration {
+        None => None,
+        Some(max_duration) => match Instant::now().checked_add(max_duration) {
+            Some(deadline) => Some(deadline),
+            None => {
+                writeln!(
+                    effects.get_output_stream(),
+                    "The duration passed to --max-duration is too long: {max_duration:?}"
+                )?;
+                return Ok(Err(ExitCode(1)));
+            }
+        },
+    };
     let repo = Repo::from_current_dir()?;
+    let revsets = if resume {
+        match read_remaining_stack_roots(&repo)? {
+            Some(root_oids) => root_oids
+                .into_iter()
+                .map(|oid| Revset(oid.to_string()))
+                .collect(),
+            None => {
+                writeln!(
+                    effects.get_output_stream(),
+                    "There is no sync in progress to continue."
+                )?;
+                return Ok(Err(ExitCode(1)));
+            }
+        }
+    } else {
+        revsets
+    };
     let conn = repo.get_db_conn()?;
     let event_log_db = EventLogDb::new(&conn)?;
     let now = SystemTime::now();
@@ -64,8 +228,29 @@ pub fn sync(
     // side-effects.
     check_revset_syntax(&repo, &revsets)?;
 
+    let remote = match remote {
+        Some(remote) => Some(remote),
+        None => get_sync_remote(&repo)?,
+    };
     if pull {
-        try_exit_code!(git_run_info.run(effects, Some(event_tx_id), &["fetch", "--all"])?);
+        let mut fetch_args = match &remote {
+            Some(remote) => vec!["fetch", remote.as_str()],
+            None => vec!["fetch", "--all"],
+        };
+        if remote.is_none()
+            && repo.get_all_remote_names()?.len() > 1
+            && repo
+                .get_readonly_config()?
+                .get::<i32, _>("fetch.parallel")?
+                .is_none()
+        {
+            // Fetch from all remotes concurrently, unless the user has
+            // configured how many remotes to fetch from at once. A value of
+            // `0` means to use a reasonable default based on the number of
+            // CPUs.
+            fetch_args.splice(0..0, ["-c", "fetch.parallel=0"]);
+        }
+        try_exit_code!(git_run_info.run(effects, Some(event_tx_id), &fetch_args)?);
     }
 
     let MoveOptions {
@@ -76,6 +261,9 @@ pub fn sync(
         resolve_merge_conflicts,
         dump_rebase_constraints,
         dump_rebase_plan,
+        preserve_timestamps,
+        reset_author,
+        committer_as_author,
     } = *move_options;
     let build_options = BuildRebasePlanOptions {
         force_rewrite_public_commits,
@@ -88,47 +276,92 @@ pub fn sync(
     let execute_options = ExecuteRebasePlanOptions {
         now,
         event_tx_id,
-        preserve_timestamps: get_restack_preserve_timestamps(&repo)?,
+        preserve_timestamps: preserve_timestamps || get_restack_preserve_timestamps(&repo)?,
         force_in_memory,
         force_on_disk,
         resolve_merge_conflicts,
+        reset_author,
+        committer_as_author,
+        signature_commit_oids: None,
         check_out_commit_options: CheckOutCommitOptions {
             additional_args: Default::default(),
             reset: false,
             render_smartlog: false,
         },
+        empty_commits: EmptyCommitsMode::Drop,
     };
     let thread_pool = ThreadPoolBuilder::new().build()?;
     let repo_pool = RepoResource::new_pool(&repo)?;
 
-    let head_info = repo.get_head_info()?;
-    if pull {
-        try_exit_code!(execute_main_branch_sync_plan(
+    if dry_run {
+        return preview_sync(
             effects,
-            git_run_info,
             &repo,
             &event_log_db,
-            &build_options,
+            build_options,
             &execute_options,
             &thread_pool,
             &repo_pool,
-            &head_info,
-        )?);
+            pull,
+            remote.as_deref(),
+            prune_merged || get_sync_prune_merged(&repo)?,
+            revsets,
+            resolve_revset_options,
+        );
     }
 
-    // The main branch might have changed since we synced with `master`, so read its information again.
-
-    execute_sync_plans(
+    with_autostash(
         effects,
         git_run_info,
         &repo,
         &event_log_db,
-        build_options,
-        &execute_options,
-        &thread_pool,
-        &repo_pool,
-        revsets,
-        resolve_revset_options,
+        autostash || get_restack_autostash(&repo)?,
+        || {
+            let head_info = repo.get_head_info()?;
+            let original_main_branch_oid = repo.get_main_branch_oid()?;
+            if pull {
+                try_exit_code!(execute_main_branch_sync_plan(
+                    effects,
+                    git_run_info,
+                    &repo,
+                    &event_log_db,
+                    &build_options,
+                    &execute_options,
+                    &thread_pool,
+                    &repo_pool,
+                    &head_info,
+                    remote.as_deref(),
+                )?);
+            }
+
+            if prune_merged || get_sync_prune_merged(&repo)? {
+                try_exit_code!(prune_merged_branches(
+                    effects,
+                    git_run_info,
+                    &repo,
+                    &event_log_db,
+                    &execute_options,
+                    original_main_branch_oid,
+                )?);
+            }
+
+            // The main branch might have changed since we synced with `master`, so read its information again.
+
+            execute_sync_plans(
+                effects,
+                git_run_info,
+                &repo,
+                &event_log_db,
+                build_options,
+                &execute_options,
+                &thread_pool,
+                &repo_pool,
+                revsets,
+                resume,
+                resolve_revset_options,
+                deadline,
+            )
+        },
     )
 }
 
@@ -142,6 +375,7 @@ fn execute_main_branch_sync_plan(
     thread_pool: &ThreadPool,
     repo_pool: &RepoPool,
     head_info: &ResolvedReferenceInfo,
+    remote: Option<&str>,
 ) -> EyreExitOr<()> {
     let event_replayer = EventReplayer::from_event_log_db(effects, repo, event_log_db)?;
     let event_cursor = event_replayer.make_default_cursor();
@@ -157,25 +391,18 @@ fn execute_main_branch_sync_plan(
     let local_main_branch = repo.get_main_branch()?;
     let local_main_branch_oid = local_main_branch.get_oid()?;
     let local_main_branch_reference_name = local_main_branch.get_reference_name()?;
-    let local_main_branch_description = effects.get_glyphs().render(
-        StyledStringBuilder::new()
-            .append_styled(
-                CategorizedReferenceName::new(&local_main_branch_reference_name)
-                    .friendly_describe(),
-                BaseColor::Green.dark(),
-            )
-            .build(),
-    )?;
+    let local_main_branch_description =
+        describe_main_branch(effects, &local_main_branch_reference_name)?;
 
-    let upstream_main_branch = match local_main_branch.get_upstream_branch()? {
+    let upstream_main_branch = match find_upstream_main_branch(
+        effects,
+        repo,
+        &local_main_branch,
+        &local_main_branch_description,
+        remote,
+    )? {
         Some(upstream_main_branch) => upstream_main_branch,
-        None => {
-            writeln!(
-                effects.get_output_stream(),
-                "{local_main_branch_description} does not track an upstream branch, so not pulling."
-            )?;
-            return Ok(Ok(()));
-        }
+        None => return Ok(Ok(())),
     };
     let upstream_main_branch_oid = match upstream_main_branch.get_oid()? {
         Some(upstream_main_branch_oid) => upstream_main_branch_oid,
@@ -237,13 +464,34 @@ fn execute_main_branch_sync_plan(
         }
 
         return Ok(Ok(()));
-    } else {
-        writeln!(
-            effects.get_output_stream(),
-            "Syncing {local_main_branch_description}"
-        )?;
     }
 
+    if get_sync_strategy(repo, local_main_branch.get_name()?)? == SyncStrategy::Merge {
+        let local_main_branch_oid = match local_main_branch_oid {
+            Some(local_main_branch_oid) => local_main_branch_oid,
+            None => return Ok(Ok(())),
+        };
+        let upstream_main_branch_reference_name = upstream_main_branch.get_reference_name()?;
+        return merge_main_branch(
+            effects,
+            git_run_info,
+            repo,
+            event_log_db,
+            execute_options,
+            head_info,
+            &local_main_branch_reference_name,
+            &local_main_branch_description,
+            local_main_branch_oid,
+            &upstream_main_branch_reference_name,
+            upstream_main_branch_oid,
+        );
+    }
+
+    writeln!(
+        effects.get_output_stream(),
+        "Syncing {local_main_branch_description}"
+    )?;
+
     let build_options = BuildRebasePlanOptions {
         // Since we're syncing the main branch, by definition, any commits on it would be public, so
         // we need to set this to `true` to get the rebase to succeed.
@@ -284,14 +532,413 @@ fn execute_main_branch_sync_plan(
         None => return Ok(Ok(())),
     };
 
-    execute_plans(
+    let upstream_main_branch_name =
+        CategorizedReferenceName::new(&upstream_main_branch.get_reference_name()?).render_suffix();
+    try_exit_code!(execute_plans(
         effects,
         git_run_info,
         repo,
         event_log_db,
         execute_options,
-        vec![(root_commit_oid, Some(rebase_plan))],
-    )
+        thread_pool,
+        repo_pool,
+        vec![(vec![root_commit_oid], Some(rebase_plan))],
+        &upstream_main_branch_name,
+        None,
+    )?);
+    Ok(Ok(()))
+}
+
+/// Find the branch that the main branch should be updated from: the branch of
+/// the same name on `remote`, if provided, or otherwise the upstream branch
+/// which the main branch tracks. If there's no such branch, prints a message
+/// and returns `None`.
+fn find_upstream_main_branch<'repo>(
+    effects: &Effects,
+    repo: &'repo Repo,
+    local_main_branch: &Branch<'repo>,
+    local_main_branch_description: &str,
+    remote: Option<&str>,
+) -> eyre::Result<Option<Branch<'repo>>> {
+    match remote {
+        Some(remote) => {
+            let remote_branch_name = format!("{remote}/{}", local_main_branch.get_name()?);
+            let upstream_main_branch = repo.find_branch(&remote_branch_name, BranchType::Remote)?;
+            if upstream_main_branch.is_none() {
+                writeln!(
+                    effects.get_output_stream(),
+                    "Remote branch {remote_branch_name} does not exist, so not pulling."
+                )?;
+            }
+            Ok(upstream_main_branch)
+        }
+        None => {
+            let upstream_main_branch = local_main_branch.get_upstream_branch()?;
+            if upstream_main_branch.is_none() {
+                writeln!(
+                    effects.get_output_stream(),
+                    "{local_main_branch_description} does not track an upstream branch, so not pulling."
+                )?;
+            }
+            Ok(upstream_main_branch)
+        }
+    }
+}
+
+/// Update the local main branch by merging its upstream branch into it, rather
+/// than rebasing the local-only commits on top of the upstream branch.
+fn merge_main_branch(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    event_log_db: &EventLogDb,
+    execute_options: &ExecuteRebasePlanOptions,
+    head_info: &ResolvedReferenceInfo,
+    local_main_branch_reference_name: &ReferenceName,
+    local_main_branch_description: &str,
+    local_main_branch_oid: NonZeroOid,
+    upstream_main_branch_reference_name: &ReferenceName,
+    upstream_main_branch_oid: NonZeroOid,
+) -> EyreExitOr<()> {
+    let upstream_main_branch_name =
+        CategorizedReferenceName::new(upstream_main_branch_reference_name).render_suffix();
+    writeln!(
+        effects.get_output_stream(),
+        "Merging {upstream_main_branch_name} into {local_main_branch_description}"
+    )?;
+
+    if head_info.reference_name.as_ref() == Some(local_main_branch_reference_name) {
+        // As with fast-forwarding, rely on the working copy when the main
+        // branch is checked out, so that the index is updated as well.
+        return git_run_info.run(
+            effects,
+            Some(execute_options.event_tx_id),
+            &[
+                "merge",
+                "--no-edit",
+                upstream_main_branch_reference_name.as_str(),
+            ],
+        );
+    }
+
+    let tree_oid = match repo.auto_merge_tree_fast(local_main_branch_oid, upstream_main_branch_oid)
+    {
+        Ok(Some(tree_oid)) => tree_oid,
+        Ok(None) => {
+            writeln!(
+                effects.get_error_stream(),
+                "{local_main_branch_description} and {upstream_main_branch_name} have no common ancestor, so they can't be merged."
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+        Err(CreateCommitFastError::MergeConflict { conflicting_paths }) => {
+            writeln!(
+                effects.get_error_stream(),
+                "There was a merge conflict, which prevented merging {upstream_main_branch_name} into {local_main_branch_description}:"
+            )?;
+            let mut conflicting_paths: Vec<_> = conflicting_paths.into_iter().collect();
+            conflicting_paths.sort();
+            for path in conflicting_paths {
+                writeln!(effects.get_error_stream(), "  {}", path.display())?;
+            }
+            writeln!(
+                effects.get_error_stream(),
+                "To resolve the conflict, check out {local_main_branch_description} and run git sync --pull again."
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    // Use `git commit-tree` so that the user's identity and commit signing
+    // settings are respected.
+    let message = format!("Merge remote-tracking branch '{upstream_main_branch_name}'");
+    let tree_oid = tree_oid.to_string();
+    let local_main_branch_oid = local_main_branch_oid.to_string();
+    let upstream_main_branch_oid = upstream_main_branch_oid.to_string();
+    let result = git_run_info.run_silent(
+        repo,
+        Some(execute_options.event_tx_id),
+        &[
+            "commit-tree",
+            tree_oid.as_str(),
+            "-p",
+            local_main_branch_oid.as_str(),
+            "-p",
+            upstream_main_branch_oid.as_str(),
+            "-m",
+            message.as_str(),
+        ],
+        GitRunOpts::default(),
+    )?;
+    let merge_commit_oid: NonZeroOid = String::from_utf8(result.stdout)?.trim().parse()?;
+    mark_commit_reachable(repo, merge_commit_oid)?;
+    event_log_db.add_events(vec![Event::CommitEvent {
+        timestamp: execute_options
+            .now
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_secs_f64(),
+        event_tx_id: execute_options.event_tx_id,
+        commit_oid: merge_commit_oid,
+    }])?;
+    repo.create_reference(
+        local_main_branch_reference_name,
+        merge_commit_oid,
+        true,
+        "sync",
+    )?;
+    Ok(Ok(()))
+}
+
+/// The branches which have landed in the main branch and would be deleted by
+/// `--prune-merged`, along with the landed commits which would be hidden.
+struct PrunePlan {
+    pruned_branches: Vec<(NonZeroOid, ReferenceName)>,
+    hidden_commits: CommitSet,
+    is_head_pruned: bool,
+}
+
+/// Find the local branches which have landed in the main branch, either because
+/// they're now ancestors of the main branch or because all of their commits'
+/// patches were applied to the main branch, and the landed commits which
+/// aren't needed by any remaining branch or `HEAD`.
+///
+/// Branches which point to `original_main_branch_oid` (the main branch commit
+/// from before any pulling) are left alone, since they were most likely just
+/// created and haven't been worked on yet.
+fn plan_prune_merged_branches(
+    effects: &Effects,
+    repo: &Repo,
+    dag: &Dag,
+    references_snapshot: &RepoReferencesSnapshot,
+    head_info: &ResolvedReferenceInfo,
+    main_branch_oid: NonZeroOid,
+    original_main_branch_oid: NonZeroOid,
+) -> eyre::Result<PrunePlan> {
+    let main_branch_reference_name = repo.get_main_branch()?.get_reference_name()?;
+
+    let mut upstream_patch_ids: HashMap<NonZeroOid, HashSet<PatchId>> = HashMap::new();
+    let mut landed_commits = CommitSet::empty();
+    let mut pruned_branches: Vec<(NonZeroOid, &ReferenceName)> = Vec::new();
+    let mut remaining_heads = CommitSet::empty();
+    for (branch_oid, reference_names) in references_snapshot.branch_oid_to_names.iter() {
+        let reference_names = reference_names
+            .iter()
+            .filter(|reference_name| *reference_name != &main_branch_reference_name)
+            .collect_vec();
+        if reference_names.is_empty() {
+            continue;
+        }
+
+        let is_landed = if *branch_oid == original_main_branch_oid {
+            false
+        } else if dag.query_is_ancestor(*branch_oid, main_branch_oid)? {
+            true
+        } else {
+            let branch_commits = dag.query_only(
+                CommitSet::from(*branch_oid),
+                CommitSet::from(main_branch_oid),
+            )?;
+            let merge_base_oids =
+                dag.query_gca_all([*branch_oid, main_branch_oid].into_iter().collect())?;
+            let mut is_landed = !dag.set_is_empty(&branch_commits)?;
+            for commit_oid in dag.commit_set_to_vec(&branch_commits)? {
+                let commit = repo.find_commit_or_fail(commit_oid)?;
+                let patch_id = match repo.get_patch_id(effects, &commit)? {
+                    Some(patch_id) => patch_id,
+                    None => {
+                        is_landed = false;
+                        break;
+                    }
+                };
+                let mut patch_ids_landed = false;
+                for merge_base_oid in dag.commit_set_to_vec(&merge_base_oids)? {
+                    let merge_base_patch_ids = match upstream_patch_ids.entry(merge_base_oid) {
+                        Entry::Occupied(entry) => entry.into_mut(),
+                        Entry::Vacant(entry) => {
+                            let path = dag.query_range(
+                                CommitSet::from(merge_base_oid),
+                                CommitSet::from(main_branch_oid),
+                            )?;
+                            let mut patch_ids = HashSet::new();
+                            for upstream_oid in dag.commit_set_to_vec(&path)? {
+                                let upstream_commit = repo.find_commit_or_fail(upstream_oid)?;
+                                if let Some(patch_id) =
+                                    repo.get_patch_id(effects, &upstream_commit)?
+                                {
+                                    patch_ids.insert(patch_id);
+                                }
+                            }
+                            entry.insert(patch_ids)
+                        }
+                    };
+                    if merge_base_patch_ids.contains(&patch_id) {
+                        patch_ids_landed = true;
+                        break;
+                    }
+                }
+                if !patch_ids_landed {
+                    is_landed = false;
+                    break;
+                }
+            }
+            if is_landed {
+                landed_commits = landed_commits.union(&branch_commits);
+            }
+            is_landed
+        };
+
+        if is_landed {
+            pruned_branches.extend(
+                reference_names
+                    .into_iter()
+                    .map(|reference_name| (*branch_oid, reference_name)),
+            );
+        } else {
+            remaining_heads = remaining_heads.union(&CommitSet::from(*branch_oid));
+        }
+    }
+    pruned_branches.sort_by_key(|(_, reference_name)| *reference_name);
+
+    let is_head_pruned = match &head_info.reference_name {
+        Some(head_reference_name) => pruned_branches
+            .iter()
+            .any(|(_, reference_name)| *reference_name == head_reference_name),
+        None => false,
+    };
+    if !is_head_pruned {
+        if let Some(head_oid) = head_info.oid {
+            remaining_heads = remaining_heads.union(&CommitSet::from(head_oid));
+        }
+    }
+
+    // Keep any landed commits which are still needed by a remaining branch,
+    // `HEAD`, or an unlanded draft commit.
+    let draft_heads = dag.query_heads(dag.query_draft_commits()?.clone())?;
+    let remaining_heads = remaining_heads.union(&draft_heads.difference(&landed_commits));
+    let hidden_commits = landed_commits.difference(&dag.query_ancestors(remaining_heads)?);
+
+    Ok(PrunePlan {
+        pruned_branches: pruned_branches
+            .into_iter()
+            .map(|(branch_oid, reference_name)| (branch_oid, reference_name.clone()))
+            .collect(),
+        hidden_commits,
+        is_head_pruned,
+    })
+}
+
+/// Delete the local branches which have landed in the main branch and hide the
+/// landed commits, as determined by [`plan_prune_merged_branches`]. If one of
+/// the deleted branches is checked out, the main branch is checked out instead.
+fn prune_merged_branches(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    event_log_db: &EventLogDb,
+    execute_options: &ExecuteRebasePlanOptions,
+    original_main_branch_oid: NonZeroOid,
+) -> EyreExitOr<()> {
+    let event_replayer = EventReplayer::from_event_log_db(effects, repo, event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let references_snapshot = repo.get_references_snapshot()?;
+    let dag = Dag::open_and_sync(
+        effects,
+        repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+    let PrunePlan {
+        pruned_branches,
+        hidden_commits,
+        is_head_pruned,
+    } = plan_prune_merged_branches(
+        effects,
+        repo,
+        &dag,
+        &references_snapshot,
+        &repo.get_head_info()?,
+        references_snapshot.main_branch_oid,
+        original_main_branch_oid,
+    )?;
+    if pruned_branches.is_empty() {
+        return Ok(Ok(()));
+    }
+
+    if is_head_pruned {
+        let main_branch_name =
+            CategorizedReferenceName::new(&repo.get_main_branch()?.get_reference_name()?)
+                .render_suffix();
+        try_exit_code!(git_run_info.run(
+            effects,
+            Some(execute_options.event_tx_id),
+            &["checkout", &main_branch_name],
+        )?);
+    }
+    let hidden_commit_oids = dag.sort(&hidden_commits)?;
+
+    let mut branch_moves = String::new();
+    let mut deleted_branch_names = Vec::new();
+    for (branch_oid, reference_name) in pruned_branches {
+        let branch_name = CategorizedReferenceName::new(&reference_name).render_suffix();
+        if let Some(mut branch) = repo.find_branch(&branch_name, BranchType::Local)? {
+            branch.delete()?;
+            writeln!(
+                branch_moves,
+                "{branch_oid} {} {}",
+                MaybeZeroOid::Zero,
+                reference_name.as_str()
+            )?;
+            deleted_branch_names.push(branch_name);
+        }
+    }
+    git_run_info.run_hook(
+        effects,
+        repo,
+        "reference-transaction",
+        execute_options.event_tx_id,
+        &["committed"],
+        Some(BString::from(branch_moves)),
+    )?;
+
+    let timestamp = execute_options
+        .now
+        .duration_since(SystemTime::UNIX_EPOCH)?
+        .as_secs_f64();
+    event_log_db.add_events(
+        hidden_commit_oids
+            .iter()
+            .map(|commit_oid| Event::ObsoleteEvent {
+                timestamp,
+                event_tx_id: execute_options.event_tx_id,
+                commit_oid: *commit_oid,
+            })
+            .collect(),
+    )?;
+
+    writeln!(
+        effects.get_output_stream(),
+        "Deleted {} which landed in the main branch: {}",
+        Pluralize {
+            determiner: None,
+            amount: deleted_branch_names.len(),
+            unit: ("branch", "branches"),
+        },
+        deleted_branch_names.join(", "),
+    )?;
+    if !hidden_commit_oids.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "Hid {}",
+            Pluralize {
+                determiner: None,
+                amount: hidden_commit_oids.len(),
+                unit: ("landed commit", "landed commits"),
+            },
+        )?;
+    }
+    Ok(Ok(()))
 }
 
 fn execute_sync_plans(
@@ -304,7 +951,9 @@ fn execute_sync_plans(
     thread_pool: &ThreadPool,
     repo_pool: &ResourcePool<RepoResource>,
     revsets: Vec<Revset>,
+    resume: bool,
     resolve_revset_options: &ResolveRevsetOptions,
+    deadline: Option<Instant>,
 ) -> EyreExitOr<()> {
     let event_replayer = EventReplayer::from_event_log_db(effects, repo, event_log_db)?;
     let event_cursor = event_replayer.make_default_cursor();
@@ -325,8 +974,36 @@ fn execute_sync_plans(
             }
         };
     let main_branch_oid = repo.get_main_branch_oid()?;
+    let is_selection = !commit_sets.is_empty();
     let root_commit_oids = get_stack_roots(&dag, commit_sets)?;
+    if is_selection && dag.set_is_empty(&root_commit_oids)? {
+        writeln!(
+            effects.get_output_stream(),
+            "The provided revsets did not select any draft commits, so there are no stacks to sync."
+        )?;
+        if resume {
+            // The remaining stacks have all been synced or hidden some other
+            // way since the sync was stopped, so there's nothing left to
+            // continue.
+            write_remaining_stack_roots(repo, &[])?;
+        }
+        return Ok(Ok(()));
+    }
     let root_commits = sorted_commit_set(repo, &dag, &root_commit_oids)?;
+
+    // Leave any stacks containing pinned commits where they are.
+    let (root_commits, pinned_root_commits) = partition_pinned_stacks(repo, &dag, root_commits)?;
+    for pinned_root_commit in pinned_root_commits {
+        writeln!(
+            effects.get_output_stream(),
+            "Not moving pinned stack at {}",
+            effects
+                .get_glyphs()
+                .render(pinned_root_commit.friendly_describe(effects.get_glyphs())?)?
+        )?;
+    }
+    let root_commit_oids: CommitSet = root_commits.iter().map(|commit| commit.get_oid()).collect();
+
     let permissions =
         match RebasePlanPermissions::verify_rewrite_set(&dag, build_options, &root_commit_oids)? {
             Ok(permissions) => permissions,
@@ -337,33 +1014,36 @@ fn execute_sync_plans(
         };
     let builder = RebasePlanBuilder::new(&dag, permissions);
 
-    let root_commit_oids = root_commits
+    let root_commit_oid_groups = group_stack_roots(&dag, root_commits)?
         .into_iter()
-        .map(|commit| commit.get_oid())
+        .map(|group| group.iter().map(|commit| commit.get_oid()).collect_vec())
         .collect_vec();
     let root_commit_and_plans = thread_pool.install(|| -> eyre::Result<_> {
-        let result = root_commit_oids
+        let result = root_commit_oid_groups
             // Don't parallelize for now, since the status updates don't render well.
             .into_iter()
             .map(
-                |root_commit_oid| -> eyre::Result<
-                    Result<(NonZeroOid, Option<RebasePlan>), BuildRebasePlanError>,
-                > {
+                |root_commit_oids| -> eyre::Result<Result<_, BuildRebasePlanError>> {
                     // Keep access to the same underlying caches by cloning the same instance of the builder.
                     let mut builder = builder.clone();
 
                     let repo = repo_pool.try_create()?;
-                    let root_commit = repo.find_commit_or_fail(root_commit_oid)?;
-
-                    let only_parent_id =
-                        root_commit.get_only_parent().map(|parent| parent.get_oid());
-                    if only_parent_id == Some(main_branch_oid) {
-                        return Ok(Ok((root_commit_oid, None)));
+                    let mut is_up_to_date = true;
+                    for root_commit_oid in root_commit_oids.iter() {
+                        let root_commit = repo.find_commit_or_fail(*root_commit_oid)?;
+                        let only_parent_id =
+                            root_commit.get_only_parent().map(|parent| parent.get_oid());
+                        if only_parent_id != Some(main_branch_oid) {
+                            is_up_to_date = false;
+                            builder.move_subtree(root_commit.get_oid(), vec![main_branch_oid])?;
+                        }
+                    }
+                    if is_up_to_date {
+                        return Ok(Ok((root_commit_oids, None)));
                     }
 
-                    builder.move_subtree(root_commit.get_oid(), vec![main_branch_oid])?;
                     let rebase_plan = builder.build(effects, thread_pool, repo_pool)?;
-                    Ok(rebase_plan.map(|rebase_plan| (root_commit_oid, rebase_plan)))
+                    Ok(rebase_plan.map(|rebase_plan| (root_commit_oids, rebase_plan)))
                 },
             )
             .collect::<eyre::Result<Vec<_>>>()?
@@ -379,14 +1059,23 @@ fn execute_sync_plans(
             return Ok(Err(ExitCode(1)));
         }
     };
-    execute_plans(
+    let main_branch_name =
+        CategorizedReferenceName::new(&repo.get_main_branch()?.get_reference_name()?)
+            .render_suffix();
+    let remaining_commit_oids = try_exit_code!(execute_plans(
         effects,
         git_run_info,
         repo,
         event_log_db,
         execute_options,
+        thread_pool,
+        repo_pool,
         root_commit_and_plans,
-    )
+        &main_branch_name,
+        deadline,
+    )?);
+    write_remaining_stack_roots(repo, &remaining_commit_oids)?;
+    Ok(Ok(()))
 }
 
 fn execute_plans(
@@ -395,40 +1084,98 @@ fn execute_plans(
     repo: &Repo,
     event_log_db: &EventLogDb,
     execute_options: &ExecuteRebasePlanOptions,
-    root_commit_and_plans: Vec<(NonZeroOid, Option<RebasePlan>)>,
-) -> EyreExitOr<()> {
-    let (success_commits, failed_merge_commits, skipped_commits) = {
+    thread_pool: &ThreadPool,
+    repo_pool: &RepoPool,
+    root_commit_and_plans: Vec<(Vec<NonZeroOid>, Option<RebasePlan>)>,
+    dest_name: &str,
+    deadline: Option<Instant>,
+) -> EyreExitOr<Vec<NonZeroOid>> {
+    // Rebase the independent stacks in memory concurrently up front, and then
+    // apply the results one at a time, since references and the working copy
+    // have to be updated serially. This isn't done when there's a deadline,
+    // since then the remaining stacks shouldn't be rebased at all.
+    let mut in_memory_results: HashMap<NonZeroOid, InMemoryRebaseResult> = if deadline.is_none()
+        && !execute_options.force_on_disk
+        && root_commit_and_plans.len() > 1
+    {
+        thread_pool.install(|| {
+                root_commit_and_plans
+                    .par_iter()
+                    .filter_map(|(root_commit_oids, rebase_plan)| {
+                        rebase_plan
+                            .as_ref()
+                            .map(|rebase_plan| (root_commit_oids[0], rebase_plan))
+                    })
+                    .map(
+                        |(root_commit_oid, rebase_plan)| -> eyre::Result<(NonZeroOid, InMemoryRebaseResult)> {
+                            let repo = repo_pool.try_create()?;
+                            let in_memory_result =
+                                rebase_plan_in_memory(effects, &repo, rebase_plan, execute_options)?;
+                            Ok((root_commit_oid, in_memory_result))
+                        },
+                    )
+                    .collect::<eyre::Result<_>>()
+            })?
+    } else {
+        HashMap::new()
+    };
+
+    let (success_commits, failed_merge_commits, skipped_commits, remaining_commit_oids) = {
         let mut success_commits: Vec<Commit> = Vec::new();
-        let mut failed_merge_commits: Vec<(Commit, FailedMergeInfo)> = Vec::new();
+        let mut failed_merge_commits: Vec<(Vec<Commit>, FailedMergeInfo)> = Vec::new();
         let mut skipped_commits: Vec<Commit> = Vec::new();
+        let mut remaining_commit_oids: Vec<NonZeroOid> = Vec::new();
 
         let (effects, progress) = effects.start_operation(OperationType::SyncCommits);
-        for (root_commit_oid, rebase_plan) in
+        for (root_commit_oids, rebase_plan) in
             root_commit_and_plans.into_iter().with_progress(progress)
         {
-            let root_commit = repo.find_commit_or_fail(root_commit_oid)?;
+            let is_past_deadline = match deadline {
+                Some(deadline) => Instant::now() >= deadline,
+                None => false,
+            };
+            if is_past_deadline {
+                remaining_commit_oids.extend(root_commit_oids);
+                continue;
+            }
+
+            let mut root_commits = Vec::new();
+            for root_commit_oid in root_commit_oids.iter() {
+                root_commits.push(repo.find_commit_or_fail(*root_commit_oid)?);
+            }
             let rebase_plan = match rebase_plan {
                 Some(rebase_plan) => rebase_plan,
                 None => {
-                    skipped_commits.push(root_commit);
+                    skipped_commits.extend(root_commits);
                     continue;
                 }
             };
 
-            let result = execute_rebase_plan(
-                &effects,
-                git_run_info,
-                repo,
-                event_log_db,
-                &rebase_plan,
-                execute_options,
-            )?;
+            let result = match in_memory_results.remove(&root_commit_oids[0]) {
+                Some(in_memory_result) => execute_rebase_plan_with_in_memory_result(
+                    &effects,
+                    git_run_info,
+                    repo,
+                    event_log_db,
+                    &rebase_plan,
+                    execute_options,
+                    in_memory_result,
+                )?,
+                None => execute_rebase_plan(
+                    &effects,
+                    git_run_info,
+                    repo,
+                    event_log_db,
+                    &rebase_plan,
+                    execute_options,
+                )?,
+            };
             match result {
                 ExecuteRebasePlanResult::Succeeded { rewritten_oids: _ } => {
-                    success_commits.push(root_commit);
+                    success_commits.extend(root_commits);
                 }
                 ExecuteRebasePlanResult::DeclinedToMerge { failed_merge_info } => {
-                    failed_merge_commits.push((root_commit, failed_merge_info));
+                    failed_merge_commits.push((root_commits, failed_merge_info));
                 }
                 ExecuteRebasePlanResult::Failed { exit_code } => {
                     return Ok(Err(exit_code));
@@ -436,7 +1183,12 @@ fn execute_plans(
             }
         }
 
-        (success_commits, failed_merge_commits, skipped_commits)
+        (
+            success_commits,
+            failed_merge_commits,
+            skipped_commits,
+            remaining_commit_oids,
+        )
     };
 
     for success_commit in success_commits {
@@ -452,10 +1204,19 @@ fn execute_plans(
         )?;
     }
 
-    for (failed_merge_commit, failed_merge_info) in failed_merge_commits {
+    for (failed_merge_commit, failed_merge_info) in
+        failed_merge_commits
+            .iter()
+            .flat_map(|(root_commits, failed_merge_info)| {
+                root_commits
+                    .iter()
+                    .map(move |root_commit| (root_commit, failed_merge_info))
+            })
+    {
         match failed_merge_info {
             FailedMergeInfo::Conflict {
                 commit_oid: _,
+                onto_oid: _,
                 conflicting_paths,
             } => {
                 writeln!(
@@ -497,5 +1258,436 @@ fn execute_plans(
         )?;
     }
 
+    if !failed_merge_commits.is_empty() {
+        // The other stacks were still synced, so give the user a queue of
+        // commands to retry the conflicting stacks one at a time.
+        writeln!(
+            effects.get_output_stream(),
+            "Skipped {} due to merge conflicts. To resolve the conflicts for each one, run:",
+            Pluralize {
+                determiner: None,
+                amount: failed_merge_commits
+                    .iter()
+                    .map(|(root_commits, _failed_merge_info)| root_commits.len())
+                    .sum(),
+                unit: ("stack", "stacks"),
+            },
+        )?;
+        for (root_commits, _failed_merge_info) in failed_merge_commits.iter() {
+            let mut sources = String::new();
+            for root_commit in root_commits {
+                write!(sources, "-s {} ", root_commit.get_short_oid()?)?;
+            }
+            writeln!(
+                effects.get_output_stream(),
+                "  git move --merge {sources}-d {dest_name}",
+            )?;
+        }
+    }
+
+    if !remaining_commit_oids.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "Stopped syncing after reaching the maximum duration ({} not yet synced).",
+            Pluralize {
+                determiner: None,
+                amount: remaining_commit_oids.len(),
+                unit: ("stack", "stacks"),
+            },
+        )?;
+        writeln!(
+            effects.get_output_stream(),
+            "To sync the remaining stacks, run: git sync --continue"
+        )?;
+    }
+
+    Ok(Ok(remaining_commit_oids))
+}
+
+/// Report what `git sync` would do with the current remote-tracking branches,
+/// without updating any branches or commits.
+fn preview_sync(
+    effects: &Effects,
+    repo: &Repo,
+    event_log_db: &EventLogDb,
+    build_options: BuildRebasePlanOptions,
+    execute_options: &ExecuteRebasePlanOptions,
+    thread_pool: &ThreadPool,
+    repo_pool: &RepoPool,
+    pull: bool,
+    remote: Option<&str>,
+    prune_merged: bool,
+    revsets: Vec<Revset>,
+    resolve_revset_options: &ResolveRevsetOptions,
+) -> EyreExitOr<()> {
+    let event_replayer = EventReplayer::from_event_log_db(effects, repo, event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let references_snapshot = repo.get_references_snapshot()?;
+    let mut dag = Dag::open_and_sync(
+        effects,
+        repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+    let in_memory_repo = repo.try_clone_in_memory()?;
+    let glyphs = effects.get_glyphs();
+
+    let original_main_branch_oid = references_snapshot.main_branch_oid;
+    let main_branch_oid = if pull {
+        try_exit_code!(preview_main_branch_sync(
+            effects,
+            repo,
+            &in_memory_repo,
+            &mut dag,
+            &build_options,
+            execute_options,
+            thread_pool,
+            repo_pool,
+            remote,
+        )?)
+    } else {
+        original_main_branch_oid
+    };
+
+    let hidden_commits = if prune_merged {
+        let PrunePlan {
+            pruned_branches,
+            hidden_commits,
+            is_head_pruned: _,
+        } = plan_prune_merged_branches(
+            effects,
+            repo,
+            &dag,
+            &references_snapshot,
+            &repo.get_head_info()?,
+            main_branch_oid,
+            original_main_branch_oid,
+        )?;
+        if !pruned_branches.is_empty() {
+            writeln!(
+                effects.get_output_stream(),
+                "Would delete {} which landed in the main branch: {}",
+                Pluralize {
+                    determiner: None,
+                    amount: pruned_branches.len(),
+                    unit: ("branch", "branches"),
+                },
+                pruned_branches
+                    .iter()
+                    .map(
+                        |(_, reference_name)| CategorizedReferenceName::new(reference_name)
+                            .render_suffix()
+                    )
+                    .join(", "),
+            )?;
+            let num_hidden_commits = dag.commit_set_to_vec(&hidden_commits)?.len();
+            if num_hidden_commits > 0 {
+                writeln!(
+                    effects.get_output_stream(),
+                    "Would hide {}",
+                    Pluralize {
+                        determiner: None,
+                        amount: num_hidden_commits,
+                        unit: ("landed commit", "landed commits"),
+                    },
+                )?;
+            }
+        }
+        hidden_commits
+    } else {
+        CommitSet::empty()
+    };
+
+    let commit_sets =
+        match resolve_commits(effects, repo, &mut dag, &revsets, resolve_revset_options) {
+            Ok(commit_sets) => commit_sets,
+            Err(err) => {
+                err.describe(effects)?;
+                return Ok(Err(ExitCode(1)));
+            }
+        };
+    let is_selection = !commit_sets.is_empty();
+    let root_commit_oids = get_stack_roots(&dag, commit_sets)?;
+    if is_selection && dag.set_is_empty(&root_commit_oids)? {
+        writeln!(
+            effects.get_output_stream(),
+            "The provided revsets did not select any draft commits, so there are no stacks to sync."
+        )?;
+    }
+    let root_commits = sorted_commit_set(repo, &dag, &root_commit_oids)?;
+    let (root_commits, pinned_root_commits) = partition_pinned_stacks(repo, &dag, root_commits)?;
+    for pinned_root_commit in pinned_root_commits {
+        writeln!(
+            effects.get_output_stream(),
+            "Would not move pinned stack at {}",
+            glyphs.render(pinned_root_commit.friendly_describe(glyphs)?)?
+        )?;
+    }
+
+    // Stacks which would be hidden entirely by pruning wouldn't be synced.
+    let mut synced_root_commits = Vec::new();
+    for root_commit in root_commits {
+        let stack = dag.filter_visible_commits(
+            dag.query_descendants(CommitSet::from(root_commit.get_oid()))?,
+        )?;
+        if !dag.set_is_empty(&stack.difference(&hidden_commits))? {
+            synced_root_commits.push(root_commit);
+        }
+    }
+    let root_commit_oids: CommitSet = synced_root_commits
+        .iter()
+        .map(|commit| commit.get_oid())
+        .collect();
+    let permissions =
+        match RebasePlanPermissions::verify_rewrite_set(&dag, build_options, &root_commit_oids)? {
+            Ok(permissions) => permissions,
+            Err(err) => {
+                err.describe(effects, repo, &dag)?;
+                return Ok(Err(ExitCode(1)));
+            }
+        };
+    let builder = RebasePlanBuilder::new(&dag, permissions);
+    for root_commits in group_stack_roots(&dag, synced_root_commits)? {
+        let mut builder = builder.clone();
+        let mut is_up_to_date = true;
+        for root_commit in root_commits.iter() {
+            if root_commit.get_only_parent_oid() != Some(main_branch_oid) {
+                is_up_to_date = false;
+                builder.move_subtree(root_commit.get_oid(), vec![main_branch_oid])?;
+            }
+        }
+        let rebase_plan = if is_up_to_date {
+            None
+        } else {
+            match builder.build(effects, thread_pool, repo_pool)? {
+                Ok(rebase_plan) => rebase_plan,
+                Err(err) => {
+                    err.describe(effects, repo, &dag)?;
+                    return Ok(Err(ExitCode(1)));
+                }
+            }
+        };
+
+        let mut root_commit_descriptions = Vec::new();
+        for root_commit in root_commits.iter() {
+            root_commit_descriptions.push(glyphs.render(root_commit.friendly_describe(glyphs)?)?);
+        }
+        let rebase_plan = match rebase_plan {
+            Some(rebase_plan) => rebase_plan,
+            None => {
+                for root_commit_description in root_commit_descriptions {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "Would not move up-to-date stack at {root_commit_description}"
+                    )?;
+                }
+                continue;
+            }
+        };
+
+        match dry_run_rebase_plan(effects, &in_memory_repo, &rebase_plan, execute_options)? {
+            Ok(_rewritten_oids) => {
+                for root_commit_description in root_commit_descriptions {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "Would sync {root_commit_description}"
+                    )?;
+                }
+            }
+            Err(failed_merge_info) => {
+                for root_commit_description in root_commit_descriptions {
+                    describe_preview_failed_merge(
+                        effects,
+                        &root_commit_description,
+                        &failed_merge_info,
+                    )?;
+                }
+            }
+        }
+    }
+
+    writeln!(
+        effects.get_output_stream(),
+        "(This was a dry-run; run without --dry-run to apply changes.)"
+    )?;
     Ok(Ok(()))
 }
+
+fn describe_preview_failed_merge(
+    effects: &Effects,
+    commit_description: &str,
+    failed_merge_info: &FailedMergeInfo,
+) -> eyre::Result<()> {
+    match failed_merge_info {
+        FailedMergeInfo::Conflict {
+            commit_oid: _,
+            onto_oid: _,
+            conflicting_paths,
+        } => {
+            writeln!(
+                effects.get_output_stream(),
+                "Would have merge conflict ({}) for {commit_description}",
+                Pluralize {
+                    determiner: None,
+                    amount: conflicting_paths.len(),
+                    unit: ("file", "files")
+                },
+            )?;
+        }
+        FailedMergeInfo::CannotRebaseMergeInMemory { commit_oid: _ } => {
+            writeln!(
+                effects.get_output_stream(),
+                "Can't rebase merge commit in-memory: {commit_description}"
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Report how the main branch would be updated from its upstream branch, and
+/// return the commit which the stacks would then be moved on top of.
+fn preview_main_branch_sync(
+    effects: &Effects,
+    repo: &Repo,
+    in_memory_repo: &Repo,
+    dag: &mut Dag,
+    build_options: &BuildRebasePlanOptions,
+    execute_options: &ExecuteRebasePlanOptions,
+    thread_pool: &ThreadPool,
+    repo_pool: &RepoPool,
+    remote: Option<&str>,
+) -> EyreExitOr<NonZeroOid> {
+    let glyphs = effects.get_glyphs();
+    let local_main_branch = repo.get_main_branch()?;
+    let local_main_branch_oid = repo.get_main_branch_oid()?;
+    let local_main_branch_description =
+        describe_main_branch(effects, &local_main_branch.get_reference_name()?)?;
+    let upstream_main_branch = match find_upstream_main_branch(
+        effects,
+        repo,
+        &local_main_branch,
+        &local_main_branch_description,
+        remote,
+    )? {
+        Some(upstream_main_branch) => upstream_main_branch,
+        None => return Ok(Ok(local_main_branch_oid)),
+    };
+    let upstream_main_branch_oid = match upstream_main_branch.get_oid()? {
+        Some(upstream_main_branch_oid) => upstream_main_branch_oid,
+        None => return Ok(Ok(local_main_branch_oid)),
+    };
+    dag.sync_from_oids(
+        effects,
+        repo,
+        CommitSet::from(upstream_main_branch_oid),
+        CommitSet::empty(),
+    )?;
+    let upstream_main_branch_commit = repo.find_commit_or_fail(upstream_main_branch_oid)?;
+    let upstream_main_branch_description =
+        glyphs.render(upstream_main_branch_commit.friendly_describe(glyphs)?)?;
+    let local_main_branch_commits = dag.query_only(
+        CommitSet::from(local_main_branch_oid),
+        CommitSet::from(upstream_main_branch_oid),
+    )?;
+    if dag.set_is_empty(&local_main_branch_commits)? {
+        if local_main_branch_oid == upstream_main_branch_oid {
+            writeln!(
+                effects.get_output_stream(),
+                "Would not update {local_main_branch_description} at {upstream_main_branch_description}"
+            )?;
+        } else {
+            writeln!(
+                effects.get_output_stream(),
+                "Would fast-forward {local_main_branch_description} to {upstream_main_branch_description}"
+            )?;
+        }
+        return Ok(Ok(upstream_main_branch_oid));
+    }
+
+    // FIXME: the stacks are previewed on top of the upstream branch, without
+    // the local-only commits on the main branch, since the rebased or merged
+    // main branch commits don't exist yet.
+    let upstream_main_branch_name =
+        CategorizedReferenceName::new(&upstream_main_branch.get_reference_name()?).render_suffix();
+    if get_sync_strategy(repo, local_main_branch.get_name()?)? == SyncStrategy::Merge {
+        match repo.auto_merge_tree_fast(local_main_branch_oid, upstream_main_branch_oid) {
+            Ok(Some(_tree_oid)) => {
+                writeln!(
+                    effects.get_output_stream(),
+                    "Would merge {upstream_main_branch_name} into {local_main_branch_description}"
+                )?;
+            }
+            Ok(None) => {
+                writeln!(
+                    effects.get_output_stream(),
+                    "{local_main_branch_description} and {upstream_main_branch_name} have no common ancestor, so they couldn't be merged."
+                )?;
+            }
+            Err(CreateCommitFastError::MergeConflict { conflicting_paths }) => {
+                writeln!(
+                    effects.get_output_stream(),
+                    "Would have merge conflict ({}) merging {upstream_main_branch_name} into {local_main_branch_description}",
+                    Pluralize {
+                        determiner: None,
+                        amount: conflicting_paths.len(),
+                        unit: ("file", "files")
+                    },
+                )?;
+            }
+            Err(err) => return Err(err.into()),
+        }
+        return Ok(Ok(upstream_main_branch_oid));
+    }
+
+    let build_options = BuildRebasePlanOptions {
+        force_rewrite_public_commits: true,
+        ..build_options.clone()
+    };
+    let permissions = match RebasePlanPermissions::verify_rewrite_set(
+        dag,
+        build_options,
+        &local_main_branch_commits,
+    )? {
+        Ok(permissions) => permissions,
+        Err(err) => {
+            err.describe(effects, repo, dag)?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+    let mut builder = RebasePlanBuilder::new(dag, permissions);
+    let local_main_branch_roots = dag.query_roots(local_main_branch_commits)?;
+    let root_commit_oid = match dag
+        .commit_set_to_vec(&local_main_branch_roots)?
+        .into_iter()
+        .exactly_one()
+    {
+        Ok(root_oid) => root_oid,
+        Err(_) => return Ok(Ok(upstream_main_branch_oid)),
+    };
+    builder.move_subtree(root_commit_oid, vec![upstream_main_branch_oid])?;
+    let rebase_plan = match builder.build(effects, thread_pool, repo_pool)? {
+        Ok(Some(rebase_plan)) => rebase_plan,
+        Ok(None) => return Ok(Ok(upstream_main_branch_oid)),
+        Err(err) => {
+            err.describe(effects, repo, dag)?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+    match dry_run_rebase_plan(effects, in_memory_repo, &rebase_plan, execute_options)? {
+        Ok(_rewritten_oids) => {
+            writeln!(
+                effects.get_output_stream(),
+                "Would sync {local_main_branch_description} onto {upstream_main_branch_name}"
+            )?;
+        }
+        Err(failed_merge_info) => {
+            describe_preview_failed_merge(
+                effects,
+                &local_main_branch_description,
+                &failed_merge_info,
+            )?;
+        }
+    }
+    Ok(Ok(upstream_main_branch_oid))
+}
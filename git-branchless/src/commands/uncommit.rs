@@ -0,0 +1,245 @@
+//! Remove a commit from history, leaving its changes in the working copy.
+//!
+//! This is the inverse of `git record`. The commit must be `HEAD` or one of its
+//! ancestors, so that the working copy already contains its changes: its
+//! descendants are rebased in memory onto its parent, and then `HEAD` is reset
+//! without touching the working copy.
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fmt::Write;
+use std::time::SystemTime;
+
+use git_branchless_opts::{MoveOptions, ResolveRevsetOptions, Revset};
+use git_branchless_revset::resolve_commits;
+use lib::core::check_out::{create_snapshot, CheckOutCommitOptions};
+use lib::core::config::get_restack_preserve_timestamps;
+use lib::core::dag::{union_all, CommitSet, Dag};
+use lib::core::effects::Effects;
+use lib::core::eventlog::{Event, EventLogDb, EventReplayer};
+use lib::core::repo_ext::RepoExt;
+use lib::core::rewrite::{
+    execute_rebase_plan, move_branches, BuildRebasePlanOptions, EmptyCommitsMode,
+    ExecuteRebasePlanOptions, ExecuteRebasePlanResult, RebasePlanBuilder, RebasePlanPermissions,
+    RepoResource,
+};
+use lib::git::{GitRunInfo, MaybeZeroOid, Repo};
+use lib::try_exit_code;
+use lib::util::{ExitCode, EyreExitOr};
+use rayon::ThreadPoolBuilder;
+use tracing::instrument;
+
+/// Remove the given commit from history and leave its changes in the working
+/// copy.
+#[instrument]
+pub fn uncommit(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    revset: Revset,
+    resolve_revset_options: &ResolveRevsetOptions,
+    move_options: &MoveOptions,
+    staged: bool,
+) -> EyreExitOr<()> {
+    let now = SystemTime::now();
+    let timestamp = now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64();
+    let repo = Repo::from_current_dir()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let references_snapshot = repo.get_references_snapshot()?;
+    let mut dag = Dag::open_and_sync(
+        effects,
+        &repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+
+    let head_oid = match repo.get_head_info()?.oid {
+        Some(head_oid) => head_oid,
+        None => {
+            writeln!(
+                effects.get_output_stream(),
+                "No commit is currently checked out. Check out a commit to uncommit and then try again."
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+
+    let commit_set = match resolve_commits(
+        effects,
+        &repo,
+        &mut dag,
+        &[revset.clone()],
+        resolve_revset_options,
+    ) {
+        Ok(commit_sets) => union_all(&commit_sets),
+        Err(err) => {
+            err.describe(effects)?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+    let commit_oid = match dag.commit_set_to_vec(&commit_set)?.as_slice() {
+        [commit_oid] => *commit_oid,
+        commit_oids => {
+            writeln!(
+                effects.get_output_stream(),
+                "Expected exactly 1 commit to uncommit, but '{}' evaluated to {}.",
+                revset,
+                commit_oids.len()
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+    let commit = repo.find_commit_or_fail(commit_oid)?;
+    let commit_description = effects
+        .get_glyphs()
+        .render(commit.friendly_describe(effects.get_glyphs())?)?;
+
+    if !dag.query_is_ancestor(commit_oid, head_oid)? {
+        writeln!(
+            effects.get_output_stream(),
+            "Cannot uncommit {commit_description}, because it is not HEAD or an ancestor of HEAD."
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+    let parent_oid = match commit.get_parent_oids().as_slice() {
+        [parent_oid] => *parent_oid,
+        [] => {
+            writeln!(
+                effects.get_output_stream(),
+                "Cannot uncommit {commit_description}, because it is a root commit."
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+        [..] => {
+            writeln!(
+                effects.get_output_stream(),
+                "Cannot uncommit {commit_description}, because it is a merge commit."
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+
+    let build_options = BuildRebasePlanOptions {
+        force_rewrite_public_commits: move_options.force_rewrite_public_commits,
+        dump_rebase_constraints: move_options.dump_rebase_constraints,
+        dump_rebase_plan: move_options.dump_rebase_plan,
+        detect_duplicate_commits_via_patch_id: move_options.detect_duplicate_commits_via_patch_id,
+    };
+    let commits_to_rewrite = dag.query_descendants(CommitSet::from(commit_oid))?;
+    let commits_to_rewrite = dag.filter_visible_commits(commits_to_rewrite)?;
+    let permissions = match RebasePlanPermissions::verify_rewrite_set(
+        &dag,
+        build_options,
+        &commits_to_rewrite,
+    )? {
+        Ok(permissions) => permissions,
+        Err(err) => {
+            err.describe(effects, &repo, &dag)?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+
+    let event_tx_id = event_log_db.make_transaction_id(now, "uncommit")?;
+    // Snapshot the working copy, so that the operation can be reverted with
+    // `git undo`.
+    create_snapshot(effects, git_run_info, &repo, &event_log_db, event_tx_id)?;
+
+    let rebase_plan = {
+        let children = dag.query_children(CommitSet::from(commit_oid))?;
+        let children = dag.filter_visible_commits(children)?;
+        let mut builder = RebasePlanBuilder::new(&dag, permissions);
+        for child_oid in dag.commit_set_to_vec(&children)? {
+            builder.move_subtree(child_oid, vec![parent_oid])?;
+        }
+
+        let thread_pool = ThreadPoolBuilder::new().build()?;
+        let repo_pool = RepoResource::new_pool(&repo)?;
+        match builder.build(effects, &thread_pool, &repo_pool)? {
+            Ok(rebase_plan) => rebase_plan,
+            Err(err) => {
+                err.describe(effects, &repo, &dag)?;
+                return Ok(Err(ExitCode(1)));
+            }
+        }
+    };
+
+    let reset_args: Vec<OsString> = if staged {
+        vec!["--soft".into()]
+    } else {
+        Vec::new()
+    };
+    if let Some(rebase_plan) = rebase_plan {
+        // The rebase must happen in memory, and `HEAD` must be reset rather
+        // than checked out afterwards, since the working copy contains the
+        // changes being uncommitted.
+        let execute_options = ExecuteRebasePlanOptions {
+            now,
+            event_tx_id,
+            force_in_memory: true,
+            force_on_disk: false,
+            preserve_timestamps: move_options.preserve_timestamps
+                || get_restack_preserve_timestamps(&repo)?,
+            resolve_merge_conflicts: false,
+            reset_author: move_options.reset_author,
+            committer_as_author: move_options.committer_as_author,
+            signature_commit_oids: None,
+            check_out_commit_options: CheckOutCommitOptions {
+                additional_args: reset_args.clone(),
+                reset: true,
+                render_smartlog: false,
+            },
+            empty_commits: EmptyCommitsMode::Drop,
+        };
+        match execute_rebase_plan(
+            effects,
+            git_run_info,
+            &repo,
+            &event_log_db,
+            &rebase_plan,
+            &execute_options,
+        )? {
+            ExecuteRebasePlanResult::Succeeded { rewritten_oids: _ } => {}
+            ExecuteRebasePlanResult::DeclinedToMerge {
+                failed_merge_info: _,
+            } => {
+                writeln!(
+                    effects.get_output_stream(),
+                    "Cannot uncommit {commit_description}, because its descendants could not be rebased in memory without it."
+                )?;
+                return Ok(Err(ExitCode(1)));
+            }
+            ExecuteRebasePlanResult::Failed { exit_code } => return Ok(Err(exit_code)),
+        }
+    }
+
+    if head_oid == commit_oid {
+        let mut args: Vec<OsString> = vec!["reset".into(), "--quiet".into()];
+        args.extend(reset_args);
+        args.push(parent_oid.to_string().into());
+        try_exit_code!(git_run_info.run(effects, Some(event_tx_id), args.as_slice())?);
+    }
+
+    // Any other branches pointing to the uncommitted commit are moved to its
+    // parent, and the commit itself is hidden.
+    move_branches(
+        effects,
+        git_run_info,
+        &repo,
+        event_tx_id,
+        &HashMap::from([(commit_oid, MaybeZeroOid::NonZero(parent_oid))]),
+    )?;
+    event_log_db.add_events(vec![Event::ObsoleteEvent {
+        timestamp,
+        event_tx_id,
+        commit_oid,
+    }])?;
+
+    writeln!(
+        effects.get_output_stream(),
+        "Uncommitted: {commit_description}"
+    )?;
+    Ok(Ok(()))
+}
@@ -1245,3 +1245,158 @@ fn test_amend_merge_commit() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_amend_to_ancestor() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+
+    git.write_file_txt("test1", "updated contents")?;
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "amend",
+            &["--to", "draft()"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Expected exactly 1 commit to amend into, but 'draft()' evaluated to 3.
+        "###);
+    }
+
+    git.branchless("amend", &["--to", "HEAD~2"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["show", "HEAD~2:test1.txt"])?;
+        insta::assert_snapshot!(stdout, @"updated contents");
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["log", "--format=%s", "--name-only"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test3.txt
+
+        test3.txt
+        create test2.txt
+
+        test2.txt
+        create test1.txt
+
+        test1.txt
+        create initial.txt
+
+        initial.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["status", "--porcelain"])?;
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_amend_sign() -> eyre::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["branch", "foo"])?;
+    git.run(&["checkout", "HEAD^"])?;
+
+    let gpg_path = git.repo_path.join(".git").join("fake-gpg");
+    std::fs::write(
+        &gpg_path,
+        "#!/bin/sh
+cat >/dev/null
+echo '-----BEGIN PGP SIGNATURE-----'
+echo 'fake signature'
+echo '-----END PGP SIGNATURE-----'
+",
+    )?;
+    std::fs::set_permissions(&gpg_path, std::fs::Permissions::from_mode(0o755))?;
+    git.run(&["config", "gpg.program", gpg_path.to_str().unwrap()])?;
+    git.run(&["config", "commit.gpgSign", "true"])?;
+
+    git.write_file_txt("test1", "updated contents")?;
+    git.branchless("amend", &[])?;
+
+    // Both the amended commit and its restacked descendant should be signed.
+    for commit in ["HEAD", "foo"] {
+        let (stdout, _stderr) = git.run(&["cat-file", "commit", commit])?;
+        assert!(
+            stdout.contains("gpgsig -----BEGIN PGP SIGNATURE-----\n fake signature\n"),
+            "commit {commit} was not signed: {stdout}"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_amend_to_ancestor_reset_author() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.run(&["config", "user.name", "Other Author"])?;
+
+    git.write_file_txt("test1", "updated contents")?;
+    git.branchless("amend", &["--to", "HEAD~2", "--reset-author"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["log", "-3", "--format=%s: %an, %cn"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test3.txt: Testy McTestface, Testy McTestface
+        create test2.txt: Testy McTestface, Testy McTestface
+        create test1.txt: Other Author, Testy McTestface
+        "###);
+    }
+
+    {
+        let (_stdout, stderr) = git.branchless_with_options(
+            "amend",
+            &["--reset-author", "--merge"],
+            &GitRunOptions {
+                expected_exit_code: 2,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @r###"
+        error: the argument '--reset-author' cannot be used with '--merge'
+
+        Usage: git-branchless amend --reset-author
+
+        For more information, try '--help'.
+        "###);
+    }
+
+    Ok(())
+}
@@ -137,6 +137,55 @@ fn test_sparse_checkout() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_unusable_database() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    // Replace the database with a directory, so that it can't be opened.
+    let db_path = git
+        .repo_path
+        .join(".git")
+        .join("branchless")
+        .join("db.sqlite3");
+    std::fs::remove_file(&db_path)?;
+    std::fs::create_dir(&db_path)?;
+
+    {
+        let (_stdout, stderr) = git.branchless_with_options(
+            "gc",
+            &[],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @r###"
+        Error: git-branchless cannot store its state for this repository: the database at <repo-path>/.git/branchless/db.sqlite3 cannot be written to (network filesystems such as NFS or SMB may not support the necessary file locking): unable to open database file: <repo-path>/.git/branchless/db.sqlite3
+
+        git-branchless refuses to run so that it doesn't fail partway through an
+        operation and leave the repository in an inconsistent state.
+
+        Here are some options:
+
+        - Make sure that the .git directory and its branchless subdirectory are on a
+          local filesystem and are writable.
+        - To store temporary files elsewhere, run: git config branchless.tempDir <path>
+          - The path must be on the same filesystem as the .git directory.
+        "###);
+    }
+
+    std::fs::remove_dir(&db_path)?;
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        @ f777ecc (> master) create initial.txt
+        "###);
+    }
+
+    Ok(())
+}
+
 /// The Git index v4 format is supported as of libgit2 v1.8.0: https://github.com/arxanas/git-branchless/issues/894#issuecomment-2044059209
 /// libgit2 v1.8.0 was bundled into git2 v0.19.0: https://github.com/arxanas/git-branchless/issues/894#issuecomment-2270760735
 ///
@@ -0,0 +1,45 @@
+use lib::testing::make_git;
+
+#[test]
+fn test_complete_revset() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    {
+        let (stdout, _stderr) = git.branchless("complete-revset", &["dra"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        draft(
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("complete-revset", &["stack() & paths."])?;
+        insta::assert_snapshot!(stdout, @r###"
+        stack() & paths.changed(
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("complete-revset", &["roots(tests."])?;
+        insta::assert_snapshot!(stdout, @r###"
+        roots(tests.failed(
+        roots(tests.fixable(
+        roots(tests.passed(
+        roots(tests.untested(
+        "###);
+    }
+
+    git.run(&[
+        "config",
+        "branchless.revsets.alias.mine",
+        "author.email(me)",
+    ])?;
+    {
+        let (stdout, _stderr) = git.branchless("complete-revset", &["draft() & mi"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        draft() & mine(
+        "###);
+    }
+
+    Ok(())
+}
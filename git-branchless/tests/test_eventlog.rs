@@ -153,3 +153,55 @@ fn test_git_v2_31_events() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_event_log_export_import() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.run(&["checkout", "master"])?;
+
+    {
+        let (stdout, _stderr) = git.branchless("event-log", &["export", "events.json"])?;
+        assert!(stdout.starts_with("Exported "), "{stdout}");
+    }
+
+    git.clear_event_log()?;
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        @ f777ecc (> master) create initial.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("event-log", &["import", "events.json"])?;
+        assert!(stdout.starts_with("Imported "), "{stdout}");
+    }
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        @ f777ecc (> master) create initial.txt
+        |
+        o 62fc20d create test1.txt
+        "###);
+    }
+
+    // Importing the same events again shouldn't duplicate them.
+    {
+        let (stdout, _stderr) = git.branchless("event-log", &["import", "events.json"])?;
+        insta::assert_snapshot!(stdout, @"Imported 0 events from events.json");
+    }
+    {
+        let (stdout, _stderr) = git.branchless("event-log", &["export", "events2.json"])?;
+        insta::assert_snapshot!(stdout, @"Exported 5 events to events2.json");
+    }
+
+    Ok(())
+}
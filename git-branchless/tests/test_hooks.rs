@@ -413,3 +413,28 @@ fn test_symbolic_transaction_ref() -> eyre::Result<()> {
     }
     Ok(())
 }
+
+#[test]
+fn test_post_rewrite_user_hook() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&[
+        "config",
+        "branchless.hook.postRewrite",
+        r#"{ echo "$BRANCHLESS_REWRITE_TYPE"; cat; } >post-rewrite.txt"#,
+    ])?;
+
+    git.branchless("move", &["--in-memory", "-x", "HEAD", "-d", "master"])?;
+
+    let post_rewrite_output = std::fs::read_to_string(git.repo_path.join("post-rewrite.txt"))?;
+    insta::assert_snapshot!(post_rewrite_output, @r###"
+    rebase
+    96d1c37a3d4363611c49f7e52186e189a04c531f fe65c1fe15584744e649b2c79d4cf9b0d878f92e
+    "###);
+
+    Ok(())
+}
@@ -689,15 +689,30 @@ fn test_install_man_pages() -> eyre::Result<()> {
     Print version
     .SH SUBCOMMANDS
     .TP
+    git\-branchless\-absorb(1)
+    Amend each change in the working copy into the draft commit in the current stack which last modified the lines that it touches, and restack any descendant commits. Changes which can\*(Aqt be attributed to exactly one commit are left in the working copy
+    .TP
     git\-branchless\-amend(1)
     Amend the current HEAD commit
     .TP
+    git\-branchless\-bottom(1)
+    Move to the first draft commit of the current stack. This is the same as `git prev \-\-all`
+    .TP
     git\-branchless\-bug\-report(1)
     Gather information about recent operations to upload as part of a bug report
     .TP
+    git\-branchless\-checkout\-pr(1)
+    Fetch a GitHub pull request, along with any pull requests that it\*(Aqs stacked on, and create a local branch for each of them, so that the stack can be reviewed with commands such as `git next` and `git prev`
+    .TP
     git\-branchless\-difftool(1)
     Use the partial commit selector UI as a Git\-compatible difftool; see git\-difftool(1) for more information on Git difftools
     .TP
+    git\-branchless\-edit(1)
+    Reorder, squash, split, drop, and reword the commits in a stack by editing a list of commands in an editor, similar to `git rebase \-\-interactive`. The edited stack is constructed in memory and can be reverted with `git undo`
+    .TP
+    git\-branchless\-event\-log(1)
+    Export or import the event log, such as to back it up or to migrate it to a new clone of the repository
+    .TP
     git\-branchless\-gc(1)
     Run internal garbage collection
     .TP
@@ -707,6 +722,9 @@ fn test_install_man_pages() -> eyre::Result<()> {
     git\-branchless\-init(1)
     Initialize the branchless workflow for this repository
     .TP
+    git\-branchless\-journal(1)
+    Show a chronological history of the operations recorded in the event log, grouped by the command which caused them
+    .TP
     git\-branchless\-install\-man\-pages(1)
     Install git\-branchless\*(Aqs man\-pages to the given path
     .TP
@@ -716,6 +734,9 @@ fn test_install_man_pages() -> eyre::Result<()> {
     git\-branchless\-next(1)
     Move to a later commit in the current stack
     .TP
+    git\-branchless\-pin(1)
+    Pin commits so that `git sync` and `git restack` don\*(Aqt rebase any stack containing them, such as for work which is intentionally based on an old release branch. Pinned commits stay pinned after they\*(Aqre amended
+    .TP
     git\-branchless\-prev(1)
     Move to an earlier commit in the current stack
     .TP
@@ -731,12 +752,24 @@ fn test_install_man_pages() -> eyre::Result<()> {
     git\-branchless\-record(1)
     Create a commit by interactively selecting which changes to include
     .TP
+    git\-branchless\-reorder(1)
+    Reorder a range of commits within a stack by editing their order in an editor
+    .TP
     git\-branchless\-reword(1)
     Reword commits
     .TP
     git\-branchless\-smartlog(1)
     `smartlog` command
     .TP
+    git\-branchless\-split(1)
+    Split a commit in two by interactively selecting which of its changes to keep in the first commit. The remaining changes are left in a second commit on top of it, and any descendant commits are restacked onto the second commit. To split a commit into more than two commits, split the second commit again
+    .TP
+    git\-branchless\-squash\-fixups(1)
+    Squash any `fixup!` and `squash!` commits into the commits that they refer to, like `git rebase \-\-autosquash`, but without touching the working copy
+    .TP
+    git\-branchless\-stash(1)
+    Shelve working copy changes as a working copy snapshot, and restore them later. Unlike `git stash`, each operation is recorded in the event log, so it shows up in `git branchless journal` and can be reverted with `git undo`
+    .TP
     git\-branchless\-submit(1)
     Push commits to a remote
     .TP
@@ -749,12 +782,21 @@ fn test_install_man_pages() -> eyre::Result<()> {
     git\-branchless\-test(1)
     Run a command on each commit in a given set and aggregate the results
     .TP
+    git\-branchless\-top(1)
+    Move to the last commit of the current stack. If the stack forks, interactively prompt which commit to move to. This is the same as `git next \-\-all \-\-interactive`
+    .TP
+    git\-branchless\-uncommit(1)
+    Remove a commit from history, leaving its changes in the working copy. This is the inverse of `git record`. Any descendants of the commit are rebased onto its parent
+    .TP
     git\-branchless\-undo(1)
     Browse or return to a previous state of the repository
     .TP
     git\-branchless\-unhide(1)
     Unhide previously\-hidden commits from the smartlog
     .TP
+    git\-branchless\-unpin(1)
+    Unpin commits which were pinned with `git branchless pin`
+    .TP
     git\-branchless\-wrap(1)
     Wrap a Git command inside a branchless transaction
     .TP
@@ -0,0 +1,84 @@
+use lib::testing::make_git;
+use regex::Regex;
+
+fn redact_timestamps(output: &str) -> String {
+    let re = Regex::new(r"(?m)^\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2} ").unwrap();
+    re.replace_all(output, "<timestamp> ").into_owned()
+}
+
+#[test]
+fn test_journal_event_types() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.run(&["checkout", "master"])?;
+    git.branchless("hide", &[&test1_oid.to_string()])?;
+    git.branchless("unhide", &[&test1_oid.to_string()])?;
+
+    {
+        let (stdout, _stderr) =
+            git.branchless("journal", &["--type", "hide", "--type", "unhide"])?;
+        insta::assert_snapshot!(redact_timestamps(&stdout), @r###"
+        <timestamp> hide
+            hid 62fc20d create test1.txt
+        <timestamp> unhide
+            unhid 62fc20d create test1.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("journal", &["--type", "commit"])?;
+        insta::assert_snapshot!(redact_timestamps(&stdout), @r###"
+        <timestamp> post-commit
+            committed 62fc20d create test1.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_journal_time_range() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.branchless("hide", &[&test1_oid.to_string()])?;
+
+    {
+        let (stdout, _stderr) = git.branchless("journal", &["--type", "hide", "--since", "1h"])?;
+        insta::assert_snapshot!(redact_timestamps(&stdout), @r###"
+        <timestamp> hide
+            hid 62fc20d create test1.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("journal", &["--type", "hide", "--until", "1h"])?;
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless(
+            "journal",
+            &["--type", "hide", "--since", "200000000000000d"],
+        )?;
+        insta::assert_snapshot!(redact_timestamps(&stdout), @r###"
+        <timestamp> hide
+            hid 62fc20d create test1.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless(
+            "journal",
+            &["--type", "hide", "--until", "200000000000000d"],
+        )?;
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    Ok(())
+}
@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+
+use lib::testing::pty::{run_in_pty, PtyAction};
 use lib::testing::{
     extract_hint_command, make_git, make_git_with_remote_repo, make_git_worktree,
     remove_rebase_lines, GitInitOptions, GitRunOptions, GitWorktreeWrapper,
@@ -2447,6 +2450,81 @@ fn test_move_merge_conflict() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_move_reuse_recorded_resolution() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_committer_date_is_author_date()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    let base_oid = git.commit_file("test1", 1)?;
+    git.detach_head()?;
+    let other_oid = git.commit_file_with_contents("conflict", 2, "conflict 1\n")?;
+    git.run(&["checkout", &base_oid.to_string()])?;
+    let dest_oid = git.commit_file_with_contents("conflict", 2, "conflict 2\n")?;
+
+    git.branchless_with_options(
+        "move",
+        &["--merge", "--source", &other_oid.to_string()],
+        &GitRunOptions {
+            expected_exit_code: 1,
+            ..Default::default()
+        },
+    )?;
+    git.resolve_file("conflict", "resolved")?;
+    git.run(&["rebase", "--continue"])?;
+
+    // Make the same conflicting change again, which should be resolved in the
+    // same way.
+    git.run(&["checkout", &base_oid.to_string()])?;
+    let other_oid = git.commit_file_with_contents("conflict", 3, "conflict 1\n")?;
+    {
+        let (stdout, _stderr) = git.branchless(
+            "move",
+            &[
+                "--source",
+                &other_oid.to_string(),
+                "--dest",
+                &dest_oid.to_string(),
+            ],
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Attempting rebase in-memory...
+        [1/1] Reused recorded resolution for 1 conflicting file: 5c309e9 create conflict.txt
+        [1/1] Committed as: b8366d1 create conflict.txt
+        branchless: processing 1 rewritten commit
+        branchless: running command: <git-executable> checkout b8366d1989d2a096c3b5b0891c82e7ced332fb0f
+        :
+        O 62fc20d (master) create test1.txt
+        |
+        o 202143f create conflict.txt
+        |\
+        | o 42951b5 create conflict.txt
+        |
+        @ b8366d1 create conflict.txt
+        In-memory rebase succeeded.
+        "###);
+    }
+
+    {
+        let stdout = git.run(&["show", "--format=", "--patch", "HEAD"])?.0;
+        insta::assert_snapshot!(stdout, @r###"
+        diff --git a/conflict.txt b/conflict.txt
+        index a6711bd..5dd5403 100644
+        --- a/conflict.txt
+        +++ b/conflict.txt
+        @@ -1 +1 @@
+        -conflict 2
+        +resolved
+        \ No newline at end of file
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_move_base() -> eyre::Result<()> {
     let git = make_git()?;
@@ -2862,6 +2940,36 @@ fn test_rebase_in_memory_updates_committer_timestamp() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_move_preserve_timestamps_flag() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.run(&["config", "branchless.restack.preserveTimestamps", "false"])?;
+
+    let repo = git.get_repo()?;
+
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.detach_head()?;
+    git.run(&["checkout", "HEAD~"])?;
+    git.commit_file("test3", 3)?;
+
+    let original_committer_timestamp = repo
+        .find_commit_or_fail(repo.get_head_info()?.oid.unwrap())?
+        .get_committer()
+        .get_time();
+    git.branchless("move", &["-d", "master", "--preserve-timestamps"])?;
+    let updated_committer_timestamp = repo
+        .find_commit_or_fail(repo.get_head_info()?.oid.unwrap())?
+        .get_committer()
+        .get_time();
+
+    assert_eq!(original_committer_timestamp, updated_committer_timestamp);
+
+    Ok(())
+}
+
 #[test]
 fn test_move_in_memory_gc() -> eyre::Result<()> {
     let git = make_git()?;
@@ -3295,6 +3403,7 @@ fn test_move_no_reapply_upstream_commits() -> eyre::Result<()> {
             Attempting rebase in-memory...
             [1/2] Skipped commit (was already applied upstream): 62fc20d create test1.txt
             [2/2] Committed as: fa46633 create test2.txt
+            Skipped 1 commit already applied upstream: 62fc20d
             branchless: processing 1 update: branch should-be-deleted
             branchless: processing 2 rewritten commits
             branchless: running command: <git-executable> checkout fa46633239bfa767036e41a77b67258286e4ddb9
@@ -3437,6 +3546,7 @@ fn test_move_no_reapply_squashed_commits() -> eyre::Result<()> {
             Attempting rebase in-memory...
             [1/2] Skipped now-empty commit: e7bcdd6 create test1.txt
             [2/2] Skipped now-empty commit: 12d361a create test2.txt
+            Skipped 2 commits which became empty: 62fc20d, 96d1c37
             branchless: processing 2 rewritten commits
             branchless: running command: <git-executable> checkout master
             :
@@ -3456,6 +3566,62 @@ fn test_move_no_reapply_squashed_commits() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_move_skip_empty() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+
+    git.run(&["checkout", "master"])?;
+    git.run(&["cherry-pick", "--no-commit", &test1_oid.to_string()])?;
+    git.run(&["cherry-pick", "--no-commit", &test2_oid.to_string()])?;
+    git.run(&["commit", "-m", "squashed test1 and test2"])?;
+
+    {
+        let (stdout, _stderr) = git.branchless(
+            "move",
+            &["--skip-empty", "-s", &test1_oid.to_string(), "-d", "master"],
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        hint: you can omit the --dest flag in this case, as it defaults to HEAD
+        hint: disable this hint by running: git config --global branchless.hint.moveImplicitHeadArgument false
+        Attempting rebase in-memory...
+        [1/3] Skipped now-empty commit: e7bcdd6 create test1.txt
+        [2/3] Skipped now-empty commit: 12d361a create test2.txt
+        [3/3] Committed as: 9313d72 create test3.txt
+        Skipped 2 commits which became empty: 62fc20d, 96d1c37
+        branchless: processing 3 rewritten commits
+        branchless: running command: <git-executable> checkout master
+        :
+        @ de4a1fe (> master) squashed test1 and test2
+        |
+        o 9313d72 create test3.txt
+        In-memory rebase succeeded.
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        @ de4a1fe (> master) squashed test1 and test2
+        |
+        o 9313d72 create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_move_delete_checked_out_branch() -> eyre::Result<()> {
     let git = make_git()?;
@@ -3557,6 +3723,7 @@ fn test_move_delete_checked_out_branch() -> eyre::Result<()> {
             [1/3] Skipped commit (was already applied upstream): 62fc20d create test1.txt
             [2/3] Skipped commit (was already applied upstream): 96d1c37 create test2.txt
             [3/3] Committed as: 012efd6 create test3.txt
+            Skipped 2 commits already applied upstream: 62fc20d, 96d1c37
             branchless: processing 2 updates: branch more-work, branch work
             branchless: processing 3 rewritten commits
             branchless: running command: <git-executable> checkout master
@@ -3616,6 +3783,58 @@ fn test_move_with_unstaged_changes() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_move_with_unstaged_changes_autostash() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.run(&["config", "branchless.restack.preserveTimestamps", "true"])?;
+
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "HEAD~"])?;
+    git.commit_file("test3", 3)?;
+
+    {
+        git.write_file_txt("test3", "new contents")?;
+        let (stdout, _stderr) =
+            git.branchless("move", &["--on-disk", "--autostash", "-d", "master"])?;
+        let stdout = stdout
+            .lines()
+            .filter(|line| line.to_lowercase().contains("autostash"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        insta::assert_snapshot!(stdout, @r###"
+        Autostashed uncommitted changes.
+        Restored autostashed changes.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["log", "--format=%s", "-2", "HEAD"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test3.txt
+        create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["diff"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        diff --git a/test3.txt b/test3.txt
+        index a474f4e..79d52c1 100644
+        --- a/test3.txt
+        +++ b/test3.txt
+        @@ -1 +1 @@
+        -test3 contents
+        +new contents
+        \ No newline at end of file
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_move_merge_commit() -> eyre::Result<()> {
     let git = make_git()?;
@@ -3811,22 +4030,18 @@ fn test_move_merge_commit() -> eyre::Result<()> {
 
     // no flag
     {
+        let git = git.duplicate_repo()?;
+
         {
-            let (stdout, stderr) = git.branchless_with_options(
-                "move",
-                &["-s", &test2_oid.to_string(), "-d", "master"],
-                &GitRunOptions {
-                    expected_exit_code: 1,
-                    ..Default::default()
-                },
-            )?;
+            let (stdout, _stderr) =
+                git.branchless("move", &["-s", &test2_oid.to_string(), "-d", "master"])?;
             insta::assert_snapshot!(stdout, @r###"
             Attempting rebase in-memory...
-            Merge commits currently can't be rebased in-memory.
-            The merge commit was: 28790c7 Merge commit 'fe65c1fe15584744e649b2c79d4cf9b0d878f92e' into HEAD
-            To resolve merge conflicts, retry this operation with the --merge option.
+            [1/2] Committed as: 96d1c37 create test2.txt
+            [2/2] Committed as: 5a6a761 Merge commit 'fe65c1fe15584744e649b2c79d4cf9b0d878f92e' into HEAD
+            branchless: processing 2 rewritten commits
+            In-memory rebase succeeded.
             "###);
-            insta::assert_snapshot!(stderr, @"");
         }
 
         {
@@ -3834,64 +4049,52 @@ fn test_move_merge_commit() -> eyre::Result<()> {
             insta::assert_snapshot!(stdout, @r###"
             O f777ecc create initial.txt
             |\
-            | o fe65c1f create test2.txt
-            | & (merge) 28790c7 Merge commit 'fe65c1fe15584744e649b2c79d4cf9b0d878f92e' into HEAD
-            |\
             | @ 98b9119 create test3.txt
             | |
-            | | & (merge) fe65c1f create test2.txt
+            | | & (merge) 96d1c37 create test2.txt
             | |/
-            | o 28790c7 Merge commit 'fe65c1fe15584744e649b2c79d4cf9b0d878f92e' into HEAD
+            | o 5a6a761 Merge commit 'fe65c1fe15584744e649b2c79d4cf9b0d878f92e' into HEAD
             |
             O 62fc20d (master) create test1.txt
+            |
+            o 96d1c37 create test2.txt
+            & (merge) 5a6a761 Merge commit 'fe65c1fe15584744e649b2c79d4cf9b0d878f92e' into HEAD
+            "###);
+        }
+    }
+
+    // --merge with no other flag
+    {
+        {
+            let (stdout, _stderr) = git.branchless(
+                "move",
+                &["--merge", "-s", &test2_oid.to_string(), "-d", "master"],
+            )?;
+            insta::assert_snapshot!(stdout, @r###"
+            Attempting rebase in-memory...
+            [1/2] Committed as: 96d1c37 create test2.txt
+            [2/2] Committed as: 5a6a761 Merge commit 'fe65c1fe15584744e649b2c79d4cf9b0d878f92e' into HEAD
+            branchless: processing 2 rewritten commits
+            In-memory rebase succeeded.
             "###);
         }
 
-        // --merge with no other flag
         {
-            {
-                let (stdout, stderr) = git.branchless(
-                    "move",
-                    &["--merge", "-s", &test2_oid.to_string(), "-d", "master"],
-                )?;
-                insta::assert_snapshot!(stdout, @r###"
-                Attempting rebase in-memory...
-                Failed to merge in-memory, trying again on-disk...
-                branchless: running command: <git-executable> diff --quiet
-                Calling Git for on-disk rebase...
-                branchless: running command: <git-executable> rebase --continue
-                "###);
-                insta::assert_snapshot!(stderr, @r###"
-                branchless: processing 1 update: ref HEAD
-                branchless: processing 1 update: ref HEAD
-                Executing: git branchless hook-detect-empty-commit fe65c1fe15584744e649b2c79d4cf9b0d878f92e
-                branchless: processing 1 update: ref refs/rewritten/parent-3
-                branchless: processing 1 update: ref HEAD
-                branchless: processing 1 update: ref HEAD
-                Executing: git branchless hook-register-extra-post-rewrite-hook
-                branchless: processing 2 rewritten commits
-                Successfully rebased and updated detached HEAD.
-                branchless: processing 1 update: ref refs/rewritten/parent-3
-                "###);
-            }
-
-            {
-                let stdout = git.smartlog()?;
-                insta::assert_snapshot!(stdout, @r###"
-                O f777ecc create initial.txt
-                |\
-                | @ 98b9119 create test3.txt
-                | |
-                | | & (merge) 96d1c37 create test2.txt
-                | |/
-                | o 5a6a761 Merge commit 'fe65c1fe15584744e649b2c79d4cf9b0d878f92e' into HEAD
-                |
-                O 62fc20d (master) create test1.txt
-                |
-                o 96d1c37 create test2.txt
-                & (merge) 5a6a761 Merge commit 'fe65c1fe15584744e649b2c79d4cf9b0d878f92e' into HEAD
-                "###);
-            }
+            let stdout = git.smartlog()?;
+            insta::assert_snapshot!(stdout, @r###"
+            O f777ecc create initial.txt
+            |\
+            | @ 98b9119 create test3.txt
+            | |
+            | | & (merge) 96d1c37 create test2.txt
+            | |/
+            | o 5a6a761 Merge commit 'fe65c1fe15584744e649b2c79d4cf9b0d878f92e' into HEAD
+            |
+            O 62fc20d (master) create test1.txt
+            |
+            o 96d1c37 create test2.txt
+            & (merge) 5a6a761 Merge commit 'fe65c1fe15584744e649b2c79d4cf9b0d878f92e' into HEAD
+            "###);
         }
     }
 
@@ -3951,10 +4154,29 @@ fn test_move_merge_commit_both_parents() -> eyre::Result<()> {
         )?;
         insta::assert_snapshot!(stdout, @r###"
         Attempting rebase in-memory...
-        Failed to merge in-memory, trying again on-disk...
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
+        [1/4] Committed as: 4838e49 create test3.txt
+        [2/4] Committed as: a248207 create test4.txt
+        [3/4] Committed as: b1f9efa create test5.txt
+        [4/4] Committed as: d9a0a04 Merge commit '355e173bf9c5d2efac2e451da0cdad3fb82b869a' into HEAD
+        branchless: processing 4 rewritten commits
+        branchless: running command: <git-executable> checkout d9a0a04d262240c52487c571636d9499d54a70ee
+        O f777ecc (master) create initial.txt
+        |
+        o 62fc20d create test1.txt
+        |\
+        | o 96d1c37 create test2.txt
+        |
+        o 4838e49 create test3.txt
+        |\
+        | o a248207 create test4.txt
+        | & (merge) d9a0a04 Merge commit '355e173bf9c5d2efac2e451da0cdad3fb82b869a' into HEAD
+        |
+        o b1f9efa create test5.txt
+        |
+        | & (merge) a248207 create test4.txt
+        |/
+        @ d9a0a04 Merge commit '355e173bf9c5d2efac2e451da0cdad3fb82b869a' into HEAD
+        In-memory rebase succeeded.
         "###);
     }
 
@@ -3983,9 +4205,8 @@ fn test_move_merge_commit_both_parents() -> eyre::Result<()> {
     Ok(())
 }
 
-/// Regression test for https://github.com/arxanas/git-branchless/issues/912
 #[test]
-fn test_move_merge_commit_issue_912() -> eyre::Result<()> {
+fn test_move_merge_commit_first_parent_becomes_empty() -> eyre::Result<()> {
     let git = make_git()?;
 
     if !git.supports_reference_transactions()? {
@@ -3993,32 +4214,125 @@ fn test_move_merge_commit_issue_912() -> eyre::Result<()> {
     }
     git.init_repo()?;
 
-    git.commit_file("A", 1)?;
-    git.commit_file("B", 2)?;
-    git.run(&["checkout", "HEAD~"])?;
-    let c_oid = git.commit_file("C", 3)?;
-    let d_oid = git.commit_file("D", 4)?;
-    git.run(&["checkout", "HEAD~"])?;
-    git.commit_file("E", 5)?;
-    git.run(&["merge", "-m", "Merge D and E", &d_oid.to_string()])?;
-    git.commit_file("F", 6)?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    let test3_oid = git.commit_file("test3", 3)?;
+    git.run(&["checkout", &test1_oid.to_string()])?;
+    git.commit_file("test2", 2)?;
+    git.run(&["merge", &test3_oid.to_string()])?;
+
+    // Make the same change as `test2` on the main branch, but together with
+    // another change, so that it's not detected as already applied upstream,
+    // and instead becomes empty when rebased.
+    git.run(&["checkout", "master"])?;
+    git.write_file_txt("test2", "test2 contents\n")?;
+    git.write_file_txt("test4", "test4 contents\n")?;
+    git.run(&["add", "."])?;
+    git.run(&["commit", "-m", "create test2.txt and test4.txt"])?;
 
     {
         let stdout = git.smartlog()?;
         insta::assert_snapshot!(stdout, @r###"
-        :
-        O d59c43f create A.txt
+        O f777ecc create initial.txt
         |\
-        | o 50eef92 create C.txt
+        | o 62fc20d create test1.txt
         | |\
-        | | o 00aa7ad create D.txt
-        | | & (merge) 52f100c Merge D and E
-        | |
-        | o af1a4ce create E.txt
-        | |
-        | | & (merge) 00aa7ad create D.txt
-        | |/
-        | o 52f100c Merge D and E
+        | | o 96d1c37 create test2.txt
+        | | |
+        | | | & (merge) 4838e49 create test3.txt
+        | | |/
+        | | o 4de5165 Merge commit '4838e49b08954becdd17c0900c1179c2c654c627' into HEAD
+        | |
+        | o 4838e49 create test3.txt
+        | & (merge) 4de5165 Merge commit '4838e49b08954becdd17c0900c1179c2c654c627' into HEAD
+        |
+        @ 3d8a47f (> master) create test2.txt and test4.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) =
+            git.branchless("move", &["--merge", "-s", &test1_oid.to_string()])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Attempting rebase in-memory...
+        [1/4] Committed as: 9ca4989 create test1.txt
+        [2/4] Committed as: d9ef8d2 create test3.txt
+        [3/4] Skipped now-empty commit: 16bdd7d create test2.txt
+        [4/4] Committed as: 2cc80e5 Merge commit '4838e49b08954becdd17c0900c1179c2c654c627' into HEAD
+        Skipped 1 commit which became empty: 96d1c37
+        branchless: processing 4 rewritten commits
+        branchless: running command: <git-executable> checkout master
+        :
+        @ 3d8a47f (> master) create test2.txt and test4.txt
+        |
+        o 9ca4989 create test1.txt
+        |\
+        | | & (merge) d9ef8d2 create test3.txt
+        | |/
+        | o 2cc80e5 Merge commit '4838e49b08954becdd17c0900c1179c2c654c627' into HEAD
+        |
+        o d9ef8d2 create test3.txt
+        & (merge) 2cc80e5 Merge commit '4838e49b08954becdd17c0900c1179c2c654c627' into HEAD
+        In-memory rebase succeeded.
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        @ 3d8a47f (> master) create test2.txt and test4.txt
+        |
+        o 9ca4989 create test1.txt
+        |\
+        | | & (merge) d9ef8d2 create test3.txt
+        | |/
+        | o 2cc80e5 Merge commit '4838e49b08954becdd17c0900c1179c2c654c627' into HEAD
+        |
+        o d9ef8d2 create test3.txt
+        & (merge) 2cc80e5 Merge commit '4838e49b08954becdd17c0900c1179c2c654c627' into HEAD
+        "###);
+    }
+
+    Ok(())
+}
+
+/// Regression test for https://github.com/arxanas/git-branchless/issues/912
+#[test]
+fn test_move_merge_commit_issue_912() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    git.commit_file("A", 1)?;
+    git.commit_file("B", 2)?;
+    git.run(&["checkout", "HEAD~"])?;
+    let c_oid = git.commit_file("C", 3)?;
+    let d_oid = git.commit_file("D", 4)?;
+    git.run(&["checkout", "HEAD~"])?;
+    git.commit_file("E", 5)?;
+    git.run(&["merge", "-m", "Merge D and E", &d_oid.to_string()])?;
+    git.commit_file("F", 6)?;
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O d59c43f create A.txt
+        |\
+        | o 50eef92 create C.txt
+        | |\
+        | | o 00aa7ad create D.txt
+        | | & (merge) 52f100c Merge D and E
+        | |
+        | o af1a4ce create E.txt
+        | |
+        | | & (merge) 00aa7ad create D.txt
+        | |/
+        | o 52f100c Merge D and E
         | |
         | @ 7f5857e create F.txt
         |
@@ -4163,10 +4477,29 @@ fn test_move_merge_commit_issue_912() -> eyre::Result<()> {
             },
         )
         Attempting rebase in-memory...
-        Failed to merge in-memory, trying again on-disk...
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
+        [1/5] Committed as: ffe2812 create C.txt
+        [2/5] Committed as: 53671af create D.txt
+        [3/5] Committed as: 8b81b37 create E.txt
+        [4/5] Committed as: f7c3c92 Merge D and E
+        [5/5] Committed as: 235027c create F.txt
+        branchless: processing 5 rewritten commits
+        branchless: running command: <git-executable> checkout 235027c0e66a3770cbfd9cbbf9998fbcab3ae571
+        :
+        O 33790e1 (master) create B.txt
+        |
+        o ffe2812 create C.txt
+        |\
+        | o 53671af create D.txt
+        | & (merge) f7c3c92 Merge D and E
+        |
+        o 8b81b37 create E.txt
+        |
+        | & (merge) 53671af create D.txt
+        |/
+        o f7c3c92 Merge D and E
+        |
+        @ 235027c create F.txt
+        In-memory rebase succeeded.
         "###);
     }
 
@@ -4194,6 +4527,60 @@ fn test_move_merge_commit_issue_912() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_move_merge_commit_preserves_conflict_resolution() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.write_file_txt("conflict", "left contents\n")?;
+    git.run(&["add", "."])?;
+    git.run(&["commit", "-m", "left"])?;
+    let left_oid = git.get_repo()?.get_head_info()?.oid.unwrap();
+    git.run(&["checkout", &test1_oid.to_string()])?;
+    git.write_file_txt("conflict", "right contents\n")?;
+    git.run(&["add", "."])?;
+    git.run(&["commit", "-m", "right"])?;
+    git.run_with_options(
+        &["merge", &left_oid.to_string()],
+        &GitRunOptions {
+            expected_exit_code: 1,
+            ..Default::default()
+        },
+    )?;
+    git.write_file_txt("conflict", "resolved contents\n")?;
+    git.run(&["add", "."])?;
+    git.run(&["commit", "--no-edit"])?;
+    let merge_oid = git.get_repo()?.get_head_info()?.oid.unwrap();
+
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", &merge_oid.to_string()])?;
+
+    {
+        let (stdout, _stderr) =
+            git.branchless("move", &["-s", &test1_oid.to_string(), "-d", "master"])?;
+        assert!(stdout.contains("In-memory rebase succeeded."));
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["show", "HEAD:conflict.txt"])?;
+        insta::assert_snapshot!(stdout, @"resolved contents");
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["show", "HEAD:test2.txt"])?;
+        insta::assert_snapshot!(stdout, @"test2 contents");
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_move_orphaned_root() -> eyre::Result<()> {
     let git = make_git()?;
@@ -4294,6 +4681,7 @@ fn test_move_orphaned_root() -> eyre::Result<()> {
             Attempting rebase in-memory...
             [1/2] Skipped now-empty commit: 270b681 new root
             [2/2] Committed as: 70deb1e create test3.txt
+            Skipped 1 commit which became empty: da90168
             branchless: processing 1 update: branch new-root
             branchless: processing 2 rewritten commits
             branchless: running command: <git-executable> checkout new-root
@@ -4844,6 +5232,7 @@ fn test_move_delete_branch_config_entry() -> eyre::Result<()> {
         insta::assert_snapshot!(stdout, @r###"
         Attempting rebase in-memory...
         [1/1] Skipped commit (was already applied upstream): 047b7ad create test1.txt
+        Skipped 1 commit already applied upstream: 047b7ad
         branchless: processing 1 update: branch bar
         branchless: processing 1 rewritten commit
         branchless: running command: <git-executable> checkout foo
@@ -6311,3 +6700,872 @@ fn test_worktree_rebase_in_memory() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_move_keeps_intentionally_empty_commit() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    git.commit_file("test1", 1)?;
+    git.detach_head()?;
+    git.run(&["commit", "--allow-empty", "-m", "marker"])?;
+    let marker_oid = git.get_repo()?.get_head_info()?.oid.unwrap();
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test2", 2)?;
+
+    git.branchless(
+        "move",
+        &["--in-memory", "-s", &marker_oid.to_string(), "-d", "master"],
+    )?;
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        @ 96d1c37 (> master) create test2.txt
+        |
+        o 739d561 (empty) marker
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_move_multiple_sources() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.run(&["checkout", "HEAD^"])?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.run(&["checkout", "HEAD^"])?;
+    let test3_oid = git.commit_file("test3", 3)?;
+    let test4_oid = git.commit_file("test4", 4)?;
+
+    {
+        let git = git.duplicate_repo()?;
+        git.branchless(
+            "move",
+            &[
+                "-s",
+                &test1_oid.to_string(),
+                "-s",
+                &test2_oid.to_string(),
+                "-d",
+                &test3_oid.to_string(),
+            ],
+        )?;
+
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        o 98b9119 create test3.txt
+        |\
+        | o 4b9ce31 create test1.txt
+        |\
+        | o 200e547 create test2.txt
+        |
+        @ 2b633ed create test4.txt
+        "###);
+    }
+
+    {
+        let git = git.duplicate_repo()?;
+        git.branchless(
+            "move",
+            &[
+                "-x",
+                &test2_oid.to_string(),
+                "-x",
+                &test4_oid.to_string(),
+                "-d",
+                &test1_oid.to_string(),
+            ],
+        )?;
+
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |\
+        | o 62fc20d create test1.txt
+        | |\
+        | | o 96d1c37 create test2.txt
+        | |
+        | @ bf0d52a create test4.txt
+        |
+        o 98b9119 create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_move_insert_before_and_after() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "master"])?;
+    git.detach_head()?;
+    let test4_oid = git.commit_file("test4", 4)?;
+
+    {
+        let git = git.duplicate_repo()?;
+        git.branchless(
+            "move",
+            &[
+                "-x",
+                &test4_oid.to_string(),
+                "--insert-before",
+                &test2_oid.to_string(),
+            ],
+        )?;
+
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        o 62fc20d create test1.txt
+        |
+        @ bf0d52a create test4.txt
+        |
+        o 44352d0 create test2.txt
+        |
+        o cf5eb24 create test3.txt
+        "###);
+    }
+
+    {
+        let git = git.duplicate_repo()?;
+        git.branchless(
+            "move",
+            &[
+                "-x",
+                &test4_oid.to_string(),
+                "--insert-after",
+                &test2_oid.to_string(),
+            ],
+        )?;
+
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        o 62fc20d create test1.txt
+        |
+        o 96d1c37 create test2.txt
+        |
+        @ f57e36f create test4.txt
+        |
+        o 4b8e07b create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_move_interactive_plan() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "HEAD~"])?;
+    git.commit_file("test3", 3)?;
+
+    {
+        // Print the plan and then clear it, which aborts the move.
+        let (stdout, _stderr) = git.branchless_with_options(
+            "move",
+            &["-i", "-s", &test1_oid.to_string(), "-d", "master"],
+            &GitRunOptions {
+                env: HashMap::from([(
+                    "GIT_EDITOR".to_string(),
+                    r#"sh -c 'cat "$1" && : >"$1"' sh"#.to_string(),
+                )]),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        pick 62fc20d create test1.txt
+        pick 96d1c37 create test2.txt
+        onto 62fc20d create test1.txt
+        pick 4838e49 create test3.txt
+        #
+        # Move 3 commits onto f777ecc create initial.txt.
+        #
+        # Commands:
+        # p, pick <commit> = move commit onto the previously picked commit
+        # d, drop <commit> = don't move commit, leaving it where it is
+        # o, onto <commit> = move the following picked commits onto <commit>
+        #
+        # The first picked commit is moved onto the destination commit unless it's
+        # preceded by an `onto` command. These lines are executed from top to bottom,
+        # and can be re-ordered. If you remove a line here, that commit won't be
+        # moved. If you remove everything, the move will be aborted.
+        Nothing to do.
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_move_interactive_reorder() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    let test3_oid = git.commit_file("test3", 3)?;
+    git.commit_file("test4", 4)?;
+
+    {
+        // Swap the first two lines of the plan.
+        let (_stdout, _stderr) = git.branchless_with_options(
+            "move",
+            &[
+                "-i",
+                "-s",
+                &test3_oid.to_string(),
+                "-d",
+                &test2_oid.to_string(),
+            ],
+            &GitRunOptions {
+                env: HashMap::from([(
+                    "GIT_EDITOR".to_string(),
+                    "sed -i -e 1{h;d} -e 2G".to_string(),
+                )]),
+                ..Default::default()
+            },
+        )?;
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        o 62fc20d create test1.txt
+        |
+        o 96d1c37 create test2.txt
+        |
+        @ f57e36f create test4.txt
+        |
+        o 4b8e07b create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_move_interactive_drop() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    let test3_oid = git.commit_file("test3", 3)?;
+    git.commit_file("test4", 4)?;
+
+    {
+        let (_stdout, _stderr) = git.branchless_with_options(
+            "move",
+            &[
+                "-i",
+                "-s",
+                &test3_oid.to_string(),
+                "-d",
+                &test1_oid.to_string(),
+            ],
+            &GitRunOptions {
+                env: HashMap::from([(
+                    "GIT_EDITOR".to_string(),
+                    "sed -i -e 's/^pick.70deb1e/drop 70deb1e/'".to_string(),
+                )]),
+                ..Default::default()
+            },
+        )?;
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        o 62fc20d create test1.txt
+        |\
+        | o 96d1c37 create test2.txt
+        | |
+        | o 70deb1e create test3.txt
+        |
+        @ bf0d52a create test4.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_move_interactive_invalid_plan() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    {
+        let (stdout, stderr) = git.branchless_with_options(
+            "move",
+            &["-i", "-s", &test1_oid.to_string(), "-d", "master"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                env: HashMap::from([(
+                    "GIT_EDITOR".to_string(),
+                    "sed -i -e 2s/^pick/squash/".to_string(),
+                )]),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @"Unknown command on line 2 of move plan: squash");
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    {
+        let (stdout, stderr) = git.branchless_with_options(
+            "move",
+            &["-i", "-s", &test1_oid.to_string(), "-d", "master"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                env: HashMap::from([(
+                    "GIT_EDITOR".to_string(),
+                    "sed -i -e '2s/^pick.96d1c37/pick f777ecc/'".to_string(),
+                )]),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @"Commit on line 2 of move plan is not one of the commits being moved: f777ecc");
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_move_autosquash() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file_with_contents_and_message("test1", 3, "updated contents\n", "fixup! create")?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test4", 4)?;
+
+    let stdout = git.smartlog()?;
+    insta::assert_snapshot!(stdout, @r###"
+    O f777ecc create initial.txt
+    |\
+    | o 62fc20d create test1.txt
+    | |
+    | o 96d1c37 create test2.txt
+    | |
+    | o 198ffe7 fixup! create test1.txt
+    |
+    @ 8f7aef5 (> master) create test4.txt
+    "###);
+
+    git.branchless(
+        "move",
+        &["--autosquash", "-s", &test1_oid.to_string(), "-d", "master"],
+    )?;
+
+    let stdout = git.smartlog()?;
+    insta::assert_snapshot!(stdout, @r###"
+    :
+    @ 8f7aef5 (> master) create test4.txt
+    |
+    o 956abb8 create test1.txt
+    |
+    o 3d85115 create test2.txt
+    "###);
+
+    let (stdout, _stderr) = git.run(&["show", "956abb8:test1.txt"])?;
+    insta::assert_snapshot!(stdout, @"updated contents
+");
+
+    Ok(())
+}
+
+#[test]
+fn test_move_dry_run() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "master"])?;
+    git.detach_head()?;
+    let test4_oid = git.commit_file("test4", 4)?;
+
+    let stdout = git.smartlog()?;
+    insta::assert_snapshot!(stdout, @r###"
+    O f777ecc (master) create initial.txt
+    |\
+    | o 62fc20d create test1.txt
+    | |
+    | o 96d1c37 create test2.txt
+    | |
+    | o 70deb1e create test3.txt
+    |
+    @ 8f7aef5 create test4.txt
+    "###);
+
+    let (stdout, _stderr) = git.branchless(
+        "move",
+        &[
+            "--dry-run",
+            "-s",
+            &test4_oid.to_string(),
+            "-d",
+            &test2_oid.to_string(),
+        ],
+    )?;
+    insta::assert_snapshot!(stdout, @r###"
+    This operation would result in:
+    :
+    o 96d1c37 create test2.txt
+    |
+    @ f57e36f create test4.txt
+    (This was a dry-run; run without --dry-run to apply changes.)
+    "###);
+
+    // Nothing should have been moved.
+    let stdout = git.smartlog()?;
+    insta::assert_snapshot!(stdout, @r###"
+    O f777ecc (master) create initial.txt
+    |\
+    | o 62fc20d create test1.txt
+    | |
+    | o 96d1c37 create test2.txt
+    | |
+    | o 70deb1e create test3.txt
+    |
+    @ 8f7aef5 create test4.txt
+    "###);
+
+    Ok(())
+}
+
+#[test]
+fn test_move_dry_run_merge_conflict() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    let base_oid = git.commit_file("test1", 1)?;
+    git.detach_head()?;
+    let other_oid = git.commit_file_with_contents("conflict", 2, "conflict 1\n")?;
+    git.run(&["checkout", &base_oid.to_string()])?;
+    git.commit_file_with_contents("conflict", 2, "conflict 2\n")?;
+
+    let (stdout, _stderr) = git.branchless_with_options(
+        "move",
+        &["--dry-run", "--source", &other_oid.to_string()],
+        &GitRunOptions {
+            expected_exit_code: 1,
+            ..Default::default()
+        },
+    )?;
+    insta::assert_snapshot!(stdout, @r###"
+    This operation would cause a merge conflict:
+    - (1 conflicting file) e85d25c create conflict.txt
+    To resolve merge conflicts, retry this operation with the --merge option.
+    "###);
+
+    Ok(())
+}
+
+#[test]
+fn test_move_create_merge() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test3", 3)?;
+
+    let (stdout, _stderr) = git.branchless(
+        "move",
+        &[
+            "--create-merge",
+            "-s",
+            &test1_oid.to_string(),
+            "-d",
+            "master",
+        ],
+    )?;
+    assert!(stdout.contains(
+        "Created merge commit: 93cf3f4 Merge commit '96d1c37a3d4363611c49f7e52186e189a04c531f'"
+    ));
+
+    {
+        let (stdout, _stderr) = git.run(&["log", "-1", "--format=%h %p %s", "master"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        93cf3f4 98b9119 96d1c37 Merge commit '96d1c37a3d4363611c49f7e52186e189a04c531f'
+        "###);
+    }
+    {
+        let (stdout, _stderr) = git.run(&["rev-parse", "--abbrev-ref", "HEAD"])?;
+        insta::assert_snapshot!(stdout, @"master");
+    }
+    {
+        let (stdout, _stderr) = git.run(&["status", "--porcelain"])?;
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_move_abort_and_continue() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_committer_date_is_author_date()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    let base_oid = git.commit_file("test1", 1)?;
+    git.detach_head()?;
+    let other_oid = git.commit_file_with_contents("conflict", 2, "conflict 1\n")?;
+    git.run(&["checkout", &base_oid.to_string()])?;
+    git.commit_file_with_contents("conflict", 2, "conflict 2\n")?;
+
+    git.branchless_with_options(
+        "move",
+        &["--merge", "--source", &other_oid.to_string()],
+        &GitRunOptions {
+            expected_exit_code: 1,
+            ..Default::default()
+        },
+    )?;
+    {
+        let (stdout, _stderr) = git.branchless("move", &["--abort"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> rebase --abort
+        Aborted the interrupted operation.
+        "###);
+    }
+    {
+        let (stdout, _stderr) = git.run(&["rev-parse", "--short", "HEAD"])?;
+        insta::assert_snapshot!(stdout, @"202143f");
+    }
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "move",
+            &["--abort"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @"There is no interrupted operation to abort.");
+    }
+
+    git.branchless_with_options(
+        "move",
+        &["--merge", "--source", &other_oid.to_string()],
+        &GitRunOptions {
+            expected_exit_code: 1,
+            ..Default::default()
+        },
+    )?;
+    git.resolve_file("conflict", "resolved")?;
+    {
+        let (stdout, _stderr) = git.branchless("move", &["--continue"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> rebase --continue
+        [detached HEAD 42951b5] create conflict.txt
+         1 file changed, 1 insertion(+), 1 deletion(-)
+        "###);
+    }
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d (master) create test1.txt
+        |
+        @ 202143f create conflict.txt
+        |
+        o 42951b5 create conflict.txt
+        "###);
+    }
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "move",
+            &["--continue"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @"There is no interrupted operation to continue.");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_move_dest_per_source_root() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    let test4_oid = git.commit_file("test4", 4)?;
+    git.run(&["checkout", &test2_oid.to_string()])?;
+    let test5_oid = git.commit_file("test5", 5)?;
+
+    git.branchless(
+        "move",
+        &[
+            "-s",
+            &test4_oid.to_string(),
+            "-s",
+            &test5_oid.to_string(),
+            "-d",
+            "parents(parents($1))",
+        ],
+    )?;
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        o 62fc20d create test1.txt
+        |\
+        | o 96d1c37 create test2.txt
+        | |\
+        | | o 70deb1e create test3.txt
+        | |
+        | o f57e36f create test4.txt
+        |
+        @ ea7aa06 create test5.txt
+        "###);
+    }
+
+    {
+        let (_stdout, stderr) = git.branchless_with_options(
+            "move",
+            &["-x", &test2_oid.to_string(), "-d", "parents($1)"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @"A destination which refers to $1 can only be used to move subtrees with --source or --base.");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_move_extract_path() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("sub/a", 2)?;
+    git.write_file_txt("sub/b", "sub/b contents\n")?;
+    git.commit_file("test2", 3)?;
+    git.commit_file("test3", 4)?;
+
+    {
+        let (stdout, _stderr) =
+            git.branchless("move", &["--extract-path", "sub", "-d", "master"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: processing 3 rewritten commits
+        Extracted changes to sub into 2 commits:
+          ddebafc create sub/a.txt
+          545451b create test2.txt
+        branchless: running command: <git-executable> checkout 0b751144d4f178c0210276ed17359e2d12e2c93b
+        O f777ecc (master) create initial.txt
+        |\
+        | o 62fc20d create test1.txt
+        | |
+        | o fc9d60a create test2.txt
+        | |
+        | @ 0b75114 create test3.txt
+        |
+        o ddebafc create sub/a.txt
+        |
+        o 545451b create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["show", "--name-only", "--format=%s", "545451b"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test2.txt
+
+        sub/b.txt
+        "###);
+    }
+
+    {
+        let (_stdout, stderr) = git.branchless_with_options(
+            "move",
+            &["--extract-path", "nonexistent", "-d", "master"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @"None of the commits to split change any files under: nonexistent");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_move_resolve_text_conflict() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+    git.write_file_txt("test", "line1\nline2\nline3\nline4\nline5\nline6\nline7\n")?;
+    git.run(&["add", "."])?;
+    git.run(&["commit", "-m", "create test.txt"])?;
+
+    git.run(&["checkout", "-b", "ours"])?;
+    git.write_file_txt("test", "ours1\nline2\nline3\nline4\nours5\nline6\nline7\n")?;
+    git.run(&["commit", "-am", "ours"])?;
+    git.run(&["checkout", "master"])?;
+    git.run(&["checkout", "-b", "theirs"])?;
+    git.write_file_txt(
+        "test",
+        "line1\nline2\nline3\nline4\ntheirs5\nline6\ntheirs7\n",
+    )?;
+    git.run(&["commit", "-am", "theirs"])?;
+
+    run_in_pty(
+        &git,
+        "move",
+        &["--resolve", "-x", "theirs", "-d", "ours"],
+        &[
+            PtyAction::WaitUntilContains("test.txt"),
+            PtyAction::Write("f"), // expand files
+            PtyAction::WaitUntilContains("theirs5"),
+            PtyAction::Write(" "),
+            PtyAction::WaitUntilContains("(●)"),
+            PtyAction::Write("c"),
+        ],
+    )?;
+
+    {
+        // Only the conflicting hunk was chosen; the non-conflicting changes
+        // from both sides are kept.
+        let (stdout, _stderr) = git.run(&["show", "theirs:test.txt"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        ours1
+        line2
+        line3
+        line4
+        theirs5
+        line6
+        theirs7
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_move_resolve_binary_conflict() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+    git.write_file("test.bin", "\0base\n")?;
+    git.run(&["add", "."])?;
+    git.run(&["commit", "-m", "create test.bin"])?;
+
+    git.run(&["checkout", "-b", "ours"])?;
+    git.write_file("test.bin", "\0ours\n")?;
+    git.run(&["commit", "-am", "ours"])?;
+
+    // Leaving the binary change unselected keeps the version being moved onto.
+    git.run(&["checkout", "-b", "theirs1", "master"])?;
+    git.write_file("test.bin", "\0theirs1\n")?;
+    git.commit_file("test1", 1)?;
+    run_in_pty(
+        &git,
+        "move",
+        &["--resolve", "-x", "theirs1", "-d", "ours"],
+        &[
+            PtyAction::WaitUntilContains("test.bin"),
+            PtyAction::Write("c"),
+        ],
+    )?;
+    {
+        let (stdout, _stderr) = git.run(&["diff", "--stat", "ours", "theirs1"])?;
+        insta::assert_snapshot!(stdout, @r###"
+         test1.txt | 1 +
+         1 file changed, 1 insertion(+)
+        "###);
+    }
+
+    // Selecting the binary change takes the version being moved.
+    git.run(&["checkout", "-b", "theirs2", "master"])?;
+    git.write_file("test.bin", "\0theirs2\n")?;
+    git.commit_file("test2", 2)?;
+    run_in_pty(
+        &git,
+        "move",
+        &["--resolve", "-x", "theirs2", "-d", "ours"],
+        &[
+            PtyAction::WaitUntilContains("test.bin"),
+            PtyAction::Write(" "),
+            PtyAction::WaitUntilContains("(●)"),
+            PtyAction::Write("c"),
+        ],
+    )?;
+    {
+        let (stdout, _stderr) = git.run(&["diff", "--stat", "ours", "theirs2"])?;
+        insta::assert_snapshot!(stdout, @r###"
+         test.bin  | Bin 6 -> 9 bytes
+         test2.txt |   1 +
+         2 files changed, 1 insertion(+)
+        "###);
+    }
+
+    Ok(())
+}
@@ -281,6 +281,255 @@ fn test_next_no_more_children() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_next_conflict() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.write_file_txt("test1", "updated contents\n")?;
+    git.run(&["commit", "-a", "-m", "update test1.txt"])?;
+    git.run(&["checkout", &test1_oid.to_string()])?;
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "next",
+            &["--conflict"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        No descendant commits with merge conflicts to go to.
+        "###);
+    }
+
+    git.write_file_txt("test1", "amended contents\n")?;
+    git.branchless("amend", &[])?;
+
+    {
+        git.branchless("next", &["--conflict"])?;
+        let (stdout, _stderr) = git.run(&["log", "-1", "--format=%s"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        update test1.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_next_failed() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    if !git.supports_log_exclude_decoration()? {
+        return Ok(());
+    }
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.branchless_with_options(
+        "test",
+        &["run", "-x", "test ! -f test2.txt || test -f test3.txt"],
+        &GitRunOptions {
+            expected_exit_code: 1,
+            ..Default::default()
+        },
+    )?;
+    git.run(&["checkout", "master"])?;
+
+    {
+        let (stdout, _stderr) = git.branchless("next", &["--failed"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout 96d1c37a3d4363611c49f7e52186e189a04c531f
+        O f777ecc (master) create initial.txt
+        |
+        o 62fc20d create test1.txt
+        |
+        @ 96d1c37 create test2.txt
+        |
+        o 70deb1e create test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "next",
+            &["--failed"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        No descendant commits with failing tests to go to.
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_navigation_sibling() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.run(&["checkout", "master"])?;
+    git.detach_head()?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "master"])?;
+    git.detach_head()?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "62fc20d"])?;
+
+    {
+        let (stdout, _stderr) = git.branchless("next", &["--sibling"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout fe65c1fe15584744e649b2c79d4cf9b0d878f92e
+        O f777ecc (master) create initial.txt
+        |\
+        | o 62fc20d create test1.txt
+        |\
+        | @ fe65c1f create test2.txt
+        |
+        o 98b9119 create test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("next", &["--sibling", "--all"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout 98b9119d16974f372e76cb64a3b77c528fc0b18b
+        O f777ecc (master) create initial.txt
+        |\
+        | o 62fc20d create test1.txt
+        |\
+        | o fe65c1f create test2.txt
+        |
+        @ 98b9119 create test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "next",
+            &["--sibling"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        No more sibling commits to go to after traversing 0 siblings.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("prev", &["--sibling", "2"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout 62fc20d2a290daea0d52bdc2ed2ad4be6491010e
+        O f777ecc (master) create initial.txt
+        |\
+        | @ 62fc20d create test1.txt
+        |\
+        | o fe65c1f create test2.txt
+        |
+        o 98b9119 create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_navigation_skip_obsolete() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_committer_date_is_author_date()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "HEAD^^"])?;
+    git.run(&["commit", "--amend", "-m", "amend test1.txt"])?;
+    git.run(&["checkout", "96d1c37"])?;
+
+    {
+        let (stdout, _stderr) = git.branchless("prev", &["--skip-obsolete"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Skipped obsolete commit: 62fc20d create test1.txt
+        branchless: running command: <git-executable> checkout master
+        @ f777ecc (> master) create initial.txt
+        |\
+        | o 024c35c amend test1.txt
+        |
+        x 62fc20d (rewritten as 024c35ce) create test1.txt
+        |
+        o 96d1c37 create test2.txt
+        |
+        o 70deb1e create test3.txt
+        hint: there is 1 abandoned commit in your commit graph
+        hint: to fix this, run: git restack
+        hint: disable this hint by running: git config --global branchless.hint.smartlogFixAbandoned false
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_navigation_top_bottom() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "96d1c37"])?;
+
+    {
+        let (stdout, _stderr) = git.branchless("top", &[])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout 70deb1e28791d8e7dd5a1f0c871a51b91282562f
+        O f777ecc (master) create initial.txt
+        |
+        o 62fc20d create test1.txt
+        |
+        o 96d1c37 create test2.txt
+        |
+        @ 70deb1e create test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("bottom", &[])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout 62fc20d2a290daea0d52bdc2ed2ad4be6491010e
+        O f777ecc (master) create initial.txt
+        |
+        @ 62fc20d create test1.txt
+        |
+        o 96d1c37 create test2.txt
+        |
+        o 70deb1e create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 #[cfg(unix)]
 fn test_switch_pty() -> eyre::Result<()> {
@@ -851,6 +1100,48 @@ fn test_navigation_switch_target_only() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_navigation_switch_message() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "master"])?;
+
+    {
+        let (stdout, _stderr) = git.branchless("switch", &["--message", "test2"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout 96d1c37a3d4363611c49f7e52186e189a04c531f
+        O f777ecc (master) create initial.txt
+        |
+        o 62fc20d create test1.txt
+        |
+        @ 96d1c37 create test2.txt
+        |
+        o 70deb1e create test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "switch",
+            &["--message", "test4"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        No draft commits have a message matching 'test4'.
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 #[cfg(unix)]
 fn test_switch_auto_switch_interactive() -> eyre::Result<()> {
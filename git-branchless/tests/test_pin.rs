@@ -0,0 +1,115 @@
+use lib::testing::make_git;
+
+#[test]
+fn test_pin_unpin() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+
+    {
+        let (stdout, _stderr) = git.branchless("pin", &[])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Pinned commit: 62fc20d create test1.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("pin", &["HEAD"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Pinned commit: 62fc20d create test1.txt
+        (It was already pinned, so this operation had no effect.)
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("unpin", &[])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Unpinned commit: 62fc20d create test1.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("unpin", &["HEAD"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Unpinned commit: 62fc20d create test1.txt
+        (It was not pinned, so this operation had no effect.)
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_pin_sync() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "-b", "foo"])?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "-b", "bar", "master"])?;
+    git.commit_file("test4", 4)?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test5", 5)?;
+
+    git.branchless("pin", &["foo"])?;
+
+    {
+        let (stdout, _stderr) = git.branchless("sync", &[])?;
+        let stdout = stdout
+            .lines()
+            .filter(|line| line.contains("pinned"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        insta::assert_snapshot!(stdout, @"Not moving pinned stack at 70deb1e create test3.txt");
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["log", "--format=%s", "-2", "foo"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test3.txt
+        create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["log", "--format=%s", "-2", "bar"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test4.txt
+        create test5.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_pin_restack() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.branchless("pin", &[])?;
+    git.run(&["checkout", "HEAD^"])?;
+    git.run(&["commit", "--amend", "-m", "amend test1.txt"])?;
+
+    {
+        let (stdout, _stderr) = git.branchless("restack", &[])?;
+        let stdout = stdout.lines().take(2).collect::<Vec<_>>().join("\n");
+        insta::assert_snapshot!(stdout, @r###"
+        Not restacking pinned stack at 96d1c37 create test2.txt
+        No abandoned commits to restack.
+        "###);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+
+use lib::testing::{make_git, GitRunOptions};
+
+#[test]
+fn test_reorder() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    let test3_oid = git.commit_file("test3", 3)?;
+    git.commit_file("test4", 4)?;
+
+    {
+        // Swap the first two commits.
+        let (_stdout, _stderr) = git.branchless_with_options(
+            "reorder",
+            &[&format!("{test1_oid} + {test2_oid} + {test3_oid}")],
+            &GitRunOptions {
+                env: HashMap::from([(
+                    "GIT_EDITOR".to_string(),
+                    "sed -i -e 1{h;d} -e 2G".to_string(),
+                )]),
+                ..Default::default()
+            },
+        )?;
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        o fe65c1f create test2.txt
+        |
+        o 0770943 create test1.txt
+        |
+        o b85f7eb create test3.txt
+        |
+        @ 6d163ae create test4.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_reorder_unchanged() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "reorder",
+            &[],
+            &GitRunOptions {
+                env: HashMap::from([("GIT_EDITOR".to_string(), "cat".to_string())]),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        62fc20d create test1.txt
+        96d1c37 create test2.txt
+        #
+        # Reorder 2 commits onto f777ecc create initial.txt.
+        #
+        # These lines can be re-ordered; the commits will be applied from top to
+        # bottom. Every commit must remain listed exactly once.
+        Nothing to do.
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_reorder_invalid() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    let test3_oid = git.commit_file("test3", 3)?;
+
+    {
+        let (stdout, stderr) = git.branchless_with_options(
+            "reorder",
+            &[&format!("{test1_oid} + {test3_oid}")],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @"The commits to reorder must form a single range of commits without gaps or merges, but 62fc20d create test1.txt is not the only parent of 70deb1e create test3.txt");
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    {
+        let (stdout, stderr) = git.branchless_with_options(
+            "reorder",
+            &[],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                env: HashMap::from([("GIT_EDITOR".to_string(), "sed -i -e 1d".to_string())]),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @"Expected all 3 commits to be listed, but only 2 listed");
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    Ok(())
+}
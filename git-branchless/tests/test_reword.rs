@@ -97,6 +97,44 @@ fn test_reword_with_multiple_messages() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_reword_trailers() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_committer_date_is_author_date()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.run(&[
+        "config",
+        "branchless.commit.trailer",
+        "Reviewed-by: Foo <foo@example.com>",
+    ])?;
+
+    git.branchless("reword", &["-f", "-m", "foo"])?;
+    {
+        let (stdout, _stderr) = git.run(&["log", "-n", "1", "--format=%B"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        foo
+
+        Reviewed-by: Foo <foo@example.com>
+
+        "###);
+    }
+
+    git.branchless("reword", &["-f", "--no-trailers", "-m", "bar"])?;
+    {
+        let (stdout, _stderr) = git.run(&["log", "-n", "1", "--format=%B"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        bar
+
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_reword_preserves_comment_lines_for_messages_on_cli() -> eyre::Result<()> {
     let git = make_git()?;
@@ -660,3 +698,80 @@ fn test_reword_merge_commit() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_reword_reset_author() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["config", "user.name", "Other Author"])?;
+
+    git.branchless(
+        "reword",
+        &[
+            "--reset-author",
+            "--committer-as-author",
+            "-m",
+            "new message",
+        ],
+    )?;
+
+    {
+        let (stdout, _stderr) = git.run(&["log", "-2", "--format=%s: %an, %cn"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        new message: Other Author, Other Author
+        create test1.txt: Testy McTestface, Testy McTestface
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["log", "-1", "--format=%at %ct"])?;
+        let (author_time, committer_time) = stdout.trim().split_once(' ').unwrap();
+        assert_eq!(author_time, committer_time);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_reword_reset_author_keeps_descendant_authors() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.run(&["config", "user.name", "Other Author"])?;
+
+    git.branchless(
+        "reword",
+        &[
+            "HEAD^",
+            "--reset-author",
+            "--committer-as-author",
+            "-m",
+            "new message",
+        ],
+    )?;
+
+    {
+        let (stdout, _stderr) = git.run(&["log", "-3", "--format=%s: %an, %cn"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test3.txt: Testy McTestface, Testy McTestface
+        new message: Other Author, Other Author
+        create test1.txt: Testy McTestface, Testy McTestface
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["log", "-1", "HEAD^", "--format=%at %ct"])?;
+        let (author_time, committer_time) = stdout.trim().split_once(' ').unwrap();
+        assert_eq!(author_time, committer_time);
+    }
+
+    Ok(())
+}
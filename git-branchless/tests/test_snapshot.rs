@@ -510,3 +510,59 @@ fn test_snapshot_restore_unborn_head() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_snapshot_restore_single_file() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.write_file_txt("test1", "test1 new contents\n")?;
+    git.write_file_txt("test2", "test2 new contents\n")?;
+
+    git.branchless("snapshot", &["create"])?;
+    git.write_file_txt("test2", "test2 newer contents\n")?;
+
+    {
+        let (stdout, stderr) = git.branchless("snapshot", &["restore", "--file", "test1.txt"])?;
+        insta::assert_snapshot!(stderr, @r###"
+        branchless: restoring test1.txt from snapshot
+        "###);
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["diff"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        diff --git a/test1.txt b/test1.txt
+        index 7432a8f..6cbc96e 100644
+        --- a/test1.txt
+        +++ b/test1.txt
+        @@ -1 +1 @@
+        -test1 contents
+        +test1 new contents
+        diff --git a/test2.txt b/test2.txt
+        index 4e512d2..252572f 100644
+        --- a/test2.txt
+        +++ b/test2.txt
+        @@ -1 +1 @@
+        -test2 contents
+        +test2 newer contents
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "snapshot",
+            &["restore", "--file", "nonexistent.txt"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use lib::testing::{make_git, GitRunOptions};
+
+#[test]
+fn test_squash_fixups() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.write_file_txt("test1", "updated contents\n")?;
+    git.run(&["add", "."])?;
+    git.run_with_options(
+        &[
+            "commit",
+            "-m",
+            "squash! create test1.txt",
+            "-m",
+            "Update test1 contents.",
+        ],
+        &GitRunOptions {
+            time: 3,
+            ..Default::default()
+        },
+    )?;
+
+    git.branchless("squash-fixups", &[])?;
+
+    let stdout = git.smartlog()?;
+    insta::assert_snapshot!(stdout, @r###"
+    O f777ecc (master) create initial.txt
+    |
+    @ f690b59 create test1.txt
+    |
+    o 6db8601 create test2.txt
+    "###);
+
+    let (stdout, _stderr) = git.run(&["log", "-n", "1", "--format=%h%n%B"])?;
+    insta::assert_snapshot!(stdout, @r###"
+    f690b59
+    create test1.txt
+
+    Update test1 contents.
+
+    "###);
+
+    let (stdout, _stderr) = git.run(&["show", "HEAD:test1.txt"])?;
+    insta::assert_snapshot!(stdout, @"updated contents
+");
+
+    Ok(())
+}
+
+#[test]
+fn test_squash_fixups_moves_branches() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.write_file_txt("test1", "updated contents\n")?;
+    git.run(&["add", "."])?;
+    git.run_with_options(
+        &[
+            "commit",
+            "-m",
+            "squash! create test1.txt",
+            "-m",
+            "Update test1 contents.",
+        ],
+        &GitRunOptions {
+            time: 3,
+            ..Default::default()
+        },
+    )?;
+    git.run(&["branch", "fixup"])?;
+
+    git.branchless("squash-fixups", &[])?;
+
+    // The branch which pointed to the squashed commit should be moved to the
+    // commit that it was squashed into.
+    let stdout = git.smartlog()?;
+    insta::assert_snapshot!(stdout, @r###"
+    O f777ecc (master) create initial.txt
+    |
+    @ f690b59 (> fixup) create test1.txt
+    |
+    o 6db8601 create test2.txt
+    "###);
+
+    Ok(())
+}
+
+#[test]
+fn test_squash_fixups_no_fixups() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file_with_contents_and_message("test2", 2, "test2 contents\n", "fixup! unknown")?;
+
+    let (stdout, _stderr) = git.branchless("squash-fixups", &[])?;
+    insta::assert_snapshot!(stdout, @"No fixup! or squash! commits to squash.
+");
+
+    Ok(())
+}
@@ -0,0 +1,164 @@
+use lib::testing::{make_git, GitRunOptions};
+
+#[test]
+fn test_stash_push_pop() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+
+    {
+        let (stdout, _stderr) = git.branchless("stash", &["push"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        No local changes to stash.
+        "###);
+    }
+
+    git.write_file_txt("test1", "test1 new contents\n")?;
+    git.write_file_txt("test2", "staged contents\n")?;
+    git.run(&["add", "test2.txt"])?;
+
+    {
+        let (stdout, _stderr) = git.branchless("stash", &["push"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Saved working copy changes to stash@{0}: WIP on master: 62fc20d create test1.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["status", "--porcelain"])?;
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("stash", &["list"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        stash@{0}: WIP on master: 62fc20d create test1.txt
+        "###);
+    }
+
+    git.branchless("stash", &["pop"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["status", "--porcelain"])?;
+        insta::assert_snapshot!(stdout, @r###"
+         M test1.txt
+        A  test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("stash", &["list"])?;
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "stash",
+            &["pop"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        No stash found: stash@{0}
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_stash_pop_onto_new_head() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+
+    git.write_file_txt("test1", "test1 new contents\n")?;
+    git.branchless("stash", &["push", "-m", "my changes"])?;
+    git.commit_file("test2", 2)?;
+
+    {
+        let (stdout, _stderr) = git.branchless("stash", &["pop", "stash@{0}"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Restored stash@{0}: my changes
+        "###);
+    }
+
+    {
+        // Use `assert_eq!` since inline snapshots strip the leading space
+        // which marks the change as unstaged.
+        let (stdout, _stderr) = git.run(&["status", "--porcelain"])?;
+        assert_eq!(stdout, " M test1.txt\n");
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["diff"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        diff --git a/test1.txt b/test1.txt
+        index 7432a8f..6cbc96e 100644
+        --- a/test1.txt
+        +++ b/test1.txt
+        @@ -1 +1 @@
+        -test1 contents
+        +test1 new contents
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_stash_pop_conflict() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+
+    git.write_file_txt("test1", "test1 new contents\n")?;
+    git.branchless("stash", &["push", "-m", "my changes"])?;
+    git.write_file_txt("test1", "conflicting contents\n")?;
+    git.run(&["commit", "-a", "-m", "conflicting commit"])?;
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "stash",
+            &["pop"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Cannot restore stash@{0}, because it conflicts with HEAD in these paths:
+          test1.txt
+        The stash was kept. Check out 62fc20d to restore it there.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("stash", &["list"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        stash@{0}: my changes
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("stash", &["drop"])?;
+        let stdout = stdout.split(" (restore it with").next().unwrap_or_default();
+        insta::assert_snapshot!(stdout, @"Dropped stash@{0}: my changes");
+    }
+
+    Ok(())
+}
@@ -86,6 +86,203 @@ fn test_sync_basic() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_sync_head_in_first_stack() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test3", 3)?;
+
+    git.detach_head()?;
+    git.commit_file("test4", 4)?;
+
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test5", 5)?;
+    git.run(&["checkout", "96d1c37a3d4363611c49f7e52186e189a04c531f"])?;
+
+    // Rebasing the first stack moves `HEAD`, so the second stack must not
+    // check out the commit that `HEAD` pointed to before the sync.
+    git.branchless("sync", &[])?;
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 117e086 (master) create test5.txt
+        |\
+        | o 87c7a36 create test1.txt
+        | |
+        | @ 8ee4f26 create test2.txt
+        |
+        o d7e7e6c create test4.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_sync_max_duration_continue() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test3", 3)?;
+
+    git.detach_head()?;
+    git.commit_file("test4", 4)?;
+
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test5", 5)?;
+
+    {
+        let (stdout, stderr) = git.branchless("sync", &["--max-duration", "0s"])?;
+        insta::assert_snapshot!(stderr, @"");
+        insta::assert_snapshot!(stdout, @r###"
+        Stopped syncing after reaching the maximum duration (2 stacks not yet synced).
+        To sync the remaining stacks, run: git sync --continue
+        "###);
+    }
+
+    {
+        let (stdout, stderr) = git.branchless("sync", &["--continue"])?;
+        insta::assert_snapshot!(stderr, @r###"
+        branchless: creating working copy snapshot
+        Switched to branch 'master'
+        branchless: processing checkout
+        branchless: creating working copy snapshot
+        Switched to branch 'master'
+        branchless: processing checkout
+        "###);
+        insta::assert_snapshot!(stdout, @r###"
+        Attempting rebase in-memory...
+        [1/2] Committed as: 87c7a36 create test1.txt
+        [2/2] Committed as: 8ee4f26 create test2.txt
+        branchless: processing 2 rewritten commits
+        branchless: running command: <git-executable> checkout master
+        In-memory rebase succeeded.
+        Attempting rebase in-memory...
+        [1/1] Committed as: d7e7e6c create test4.txt
+        branchless: processing 1 rewritten commit
+        branchless: running command: <git-executable> checkout master
+        In-memory rebase succeeded.
+        Synced 62fc20d create test1.txt
+        Synced 2b633ed create test4.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "sync",
+            &["--continue"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        There is no sync in progress to continue.
+        "###);
+    }
+
+    {
+        let (_stdout, stderr) = git.branchless_with_options(
+            "sync",
+            &["--max-duration", "300000000000000d"],
+            &GitRunOptions {
+                expected_exit_code: 2,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @r###"
+        error: invalid value '300000000000000d' for '--max-duration <MAX_DURATION>': duration is too long: 300000000000000d
+
+        For more information, try '--help'.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "sync",
+            &["--max-duration", "18000000000000000000s"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        The duration passed to --max-duration is too long: 18000000000000000000s
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_sync_continue_after_stacks_hidden() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test2", 2)?;
+
+    {
+        let (stdout, _stderr) = git.branchless("sync", &["--max-duration", "0s"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Stopped syncing after reaching the maximum duration (1 stack not yet synced).
+        To sync the remaining stacks, run: git sync --continue
+        "###);
+    }
+
+    git.branchless("hide", &["draft()"])?;
+
+    {
+        let (stdout, _stderr) = git.branchless("sync", &["--continue"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        The provided revsets did not select any draft commits, so there are no stacks to sync.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "sync",
+            &["--continue"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        There is no sync in progress to continue.
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_sync_up_to_date() -> eyre::Result<()> {
     let git = make_git()?;
@@ -260,6 +457,201 @@ fn test_sync_stack_from_commit() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_sync_skip_conflicting_stacks() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "-b", "foo"])?;
+    let foo_oid = git.commit_file_with_contents("test3", 3, "foo contents\n")?;
+    git.run(&["checkout", "-b", "bar", "master"])?;
+    git.commit_file("test4", 4)?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file_with_contents("test3", 5, "master contents\n")?;
+
+    {
+        let (stdout, _stderr) = git.branchless("sync", &[])?;
+        let stdout = stdout.replace(&foo_oid.to_string()[..7], "<foo>");
+        let stdout = stdout
+            .lines()
+            .skip_while(|line| !line.starts_with("Skipped"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        insta::assert_snapshot!(stdout, @r###"
+        Skipped 1 stack due to merge conflicts. To resolve the conflicts for each one, run:
+          git move --merge -s <foo> -d master
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["log", "--format=%s", "-2", "bar"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test4.txt
+        create test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["log", "--format=%s", "-2", "foo"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test3.txt
+        create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_sync_dry_run() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "-b", "foo"])?;
+    let foo_oid = git.commit_file_with_contents("test3", 3, "foo contents\n")?;
+    git.run(&["checkout", "-b", "bar", "master"])?;
+    let bar_oid = git.commit_file("test4", 4)?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file_with_contents("test3", 5, "master contents\n")?;
+
+    {
+        let (stdout, _stderr) = git.branchless("sync", &["--dry-run"])?;
+        let stdout = stdout
+            .replace(&foo_oid.to_string()[..7], "<foo>")
+            .replace(&bar_oid.to_string()[..7], "<bar>");
+        insta::assert_snapshot!(stdout, @r###"
+        Would have merge conflict (1 file) for <foo> create test3.txt
+        Would sync <bar> create test4.txt
+        (This was a dry-run; run without --dry-run to apply changes.)
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["log", "--format=%s", "-2", "bar"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test4.txt
+        create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_sync_stack_from_revset() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "-b", "foo"])?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "-b", "bar", "master"])?;
+    git.commit_file("test4", 4)?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test5", 5)?;
+
+    {
+        let (stdout, _stderr) = git.branchless("sync", &["draft() & paths.changed(test6.txt)"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        The provided revsets did not select any draft commits, so there are no stacks to sync.
+        "###);
+    }
+
+    git.branchless("sync", &["draft() & paths.changed(test4.txt)"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["log", "--format=%s", "-3", "bar"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test4.txt
+        create test5.txt
+        create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["log", "--format=%s", "-3", "foo"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test3.txt
+        create test2.txt
+        create test1.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_sync_pull_remote() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _guard,
+        original_repo,
+        cloned_repo,
+    } = make_git_with_remote_repo()?;
+    if !original_repo.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    original_repo.init_repo()?;
+    original_repo.commit_file("test1", 1)?;
+    original_repo.commit_file("test2", 2)?;
+
+    original_repo.clone_repo_into(&cloned_repo, &["--branch", "master"])?;
+    cloned_repo.init_repo_with_options(&GitInitOptions {
+        make_initial_commit: false,
+        ..Default::default()
+    })?;
+    cloned_repo.run(&["branch", "--unset-upstream"])?;
+    cloned_repo.run(&[
+        "remote",
+        "add",
+        "upstream",
+        original_repo.repo_path.to_str().unwrap(),
+    ])?;
+    cloned_repo.detach_head()?;
+
+    original_repo.commit_file("test3", 3)?;
+
+    {
+        let (stdout, _stderr) = cloned_repo.branchless("sync", &["-p"])?;
+        let stdout = remove_nondeterministic_lines(stdout);
+        let stdout = stdout.lines().take(2).collect::<Vec<_>>().join("\n");
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> -c fetch.parallel=0 fetch --all
+        branch master does not track an upstream branch, so not pulling.
+        "###);
+    }
+
+    cloned_repo.run(&["config", "branchless.sync.remote", "upstream"])?;
+    {
+        let (stdout, _stderr) = cloned_repo.branchless("sync", &["-p"])?;
+        let stdout = remove_nondeterministic_lines(stdout);
+        let stdout = stdout.lines().take(2).collect::<Vec<_>>().join("\n");
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> fetch upstream
+        Fast-forwarding branch master to 70deb1e create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_sync_divergent_main_branch() -> eyre::Result<()> {
     let GitWrapperWithRemoteRepo {
@@ -322,6 +714,129 @@ fn test_sync_divergent_main_branch() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_sync_divergent_main_branch_merge_strategy() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _guard,
+        original_repo,
+        cloned_repo,
+    } = make_git_with_remote_repo()?;
+    if !original_repo.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    original_repo.init_repo()?;
+    original_repo.commit_file("test1", 1)?;
+    original_repo.commit_file("test2", 2)?;
+
+    original_repo.clone_repo_into(&cloned_repo, &["--branch", "master"])?;
+    cloned_repo.init_repo_with_options(&GitInitOptions {
+        make_initial_commit: false,
+        ..Default::default()
+    })?;
+    cloned_repo.run(&["config", "branchless.sync.strategy", "rebase"])?;
+    cloned_repo.run(&["config", "branch.master.branchlessSyncStrategy", "merge"])?;
+
+    original_repo.commit_file("test3", 3)?;
+    original_repo.commit_file("test4", 4)?;
+    cloned_repo.commit_file("test5", 5)?;
+    cloned_repo.detach_head()?;
+
+    {
+        let (stdout, _stderr) = cloned_repo.branchless("sync", &["-p"])?;
+        let stdout = remove_nondeterministic_lines(stdout);
+        let stdout = stdout.lines().take(2).collect::<Vec<_>>().join("\n");
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> fetch --all
+        Merging origin/master into branch master
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) =
+            cloned_repo.run(&["log", "--format=%s", "--first-parent", "master"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Merge remote-tracking branch 'origin/master'
+        create test5.txt
+        create test2.txt
+        create test1.txt
+        create initial.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_sync_prune_merged() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _guard,
+        original_repo,
+        cloned_repo,
+    } = make_git_with_remote_repo()?;
+    if !original_repo.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    original_repo.init_repo()?;
+    original_repo.commit_file("test1", 1)?;
+    original_repo.commit_file("test2", 2)?;
+
+    original_repo.clone_repo_into(&cloned_repo, &["--branch", "master"])?;
+    cloned_repo.init_repo_with_options(&GitInitOptions {
+        make_initial_commit: false,
+        ..Default::default()
+    })?;
+    cloned_repo.run(&["branch", "old", "HEAD^"])?;
+    cloned_repo.run(&["branch", "new"])?;
+    cloned_repo.run(&["checkout", "-b", "foo"])?;
+    cloned_repo.commit_file("test3", 3)?;
+    cloned_repo.run(&["checkout", "-b", "bar", "master"])?;
+    cloned_repo.commit_file("test4", 4)?;
+    cloned_repo.run(&["checkout", "foo"])?;
+
+    // Land the same patch as `foo` upstream, but as a different commit.
+    original_repo.commit_file("test5", 5)?;
+    original_repo.commit_file("test3", 3)?;
+
+    {
+        let (stdout, _stderr) = cloned_repo.branchless("sync", &["-p", "--prune-merged"])?;
+        let stdout = stdout
+            .lines()
+            .filter(|line| line.starts_with("Deleted") || line.starts_with("Hid"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        insta::assert_snapshot!(stdout, @r###"
+        Deleted 2 branches which landed in the main branch: foo, old
+        Hid 1 landed commit
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = cloned_repo.run(&["branch", "--format=%(refname:short)"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        bar
+        master
+        new
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = cloned_repo.run(&["symbolic-ref", "--short", "HEAD"])?;
+        insta::assert_snapshot!(stdout, @"master");
+    }
+
+    {
+        let (stdout, _stderr) = cloned_repo.run(&["log", "--format=%s", "-2", "bar"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test4.txt
+        create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_sync_no_delete_main_branch() -> eyre::Result<()> {
     let GitWrapperWithRemoteRepo {
@@ -434,9 +949,35 @@ fn test_sync_merge_commit() -> eyre::Result<()> {
         let (stdout, _stderr) = git.branchless("sync", &[])?;
         insta::assert_snapshot!(stdout, @r###"
         Attempting rebase in-memory...
-        Attempting rebase in-memory...
-        Can't rebase merge commit in-memory: 62fc20d create test1.txt
-        Can't rebase merge commit in-memory: 98b9119 create test3.txt
+        [1/4] Committed as: 6c398da create test1.txt
+        [2/4] Committed as: d166405 create test2.txt
+        [3/4] Committed as: 9799dd9 create test3.txt
+        [4/4] Committed as: 4ccacbb Merge branch 'foo' into HEAD
+        branchless: processing 1 update: branch foo
+        branchless: processing 4 rewritten commits
+        branchless: running command: <git-executable> checkout master
+        In-memory rebase succeeded.
+        Synced 62fc20d create test1.txt
+        Synced 98b9119 create test3.txt
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        @ 8f7aef5 (> master) create test4.txt
+        |\
+        | o 6c398da create test1.txt
+        | |
+        | o d166405 (foo) create test2.txt
+        | & (merge) 4ccacbb Merge branch 'foo' into HEAD
+        |
+        o 9799dd9 create test3.txt
+        |
+        | & (merge) d166405 (foo) create test2.txt
+        |/
+        o 4ccacbb Merge branch 'foo' into HEAD
         "###);
     }
 
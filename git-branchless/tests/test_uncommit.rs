@@ -0,0 +1,118 @@
+use lib::testing::{make_git, GitRunOptions};
+
+#[test]
+fn test_uncommit_head() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    git.branchless("uncommit", &[])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["log", "--format=%s"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test1.txt
+        create initial.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["status", "--porcelain"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        ?? test2.txt
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        @ 62fc20d create test1.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_uncommit_staged() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.write_file_txt("test1", "test1 new contents\n")?;
+    git.run(&["commit", "-a", "-m", "update test1"])?;
+
+    git.branchless("uncommit", &["--staged"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["status", "--porcelain"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        M  test1.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_uncommit_ancestor() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+
+    git.branchless("uncommit", &["parents(@)"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["log", "--format=%s"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test3.txt
+        create test1.txt
+        create initial.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["status", "--porcelain"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        ?? test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_uncommit_not_ancestor() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "HEAD^"])?;
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "uncommit",
+            &["master"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Cannot uncommit 96d1c37 create test2.txt, because it is not HEAD or an ancestor of HEAD.
+        "###);
+    }
+
+    Ok(())
+}
@@ -106,7 +106,6 @@ fn test_undo_help() -> eyre::Result<()> {
         │O f777ecc (master) create initial.txt                                                                                 │
         │                                                                                                                      │
         │                                                                                                                      │
-        │                                                                                                                      │
         │        ┌───────────────────────────────────────────┤ How to use ├───────────────────────────────────────────┐        │
         │        │ Use `git undo` to view and revert to previous states of the repository.                            │        │
         │        │                                                                                                    │        │
@@ -114,6 +113,8 @@ fn test_undo_help() -> eyre::Result<()> {
         │        │ q: Quit.                                                                                           │        │
         │        │ p/n or <left>/<right>: View next/previous state.                                                   │        │
         │        │ g: Go to a provided event ID.                                                                      │        │
+        │        │ /: Search for an earlier event by its description.                                                 │        │
+        │        │ [/]: Go to the previous/next event matching the search.                                            │        │
         │        │ <enter>: Revert the repository to the given state (requires confirmation).                         │        │
         │        │                                                                                                    │        │
         │        │ You can also copy a commit hash from the past and manually run `git unhide` or `git rebase` on it. │        │
@@ -121,7 +122,6 @@ fn test_undo_help() -> eyre::Result<()> {
         │        │                                                                                            <Close> │        │
         │        └────────────────────────────────────────────────────────────────────────────────────────────────────┘        │
         │                                                                                                                      │
-        │                                                                                                                      │
         └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
         ┌──────────────────────────────────────────────────────┤ Events ├──────────────────────────────────────────────────────┐
         │There are no previous available events.                                                                               │
@@ -303,6 +303,40 @@ fn test_go_to_event() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_undo_search() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? || git.produces_auto_merge_refs()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    let mut events = vec![CursiveTestingEvent::Event('/'.into())];
+    events.extend(
+        "initial"
+            .chars()
+            .map(|c| CursiveTestingEvent::Event(c.into())),
+    );
+    events.extend([
+        CursiveTestingEvent::Event(Key::Enter.into()),
+        CursiveTestingEvent::Event(Key::Enter.into()),
+    ]);
+    let event_cursor = run_select_past_event(&git.get_repo()?, events)?;
+    insta::assert_debug_snapshot!(event_cursor, @r###"
+        Some(
+            EventCursor {
+                event_id: 2,
+            },
+        )
+        "###);
+
+    Ok(())
+}
+
 #[test]
 fn test_undo_hide() -> eyre::Result<()> {
     let git = make_git()?;
@@ -372,6 +406,55 @@ fn test_undo_hide() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_undo_only_branch() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.run(&["checkout", "-b", "test1"])?;
+    git.commit_file("test1", 1)?;
+    git.run(&["checkout", "master"])?;
+    git.run(&["checkout", "-b", "test2"])?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "master"])?;
+    git.branchless("hide", &["test1", "test2"])?;
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "undo",
+            &["--only", "test1"],
+            &GitRunOptions {
+                input: Some("y".to_string()),
+                ..Default::default()
+            },
+        )?;
+        let stdout = trim_lines(stdout);
+        insta::assert_snapshot!(stdout, @r###"
+        Will apply these actions:
+        1. Create branch test1 at 62fc20d create test1.txt
+
+        2. Unhide commit 62fc20d create test1.txt
+
+        Confirm? [yN] Applied 2 inverse events.
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        @ f777ecc (> master) create initial.txt
+        |
+        o 62fc20d (test1) create test1.txt
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_undo_move_refs() -> eyre::Result<()> {
     let git = make_git()?;
@@ -898,6 +981,71 @@ fn test_undo_noninteractive() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_undo_diverges_from_pushed_branch() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    let amended_oid = {
+        git.branchless("wrap", &["--", "commit", "--amend", "-m", "bad message"])?;
+        git.get_repo()?.get_head_info()?.oid.unwrap()
+    };
+
+    // Simulate having pushed the amended commit, without adding any events to
+    // the event log.
+    git.run(&["remote", "add", "origin", "https://example.com/repo.git"])?;
+    git.get_repo()?.create_reference(
+        &"refs/remotes/origin/master".into(),
+        amended_oid,
+        false,
+        "simulate push",
+    )?;
+    git.run(&["branch", "--set-upstream-to=origin/master", "master"])?;
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "undo",
+            &["-y"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        let stdout = trim_lines(stdout);
+        insta::assert_snapshot!(stdout, @r###"
+        Will apply these actions:
+        1. Rewrite commit 9ed8f9a bad message
+                      as 96d1c37 create test2.txt
+        2. Hide commit 9ed8f9a bad message
+
+        3. Move branch master from 9ed8f9a bad message
+                                to 96d1c37 create test2.txt
+        4. Check out from 9ed8f9a bad message
+                       to 96d1c37 create test2.txt
+        Warning: branch master would no longer include the commits already pushed to origin/master.
+        Your collaborators may already have these commits, so undoing this operation
+        could cause you to recreate commits that they already have.
+        Retry with --force to proceed anyways.
+        "###);
+    }
+
+    git.branchless("undo", &["-y", "--force"])?;
+    {
+        let (stdout, _stderr) = git.run(&["rev-parse", "master"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        96d1c37a3d4363611c49f7e52186e189a04c531f
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_undo_no_confirm() -> eyre::Result<()> {
     let git = make_git()?;
@@ -1007,3 +1155,56 @@ fn test_undo_unseen_commit() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_undo_post_undo_hook() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.run(&["branch", "foo"])?;
+    git.run(&[
+        "config",
+        "branchless.hook.postUndo",
+        r#"echo "$BRANCHLESS_UNDO_FIRST_EVENT_ID $BRANCHLESS_UNDO_LAST_EVENT_ID" >post-undo.txt"#,
+    ])?;
+
+    let (expected_first_event_id, expected_last_event_id) = {
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let repo = git.get_repo()?;
+        let conn = repo.get_db_conn()?;
+        let event_log_db = EventLogDb::new(&conn)?;
+        let event_replayer = EventReplayer::from_event_log_db(&effects, &repo, &event_log_db)?;
+        let default_cursor = event_replayer.make_default_cursor();
+        let cursor = event_replayer.advance_cursor_by_transaction(default_cursor, -1);
+        let get_event_id = |cursor: EventCursor| {
+            event_replayer
+                .get_event_before_cursor(cursor)
+                .map_or(0, |(event_id, _event)| event_id)
+        };
+        (get_event_id(cursor) + 1, get_event_id(default_cursor))
+    };
+
+    {
+        let (stdout, _stderr) = git.branchless("undo", &["--yes"])?;
+        let stdout = trim_lines(stdout);
+        insta::assert_snapshot!(stdout, @r###"
+        Will apply these actions:
+        1. Delete branch foo at 62fc20d create test1.txt
+
+        Applied 1 inverse event.
+        "###);
+    }
+
+    let post_undo_output = std::fs::read_to_string(git.repo_path.join("post-undo.txt"))?;
+    assert_eq!(
+        post_undo_output,
+        format!("{expected_first_event_id} {expected_last_event_id}\n")
+    );
+
+    Ok(())
+}